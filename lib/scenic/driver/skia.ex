@@ -18,7 +18,7 @@ defmodule Scenic.Driver.Skia do
   import Bitwise, only: [|||: 2]
   alias Scenic.Driver
 
-  alias Scenic.Driver.Skia.Native
+  alias Scenic.Driver.Skia.{InputBinary, Native}
   alias Scenic.Assets.Static
   alias Scenic.Assets.Stream
   alias Scenic.{Script, ViewPort}
@@ -30,15 +30,117 @@ defmodule Scenic.Driver.Skia do
 
   @drm_schema [
     card: [type: :string],
+    driver: [type: :string],
+    render_node: [type: {:or, [{:in, [:auto]}, :string]}],
     hw_cursor: [type: :boolean, default: true],
-    input_log: [type: :boolean, default: false]
+    input_log: [type: :boolean, default: false],
+    pointer_profile: [type: {:in, [:flat, :adaptive]}, default: :flat],
+    pointer_sensitivity: [type: {:or, [:float, :pos_integer]}, default: 1.0],
+    rotation: [type: {:in, [0, 90, 180, 270]}, default: 0]
+  ]
+
+  @margin_type {:or, [:float, :non_neg_integer]}
+
+  @overscan_schema [
+    left: [type: @margin_type, default: 0],
+    top: [type: @margin_type, default: 0],
+    right: [type: @margin_type, default: 0],
+    bottom: [type: @margin_type, default: 0],
+    unit: [type: {:in, [:px, :percent]}, default: :px]
+  ]
+
+  @theme_schema [
+    font: [type: {:or, [:string, :atom]}],
+    fallback_fonts: [type: {:list, {:or, [:string, :atom]}}],
+    font_size: [type: {:or, [:float, :pos_integer]}, default: 20],
+    clear_color: [type: :any]
+  ]
+
+  @reference_check_schema [
+    strict: [type: :boolean, default: false],
+    max_frames: [type: :non_neg_integer, default: 0]
+  ]
+
+  @script_limits_schema [
+    max_ops: [type: :non_neg_integer, default: 0],
+    max_expansions: [type: :non_neg_integer, default: 0],
+    max_text_bytes: [type: :non_neg_integer, default: 0]
+  ]
+
+  @script_cache_schema [
+    dir: [type: {:or, [:string, nil]}, default: nil],
+    max_bytes: [type: :pos_integer, default: 64 * 1024 * 1024]
+  ]
+
+  @quality_schema [
+    target_fps: [type: :non_neg_integer, default: 0],
+    thermal_zones: [type: {:list, :string}, default: []],
+    thermal_limit_c: [type: {:or, [:integer, :float]}, default: 80]
+  ]
+
+  @flight_recorder_schema [
+    frames: [type: :non_neg_integer, default: 0],
+    max_width: [type: :pos_integer, default: 320],
+    dump_dir: [type: {:or, [:string, nil]}, default: nil]
+  ]
+
+  @stats_export_schema [
+    path: [type: {:or, [:string, nil]}, default: nil],
+    format: [type: {:in, [:json, :binary]}, default: :json]
+  ]
+
+  @dev_overlay_schema [
+    enabled: [type: :boolean, default: false],
+    spacing: [type: {:or, [:float, :pos_integer]}, default: 10],
+    color: [type: :any, default: {255, 0, 255, 96}],
+    rulers: [type: :boolean, default: true],
+    pointer: [type: :boolean, default: true]
+  ]
+
+  @watermark_schema [
+    text: [type: {:or, [:string, nil]}, default: nil],
+    font: [type: {:or, [:string, nil]}, default: nil],
+    size: [type: {:or, [:float, :pos_integer]}, default: 14],
+    position: [
+      type: {:in, [:top_left, :top_right, :bottom_left, :bottom_right]},
+      default: :bottom_right
+    ],
+    margin: [type: {:or, [:float, :non_neg_integer]}, default: 8],
+    color: [type: :any, default: :white],
+    background: [type: :any, default: {0, 0, 0, 160}],
+    target: [type: {:in, [:frames, :captures]}, default: :frames]
   ]
 
   @opts_schema [
     backend: [type: {:or, [:atom, :string]}, default: :wayland],
     debug: [type: :boolean, default: false],
     window: [type: :keyword_list, keys: @window_schema, default: []],
-    drm: [type: :keyword_list, keys: @drm_schema, default: []]
+    drm: [type: :keyword_list, keys: @drm_schema, default: []],
+    overscan: [type: :keyword_list, keys: @overscan_schema, default: []],
+    gradient_dither: [type: {:in, [:off, :paint, :noise]}, default: :off],
+    text_fallback: [type: {:in, [:auto, :always, :off]}, default: :auto],
+    isolate_scripts: [type: :boolean, default: false],
+    reduced_motion: [type: :boolean, default: false],
+    linear_blending: [type: :boolean, default: false],
+    software_gl: [type: {:in, [:warn, :raster]}, default: :warn],
+    dedup_images: [type: :boolean, default: false],
+    frame_deadline_ms: [type: :non_neg_integer, default: 0],
+    cursor_interval_ms: [type: :non_neg_integer, default: 0],
+    input_encoding: [type: {:in, [:terms, :binary]}, default: :terms],
+    key_details: [type: {:or, [:pid, :atom]}, default: nil],
+    reference_check: [type: :keyword_list, keys: @reference_check_schema, default: []],
+    script_limits: [type: :keyword_list, keys: @script_limits_schema, default: []],
+    quality: [type: :keyword_list, keys: @quality_schema, default: []],
+    script_cache: [type: :keyword_list, keys: @script_cache_schema, default: []],
+    geometry: [type: {:in, [:clamp, :strict]}, default: :clamp],
+    poisoned_state: [type: {:in, [:recover, :fail]}, default: :recover],
+    theme: [type: :keyword_list, keys: @theme_schema, default: []],
+    flight_recorder: [type: :keyword_list, keys: @flight_recorder_schema, default: []],
+    stats_export: [type: :keyword_list, keys: @stats_export_schema, default: []],
+    dev_overlay: [type: :keyword_list, keys: @dev_overlay_schema, default: []],
+    watermark: [type: :keyword_list, keys: @watermark_schema, default: []],
+    hardware_decode: [type: {:or, [:boolean, :string]}, default: false],
+    asset_store: [type: :any]
   ]
 
   @input_mask_key 0x01
@@ -47,7 +149,9 @@ defmodule Scenic.Driver.Skia do
   @input_mask_cursor_button 0x08
   @input_mask_cursor_scroll 0x10
   @input_mask_viewport 0x20
-  @input_mask_all 0x3F
+  @input_mask_touch 0x40
+  @input_mask_hover 0x80
+  @input_mask_all 0xFF
   @impl Scenic.Driver
   def validate_opts(opts) do
     with {:ok, opts} <- NimbleOptions.validate(opts, @opts_schema) do
@@ -69,31 +173,58 @@ defmodule Scenic.Driver.Skia do
     window_title = Keyword.get(window_opts, :title, "Scenic Window")
     window_resizeable = Keyword.get(window_opts, :resizeable, false)
     drm_opts = Keyword.get(opts, :drm, [])
-    drm_card = Keyword.get(drm_opts, :card)
     drm_hw_cursor = Keyword.get(drm_opts, :hw_cursor, true)
     drm_input_log = Keyword.get(drm_opts, :input_log, false)
 
-    case Native.start(
-           opts[:backend],
-           viewport_size,
-           window_title,
-           window_resizeable,
-           drm_card,
-           drm_hw_cursor,
-           drm_input_log
-         ) do
-      {:ok, renderer} ->
-        maybe_set_input_target(renderer, self())
-
-        {:ok,
-         assign(driver,
-           opts: opts,
-           update_count: 0,
-           input_mask: 0,
-           renderer: renderer,
-           media: %{fonts: [], images: [], streams: []}
-         )}
-
+    with {:ok, drm_card} <- drm_card(drm_opts),
+         {:ok, renderer} <-
+           Native.start(
+             opts[:backend],
+             viewport_size,
+             window_title,
+             window_resizeable,
+             drm_card,
+             drm_hw_cursor,
+             drm_input_log
+           ) do
+      maybe_set_input_target(renderer, self())
+      maybe_set_script_cache(renderer, Keyword.get(opts, :script_cache, []))
+      maybe_set_asset_store(renderer, opts[:asset_store])
+      maybe_set_image_dedup(renderer, Keyword.get(opts, :dedup_images, false))
+      maybe_set_pointer_accel(renderer, drm_opts)
+      maybe_set_output_rotation(renderer, Keyword.get(drm_opts, :rotation, 0))
+      maybe_set_overscan(renderer, Keyword.get(opts, :overscan, []))
+      maybe_set_gradient_dither(renderer, Keyword.get(opts, :gradient_dither, :off))
+      maybe_set_text_fallback(renderer, Keyword.get(opts, :text_fallback, :auto))
+      maybe_set_script_isolation(renderer, Keyword.get(opts, :isolate_scripts, false))
+      maybe_set_reduced_motion(renderer, Keyword.get(opts, :reduced_motion, false))
+      maybe_set_linear_blending(renderer, Keyword.get(opts, :linear_blending, false))
+      maybe_set_software_gl(renderer, Keyword.get(opts, :software_gl, :warn))
+      maybe_set_frame_deadline(renderer, Keyword.get(opts, :frame_deadline_ms, 0))
+      maybe_set_cursor_interval(renderer, Keyword.get(opts, :cursor_interval_ms, 0))
+      maybe_set_reference_check(renderer, Keyword.get(opts, :reference_check, []))
+      maybe_set_script_limits(renderer, Keyword.get(opts, :script_limits, []))
+      maybe_set_quality_governor(renderer, Keyword.get(opts, :quality, []))
+      maybe_set_geometry_mode(renderer, Keyword.get(opts, :geometry, :clamp))
+      maybe_set_poisoned_state(renderer, Keyword.get(opts, :poisoned_state, :recover))
+      maybe_set_flight_recorder(renderer, Keyword.get(opts, :flight_recorder, []))
+      maybe_set_stats_export(renderer, Keyword.get(opts, :stats_export, []))
+      maybe_set_dev_overlay(renderer, Keyword.get(opts, :dev_overlay, []))
+      maybe_set_watermark(renderer, Keyword.get(opts, :watermark, []))
+      maybe_set_hardware_decode(Keyword.get(opts, :hardware_decode, false))
+
+      driver =
+        assign(driver,
+          opts: opts,
+          update_count: 0,
+          input_mask: 0,
+          degraded: false,
+          renderer: renderer,
+          media: %{fonts: [], images: [], streams: []}
+        )
+
+      {:ok, maybe_set_theme(driver, Keyword.get(opts, :theme, []))}
+    else
       {:error, reason} ->
         {:stop, reason}
 
@@ -132,7 +263,7 @@ defmodule Scenic.Driver.Skia do
   @impl GenServer
   def handle_info(:input_ready, driver) do
     events =
-      case Native.drain_input_events(driver.assigns.renderer) do
+      case drain_driver_input(driver) do
         {:ok, list} when is_list(list) ->
           list
 
@@ -140,22 +271,59 @@ defmodule Scenic.Driver.Skia do
           list
 
         {:error, reason} ->
-          Logger.warning("drain_input_events failed: #{inspect(reason)}")
+          Logger.warning("draining input failed: #{inspect(reason)}")
           []
 
         other ->
-          Logger.warning("drain_input_events returned #{inspect(other)}")
+          Logger.warning("draining input returned #{inspect(other)}")
           []
       end
 
+    # Scenic has no touch, hover or scroll stop input; those only go to extra input targets.
     driver =
-      Enum.reduce(events, driver, fn event, acc ->
-        Driver.send_input(acc, event)
+      events
+      |> Enum.reject(&target_only_input?/1)
+      |> Enum.reduce(driver, fn event, acc ->
+        Driver.send_input(acc, scenic_input(event, acc.assigns.opts[:key_details]))
       end)
 
     {:noreply, driver}
   end
 
+  @impl GenServer
+  def handle_info({:degraded_mode, frame_ms, deadline_ms}, driver) do
+    Logger.warning(
+      "Scenic.Driver.Skia frame took #{frame_ms}ms (deadline #{deadline_ms}ms); " <>
+        "degraded render mode enabled"
+    )
+
+    maybe_dump_flight_recorder(driver, "degraded")
+    {:noreply, assign(driver, :degraded, true)}
+  end
+
+  @impl GenServer
+  def handle_info({:render_state_recovered, count}, driver) do
+    Logger.error(
+      "Scenic.Driver.Skia render state was poisoned by a panic; " <>
+        "scene cleared and resubmitted (recovery #{count})"
+    )
+
+    maybe_dump_flight_recorder(driver, "recovered")
+    {:ok, driver} = update_scene(ViewPort.all_script_ids(driver.viewport), driver)
+    {:noreply, driver}
+  end
+
+  @impl GenServer
+  def handle_info({:frame, timestamp_us}, driver) do
+    {:noreply, assign(driver, :frame_us, timestamp_us)}
+  end
+
+  @impl GenServer
+  def handle_info({:drm_session, state}, driver) when state in [:paused, :resumed] do
+    Logger.info("Scenic.Driver.Skia DRM session #{state}")
+    {:noreply, assign(driver, :session, state)}
+  end
+
   @impl GenServer
   def handle_info({{Stream, :put}, _type, id}, driver) do
     driver = put_stream_asset(id, driver)
@@ -201,7 +369,9 @@ defmodule Scenic.Driver.Skia do
         Native.submit_scripts(driver.assigns.renderer, updates)
         |> case do
           :ok -> :ok
-          {:ok, _} -> :ok
+          {:ok, []} -> :ok
+          {:ok, unresolved} ->
+            Logger.warning("unresolved script references: #{inspect(unresolved)}")
           {:error, reason} -> Logger.warning("submit_scripts failed: #{inspect(reason)}")
           other -> Logger.warning("submit_scripts returned #{inspect(other)}")
         end
@@ -211,6 +381,25 @@ defmodule Scenic.Driver.Skia do
     {:ok, driver}
   end
 
+  defp image_hash(image) do
+    case Static.to_hash(image) do
+      {:ok, hash} -> hash
+      _ -> to_string(image)
+    end
+  end
+
+  defp format_number(value, nil) when is_integer(value), do: Integer.to_string(value)
+  defp format_number(value, nil), do: format_number(value, 2)
+
+  defp format_number(value, decimals) when is_integer(decimals) and decimals >= 0 do
+    :erlang.float_to_binary(value / 1, decimals: decimals)
+  end
+
+  defp rgba(color) do
+    {:color_rgba, rgba} = Scenic.Color.to_rgba(color)
+    rgba
+  end
+
   defp normalize_viewport_size(nil), do: nil
 
   defp normalize_viewport_size({width, height}) do
@@ -227,8 +416,224 @@ defmodule Scenic.Driver.Skia do
     encode_clip_path(mode)
   end
 
+  defp serialize_op({:non_scaling_stroke, enabled}) when is_boolean(enabled) do
+    <<0x0076::16-big, if(enabled, do: 0x01, else: 0x00)::16-big>>
+  end
+
+  defp serialize_op({:text_path, text}) when is_binary(text) do
+    pad = rem(4 - rem(byte_size(text), 4), 4)
+    <<0x0033::16-big, byte_size(text)::16-big, text::binary, 0::size(pad * 8)>>
+  end
+
+  defp serialize_op({:draw_text_ellipsized, text, max_width})
+       when is_binary(text) and is_number(max_width) do
+    pad = rem(4 - rem(byte_size(text), 4), 4)
+
+    <<0x0034::16-big, byte_size(text)::16-big, max_width::float-32-big, text::binary,
+      0::size(pad * 8)>>
+  end
+
+  defp serialize_op({:draw_number, value}), do: serialize_op({:draw_number, value, []})
+
+  defp serialize_op({:draw_number, value, opts}) when is_number(value) and is_list(opts) do
+    text =
+      value
+      |> format_number(Keyword.get(opts, :decimals))
+      |> String.pad_leading(Keyword.get(opts, :width, 0))
+
+    pad = rem(4 - rem(byte_size(text), 4), 4)
+    <<0x0035::16-big, byte_size(text)::16-big, text::binary, 0::size(pad * 8)>>
+  end
+
+  defp serialize_op({:draw_animated_sprite, image, {dx, dy, dw, dh}, frames})
+       when is_list(frames) do
+    id = image_hash(image)
+    pad = rem(4 - rem(byte_size(id), 4), 4)
+
+    frames =
+      for {{sx, sy, sw, sh}, duration_ms} <- frames, into: <<>> do
+        <<sx::float-32-big, sy::float-32-big, sw::float-32-big, sh::float-32-big,
+          duration_ms::32-big>>
+      end
+
+    <<0x0036::16-big, byte_size(id)::16-big, div(byte_size(frames), 20)::32-big, id::binary,
+      0::size(pad * 8), dx::float-32-big, dy::float-32-big, dw::float-32-big,
+      dh::float-32-big, frames::binary>>
+  end
+
+  defp serialize_op({:draw_sprite_cell, image, {dx, dy, dw, dh}, {columns, rows}})
+       when columns in 1..0xFFFF and rows in 1..0xFFFF do
+    id = image_hash(image)
+    pad = rem(4 - rem(byte_size(id), 4), 4)
+
+    <<0x003D::16-big, byte_size(id)::16-big, columns::16-big, rows::16-big, id::binary,
+      0::size(pad * 8), dx::float-32-big, dy::float-32-big, dw::float-32-big,
+      dh::float-32-big>>
+  end
+
+  defp serialize_op({:fill_pattern, id, {width, height}})
+       when is_binary(id) and is_number(width) and width > 0 and is_number(height) and
+              height > 0 do
+    pad = rem(4 - rem(byte_size(id), 4), 4)
+
+    <<0x0065::16-big, byte_size(id)::16-big, width::float-32-big, height::float-32-big,
+      id::binary, 0::size(pad * 8)>>
+  end
+
+  defp serialize_op({:drop_shadow, {dx, dy, sigma, color}})
+       when is_number(dx) and is_number(dy) and is_number(sigma) and sigma >= 0 do
+    {:color_rgba, {r, g, b, a}} = Scenic.Color.to_rgba(color)
+
+    <<0x0066::16-big, 0x00::16-big, dx::float-32-big, dy::float-32-big, sigma::float-32-big,
+      r::8, g::8, b::8, a::8>>
+  end
+
+  defp serialize_op({:blur, sigma}) when is_number(sigma) and sigma >= 0 do
+    <<0x0067::16-big, 0x00::16-big, sigma::float-32-big>>
+  end
+
+  defp serialize_op({:blink, period_ms}) when is_integer(period_ms) and period_ms >= 0 do
+    <<0x003B::16-big, 0x00::16-big, period_ms::32-big>>
+  end
+
+  defp serialize_op({:marquee, width, speed}) when is_number(width) and is_number(speed) do
+    <<0x003C::16-big, 0x00::16-big, width::float-32-big, speed::float-32-big>>
+  end
+
+  defp serialize_op({:text_width, width}) when is_number(width) and width >= 0 do
+    <<0x0094::16-big, 0x00::16-big, width::float-32-big>>
+  end
+
+  defp serialize_op({:line_height, height}) when is_number(height) and height >= 0 do
+    <<0x0095::16-big, 0x00::16-big, height::float-32-big>>
+  end
+
+  defp serialize_op({:text_wrap, mode}) when mode in [:none, :word, :char] do
+    code =
+      case mode do
+        :none -> 0x00
+        :word -> 0x01
+        :char -> 0x02
+      end
+
+    <<0x0096::16-big, code::16-big>>
+  end
+
+  defp serialize_op({:draw_squircle, {width, height, exponent, flag}})
+       when is_number(width) and is_number(height) and is_number(exponent) and exponent > 0 do
+    <<0x0039::16-big, draw_flag(flag)::16-big, width::float-32-big, height::float-32-big,
+      exponent::float-32-big>>
+  end
+
+  defp serialize_op({:superellipse, {width, height, exponent}})
+       when is_number(width) and is_number(height) and is_number(exponent) and exponent > 0 do
+    <<0x003A::16-big, 0x00::16-big, width::float-32-big, height::float-32-big,
+      exponent::float-32-big>>
+  end
+
+  defp serialize_op({:draw_polygon, {sides, radius, rounding, flag}})
+       when is_integer(sides) and sides >= 3 and is_number(radius) and is_number(rounding) do
+    <<0x0037::16-big, draw_flag(flag)::16-big, sides::32-big, radius::float-32-big,
+      rounding::float-32-big>>
+  end
+
+  defp serialize_op({:draw_star, {points, outer, inner, rounding, flag}})
+       when is_integer(points) and points >= 2 and is_number(outer) and is_number(inner) and
+              is_number(rounding) do
+    <<0x0038::16-big, draw_flag(flag)::16-big, points::32-big, outer::float-32-big,
+      inner::float-32-big, rounding::float-32-big>>
+  end
+
+  defp serialize_op({:save_layer, bounds}), do: serialize_op({:save_layer, bounds, []})
+
+  defp serialize_op({:save_layer, {x, y, w, h}, opts}) when is_list(opts) do
+    alpha = opts |> Keyword.get(:alpha, 1.0) |> layer_alpha()
+    blend = opts |> Keyword.get(:blend, :normal) |> layer_blend()
+
+    <<0x0046::16-big, blend::8, alpha::8, x::float-32-big, y::float-32-big, w::float-32-big,
+      h::float-32-big>>
+  end
+
+  defp serialize_op(:restore), do: <<0x0047::16-big, 0x00::16-big>>
+
+  defp serialize_op({kind, shape, stops})
+       when kind in [:fill_gradient, :stroke_gradient] and is_list(stops) do
+    encode_gradient(kind, shape, stops, 0x00)
+  end
+
+  defp serialize_op({:dither, op}), do: serialize_op({:dither, op, :paint})
+
+  defp serialize_op({:dither, {kind, shape, stops}, mode})
+       when kind in [:fill_gradient, :stroke_gradient] and mode in [:paint, :noise] do
+    encode_gradient(kind, shape, stops, dither_flag(mode))
+  end
+
+  defp serialize_op({:dither, {kind, _} = op, mode})
+       when kind in [:fill_linear, :fill_radial, :stroke_linear, :stroke_radial] and
+              mode in [:paint, :noise] do
+    <<opcode::16-big, _flag::16-big, payload::binary>> =
+      [op] |> Script.serialize() |> IO.iodata_to_binary()
+
+    <<opcode::16-big, dither_flag(mode)::16-big, payload::binary>>
+  end
+
   defp serialize_op(other), do: other
 
+  defp layer_alpha(alpha) when is_number(alpha) and alpha >= 0 and alpha <= 1,
+    do: round(alpha * 255)
+
+  defp layer_alpha(alpha) do
+    raise ArgumentError, "invalid save_layer alpha: #{inspect(alpha)}"
+  end
+
+  @layer_blend_modes [
+    :normal,
+    :multiply,
+    :screen,
+    :overlay,
+    :darken,
+    :lighten,
+    :plus,
+    :difference
+  ]
+
+  defp layer_blend(mode) do
+    case Enum.find_index(@layer_blend_modes, &(&1 == mode)) do
+      nil -> raise ArgumentError, "invalid save_layer blend mode: #{inspect(mode)}"
+      index -> index
+    end
+  end
+
+  defp encode_gradient(kind, shape, stops, flag) when length(stops) in 2..256 do
+    opcode = if kind == :fill_gradient, do: 0x0068, else: 0x0077
+    {shape_id, {a, b, c, d}} = gradient_shape(shape)
+
+    encoded = for {position, color} <- stops, into: <<>>, do: encode_stop(position, color)
+
+    <<opcode::16-big, flag::16-big, shape_id::16-big, length(stops)::16-big, 0::16,
+      a::float-32-big, b::float-32-big, c::float-32-big, d::float-32-big, encoded::binary>>
+  end
+
+  defp encode_stop(position, color) when is_number(position) do
+    {:color_rgba, {r, g, b, a}} = Scenic.Color.to_rgba(color)
+    <<position::float-32-big, r::8, g::8, b::8, a::8>>
+  end
+
+  defp gradient_shape({:linear, {x0, y0, x1, y1}}), do: {0, {x0, y0, x1, y1}}
+  defp gradient_shape({:radial, {cx, cy, inner, outer}}), do: {1, {cx, cy, inner, outer}}
+  defp gradient_shape({:conic, {cx, cy, start_angle}}), do: {2, {cx, cy, start_angle, 0}}
+
+  defp dither_flag(:paint), do: 0x01
+  defp dither_flag(:noise), do: 0x02
+
+  defp draw_flag(:fill), do: 0x01
+  defp draw_flag(:stroke), do: 0x02
+  defp draw_flag(:fill_stroke), do: 0x03
+
+  defp draw_flag(flag) do
+    raise ArgumentError, "invalid draw flag: #{inspect(flag)}"
+  end
+
   defp encode_clip_path(:intersect), do: <<0x0045::16-big, 0x00::16-big>>
   defp encode_clip_path(:difference), do: <<0x0045::16-big, 0x01::16-big>>
 
@@ -257,9 +662,17 @@ defmodule Scenic.Driver.Skia do
     driver
     |> ensure_fonts(Map.get(media, :fonts, []))
     |> ensure_images(Map.get(media, :images, []))
+    |> ensure_images(animated_sprite_images(script))
     |> ensure_streams(Map.get(media, :streams, []))
   end
 
+  defp animated_sprite_images(script) do
+    for {op, image, _dst, _frames} <- script,
+        op in [:draw_animated_sprite, :draw_sprite_cell],
+        uniq: true,
+        do: image
+  end
+
   defp ensure_fonts(driver, []), do: driver
 
   defp ensure_fonts(%{assigns: %{renderer: renderer, media: media}} = driver, ids) do
@@ -271,7 +684,11 @@ defmodule Scenic.Driver.Skia do
              {:ok, {Static.Font, _}} <- Static.meta(id),
              {:ok, hash} <- Static.to_hash(id),
              {:ok, bin} <- Static.load(id) do
-          _ = Native.put_font(renderer, hash, bin)
+          case put_font(renderer, hash, bin) do
+            :ok -> :ok
+            {:error, reason} -> Logger.warning("put_font failed: #{inspect(reason)}")
+          end
+
           [id | fonts]
         else
           _ -> fonts
@@ -369,9 +786,11 @@ defmodule Scenic.Driver.Skia do
   Start the renderer manually with the provided backend.
 
   This bypasses the Scenic ViewPort lifecycle and is intended for demos/tests.
-  Accepts `:wayland` or `:drm` and returns a renderer handle.
+  Accepts `:wayland`, `:vulkan`, `:drm`, `:raster`, `:raster_gpu` or `:auto` and returns a
+  renderer handle.
   """
-  @spec start(:wayland | :drm | String.t()) :: {:ok, term()} | {:error, term()}
+  @spec start(:wayland | :vulkan | :drm | :raster | :raster_gpu | :auto | String.t()) ::
+          {:ok, term()} | {:error, term()}
   def start(backend) when is_atom(backend) or is_binary(backend) do
     backend
     |> normalize_backend()
@@ -389,7 +808,7 @@ defmodule Scenic.Driver.Skia do
   end
 
   @doc """
-  Show the cursor when using the DRM backend.
+  Show the cursor on the DRM and Wayland backends.
 
   Accepts a renderer handle returned by `start/0` or `start/1`.
   """
@@ -400,7 +819,7 @@ defmodule Scenic.Driver.Skia do
   end
 
   @doc """
-  Hide the cursor when using the DRM backend.
+  Hide the cursor on the DRM and Wayland backends.
 
   Accepts a renderer handle returned by `start/0` or `start/1`.
   """
@@ -411,54 +830,2147 @@ defmodule Scenic.Driver.Skia do
   end
 
   @doc """
-  Update the text rendered by the driver.
+  Replace the default cursor with a custom image, or restore it with `nil`.
 
-  Accepts a renderer handle returned by `start/0` or `start/1`.
+  `image` is `{width, height, rgba}` with unpremultiplied RGBA rows of at most 64x64
+  pixels, and `hotspot` the `{x, y}` pixel that sits at the pointer position. Cursor
+  regions set with `set_cursor_regions/2` still show their own shapes. Wayland sets it as
+  the window cursor; DRM writes it to the cursor plane or draws it as the software cursor.
   """
-  @spec set_text(term(), String.t()) :: :ok | {:error, term()}
-  def set_text(renderer, text) when is_binary(text) do
-    Native.set_text(renderer, text)
+  @spec set_cursor_image(
+          term(),
+          {pos_integer(), pos_integer(), binary()} | nil,
+          {non_neg_integer(), non_neg_integer()}
+        ) :: :ok | {:error, term()}
+  def set_cursor_image(renderer, image, hotspot \\ {0, 0})
+
+  def set_cursor_image(renderer, nil, _hotspot) do
+    Native.set_cursor_image(renderer, nil)
+    |> normalize_start_result()
   end
 
-  @doc false
-  @spec renderer_handle(GenServer.server()) :: term()
-  def renderer_handle(driver_pid) do
-    GenServer.call(driver_pid, :renderer_handle)
+  def set_cursor_image(renderer, {width, height, rgba}, {hx, hy})
+      when is_binary(rgba) do
+    Native.set_cursor_image(renderer, {width, height, rgba, {hx, hy}})
+    |> normalize_start_result()
   end
 
-  defp normalize_backend(backend) do
-    backend
-    |> to_string()
-    |> String.downcase()
+  @doc """
+  Register cursor hit regions as `[{{x, y, width, height}, cursor}]` in scene coordinates.
+
+  While the pointer is over a region the backend shows its cursor: the window cursor on
+  Wayland, the cursor plane image or software cursor on DRM. Later regions are on top, and
+  outside every region the default cursor is shown. `[]` clears the regions.
+
+  Cursors: `:default`, `:pointer`, `:text`, `:crosshair`, `:move`, `:not_allowed`, `:grab`,
+  `:grabbing`, `:ew_resize`, `:ns_resize` and `:wait`.
+  """
+  @spec set_cursor_regions(term(), [{{number(), number(), number(), number()}, atom()}]) ::
+          :ok | {:error, term()}
+  def set_cursor_regions(renderer, regions) when is_list(regions) do
+    regions =
+      Enum.map(regions, fn {{x, y, w, h}, cursor} when is_atom(cursor) ->
+        {{x / 1, y / 1, w / 1, h / 1}, Atom.to_string(cursor)}
+      end)
+
+    Native.set_cursor_regions(renderer, regions)
+    |> normalize_start_result()
   end
 
-  defp input_mask_from_request(:all), do: @input_mask_all
+  @doc """
+  Register hover regions as `[{id, {x, y, width, height}}]` in scene coordinates.
+
+  Input targets that ask for `:hover` (see `add_input_target/3`) receive
+  `{:hover, {:enter, id}}` and `{:hover, {:leave, id}}` as the pointer crosses the edges of
+  a region, worked out natively from the cursor positions the backend delivers instead of
+  from every position sent to the BEAM. Overlapping regions are hovered together and rects
+  sharing an id form one region. The pointer leaving the window leaves every region, and
+  `[]` clears them.
+  """
+  @spec set_hover_regions(term(), [{String.t(), {number(), number(), number(), number()}}]) ::
+          :ok | {:error, term()}
+  def set_hover_regions(renderer, regions) when is_list(regions) do
+    regions =
+      Enum.map(regions, fn {id, {x, y, w, h}} when is_binary(id) ->
+        {id, {x / 1, y / 1, w / 1, h / 1}}
+      end)
 
-  defp input_mask_from_request(request) when is_list(request) do
-    Enum.reduce(request, 0, fn
-      :key, mask -> mask ||| @input_mask_key
-      :codepoint, mask -> mask ||| @input_mask_codepoint
-      :cursor_pos, mask -> mask ||| @input_mask_cursor_pos
-      :cursor_button, mask -> mask ||| @input_mask_cursor_button
-      :cursor_scroll, mask -> mask ||| @input_mask_cursor_scroll
-      :viewport, mask -> mask ||| @input_mask_viewport
-      _, mask -> mask
-    end)
+    Native.set_hover_regions(renderer, regions)
+    |> normalize_start_result()
   end
 
-  defp input_mask_from_request(_), do: 0
+  @doc """
+  Register focusable regions as `[{id, {x, y, width, height}}]` in scene coordinates.
 
-  defp normalize_start_result(:ok), do: :ok
-  defp normalize_start_result({:ok, _}), do: :ok
-  defp normalize_start_result({:error, _} = error), do: error
-  defp normalize_start_result(other), do: {:error, {:unexpected_result, other}}
+  Keypad and remote-driven UIs move between them with `focus_next/2` instead of searching
+  the layout in Elixir. Ids must be unique; the focus stays on its region while the id is
+  still registered, and `[]` clears the regions and the focus.
+  """
+  @spec set_focus_regions(term(), [{String.t(), {number(), number(), number(), number()}}]) ::
+          :ok | {:error, term()}
+  def set_focus_regions(renderer, regions) when is_list(regions) do
+    regions =
+      Enum.map(regions, fn {id, {x, y, w, h}} when is_binary(id) ->
+        {id, {x / 1, y / 1, w / 1, h / 1}}
+      end)
 
-  defp maybe_set_input_target(renderer, pid) do
-    case Native.set_input_target(renderer, pid) do
-      :ok -> :ok
-      {:ok, _} -> :ok
-      {:error, reason} -> Logger.warning("set_input_target failed: #{inspect(reason)}")
-      other -> Logger.warning("set_input_target returned #{inspect(other)}")
+    Native.set_focus_regions(renderer, regions)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Move the focus to the nearest region in `direction` and return the focused id.
+
+  `direction` is `:up`, `:down`, `:left` or `:right`. A candidate has to lie further that
+  way than the focused region; the closest one wins, with regions in line with the focus
+  preferred over ones off to the side. Without a focus the first registered region is
+  focused, and the focus stays put when nothing lies in `direction`. Returns `{:ok, nil}`
+  when no regions are registered.
+  """
+  @spec focus_next(term(), :up | :down | :left | :right) ::
+          {:ok, String.t() | nil} | {:error, term()}
+  def focus_next(renderer, direction) when direction in [:up, :down, :left, :right] do
+    Native.focus_next(renderer, Atom.to_string(direction))
+  end
+
+  @doc """
+  Focus region `id` (as registered with `set_focus_regions/2`), or clear the focus with
+  `nil`.
+  """
+  @spec set_focus(term(), String.t() | nil) :: :ok | {:error, term()}
+  def set_focus(renderer, id) when is_binary(id) or is_nil(id) do
+    Native.set_focus(renderer, id)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Show or hide a natively drawn ring around the focused region.
+
+  With `enabled: true`, a `width`-wide outline (default `3`) in `color` (default
+  `:white`) is drawn just outside the focused region with corner `radius` (default `0`),
+  over the scene and without any script changes. Moving the focus only redraws the area
+  the ring left and entered. `enabled: false` hides it.
+  """
+  @spec set_focus_ring(term(), keyword()) :: :ok | {:error, term()}
+  def set_focus_ring(renderer, opts) when is_list(opts) do
+    ring =
+      if Keyword.get(opts, :enabled, false) do
+        {:color_rgba, rgba} = Scenic.Color.to_rgba(Keyword.get(opts, :color, :white))
+        {rgba, Keyword.get(opts, :width, 3) / 1, Keyword.get(opts, :radius, 0) / 1}
+      end
+
+    Native.set_focus_ring(renderer, ring)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Submit a serialized script under `id`.
+
+  The renderer measures the area the update touches from where the script was drawn in
+  the last frame: raster backends repaint just those pixels instead of the whole frame,
+  and on Wayland the compositor is told which part changed. With
+  `bounds: {x, y, width, height}` (root coordinates) that area is taken as given instead.
+  The hint must cover everything the change affects, including other scripts that draw
+  `id`.
+  """
+  @spec submit_script_with_id(term(), String.t(), binary(), keyword()) ::
+          :ok | {:ok, [script_reference()]} | {:error, term()}
+  def submit_script_with_id(renderer, id, script, opts \\ [])
+      when is_binary(id) and is_binary(script) and is_list(opts) do
+    bounds =
+      case Keyword.get(opts, :bounds) do
+        {x, y, w, h} -> {x / 1, y / 1, w / 1, h / 1}
+        nil -> nil
+      end
+
+    Native.submit_script_with_id(renderer, id, script, bounds)
+    |> normalize_submit_result()
+  end
+
+  @doc """
+  Submit the serialized script stored in the file at `path` under `id`.
+
+  The renderer reads the file itself, so large precompiled scenes are not read into the
+  BEAM and copied across the NIF boundary. Returns like `submit_script_with_id/4`.
+  """
+  @spec submit_script_file(term(), String.t(), Path.t()) ::
+          :ok | {:ok, [script_reference()]} | {:error, term()}
+  def submit_script_file(renderer, id, path) when is_binary(id) do
+    Native.submit_script_file(renderer, id, to_string(path))
+    |> normalize_submit_result()
+  end
+
+  @doc """
+  Schedule a serialized script to replace `id` at a time on the driver clock.
+
+  `monotonic_ms` is compared against `monotonic_ms/0`, and the render loop swaps the
+  script in on the first frame at or after that time. Use it to line up scene changes
+  across devices without depending on BEAM scheduling.
+  """
+  @spec submit_script_at(term(), String.t(), binary(), non_neg_integer()) ::
+          :ok | {:error, term()}
+  def submit_script_at(renderer, id, script, monotonic_ms)
+      when is_binary(id) and is_binary(script) and is_integer(monotonic_ms) and
+             monotonic_ms >= 0 do
+    Native.submit_script_at(renderer, id, script, monotonic_ms)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Apply stream textures and serialized scripts in the same frame.
+
+  `textures` is a list of `{id, format, width, height, data}` as accepted by
+  `Native.put_stream_texture/6` and `scripts` a list of `{id, binary}`. Every texture and
+  script is swapped in together, so a video frame and its subtitle overlay never tear.
+  Pass `at: monotonic_ms` to hold the whole update until that time on the driver clock.
+
+  With strict reference checks enabled (see `set_reference_check/2`), an immediate update
+  that references missing scripts or images returns `{:ok, unresolved}`.
+  """
+  @spec submit_frame(
+          term(),
+          [{String.t(), String.t(), non_neg_integer(), non_neg_integer(), binary()}],
+          [{String.t(), binary()}],
+          keyword()
+        ) :: :ok | {:ok, [script_reference()]} | {:error, term()}
+  def submit_frame(renderer, textures, scripts, opts \\ [])
+      when is_list(textures) and is_list(scripts) and is_list(opts) do
+    Native.submit_frame(renderer, textures, scripts, Keyword.get(opts, :at))
+    |> normalize_submit_result()
+  end
+
+  @doc """
+  Apply scripts, static images, stream textures and deletions in the same frame.
+
+  Accepts the keys `scripts: [{id, binary}]`, `images: [{id, encoded}]` (as
+  `Native.put_static_image/3` takes them), `streams: [{id, format, width, height, data}]`
+  and `delete: [{:script | :image | :stream, id}]`; each defaults to `[]`. Everything is
+  decoded and parsed first, so a bad entry changes nothing. Deletions are applied before
+  the additions, then all of it is swapped in under one render state lock, so a scene
+  update that touches both scripts and images never presents half of itself.
+
+  With strict reference checks enabled, references the transaction leaves unresolved are
+  returned as `{:ok, unresolved}`.
+  """
+  @spec submit_transaction(term(), keyword()) ::
+          :ok | {:ok, [script_reference()]} | {:error, term()}
+  def submit_transaction(renderer, changes) when is_list(changes) do
+    Native.submit_transaction(renderer, %{
+      scripts: Keyword.get(changes, :scripts, []),
+      images: Keyword.get(changes, :images, []),
+      streams: Keyword.get(changes, :streams, []),
+      deletes: Keyword.get(changes, :delete, [])
+    })
+    |> normalize_submit_result()
+  end
+
+  @doc """
+  Load a bundle of scripts, static images and fonts from one tar archive.
+
+  `source` is the archive itself (plain or gzipped tar) or a path to it. The archive must
+  contain a `manifest` file listing its entries, one per line:
+
+      font roboto fonts/Roboto-Regular.ttf
+      image logo images/logo.png
+      script _root_ scripts/root.bin
+
+  Ids are the names scripts use to refer to the entry and paths are relative to the
+  archive root; blank lines and lines starting with `#` are ignored. Scripts are
+  serialized script binaries. Every entry is decoded before anything is registered, and
+  all of them are swapped in under one render state lock, so the next frame shows the
+  whole bundle, or nothing changes if an entry is bad.
+
+  With strict reference checks enabled, references the bundle leaves unresolved are
+  returned as `{:ok, unresolved}`.
+  """
+  @spec load_bundle(term(), binary() | Path.t()) ::
+          :ok | {:ok, [script_reference()]} | {:error, term()}
+  def load_bundle(renderer, source) when is_binary(source) do
+    with {:ok, archive} <- read_bundle_source(source),
+         {:ok, files} <- extract_bundle(archive),
+         {:ok, entries} <- bundle_entries(files) do
+      entries = Enum.group_by(entries, &elem(&1, 0), &Tuple.delete_at(&1, 0))
+
+      Native.load_bundle(
+        renderer,
+        Map.get(entries, :font, []),
+        Map.get(entries, :image, []),
+        Map.get(entries, :script, [])
+      )
+      |> normalize_submit_result()
+    end
+  end
+
+  @doc """
+  Draw a script over the scene on the next frame only.
+
+  Immediate scripts are not stored, counted as references or addressable by id, so
+  transient overlays such as drag ghosts or touch ripples can be sent at input rate
+  without churning the script store. `script` is a serialized binary or a `Scenic.Script`
+  op list. A batch that has not been drawn yet is replaced by the newer one; send an empty
+  script (`<<>>` or `[]`) to remove the overlay when the interaction ends.
+  """
+  @spec draw_immediate(term(), binary() | Script.t()) :: :ok | {:error, term()}
+  def draw_immediate(renderer, script) when is_list(script) do
+    draw_immediate(renderer, serialize_script(script))
+  end
+
+  def draw_immediate(renderer, script) when is_binary(script) do
+    Native.draw_immediate(renderer, script)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Present a solid `color` frame next, bypassing the script pipeline.
+
+  The frame skips scripts, immediate ops and partial redraws, so it costs the same
+  regardless of scene complexity. With `hold: true` every frame stays blank until
+  `release_display/1`; otherwise the scene returns with the next redraw. Scene updates
+  are still accepted while the display is held.
+  """
+  @spec clear_display(term(), Scenic.Color.t(), keyword()) :: :ok | {:error, term()}
+  def clear_display(renderer, color, opts \\ []) when is_list(opts) do
+    {:color_rgba, rgba} = Scenic.Color.to_rgba(color)
+
+    Native.clear_display(renderer, rgba, Keyword.get(opts, :hold, false))
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Release a display held by `clear_display/3` and redraw the scene.
+
+  Returns `{:ok, true}` when a blank was active.
+  """
+  @spec release_display(term()) :: {:ok, boolean()} | {:error, term()}
+  def release_display(renderer) do
+    Native.release_display(renderer)
+  end
+
+  @doc """
+  Current time of the driver clock (`CLOCK_MONOTONIC`) in milliseconds.
+  """
+  @spec monotonic_ms() :: non_neg_integer()
+  def monotonic_ms, do: Native.monotonic_ms()
+
+  @doc """
+  Choose the clock the renderer animates by.
+
+  `:monotonic` (default) follows the driver clock. `:manual` freezes time at the current
+  driver time, `{:manual, start_ms}` at `start_ms`; from then on time only moves with
+  `advance_time/2`. Blinks, marquees, animated sprites and updates scheduled with
+  `submit_script_at/4` or `submit_frame/4` all follow this clock, so golden-frame tests of
+  animated content render the same frames on every run.
+  """
+  @spec set_clock(term(), :monotonic | :manual | {:manual, non_neg_integer()}) ::
+          :ok | {:error, term()}
+  def set_clock(renderer, :monotonic) do
+    Native.set_clock(renderer, "monotonic", nil)
+    |> normalize_start_result()
+  end
+
+  def set_clock(renderer, :manual) do
+    Native.set_clock(renderer, "manual", nil)
+    |> normalize_start_result()
+  end
+
+  def set_clock(renderer, {:manual, start_ms}) when is_integer(start_ms) and start_ms >= 0 do
+    Native.set_clock(renderer, "manual", start_ms)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Move the manual clock forward by `ms` milliseconds and redraw.
+
+  Returns the new clock time, or an error while the renderer follows the driver clock.
+  """
+  @spec advance_time(term(), non_neg_integer()) :: {:ok, non_neg_integer()} | {:error, term()}
+  def advance_time(renderer, ms) when is_integer(ms) and ms >= 0 do
+    Native.advance_time(renderer, ms)
+  end
+
+  @doc """
+  Submit serialized scripts under a namespace.
+
+  Each `{id, binary}` is stored as `"namespace/id"`, and `draw_script` references inside
+  the binaries resolve relative to the namespace. Prefix a reference with `/` to point at
+  a script outside the namespace (for example `"/_root_"`). In strict reference check
+  mode, unresolved references are returned as `{:ok, unresolved}`.
+  """
+  @spec submit_scripts(term(), String.t(), [{String.t(), binary()}]) ::
+          :ok | {:ok, [script_reference()]} | {:error, term()}
+  def submit_scripts(renderer, namespace, scripts)
+      when is_binary(namespace) and is_list(scripts) do
+    Native.submit_scripts(renderer, namespace, scripts)
+    |> normalize_submit_result()
+  end
+
+  @doc """
+  Delete every script stored under `namespace` in a single update.
+
+  Returns the number of scripts removed.
+  """
+  @spec del_namespace(term(), String.t()) :: {:ok, non_neg_integer()} | {:error, term()}
+  def del_namespace(renderer, namespace) when is_binary(namespace) do
+    case Native.del_namespace(renderer, namespace) do
+      {:ok, count} -> {:ok, count}
+      count when is_integer(count) -> {:ok, count}
+      {:error, _} = error -> error
+      other -> {:error, {:unexpected_result, other}}
+    end
+  end
+
+  @doc """
+  Snapshot the stored scripts and return a token for `rollback/2`.
+
+  The snapshot covers the scripts, the root script and pending scheduled scripts, not
+  images or fonts. Script ops are shared with the live store, so checkpoints are cheap;
+  the newest 16 are kept and older tokens stop resolving.
+  """
+  @spec checkpoint(term()) :: {:ok, non_neg_integer()} | {:error, term()}
+  def checkpoint(renderer) do
+    case Native.checkpoint(renderer) do
+      {:ok, token} -> {:ok, token}
+      {:error, _} = error -> error
+      other -> {:error, {:unexpected_result, other}}
+    end
+  end
+
+  @doc """
+  Restore the scripts saved by `checkpoint/1` under `token`.
+
+  The checkpoint stays available, so a wizard can step back to it repeatedly.
+  """
+  @spec rollback(term(), non_neg_integer()) :: :ok | {:error, term()}
+  def rollback(renderer, token) when is_integer(token) and token >= 0 do
+    Native.rollback(renderer, token)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Drop the checkpoint saved under `token`. Returns `{:ok, false}` for unknown tokens.
+  """
+  @spec release_checkpoint(term(), non_neg_integer()) :: {:ok, boolean()} | {:error, term()}
+  def release_checkpoint(renderer, token) when is_integer(token) and token >= 0 do
+    Native.release_checkpoint(renderer, token)
+  end
+
+  @doc """
+  Look up the size and format of an uploaded static image or stream texture.
+
+  Returns `{:ok, %{width: w, height: h, format: format, has_alpha: boolean}}`, where
+  `format` is the detected container (`"png"`, `"jpeg"`, ...) for encoded files or the
+  bitmap layout (`"g"`, `"ga"`, `"rgb"`, `"rgba"`, or `"nv12"` and `"yuv420p"` for camera
+  frames). Static images are keyed by their
+  asset hash, as uploaded by the driver. `Native.put_static_image/3` and
+  `Native.put_stream_texture/6` return the same map on success.
+  """
+  @spec get_image_info(term(), String.t()) ::
+          {:ok,
+           %{
+             width: non_neg_integer(),
+             height: non_neg_integer(),
+             format: String.t(),
+             has_alpha: boolean()
+           }}
+          | {:error, term()}
+  def get_image_info(renderer, id) when is_binary(id) do
+    Native.get_image_info(renderer, id)
+  end
+
+  @doc """
+  Upload the encoded image in the file at `path` as the static image `id`.
+
+  Like `Native.put_static_image/3`, but the renderer reads the file itself, so large images
+  are not copied through the BEAM. Texture options and dedup apply as for uploaded bytes.
+  Returns the image info map.
+  """
+  @spec put_static_image_file(term(), String.t(), Path.t()) ::
+          {:ok, map()} | {:error, term()}
+  def put_static_image_file(renderer, id, path) when is_binary(id) do
+    Native.put_static_image_file(renderer, id, to_string(path))
+  end
+
+  @doc """
+  Extract up to `count` dominant colors from an uploaded static image or stream texture.
+
+  The renderer samples the cached pixels natively, so themes can adapt to album art
+  without decoding the image in Elixir. Returns `{:ok, [%{color: {r, g, b}, share: share}]}`,
+  most common first; `share` is the fraction of opaque sampled pixels close to the color.
+  """
+  @spec extract_palette(term(), String.t(), pos_integer()) ::
+          {:ok, [%{color: {byte(), byte(), byte()}, share: float()}]} | {:error, term()}
+  def extract_palette(renderer, id, count \\ 5)
+      when is_binary(id) and is_integer(count) and count > 0 do
+    Native.extract_palette(renderer, id, count)
+  end
+
+  @doc """
+  WCAG 2 relative luminance of a color, from `0.0` for black to `1.0` for white.
+  """
+  @spec relative_luminance(Scenic.Color.t()) :: float()
+  def relative_luminance(color) do
+    Native.relative_luminance(rgba(color))
+  end
+
+  @doc """
+  WCAG 2 contrast ratio between two colors, from `1.0` to `21.0`. Alpha is ignored.
+
+  Text needs at least `4.5` against its background for WCAG AA.
+  """
+  @spec contrast_ratio(Scenic.Color.t(), Scenic.Color.t()) :: float()
+  def contrast_ratio(color_a, color_b) do
+    Native.contrast_ratio(rgba(color_a), rgba(color_b))
+  end
+
+  @doc """
+  Composite `fg` over `bg` with source-over alpha blending and return the RGBA result.
+  """
+  @spec blend_colors(Scenic.Color.t(), Scenic.Color.t()) :: {byte(), byte(), byte(), byte()}
+  def blend_colors(fg, bg) do
+    Native.blend_colors(rgba(fg), rgba(bg))
+  end
+
+  @doc """
+  Measure a path built from script path ops, such as a gauge arc.
+
+  `script` is a `Scenic.Script` op list or a serialized script; its path ops
+  (`move_to`, `line_to`, `arc`, `circle` and the rest) build the path as they would for
+  `fill_path`, `begin_path` starts over and all other ops, including transforms, are
+  ignored. Returns `{:ok, {length, samples}}` with one `{x, y, tangent_x, tangent_y}` per
+  entry of `fractions`: the point at that fraction of the total length and the unit
+  tangent there. Fractions are clamped to `0.0..1.0`; contours are measured one after the
+  other in the order they were added.
+
+      arc =
+        Script.start()
+        |> Script.begin_path()
+        |> Script.arc(0, 0, 100, 0.0, :math.pi(), 1)
+        |> Script.finish()
+
+      {:ok, {_length, ticks}} = Scenic.Driver.Skia.measure_path(arc, [0.0, 0.5, 1.0])
+  """
+  @spec measure_path(Script.t() | binary(), [number()]) ::
+          {:ok, {float(), [{float(), float(), float(), float()}]}} | {:error, term()}
+  def measure_path(script, fractions) when is_list(script) do
+    measure_path(serialize_script(script), fractions)
+  end
+
+  def measure_path(script, fractions) when is_binary(script) and is_list(fractions) do
+    Native.measure_path(script, Enum.map(fractions, &(&1 / 1)))
+  end
+
+  @doc """
+  Remove a static image from the renderer's asset store.
+
+  Returns `{:ok, true}` when the id was cached. With image dedup enabled the decoded
+  image is only freed once the last id uploaded with the same bytes is removed.
+  """
+  @spec del_static_image(term(), String.t()) :: {:ok, boolean()} | {:error, term()}
+  def del_static_image(renderer, id) when is_binary(id) do
+    Native.del_static_image(renderer, id)
+  end
+
+  @doc """
+  Upload a TrueType or OpenType font under `id`.
+
+  Scripts select it with `font(id)`; text drawn with an id that was not uploaded is
+  skipped. Uploading to an existing id replaces the font. Data Skia cannot load as a font
+  returns `{:error, reason}` and leaves any font already under `id` in place.
+  """
+  @spec put_font(term(), String.t(), binary()) :: :ok | {:error, term()}
+  def put_font(renderer, id, data) when is_binary(id) and is_binary(data) do
+    Native.put_font(renderer, id, data)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Remove a font uploaded with `put_font/3`.
+
+  Returns `{:ok, true}` when the font was loaded. Text drawn with it is skipped until it
+  is uploaded again.
+  """
+  @spec del_font(term(), String.t()) :: {:ok, boolean()} | {:error, term()}
+  def del_font(renderer, id) when is_binary(id) do
+    Native.del_font(renderer, id)
+  end
+
+  @doc """
+  Enable content-addressed dedup of static images in the renderer's asset store.
+
+  When enabled, `put_static_image` takes the SHA-256 of the uploaded bytes and identical
+  uploads under different ids share one decoded image, reference-counted across the ids.
+  """
+  @spec set_image_dedup(term(), boolean()) :: :ok | {:error, term()}
+  def set_image_dedup(renderer, enabled) when is_boolean(enabled) do
+    Native.set_image_dedup(renderer, enabled)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Set how the next uploads of texture `id` are interpreted.
+
+  Options:
+    * `:alpha` - `:premul` (default) or `:unpremul`, for raw `"ga"` and `"rgba"` bitmaps
+      whose color channels are not multiplied by alpha.
+    * `:color_space` - `:none` (default) draws pixel values as they are, `:embedded`
+      converts encoded files from their ICC profile to sRGB and `:linear_srgb` converts
+      linear sRGB pixels.
+
+  Options apply to `put_static_image`, `put_stream_texture`, `submit_frame` and
+  `load_bundle` uploads of that id; textures already uploaded are left unchanged.
+  Calling with the defaults clears the id's options.
+  """
+  @spec set_texture_options(term(), String.t(), keyword()) :: :ok | {:error, term()}
+  def set_texture_options(renderer, id, opts \\ []) when is_binary(id) and is_list(opts) do
+    alpha = Keyword.get(opts, :alpha, :premul)
+    color_space = Keyword.get(opts, :color_space, :none)
+
+    Native.set_texture_options(renderer, id, Atom.to_string(alpha), Atom.to_string(color_space))
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Set how frames of stream texture `id` are handled when they arrive faster than frames
+  are rendered.
+
+  `:latest` (default) replaces a frame that has not been drawn yet, so only the newest
+  upload is ever shown. `{:queue, n}` keeps up to `n` uploads waiting behind the frame on
+  screen and draws them one per rendered frame, dropping the oldest waiting frame when
+  the queue is full. The policy applies to `put_stream_texture` and `submit_frame` uploads
+  of that id and persists across `del_stream_texture`. `get_stats/1` reports the frames
+  received, presented and dropped per stream under `streams`.
+  """
+  @spec set_stream_policy(term(), String.t(), :latest | {:queue, pos_integer()}) ::
+          :ok | {:error, term()}
+  def set_stream_policy(renderer, id, :latest) when is_binary(id) do
+    Native.set_stream_policy(renderer, id, "latest", 0)
+    |> normalize_start_result()
+  end
+
+  def set_stream_policy(renderer, id, {:queue, depth})
+      when is_binary(id) and is_integer(depth) and depth > 0 do
+    Native.set_stream_policy(renderer, id, "queue", depth)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Create an asset store that several renderers can share.
+
+  Pass the returned store as the `asset_store:` driver option (or to
+  `set_asset_store/2`) for every driver that should share it. Static images and
+  fonts are then decoded once: uploading the same bytes to an id again is skipped, while
+  different bytes replace what the id holds.
+  """
+  @spec new_asset_store() :: term()
+  def new_asset_store, do: Native.new_asset_store()
+
+  @doc """
+  Attach a store created with `new_asset_store/0` to a running renderer.
+
+  Assets uploaded before the call stay in the previous store and must be uploaded again.
+  """
+  @spec set_asset_store(term(), term()) :: :ok | {:error, term()}
+  def set_asset_store(renderer, store) do
+    Native.set_asset_store(renderer, store)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Shrink the rendered scene to fit inside overscan margins.
+
+  `margins` is `{left, top, right, bottom}` in logical pixels (`unit: :px`) or in
+  percent of the viewport (`unit: :percent`). Pointer input is mapped back into
+  scene coordinates so hit testing keeps working. Pass `{0, 0, 0, 0}` to disable.
+  """
+  @spec set_overscan(term(), {number(), number(), number(), number()}, :px | :percent) ::
+          :ok | {:error, term()}
+  def set_overscan(renderer, {left, top, right, bottom}, unit \\ :px)
+      when unit in [:px, :percent] do
+    Native.set_overscan(
+      renderer,
+      {left / 1, top / 1, right / 1, bottom / 1},
+      Atom.to_string(unit)
+    )
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Rotate the scene clockwise by `degrees` (`0`, `90`, `180` or `270`) on DRM and raster
+  outputs.
+
+  Frames are pre-rotated: the surface keeps the panel's native orientation and the scene
+  is drawn into it through a rotation transform, so drivers without plane rotation
+  support work, and mobile GPUs (Mali, Adreno) avoid the slow path of scanning out a
+  rotated swapchain. Pointer and touch input is rotated back into scene coordinates, and
+  a quarter turn reports the viewport with width and height swapped. Wayland and X11
+  leave rotation to the compositor and return an error.
+  """
+  @spec set_output_rotation(term(), 0 | 90 | 180 | 270) :: :ok | {:error, term()}
+  def set_output_rotation(renderer, degrees) when degrees in [0, 90, 180, 270] do
+    Native.set_output_rotation(renderer, degrees)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Set a property of the primary plane of the DRM output named `output`, e.g.
+  `set_plane_property(renderer, "HDMI-A-1", "zpos", 2)` or
+  `set_plane_property(renderer, "DSI-1", "COLOR_RANGE", "YCbCr full range")`.
+
+  `value` is an integer, a boolean or, for enum properties, one of the names the kernel
+  lists for them (see `modetest -p`). It is validated against the plane's property list
+  before anything is committed: unknown and immutable properties, values out of range
+  and properties the backend manages itself (`FB_ID`, `CRTC_ID`, the `SRC_*` and
+  `CRTC_*` geometry) return an error. The value goes out with the next page flip and is
+  applied again after hotplug and VT switches; one the kernel refuses is dropped and
+  logged. Other backends return an error.
+  """
+  @spec set_plane_property(term(), String.t(), String.t(), integer() | boolean() | String.t()) ::
+          :ok | {:error, term()}
+  def set_plane_property(renderer, output, name, value)
+      when is_binary(output) and is_binary(name) and
+             (is_integer(value) or is_boolean(value) or is_binary(value)) do
+    Native.set_plane_property(renderer, output, name, to_string(value))
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Dither every gradient to hide banding on 8-bit panels.
+
+  `:paint` sets Skia's paint dither flag, `:noise` also adds a small SkSL noise term for
+  panels where the flag is not enough, and `:off` leaves gradients to their per-op flag.
+  Individual gradients can opt in by wrapping the op, e.g.
+  `{:dither, {:fill_linear, ...}}` or `{:dither, {:fill_radial, ...}, :noise}`.
+  """
+  @spec set_gradient_dither(term(), :off | :paint | :noise) :: :ok | {:error, term()}
+  def set_gradient_dither(renderer, mode) when mode in [:off, :paint, :noise] do
+    Native.set_gradient_dither(renderer, Atom.to_string(mode))
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Choose when text is rasterized on the CPU instead of through the GPU glyph atlas.
+
+  With `:auto` (the default) the renderer watches for glyph atlas allocation or upload
+  failures, which some Mali drivers hit intermittently, repaints the affected frame with
+  every text run drawn through a CPU image and keeps doing so for the next 300 frames.
+  `:always` forces the fallback and `:off` disables it. `get_stats/1` counts detected
+  failures and fallback runs under `text_fallback`.
+  """
+  @spec set_text_fallback(term(), :auto | :always | :off) :: :ok | {:error, term()}
+  def set_text_fallback(renderer, mode) when mode in [:auto, :always, :off] do
+    Native.set_text_fallback(renderer, Atom.to_string(mode))
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Render `draw_script` children with isolated state.
+
+  When enabled, each referenced script starts from a copy of the caller's fill, stroke,
+  font and path state inside a canvas save/restore, so transforms, clips and styles it
+  leaves behind (for example a missing `pop_state`) never leak into the caller.
+  """
+  @spec set_script_isolation(term(), boolean()) :: :ok | {:error, term()}
+  def set_script_isolation(renderer, enabled) when is_boolean(enabled) do
+    Native.set_script_isolation(renderer, enabled)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Move or scale a submitted script without submitting it again.
+
+  The transform applies wherever script `id` is drawn, before its own ops, and lasts
+  until it is cleared with `nil` or the script is deleted; replacing the script keeps it.
+  Scrolling a long list then only updates the transform of the list script each frame.
+
+  `transform` is either an affine matrix `{a, b, c, d, e, f}` as taken by the `transform`
+  script op, or a keyword list:
+
+    * `:translate` - `{x, y}` offset, default `{0, 0}`
+    * `:scale` - a factor or `{sx, sy}`, default `1`
+
+  The scale is applied first. Canvas changes the script leaves behind end with it while
+  it has a transform. Input is not affected: hit testing still uses the scene graph.
+  """
+  @spec set_script_transform(term(), String.t(), tuple() | keyword() | nil) ::
+          :ok | {:error, term()}
+  def set_script_transform(renderer, id, nil) when is_binary(id) do
+    Native.set_script_transform(renderer, id, nil)
+    |> normalize_start_result()
+  end
+
+  def set_script_transform(renderer, id, opts) when is_binary(id) and is_list(opts) do
+    {x, y} = Keyword.get(opts, :translate, {0, 0})
+
+    {sx, sy} =
+      case Keyword.get(opts, :scale, 1) do
+        {sx, sy} -> {sx, sy}
+        scale -> {scale, scale}
+      end
+
+    set_script_transform(renderer, id, {sx, 0, 0, sy, x, y})
+  end
+
+  def set_script_transform(renderer, id, {a, b, c, d, e, f}) when is_binary(id) do
+    Native.set_script_transform(renderer, id, {a / 1, b / 1, c / 1, d / 1, e / 1, f / 1})
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Select the cell the `draw_sprite_cell` ops of script `id` draw.
+
+  Cells are counted row by row from the top left of the sprite sheet, and indexes past
+  the last cell wrap around, so a frame counter can be passed as is. The index lasts
+  until the script is deleted; replacing the script keeps it. Animated icons then step
+  through their frames without the script being encoded and submitted again.
+  """
+  @spec set_script_param(term(), String.t(), non_neg_integer()) :: :ok | {:error, term()}
+  def set_script_param(renderer, id, index)
+      when is_binary(id) and index in 0..0xFFFFFFFF do
+    Native.set_script_param(renderer, id, index)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Honor a reduced-motion accessibility preference with a single switch.
+
+  While enabled, animations the renderer runs on its own are skipped: animated sprites
+  show their first frame and stop scheduling redraws, `blink` ops stay visible, `marquee`
+  text holds at the start of its window, and pointer paths started with
+  `play_pointer_path/3` jump straight to each waypoint, still clicking on time. Paths
+  already playing finish as they started. Scene content itself is unaffected, so apps
+  should also drop their own transitions when the user asks for reduced motion.
+  """
+  @spec set_reduced_motion(term(), boolean()) :: :ok | {:error, term()}
+  def set_reduced_motion(renderer, enabled) when is_boolean(enabled) do
+    Native.set_reduced_motion(renderer, enabled)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Choose what happens when the GL driver turns out to be a software rasterizer.
+
+  Without a working hardware driver Mesa silently serves GL through llvmpipe, which
+  renders on the CPU and is slower than Skia's own CPU rasterizer for driver workloads.
+  Either way the driver logs a warning and reports `software: true` from `get_status/1`
+  and `get_gpu_info/1`. With `:raster`, frames are then rendered by Skia on the CPU and GL
+  only copies each finished frame to the output; `:warn` (default) keeps rendering
+  through GL. The mode takes effect from the next frame and does nothing on hardware GL.
+  """
+  @spec set_software_gl(term(), :warn | :raster) :: :ok | {:error, term()}
+  def set_software_gl(renderer, mode) when mode in [:warn, :raster] do
+    Native.set_software_gl(renderer, Atom.to_string(mode))
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Blend in linear light instead of sRGB.
+
+  Skia normally blends the sRGB-encoded values it writes, which darkens anti-aliased
+  edges and translucent fills over bright backgrounds. While enabled, frames are drawn
+  into an intermediate linear-light surface (half-float where the backend supports it,
+  sRGB-encoded 8-bit otherwise) and encoded to sRGB when copied to the output. Colors
+  stay as specified; only the blending changes. The intermediate surface costs a
+  full-screen copy per frame and, with half floats, eight bytes per pixel.
+  """
+  @spec set_linear_blending(term(), boolean()) :: :ok | {:error, term()}
+  def set_linear_blending(renderer, enabled) when is_boolean(enabled) do
+    Native.set_linear_blending(renderer, enabled)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Arm the frame watchdog.
+
+  When a frame takes longer than `deadline_ms`, the slowest scripts are logged, optional
+  effects (blurs, drop shadows and noise dithering) are skipped for subsequent frames, and
+  `pid` receives `{:degraded_mode, frame_ms, deadline_ms}`. Calling it again clears
+  degraded mode; `0` disables the watchdog.
+  """
+  @spec set_frame_deadline(term(), non_neg_integer(), pid() | nil) :: :ok | {:error, term()}
+  def set_frame_deadline(renderer, deadline_ms, pid \\ self())
+      when is_integer(deadline_ms) and deadline_ms >= 0 do
+    Native.set_frame_deadline(renderer, deadline_ms, pid)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Send `{:frame, timestamp_us}` to the input target after every presented frame.
+
+  The message follows the page flip on DRM, whose time it carries, and the buffer swap or
+  Vulkan present on Wayland; the raster backend sends it for every rendered frame.
+  `timestamp_us` is in microseconds on the driver clock (`CLOCK_MONOTONIC`), so
+  animations can step with the display refresh instead of drifting timers. Frames are
+  only presented when something changed; nothing is sent while the scene is idle.
+  """
+  @spec request_animation_frames(term(), boolean()) :: :ok | {:error, term()}
+  def request_animation_frames(renderer, enabled) when is_boolean(enabled) do
+    Native.request_animation_frames(renderer, enabled)
+    |> normalize_start_result()
+  end
+
+  @typedoc "A script, static image or stream id referenced by a submitted script."
+  @type script_reference :: {:script | :image | :stream, String.t()}
+
+  @doc """
+  Configure reference checks for submitted scripts.
+
+  With `strict: true`, submits return `{:ok, unresolved}` when a stored script references a
+  `draw_script` id, image or stream that has not been provided yet. With `max_frames: n`,
+  such references are tracked and logged once if they are still missing after `n` rendered
+  frames (`0` disables tracking). Calling it again drops pending references.
+  """
+  @spec set_reference_check(term(), keyword()) :: :ok | {:error, term()}
+  def set_reference_check(renderer, opts) when is_list(opts) do
+    strict = Keyword.get(opts, :strict, false)
+    max_frames = Keyword.get(opts, :max_frames, 0)
+
+    Native.set_reference_check(renderer, strict, max_frames)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Bound how much script work a single frame may do.
+
+  `max_ops` caps the ops executed across all scripts, `max_expansions` the `draw_script`
+  ops expanded and `max_text_bytes` the bytes of text drawn. `0` (the default) leaves a
+  limit off. A frame that exceeds a limit stops drawing scripts at that op, restores the
+  canvas and is presented as far as it got; `get_stats/1` counts such frames per limit
+  under `script_limits`.
+  """
+  @spec set_script_limits(term(), keyword()) :: :ok | {:error, term()}
+  def set_script_limits(renderer, opts) when is_list(opts) do
+    max_ops = Keyword.get(opts, :max_ops, 0)
+    max_expansions = Keyword.get(opts, :max_expansions, 0)
+    max_text_bytes = Keyword.get(opts, :max_text_bytes, 0)
+
+    Native.set_script_limits(renderer, max_ops, max_expansions, max_text_bytes)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Trade render quality for frame time when the device cannot keep up.
+
+  With `target_fps` above `0` the renderer averages its frame times over windows of 30
+  frames. A window slower than the target, or any window while the hottest of
+  `thermal_zones` (sysfs `temp` files such as
+  `"/sys/class/thermal/thermal_zone0/temp"`) is above `thermal_limit_c` (default `80`),
+  lowers quality one step: anti-aliasing off, then drawing at 75% and at 50% of the
+  output size and scaling up on present. Four windows in a row under 60% of the frame
+  budget, below the thermal limit less 5°C, restore one step. `get_stats/1` reports the
+  current level under `quality`. Reconfiguring starts again from full quality; a
+  `target_fps` of `0` (the default) turns the governor off.
+  """
+  @spec set_quality_governor(term(), keyword()) :: :ok | {:error, term()}
+  def set_quality_governor(renderer, opts) when is_list(opts) do
+    target_fps = Keyword.get(opts, :target_fps, 0)
+    thermal_zones = Keyword.get(opts, :thermal_zones, [])
+    thermal_limit_c = Keyword.get(opts, :thermal_limit_c, 80)
+
+    Native.set_quality_governor(renderer, target_fps, thermal_zones, thermal_limit_c / 1)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Keep parsed scripts between restarts in the directory `dir`.
+
+  Scripts are always cached in memory by SHA-256 digest (see `cache_stats/1`); with a
+  directory every script submitted is also stored there, up to `max_bytes` (64 MiB by
+  default; the oldest stored scripts are removed first). Opening the cache parses what is
+  already stored, so when a device reboots into the same UI the scripts Scenic submits at
+  startup are matched by content and skip the parser. The driver opens the cache before
+  any script arrives when the `script_cache: [dir: ...]` option is set. A `nil` dir closes
+  the directory. Returns the number of stored scripts loaded.
+  """
+  @spec set_script_cache(term(), keyword()) :: {:ok, non_neg_integer()} | {:error, term()}
+  def set_script_cache(renderer, opts) when is_list(opts) do
+    dir = Keyword.get(opts, :dir)
+    max_bytes = Keyword.get(opts, :max_bytes, 64 * 1024 * 1024)
+
+    Native.set_script_cache(renderer, dir && to_string(dir), max_bytes)
+  end
+
+  @doc """
+  Returns the parsed script cache's counters.
+
+  Submitting a script with the same bytes as an earlier one reuses its parsed ops instead
+  of parsing it again, which is common when Scenic pushes a graph whose scripts did not
+  change. `entries` is the number of parsed scripts held, and `hits` and `misses` count
+  the submissions that reused ops or had to be parsed. The ops of the oldest scripts are
+  dropped once their scripts pass 16 MiB, or the `max_bytes` of `set_script_cache/2` if
+  larger. `get_stats/1` reports the same map under `script_cache`.
+  """
+  @spec cache_stats(term()) ::
+          {:ok,
+           %{entries: non_neg_integer(), hits: non_neg_integer(), misses: non_neg_integer()}}
+          | {:error, term()}
+  def cache_stats(renderer) do
+    Native.cache_stats(renderer)
+  end
+
+  @doc """
+  Choose how scripts with unusable geometry are handled when they are submitted.
+
+  NaN or infinite values, negative widths, heights and radii, and coordinates or sizes
+  beyond a million units are fixed by `:clamp` (the default): a NaN becomes `0` (`1` for
+  scale factors), negative sizes become `0` and other values are clamped to the nearest
+  usable one, and each change is recorded for `take_geometry_warnings/1`. `:strict`
+  rejects the script instead, so its submit returns `{:error, reason}`.
+  """
+  @spec set_geometry_mode(term(), :clamp | :strict) :: :ok | {:error, term()}
+  def set_geometry_mode(renderer, mode) when mode in [:clamp, :strict] do
+    Native.set_geometry_mode(renderer, Atom.to_string(mode))
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Choose what happens after a panic while the render state was locked.
+
+  With `:recover` (the default) the next call that needs the state clears the scene,
+  keeping assets, fonts and configuration, and `pid` receives
+  `{:render_state_recovered, count}`; the driver resubmits every script of its viewport
+  when it gets that message. With `:fail` every later call returns
+  `{:error, "render state lock poisoned"}` until the renderer is restarted, which suits
+  supervisors that would rather restart the whole driver.
+  """
+  @spec set_poisoned_state(term(), :recover | :fail, pid() | nil) :: :ok | {:error, term()}
+  def set_poisoned_state(renderer, policy, pid \\ self()) when policy in [:recover, :fail] do
+    Native.set_poisoned_state(renderer, Atom.to_string(policy), pid)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Return and clear the values clamped since the last call, oldest first.
+
+  Each warning is a map such as
+  `%{script: "_root_", op: "draw_rect", field: "width", value: -4.0, problem: "negative"}`;
+  `problem` is `"nan"`, `"infinite"`, `"negative"` or `"out_of_range"`, and `value` is `nil`
+  for NaN and infinite values. Only the 64 most recent warnings are kept.
+  """
+  @spec take_geometry_warnings(term()) :: {:ok, [map()]} | {:error, term()}
+  def take_geometry_warnings(renderer) do
+    Native.take_geometry_warnings(renderer)
+  end
+
+  @doc """
+  Configure the flight recorder.
+
+  With `frames: n`, the last `n` rendered frames are kept, scaled down to at most
+  `max_width` pixels wide, together with recent input events and driver log lines.
+  `frames: 0` disables recording. Reconfiguring drops everything recorded so far.
+  """
+  @spec set_flight_recorder(term(), keyword()) :: :ok | {:error, term()}
+  def set_flight_recorder(renderer, opts) when is_list(opts) do
+    frames = Keyword.get(opts, :frames, 0)
+    max_width = Keyword.get(opts, :max_width, 320)
+
+    Native.set_flight_recorder(renderer, frames, max_width)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Stream per-frame statistics to a Unix domain socket for remote profiling.
+
+  With `path: "/tmp/skia-stats.sock"`, the renderer listens there and writes one record
+  per rendered frame to every connected client: the frame number, a monotonic timestamp
+  and the render time in microseconds, the script ops executed, the distinct scripts
+  drawn, text run cache hits and misses, and the slowest scripts with their inclusive
+  times. `format: :json` (the default) writes one JSON object per line; `format: :binary`
+  writes length-prefixed big-endian records (see `GUIDES.md`). Records go from the render
+  thread straight to the socket; clients that fall behind are disconnected. `path: nil`
+  stops exporting and removes the socket.
+  """
+  @spec set_stats_export(term(), keyword()) :: :ok | {:error, term()}
+  def set_stats_export(renderer, opts) when is_list(opts) do
+    format = Keyword.get(opts, :format, :json)
+
+    Native.set_stats_export(renderer, Keyword.get(opts, :path), Atom.to_string(format))
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Show or hide the development overlay.
+
+  With `enabled: true`, a grid every `spacing` scene units is drawn over the scene in
+  `color`, with rulers labelled in scene coordinates along the top and left edges
+  (`rulers: false` hides them) and a crosshair with the pointer position
+  (`pointer: false` hides it). The overlay needs no scene changes and is not part of any
+  script. `enabled: false` hides it.
+  """
+  @spec set_dev_overlay(term(), keyword()) :: :ok | {:error, term()}
+  def set_dev_overlay(renderer, opts) when is_list(opts) do
+    overlay =
+      if Keyword.get(opts, :enabled, false) do
+        {:color_rgba, rgba} = Scenic.Color.to_rgba(Keyword.get(opts, :color, {255, 0, 255, 96}))
+
+        {Keyword.get(opts, :spacing, 10) / 1, rgba, Keyword.get(opts, :rulers, true),
+         Keyword.get(opts, :pointer, true)}
+      end
+
+    Native.set_dev_overlay(renderer, overlay)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Stamp text such as a device id, firmware version and time into a corner of frames.
+
+  `text` may span lines separated by `"\\n"`, and `{timestamp}` in it is replaced by the
+  current UTC time (`2026-10-16T09:30:00Z`), redrawn every second. With `target: :frames`
+  (the default) the stamp is drawn over every presented frame after the scene, overlays
+  and pointer, so screen recordings carry it; `target: :captures` stamps only frames
+  read back with `capture_frame/2` and leaves the display clean.
+
+  Options are `font` (an uploaded font id or installed family, default font otherwise),
+  `size` and `margin` in scene units, `position` (`:top_left`, `:top_right`,
+  `:bottom_left` or `:bottom_right`), `color` and `background` (`nil` for none). The
+  stamp stays upright under `set_output_rotation/2`. `text: nil` removes it. While a
+  frame stamp is shown, raster outputs repaint whole frames instead of damaged areas.
+  """
+  @spec set_watermark(term(), keyword()) :: :ok | {:error, term()}
+  def set_watermark(renderer, opts) when is_list(opts) do
+    config =
+      case Keyword.get(opts, :text) do
+        nil ->
+          nil
+
+        text when is_binary(text) ->
+          {:color_rgba, color} = Scenic.Color.to_rgba(Keyword.get(opts, :color, :white))
+
+          background =
+            case Keyword.get(opts, :background, {0, 0, 0, 160}) do
+              nil -> nil
+              background -> background |> Scenic.Color.to_rgba() |> elem(1)
+            end
+
+          %{
+            text: text,
+            font: Keyword.get(opts, :font),
+            size: Keyword.get(opts, :size, 14) / 1,
+            position: Atom.to_string(Keyword.get(opts, :position, :bottom_right)),
+            margin: Keyword.get(opts, :margin, 8) / 1,
+            color: color,
+            background: background,
+            target: Atom.to_string(Keyword.get(opts, :target, :frames))
+          }
+      end
+
+    Native.set_watermark(renderer, config)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Write the flight recorder contents to `dir`.
+
+  Frames are saved as `frame_000.png` onwards (oldest first) with their timestamps in
+  `frames.log`; recent input events go to `input.log` and driver log lines to
+  `driver.log`. Timestamps are milliseconds since the Unix epoch. Returns the number of
+  frames written.
+  """
+  @spec dump_flight_recorder(term(), Path.t()) :: {:ok, non_neg_integer()} | {:error, term()}
+  def dump_flight_recorder(renderer, dir) do
+    Native.dump_flight_recorder(renderer, to_string(dir))
+  end
+
+  @doc """
+  Register `pid` as an additional input target next to the driver.
+
+  `inputs` is `:all` or a list of input types as accepted by `request_input`. Matching
+  events are queued for `pid` independently of the driver, and `pid` receives
+  `:input_ready` when new events arrive; fetch them with `drain_input/2`. Key events keep
+  the raw `{key, action, mods, scancode, repeat}` shape. Calling it again updates the mask.
+
+  Targets may also ask for `:touch`, which Scenic itself never receives. Each contact on a
+  touchscreen arrives as `{:touch, {id, phase, {x, y}}}`, where `phase` is `:down`,
+  `:move`, `:up` or `:cancel` and `id` stays the same for the life of the contact, so
+  gestures can follow several fingers. Touchscreens keep emulating the pointer as well.
+  `:hover` delivers the enter and leave events of `set_hover_regions/2`.
+
+  Scroll events carry what Scenic's `{{dx, dy}, {x, y}}` drops, as
+  `{:cursor_scroll, {{dx, dy}, {x, y}, %{source: source, lines: lines, pixels: pixels,
+  stop: stop}}}`. `source` is `:wheel`, `:finger` or `:continuous`; `lines` and `pixels`
+  are `{x, y}` deltas or `nil` where the device did not report them. A finger scroll ends
+  with `stop: true`, usually with zero deltas, which is the cue to start kinetic
+  scrolling; Scenic only receives stops that still move.
+  """
+  @spec add_input_target(term(), :all | [atom()], pid()) :: :ok | {:error, term()}
+  def add_input_target(renderer, inputs, pid \\ self()) when is_pid(pid) do
+    Native.add_input_target(renderer, pid, input_mask_from_request(inputs))
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Unregister an input target added with `add_input_target/3`, dropping its pending events.
+  """
+  @spec remove_input_target(term(), pid()) :: :ok | {:error, term()}
+  def remove_input_target(renderer, pid \\ self()) when is_pid(pid) do
+    Native.remove_input_target(renderer, pid)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Emit the current viewport size again.
+
+  Queues `{:viewport, {:reshape, {width, height}}}` with the current logical size for the
+  driver and every input target accepting `:viewport` input, so targets attached after
+  the backend's initial reshape learn the size without a window resize. Returns
+  `{:ok, %{width: w, height: h, scale: scale}}`, or an error before the backend has an
+  output.
+  """
+  @spec request_viewport_info(term()) ::
+          {:ok, %{width: non_neg_integer(), height: non_neg_integer(), scale: float()}}
+          | {:error, term()}
+  def request_viewport_info(renderer) do
+    Native.request_viewport_info(renderer)
+  end
+
+  @doc """
+  Drain the events queued for an input target added with `add_input_target/3`.
+  """
+  @spec drain_input(term(), pid()) :: {:ok, [term()]} | {:error, term()}
+  def drain_input(renderer, pid \\ self()) when is_pid(pid) do
+    Native.drain_input_target(renderer, pid)
+  end
+
+  @doc """
+  Restrict which input types a device class may deliver.
+
+  `class` is one of `:keyboard`, `:pointer`, `:touch` or `:gamepad`; `inputs` is `:all` or a
+  list of input types (`:key`, `:cursor_button`, ...), as accepted by `request_input`. The
+  class mask is applied on top of the scene's requested input, so a touchscreen can be limited
+  to button events while a mouse still reports motion.
+  """
+  @spec set_device_class_mask(
+          term(),
+          :keyboard | :pointer | :touch | :gamepad,
+          :all | [atom()]
+        ) :: :ok | {:error, term()}
+  def set_device_class_mask(renderer, class, inputs)
+      when class in [:keyboard, :pointer, :touch, :gamepad] do
+    mask = input_mask_from_request(inputs)
+
+    Native.set_device_class_mask(renderer, Atom.to_string(class), mask)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Arm the input dead-man timer.
+
+  When no input event reaches the renderer for `timeout_ms`, `pid` receives
+  `{:input_watchdog_expired, idle_ms}` and `get_status/1` reports `input_idle: true`. The
+  timer runs natively, so a busy BEAM does not cause false alarms. The next input event
+  clears the flag and restarts the timeout; each idle period is reported once. Only input
+  types requested by the scene or an input target are seen. Arming again restarts it.
+  """
+  @spec arm_input_watchdog(term(), pos_integer(), pid()) :: :ok | {:error, term()}
+  def arm_input_watchdog(renderer, timeout_ms, pid \\ self())
+      when is_integer(timeout_ms) and timeout_ms > 0 and is_pid(pid) do
+    Native.arm_input_watchdog(renderer, timeout_ms, pid)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Disarm the input dead-man timer. Returns `{:ok, true}` when it was armed.
+  """
+  @spec disarm_input_watchdog(term()) :: {:ok, boolean()} | {:error, term()}
+  def disarm_input_watchdog(renderer) do
+    Native.disarm_input_watchdog(renderer)
+  end
+
+  @doc """
+  Deliver at most one cursor position event per `interval_ms`.
+
+  Positions arriving faster are held back and only the newest is delivered, at the end of
+  the interval or right before the next cursor button or scroll event, so clicks always
+  land at the latest position. `0` delivers every position.
+  """
+  @spec set_cursor_interval(term(), non_neg_integer()) :: :ok | {:error, term()}
+  def set_cursor_interval(renderer, interval_ms)
+      when is_integer(interval_ms) and interval_ms >= 0 do
+    Native.set_cursor_interval(renderer, interval_ms)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Returns whether the frame watchdog has put the renderer into degraded mode.
+  """
+  @spec degraded_mode?(term()) :: boolean()
+  def degraded_mode?(renderer) do
+    case Native.degraded_mode(renderer) do
+      {:ok, degraded} -> degraded
+      degraded when is_boolean(degraded) -> degraded
+      _ -> false
+    end
+  end
+
+  @type op_counts :: %{String.t() => non_neg_integer()}
+
+  @doc """
+  Returns script op usage for this renderer session.
+
+  `parsed` counts ops as scripts are submitted and `drawn` counts ops executed while
+  rendering frames, both keyed by op name (for example `"line_to"`). `text_fallback` has
+  the detected glyph upload `failures` and the text `runs` rasterized on the CPU (see
+  `set_text_fallback/2`). `script_limits` counts the frames cut short by each limit of
+  `set_script_limits/2`. `streams` maps each live stream texture id to the frames
+  `received`, `presented` (drawn at least once) and `dropped` unseen, plus the frames
+  `pending` in its queue (see `set_stream_policy/3`). `quality` has the `level` the
+  quality governor holds (`"full"`, `"no_antialias"`, `"scale_75"` or `"scale_50"`), whether
+  it draws with `antialias`, its render `scale`, how often it `downgrades` and `upgrades`,
+  and the last `temperature_c` read, if any (see `set_quality_governor/2`). `script_cache`
+  has the `entries` held and the submissions that were `hits` or `misses` (see
+  `cache_stats/1`).
+  """
+  @spec get_stats(term()) ::
+          {:ok,
+           %{
+             parsed: op_counts(),
+             drawn: op_counts(),
+             text_fallback: %{failures: non_neg_integer(), runs: non_neg_integer()},
+             script_limits: %{
+               ops: non_neg_integer(),
+               expansions: non_neg_integer(),
+               text: non_neg_integer()
+             },
+             streams: %{
+               String.t() => %{
+                 received: non_neg_integer(),
+                 presented: non_neg_integer(),
+                 dropped: non_neg_integer(),
+                 pending: non_neg_integer()
+               }
+             },
+             quality: %{
+               level: String.t(),
+               antialias: boolean(),
+               scale: float(),
+               downgrades: non_neg_integer(),
+               upgrades: non_neg_integer(),
+               temperature_c: float() | nil
+             },
+             script_cache: %{
+               entries: non_neg_integer(),
+               hits: non_neg_integer(),
+               misses: non_neg_integer()
+             }
+           }}
+          | {:error, term()}
+  def get_stats(renderer) do
+    Native.get_stats(renderer)
+  end
+
+  @doc """
+  Returns the renderer's health.
+
+  The map has `backend`, `running`, `gpu` and `software` (both `nil` until the backend has
+  created its renderer), `fallback`, `degraded` and `input_idle`. `software` is `true` when
+  GL or Vulkan is served by a CPU rasterizer such as llvmpipe (see `set_software_gl/2`).
+  On DRM, `fallback` holds the EGL/GLES setup error when the backend fell back to CPU
+  rendering into dumb buffers; `degraded` reports the frame watchdog's degraded mode and
+  `input_idle` an expired input watchdog (see `arm_input_watchdog/3`). With
+  `backend: :auto`, `backend` is the probed backend and `backend_reason` says why it was
+  chosen; otherwise `backend_reason` is `nil`.
+  """
+  @spec get_status(term()) :: {:ok, map()} | {:error, term()}
+  def get_status(renderer) do
+    Native.get_status(renderer)
+  end
+
+  @doc """
+  Returns the graphics stack of the running renderer, for support tickets.
+
+  The map has `backend`, `gpu` (`false` when frames are rendered on the CPU), `software`
+  (`true` when the GPU API runs on a CPU rasterizer such as llvmpipe), the GL `vendor`,
+  `renderer`, `version` and `shading_language_version` strings, the
+  driver-relevant `egl_extensions` and the `fallback` reason, if any. With
+  `backend: :vulkan`, `renderer` is the Vulkan device name, `version` the API and driver
+  version and the EGL fields are empty. On DRM, `card` and `card_driver` name the node
+  frames are scanned out on, and `render_node` and `render_driver` the node of another
+  GPU they are drawn on (see `drm: [render_node: ...]`), or `nil`. Returns an error until
+  the backend has created its renderer.
+  """
+  @spec get_gpu_info(term()) :: {:ok, map()} | {:error, term()}
+  def get_gpu_info(renderer) do
+    Native.get_gpu_info(renderer)
+  end
+
+  @doc """
+  Lists the DRM cards and render nodes in `/dev/dri`, for choosing a GPU.
+
+  Each map has the node `path`, its `kind` (`"card"` for KMS nodes, `"render"` for
+  render nodes), the kernel `driver`, the `bus_id` shared by the nodes of one GPU, and
+  for cards the number of `connectors` and how many are `connected` to a display. Works
+  without a running renderer.
+  """
+  @spec list_dri_devices() :: [map()]
+  def list_dri_devices do
+    Native.list_dri_devices()
+  end
+
+  @doc """
+  Returns the display connectors of the DRM card, for driving several panels at once.
+
+  With `backend: :drm` the first connected connector is the primary output, which takes
+  input and shows the cursor; every other connected connector with a free CRTC is driven
+  next to it at its preferred mode. Each map has the connector `name` (such as
+  `"HDMI-A-1"`), `connected`, the `width`, `height` and `refresh` of its mode and
+  `active`, which is `true` for the outputs being driven. The list is refreshed whenever
+  a display is plugged in or out and is empty on other backends.
+  """
+  @spec list_outputs(term()) :: {:ok, [map()]} | {:error, term()}
+  def list_outputs(renderer) do
+    Native.list_outputs(renderer)
+  end
+
+  @doc """
+  Draw the script `script_id` as the root of the output named `output` (see
+  `list_outputs/1`) instead of the main root script, so each panel of a multi-display
+  sign can show its own scene. `nil` returns the output to the main root.
+
+  The assignment is kept by name, so it applies again when the display is plugged back
+  in, and the script may be submitted before or after it is assigned.
+  """
+  @spec set_output_root(term(), String.t(), String.t() | nil) :: :ok | {:error, term()}
+  def set_output_root(renderer, output, script_id)
+      when is_binary(output) and (is_binary(script_id) or is_nil(script_id)) do
+    Native.set_output_root(renderer, output, script_id)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Returns why the last frames were rendered, oldest first, to diagnose redraw storms.
+
+  Each map has the `frame` number, `timestamp_ms` on the driver's monotonic clock, the
+  `scripts` and `textures` ids changed since the previous frame, and `cursor`, `resize`
+  and `animation` flags for software cursor moves, output resizes and sprite animation
+  ticks. `other` marks frames without a recorded reason, such as compositor exposes or
+  renderer setting changes. The last 64 frames are kept.
+  """
+  @spec get_redraw_reasons(term()) :: {:ok, [map()]} | {:error, term()}
+  def get_redraw_reasons(renderer) do
+    Native.get_redraw_reasons(renderer)
+  end
+
+  @doc """
+  Returns where the time of the last rendered frame went, to tell jank in the renderer
+  from jank in the scene process.
+
+  `parse_us` is the CPU time spent parsing the scripts submitted since the frame before,
+  `draw_us` the time drawing the scene into Skia and `flush_us` the time Skia took to flush
+  and submit the frame to the GPU (`0` for raster output); `frame_us` is the whole frame.
+  `ops` counts the script ops executed, `frame` numbers the frames rendered and `fps` is
+  the frame rate over the last 60 frames. All zeros until the first frame.
+  """
+  @spec get_render_stats(term()) ::
+          {:ok,
+           %{
+             frame: non_neg_integer(),
+             parse_us: non_neg_integer(),
+             draw_us: non_neg_integer(),
+             flush_us: non_neg_integer(),
+             frame_us: non_neg_integer(),
+             ops: non_neg_integer(),
+             fps: float()
+           }}
+          | {:error, term()}
+  def get_render_stats(renderer) do
+    Native.get_render_stats(renderer)
+  end
+
+  @doc """
+  Capture the next rendered frame as `{:ok, {width, height, rgba}}`.
+
+  Unlike `Native.get_raster_frame/1`, this works on every backend: the renderer is woken,
+  draws a frame and reads its surface back (the GL, Vulkan or DRM surface on those
+  backends), so Wayland and DRM displays can take screenshots for diagnostics. `rgba` holds
+  unpremultiplied 8-bit RGBA pixels in rows of `width * 4` bytes at the output's physical
+  size. Returns `{:error, reason}` when no frame is rendered within `timeout_ms`, for
+  example while the display is blanked.
+  """
+  @spec capture_frame(term(), non_neg_integer()) ::
+          {:ok, {pos_integer(), pos_integer(), binary()}} | {:error, term()}
+  def capture_frame(renderer, timeout_ms \\ 1_000)
+      when is_integer(timeout_ms) and timeout_ms >= 0 do
+    Native.capture_frame(renderer, timeout_ms)
+  end
+
+  @doc """
+  Set the font used by text that is drawn without a `font` style.
+
+  `font` is the id of a font uploaded with `Native.put_font/3` or the family name of an
+  installed font (for example `"Inter"`); `nil` restores the built-in sans font. `size` is
+  the default font size for text without a `font_size` style.
+  """
+  @spec set_default_font(term(), String.t() | nil, number()) :: :ok | {:error, term()}
+  def set_default_font(renderer, font, size \\ 20)
+      when (is_binary(font) or is_nil(font)) and is_number(size) and size > 0 do
+    Native.set_default_font(renderer, font, size / 1)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Set the fonts used for characters the drawing font has no glyph for.
+
+  Each entry of `fonts` is the id of a font uploaded with `Native.put_font/3` or an
+  installed family name, tried in order. After them the system font manager is asked for
+  any installed font covering the character (Noto Color Emoji for emoji on most Linux
+  images) unless `system: false` is given. Text is split into runs at grapheme
+  boundaries, so emoji sequences draw from a single font. Until this is called only the
+  system lookup is used.
+  """
+  @spec set_fallback_fonts(term(), [String.t()], keyword()) :: :ok | {:error, term()}
+  def set_fallback_fonts(renderer, fonts, opts \\ []) when is_list(fonts) and is_list(opts) do
+    Native.set_fallback_fonts(renderer, fonts, Keyword.get(opts, :system, true))
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Report the characters of `text` that would draw as missing glyphs ("tofu").
+
+  Returns `{:ok, [{byte_offset, codepoint}]}` for each character neither the font nor a
+  fallback font (see `set_fallback_fonts/3`) has a glyph for, so UI code can substitute
+  an image or alternate text before drawing. The font is the default font unless
+  `font: id` names one uploaded with `Native.put_font/3`. Control characters and
+  invisible ones such as zero-width joiners and variation selectors are never reported.
+  """
+  @spec missing_glyphs(term(), String.t(), keyword()) ::
+          {:ok, [{non_neg_integer(), non_neg_integer()}]} | {:error, term()}
+  def missing_glyphs(renderer, text, opts \\ []) when is_binary(text) and is_list(opts) do
+    Native.missing_glyphs(renderer, text, Keyword.get(opts, :font))
+  end
+
+  @doc """
+  Measure `text` as `draw_text` draws it, for layout code that centers or stacks labels.
+
+  Returns `{:ok, %{width: w, ascent: a, descent: d, line_height: h}}` in pixels. `ascent`
+  and `descent` are distances above and below the baseline and `line_height` adds the
+  font's leading. The font is the default font unless `font: id` names one uploaded with
+  `Native.put_font/3`; `font_size:` defaults to 20. Characters drawn from fallback fonts
+  are measured in those fonts.
+  """
+  @spec measure_text(term(), String.t(), keyword()) :: {:ok, map()} | {:error, term()}
+  def measure_text(renderer, text, opts \\ []) when is_binary(text) and is_list(opts) do
+    size = Keyword.get(opts, :font_size, 20)
+    Native.measure_text(renderer, Keyword.get(opts, :font), size / 1, text)
+  end
+
+  @doc """
+  Decode JPEG images with a V4L2 memory-to-memory decoder when one is available.
+
+  `true` uses the first JPEG-capable decoder among `/dev/video10` to `/dev/video12` (the
+  Raspberry Pi codec nodes); a path selects the device; `false` turns hardware decoding off.
+  The setting applies to every renderer in the VM and covers JPEG data passed to
+  `put_static_image` and `put_stream_texture` with the `:file` format. Images the decoder
+  rejects fall back to software decoding. Returns the device in use, or `nil`.
+  """
+  @spec set_hardware_decode(boolean() | String.t()) :: {:ok, String.t() | nil} | {:error, term()}
+  def set_hardware_decode(device) when is_binary(device) do
+    Native.set_hardware_decode(true, device)
+  end
+
+  def set_hardware_decode(enabled) when is_boolean(enabled) do
+    Native.set_hardware_decode(enabled, nil)
+  end
+
+  @doc """
+  Configure the input coordinate pipeline for rotated or scaled outputs.
+
+  Pointer, touch and pen positions from every backend pass through one chain: the
+  calibration matrix, output rotation, letterbox offset, scale, then the overscan margins
+  from `set_overscan/3`. Options:
+
+    * `:rotation` - output rotation in degrees clockwise (`0`, `90`, `180`, `270`)
+    * `:scale` - `{sx, sy}` from scene to output pixels, default `{1.0, 1.0}`
+    * `:offset` - `{x, y}` letterbox offset of the centered scene, default `{0.0, 0.0}`
+    * `:calibration` - `[a, b, c, d, e, f]` affine matrix on coordinates normalized to the
+      output size (libinput style), default identity
+
+  Options that are left out are reset to their defaults.
+  """
+  @spec set_input_transform(term(), keyword()) :: :ok | {:error, term()}
+  def set_input_transform(renderer, opts) when is_list(opts) do
+    {sx, sy} = Keyword.get(opts, :scale, {1.0, 1.0})
+    {ox, oy} = Keyword.get(opts, :offset, {0.0, 0.0})
+    calibration = Keyword.get(opts, :calibration, [1.0, 0.0, 0.0, 0.0, 1.0, 0.0])
+
+    Native.set_input_transform(
+      renderer,
+      Keyword.get(opts, :rotation, 0),
+      {sx / 1, sy / 1},
+      {ox / 1, oy / 1},
+      Enum.map(calibration, &(&1 / 1))
+    )
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Return the current input coordinate pipeline, including the overscan margins and the
+  output size of the last mapped input event.
+  """
+  @spec get_input_transform(term()) :: {:ok, map()} | {:error, term()}
+  def get_input_transform(renderer) do
+    Native.get_input_transform(renderer)
+  end
+
+  @doc """
+  Map a device point through the input pipeline, as the backends do for input events.
+
+  Useful to check calibration or rotation settings without touching the panel.
+  """
+  @spec map_input_point(term(), number(), number()) ::
+          {:ok, {float(), float()}} | {:error, term()}
+  def map_input_point(renderer, x, y) when is_number(x) and is_number(y) do
+    Native.map_input_point(renderer, x / 1, y / 1)
+  end
+
+  @doc """
+  Configure relative pointer motion for the DRM backend.
+
+  `profile` is `:flat` (constant gain) or `:adaptive` (faster motion moves further).
+  `sensitivity` multiplies device units before they are applied to the cursor.
+  Other backends accept the call and ignore it.
+  """
+  @spec set_pointer_accel(term(), :flat | :adaptive, number()) :: :ok | {:error, term()}
+  def set_pointer_accel(renderer, profile, sensitivity)
+      when profile in [:flat, :adaptive] and is_number(sensitivity) do
+    Native.set_pointer_accel(renderer, Atom.to_string(profile), sensitivity / 1)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Start recording delivered input events to a binary log at `path`.
+
+  Events are stored with millisecond timestamps and can be fed back through
+  the input queue with `replay_input/2`. Recording continues until
+  `stop_record/1` is called.
+  """
+  @spec record_input(term(), Path.t()) :: :ok | {:error, term()}
+  def record_input(renderer, path) when is_binary(path) do
+    Native.record_input(renderer, path)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Stop an active input recording and flush the log.
+  """
+  @spec stop_record(term()) :: :ok | {:error, term()}
+  def stop_record(renderer) do
+    Native.stop_record(renderer)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Replay an input log recorded with `record_input/2`.
+
+  Events are pushed into the normal input queue with their original timing and
+  filtered by the current input mask. Replay runs in the background and stops
+  when the renderer stops.
+  """
+  @spec replay_input(term(), Path.t()) :: :ok | {:error, term()}
+  def replay_input(renderer, path) when is_binary(path) do
+    Native.replay_input(renderer, path)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Move a rendered pointer along `path` for self-running demos and walkthrough captures.
+
+  `path` is a list of `{x, y, duration_ms}` or `{x, y, duration_ms, :click}` waypoints in
+  scene coordinates. The pointer moves to each waypoint in turn over its duration, eased
+  with `easing:` (`:linear`, `:ease_in`, `:ease_out` or the default `:ease_in_out`), and
+  `:click` waypoints press and release the left button once reached. The pointer is drawn
+  over the scene on every backend, and the matching `cursor_pos` and `cursor_button`
+  events go through the input mask and queue like real input.
+
+  The path plays in the background, starting where the previous one left the pointer, and
+  replaces any path still playing. The pointer stays on screen until `hide_pointer/1`.
+  """
+  @spec play_pointer_path(term(), [tuple()], keyword()) :: :ok | {:error, term()}
+  def play_pointer_path(renderer, path, opts \\ []) when is_list(path) and is_list(opts) do
+    waypoints =
+      Enum.map(path, fn
+        {x, y, duration_ms} -> {x / 1, y / 1, duration_ms, false}
+        {x, y, duration_ms, :click} -> {x / 1, y / 1, duration_ms, true}
+      end)
+
+    easing = opts |> Keyword.get(:easing, :ease_in_out) |> Atom.to_string()
+
+    Native.play_pointer_path(renderer, waypoints, easing)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Stop a path started with `play_pointer_path/3` and remove the rendered pointer.
+  """
+  @spec hide_pointer(term()) :: :ok | {:error, term()}
+  def hide_pointer(renderer) do
+    Native.hide_pointer(renderer)
+    |> normalize_start_result()
+  end
+
+  @doc """
+  Update the text rendered by the driver.
+
+  Accepts a renderer handle returned by `start/0` or `start/1`.
+  """
+  @spec set_text(term(), String.t()) :: :ok | {:error, term()}
+  def set_text(renderer, text) when is_binary(text) do
+    Native.set_text(renderer, text)
+  end
+
+  @doc false
+  @spec input_mask(:all | [atom()]) :: non_neg_integer()
+  def input_mask(inputs), do: input_mask_from_request(inputs)
+
+  @doc false
+  @spec renderer_handle(GenServer.server()) :: term()
+  def renderer_handle(driver_pid) do
+    GenServer.call(driver_pid, :renderer_handle)
+  end
+
+  defp normalize_backend(backend) do
+    backend
+    |> to_string()
+    |> String.downcase()
+  end
+
+  defp input_mask_from_request(:all), do: @input_mask_all
+
+  defp input_mask_from_request(request) when is_list(request) do
+    Enum.reduce(request, 0, fn
+      :key, mask -> mask ||| @input_mask_key
+      :codepoint, mask -> mask ||| @input_mask_codepoint
+      :cursor_pos, mask -> mask ||| @input_mask_cursor_pos
+      :cursor_button, mask -> mask ||| @input_mask_cursor_button
+      :cursor_scroll, mask -> mask ||| @input_mask_cursor_scroll
+      :viewport, mask -> mask ||| @input_mask_viewport
+      :touch, mask -> mask ||| @input_mask_touch
+      :hover, mask -> mask ||| @input_mask_hover
+      _, mask -> mask
+    end)
+  end
+
+  defp input_mask_from_request(_), do: 0
+
+  # Key events arrive with the scan code and repeat count appended; Scenic only accepts
+  # `{key, action, mods}`, so the extras go to the optional `key_details` process.
+  defp scenic_input({:key, {key, action, mods, scancode, repeat}}, target) do
+    if target, do: send(target, {:key_details, key, action, scancode, repeat})
+    {:key, {key, action, mods}}
+  end
+
+  defp scenic_input({:cursor_scroll, {delta, pos, _details}}, _target),
+    do: {:cursor_scroll, {delta, pos}}
+
+  defp scenic_input(event, _target), do: event
+
+  defp target_only_input?({:touch, _}), do: true
+  defp target_only_input?({:hover, _}), do: true
+
+  defp target_only_input?({:cursor_scroll, {{dx, dy}, _pos, %{stop: true}}}),
+    do: dx == 0 and dy == 0
+
+  defp target_only_input?(_event), do: false
+
+  defp normalize_start_result(:ok), do: :ok
+  defp normalize_start_result({:ok, _}), do: :ok
+  defp normalize_start_result({:error, _} = error), do: error
+  defp normalize_start_result(other), do: {:error, {:unexpected_result, other}}
+
+  defp normalize_submit_result({:ok, [_ | _]} = unresolved), do: unresolved
+  defp normalize_submit_result(result), do: normalize_start_result(result)
+
+  defp read_bundle_source(<<0x1F, 0x8B, _::binary>> = archive), do: {:ok, archive}
+
+  defp read_bundle_source(<<_::binary-size(257), "ustar", _::binary>> = archive),
+    do: {:ok, archive}
+
+  defp read_bundle_source(path) do
+    case File.read(path) do
+      {:ok, archive} -> {:ok, archive}
+      {:error, reason} -> {:error, {:bundle_read_failed, path, reason}}
+    end
+  end
+
+  defp extract_bundle(<<0x1F, 0x8B, _::binary>> = archive) do
+    archive |> :zlib.gunzip() |> extract_bundle()
+  rescue
+    ErlangError -> {:error, {:invalid_bundle, :gzip}}
+  end
+
+  defp extract_bundle(archive) do
+    case :erl_tar.extract({:binary, archive}, [:memory]) do
+      {:ok, files} -> {:ok, Map.new(files, fn {name, data} -> {bundle_path(name), data} end)}
+      {:error, reason} -> {:error, {:invalid_bundle, reason}}
+    end
+  end
+
+  defp bundle_entries(%{"manifest" => manifest} = files) do
+    manifest
+    |> String.split("\n")
+    |> Enum.map(&String.trim/1)
+    |> Enum.reject(&(&1 == "" or String.starts_with?(&1, "#")))
+    |> Enum.reduce_while({:ok, []}, fn line, {:ok, entries} ->
+      with [kind, id, path] when kind in ["font", "image", "script"] <- String.split(line),
+           {:ok, data} <- Map.fetch(files, bundle_path(path)) do
+        {:cont, {:ok, [{String.to_atom(kind), id, data} | entries]}}
+      else
+        :error -> {:halt, {:error, {:bundle_file_missing, line}}}
+        _ -> {:halt, {:error, {:invalid_manifest_line, line}}}
+      end
+    end)
+    |> case do
+      {:ok, entries} -> {:ok, Enum.reverse(entries)}
+      error -> error
+    end
+  end
+
+  defp bundle_entries(_files), do: {:error, :bundle_manifest_missing}
+
+  defp bundle_path(name), do: name |> to_string() |> String.trim_leading("./")
+
+  defp drm_card(drm_opts) do
+    card =
+      case {Keyword.get(drm_opts, :card), Keyword.get(drm_opts, :driver)} do
+        {nil, driver} when is_binary(driver) -> Native.find_drm_card(driver)
+        {card, _driver} -> {:ok, card}
+      end
+
+    case {card, Keyword.get(drm_opts, :render_node)} do
+      {{:ok, card}, render_node} when not is_nil(render_node) ->
+        {:ok, {card, to_string(render_node)}}
+
+      {card, _render_node} ->
+        card
+    end
+  end
+
+  defp maybe_set_input_target(renderer, pid) do
+    case Native.set_input_target(renderer, pid) do
+      :ok -> :ok
+      {:ok, _} -> :ok
+      {:error, reason} -> Logger.warning("set_input_target failed: #{inspect(reason)}")
+      other -> Logger.warning("set_input_target returned #{inspect(other)}")
+    end
+  end
+
+  defp maybe_set_asset_store(_renderer, nil), do: :ok
+
+  defp maybe_set_asset_store(renderer, store) do
+    case set_asset_store(renderer, store) do
+      :ok -> :ok
+      {:error, reason} -> Logger.warning("set_asset_store failed: #{inspect(reason)}")
+    end
+  end
+
+  defp maybe_set_image_dedup(_renderer, false), do: :ok
+
+  defp maybe_set_image_dedup(renderer, true) do
+    case set_image_dedup(renderer, true) do
+      :ok -> :ok
+      {:error, reason} -> Logger.warning("set_image_dedup failed: #{inspect(reason)}")
+    end
+  end
+
+  defp maybe_set_overscan(renderer, overscan_opts) do
+    margins =
+      {Keyword.get(overscan_opts, :left, 0), Keyword.get(overscan_opts, :top, 0),
+       Keyword.get(overscan_opts, :right, 0), Keyword.get(overscan_opts, :bottom, 0)}
+
+    if margins != {0, 0, 0, 0} do
+      case set_overscan(renderer, margins, Keyword.get(overscan_opts, :unit, :px)) do
+        :ok -> :ok
+        {:error, reason} -> Logger.warning("set_overscan failed: #{inspect(reason)}")
+      end
+    end
+
+    :ok
+  end
+
+  defp drain_driver_input(driver) do
+    case driver.assigns.opts[:input_encoding] do
+      :binary ->
+        with {:ok, batch} <- Native.drain_input_binary(driver.assigns.renderer) do
+          InputBinary.decode(batch)
+        end
+
+      _ ->
+        Native.drain_input_events(driver.assigns.renderer)
+    end
+  end
+
+  defp maybe_set_cursor_interval(_renderer, 0), do: :ok
+
+  defp maybe_set_cursor_interval(renderer, interval_ms) do
+    case set_cursor_interval(renderer, interval_ms) do
+      :ok -> :ok
+      {:error, reason} -> Logger.warning("set_cursor_interval failed: #{inspect(reason)}")
+    end
+  end
+
+  defp maybe_set_frame_deadline(_renderer, 0), do: :ok
+
+  defp maybe_set_frame_deadline(renderer, deadline_ms) do
+    case set_frame_deadline(renderer, deadline_ms, self()) do
+      :ok -> :ok
+      {:error, reason} -> Logger.warning("set_frame_deadline failed: #{inspect(reason)}")
+    end
+  end
+
+  defp maybe_set_theme(driver, []), do: driver
+
+  defp maybe_set_theme(%{assigns: %{renderer: renderer}} = driver, theme) do
+    if color = theme[:clear_color] do
+      {:color_rgba, rgba} = Scenic.Color.to_rgba(color)
+
+      case Native.set_clear_color(renderer, rgba) |> normalize_start_result() do
+        :ok -> :ok
+        {:error, reason} -> Logger.warning("set_clear_color failed: #{inspect(reason)}")
+      end
+    end
+
+    {driver, font} = theme_font(driver, theme[:font])
+
+    case set_default_font(renderer, font, Keyword.get(theme, :font_size, 20)) do
+      :ok -> :ok
+      {:error, reason} -> Logger.warning("set_default_font failed: #{inspect(reason)}")
+    end
+
+    maybe_set_fallback_fonts(driver, theme[:fallback_fonts])
+  end
+
+  defp maybe_set_fallback_fonts(driver, nil), do: driver
+
+  defp maybe_set_fallback_fonts(%{assigns: %{renderer: renderer}} = driver, fonts) do
+    {fonts, driver} =
+      Enum.map_reduce(fonts, driver, fn font, driver ->
+        {driver, font} = theme_font(driver, font)
+        {font, driver}
+      end)
+
+    case set_fallback_fonts(renderer, fonts) do
+      :ok -> :ok
+      {:error, reason} -> Logger.warning("set_fallback_fonts failed: #{inspect(reason)}")
+    end
+
+    driver
+  end
+
+  # Scenic static font assets are uploaded like script fonts; anything else is treated as
+  # an installed family name.
+  defp theme_font(driver, nil), do: {driver, nil}
+
+  defp theme_font(driver, font) do
+    with {:ok, {Static.Font, _}} <- Static.meta(font),
+         {:ok, hash} <- Static.to_hash(font) do
+      {ensure_fonts(driver, [font]), hash}
+    else
+      _ -> {driver, to_string(font)}
+    end
+  end
+
+  defp maybe_set_flight_recorder(renderer, opts) do
+    if Keyword.get(opts, :frames, 0) > 0 do
+      case set_flight_recorder(renderer, opts) do
+        :ok -> :ok
+        {:error, reason} -> Logger.warning("set_flight_recorder failed: #{inspect(reason)}")
+      end
+    end
+
+    :ok
+  end
+
+  defp maybe_set_stats_export(renderer, opts) do
+    if Keyword.get(opts, :path) do
+      case set_stats_export(renderer, opts) do
+        :ok -> :ok
+        {:error, reason} -> Logger.warning("set_stats_export failed: #{inspect(reason)}")
+      end
+    end
+
+    :ok
+  end
+
+  defp maybe_set_dev_overlay(renderer, opts) do
+    if Keyword.get(opts, :enabled, false) do
+      case set_dev_overlay(renderer, opts) do
+        :ok -> :ok
+        {:error, reason} -> Logger.warning("set_dev_overlay failed: #{inspect(reason)}")
+      end
+    end
+
+    :ok
+  end
+
+  defp maybe_set_watermark(renderer, opts) do
+    if Keyword.get(opts, :text) do
+      case set_watermark(renderer, opts) do
+        :ok -> :ok
+        {:error, reason} -> Logger.warning("set_watermark failed: #{inspect(reason)}")
+      end
+    end
+
+    :ok
+  end
+
+  defp maybe_set_hardware_decode(false), do: :ok
+
+  defp maybe_set_hardware_decode(setting) do
+    case set_hardware_decode(setting) do
+      {:ok, nil} -> Logger.info("No hardware JPEG decoder found, using software decode")
+      {:ok, _device} -> :ok
+      {:error, reason} -> Logger.warning("set_hardware_decode failed: #{inspect(reason)}")
+    end
+
+    :ok
+  end
+
+  defp maybe_dump_flight_recorder(%{assigns: %{renderer: renderer, opts: opts}}, reason) do
+    recorder = Keyword.get(opts, :flight_recorder, [])
+
+    with dir when is_binary(dir) <- recorder[:dump_dir],
+         true <- Keyword.get(recorder, :frames, 0) > 0 do
+      dir = Path.join(dir, "#{reason}-#{System.os_time(:millisecond)}")
+
+      case dump_flight_recorder(renderer, dir) do
+        {:ok, frames} -> Logger.warning("Flight recorder dumped #{frames} frames to #{dir}")
+        {:error, error} -> Logger.warning("Flight recorder dump failed: #{inspect(error)}")
+      end
+    end
+
+    :ok
+  end
+
+  defp maybe_dump_flight_recorder(_driver, _reason), do: :ok
+
+  defp maybe_set_reference_check(renderer, opts) do
+    if Keyword.get(opts, :strict, false) or Keyword.get(opts, :max_frames, 0) > 0 do
+      case set_reference_check(renderer, opts) do
+        :ok -> :ok
+        {:error, reason} -> Logger.warning("set_reference_check failed: #{inspect(reason)}")
+      end
+    end
+
+    :ok
+  end
+
+  defp maybe_set_script_limits(renderer, opts) do
+    if Enum.any?(opts, fn {_key, limit} -> limit > 0 end) do
+      case set_script_limits(renderer, opts) do
+        :ok -> :ok
+        {:error, reason} -> Logger.warning("set_script_limits failed: #{inspect(reason)}")
+      end
+    end
+
+    :ok
+  end
+
+  defp maybe_set_script_cache(renderer, opts) do
+    if Keyword.get(opts, :dir) do
+      case set_script_cache(renderer, opts) do
+        {:ok, _loaded} -> :ok
+        {:error, reason} -> Logger.warning("set_script_cache failed: #{inspect(reason)}")
+      end
+    end
+
+    :ok
+  end
+
+  defp maybe_set_quality_governor(renderer, opts) do
+    if Keyword.get(opts, :target_fps, 0) > 0 do
+      case set_quality_governor(renderer, opts) do
+        :ok -> :ok
+        {:error, reason} -> Logger.warning("set_quality_governor failed: #{inspect(reason)}")
+      end
+    end
+
+    :ok
+  end
+
+  defp maybe_set_gradient_dither(_renderer, :off), do: :ok
+
+  defp maybe_set_gradient_dither(renderer, mode) do
+    case set_gradient_dither(renderer, mode) do
+      :ok -> :ok
+      {:error, reason} -> Logger.warning("set_gradient_dither failed: #{inspect(reason)}")
+    end
+  end
+
+  defp maybe_set_text_fallback(_renderer, :auto), do: :ok
+
+  defp maybe_set_text_fallback(renderer, mode) do
+    case set_text_fallback(renderer, mode) do
+      :ok -> :ok
+      {:error, reason} -> Logger.warning("set_text_fallback failed: #{inspect(reason)}")
+    end
+  end
+
+  defp maybe_set_geometry_mode(_renderer, :clamp), do: :ok
+
+  defp maybe_set_geometry_mode(renderer, mode) do
+    case set_geometry_mode(renderer, mode) do
+      :ok -> :ok
+      {:error, reason} -> Logger.warning("set_geometry_mode failed: #{inspect(reason)}")
+    end
+  end
+
+  defp maybe_set_poisoned_state(renderer, policy) do
+    case set_poisoned_state(renderer, policy) do
+      :ok -> :ok
+      {:error, reason} -> Logger.warning("set_poisoned_state failed: #{inspect(reason)}")
+    end
+  end
+
+  defp maybe_set_reduced_motion(_renderer, false), do: :ok
+
+  defp maybe_set_reduced_motion(renderer, true) do
+    case set_reduced_motion(renderer, true) do
+      :ok -> :ok
+      {:error, reason} -> Logger.warning("set_reduced_motion failed: #{inspect(reason)}")
+    end
+  end
+
+  defp maybe_set_software_gl(_renderer, :warn), do: :ok
+
+  defp maybe_set_software_gl(renderer, mode) do
+    case set_software_gl(renderer, mode) do
+      :ok -> :ok
+      {:error, reason} -> Logger.warning("set_software_gl failed: #{inspect(reason)}")
+    end
+  end
+
+  defp maybe_set_linear_blending(_renderer, false), do: :ok
+
+  defp maybe_set_linear_blending(renderer, true) do
+    case set_linear_blending(renderer, true) do
+      :ok -> :ok
+      {:error, reason} -> Logger.warning("set_linear_blending failed: #{inspect(reason)}")
+    end
+  end
+
+  defp maybe_set_script_isolation(_renderer, false), do: :ok
+
+  defp maybe_set_script_isolation(renderer, true) do
+    case set_script_isolation(renderer, true) do
+      :ok -> :ok
+      {:error, reason} -> Logger.warning("set_script_isolation failed: #{inspect(reason)}")
+    end
+  end
+
+  defp maybe_set_pointer_accel(renderer, drm_opts) do
+    profile = Keyword.get(drm_opts, :pointer_profile, :flat)
+    sensitivity = Keyword.get(drm_opts, :pointer_sensitivity, 1.0)
+
+    case set_pointer_accel(renderer, profile, sensitivity) do
+      :ok -> :ok
+      {:error, reason} -> Logger.warning("set_pointer_accel failed: #{inspect(reason)}")
+    end
+  end
+
+  defp maybe_set_output_rotation(_renderer, 0), do: :ok
+
+  defp maybe_set_output_rotation(renderer, degrees) do
+    case set_output_rotation(renderer, degrees) do
+      :ok -> :ok
+      {:error, reason} -> Logger.warning("set_output_rotation failed: #{inspect(reason)}")
     end
   end
 
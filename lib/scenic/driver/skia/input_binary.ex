@@ -0,0 +1,127 @@
+defmodule Scenic.Driver.Skia.InputBinary do
+  @moduledoc """
+  Decoder for the packed input batches returned by `drain_input_binary`.
+
+  The driver drains its input queue this way with `input_encoding: :binary`, so a burst
+  of touch or pen events crosses the NIF as one binary instead of a term per event. The
+  decoded events have the same shapes `drain_input_events` returns.
+
+  A batch is a sequence of events, each a tag byte followed by a big-endian payload.
+  Floats are 32-bit, strings are a `u16` byte length followed by UTF-8, `mods` is a bit
+  set (`0x01` shift, `0x02` ctrl, `0x04` alt, `0x08` meta) and `action` is `0` or `1`:
+
+  | Tag | Event | Payload |
+  | --- | --- | --- |
+  | 0 | `:key` | `action` u8, `mods` u8, scancode u32, repeat u32, key string |
+  | 1 | `:codepoint` | codepoint u32, `mods` u8 |
+  | 2 | `:cursor_pos` | x, y |
+  | 3 | `:cursor_button` | `action` u8, `mods` u8, x, y, button string |
+  | 4 | `:cursor_scroll` | source u8, flags u8, dx, dy, x, y, optional lines, pixels |
+  | 5 | `:viewport` enter/exit | entered u8, x, y |
+  | 6 | `:viewport` reshape | width u32, height u32 |
+  | 7 | `:touch` | id u32, phase u8, x, y |
+  | 8 | `:hover` | entered u8, region string |
+
+  Scroll sources are `0` wheel, `1` finger and `2` continuous. The scroll flags are `0x01`
+  when an `{x, y}` line delta follows, `0x02` when a pixel delta follows (after the line
+  delta) and `0x04` for a scroll stop. Touch phases are `0` down, `1` move, `2` up and
+  `3` cancel.
+  """
+
+  @scroll_lines 0x01
+  @scroll_pixels 0x02
+  @scroll_stop 0x04
+
+  @doc """
+  Decode a batch into input events, oldest first.
+  """
+  @spec decode(binary()) :: {:ok, [tuple()]} | {:error, term()}
+  def decode(batch) when is_binary(batch), do: decode(batch, [])
+
+  defp decode(<<>>, events), do: {:ok, Enum.reverse(events)}
+
+  defp decode(batch, events) do
+    case decode_event(batch) do
+      {:ok, event, rest} -> decode(rest, [event | events])
+      :error -> {:error, {:truncated_input_batch, byte_size(batch)}}
+    end
+  end
+
+  defp decode_event(
+         <<0, action, mods, scancode::32, repeat::32, len::16, key::binary-size(len),
+           rest::binary>>
+       ) do
+    {:ok, {:key, {String.to_atom(key), action, mods(mods), scancode, repeat}}, rest}
+  end
+
+  defp decode_event(<<1, codepoint::32, mods, rest::binary>>) do
+    {:ok, {:codepoint, {<<codepoint::utf8>>, mods(mods)}}, rest}
+  end
+
+  defp decode_event(<<2, x::float-32, y::float-32, rest::binary>>) do
+    {:ok, {:cursor_pos, {x, y}}, rest}
+  end
+
+  defp decode_event(
+         <<3, action, mods, x::float-32, y::float-32, len::16, button::binary-size(len),
+           rest::binary>>
+       ) do
+    {:ok, {:cursor_button, {String.to_atom(button), action, mods(mods), {x, y}}}, rest}
+  end
+
+  defp decode_event(
+         <<4, source, flags, dx::float-32, dy::float-32, x::float-32, y::float-32,
+           rest::binary>>
+       ) do
+    with {:ok, lines, rest} <- scroll_delta(rest, flags, @scroll_lines),
+         {:ok, pixels, rest} <- scroll_delta(rest, flags, @scroll_pixels) do
+      details = %{
+        source: scroll_source(source),
+        lines: lines,
+        pixels: pixels,
+        stop: Bitwise.band(flags, @scroll_stop) != 0
+      }
+
+      {:ok, {:cursor_scroll, {{dx, dy}, {x, y}, details}}, rest}
+    end
+  end
+
+  defp decode_event(<<5, entered, x::float-32, y::float-32, rest::binary>>) do
+    direction = if entered == 0, do: :exit, else: :enter
+    {:ok, {:viewport, {direction, {x, y}}}, rest}
+  end
+
+  defp decode_event(<<6, width::32, height::32, rest::binary>>) do
+    {:ok, {:viewport, {:reshape, {width, height}}}, rest}
+  end
+
+  defp decode_event(<<7, id::32, phase, x::float-32, y::float-32, rest::binary>>)
+       when phase <= 3 do
+    {:ok, {:touch, {id, Enum.at([:down, :move, :up, :cancel], phase), {x, y}}}, rest}
+  end
+
+  defp decode_event(<<8, entered, len::16, region::binary-size(len), rest::binary>>) do
+    change = if entered == 0, do: :leave, else: :enter
+    {:ok, {:hover, {change, region}}, rest}
+  end
+
+  defp decode_event(_batch), do: :error
+
+  defp scroll_delta(rest, flags, flag) do
+    case {Bitwise.band(flags, flag), rest} do
+      {0, rest} -> {:ok, nil, rest}
+      {_, <<x::float-32, y::float-32, rest::binary>>} -> {:ok, {x, y}, rest}
+      _ -> :error
+    end
+  end
+
+  defp scroll_source(1), do: :finger
+  defp scroll_source(2), do: :continuous
+  defp scroll_source(_), do: :wheel
+
+  defp mods(mods) do
+    for {bit, mod} <- [{0x01, :shift}, {0x02, :ctrl}, {0x04, :alt}, {0x08, :meta}],
+        Bitwise.band(mods, bit) != 0,
+        do: mod
+  end
+end
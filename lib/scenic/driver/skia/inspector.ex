@@ -0,0 +1,95 @@
+defmodule Scenic.Driver.Skia.Inspector do
+  @moduledoc """
+  Read-only attachment to a running renderer, for debugging a production display.
+
+  An inspector is a separate NIF handle next to the driver's renderer handle. It can read
+  stats, dump the stored scene, capture frames and subscribe to input, but it cannot be
+  passed to any function that submits scripts or changes settings, so an IEx session
+  (for example `iex --remsh`) can look at a live display without disturbing it:
+
+      {:ok, inspector} = Scenic.Driver.Skia.Inspector.attach(driver_pid)
+      Scenic.Driver.Skia.Inspector.dump_scene(inspector)
+      {:ok, {w, h, rgba}} = Scenic.Driver.Skia.Inspector.capture_frame(inspector)
+
+  The inspector stays usable until the renderer stops; calls then return
+  `{:error, reason}`.
+  """
+
+  alias Scenic.Driver.Skia.Native
+
+  @type t :: reference()
+
+  @doc """
+  Attach an inspector to a driver process (`pid` or registered name) or a renderer handle.
+  """
+  @spec attach(GenServer.server() | term()) :: {:ok, t()} | {:error, term()}
+  def attach(driver) when is_pid(driver) or is_atom(driver) do
+    driver
+    |> Scenic.Driver.Skia.renderer_handle()
+    |> attach()
+  end
+
+  def attach(renderer) do
+    case Native.attach_inspector(renderer) do
+      {:ok, inspector} -> {:ok, inspector}
+      {:error, _} = error -> error
+      other -> {:error, {:unexpected_result, other}}
+    end
+  end
+
+  @doc "Same as `Scenic.Driver.Skia.get_stats/1`."
+  @spec get_stats(t()) :: {:ok, map()} | {:error, term()}
+  def get_stats(inspector), do: Native.inspector_get_stats(inspector)
+
+  @doc "Same as `Scenic.Driver.Skia.get_render_stats/1`."
+  @spec get_render_stats(t()) :: {:ok, map()} | {:error, term()}
+  def get_render_stats(inspector), do: Native.inspector_get_render_stats(inspector)
+
+  @doc """
+  Dump the scripts the renderer holds.
+
+  Returns `{:ok, %{root_id: id | nil, output_roots: %{output => id}, scripts: scripts,
+  scheduled: n}}`, where `scripts` maps each script id to `%{ops: n, draw_scripts: ids}`
+  and `scheduled` counts scripts waiting for their `submit_script_at` time.
+  """
+  @spec dump_scene(t()) :: {:ok, map()} | {:error, term()}
+  def dump_scene(inspector), do: Native.inspector_dump_scene(inspector)
+
+  @doc "Same as `Scenic.Driver.Skia.capture_frame/2`."
+  @spec capture_frame(t(), non_neg_integer()) ::
+          {:ok, {pos_integer(), pos_integer(), binary()}} | {:error, term()}
+  def capture_frame(inspector, timeout_ms \\ 1_000)
+      when is_integer(timeout_ms) and timeout_ms >= 0 do
+    Native.inspector_capture_frame(inspector, timeout_ms)
+  end
+
+  @doc """
+  Subscribe `pid` to the input types in `inputs`, like `Scenic.Driver.Skia.add_input_target/3`.
+
+  `pid` receives `:input_ready` and drains with `drain_input/2`. The driver keeps receiving
+  the input its scene requested.
+  """
+  @spec subscribe_input(t(), :all | [atom()], pid()) :: :ok | {:error, term()}
+  def subscribe_input(inspector, inputs, pid \\ self()) when is_pid(pid) do
+    Native.inspector_subscribe_input(inspector, pid, Scenic.Driver.Skia.input_mask(inputs))
+    |> normalize_result()
+  end
+
+  @doc "Unsubscribe `pid`, dropping its pending events."
+  @spec unsubscribe_input(t(), pid()) :: :ok | {:error, term()}
+  def unsubscribe_input(inspector, pid \\ self()) when is_pid(pid) do
+    Native.inspector_unsubscribe_input(inspector, pid)
+    |> normalize_result()
+  end
+
+  @doc "Take the input events queued for `pid`, oldest first."
+  @spec drain_input(t(), pid()) :: {:ok, [term()]} | {:error, term()}
+  def drain_input(inspector, pid \\ self()) when is_pid(pid) do
+    Native.inspector_drain_input(inspector, pid)
+  end
+
+  defp normalize_result(:ok), do: :ok
+  defp normalize_result({:ok, _}), do: :ok
+  defp normalize_result({:error, _} = error), do: error
+  defp normalize_result(other), do: {:error, {:unexpected_result, other}}
+end
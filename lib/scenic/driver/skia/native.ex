@@ -23,6 +23,12 @@ defmodule Scenic.Driver.Skia.Native do
       ),
       do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  def find_drm_card(_driver), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def list_dri_devices, do: :erlang.nif_error(:nif_not_loaded)
+
   @doc false
   def stop(_renderer), do: :erlang.nif_error(:nif_not_loaded)
 
@@ -33,51 +39,371 @@ defmodule Scenic.Driver.Skia.Native do
   def submit_script(_renderer, _script), do: :erlang.nif_error(:nif_not_loaded)
 
   @doc false
-  def submit_script_with_id(_renderer, _id, _script), do: :erlang.nif_error(:nif_not_loaded)
+  def submit_script_with_id(_renderer, _id, _script, _bounds \\ nil),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def submit_script_file(_renderer, _id, _path), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def submit_script_at(_renderer, _id, _script, _monotonic_ms),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def submit_frame(_renderer, _textures, _scripts, _monotonic_ms),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def load_bundle(_renderer, _fonts, _images, _scripts), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def submit_transaction(_renderer, _transaction), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def draw_immediate(_renderer, _script), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def monotonic_ms, do: :erlang.nif_error(:nif_not_loaded)
 
   @doc false
   def submit_scripts(_renderer, _scripts), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  def submit_scripts(_renderer, _namespace, _scripts), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def del_namespace(_renderer, _namespace), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def checkpoint(_renderer), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def rollback(_renderer, _token), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def release_checkpoint(_renderer, _token), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc false
   def del_script(_renderer, _id), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  def new_asset_store, do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_asset_store(_renderer, _store), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc false
   def put_static_image(_renderer, _id, _data), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  def put_static_image_file(_renderer, _id, _path), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def extract_palette(_renderer, _id, _count), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def relative_luminance(_color), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def contrast_ratio(_color_a, _color_b), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def blend_colors(_fg, _bg), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def measure_path(_script, _fractions), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc false
   def put_font(_renderer, _id, _data), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  def del_font(_renderer, _id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def missing_glyphs(_renderer, _text, _font), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def measure_text(_renderer, _font, _size, _text), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc false
   def put_stream_texture(_renderer, _id, _format, _width, _height, _data),
     do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  def get_image_info(_renderer, _id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def del_static_image(_renderer, _id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_image_dedup(_renderer, _enabled), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_texture_options(_renderer, _id, _alpha, _color_space),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_stream_policy(_renderer, _id, _policy, _depth), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc false
   def del_stream_texture(_renderer, _id), do: :erlang.nif_error(:nif_not_loaded)
 
   @doc false
   def set_clear_color(_renderer, _color), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  def set_overscan(_renderer, _margins, _unit), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_default_font(_renderer, _font, _size), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_fallback_fonts(_renderer, _fonts, _system), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_gradient_dither(_renderer, _mode), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_text_fallback(_renderer, _mode), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_script_limits(_renderer, _max_ops, _max_expansions, _max_text_bytes),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_script_cache(_renderer, _dir, _max_bytes), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def cache_stats(_renderer), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_quality_governor(_renderer, _target_fps, _thermal_zones, _thermal_limit_c),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_geometry_mode(_renderer, _mode), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_poisoned_state(_renderer, _policy, _pid), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def take_geometry_warnings(_renderer), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_software_gl(_renderer, _mode), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_linear_blending(_renderer, _enabled), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_reduced_motion(_renderer, _enabled), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_clock(_renderer, _mode, _start_ms), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def advance_time(_renderer, _ms), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_script_isolation(_renderer, _enabled), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_script_transform(_renderer, _id, _matrix), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_script_param(_renderer, _id, _index), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc false
   def reset_scene(_renderer), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  def set_dev_overlay(_renderer, _overlay), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_watermark(_renderer, _config), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def clear_display(_renderer, _color, _hold), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def release_display(_renderer), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc false
   def get_raster_frame(_renderer), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  def capture_frame(_renderer, _timeout_ms), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc false
   def script_count(_renderer), do: :erlang.nif_error(:nif_not_loaded)
 
   @doc false
   def set_input_mask(_renderer, _mask), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  def add_input_target(_renderer, _pid, _mask), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def remove_input_target(_renderer, _pid), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def drain_input_target(_renderer, _pid), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def request_viewport_info(_renderer), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def arm_input_watchdog(_renderer, _timeout_ms, _pid), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def disarm_input_watchdog(_renderer), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_cursor_interval(_renderer, _interval_ms), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_device_class_mask(_renderer, _class, _mask),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_frame_deadline(_renderer, _deadline_ms, _pid), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def request_animation_frames(_renderer, _enabled), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_reference_check(_renderer, _strict, _max_frames),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_flight_recorder(_renderer, _frames, _max_width), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_stats_export(_renderer, _path, _format), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def dump_flight_recorder(_renderer, _dir), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_hardware_decode(_enabled, _device), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def degraded_mode(_renderer), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def get_stats(_renderer), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def get_gpu_info(_renderer), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def list_outputs(_renderer), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_output_root(_renderer, _output, _script_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def get_redraw_reasons(_renderer), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def get_render_stats(_renderer), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def get_status(_renderer), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc false
   def drain_input_events(_renderer), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  def drain_input_binary(_renderer), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_pointer_accel(_renderer, _profile, _sensitivity),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_input_transform(_renderer, _rotation, _scale, _offset, _calibration),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def get_input_transform(_renderer), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def map_input_point(_renderer, _x, _y), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc false
   def set_input_target(_renderer, _pid), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  def record_input(_renderer, _path), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def stop_record(_renderer), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def replay_input(_renderer, _path), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def play_pointer_path(_renderer, _path, _easing), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def hide_pointer(_renderer), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc false
   def show_cursor(_renderer), do: :erlang.nif_error(:nif_not_loaded)
 
   @doc false
   def hide_cursor(_renderer), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_cursor_image(_renderer, _image), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_cursor_regions(_renderer, _regions), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_hover_regions(_renderer, _regions), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_focus_regions(_renderer, _regions), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def focus_next(_renderer, _direction), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_focus(_renderer, _id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_focus_ring(_renderer, _ring), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_output_rotation(_renderer, _degrees), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_plane_property(_renderer, _output, _name, _value),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def attach_inspector(_renderer), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def inspector_get_stats(_inspector), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def inspector_get_render_stats(_inspector), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def inspector_dump_scene(_inspector), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def inspector_capture_frame(_inspector, _timeout_ms), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def inspector_subscribe_input(_inspector, _pid, _mask), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def inspector_unsubscribe_input(_inspector, _pid), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def inspector_drain_input(_inspector, _pid), do: :erlang.nif_error(:nif_not_loaded)
 end
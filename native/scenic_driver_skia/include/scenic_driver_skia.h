@@ -0,0 +1,37 @@
+/*
+ * C ABI for the scenic_driver_skia renderer, available when the crate is built
+ * with `--features c-api`. Scripts use the Scenic script binary format.
+ */
+#ifndef SCENIC_DRIVER_SKIA_H
+#define SCENIC_DRIVER_SKIA_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct ScenicSkiaRenderer ScenicSkiaRenderer;
+
+/* backend: "wayland", "vulkan", "drm", "raster" or "raster_gpu" (NULL selects wayland).
+ * A width or height of 0 lets the backend choose the size. Returns NULL on failure. */
+ScenicSkiaRenderer *scenic_skia_start(const char *backend, uint32_t width, uint32_t height,
+                                      const char *title);
+
+/* Stores a serialized script under id; "_root_" is the script drawn first.
+ * Returns 0 on success, -1 on failure. */
+int scenic_skia_submit_script(ScenicSkiaRenderer *renderer, const char *id,
+                              const uint8_t *data, size_t len);
+
+/* Stops the renderer and frees it. Returns 0 on success, -1 on failure. */
+int scenic_skia_stop(ScenicSkiaRenderer *renderer);
+
+/* Message of the last failure on the calling thread, or NULL. */
+const char *scenic_skia_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SCENIC_DRIVER_SKIA_H */
@@ -6,67 +6,113 @@ use std::{
         atomic::{AtomicBool, AtomicU32, Ordering},
         mpsc::Sender,
     },
+    time::Instant,
 };
 
 use glutin::{
     config::{ConfigTemplateBuilder, GlConfig},
     context::{ContextApi, ContextAttributesBuilder, NotCurrentGlContext, PossiblyCurrentContext},
-    display::{GetGlDisplay, GlDisplay},
+    display::{GetDisplayExtensions, GetGlDisplay, GlDisplay},
     prelude::GlSurface,
-    surface::{Surface as GlutinSurface, SurfaceAttributesBuilder, WindowSurface},
+    surface::{
+        Rect as DamageRect, Surface as GlutinSurface, SurfaceAttributesBuilder, WindowSurface,
+    },
 };
 use glutin_winit::DisplayBuilder;
 use raw_window_handle::HasWindowHandle;
+use rustler::LocalPid;
 use skia_safe::gpu::gl::FramebufferInfo;
 use winit::{
     application::ApplicationHandler,
     dpi::{LogicalPosition, LogicalSize},
     event::{ElementState, MouseScrollDelta, WindowEvent},
-    event_loop::{EventLoop, EventLoopProxy},
+    event_loop::{ControlFlow, EventLoop, EventLoopProxy},
     keyboard::{Key, KeyLocation, ModifiersState, NamedKey},
-    platform::wayland::EventLoopBuilderExtWayland,
-    window::{Window, WindowAttributes},
+    platform::{scancode::PhysicalKeyExtScancode, wayland::EventLoopBuilderExtWayland},
+    window::{CursorIcon, CustomCursor, Window, WindowAttributes},
 };
 
+use crate::cursor::{CursorImageSlot, CursorRegions, CursorShape, CursorState};
+use crate::dev_overlay::PointerTracker;
+use crate::flight_recorder::log_line;
+use crate::gpu_info::GpuInfo;
 use crate::input::{
-    ACTION_PRESS, ACTION_RELEASE, INPUT_MASK_CODEPOINT, INPUT_MASK_CURSOR_BUTTON,
-    INPUT_MASK_CURSOR_POS, INPUT_MASK_CURSOR_SCROLL, INPUT_MASK_KEY, INPUT_MASK_VIEWPORT,
-    InputEvent, InputQueue, notify_input_ready,
+    ACTION_PRESS, ACTION_RELEASE, DeviceClass, DeviceClassMasks, INPUT_MASK_CODEPOINT,
+    INPUT_MASK_CURSOR_BUTTON, INPUT_MASK_CURSOR_POS, INPUT_MASK_CURSOR_SCROLL, INPUT_MASK_KEY,
+    INPUT_MASK_TOUCH, INPUT_MASK_VIEWPORT, InputEvent, InputQueue, ScrollSource, TouchPhase,
+    ViewportInfo, flush_held_cursor, notify_frame_presented, notify_input_ready,
 };
+use crate::input_transform::InputTransform;
 use crate::input_translate::{
     Key as ScenicKey, KeyLocation as ScenicKeyLocation, Modifiers as ScenicModifiers,
     MouseButton as ScenicMouseButton, NamedKey as ScenicNamedKey, button_to_scenic, key_to_scenic,
     modifiers_to_mask,
 };
-use crate::renderer::{RenderState, Renderer};
+use crate::poll_scheduled_scripts;
+use crate::renderer::Renderer;
+use crate::state_lock::RenderStateLock;
+use crate::vulkan_backend::VulkanPresenter;
 
 #[derive(Debug)]
 pub enum UserEvent {
     Stop,
     Redraw,
+    /// Cursor visibility or the custom cursor image changed.
+    Cursor,
+}
+
+/// Graphics API the window renders through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphicsApi {
+    Gl,
+    Vulkan,
+}
+
+enum Presenter {
+    Gl {
+        gl_surface: GlutinSurface<WindowSurface>,
+        gl_context: PossiblyCurrentContext,
+    },
+    Vulkan(VulkanPresenter),
 }
 
+/// Declared before the window so the presenter is torn down while the window is alive.
 struct Env {
-    gl_surface: GlutinSurface<WindowSurface>,
-    gl_context: PossiblyCurrentContext,
+    presenter: Presenter,
     window: Window,
 }
 
 struct App {
     env: Option<Env>,
     renderer: Option<Renderer>,
+    graphics: GraphicsApi,
     running: bool,
     running_flag: Arc<AtomicBool>,
-    render_state: Arc<Mutex<RenderState>>,
+    render_state: Arc<RenderStateLock>,
     input_mask: Arc<AtomicU32>,
+    device_masks: Arc<DeviceClassMasks>,
     input_events: Arc<Mutex<InputQueue>>,
     cursor_pos: (f32, f32),
     window_size: (u32, u32),
     scale_factor: f64,
     modifiers: ModifiersState,
-    /// Tracks if we've sent an input notification this event loop iteration.
-    /// Reset in about_to_wait to allow one notification per iteration.
-    notified_this_iteration: bool,
+    /// Auto-repeats seen since the last key press.
+    key_repeat: u32,
+    /// Maps pointer input into scene space; shared with the NIF layer.
+    input_transform: Arc<Mutex<InputTransform>>,
+    /// Hit regions picking the window cursor; shared with the render state.
+    cursor_regions: Arc<CursorRegions>,
+    cursor_shape: CursorShape,
+    /// Visibility set through the cursor NIFs; position and shape mirror the DRM backend.
+    cursor_state: Arc<Mutex<CursorState>>,
+    cursor_image: Arc<CursorImageSlot>,
+    /// Window cursor built from the custom image with that image's serial.
+    custom_cursor: Option<(u64, CustomCursor)>,
+    /// Scene-space pointer position for the dev overlay.
+    pointer: Arc<PointerTracker>,
+    /// Input targets notified during this event loop iteration.
+    /// Cleared in about_to_wait to allow one notification per target per iteration.
+    notified_this_iteration: Vec<LocalPid>,
 }
 
 impl App {
@@ -81,11 +127,21 @@ impl App {
         }
 
         let (w, h): (u32, u32) = physical_size.into();
+        let (logical_w, logical_h) = self.logical_size(physical_size);
+        if let Ok(mut queue) = self.input_events.lock() {
+            queue.set_viewport(ViewportInfo {
+                width: logical_w,
+                height: logical_h,
+                scale: self.scale_factor,
+            });
+        }
         if (w, h) != self.window_size {
             self.window_size = (w, h);
+            if let Ok(render_state) = self.render_state.lock() {
+                render_state.redraws.add_resize();
+            }
             let mask = self.input_mask.load(Ordering::Relaxed);
             if mask & INPUT_MASK_VIEWPORT != 0 {
-                let (logical_w, logical_h) = self.logical_size(physical_size);
                 self.push_input(InputEvent::ViewportReshape {
                     width: logical_w,
                     height: logical_h,
@@ -93,11 +149,17 @@ impl App {
             }
         }
         if let (Some(env), Some(renderer)) = (self.env.as_mut(), self.renderer.as_mut()) {
-            env.gl_surface.resize(
-                &env.gl_context,
-                NonZeroU32::new(w.max(1)).unwrap(),
-                NonZeroU32::new(h.max(1)).unwrap(),
-            );
+            match &mut env.presenter {
+                Presenter::Gl {
+                    gl_surface,
+                    gl_context,
+                } => gl_surface.resize(
+                    gl_context,
+                    NonZeroU32::new(w.max(1)).unwrap(),
+                    NonZeroU32::new(h.max(1)).unwrap(),
+                ),
+                Presenter::Vulkan(vulkan) => vulkan.resize((w.max(1), h.max(1))),
+            }
 
             renderer.resize((w.max(1), h.max(1)));
             env.window.request_redraw();
@@ -108,12 +170,26 @@ impl App {
         if let (Some(env), Some(renderer)) = (self.env.as_mut(), self.renderer.as_mut()) {
             // Use try_lock to avoid blocking the event loop if NIFs are updating render state.
             // This prevents "Application Not Responding" when scene updates are being processed.
-            if let Ok(render_state) = self.render_state.try_lock() {
+            if let Some(render_state) = self.render_state.try_lock() {
                 renderer.set_scale_factor(self.scale_factor as f32);
-                renderer.redraw(&render_state);
-                env.gl_surface
-                    .swap_buffers(&env.gl_context)
-                    .expect("swap_buffers failed");
+                match &mut env.presenter {
+                    Presenter::Gl {
+                        gl_surface,
+                        gl_context,
+                    } => {
+                        renderer.redraw(&render_state);
+                        swap_with_damage(gl_surface, gl_context, renderer);
+                        notify_frame_presented(&self.input_events, crate::monotonic_now_us());
+                    }
+                    Presenter::Vulkan(vulkan) => match vulkan.render(renderer, &render_state) {
+                        Ok(true) => {
+                            notify_frame_presented(&self.input_events, crate::monotonic_now_us())
+                        }
+                        // The swapchain went out of date mid-frame; draw again once rebuilt.
+                        Ok(false) => env.window.request_redraw(),
+                        Err(err) => log_line!("Vulkan frame failed: {err}"),
+                    },
+                }
             } else {
                 // Lock not available - request another redraw to try again soon
                 env.window.request_redraw();
@@ -124,8 +200,9 @@ impl App {
     fn set_running(&mut self, event_loop: &winit::event_loop::ActiveEventLoop, running: bool) {
         if running && !self.running {
             if self.env.is_none() || self.renderer.is_none() {
-                match create_env_renderer_with_active_event_loop(event_loop) {
+                match create_env_renderer_with_active_event_loop(event_loop, self.graphics) {
                     Ok((env, renderer)) => {
+                        publish_gpu_info(&self.render_state, &env);
                         let size = env.window.inner_size();
                         self.env = Some(env);
                         self.renderer = Some(renderer);
@@ -133,9 +210,17 @@ impl App {
                         if let Some(env) = self.env.as_ref() {
                             self.scale_factor = env.window.scale_factor();
                         }
+                        let (width, height) = self.logical_size(size);
+                        if let Ok(mut queue) = self.input_events.lock() {
+                            queue.set_viewport(ViewportInfo {
+                                width,
+                                height,
+                                scale: self.scale_factor,
+                            });
+                        }
                     }
                     Err(err) => {
-                        eprintln!("Failed to initialize renderer: {err}");
+                        log_line!("Failed to initialize renderer: {err}");
                         self.running_flag.store(false, Ordering::Relaxed);
                         return;
                     }
@@ -156,19 +241,90 @@ impl App {
         }
     }
 
-    fn push_input(&mut self, event: InputEvent) {
+    /// Input mask for events from `class`, combining the global and per-class masks.
+    fn class_mask(&self, class: DeviceClass) -> u32 {
+        self.device_masks
+            .filter(class, self.input_mask.load(Ordering::Relaxed))
+    }
+
+    fn push_input(&mut self, mut event: InputEvent) {
+        let (width, height) = self.logical_size(self.window_size.into());
+        if let Ok(mut transform) = self.input_transform.lock() {
+            transform.apply((width as f32, height as f32), &mut event);
+        }
         let notify = if let Ok(mut queue) = self.input_events.lock() {
             queue.push_event(event)
         } else {
-            None
+            Vec::new()
         };
 
-        // Only notify once per event loop iteration to avoid flooding the BEAM
-        if !self.notified_this_iteration
-            && let Some(pid) = notify
-        {
-            notify_input_ready(pid);
-            self.notified_this_iteration = true;
+        // Only notify each target once per event loop iteration to avoid flooding the BEAM
+        for pid in notify {
+            if !self.notified_this_iteration.contains(&pid) {
+                notify_input_ready(pid);
+                self.notified_this_iteration.push(pid);
+            }
+        }
+    }
+
+    /// Shows the cursor of the hit region under the pointer, in scene coordinates.
+    fn update_cursor_shape(&mut self) {
+        let (width, height) = self.logical_size(self.window_size.into());
+        let (x, y) = self.cursor_pos;
+        let (x, y) = self.input_transform.lock().map_or((x, y), |transform| {
+            transform.map((width as f32, height as f32), x, y)
+        });
+        self.pointer.update(x, y);
+        let shape = self.cursor_regions.shape_at(x, y);
+        if let Ok(mut cursor) = self.cursor_state.lock() {
+            cursor.shape = shape;
+            cursor.image = match (shape, &self.custom_cursor) {
+                (CursorShape::Default, Some((serial, _))) => Some(*serial),
+                _ => None,
+            };
+        }
+        if shape != self.cursor_shape && self.env.is_some() {
+            self.set_window_cursor(shape);
+            self.cursor_shape = shape;
+        }
+    }
+
+    /// Shows `shape`, or the custom cursor image in place of the default shape.
+    fn set_window_cursor(&self, shape: CursorShape) {
+        let Some(env) = self.env.as_ref() else {
+            return;
+        };
+        match (shape, &self.custom_cursor) {
+            (CursorShape::Default, Some((_, custom))) => env.window.set_cursor(custom.clone()),
+            _ => env.window.set_cursor(cursor_icon(shape)),
+        }
+    }
+
+    /// Applies the visibility and custom image set through the cursor NIFs.
+    fn apply_cursor(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        let visible = self
+            .cursor_state
+            .lock()
+            .map_or(true, |cursor| cursor.visible);
+        let image = self.cursor_image.current();
+        let serial = image.as_ref().map(|image| image.serial);
+        if serial != self.custom_cursor.as_ref().map(|(serial, _)| *serial) {
+            self.custom_cursor = image.and_then(|image| {
+                let source = CustomCursor::from_rgba(
+                    image.rgba.clone(),
+                    image.width as u16,
+                    image.height as u16,
+                    image.hotspot.0 as u16,
+                    image.hotspot.1 as u16,
+                )
+                .map_err(|err| log_line!("Invalid cursor image: {err}"))
+                .ok()?;
+                Some((image.serial, event_loop.create_custom_cursor(source)))
+            });
+            self.set_window_cursor(self.cursor_shape);
+        }
+        if let Some(env) = self.env.as_ref() {
+            env.window.set_cursor_visible(visible);
         }
     }
 }
@@ -178,6 +334,8 @@ pub struct WaylandWindowConfig {
     pub requested_size: Option<(u32, u32)>,
     pub window_title: String,
     pub window_resizeable: bool,
+    pub cursor_state: Arc<Mutex<CursorState>>,
+    pub graphics: GraphicsApi,
 }
 
 fn create_env_renderer_with_event_loop(
@@ -192,6 +350,13 @@ fn create_env_renderer_with_event_loop(
     } else {
         window_attributes.with_inner_size(LogicalSize::new(800, 600))
     };
+    if config.graphics == GraphicsApi::Vulkan {
+        #[allow(deprecated)]
+        let window = event_loop
+            .create_window(window_attributes)
+            .map_err(|err| format!("could not create window: {err}"))?;
+        return create_vulkan_env_renderer(window);
+    }
 
     let template = ConfigTemplateBuilder::new()
         .with_alpha_size(8)
@@ -295,8 +460,10 @@ fn create_env_renderer_with_event_loop(
     );
 
     let env = Env {
-        gl_surface,
-        gl_context,
+        presenter: Presenter::Gl {
+            gl_surface,
+            gl_context,
+        },
         window,
     };
 
@@ -305,10 +472,17 @@ fn create_env_renderer_with_event_loop(
 
 fn create_env_renderer_with_active_event_loop(
     event_loop: &winit::event_loop::ActiveEventLoop,
+    graphics: GraphicsApi,
 ) -> Result<(Env, Renderer), String> {
     let window_attributes = WindowAttributes::default()
         .with_title("skia-wayland-hello")
         .with_inner_size(LogicalSize::new(800, 600));
+    if graphics == GraphicsApi::Vulkan {
+        let window = event_loop
+            .create_window(window_attributes)
+            .map_err(|err| format!("could not create window: {err}"))?;
+        return create_vulkan_env_renderer(window);
+    }
 
     let template = ConfigTemplateBuilder::new()
         .with_alpha_size(8)
@@ -412,14 +586,25 @@ fn create_env_renderer_with_active_event_loop(
     );
 
     let env = Env {
-        gl_surface,
-        gl_context,
+        presenter: Presenter::Gl {
+            gl_surface,
+            gl_context,
+        },
         window,
     };
 
     Ok((env, renderer))
 }
 
+fn create_vulkan_env_renderer(window: Window) -> Result<(Env, Renderer), String> {
+    let (vulkan, renderer) = VulkanPresenter::new(&window)?;
+    let env = Env {
+        presenter: Presenter::Vulkan(vulkan),
+        window,
+    };
+    Ok((env, renderer))
+}
+
 impl ApplicationHandler<UserEvent> for App {
     fn resumed(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {}
 
@@ -434,7 +619,12 @@ impl ApplicationHandler<UserEvent> for App {
                 self.modifiers = modifiers.state();
             }
             WindowEvent::KeyboardInput { event, .. } => {
-                let mask = self.input_mask.load(Ordering::Relaxed);
+                let mask = self.class_mask(DeviceClass::Keyboard);
+                self.key_repeat = if event.repeat {
+                    self.key_repeat.saturating_add(1)
+                } else {
+                    0
+                };
                 if mask & INPUT_MASK_KEY != 0 {
                     let action = match event.state {
                         ElementState::Pressed => ACTION_PRESS,
@@ -445,7 +635,13 @@ impl ApplicationHandler<UserEvent> for App {
                         map_key_location(event.location),
                     );
                     let mods = modifiers_to_mask(map_modifiers(self.modifiers));
-                    self.push_input(InputEvent::Key { key, action, mods });
+                    self.push_input(InputEvent::Key {
+                        key,
+                        action,
+                        mods,
+                        scancode: event.physical_key.to_scancode().unwrap_or(0),
+                        repeat: self.key_repeat,
+                    });
                 }
 
                 if mask & INPUT_MASK_CODEPOINT != 0
@@ -463,7 +659,7 @@ impl ApplicationHandler<UserEvent> for App {
             }
 
             WindowEvent::Ime(ime) => {
-                let mask = self.input_mask.load(Ordering::Relaxed);
+                let mask = self.class_mask(DeviceClass::Keyboard);
                 if mask & INPUT_MASK_CODEPOINT != 0
                     && let winit::event::Ime::Commit(text) = ime
                 {
@@ -478,18 +674,21 @@ impl ApplicationHandler<UserEvent> for App {
             }
 
             WindowEvent::CursorMoved { position, .. } => {
-                let mask = self.input_mask.load(Ordering::Relaxed);
+                let mask = self.class_mask(DeviceClass::Pointer);
                 let logical: LogicalPosition<f64> = position.to_logical(self.scale_factor);
                 let x = logical.x as f32;
                 let y = logical.y as f32;
                 self.cursor_pos = (x, y);
+                if let Ok(mut cursor) = self.cursor_state.lock() {
+                    cursor.pos = (x, y);
+                }
                 if mask & INPUT_MASK_CURSOR_POS != 0 {
                     self.push_input(InputEvent::CursorPos { x, y });
                 }
             }
 
             WindowEvent::CursorEntered { .. } => {
-                let mask = self.input_mask.load(Ordering::Relaxed);
+                let mask = self.class_mask(DeviceClass::Pointer);
                 if mask & INPUT_MASK_VIEWPORT != 0 {
                     let (x, y) = self.cursor_pos;
                     self.push_input(InputEvent::Viewport {
@@ -501,7 +700,7 @@ impl ApplicationHandler<UserEvent> for App {
             }
 
             WindowEvent::CursorLeft { .. } => {
-                let mask = self.input_mask.load(Ordering::Relaxed);
+                let mask = self.class_mask(DeviceClass::Pointer);
                 if mask & INPUT_MASK_VIEWPORT != 0 {
                     let (x, y) = self.cursor_pos;
                     self.push_input(InputEvent::Viewport {
@@ -513,7 +712,7 @@ impl ApplicationHandler<UserEvent> for App {
             }
 
             WindowEvent::MouseInput { state, button, .. } => {
-                let mask = self.input_mask.load(Ordering::Relaxed);
+                let mask = self.class_mask(DeviceClass::Pointer);
                 if mask & INPUT_MASK_CURSOR_BUTTON != 0 {
                     let action = match state {
                         ElementState::Pressed => ACTION_PRESS,
@@ -532,18 +731,54 @@ impl ApplicationHandler<UserEvent> for App {
                 }
             }
 
-            WindowEvent::MouseWheel { delta, .. } => {
-                let mask = self.input_mask.load(Ordering::Relaxed);
+            WindowEvent::MouseWheel { delta, phase, .. } => {
+                let mask = self.class_mask(DeviceClass::Pointer);
                 if mask & INPUT_MASK_CURSOR_SCROLL != 0 {
-                    let (dx, dy) = match delta {
-                        MouseScrollDelta::LineDelta(x, y) => (x, y),
+                    // winit hides the Wayland axis source: wheels scroll in lines, while
+                    // touchpads and other continuous axes scroll in pixels and end with
+                    // `axis_stop`, which winit reports as the `Ended` phase.
+                    let (source, lines, pixels) = match delta {
+                        MouseScrollDelta::LineDelta(x, y) => {
+                            (ScrollSource::Wheel, Some((x, y)), None)
+                        }
                         MouseScrollDelta::PixelDelta(pos) => {
                             let logical: LogicalPosition<f64> = pos.to_logical(self.scale_factor);
-                            (logical.x as f32, logical.y as f32)
+                            let pixels = (logical.x as f32, logical.y as f32);
+                            (ScrollSource::Finger, None, Some(pixels))
                         }
                     };
+                    let (dx, dy) = lines.or(pixels).unwrap_or_default();
                     let (x, y) = self.cursor_pos;
-                    self.push_input(InputEvent::CursorScroll { dx, dy, x, y });
+                    self.push_input(InputEvent::CursorScroll {
+                        dx,
+                        dy,
+                        x,
+                        y,
+                        source,
+                        lines,
+                        pixels,
+                        stop: phase == winit::event::TouchPhase::Ended,
+                    });
+                }
+            }
+
+            WindowEvent::Touch(touch) => {
+                let mask = self.class_mask(DeviceClass::Touch);
+                if mask & INPUT_MASK_TOUCH != 0 {
+                    let logical: LogicalPosition<f64> =
+                        touch.location.to_logical(self.scale_factor);
+                    let phase = match touch.phase {
+                        winit::event::TouchPhase::Started => TouchPhase::Down,
+                        winit::event::TouchPhase::Moved => TouchPhase::Move,
+                        winit::event::TouchPhase::Ended => TouchPhase::Up,
+                        winit::event::TouchPhase::Cancelled => TouchPhase::Cancel,
+                    };
+                    self.push_input(InputEvent::Touch {
+                        id: touch.id as u32,
+                        phase,
+                        x: logical.x as f32,
+                        y: logical.y as f32,
+                    });
                 }
             }
 
@@ -581,22 +816,90 @@ impl ApplicationHandler<UserEvent> for App {
                     self.redraw();
                 }
             }
+            UserEvent::Cursor => self.apply_cursor(event_loop),
         }
     }
 
-    fn about_to_wait(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {
-        // Reset notification flag at end of each event loop iteration.
+    fn about_to_wait(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        // Reset notified targets at end of each event loop iteration.
         // This allows one input notification per iteration - responsive but not flooding.
-        self.notified_this_iteration = false;
+        self.notified_this_iteration.clear();
+        self.update_cursor_shape();
+        if self.pointer.take_moved()
+            && self.running
+            && let Some(env) = self.env.as_ref()
+        {
+            env.window.request_redraw();
+        }
+        let (notified, held_cursor_due) = flush_held_cursor(&self.input_events);
+        self.notified_this_iteration.extend(notified);
+
+        // Wake up for the next scheduled script swap instead of waiting for external events.
+        let (activated, next_scheduled) = poll_scheduled_scripts(&self.render_state);
+        if activated
+            && self.running
+            && let Some(env) = self.env.as_ref()
+        {
+            env.window.request_redraw();
+        }
+        let wake = next_scheduled.map(|delay| Instant::now() + delay);
+        match wake.into_iter().chain(held_cursor_due).min() {
+            Some(at) => event_loop.set_control_flow(ControlFlow::WaitUntil(at)),
+            None => event_loop.set_control_flow(ControlFlow::Wait),
+        }
+    }
+}
+
+/// Presents the frame, telling the compositor which part of it changed when the renderer
+/// knows. EGL damage rects have their origin at the bottom left; drivers without
+/// `EGL_KHR_swap_buffers_with_damage` get a plain swap.
+fn swap_with_damage(
+    gl_surface: &GlutinSurface<WindowSurface>,
+    gl_context: &PossiblyCurrentContext,
+    renderer: &mut Renderer,
+) {
+    if let Some(damage) = renderer.frame_damage() {
+        let height = renderer.surface_mut().height();
+        let rect = DamageRect::new(
+            damage.left,
+            height - damage.bottom,
+            damage.width(),
+            damage.height(),
+        );
+        if gl_surface
+            .swap_buffers_with_damage(gl_context, &[rect])
+            .is_ok()
+        {
+            return;
+        }
+    }
+    gl_surface
+        .swap_buffers(gl_context)
+        .expect("swap_buffers failed");
+}
+
+/// Records the GL strings of a freshly created environment, whose context is current, or
+/// the properties of its Vulkan device.
+fn publish_gpu_info(render_state: &Arc<RenderStateLock>, env: &Env) {
+    match &env.presenter {
+        Presenter::Gl { gl_context, .. } => {
+            let display = gl_context.display();
+            unsafe { GpuInfo::query_gl("wayland") }
+                .with_egl_extensions(display.extensions().iter().copied())
+                .publish(render_state);
+        }
+        Presenter::Vulkan(vulkan) => vulkan.gpu_info().publish(render_state),
     }
 }
 
 pub fn run(
     proxy_ready: Sender<EventLoopProxy<UserEvent>>,
     running_flag: Arc<AtomicBool>,
-    render_state: Arc<Mutex<RenderState>>,
+    render_state: Arc<RenderStateLock>,
     input_mask: Arc<AtomicU32>,
     input_events: Arc<Mutex<InputQueue>>,
+    device_masks: Arc<DeviceClassMasks>,
+    input_transform: Arc<Mutex<InputTransform>>,
     config: WaylandWindowConfig,
 ) {
     let mut el_builder = EventLoop::<UserEvent>::with_user_event();
@@ -604,35 +907,74 @@ pub fn run(
     let el = el_builder.build().expect("Failed to create event loop");
     let proxy = el.create_proxy();
     let _ = proxy_ready.send(proxy);
+    let cursor_state = Arc::clone(&config.cursor_state);
+    let graphics = config.graphics;
     let (env, renderer) = match create_env_renderer_with_event_loop(&el, config) {
         Ok(values) => values,
         Err(err) => {
-            eprintln!("Failed to initialize renderer: {err}");
+            log_line!("Failed to initialize renderer: {err}");
             running_flag.store(false, Ordering::Relaxed);
             return;
         }
     };
+    publish_gpu_info(&render_state, &env);
     let size = env.window.inner_size();
     let scale_factor = env.window.scale_factor();
+    let (cursor_regions, pointer, cursor_image) = render_state
+        .lock()
+        .map(|state| {
+            (
+                Arc::clone(&state.cursor_regions),
+                Arc::clone(&state.pointer),
+                Arc::clone(&state.cursor_image),
+            )
+        })
+        .unwrap_or_default();
 
     let mut app = App {
         env: Some(env),
         renderer: Some(renderer),
+        graphics,
         running: true,
         running_flag,
         render_state,
         input_mask,
+        device_masks,
         input_events,
         cursor_pos: (0.0, 0.0),
         window_size: (size.width, size.height),
         scale_factor,
         modifiers: ModifiersState::empty(),
-        notified_this_iteration: false,
+        key_repeat: 0,
+        input_transform,
+        cursor_regions,
+        cursor_shape: CursorShape::Default,
+        cursor_state,
+        cursor_image,
+        custom_cursor: None,
+        pointer,
+        notified_this_iteration: Vec::new(),
     };
     app.redraw();
     el.run_app(&mut app).expect("run_app failed");
 }
 
+fn cursor_icon(shape: CursorShape) -> CursorIcon {
+    match shape {
+        CursorShape::Default => CursorIcon::Default,
+        CursorShape::Pointer => CursorIcon::Pointer,
+        CursorShape::Text => CursorIcon::Text,
+        CursorShape::Crosshair => CursorIcon::Crosshair,
+        CursorShape::Move => CursorIcon::Move,
+        CursorShape::NotAllowed => CursorIcon::NotAllowed,
+        CursorShape::Grab => CursorIcon::Grab,
+        CursorShape::Grabbing => CursorIcon::Grabbing,
+        CursorShape::EwResize => CursorIcon::EwResize,
+        CursorShape::NsResize => CursorIcon::NsResize,
+        CursorShape::Wait => CursorIcon::Wait,
+    }
+}
+
 fn map_modifiers(mods: ModifiersState) -> ScenicModifiers {
     ScenicModifiers {
         shift: mods.shift_key(),
@@ -0,0 +1,355 @@
+use std::fs;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_WAYLAND_DISPLAY: &str = "wayland-0";
+const DEV_DRI: &str = "/dev/dri";
+/// Card opened when none is configured and no card has KMS connectors.
+pub const DEFAULT_CARD: &str = "/dev/dri/card0";
+const SYSFS_DRM: &str = "/sys/class/drm";
+
+/// Backend picked for `backend: "auto"` and why, reported by `get_status`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackendChoice {
+    pub backend: String,
+    pub reason: String,
+    /// DRM card the probe found, used when no card was configured.
+    pub card: Option<String>,
+}
+
+/// A `/dev/dri` node, as listed by `list_dri_devices`.
+#[derive(Clone, Debug, PartialEq, rustler::NifMap)]
+pub struct DriDevice {
+    pub path: String,
+    /// `"card"` for KMS nodes (`cardN`) and `"render"` for render nodes (`renderDN`).
+    pub kind: String,
+    /// Kernel driver bound to the GPU, such as `i915`, `amdgpu` or `panfrost`.
+    pub driver: Option<String>,
+    /// Bus address of the GPU (`0000:01:00.0`, `ff9a0000.gpu`); nodes of one GPU share it.
+    pub bus_id: Option<String>,
+    /// KMS connectors of a card and how many of them have a display attached.
+    pub connectors: usize,
+    pub connected: usize,
+}
+
+/// Where the probe looks, so tests can point it at a scratch directory.
+struct ProbePaths {
+    runtime_dir: Option<PathBuf>,
+    wayland_display: Option<String>,
+    dev_dri: PathBuf,
+    sysfs_drm: PathBuf,
+}
+
+/// Probes the system in order: a Wayland socket selects `wayland`, a DRM card with KMS
+/// connectors selects `drm`, anything else `raster`. `drm_card` limits the DRM probe to
+/// the configured card.
+pub fn probe(drm_card: Option<&str>) -> BackendChoice {
+    let paths = ProbePaths {
+        runtime_dir: std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from),
+        wayland_display: std::env::var("WAYLAND_DISPLAY").ok(),
+        dev_dri: PathBuf::from(DEV_DRI),
+        sysfs_drm: PathBuf::from(SYSFS_DRM),
+    };
+    probe_with(&paths, drm_card)
+}
+
+fn probe_with(paths: &ProbePaths, drm_card: Option<&str>) -> BackendChoice {
+    let socket = wayland_socket(paths);
+    if let Some(socket) = &socket
+        && is_socket(socket)
+    {
+        return BackendChoice {
+            backend: "wayland".to_string(),
+            reason: format!("Wayland socket {} present", socket.display()),
+            card: None,
+        };
+    }
+
+    let card = match drm_card {
+        Some(card) => dri_device(&paths.sysfs_drm, Path::new(card)),
+        None => preferred_card(&dri_devices_in(&paths.dev_dri, &paths.sysfs_drm), None).cloned(),
+    };
+    if let Some(card) = card
+        && card.connectors > 0
+        && Path::new(&card.path).exists()
+    {
+        return BackendChoice {
+            backend: "drm".to_string(),
+            reason: format!(
+                "{} has {} KMS connector(s), {} connected",
+                card.path, card.connectors, card.connected
+            ),
+            card: Some(card.path),
+        };
+    }
+
+    let wayland = match &socket {
+        Some(socket) => format!("no Wayland socket at {}", socket.display()),
+        None => "XDG_RUNTIME_DIR not set".to_string(),
+    };
+    let drm = match drm_card {
+        Some(card) => format!("{card} has no KMS connectors"),
+        None => format!("no KMS card in {}", paths.dev_dri.display()),
+    };
+    BackendChoice {
+        backend: "raster".to_string(),
+        reason: format!("{wayland} and {drm}"),
+        card: None,
+    }
+}
+
+/// Every card and render node in `/dev/dri`, sorted by name.
+pub fn dri_devices() -> Vec<DriDevice> {
+    dri_devices_in(Path::new(DEV_DRI), Path::new(SYSFS_DRM))
+}
+
+/// `/dev/dri/cardN` bound to the kernel `driver` (`vkms`, `i915`, ...), for picking a card
+/// by driver instead of by its probe-order dependent number. Cards with a display
+/// attached are preferred.
+pub fn card_for_driver(driver: &str) -> Result<String, String> {
+    card_for_driver_in(Path::new(DEV_DRI), Path::new(SYSFS_DRM), driver)
+}
+
+fn card_for_driver_in(dev_dri: &Path, sysfs_drm: &Path, driver: &str) -> Result<String, String> {
+    preferred_card(&dri_devices_in(dev_dri, sysfs_drm), Some(driver))
+        .map(|card| card.path.clone())
+        .ok_or_else(|| format!("no DRM card in {} uses driver {driver}", dev_dri.display()))
+}
+
+/// Card the DRM backend opens when none is configured: the first one with a display
+/// attached, else the first with KMS connectors. On hybrid systems `card0` may be a
+/// render-only GPU, so it is not assumed.
+pub fn default_card() -> Option<String> {
+    preferred_card(&dri_devices(), None)
+        .filter(|card| card.connectors > 0)
+        .map(|card| card.path.clone())
+}
+
+/// Render node to draw on for frames scanned out by `card`, for `render_node: :auto`.
+/// `None` while the card's own GPU has a render node, so frames stay on one device;
+/// display-only KMS drivers get the first render node of another GPU.
+pub fn render_node_for(card: &str) -> Option<String> {
+    render_node_for_in(Path::new(DEV_DRI), Path::new(SYSFS_DRM), card)
+}
+
+fn render_node_for_in(dev_dri: &Path, sysfs_drm: &Path, card: &str) -> Option<String> {
+    let bus_id = dri_device(sysfs_drm, Path::new(card))?.bus_id?;
+    let render_nodes: Vec<DriDevice> = dri_devices_in(dev_dri, sysfs_drm)
+        .into_iter()
+        .filter(|device| device.kind == "render")
+        .collect();
+    if render_nodes
+        .iter()
+        .any(|node| node.bus_id.as_ref() == Some(&bus_id))
+    {
+        return None;
+    }
+    render_nodes.into_iter().next().map(|node| node.path)
+}
+
+/// Kernel driver of the GPU behind a `/dev/dri` node.
+pub fn node_driver(path: &str) -> Option<String> {
+    node_driver_in(Path::new(SYSFS_DRM), Path::new(path))
+}
+
+/// Among the cards (bound to `driver`, if given), the first with a display attached,
+/// else the first with KMS connectors, else the first one.
+fn preferred_card<'a>(devices: &'a [DriDevice], driver: Option<&str>) -> Option<&'a DriDevice> {
+    devices
+        .iter()
+        .filter(|device| device.kind == "card")
+        .filter(|device| driver.is_none() || device.driver.as_deref() == driver)
+        .min_by_key(|device| (device.connected == 0, device.connectors == 0))
+}
+
+fn dri_devices_in(dev_dri: &Path, sysfs_drm: &Path) -> Vec<DriDevice> {
+    dri_nodes(dev_dri)
+        .iter()
+        .filter_map(|node| dri_device(sysfs_drm, node))
+        .collect()
+}
+
+/// Describes the card or render node at `node` from sysfs.
+fn dri_device(sysfs_drm: &Path, node: &Path) -> Option<DriDevice> {
+    let name = node.file_name()?.to_str()?;
+    let kind = if is_node(name, "card") {
+        "card"
+    } else if is_node(name, "renderD") {
+        "render"
+    } else {
+        return None;
+    };
+    let (connectors, connected) = connectors(sysfs_drm, name);
+    let bus_id = fs::canonicalize(sysfs_drm.join(name).join("device"))
+        .ok()
+        .and_then(|device| Some(device.file_name()?.to_str()?.to_string()));
+    Some(DriDevice {
+        path: node.display().to_string(),
+        kind: kind.to_string(),
+        driver: node_driver_in(sysfs_drm, node),
+        bus_id,
+        connectors,
+        connected,
+    })
+}
+
+/// Driver name from the `<node>/device/driver` link in sysfs.
+fn node_driver_in(sysfs_drm: &Path, node: &Path) -> Option<String> {
+    let name = node.file_name()?;
+    let link = fs::read_link(sysfs_drm.join(name).join("device/driver")).ok()?;
+    Some(link.file_name()?.to_str()?.to_string())
+}
+
+/// Socket path the Wayland client library would connect to.
+fn wayland_socket(paths: &ProbePaths) -> Option<PathBuf> {
+    let display = paths
+        .wayland_display
+        .as_deref()
+        .unwrap_or(DEFAULT_WAYLAND_DISPLAY);
+    if Path::new(display).is_absolute() {
+        return Some(PathBuf::from(display));
+    }
+    paths.runtime_dir.as_ref().map(|dir| dir.join(display))
+}
+
+fn is_socket(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|meta| meta.file_type().is_socket())
+}
+
+/// Whether `name` is `prefix` followed by a node number, such as `card0` or `renderD128`.
+fn is_node(name: &str, prefix: &str) -> bool {
+    name.strip_prefix(prefix)
+        .is_some_and(|index| !index.is_empty() && index.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// `cardN` and `renderDN` nodes in `dev_dri`, sorted by name.
+fn dri_nodes(dev_dri: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dev_dri) else {
+        return Vec::new();
+    };
+    let mut nodes: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| is_node(name, "card") || is_node(name, "renderD"))
+        })
+        .map(|entry| entry.path())
+        .collect();
+    nodes.sort();
+    nodes
+}
+
+/// Connectors sysfs lists for the node `name` (`cardN-HDMI-A-1`, ...) and how many report
+/// `connected`. Render nodes and render-only cards have none.
+fn connectors(sysfs_drm: &Path, name: &str) -> (usize, usize) {
+    let prefix = format!("{name}-");
+    let Ok(entries) = fs::read_dir(sysfs_drm) else {
+        return (0, 0);
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|entry| entry.starts_with(&prefix))
+        })
+        .fold((0, 0), |(total, connected), entry| {
+            let status = fs::read_to_string(entry.path().join("status")).unwrap_or_default();
+            (
+                total + 1,
+                connected + usize::from(status.trim() == "connected"),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+
+    #[test]
+    fn probe_prefers_wayland_then_kms_then_raster() {
+        let root = std::env::temp_dir().join(format!("scenic-skia-probe-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let runtime_dir = root.join("run");
+        let dev_dri = root.join("dri");
+        let sysfs_drm = root.join("sysfs");
+        for dir in [&runtime_dir, &dev_dri, &sysfs_drm] {
+            fs::create_dir_all(dir).expect("create probe dir");
+        }
+        let paths = ProbePaths {
+            runtime_dir: Some(runtime_dir.clone()),
+            wayland_display: Some("wayland-1".to_string()),
+            dev_dri: dev_dri.clone(),
+            sysfs_drm: sysfs_drm.clone(),
+        };
+
+        fs::write(dev_dri.join("card0"), b"").expect("card0");
+        fs::write(dev_dri.join("card1"), b"").expect("card1");
+        fs::write(dev_dri.join("renderD128"), b"").expect("render node");
+        assert_eq!(probe_with(&paths, None).backend, "raster");
+
+        fs::create_dir(sysfs_drm.join("card1-HDMI-A-1")).expect("connector");
+        let choice = probe_with(&paths, None);
+        assert_eq!(choice.backend, "drm");
+        assert_eq!(
+            choice.card,
+            Some(dev_dri.join("card1").display().to_string())
+        );
+        let card0 = dev_dri.join("card0").display().to_string();
+        assert_eq!(probe_with(&paths, Some(&card0)).backend, "raster");
+
+        fs::create_dir_all(sysfs_drm.join("card1/device")).expect("card1 device");
+        fs::create_dir_all(root.join("drivers/vkms")).expect("driver dir");
+        std::os::unix::fs::symlink(
+            root.join("drivers/vkms"),
+            sysfs_drm.join("card1/device/driver"),
+        )
+        .expect("driver link");
+        assert_eq!(
+            card_for_driver_in(&dev_dri, &sysfs_drm, "vkms"),
+            Ok(dev_dri.join("card1").display().to_string())
+        );
+        assert!(card_for_driver_in(&dev_dri, &sysfs_drm, "i915").is_err());
+
+        // A display-only card with a panel attached, and a GPU with only a render node.
+        let link = |target: &str, node: &str| {
+            fs::create_dir_all(root.join("devices").join(target)).expect("device dir");
+            fs::create_dir_all(sysfs_drm.join(node)).expect("node dir");
+            std::os::unix::fs::symlink(
+                root.join("devices").join(target),
+                sysfs_drm.join(node).join("device"),
+            )
+            .expect("device link");
+        };
+        link("display-subsystem", "card0");
+        link("ff9a0000.gpu", "renderD128");
+        fs::create_dir(sysfs_drm.join("card0-DSI-1")).expect("connector");
+        fs::write(sysfs_drm.join("card0-DSI-1/status"), "connected\n").expect("status");
+        let devices = dri_devices_in(&dev_dri, &sysfs_drm);
+        assert_eq!(devices.len(), 3);
+        assert_eq!(devices[2].kind, "render");
+        assert_eq!(devices[2].bus_id.as_deref(), Some("ff9a0000.gpu"));
+        assert_eq!((devices[0].connectors, devices[0].connected), (1, 1));
+        assert_eq!(preferred_card(&devices, None), Some(&devices[0]));
+        assert_eq!(preferred_card(&devices, Some("vkms")), Some(&devices[1]));
+        let render_node = dev_dri.join("renderD128").display().to_string();
+        assert_eq!(
+            render_node_for_in(&dev_dri, &sysfs_drm, &card0),
+            Some(render_node)
+        );
+        fs::write(dev_dri.join("renderD129"), b"").expect("render node");
+        link("display-subsystem", "renderD129");
+        assert_eq!(render_node_for_in(&dev_dri, &sysfs_drm, &card0), None);
+
+        let _listener = UnixListener::bind(runtime_dir.join("wayland-1")).expect("socket");
+        let choice = probe_with(&paths, None);
+        assert_eq!(choice.backend, "wayland");
+        assert_eq!(choice.card, None);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
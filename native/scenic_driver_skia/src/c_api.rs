@@ -0,0 +1,194 @@
+//! C ABI for using the renderer without the BEAM (enabled with the `c-api` feature).
+//!
+//! Functions return `0` on success and `-1` on failure; the failure message is available
+//! from `scenic_skia_last_error` on the same thread until the next failure. See
+//! `include/scenic_driver_skia.h`.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char, c_int};
+use std::sync::Mutex;
+
+use crate::{
+    RendererResource, parse_counted, set_script, start_driver, stop_driver, update_render_state,
+    with_handle,
+};
+
+/// Opaque renderer handle owned by the C caller.
+pub struct ScenicSkiaRenderer {
+    resource: RendererResource,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(err: String) {
+    let message = CString::new(err.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+fn status(result: Result<(), String>) -> c_int {
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            set_last_error(err);
+            -1
+        }
+    }
+}
+
+/// # Safety
+/// `value` must be null or point to a NUL-terminated string.
+unsafe fn optional_str(value: *const c_char) -> Result<Option<String>, String> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    unsafe { CStr::from_ptr(value) }
+        .to_str()
+        .map(|value| Some(value.to_string()))
+        .map_err(|_| "string argument is not valid UTF-8".to_string())
+}
+
+/// Returns the last error raised on this thread, or null. The pointer stays valid until
+/// the next failing call from the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn scenic_skia_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(std::ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Starts a renderer. `backend` is `"wayland"`, `"vulkan"`, `"drm"`, `"raster"` or
+/// `"raster_gpu"` (null selects Wayland); a `width` or `height` of `0` lets the backend pick
+/// the size. Returns null on failure.
+///
+/// # Safety
+/// `backend` and `title` must each be null or point to a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scenic_skia_start(
+    backend: *const c_char,
+    width: u32,
+    height: u32,
+    title: *const c_char,
+) -> *mut ScenicSkiaRenderer {
+    let started = unsafe { optional_str(backend) }.and_then(|backend| {
+        let title = unsafe { optional_str(title) }?;
+        let viewport_size = (width > 0 && height > 0).then_some((width, height));
+        start_driver(
+            backend,
+            viewport_size,
+            title.unwrap_or_else(|| "Scenic Window".to_string()),
+            false,
+            None,
+            None,
+            true,
+            false,
+        )
+    });
+    match started {
+        Ok(handle) => Box::into_raw(Box::new(ScenicSkiaRenderer {
+            resource: RendererResource {
+                handle: Mutex::new(handle),
+            },
+        })),
+        Err(err) => {
+            set_last_error(err);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Parses a serialized Scenic script and stores it under `id` (`"_root_"` is drawn first).
+///
+/// # Safety
+/// `renderer` must come from `scenic_skia_start`, `id` must be a NUL-terminated string and
+/// `data` must point to `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scenic_skia_submit_script(
+    renderer: *mut ScenicSkiaRenderer,
+    id: *const c_char,
+    data: *const u8,
+    len: usize,
+) -> c_int {
+    let Some(renderer) = (unsafe { renderer.as_ref() }) else {
+        return status(Err("renderer is null".to_string()));
+    };
+    let id = match unsafe { optional_str(id) } {
+        Ok(Some(id)) => id,
+        Ok(None) => return status(Err("script id is null".to_string())),
+        Err(err) => return status(Err(err)),
+    };
+    if data.is_null() && len > 0 {
+        return status(Err("script data is null".to_string()));
+    }
+    let script: &[u8] = if len == 0 {
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(data, len) }
+    };
+    status(update_render_state(&renderer.resource, |state| {
+        let ops = parse_counted(state, &id, script)?;
+        set_script(state, id, ops);
+        Ok(())
+    }))
+}
+
+/// Stops the renderer thread and frees the handle. Passing null is a no-op.
+///
+/// # Safety
+/// `renderer` must come from `scenic_skia_start` and must not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scenic_skia_stop(renderer: *mut ScenicSkiaRenderer) -> c_int {
+    if renderer.is_null() {
+        return 0;
+    }
+    let renderer = unsafe { Box::from_raw(renderer) };
+    status(with_handle(&renderer.resource, stop_driver))
+}
+
+#[cfg(all(test, feature = "c-api"))]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn raster_renderer_draws_submitted_scripts() {
+        let renderer = unsafe { scenic_skia_start(c"raster".as_ptr(), 32, 32, std::ptr::null()) };
+        assert!(!renderer.is_null());
+
+        let mut script = vec![0x00, 0x60, 0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF];
+        script.extend_from_slice(&[0x00, 0x04, 0x00, 0x01]);
+        script.extend_from_slice(&32.0f32.to_be_bytes());
+        script.extend_from_slice(&32.0f32.to_be_bytes());
+        let root = c"_root_".as_ptr();
+        let submitted =
+            unsafe { scenic_skia_submit_script(renderer, root, script.as_ptr(), script.len()) };
+        assert_eq!(submitted, 0);
+        let null_data = unsafe { scenic_skia_submit_script(renderer, root, std::ptr::null(), 4) };
+        assert_eq!(null_data, -1);
+        assert!(!scenic_skia_last_error().is_null());
+
+        let resource = unsafe { &(*renderer).resource };
+        let red_pixel = || {
+            with_handle(resource, |handle| {
+                let frame = handle.raster_frame.as_ref().expect("raster frame slot");
+                let frame = frame.lock().map_err(|_| "raster frame lock poisoned")?;
+                Ok(frame.as_ref().is_some_and(|frame| {
+                    let offset = ((4 * frame.width + 4) * 3) as usize;
+                    frame.data[offset..offset + 3] == [255, 0, 0]
+                }))
+            })
+        };
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !red_pixel().expect("frame") && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(red_pixel().expect("frame"));
+
+        assert_eq!(unsafe { scenic_skia_stop(renderer) }, 0);
+        assert_eq!(unsafe { scenic_skia_stop(std::ptr::null_mut()) }, 0);
+    }
+}
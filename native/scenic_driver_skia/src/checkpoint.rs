@@ -0,0 +1,120 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use crate::renderer::{RenderState, ScheduledScript, ScriptOp};
+
+const MAX_CHECKPOINTS: usize = 16;
+
+#[derive(Clone, Debug)]
+struct Checkpoint {
+    scripts: HashMap<String, Arc<Vec<ScriptOp>>>,
+    root_id: Option<String>,
+    scheduled: Vec<ScheduledScript>,
+}
+
+/// Snapshots of the script store taken by `checkpoint` and restored by `rollback`.
+///
+/// Script ops are shared with the live store, so a checkpoint copies ids and pointers
+/// rather than ops. The newest 16 checkpoints are kept; older tokens stop resolving.
+#[derive(Clone, Debug, Default)]
+pub struct Checkpoints {
+    next_token: u64,
+    saved: VecDeque<(u64, Checkpoint)>,
+}
+
+/// Snapshots the scripts, root and scheduled scripts of `state` and returns the token.
+pub fn checkpoint(state: &mut RenderState) -> u64 {
+    let snapshot = Checkpoint {
+        scripts: state.scripts.clone(),
+        root_id: state.root_id.clone(),
+        scheduled: state.scheduled.clone(),
+    };
+    let checkpoints = &mut state.checkpoints;
+    checkpoints.next_token += 1;
+    if checkpoints.saved.len() >= MAX_CHECKPOINTS {
+        checkpoints.saved.pop_front();
+    }
+    checkpoints
+        .saved
+        .push_back((checkpoints.next_token, snapshot));
+    checkpoints.next_token
+}
+
+/// Restores the script store saved under `token`. The checkpoint stays available, so
+/// the same state can be rolled back to again.
+pub fn rollback(state: &mut RenderState, token: u64) -> Result<(), String> {
+    let snapshot = state
+        .checkpoints
+        .saved
+        .iter()
+        .find(|(saved, _)| *saved == token)
+        .map(|(_, snapshot)| snapshot.clone())
+        .ok_or_else(|| format!("unknown checkpoint: {token}"))?;
+
+    for (id, ops) in &state.scripts {
+        if snapshot
+            .scripts
+            .get(id)
+            .is_none_or(|saved| !Arc::ptr_eq(saved, ops))
+        {
+            state.redraws.add_script(id);
+        }
+    }
+    for id in snapshot.scripts.keys() {
+        if !state.scripts.contains_key(id) {
+            state.redraws.add_script(id);
+        }
+    }
+
+    state.scripts = snapshot.scripts;
+    state.root_id = snapshot.root_id;
+    state.scheduled = snapshot.scheduled;
+    Ok(())
+}
+
+/// Drops the checkpoint saved under `token`; returns whether it existed.
+pub fn release(state: &mut RenderState, token: u64) -> bool {
+    let before = state.checkpoints.saved.len();
+    state.checkpoints.saved.retain(|(saved, _)| *saved != token);
+    state.checkpoints.saved.len() != before
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rollback_restores_scripts_and_keeps_the_checkpoint() {
+        let mut state = RenderState::default();
+        state
+            .scripts
+            .insert("a".to_string(), Arc::new(vec![ScriptOp::PushState]));
+        state.root_id = Some("a".to_string());
+        let token = checkpoint(&mut state);
+
+        state
+            .scripts
+            .insert("a".to_string(), Arc::new(vec![ScriptOp::PopState]));
+        state.scripts.insert("b".to_string(), Arc::new(Vec::new()));
+        state.root_id = None;
+
+        rollback(&mut state, token).expect("rollback");
+        assert_eq!(state.scripts.len(), 1);
+        assert_eq!(state.scripts["a"].as_slice(), &[ScriptOp::PushState]);
+        assert_eq!(state.root_id.as_deref(), Some("a"));
+        let reasons = state.redraws.finish_frame(0);
+        assert_eq!(reasons.scripts, vec!["a", "b"]);
+
+        assert!(rollback(&mut state, token).is_ok());
+        assert!(release(&mut state, token));
+        assert!(!release(&mut state, token));
+        assert!(rollback(&mut state, token).is_err());
+
+        let first = checkpoint(&mut state);
+        for _ in 0..MAX_CHECKPOINTS {
+            checkpoint(&mut state);
+        }
+        assert!(rollback(&mut state, first).is_err());
+        assert!(rollback(&mut state, first + 1).is_ok());
+    }
+}
@@ -0,0 +1,79 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Time the renderer animates by and activates scheduled updates at.
+///
+/// Normally this is the driver's monotonic clock. Switched to manual it stands still and
+/// only moves with [`RenderClock::advance`], so blinks, marquees and animated sprites draw
+/// the same frames on every run of a golden-frame test.
+#[derive(Debug, Default)]
+pub struct RenderClock {
+    manual: AtomicBool,
+    now_us: AtomicU64,
+}
+
+impl RenderClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn now_us(&self) -> u64 {
+        if self.manual.load(Ordering::Acquire) {
+            self.now_us.load(Ordering::Acquire)
+        } else {
+            crate::monotonic_now_us()
+        }
+    }
+
+    pub fn now_ms(&self) -> u64 {
+        self.now_us() / 1000
+    }
+
+    /// Switches to a manual clock standing at `start_ms`, or at the current time when
+    /// `None`, so times taken from `monotonic_ms` stay meaningful.
+    pub fn set_manual(&self, start_ms: Option<u64>) {
+        let start_us = start_ms.map_or_else(crate::monotonic_now_us, |ms| ms * 1000);
+        self.now_us.store(start_us, Ordering::Release);
+        self.manual.store(true, Ordering::Release);
+    }
+
+    /// Goes back to the monotonic clock.
+    pub fn set_monotonic(&self) {
+        self.manual.store(false, Ordering::Release);
+    }
+
+    pub fn is_manual(&self) -> bool {
+        self.manual.load(Ordering::Acquire)
+    }
+
+    /// Moves the manual clock forward by `ms` and returns the new time in milliseconds.
+    pub fn advance(&self, ms: u64) -> Result<u64, String> {
+        if !self.is_manual() {
+            return Err("advance_time needs the manual clock".to_string());
+        }
+        let advanced = self.now_us.fetch_add(ms * 1000, Ordering::AcqRel) + ms * 1000;
+        Ok(advanced / 1000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_time_only_moves_when_advanced() {
+        let clock = RenderClock::new();
+        assert!(!clock.is_manual());
+        assert!(clock.advance(10).is_err());
+
+        clock.set_manual(Some(1_000));
+        assert_eq!(clock.now_ms(), 1_000);
+        assert_eq!(clock.advance(250), Ok(1_250));
+        assert_eq!(clock.now_us(), 1_250_000);
+
+        clock.set_manual(None);
+        let now = clock.now_ms();
+        assert!(now.abs_diff(crate::monotonic_now_ms()) < 1_000);
+        clock.set_monotonic();
+        assert!(clock.now_ms() >= now);
+    }
+}
@@ -0,0 +1,190 @@
+use skia_safe::{
+    AlphaType, ColorType, FilterMode, Image, ImageInfo, MipmapMode, Paint, Rect, SamplingOptions,
+    image::CachingHint, surfaces,
+};
+
+/// Longest side images are scaled down to before palette extraction.
+const PALETTE_SAMPLE_SIZE: f32 = 64.0;
+/// Pixels more transparent than this are left out of the palette.
+const PALETTE_MIN_ALPHA: u8 = 128;
+/// Squared RGB distance under which two candidate colors count as the same palette entry.
+const PALETTE_MIN_DISTANCE_SQ: i32 = 32 * 32;
+
+pub type Rgba = (u8, u8, u8, u8);
+
+/// Dominant color of an image and the share of sampled pixels it stands for, returned to
+/// Elixir as `%{color: {r, g, b}, share: 0.42}`.
+#[derive(Clone, Debug, PartialEq, rustler::NifMap)]
+pub struct PaletteEntry {
+    pub color: (u8, u8, u8),
+    pub share: f64,
+}
+
+/// WCAG 2 relative luminance of an sRGB color, from 0.0 (black) to 1.0 (white).
+pub fn relative_luminance((r, g, b, _): Rgba) -> f64 {
+    let linear = |channel: u8| {
+        let c = f64::from(channel) / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+/// WCAG 2 contrast ratio between two colors, from 1.0 to 21.0. Alpha is ignored.
+pub fn contrast_ratio(a: Rgba, b: Rgba) -> f64 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+/// Composites unpremultiplied `fg` over `bg` (source-over).
+pub fn blend(fg: Rgba, bg: Rgba) -> Rgba {
+    let fa = f64::from(fg.3) / 255.0;
+    let ba = f64::from(bg.3) / 255.0;
+    let out_a = fa + ba * (1.0 - fa);
+    if out_a <= 0.0 {
+        return (0, 0, 0, 0);
+    }
+    let channel = |f: u8, b: u8| {
+        let value = (f64::from(f) * fa + f64::from(b) * ba * (1.0 - fa)) / out_a;
+        value.round().clamp(0.0, 255.0) as u8
+    };
+    (
+        channel(fg.0, bg.0),
+        channel(fg.1, bg.1),
+        channel(fg.2, bg.2),
+        (out_a * 255.0).round() as u8,
+    )
+}
+
+/// Up to `count` dominant colors of `image`, most common first.
+pub fn extract_palette(image: &Image, count: usize) -> Result<Vec<PaletteEntry>, String> {
+    let (width, height) = (image.width(), image.height());
+    if width <= 0 || height <= 0 {
+        return Ok(Vec::new());
+    }
+    let scale = (PALETTE_SAMPLE_SIZE / width.max(height) as f32).min(1.0);
+    let size = (
+        ((width as f32 * scale).round() as i32).max(1),
+        ((height as f32 * scale).round() as i32).max(1),
+    );
+    let mut surface = surfaces::raster_n32_premul(size)
+        .ok_or_else(|| "failed to create palette surface".to_string())?;
+    surface.canvas().draw_image_rect_with_sampling_options(
+        image,
+        None,
+        Rect::from_iwh(size.0, size.1),
+        SamplingOptions::new(FilterMode::Linear, MipmapMode::None),
+        &Paint::default(),
+    );
+    let info = ImageInfo::new(size, ColorType::RGBA8888, AlphaType::Unpremul, None);
+    let row_bytes = info.min_row_bytes();
+    let mut pixels = vec![0u8; row_bytes * size.1 as usize];
+    if !surface.image_snapshot().read_pixels(
+        &info,
+        pixels.as_mut_slice(),
+        row_bytes,
+        (0, 0),
+        CachingHint::Disallow,
+    ) {
+        return Err("failed to read image pixels".to_string());
+    }
+    Ok(palette_from_pixels(&pixels, count))
+}
+
+/// Buckets RGBA pixels by their top four bits per channel and keeps the fullest buckets
+/// whose average colors are visibly apart.
+fn palette_from_pixels(pixels: &[u8], count: usize) -> Vec<PaletteEntry> {
+    let mut buckets = vec![(0u32, [0u64; 3]); 4096];
+    let mut total = 0u32;
+    for pixel in pixels.chunks_exact(4) {
+        if pixel[3] < PALETTE_MIN_ALPHA {
+            continue;
+        }
+        let index = (usize::from(pixel[0] >> 4) << 8)
+            | (usize::from(pixel[1] >> 4) << 4)
+            | usize::from(pixel[2] >> 4);
+        let (hits, sums) = &mut buckets[index];
+        *hits += 1;
+        for (sum, &channel) in sums.iter_mut().zip(pixel) {
+            *sum += u64::from(channel);
+        }
+        total += 1;
+    }
+    if total == 0 {
+        return Vec::new();
+    }
+
+    let mut candidates: Vec<(u32, (u8, u8, u8))> = buckets
+        .iter()
+        .filter(|(hits, _)| *hits > 0)
+        .map(|(hits, sums)| {
+            let average = |sum: u64| (sum / u64::from(*hits)) as u8;
+            (
+                *hits,
+                (average(sums[0]), average(sums[1]), average(sums[2])),
+            )
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.0.cmp(&a.0));
+
+    let mut palette: Vec<PaletteEntry> = Vec::with_capacity(count);
+    for (hits, color) in candidates {
+        if palette.len() >= count {
+            break;
+        }
+        let distinct = palette.iter().all(|entry| {
+            let d = |a: u8, b: u8| i32::from(a) - i32::from(b);
+            let (dr, dg, db) = (
+                d(entry.color.0, color.0),
+                d(entry.color.1, color.1),
+                d(entry.color.2, color.2),
+            );
+            dr * dr + dg * dg + db * db >= PALETTE_MIN_DISTANCE_SQ
+        });
+        if distinct {
+            palette.push(PaletteEntry {
+                color,
+                share: f64::from(hits) / f64::from(total),
+            });
+        }
+    }
+    palette
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_utilities_match_wcag_and_source_over() {
+        let black = (0, 0, 0, 255);
+        let white = (255, 255, 255, 255);
+        assert!((contrast_ratio(black, white) - 21.0).abs() < 1e-9);
+        assert!((contrast_ratio(white, white) - 1.0).abs() < 1e-9);
+        assert!((relative_luminance((255, 0, 0, 255)) - 0.2126).abs() < 1e-9);
+
+        assert_eq!(blend((255, 0, 0, 128), white), (255, 127, 127, 255));
+        assert_eq!(blend((0, 0, 255, 255), white), (0, 0, 255, 255));
+        assert_eq!(blend((0, 0, 0, 0), (10, 20, 30, 40)), (10, 20, 30, 40));
+        assert_eq!(blend((0, 0, 0, 0), (0, 0, 0, 0)), (0, 0, 0, 0));
+
+        let mut pixels = Vec::new();
+        for _ in 0..6 {
+            pixels.extend_from_slice(&[200, 30, 30, 255]);
+        }
+        for _ in 0..3 {
+            pixels.extend_from_slice(&[20, 20, 220, 255]);
+        }
+        pixels.extend_from_slice(&[205, 32, 28, 255]);
+        pixels.extend_from_slice(&[0, 255, 0, 0]);
+        let palette = palette_from_pixels(&pixels, 4);
+        assert_eq!(palette.len(), 2);
+        assert_eq!(palette[0].color, (200, 30, 30));
+        assert!((palette[0].share - 0.6).abs() < 1e-9);
+        assert_eq!(palette[1].color, (20, 20, 220));
+        assert_eq!(palette_from_pixels(&pixels, 1).len(), 1);
+    }
+}
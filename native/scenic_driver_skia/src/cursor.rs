@@ -1,7 +1,17 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use skia_safe::{AlphaType, ColorType, Data, Image, ImageInfo, images};
+
 #[derive(Debug, Clone, Copy)]
 pub struct CursorState {
     pub pos: (f32, f32),
     pub visible: bool,
+    /// Shape of the hit region under the pointer, resolved by the backend.
+    pub shape: CursorShape,
+    /// Serial of the custom image shown instead of the default shape, resolved by the
+    /// backend.
+    pub image: Option<u64>,
 }
 
 impl CursorState {
@@ -9,6 +19,294 @@ impl CursorState {
         Self {
             pos: (0.0, 0.0),
             visible: true,
+            shape: CursorShape::Default,
+            image: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccelProfile {
+    Flat,
+    Adaptive,
+}
+
+impl AccelProfile {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "flat" => Ok(AccelProfile::Flat),
+            "adaptive" => Ok(AccelProfile::Adaptive),
+            other => Err(format!("unsupported pointer profile: {other}")),
+        }
+    }
+}
+
+/// Maps relative pointer motion in device units to screen pixels.
+#[derive(Debug, Clone, Copy)]
+pub struct PointerAccel {
+    pub profile: AccelProfile,
+    pub sensitivity: f32,
+}
+
+const ADAPTIVE_THRESHOLD: f32 = 2.0;
+const ADAPTIVE_SLOPE: f32 = 0.08;
+const ADAPTIVE_MAX_GAIN: f32 = 3.0;
+
+impl PointerAccel {
+    pub fn new() -> Self {
+        Self {
+            profile: AccelProfile::Flat,
+            sensitivity: 1.0,
+        }
+    }
+
+    pub fn apply(&self, dx: f32, dy: f32) -> (f32, f32) {
+        let gain = match self.profile {
+            AccelProfile::Flat => 1.0,
+            AccelProfile::Adaptive => {
+                // Slow, precise movements stay 1:1; faster ones ramp up to a capped gain.
+                let speed = (dx * dx + dy * dy).sqrt();
+                (1.0 + (speed - ADAPTIVE_THRESHOLD).max(0.0) * ADAPTIVE_SLOPE)
+                    .min(ADAPTIVE_MAX_GAIN)
+            }
+        };
+        let factor = self.sensitivity * gain;
+        (dx * factor, dy * factor)
+    }
+}
+
+/// Cursor images the driver switches to while the pointer is over a hit region.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CursorShape {
+    #[default]
+    Default,
+    Pointer,
+    Text,
+    Crosshair,
+    Move,
+    NotAllowed,
+    Grab,
+    Grabbing,
+    EwResize,
+    NsResize,
+    Wait,
+}
+
+impl CursorShape {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "default" => Ok(CursorShape::Default),
+            "pointer" => Ok(CursorShape::Pointer),
+            "text" => Ok(CursorShape::Text),
+            "crosshair" => Ok(CursorShape::Crosshair),
+            "move" => Ok(CursorShape::Move),
+            "not_allowed" => Ok(CursorShape::NotAllowed),
+            "grab" => Ok(CursorShape::Grab),
+            "grabbing" => Ok(CursorShape::Grabbing),
+            "ew_resize" => Ok(CursorShape::EwResize),
+            "ns_resize" => Ok(CursorShape::NsResize),
+            "wait" => Ok(CursorShape::Wait),
+            other => Err(format!("unsupported cursor: {other}")),
+        }
+    }
+}
+
+/// Scene-space rects `(x, y, w, h)` and the cursor shown while the pointer is over them.
+///
+/// Regions registered later are on top, so the last one containing the pointer wins;
+/// outside every region the default cursor is shown.
+#[derive(Debug, Default)]
+pub struct CursorRegions {
+    regions: Mutex<Vec<((f32, f32, f32, f32), CursorShape)>>,
+}
+
+impl CursorRegions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, regions: Vec<((f32, f32, f32, f32), CursorShape)>) -> Result<(), String> {
+        *self
+            .regions
+            .lock()
+            .map_err(|_| "cursor regions lock poisoned".to_string())? = regions;
+        Ok(())
+    }
+
+    pub fn shape_at(&self, x: f32, y: f32) -> CursorShape {
+        let Ok(regions) = self.regions.lock() else {
+            return CursorShape::Default;
+        };
+        regions
+            .iter()
+            .rev()
+            .find(|((rx, ry, rw, rh), _)| x >= *rx && x < rx + rw && y >= *ry && y < ry + rh)
+            .map_or(CursorShape::Default, |(_, shape)| *shape)
+    }
+}
+
+/// Largest custom cursor image, the size of a DRM cursor plane.
+pub const MAX_CURSOR_IMAGE_SIZE: u32 = 64;
+
+/// Custom bitmap shown instead of the default cursor, set through `set_cursor_image`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CursorImage {
+    pub width: u32,
+    pub height: u32,
+    /// Unpremultiplied RGBA rows.
+    pub rgba: Vec<u8>,
+    /// Pixel of the image that sits at the pointer position.
+    pub hotspot: (u32, u32),
+    /// Changes whenever the image is replaced, so backends know when to upload it again.
+    pub serial: u64,
+}
+
+impl CursorImage {
+    pub fn new(
+        width: u32,
+        height: u32,
+        rgba: Vec<u8>,
+        hotspot: (u32, u32),
+    ) -> Result<Self, String> {
+        if width == 0
+            || height == 0
+            || width > MAX_CURSOR_IMAGE_SIZE
+            || height > MAX_CURSOR_IMAGE_SIZE
+        {
+            return Err(format!(
+                "cursor image must be 1x1 to {MAX_CURSOR_IMAGE_SIZE}x{MAX_CURSOR_IMAGE_SIZE}, got {width}x{height}"
+            ));
+        }
+        if rgba.len() != (width * height * 4) as usize {
+            return Err(format!(
+                "cursor image has {} bytes, expected {} for {width}x{height} RGBA",
+                rgba.len(),
+                width * height * 4
+            ));
         }
+        if hotspot.0 >= width || hotspot.1 >= height {
+            return Err(format!("cursor hotspot {hotspot:?} is outside the image"));
+        }
+        Ok(Self {
+            width,
+            height,
+            rgba,
+            hotspot,
+            serial: 0,
+        })
+    }
+
+    pub fn to_image(&self) -> Option<Image> {
+        let info = ImageInfo::new(
+            (self.width as i32, self.height as i32),
+            ColorType::RGBA8888,
+            AlphaType::Unpremul,
+            None,
+        );
+        images::raster_from_data(&info, Data::new_copy(&self.rgba), self.width as usize * 4)
+    }
+}
+
+/// Custom cursor image shared by the NIFs and the backends; `None` keeps the default cursor.
+#[derive(Debug, Default)]
+pub struct CursorImageSlot {
+    image: Mutex<Option<Arc<CursorImage>>>,
+    serial: AtomicU64,
+}
+
+impl CursorImageSlot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, image: Option<CursorImage>) -> Result<(), String> {
+        let image = image.map(|mut image| {
+            image.serial = self.serial.fetch_add(1, Ordering::Relaxed) + 1;
+            Arc::new(image)
+        });
+        *self
+            .image
+            .lock()
+            .map_err(|_| "cursor image lock poisoned".to_string())? = image;
+        Ok(())
+    }
+
+    pub fn current(&self) -> Option<Arc<CursorImage>> {
+        self.image.lock().ok().and_then(|image| image.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flat_profile_scales_by_sensitivity() {
+        let accel = PointerAccel {
+            profile: AccelProfile::Flat,
+            sensitivity: 2.0,
+        };
+        assert_eq!(accel.apply(3.0, -4.0), (6.0, -8.0));
+        assert_eq!(accel.apply(30.0, 0.0), (60.0, 0.0));
+    }
+
+    #[test]
+    fn adaptive_profile_accelerates_fast_motion() {
+        let accel = PointerAccel {
+            profile: AccelProfile::Adaptive,
+            sensitivity: 1.0,
+        };
+        assert_eq!(accel.apply(1.0, 0.0), (1.0, 0.0));
+        let (fast, _) = accel.apply(20.0, 0.0);
+        assert!(fast > 20.0);
+        let (capped, _) = accel.apply(1000.0, 0.0);
+        assert_eq!(capped, 1000.0 * ADAPTIVE_MAX_GAIN);
+    }
+
+    #[test]
+    fn profile_parse_rejects_unknown() {
+        assert_eq!(AccelProfile::parse("flat"), Ok(AccelProfile::Flat));
+        assert_eq!(AccelProfile::parse("adaptive"), Ok(AccelProfile::Adaptive));
+        assert!(AccelProfile::parse("turbo").is_err());
+    }
+
+    #[test]
+    fn cursor_regions_pick_the_topmost_region() {
+        let regions = CursorRegions::new();
+        assert_eq!(regions.shape_at(5.0, 5.0), CursorShape::Default);
+        regions
+            .set(vec![
+                ((0.0, 0.0, 100.0, 100.0), CursorShape::Pointer),
+                ((10.0, 10.0, 20.0, 20.0), CursorShape::Text),
+            ])
+            .expect("set regions");
+        assert_eq!(regions.shape_at(5.0, 5.0), CursorShape::Pointer);
+        assert_eq!(regions.shape_at(15.0, 15.0), CursorShape::Text);
+        assert_eq!(regions.shape_at(30.0, 30.0), CursorShape::Pointer);
+        assert_eq!(regions.shape_at(100.0, 50.0), CursorShape::Default);
+        assert_eq!(
+            CursorShape::parse("not_allowed"),
+            Ok(CursorShape::NotAllowed)
+        );
+        assert!(CursorShape::parse("hand").is_err());
+    }
+
+    #[test]
+    fn cursor_images_are_validated_and_renumbered() {
+        assert!(CursorImage::new(0, 4, Vec::new(), (0, 0)).is_err());
+        assert!(CursorImage::new(65, 1, vec![0; 65 * 4], (0, 0)).is_err());
+        assert!(CursorImage::new(2, 2, vec![0; 15], (0, 0)).is_err());
+        assert!(CursorImage::new(2, 2, vec![0; 16], (2, 0)).is_err());
+
+        let image = CursorImage::new(2, 2, vec![255; 16], (1, 1)).expect("image");
+        assert!(image.to_image().is_some());
+        let slot = CursorImageSlot::new();
+        assert!(slot.current().is_none());
+        slot.set(Some(image.clone())).expect("set");
+        let first = slot.current().expect("current").serial;
+        slot.set(Some(image)).expect("set again");
+        assert!(slot.current().expect("current").serial > first);
+        slot.set(None).expect("clear");
+        assert!(slot.current().is_none());
     }
 }
@@ -0,0 +1,199 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use skia_safe::{Canvas, Color, Font, Paint, PaintStyle, Rect};
+
+const RULER_SIZE: f32 = 16.0;
+const LABEL_SPACING: f32 = 40.0;
+
+/// Development guides drawn over the scene in scene coordinates: a grid every `spacing`
+/// units, rulers along the top and left edges and the pointer position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DevOverlay {
+    pub spacing: f32,
+    pub color: Color,
+    pub rulers: bool,
+    pub pointer: bool,
+}
+
+/// Pointer position in scene coordinates, reported by backends for the dev overlay.
+///
+/// Backends update it wherever they resolve cursor regions. While tracking is on, a
+/// moved pointer sets a flag the backend takes to schedule a redraw.
+#[derive(Debug, Default)]
+pub struct PointerTracker {
+    pos: Mutex<Option<(f32, f32)>>,
+    tracking: AtomicBool,
+    moved: AtomicBool,
+}
+
+impl PointerTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_tracking(&self, tracking: bool) {
+        self.tracking.store(tracking, Ordering::Relaxed);
+    }
+
+    pub fn update(&self, x: f32, y: f32) {
+        let Ok(mut pos) = self.pos.lock() else {
+            return;
+        };
+        if *pos != Some((x, y)) {
+            *pos = Some((x, y));
+            if self.tracking.load(Ordering::Relaxed) {
+                self.moved.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Returns whether the pointer moved while tracked since the last call.
+    pub fn take_moved(&self) -> bool {
+        self.moved.swap(false, Ordering::Relaxed)
+    }
+
+    pub fn position(&self) -> Option<(f32, f32)> {
+        self.pos.lock().ok().and_then(|pos| *pos)
+    }
+}
+
+/// Draws `overlay` over the visible part of the canvas, whose matrix maps scene
+/// coordinates. `font` labels the rulers and the pointer; without one only lines are drawn.
+pub fn draw(
+    canvas: &Canvas,
+    overlay: &DevOverlay,
+    pointer: Option<(f32, f32)>,
+    font: Option<&Font>,
+) {
+    let bounds = canvas.local_clip_bounds().unwrap_or(Rect::new_empty());
+    if bounds.is_empty() {
+        return;
+    }
+    let mut line = Paint::default();
+    line.set_anti_alias(false);
+    line.set_style(PaintStyle::Stroke);
+    line.set_stroke_width(0.0);
+    line.set_color(overlay.color);
+
+    let xs = grid_lines(bounds.left, bounds.right, overlay.spacing);
+    let ys = grid_lines(bounds.top, bounds.bottom, overlay.spacing);
+    for &x in &xs {
+        canvas.draw_line((x, bounds.top), (x, bounds.bottom), &line);
+    }
+    for &y in &ys {
+        canvas.draw_line((bounds.left, y), (bounds.right, y), &line);
+    }
+
+    let mut text = Paint::default();
+    text.set_anti_alias(true);
+    text.set_color(overlay.color.with_a(255));
+    let mut band = Paint::default();
+    band.set_color(Color::from_argb(192, 0, 0, 0));
+
+    if overlay.rulers {
+        let (left, top) = (bounds.left, bounds.top);
+        canvas.draw_rect(
+            Rect::from_xywh(left, top, bounds.width(), RULER_SIZE),
+            &band,
+        );
+        canvas.draw_rect(
+            Rect::from_xywh(left, top, RULER_SIZE, bounds.height()),
+            &band,
+        );
+        let every = label_step(overlay.spacing);
+        for &x in &xs {
+            canvas.draw_line((x, top), (x, top + RULER_SIZE * 0.5), &text);
+            if let Some(font) = font
+                && is_labeled(x, overlay.spacing, every)
+                && x > left + RULER_SIZE
+            {
+                canvas.draw_str(
+                    format!("{x}"),
+                    (x + 2.0, top + RULER_SIZE - 3.0),
+                    font,
+                    &text,
+                );
+            }
+        }
+        for &y in &ys {
+            canvas.draw_line((left, y), (left + RULER_SIZE * 0.5, y), &text);
+            if let Some(font) = font
+                && is_labeled(y, overlay.spacing, every)
+                && y > top + RULER_SIZE
+            {
+                canvas.draw_str(format!("{y}"), (left + 1.0, y - 2.0), font, &text);
+            }
+        }
+    }
+
+    if overlay.pointer
+        && let Some((x, y)) = pointer
+    {
+        canvas.draw_line((x, bounds.top), (x, bounds.bottom), &text);
+        canvas.draw_line((bounds.left, y), (bounds.right, y), &text);
+        if let Some(font) = font {
+            let label = format!("{x:.0}, {y:.0}");
+            let (width, _) = font.measure_str(&label, None);
+            let height = font.size() + 4.0;
+            // Keep the label inside the visible area near the right and bottom edges.
+            let lx = if x + 8.0 + width + 4.0 > bounds.right {
+                x - width - 12.0
+            } else {
+                x + 8.0
+            };
+            let ly = if y + 8.0 + height > bounds.bottom {
+                y - height - 8.0
+            } else {
+                y + 8.0
+            };
+            canvas.draw_rect(Rect::from_xywh(lx, ly, width + 4.0, height), &band);
+            canvas.draw_str(&label, (lx + 2.0, ly + font.size()), font, &text);
+        }
+    }
+}
+
+/// Multiples of `spacing` between `start` and `end`.
+fn grid_lines(start: f32, end: f32, spacing: f32) -> Vec<f32> {
+    if spacing <= 0.0 || !spacing.is_finite() {
+        return Vec::new();
+    }
+    let first = (start / spacing).ceil() as i64;
+    let last = (end / spacing).floor() as i64;
+    (first..=last).map(|index| index as f32 * spacing).collect()
+}
+
+/// Every how many grid lines a ruler label fits.
+fn label_step(spacing: f32) -> i64 {
+    (LABEL_SPACING / spacing).ceil().max(1.0) as i64
+}
+
+fn is_labeled(value: f32, spacing: f32, every: i64) -> bool {
+    (value / spacing).round() as i64 % every == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_lines_cover_the_visible_range() {
+        assert_eq!(grid_lines(-5.0, 25.0, 10.0), vec![0.0, 10.0, 20.0]);
+        assert_eq!(grid_lines(0.0, 20.0, 10.0), vec![0.0, 10.0, 20.0]);
+        assert!(grid_lines(0.0, 20.0, 0.0).is_empty());
+        assert_eq!(label_step(10.0), 4);
+        assert_eq!(label_step(50.0), 1);
+        assert!(is_labeled(40.0, 10.0, 4));
+        assert!(!is_labeled(30.0, 10.0, 4));
+
+        let pointer = PointerTracker::new();
+        pointer.update(1.0, 2.0);
+        assert!(!pointer.take_moved());
+        pointer.set_tracking(true);
+        pointer.update(1.0, 2.0);
+        assert!(!pointer.take_moved());
+        pointer.update(3.0, 4.0);
+        assert!(pointer.take_moved());
+        assert_eq!(pointer.position(), Some((3.0, 4.0)));
+    }
+}
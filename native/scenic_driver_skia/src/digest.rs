@@ -0,0 +1,35 @@
+use sha2::{Digest as _, Sha256};
+
+/// SHA-256 of asset or script bytes.
+///
+/// Content-addressed caches key on it instead of a `std` hash: a 64-bit hash collides
+/// often enough across a device's lifetime to alias two assets, and `DefaultHasher`
+/// output may change between Rust releases, which would orphan anything named by it on
+/// disk.
+pub type Digest = [u8; 32];
+
+pub fn sha256(data: &[u8]) -> Digest {
+    Sha256::digest(data).into()
+}
+
+/// Lowercase hex of `digest`, for file names.
+pub fn hex(digest: &Digest) -> String {
+    digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digests_match_the_published_test_vectors() {
+        assert_eq!(
+            hex(&sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
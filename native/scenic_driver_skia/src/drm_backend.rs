@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 use std::fs::{File, OpenOptions};
 use std::os::fd::{AsFd, AsRawFd, BorrowedFd};
 use std::os::raw::c_void;
@@ -12,9 +12,10 @@ use std::time::{Duration, Instant};
 
 use drm::ClientCapability;
 use drm::Device as BasicDevice;
+use drm::buffer::{self, Buffer, DrmFourcc};
 use drm::control::{
     self, AtomicCommitFlags, Device as ControlDevice, Event, PlaneType, ResourceHandles, atomic,
-    connector, crtc, framebuffer, plane, property,
+    connector, crtc, dumbbuffer::DumbBuffer, framebuffer, plane, property,
 };
 use gbm::{
     AsRaw, BufferObject, BufferObjectFlags, Device as GbmDevice, Format as GbmFormat, Surface,
@@ -22,14 +23,32 @@ use gbm::{
 use glutin_egl_sys::egl;
 use glutin_egl_sys::egl::types::{EGLConfig, EGLContext, EGLDisplay, EGLSurface, EGLenum, EGLint};
 use libloading::Library;
-use skia_safe::{Color, Paint, PaintStyle, gpu::gl::FramebufferInfo};
+use skia_safe::{
+    AlphaType, Canvas, Color, ColorType, ImageInfo, Paint, PaintCap, PaintJoin, PaintStyle,
+    PathBuilder, PathDirection, Point, RRect, Rect, gpu::gl::FramebufferInfo, surfaces,
+};
 
-use crate::cursor::CursorState;
-use crate::drm_input::DrmInput;
-use crate::input::{InputEvent, InputQueue, notify_input_ready};
+use crate::backend_probe::DEFAULT_CARD;
+use crate::cursor::{
+    CursorImage, CursorImageSlot, CursorRegions, CursorShape, CursorState, PointerAccel,
+};
+use crate::dev_overlay::PointerTracker;
+use crate::drm_input::{DrmInput, DrmInputThread};
+use crate::flight_recorder::log_line;
+use crate::gpu_info::GpuInfo;
+use crate::input::{
+    DeviceClassMasks, InputEvent, InputQueue, ViewportInfo, notify_drm_session,
+    notify_frame_presented, notify_input_ready,
+};
+use crate::input_transform::InputTransform;
+use crate::outputs::{self, OutputInfo};
+use crate::plane_props::{PlaneChange, PlaneProperties, PlaneProperty, PropertyKind};
 use crate::renderer::{RenderState, Renderer};
+use crate::state_lock::RenderStateLock;
+use crate::{monotonic_now_ms, poll_scheduled_scripts};
 
 const EGL_PLATFORM_GBM_KHR: EGLenum = 0x31D7;
+const HOTPLUG_INTERVAL: Duration = Duration::from_millis(750);
 
 struct Card(File);
 
@@ -52,20 +71,38 @@ struct EglState {
     egl: egl::Egl,
     _egl_lib: Library,
     display: EGLDisplay,
-    _context: EGLContext,
+    context: EGLContext,
     surface: EGLSurface,
 }
 
+impl EglState {
+    fn make_current(&self) -> Result<(), String> {
+        if unsafe {
+            self.egl
+                .MakeCurrent(self.display, self.surface, self.surface, self.context)
+        } == egl::FALSE
+        {
+            return Err("failed to make EGL context current".to_string());
+        }
+        Ok(())
+    }
+}
+
 struct CursorPlane {
     handle: plane::Handle,
     props: HashMap<String, property::Info>,
     fb: framebuffer::Handle,
-    _bo: BufferObject<()>,
+    bo: BufferObject<()>,
     size: (u32, u32),
+    /// Shape currently written to `bo` and its hotspot.
+    shape: CursorShape,
+    hotspot: (i32, i32),
+    /// Serial of the custom cursor image written to `bo`, if any.
+    image: Option<u64>,
 }
 
 fn open_card(card_path: Option<&str>) -> Result<Card, String> {
-    let card_path = card_path.unwrap_or("/dev/dri/card0");
+    let card_path = card_path.unwrap_or(DEFAULT_CARD);
 
     let fd = OpenOptions::new()
         .read(true)
@@ -76,6 +113,62 @@ fn open_card(card_path: Option<&str>) -> Result<Card, String> {
     Ok(Card(fd))
 }
 
+const ACTIVE_VT_PATH: &str = "/sys/class/tty/tty0/active";
+
+/// Name of the foreground virtual terminal (for example `tty1`), if the kernel exposes it.
+fn active_vt() -> Option<String> {
+    std::fs::read_to_string(ACTIVE_VT_PATH)
+        .ok()
+        .map(|vt| vt.trim().to_string())
+        .filter(|vt| !vt.is_empty())
+}
+
+fn session_active(session_vt: &Option<String>) -> bool {
+    session_vt.is_none() || active_vt() == *session_vt
+}
+
+fn notify_session(input_events: &Arc<Mutex<InputQueue>>, active: bool) {
+    let target = input_events.lock().ok().and_then(|queue| queue.target());
+    if let Some(pid) = target {
+        notify_drm_session(pid, active);
+    }
+}
+
+/// Blocks while another virtual terminal is in the foreground.
+///
+/// DRM master is dropped so the other session can take the display, and reacquired once
+/// our VT is active again. Returns `false` if the backend was stopped while waiting.
+fn wait_for_session(
+    card: &Card,
+    session_vt: &Option<String>,
+    stop: &AtomicBool,
+    input_events: &Arc<Mutex<InputQueue>>,
+) -> bool {
+    if session_active(session_vt) {
+        return true;
+    }
+
+    log_line!("DRM session inactive; pausing rendering");
+    if let Err(e) = card.release_master_lock() {
+        log_line!("DRM drop master failed: {e}");
+    }
+    notify_session(input_events, false);
+
+    while !session_active(session_vt) {
+        if stop.load(Ordering::Relaxed) {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(250));
+    }
+
+    if let Err(e) = card.acquire_master_lock() {
+        log_line!("DRM set master failed: {e}");
+    }
+    log_line!("DRM session active; resuming rendering");
+    notify_session(input_events, true);
+    true
+}
+
 fn mode_distance(mode: &control::Mode, requested: (u32, u32)) -> i64 {
     let (width, height) = mode.size();
     let dx = width as i64 - requested.0 as i64;
@@ -136,6 +229,62 @@ fn first_connected_connector(
     Err("no connected DRM connectors found".into())
 }
 
+/// A connector as found while setting up outputs, with the CRTCs its encoders can drive.
+struct DrmOutput {
+    info: OutputInfo,
+    connector: connector::Handle,
+    /// Preferred mode, `None` while nothing is connected.
+    mode: Option<control::Mode>,
+    crtcs: Vec<crtc::Handle>,
+}
+
+/// Every connector of the card, in the order the kernel lists them.
+fn drm_outputs(card: &Card, resources: &ResourceHandles) -> Result<Vec<DrmOutput>, String> {
+    let mut found = Vec::new();
+    for handle in resources.connectors() {
+        let info = card
+            .get_connector(*handle, false)
+            .map_err(|e| format!("failed to read connector {handle:?}: {e}"))?;
+        let connected = info.state() == connector::State::Connected;
+        let mode = info.modes().first().copied().filter(|_| connected);
+        let mut crtcs = Vec::new();
+        for encoder in info.encoders() {
+            let Ok(encoder) = card.get_encoder(*encoder) else {
+                continue;
+            };
+            for crtc in resources.filter_crtcs(encoder.possible_crtcs()) {
+                if !crtcs.contains(&crtc) {
+                    crtcs.push(crtc);
+                }
+            }
+        }
+        let (width, height) = mode.map(|mode| mode.size()).unwrap_or_default();
+        found.push(DrmOutput {
+            info: OutputInfo {
+                name: outputs::output_name(info.interface().as_str(), info.interface_id()),
+                connected,
+                width: width as u32,
+                height: height as u32,
+                refresh: mode.map(|mode| mode.vrefresh()).unwrap_or(0),
+                active: false,
+            },
+            connector: *handle,
+            mode,
+            crtcs,
+        });
+    }
+    Ok(found)
+}
+
+/// Connectors with a display attached, for noticing displays plugged in or out.
+fn connected_connectors(found: &[DrmOutput]) -> Vec<connector::Handle> {
+    found
+        .iter()
+        .filter(|output| output.info.connected)
+        .map(|output| output.connector)
+        .collect()
+}
+
 fn is_primary_plane(card: &Card, plane: plane::Handle) -> Result<bool, String> {
     let props = card
         .get_properties(plane)
@@ -226,7 +375,8 @@ fn find_cursor_plane(
         if !compatible_crtcs.contains(&crtc_handle) {
             continue;
         }
-        if is_cursor_plane(card, plane)? {
+        // A plane whose type can't be read is skipped instead of failing cursor setup.
+        if is_cursor_plane(card, plane).unwrap_or(false) {
             compatible.push(plane);
         }
     }
@@ -279,6 +429,158 @@ fn draw_cursor_bitmap(size: u32) -> Vec<u8> {
     data
 }
 
+/// Hotspot of a cursor plane image, in pixels from its top-left corner.
+fn cursor_hotspot(shape: CursorShape) -> (i32, i32) {
+    match shape {
+        CursorShape::Default => (0, 0),
+        CursorShape::Pointer => (8, 1),
+        _ => (12, 12),
+    }
+}
+
+/// Returns the BGRA pixels and hotspot of the cursor plane image for `shape`.
+fn cursor_image(shape: CursorShape, size: u32) -> Result<(Vec<u8>, (i32, i32)), String> {
+    let hotspot = cursor_hotspot(shape);
+    if shape == CursorShape::Default {
+        return Ok((draw_cursor_bitmap(size), hotspot));
+    }
+    let info = ImageInfo::new(
+        (size as i32, size as i32),
+        ColorType::BGRA8888,
+        AlphaType::Premul,
+        None,
+    );
+    let mut surface = surfaces::raster(&info, None, None)
+        .ok_or_else(|| "failed to create cursor surface".to_string())?;
+    let canvas = surface.canvas();
+    canvas.clear(Color::TRANSPARENT);
+    canvas.translate((hotspot.0 as f32, hotspot.1 as f32));
+    draw_cursor_shape(canvas, shape);
+    let mut data = vec![0u8; (size * size * 4) as usize];
+    if !surface.read_pixels(&info, &mut data, info.min_row_bytes(), (0, 0)) {
+        return Err("failed to read cursor image".to_string());
+    }
+    Ok((data, hotspot))
+}
+
+/// Returns the BGRA pixels and hotspot of the cursor plane image for a custom cursor.
+fn custom_cursor_image(image: &CursorImage, size: u32) -> Result<(Vec<u8>, (i32, i32)), String> {
+    let pixels = image
+        .to_image()
+        .ok_or_else(|| "failed to decode cursor image".to_string())?;
+    let info = ImageInfo::new(
+        (size as i32, size as i32),
+        ColorType::BGRA8888,
+        AlphaType::Premul,
+        None,
+    );
+    let mut surface = surfaces::raster(&info, None, None)
+        .ok_or_else(|| "failed to create cursor surface".to_string())?;
+    let canvas = surface.canvas();
+    canvas.clear(Color::TRANSPARENT);
+    canvas.draw_image(&pixels, (0.0, 0.0), None);
+    let mut data = vec![0u8; (size * size * 4) as usize];
+    if !surface.read_pixels(&info, &mut data, info.min_row_bytes(), (0, 0)) {
+        return Err("failed to read cursor image".to_string());
+    }
+    Ok((data, (image.hotspot.0 as i32, image.hotspot.1 as i32)))
+}
+
+fn polyline(path: &mut PathBuilder, points: &[(f32, f32)]) {
+    path.move_to(points[0]);
+    for point in &points[1..] {
+        path.line_to(*point);
+    }
+}
+
+/// Draws `shape` with its hotspot at the canvas origin: the default cursor is a dot,
+/// the others are white shapes with a black outline.
+fn draw_cursor_shape(canvas: &Canvas, shape: CursorShape) {
+    let mut fill = Paint::default();
+    fill.set_anti_alias(true);
+    fill.set_color(Color::WHITE);
+    let mut outline = fill.clone();
+    outline.set_style(PaintStyle::Stroke);
+    outline.set_stroke_width(1.5);
+    outline.set_color(Color::BLACK);
+    let filled = |rrects: &[RRect]| {
+        for rrect in rrects {
+            canvas.draw_rrect(rrect, &fill);
+            canvas.draw_rrect(rrect, &outline);
+        }
+    };
+    let rrect =
+        |x, y, w, h, radius| RRect::new_rect_xy(Rect::from_xywh(x, y, w, h), radius, radius);
+
+    let mut lines = PathBuilder::new();
+    match shape {
+        CursorShape::Default => {
+            let mut dot = fill.clone();
+            dot.set_color(Color::from_argb(240, 255, 255, 255));
+            canvas.draw_circle((0.0, 0.0), 4.0, &dot);
+            dot.set_style(PaintStyle::Stroke);
+            dot.set_stroke_width(1.0);
+            dot.set_color(Color::from_argb(200, 0, 0, 0));
+            canvas.draw_circle((0.0, 0.0), 4.0, &dot);
+        }
+        CursorShape::Pointer => filled(&[
+            rrect(-2.0, 0.0, 4.0, 12.0, 2.0),
+            rrect(-6.0, 7.0, 13.0, 12.0, 3.0),
+        ]),
+        CursorShape::Grab => filled(&[
+            rrect(-7.0, -8.0, 3.5, 7.0, 1.75),
+            rrect(-3.5, -9.0, 3.5, 8.0, 1.75),
+            rrect(0.0, -9.0, 3.5, 8.0, 1.75),
+            rrect(3.5, -8.0, 3.5, 7.0, 1.75),
+            rrect(-7.0, -4.0, 14.0, 12.0, 4.0),
+        ]),
+        CursorShape::Grabbing => filled(&[rrect(-7.0, -5.0, 14.0, 11.0, 5.0)]),
+        CursorShape::Text => {
+            polyline(&mut lines, &[(-3.0, -9.0), (3.0, -9.0)]);
+            polyline(&mut lines, &[(0.0, -9.0), (0.0, 9.0)]);
+            polyline(&mut lines, &[(-3.0, 9.0), (3.0, 9.0)]);
+        }
+        CursorShape::Crosshair => {
+            polyline(&mut lines, &[(-9.0, 0.0), (9.0, 0.0)]);
+            polyline(&mut lines, &[(0.0, -9.0), (0.0, 9.0)]);
+        }
+        CursorShape::Move | CursorShape::EwResize | CursorShape::NsResize => {
+            if shape != CursorShape::NsResize {
+                polyline(&mut lines, &[(-9.0, 0.0), (9.0, 0.0)]);
+                polyline(&mut lines, &[(-6.0, -3.0), (-9.0, 0.0), (-6.0, 3.0)]);
+                polyline(&mut lines, &[(6.0, -3.0), (9.0, 0.0), (6.0, 3.0)]);
+            }
+            if shape != CursorShape::EwResize {
+                polyline(&mut lines, &[(0.0, -9.0), (0.0, 9.0)]);
+                polyline(&mut lines, &[(-3.0, -6.0), (0.0, -9.0), (3.0, -6.0)]);
+                polyline(&mut lines, &[(-3.0, 6.0), (0.0, 9.0), (3.0, 6.0)]);
+            }
+        }
+        CursorShape::NotAllowed => {
+            lines.add_circle(Point::new(0.0, 0.0), 8.0, PathDirection::CW);
+            polyline(&mut lines, &[(-5.5, -5.5), (5.5, 5.5)]);
+        }
+        CursorShape::Wait => {
+            lines.add_circle(Point::new(0.0, 0.0), 8.0, PathDirection::CW);
+            polyline(&mut lines, &[(0.0, -5.0), (0.0, 0.0), (4.0, 0.0)]);
+        }
+    }
+
+    // Line cursors are a black stroke over a wider white one, visible on any background.
+    let lines = lines.detach();
+    if !lines.is_empty() {
+        let mut halo = outline.clone();
+        halo.set_stroke_cap(PaintCap::Round);
+        halo.set_stroke_join(PaintJoin::Round);
+        halo.set_stroke_width(4.0);
+        halo.set_color(Color::WHITE);
+        canvas.draw_path(&lines, &halo);
+        halo.set_stroke_width(2.0);
+        halo.set_color(Color::BLACK);
+        canvas.draw_path(&lines, &halo);
+    }
+}
+
 fn create_cursor_plane<T: AsFd>(
     card: &Card,
     gbm_device: &GbmDevice<T>,
@@ -315,18 +617,37 @@ fn create_cursor_plane<T: AsFd>(
         handle,
         props,
         fb,
-        _bo: bo,
+        bo,
         size,
+        shape: CursorShape::Default,
+        hotspot: cursor_hotspot(CursorShape::Default),
+        image: None,
     }))
 }
 
 fn update_cursor_plane(
     card: &Card,
     crtc_handle: crtc::Handle,
-    plane: &CursorPlane,
+    plane: &mut CursorPlane,
     cursor: CursorState,
+    cursor_images: &CursorImageSlot,
     screen_size: (u32, u32),
 ) -> Result<(), String> {
+    if cursor.visible && (cursor.shape != plane.shape || cursor.image != plane.image) {
+        let custom = cursor.image.and_then(|_| cursor_images.current());
+        let (data, hotspot) = match &custom {
+            Some(image) => custom_cursor_image(image, plane.size.0)?,
+            None => cursor_image(cursor.shape, plane.size.0)?,
+        };
+        plane
+            .bo
+            .write(&data)
+            .map_err(|e| format!("failed to write cursor bo: {e}"))?;
+        plane.shape = cursor.shape;
+        plane.hotspot = hotspot;
+        plane.image = custom.map(|image| image.serial);
+    }
+
     let mut req = atomic::AtomicModeReq::new();
     if cursor.visible {
         let (screen_w, screen_h) = screen_size;
@@ -334,8 +655,8 @@ fn update_cursor_plane(
         let min_y = -(plane.size.1 as i64) + 1;
         let max_x = screen_w.saturating_sub(1) as i64;
         let max_y = screen_h.saturating_sub(1) as i64;
-        let x = (cursor.pos.0.round() as i64).clamp(min_x, max_x);
-        let y = (cursor.pos.1.round() as i64).clamp(min_y, max_y);
+        let x = (cursor.pos.0.round() as i64 - plane.hotspot.0 as i64).clamp(min_x, max_x);
+        let y = (cursor.pos.1.round() as i64 - plane.hotspot.1 as i64).clamp(min_y, max_y);
         req.add_property(
             plane.handle,
             prop_handle(&plane.props, "FB_ID")?,
@@ -473,24 +794,107 @@ fn add_plane_geometry(
     Ok(())
 }
 
-fn wait_for_page_flip(card: &Card) -> Result<(), String> {
-    loop {
+/// What the kernel reports about a plane's properties, for `set_plane_property` to
+/// validate values against.
+fn describe_plane_properties(plane_props: &HashMap<String, property::Info>) -> Vec<PlaneProperty> {
+    plane_props
+        .iter()
+        .map(|(name, info)| {
+            let kind = match info.value_type() {
+                property::ValueType::UnsignedRange(min, max) => PropertyKind::Unsigned { min, max },
+                property::ValueType::SignedRange(min, max) => PropertyKind::Signed { min, max },
+                property::ValueType::Boolean => PropertyKind::Boolean,
+                property::ValueType::Enum(values) => PropertyKind::Enum(
+                    values
+                        .values()
+                        .1
+                        .iter()
+                        .map(|value| (value.name().to_string_lossy().into_owned(), value.value()))
+                        .collect(),
+                ),
+                _ => PropertyKind::Other,
+            };
+            PlaneProperty {
+                name: name.clone(),
+                kind,
+                immutable: !info.mutable(),
+            }
+        })
+        .collect()
+}
+
+/// Adds plane property values set from Elixir to `req`.
+fn add_plane_values(
+    req: &mut atomic::AtomicModeReq,
+    plane: plane::Handle,
+    plane_props: &HashMap<String, property::Info>,
+    values: &[(String, u64)],
+) -> Result<(), String> {
+    for (name, value) in values {
+        req.add_property(
+            plane,
+            prop_handle(plane_props, name)?,
+            property::Value::Unknown(*value),
+        );
+    }
+    Ok(())
+}
+
+/// Adds the plane property changes queued since the last commit to `req`, for the
+/// outputs in `flipping`. Changes for outputs this commit does not flip are queued
+/// again, since touching their plane would add a page flip event nobody waits for.
+/// Returns the changes added.
+fn add_plane_changes(
+    req: &mut atomic::AtomicModeReq,
+    plane_properties: &PlaneProperties,
+    flipping: &[(&str, plane::Handle, &HashMap<String, property::Info>)],
+) -> Result<Vec<PlaneChange>, String> {
+    let (added, later): (Vec<PlaneChange>, Vec<PlaneChange>) = plane_properties
+        .take_pending()
+        .into_iter()
+        .partition(|change| flipping.iter().any(|(name, ..)| *name == change.output));
+    plane_properties.requeue(later);
+    for change in &added {
+        if let Some((_, plane, plane_props)) =
+            flipping.iter().find(|(name, ..)| *name == change.output)
+        {
+            req.add_property(
+                *plane,
+                prop_handle(plane_props, &change.name)?,
+                property::Value::Unknown(change.value),
+            );
+        }
+    }
+    Ok(added)
+}
+
+fn wait_for_page_flip(card: &Card) -> Result<u64, String> {
+    wait_for_page_flips(card, 1)
+}
+
+/// Waits for `count` page flip events: one per CRTC flipped by the last commit. Returns
+/// when the last flip happened, in microseconds on `CLOCK_MONOTONIC`.
+fn wait_for_page_flips(card: &Card, mut count: usize) -> Result<u64, String> {
+    let mut flipped_us = 0;
+    while count > 0 {
         let events = card
             .receive_events()
             .map_err(|e| format!("failed to read DRM events: {e}"))?;
         for event in events {
-            if matches!(event, Event::PageFlip(_)) {
-                return Ok(());
+            if let Event::PageFlip(flip) = event {
+                count = count.saturating_sub(1);
+                flipped_us = flipped_us.max(flip.duration.as_micros() as u64);
             }
         }
     }
+    Ok(flipped_us)
 }
 
 fn is_ebusy(err: &str) -> bool {
     err.contains("Device or resource busy") || err.contains("EBUSY")
 }
 
-fn load_egl() -> Result<(Library, egl::Egl), String> {
+pub(crate) fn load_egl() -> Result<(Library, egl::Egl), String> {
     let lib = unsafe { Library::new("libEGL.so.1") }
         .map_err(|e| format!("failed to load libEGL: {e}"))?;
     let get_proc = unsafe {
@@ -643,54 +1047,707 @@ fn create_renderer(
     Ok(Renderer::new(dimensions, fb_info, gr_context, 0, 0))
 }
 
+/// Records the GL strings while the EGL context created by `init_egl` is current.
+fn publish_gpu_info(
+    render_state: &Arc<RenderStateLock>,
+    egl_state: &EglState,
+    config: &DrmRunConfig,
+) {
+    let extensions = unsafe {
+        egl_state
+            .egl
+            .QueryString(egl_state.display, egl::EXTENSIONS as EGLint)
+    };
+    let extensions = if extensions.is_null() {
+        String::new()
+    } else {
+        unsafe { CStr::from_ptr(extensions) }
+            .to_string_lossy()
+            .into_owned()
+    };
+    unsafe { GpuInfo::query_gl("drm") }
+        .with_egl_extensions(extensions.split_whitespace())
+        .with_nodes(config.card_path.as_deref(), config.render_node.as_deref())
+        .publish(render_state);
+}
+
+/// A buffer drawn on a separate render node, imported into the card through its dma-buf.
+struct ImportedBuffer {
+    size: (u32, u32),
+    pitch: u32,
+    handle: buffer::Handle,
+}
+
+impl Buffer for ImportedBuffer {
+    fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    fn format(&self) -> DrmFourcc {
+        DrmFourcc::Xrgb8888
+    }
+
+    fn pitch(&self) -> u32 {
+        self.pitch
+    }
+
+    fn handle(&self) -> buffer::Handle {
+        self.handle
+    }
+}
+
+/// GBM usage of the surfaces frames are drawn into. Buffers drawn on a separate render
+/// node are linear, since the display controller of another GPU cannot read its tiling.
+fn surface_flags(cross_device: bool) -> BufferObjectFlags {
+    if cross_device {
+        BufferObjectFlags::RENDERING | BufferObjectFlags::LINEAR
+    } else {
+        BufferObjectFlags::SCANOUT | BufferObjectFlags::RENDERING
+    }
+}
+
+/// Framebuffer on `card` for a buffer of the render surface. With `cross_device` the
+/// buffer belongs to a separate render node and is imported into the card first.
 fn framebuffer_for_bo(
     card: &Card,
     cache: &mut HashMap<u32, framebuffer::Handle>,
     bo: &BufferObject<()>,
+    cross_device: bool,
 ) -> Result<framebuffer::Handle, String> {
     let handle = unsafe { bo.handle().u32_ };
     if let Some(existing) = cache.get(&handle).copied() {
         return Ok(existing);
     }
 
-    let framebuffer = card
-        .add_framebuffer(bo, 24, 32)
-        .map_err(|e| format!("failed to create framebuffer: {e}"))?;
+    let framebuffer = if cross_device {
+        let fd = bo
+            .fd()
+            .map_err(|e| format!("failed to export render buffer: {e}"))?;
+        let imported = ImportedBuffer {
+            size: (bo.width(), bo.height()),
+            pitch: bo.stride(),
+            handle: card
+                .prime_fd_to_buffer(fd.as_fd())
+                .map_err(|e| format!("failed to import render buffer: {e}"))?,
+        };
+        let framebuffer = card.add_framebuffer(&imported, 24, 32);
+        // The framebuffer keeps the imported buffer alive on its own.
+        let _ = card.close_buffer(imported.handle);
+        framebuffer
+    } else {
+        card.add_framebuffer(bo, 24, 32)
+    }
+    .map_err(|e| format!("failed to create framebuffer: {e}"))?;
     cache.insert(handle, framebuffer);
     Ok(framebuffer)
 }
 
-fn cursor_snapshot(cursor_state: &Arc<Mutex<CursorState>>) -> CursorState {
-    cursor_state
+/// Copies the cursor state and resolves the shape of the hit region under the pointer.
+fn cursor_snapshot(config: &DrmRunConfig) -> CursorState {
+    let mut cursor = config
+        .cursor_state
         .lock()
         .map(|state| *state)
-        .unwrap_or_else(|_| CursorState::new())
+        .unwrap_or_else(|_| CursorState::new());
+    let (x, y) = cursor.pos;
+    let (x, y) = config
+        .input_transform
+        .lock()
+        .map_or((x, y), |transform| transform.map_point(x, y));
+    config.pointer.update(x, y);
+    cursor.shape = config.cursor_regions.shape_at(x, y);
+    // A custom image replaces only the default cursor; region shapes still win.
+    cursor.image = match cursor.shape {
+        CursorShape::Default => config.cursor_image.current().map(|image| image.serial),
+        _ => None,
+    };
+    cursor
 }
 
-fn draw_software_cursor(renderer: &mut Renderer, cursor_pos: (f32, f32), screen_size: (u32, u32)) {
+fn draw_software_cursor(
+    renderer: &mut Renderer,
+    cursor: CursorState,
+    cursor_images: &CursorImageSlot,
+    screen_size: (u32, u32),
+) {
     let (width, height) = screen_size;
-    let x = cursor_pos.0.clamp(0.0, width.saturating_sub(1) as f32);
-    let y = cursor_pos.1.clamp(0.0, height.saturating_sub(1) as f32);
+    let x = cursor.pos.0.clamp(0.0, width.saturating_sub(1) as f32);
+    let y = cursor.pos.1.clamp(0.0, height.saturating_sub(1) as f32);
+    let custom = cursor
+        .image
+        .and_then(|_| cursor_images.current())
+        .and_then(|image| image.to_image().map(|pixels| (image.hotspot, pixels)));
 
     let canvas = renderer.surface_mut().canvas();
-    let mut fill = Paint::default();
-    fill.set_anti_alias(true);
-    fill.set_color(Color::from_argb(240, 255, 255, 255));
-    canvas.draw_circle((x, y), 4.0, &fill);
-
-    let mut stroke = Paint::default();
-    stroke.set_anti_alias(true);
-    stroke.set_style(PaintStyle::Stroke);
-    stroke.set_stroke_width(1.0);
-    stroke.set_color(Color::from_argb(200, 0, 0, 0));
-    canvas.draw_circle((x, y), 4.0, &stroke);
+    canvas.save();
+    canvas.translate((x, y));
+    match custom {
+        Some((hotspot, pixels)) => {
+            canvas.draw_image(&pixels, (-(hotspot.0 as f32), -(hotspot.1 as f32)), None);
+        }
+        None => draw_cursor_shape(canvas, cursor.shape),
+    }
+    canvas.restore();
+}
+
+/// Returns `true` when a display was plugged in or out since `connected` was read, so
+/// the outputs driven next to the primary one have to be set up again.
+fn connectors_changed(card: &Card, connected: &[connector::Handle]) -> bool {
+    let Ok(resources) = card.resource_handles() else {
+        return true;
+    };
+    match drm_outputs(card, &resources) {
+        Ok(found) => connected_connectors(&found) != connected,
+        Err(_) => true,
+    }
+}
+
+/// Returns `true` when the session, connector, CRTC or mode no longer match the output
+/// being driven, so the caller has to set it up again.
+fn output_changed(
+    card: &Card,
+    session_vt: &Option<String>,
+    requested_size: Option<(u32, u32)>,
+    connector: connector::Handle,
+    crtc_handle: crtc::Handle,
+    dimensions: (u32, u32),
+) -> bool {
+    if !session_active(session_vt) {
+        return true;
+    }
+    let Ok(resources) = card.resource_handles() else {
+        return true;
+    };
+    match first_connected_connector(card, &resources, requested_size) {
+        Ok((next_connector, next_mode, next_crtc)) => {
+            let (width, height) = next_mode.size();
+            next_connector != connector
+                || next_crtc != crtc_handle
+                || (width as u32, height as u32) != dimensions
+        }
+        Err(_) => true,
+    }
+}
+
+/// The connector, CRTC and primary plane driven by the raster scanout fallback.
+struct ScanoutOutput {
+    name: String,
+    connector: connector::Handle,
+    crtc: crtc::Handle,
+    plane: plane::Handle,
+    mode: control::Mode,
+    con_props: HashMap<String, property::Info>,
+    crtc_props: HashMap<String, property::Info>,
+    plane_props: HashMap<String, property::Info>,
+}
+
+/// Dumb buffers with their framebuffers, released when dropped.
+struct DumbBuffers<'a> {
+    card: &'a Card,
+    buffers: Vec<(DumbBuffer, framebuffer::Handle)>,
+}
+
+impl<'a> DumbBuffers<'a> {
+    fn new(card: &'a Card, dimensions: (u32, u32), count: usize) -> Result<Self, String> {
+        let mut buffers = Self {
+            card,
+            buffers: Vec::with_capacity(count),
+        };
+        for _ in 0..count {
+            let buffer = card
+                .create_dumb_buffer(dimensions, DrmFourcc::Xrgb8888, 32)
+                .map_err(|e| format!("failed to create dumb buffer: {e}"))?;
+            match card.add_framebuffer(&buffer, 24, 32) {
+                Ok(fb) => buffers.buffers.push((buffer, fb)),
+                Err(e) => {
+                    let _ = card.destroy_dumb_buffer(buffer);
+                    return Err(format!("failed to create framebuffer: {e}"));
+                }
+            }
+        }
+        Ok(buffers)
+    }
+}
+
+impl Drop for DumbBuffers<'_> {
+    fn drop(&mut self) {
+        for (buffer, fb) in self.buffers.drain(..) {
+            let _ = self.card.destroy_framebuffer(fb);
+            let _ = self.card.destroy_dumb_buffer(buffer);
+        }
+    }
+}
+
+/// Renders the scene and software cursor on the CPU and copies it into `buffer`.
+fn draw_raster_frame(
+    card: &Card,
+    renderer: &mut Renderer,
+    buffer: &mut DumbBuffer,
+    render_state: &Arc<RenderStateLock>,
+    cursor: CursorState,
+    cursor_images: &CursorImageSlot,
+) -> Result<(), String> {
+    let (width, height) = buffer.size();
+    if let Ok(state) = render_state.lock() {
+        renderer.redraw(&state);
+    }
+    if cursor.visible {
+        draw_software_cursor(renderer, cursor, cursor_images, (width, height));
+    }
+
+    let info = ImageInfo::new(
+        (width as i32, height as i32),
+        ColorType::BGRA8888,
+        AlphaType::Premul,
+        None,
+    );
+    let pitch = buffer.pitch() as usize;
+    let mut mapping = card
+        .map_dumb_buffer(buffer)
+        .map_err(|e| format!("failed to map dumb buffer: {e}"))?;
+    if !renderer
+        .surface_mut()
+        .read_pixels(&info, mapping.as_mut(), pitch, (0, 0))
+    {
+        return Err("failed to read raster frame".to_string());
+    }
+    Ok(())
+}
+
+/// Scans out CPU-rendered frames from double-buffered DRM dumb buffers.
+///
+/// Used when EGL/GLES can't be initialized so the UI still appears. Returns when the
+/// backend is stopped or the output changes; the caller then sets up the output again.
+fn run_raster_scanout(
+    card: &Card,
+    output: &ScanoutOutput,
+    session_vt: &Option<String>,
+    stop: &AtomicBool,
+    dirty: &AtomicBool,
+    render_state: &Arc<RenderStateLock>,
+    config: &DrmRunConfig,
+) -> Result<(), String> {
+    let (width, height) = output.mode.size();
+    let dimensions = (width as u32, height as u32);
+    let mut buffers = DumbBuffers::new(card, dimensions, 2)?;
+    let image_info = ImageInfo::new(
+        (width as i32, height as i32),
+        ColorType::BGRA8888,
+        AlphaType::Premul,
+        None,
+    );
+    let surface = surfaces::raster(&image_info, None, None)
+        .ok_or_else(|| "failed to create raster surface".to_string())?;
+    let mut renderer = Renderer::from_surface(surface, None);
+    renderer.set_output(&output.name, false);
+    let plane_properties = render_state
+        .lock()
+        .map(|state| Arc::clone(&state.plane_properties))
+        .map_err(|_| "render state lock poisoned".to_string())?;
+    plane_properties.publish(HashMap::from([(
+        output.name.clone(),
+        describe_plane_properties(&output.plane_props),
+    )]));
+
+    let mut cursor = cursor_snapshot(config);
+    if let Ok(state) = render_state.lock() {
+        state.redraws.add_resize();
+    }
+    let (buffer, fb) = &mut buffers.buffers[0];
+    draw_raster_frame(
+        card,
+        &mut renderer,
+        buffer,
+        render_state,
+        cursor,
+        &config.cursor_image,
+    )?;
+    let mode_blob = card
+        .create_property_blob(&output.mode)
+        .map_err(|e| format!("failed to create mode blob: {e}"))?;
+    let mut atomic_req = atomic::AtomicModeReq::new();
+    atomic_req.add_property(
+        output.connector,
+        prop_handle(&output.con_props, "CRTC_ID")?,
+        property::Value::CRTC(Some(output.crtc)),
+    );
+    atomic_req.add_property(
+        output.crtc,
+        prop_handle(&output.crtc_props, "MODE_ID")?,
+        mode_blob,
+    );
+    atomic_req.add_property(
+        output.crtc,
+        prop_handle(&output.crtc_props, "ACTIVE")?,
+        property::Value::Boolean(true),
+    );
+    add_plane_properties(
+        &mut atomic_req,
+        output.plane,
+        &output.plane_props,
+        output.crtc,
+        *fb,
+    )?;
+    add_plane_geometry(
+        &mut atomic_req,
+        output.plane,
+        &output.plane_props,
+        &output.mode,
+    )?;
+    add_plane_values(
+        &mut atomic_req,
+        output.plane,
+        &output.plane_props,
+        &plane_properties.values(&output.name),
+    )?;
+    card.atomic_commit(AtomicCommitFlags::ALLOW_MODESET, atomic_req)
+        .map_err(|e| format!("failed to set mode: {e}"))?;
+
+    let mut front = 0;
+    let mut next_hotplug_check = Instant::now() + HOTPLUG_INTERVAL;
+    loop {
+        if stop.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        if Instant::now() >= next_hotplug_check {
+            if output_changed(
+                card,
+                session_vt,
+                config.requested_size,
+                output.connector,
+                output.crtc,
+                dimensions,
+            ) {
+                return Ok(());
+            }
+            next_hotplug_check = Instant::now() + HOTPLUG_INTERVAL;
+        }
+
+        let last_cursor = cursor;
+        cursor = cursor_snapshot(config);
+        if config.pointer.take_moved() {
+            dirty.store(true, Ordering::Relaxed);
+        }
+        let cursor_dirty = cursor.visible != last_cursor.visible
+            || (cursor.visible
+                && (cursor.pos != last_cursor.pos
+                    || cursor.shape != last_cursor.shape
+                    || cursor.image != last_cursor.image));
+        if poll_scheduled_scripts(render_state).0 {
+            dirty.store(true, Ordering::Relaxed);
+        }
+        if dirty.swap(false, Ordering::Relaxed) || cursor_dirty {
+            if cursor_dirty && let Ok(state) = render_state.lock() {
+                state.redraws.add_cursor();
+            }
+            let back = 1 - front;
+            let (buffer, fb) = &mut buffers.buffers[back];
+            draw_raster_frame(
+                card,
+                &mut renderer,
+                buffer,
+                render_state,
+                cursor,
+                &config.cursor_image,
+            )?;
+
+            let mut flip_req = atomic::AtomicModeReq::new();
+            add_plane_properties(
+                &mut flip_req,
+                output.plane,
+                &output.plane_props,
+                output.crtc,
+                *fb,
+            )?;
+            let changes = add_plane_changes(
+                &mut flip_req,
+                &plane_properties,
+                &[(output.name.as_str(), output.plane, &output.plane_props)],
+            )?;
+            if let Err(e) = card.atomic_commit(
+                AtomicCommitFlags::NONBLOCK | AtomicCommitFlags::PAGE_FLIP_EVENT,
+                flip_req,
+            ) {
+                let err = e.to_string();
+                if is_ebusy(&err) {
+                    plane_properties.requeue(changes);
+                    dirty.store(true, Ordering::Relaxed);
+                    std::thread::sleep(Duration::from_millis(2));
+                    continue;
+                }
+                if !changes.is_empty() {
+                    // The frame is flipped again without the values the kernel refused.
+                    plane_properties.reject(&changes, &err);
+                    dirty.store(true, Ordering::Relaxed);
+                    continue;
+                }
+                return Err(err);
+            }
+            let flipped_us = wait_for_page_flip(card)?;
+            notify_frame_presented(&config.input_events, flipped_us);
+            front = back;
+        }
+        std::thread::sleep(Duration::from_millis(4));
+    }
+}
+
+/// An output driven next to the primary one, on a CRTC of its own, with its own GBM
+/// surface, EGL context and renderer. It draws the scene from the script assigned to it
+/// with `set_output_root`; input, the cursor and the raster fallback stay on the
+/// primary output.
+struct SecondaryOutput<'a> {
+    card: &'a Card,
+    name: String,
+    crtc: crtc::Handle,
+    plane: plane::Handle,
+    plane_props: HashMap<String, property::Info>,
+    renderer: Option<Renderer>,
+    framebuffers: HashMap<u32, framebuffer::Handle>,
+    current_bo: Option<BufferObject<()>>,
+    next_bo: Option<BufferObject<()>>,
+    gbm_surface: Surface<()>,
+    /// Frames are drawn on a separate render node and imported into the card.
+    cross_device: bool,
+    egl: EglState,
+}
+
+impl<'a> SecondaryOutput<'a> {
+    /// Sets the output up on `crtc`, draws its first frame and sets its mode.
+    fn start<T: AsFd>(
+        card: &'a Card,
+        gbm_device: &GbmDevice<T>,
+        cross_device: bool,
+        resources: &ResourceHandles,
+        output: &DrmOutput,
+        crtc: crtc::Handle,
+        render_state: &Arc<RenderStateLock>,
+    ) -> Result<Self, String> {
+        let mode = output
+            .mode
+            .ok_or_else(|| "connector has no modes".to_string())?;
+        let (width, height) = mode.size();
+        let dimensions = (width as u32, height as u32);
+        let plane = find_primary_plane(card, resources, crtc)?;
+        let con_props = card
+            .get_properties(output.connector)
+            .and_then(|props| props.as_hashmap(card))
+            .map_err(|e| format!("failed to read connector properties: {e}"))?;
+        let crtc_props = card
+            .get_properties(crtc)
+            .and_then(|props| props.as_hashmap(card))
+            .map_err(|e| format!("failed to read CRTC properties: {e}"))?;
+        let plane_props = card
+            .get_properties(plane)
+            .and_then(|props| props.as_hashmap(card))
+            .map_err(|e| format!("failed to read plane properties: {e}"))?;
+
+        let gbm_surface: Surface<()> = gbm_device
+            .create_surface(
+                dimensions.0,
+                dimensions.1,
+                GbmFormat::Xrgb8888,
+                surface_flags(cross_device),
+            )
+            .map_err(|e| format!("failed to create GBM surface: {e}"))?;
+        let (egl_lib, egl_api) = load_egl()?;
+        let (display, context, surface) = init_egl(
+            &egl_api,
+            gbm_device.as_raw() as *mut c_void,
+            gbm_surface.as_raw() as *mut c_void,
+        )?;
+        let mut secondary = Self {
+            card,
+            name: output.info.name.clone(),
+            crtc,
+            plane,
+            plane_props,
+            renderer: None,
+            framebuffers: HashMap::new(),
+            current_bo: None,
+            next_bo: None,
+            gbm_surface,
+            cross_device,
+            egl: EglState {
+                egl: egl_api,
+                _egl_lib: egl_lib,
+                display,
+                context,
+                surface,
+            },
+        };
+        let mut renderer = create_renderer(&secondary.egl.egl, dimensions)?;
+        renderer.set_output(&secondary.name, true);
+        secondary.renderer = Some(renderer);
+
+        let fb = {
+            let state = render_state
+                .lock()
+                .map_err(|_| "render state lock poisoned".to_string())?;
+            secondary.render(&state)?
+        };
+        secondary.flipped();
+        let mode_blob = card
+            .create_property_blob(&mode)
+            .map_err(|e| format!("failed to create mode blob: {e}"))?;
+        let mut atomic_req = atomic::AtomicModeReq::new();
+        atomic_req.add_property(
+            output.connector,
+            prop_handle(&con_props, "CRTC_ID")?,
+            property::Value::CRTC(Some(crtc)),
+        );
+        atomic_req.add_property(crtc, prop_handle(&crtc_props, "MODE_ID")?, mode_blob);
+        atomic_req.add_property(
+            crtc,
+            prop_handle(&crtc_props, "ACTIVE")?,
+            property::Value::Boolean(true),
+        );
+        add_plane_properties(&mut atomic_req, plane, &secondary.plane_props, crtc, fb)?;
+        add_plane_geometry(&mut atomic_req, plane, &secondary.plane_props, &mode)?;
+        let values = render_state
+            .lock()
+            .map(|state| state.plane_properties.values(&secondary.name))
+            .unwrap_or_default();
+        add_plane_values(&mut atomic_req, plane, &secondary.plane_props, &values)?;
+        card.atomic_commit(AtomicCommitFlags::ALLOW_MODESET, atomic_req)
+            .map_err(|e| format!("failed to set mode: {e}"))?;
+        Ok(secondary)
+    }
+
+    /// Draws the frame and returns the framebuffer to flip to.
+    fn render(&mut self, render_state: &RenderState) -> Result<framebuffer::Handle, String> {
+        self.egl.make_current()?;
+        let renderer = self
+            .renderer
+            .as_mut()
+            .ok_or_else(|| "renderer not initialized".to_string())?;
+        renderer.redraw_output(render_state);
+        if unsafe { self.egl.egl.SwapBuffers(self.egl.display, self.egl.surface) } == egl::FALSE {
+            return Err("eglSwapBuffers failed".to_string());
+        }
+        let bo = unsafe { self.gbm_surface.lock_front_buffer() }
+            .map_err(|e| format!("failed to lock front buffer: {e}"))?;
+        let fb = framebuffer_for_bo(self.card, &mut self.framebuffers, &bo, self.cross_device)?;
+        self.next_bo = Some(bo);
+        Ok(fb)
+    }
+
+    /// Keeps the buffer drawn last on screen and releases the one it replaced.
+    fn flipped(&mut self) {
+        if let Some(bo) = self.next_bo.take() {
+            self.current_bo = Some(bo);
+        }
+    }
+}
+
+impl Drop for SecondaryOutput<'_> {
+    fn drop(&mut self) {
+        // Skia releases its GL objects through whichever context is current.
+        let _ = self.egl.make_current();
+        drop(self.renderer.take());
+        unsafe {
+            self.egl.egl.MakeCurrent(
+                self.egl.display,
+                egl::NO_SURFACE,
+                egl::NO_SURFACE,
+                egl::NO_CONTEXT,
+            );
+            self.egl
+                .egl
+                .DestroySurface(self.egl.display, self.egl.surface);
+            self.egl
+                .egl
+                .DestroyContext(self.egl.display, self.egl.context);
+        }
+        for fb in self.framebuffers.values() {
+            let _ = self.card.destroy_framebuffer(*fb);
+        }
+    }
+}
+
+/// Starts every connected output other than the primary one on a CRTC of its own.
+/// Outputs without a free CRTC or that fail to start are left off and logged.
+fn start_secondary_outputs<'a, T: AsFd>(
+    card: &'a Card,
+    gbm_device: &GbmDevice<T>,
+    cross_device: bool,
+    resources: &ResourceHandles,
+    found: &mut [DrmOutput],
+    primary: (connector::Handle, crtc::Handle),
+    render_state: &Arc<RenderStateLock>,
+) -> Vec<SecondaryOutput<'a>> {
+    let indices: Vec<usize> = (0..found.len())
+        .filter(|&index| found[index].mode.is_some() && found[index].connector != primary.0)
+        .collect();
+    let candidates: Vec<Vec<crtc::Handle>> = indices
+        .iter()
+        .map(|&index| found[index].crtcs.clone())
+        .collect();
+    let crtcs = outputs::assign_crtcs(&candidates, &[primary.1]);
+
+    let mut started = Vec::new();
+    for (index, crtc) in indices.into_iter().zip(crtcs) {
+        let output = &mut found[index];
+        let Some(crtc) = crtc else {
+            log_line!(
+                "DRM output {} has no free CRTC; leaving it off",
+                output.info.name
+            );
+            continue;
+        };
+        match SecondaryOutput::start(
+            card,
+            gbm_device,
+            cross_device,
+            resources,
+            output,
+            crtc,
+            render_state,
+        ) {
+            Ok(secondary) => {
+                output.info.active = true;
+                started.push(secondary);
+            }
+            Err(e) => log_line!("DRM output {} unavailable: {e}", output.info.name),
+        }
+    }
+    started
+}
+
+/// Draws the frame on every secondary output and adds their flips to `req`.
+fn render_secondary_outputs(
+    render_state: &Arc<RenderStateLock>,
+    secondaries: &mut [SecondaryOutput<'_>],
+    req: &mut atomic::AtomicModeReq,
+) -> Result<(), String> {
+    if secondaries.is_empty() {
+        return Ok(());
+    }
+    let state = render_state
+        .lock()
+        .map_err(|_| "render state lock poisoned".to_string())?;
+    for output in secondaries.iter_mut() {
+        let fb = output
+            .render(&state)
+            .map_err(|e| format!("output {}: {e}", output.name))?;
+        add_plane_properties(req, output.plane, &output.plane_props, output.crtc, fb)?;
+    }
+    Ok(())
 }
 
 #[derive(Clone)]
 pub struct DrmRunConfig {
     pub requested_size: Option<(u32, u32)>,
+    pub input_events: Arc<Mutex<InputQueue>>,
     pub cursor_state: Arc<Mutex<CursorState>>,
+    pub pointer_accel: Arc<Mutex<PointerAccel>>,
+    pub device_masks: Arc<DeviceClassMasks>,
+    pub input_transform: Arc<Mutex<InputTransform>>,
+    pub cursor_regions: Arc<CursorRegions>,
+    pub pointer: Arc<PointerTracker>,
+    pub cursor_image: Arc<CursorImageSlot>,
     pub card_path: Option<String>,
+    /// Render node frames are drawn on when it is not the card's own GPU.
+    pub render_node: Option<String>,
     pub hw_cursor: bool,
     pub input_log: bool,
 }
@@ -698,48 +1755,83 @@ pub struct DrmRunConfig {
 pub fn run(
     stop: Arc<AtomicBool>,
     dirty: Arc<AtomicBool>,
-    render_state: Arc<Mutex<RenderState>>,
+    render_state: Arc<RenderStateLock>,
     input_mask: Arc<AtomicU32>,
-    input_events: Arc<Mutex<InputQueue>>,
     config: DrmRunConfig,
 ) {
+    let input_events = Arc::clone(&config.input_events);
+    let plane_properties = render_state
+        .lock()
+        .map(|state| Arc::clone(&state.plane_properties))
+        .unwrap_or_default();
     let card = match open_card(config.card_path.as_deref()) {
         Ok(card) => card,
         Err(e) => {
-            eprintln!("DRM backend unavailable: {e}");
+            log_line!("DRM backend unavailable: {e}");
             return;
         }
     };
 
     if let Err(e) = card.set_client_capability(ClientCapability::UniversalPlanes, true) {
-        eprintln!("DRM backend unavailable: {e}");
+        log_line!("DRM backend unavailable: {e}");
         return;
     }
     if let Err(e) = card.set_client_capability(ClientCapability::Atomic, true) {
-        eprintln!("DRM backend unavailable: {e}");
+        log_line!("DRM backend unavailable: {e}");
         return;
     }
 
-    let gbm_device = match GbmDevice::new(card.as_fd()) {
+    // With a separate render node, frames are drawn there and imported into the card for
+    // scanout; the card's own GBM device still allocates the cursor.
+    let render_card = match config
+        .render_node
+        .as_deref()
+        .map(|path| open_card(Some(path)))
+    {
+        Some(Ok(render_card)) => Some(render_card),
+        Some(Err(e)) => {
+            log_line!("DRM backend unavailable: {e}");
+            return;
+        }
+        None => None,
+    };
+    let cross_device = render_card.is_some();
+
+    let scanout_gbm = match GbmDevice::new(card.as_fd()) {
         Ok(device) => device,
         Err(e) => {
-            eprintln!("DRM backend unavailable: {e}");
+            log_line!("DRM backend unavailable: {e}");
             return;
         }
     };
+    let render_gbm = match render_card
+        .as_ref()
+        .map(|render| GbmDevice::new(render.as_fd()))
+    {
+        Some(Ok(device)) => Some(device),
+        Some(Err(e)) => {
+            log_line!("DRM backend unavailable: {e}");
+            return;
+        }
+        None => None,
+    };
+    let gbm_device = render_gbm.as_ref().unwrap_or(&scanout_gbm);
 
     let mut last_dimensions: Option<(u32, u32)> = None;
-    let hotplug_interval = Duration::from_millis(750);
+    let session_vt = active_vt();
 
     loop {
         if stop.load(Ordering::Relaxed) {
             break;
         }
+        if !wait_for_session(&card, &session_vt, &stop, &input_events) {
+            break;
+        }
 
         let resources = match card.resource_handles() {
             Ok(handles) => handles,
             Err(e) => {
-                eprintln!("DRM backend unavailable: {e}");
+                log_line!("DRM backend unavailable: {e}");
                 std::thread::sleep(Duration::from_millis(250));
                 continue;
             }
@@ -749,7 +1841,7 @@ pub fn run(
             match first_connected_connector(&card, &resources, config.requested_size) {
                 Ok(values) => values,
                 Err(e) => {
-                    eprintln!("DRM backend unavailable: {e}");
+                    log_line!("DRM backend unavailable: {e}");
                     std::thread::sleep(Duration::from_millis(250));
                     continue;
                 }
@@ -758,7 +1850,7 @@ pub fn run(
         let plane = match find_primary_plane(&card, &resources, crtc_handle) {
             Ok(handle) => handle,
             Err(e) => {
-                eprintln!("DRM backend unavailable: {e}");
+                log_line!("DRM backend unavailable: {e}");
                 std::thread::sleep(Duration::from_millis(250));
                 continue;
             }
@@ -770,7 +1862,7 @@ pub fn run(
         {
             Ok(props) => props,
             Err(e) => {
-                eprintln!("DRM backend unavailable: {e}");
+                log_line!("DRM backend unavailable: {e}");
                 std::thread::sleep(Duration::from_millis(250));
                 continue;
             }
@@ -781,7 +1873,7 @@ pub fn run(
         {
             Ok(props) => props,
             Err(e) => {
-                eprintln!("DRM backend unavailable: {e}");
+                log_line!("DRM backend unavailable: {e}");
                 std::thread::sleep(Duration::from_millis(250));
                 continue;
             }
@@ -792,7 +1884,7 @@ pub fn run(
         {
             Ok(props) => props,
             Err(e) => {
-                eprintln!("DRM backend unavailable: {e}");
+                log_line!("DRM backend unavailable: {e}");
                 std::thread::sleep(Duration::from_millis(250));
                 continue;
             }
@@ -800,32 +1892,70 @@ pub fn run(
 
         let (width, height) = mode.size();
         let dimensions = (width as u32, height as u32);
+        let mut found = drm_outputs(&card, &resources).unwrap_or_default();
+        let connected = connected_connectors(&found);
+        let mut primary_name = String::new();
+        if let Some(primary) = found
+            .iter_mut()
+            .find(|output| output.connector == connector)
+        {
+            primary.info.width = dimensions.0;
+            primary.info.height = dimensions.1;
+            primary.info.refresh = mode.vrefresh();
+            primary.info.active = true;
+            primary_name = primary.info.name.clone();
+        }
+        // Frames are pre-rotated, so the scene is laid out for the rotated panel.
+        let (scene_width, scene_height) = render_state
+            .lock()
+            .map(|state| state.output_rotation.rotate_size(dimensions))
+            .unwrap_or(dimensions);
         if last_dimensions != Some(dimensions)
             && let Ok(mut queue) = input_events.lock()
         {
+            queue.set_viewport(ViewportInfo {
+                width: scene_width,
+                height: scene_height,
+                scale: 1.0,
+            });
             let notify = queue.push_event(InputEvent::ViewportReshape {
-                width: dimensions.0,
-                height: dimensions.1,
+                width: scene_width,
+                height: scene_height,
             });
-            if let Some(pid) = notify {
+            for pid in notify {
                 notify_input_ready(pid);
             }
             last_dimensions = Some(dimensions);
         }
 
-        let mut input = DrmInput::new(
+        // Held for the session; dropping it stops the input thread.
+        let _input = match DrmInputThread::spawn(DrmInput::new(
             dimensions,
             Arc::clone(&input_mask),
             input_events.clone(),
             Arc::clone(&config.cursor_state),
+            Arc::clone(&config.pointer_accel),
+            Arc::clone(&config.device_masks),
+            Arc::clone(&config.input_transform),
             config.input_log,
-        );
+        )) {
+            Ok(input) => input,
+            Err(e) => {
+                log_line!("DRM backend unavailable: {e}");
+                std::thread::sleep(Duration::from_millis(250));
+                continue;
+            }
+        };
 
         let mut cursor_plane = if config.hw_cursor {
-            match create_cursor_plane(&card, &gbm_device, &resources, crtc_handle) {
-                Ok(plane) => plane,
+            match create_cursor_plane(&card, &scanout_gbm, &resources, crtc_handle) {
+                Ok(Some(plane)) => Some(plane),
+                Ok(None) => {
+                    log_line!("DRM card has no cursor plane; drawing a software cursor");
+                    None
+                }
                 Err(e) => {
-                    eprintln!("DRM cursor setup failed: {e}");
+                    log_line!("DRM cursor setup failed: {e}");
                     None
                 }
             }
@@ -837,59 +1967,81 @@ pub fn run(
             dimensions.0,
             dimensions.1,
             GbmFormat::Xrgb8888,
-            BufferObjectFlags::SCANOUT | BufferObjectFlags::RENDERING,
+            surface_flags(cross_device),
         ) {
             Ok(surface) => surface,
             Err(e) => {
-                eprintln!("DRM backend unavailable: {e}");
+                log_line!("DRM backend unavailable: {e}");
                 std::thread::sleep(Duration::from_millis(250));
                 continue;
             }
         };
 
-        let (egl_lib, egl_api) = match load_egl() {
-            Ok(values) => values,
-            Err(e) => {
-                eprintln!("DRM backend unavailable: {e}");
-                std::thread::sleep(Duration::from_millis(250));
-                continue;
-            }
-        };
+        let gpu = load_egl().and_then(|(egl_lib, egl_api)| {
+            let (display, context, surface) = init_egl(
+                &egl_api,
+                gbm_device.as_raw() as *mut c_void,
+                gbm_surface.as_raw() as *mut c_void,
+            )?;
+            let egl_state = EglState {
+                egl: egl_api,
+                _egl_lib: egl_lib,
+                display,
+                context,
+                surface,
+            };
+            let renderer = create_renderer(&egl_state.egl, dimensions)?;
+            Ok((egl_state, renderer))
+        });
 
-        let (display, context, surface) = match init_egl(
-            &egl_api,
-            gbm_device.as_raw() as *mut c_void,
-            gbm_surface.as_raw() as *mut c_void,
-        ) {
+        let (egl_state, mut renderer) = match gpu {
             Ok(values) => values,
             Err(e) => {
-                eprintln!("DRM backend unavailable: {e}");
-                std::thread::sleep(Duration::from_millis(250));
+                log_line!("DRM GPU rendering unavailable ({e}); falling back to raster scanout");
+                GpuInfo {
+                    fallback: Some(e),
+                    ..GpuInfo::raster("drm")
+                }
+                // Raster scanout fills dumb buffers on the card; no render node is used.
+                .with_nodes(config.card_path.as_deref(), None)
+                .publish(&render_state);
+                outputs::publish(
+                    &render_state,
+                    found.into_iter().map(|output| output.info).collect(),
+                );
+                let output = ScanoutOutput {
+                    name: primary_name,
+                    connector,
+                    crtc: crtc_handle,
+                    plane,
+                    mode,
+                    con_props,
+                    crtc_props,
+                    plane_props,
+                };
+                if let Err(e) = run_raster_scanout(
+                    &card,
+                    &output,
+                    &session_vt,
+                    &stop,
+                    &dirty,
+                    &render_state,
+                    &config,
+                ) {
+                    log_line!("DRM raster scanout failed: {e}");
+                    std::thread::sleep(Duration::from_millis(250));
+                }
                 continue;
             }
         };
 
-        let egl_state = EglState {
-            egl: egl_api,
-            _egl_lib: egl_lib,
-            display,
-            _context: context,
-            surface,
-        };
-
-        let mut renderer = match create_renderer(&egl_state.egl, dimensions) {
-            Ok(renderer) => renderer,
-            Err(e) => {
-                eprintln!("DRM backend unavailable: {e}");
-                std::thread::sleep(Duration::from_millis(250));
-                continue;
-            }
-        };
+        publish_gpu_info(&render_state, &egl_state, &config);
+        renderer.set_output(&primary_name, false);
 
         let mode_blob = match card.create_property_blob(&mode) {
             Ok(blob) => blob,
             Err(e) => {
-                eprintln!("DRM backend unavailable: {e}");
+                log_line!("DRM backend unavailable: {e}");
                 std::thread::sleep(Duration::from_millis(250));
                 continue;
             }
@@ -898,11 +2050,16 @@ pub fn run(
         let mut framebuffer_cache: HashMap<u32, framebuffer::Handle> = HashMap::new();
 
         if let Ok(state) = render_state.lock() {
+            state.redraws.add_resize();
             renderer.redraw(&state);
         }
-        let mut cursor = cursor_snapshot(&config.cursor_state);
+        if cursor_plane.is_none() {
+            renderer.snapshot_scene();
+        }
+        let mut cursor = cursor_snapshot(&config);
         if cursor_plane.is_none() && cursor.visible {
-            draw_software_cursor(&mut renderer, cursor.pos, dimensions);
+            draw_software_cursor(&mut renderer, cursor, &config.cursor_image, dimensions);
+            renderer.flush();
         }
 
         if unsafe {
@@ -911,7 +2068,7 @@ pub fn run(
                 .SwapBuffers(egl_state.display, egl_state.surface)
         } == egl::FALSE
         {
-            eprintln!("DRM backend unavailable: eglSwapBuffers failed");
+            log_line!("DRM backend unavailable: eglSwapBuffers failed");
             std::thread::sleep(Duration::from_millis(250));
             continue;
         }
@@ -919,16 +2076,16 @@ pub fn run(
         let bo = match unsafe { gbm_surface.lock_front_buffer() } {
             Ok(bo) => bo,
             Err(e) => {
-                eprintln!("DRM backend unavailable: {e}");
+                log_line!("DRM backend unavailable: {e}");
                 std::thread::sleep(Duration::from_millis(250));
                 continue;
             }
         };
 
-        let fb = match framebuffer_for_bo(&card, &mut framebuffer_cache, &bo) {
+        let fb = match framebuffer_for_bo(&card, &mut framebuffer_cache, &bo, cross_device) {
             Ok(fb) => fb,
             Err(e) => {
-                eprintln!("DRM backend unavailable: {e}");
+                log_line!("DRM backend unavailable: {e}");
                 std::thread::sleep(Duration::from_millis(250));
                 continue;
             }
@@ -948,33 +2105,69 @@ pub fn run(
                 property::Value::Boolean(true),
             );
             add_plane_properties(&mut atomic_req, plane, &plane_props, crtc_handle, fb)?;
-            add_plane_geometry(&mut atomic_req, plane, &plane_props, &mode)
+            add_plane_geometry(&mut atomic_req, plane, &plane_props, &mode)?;
+            let values = plane_properties.values(&primary_name);
+            add_plane_values(&mut atomic_req, plane, &plane_props, &values)
         })() {
-            eprintln!("DRM backend unavailable: {e}");
+            log_line!("DRM backend unavailable: {e}");
             std::thread::sleep(Duration::from_millis(250));
             continue;
         }
 
         if let Err(e) = card.atomic_commit(AtomicCommitFlags::ALLOW_MODESET, atomic_req) {
-            eprintln!("DRM backend unavailable: {e}");
+            log_line!("DRM backend unavailable: {e}");
             std::thread::sleep(Duration::from_millis(250));
             continue;
         }
 
+        let mut secondaries = start_secondary_outputs(
+            &card,
+            gbm_device,
+            cross_device,
+            &resources,
+            &mut found,
+            (connector, crtc_handle),
+            &render_state,
+        );
+        let mut planes = HashMap::from([(
+            primary_name.clone(),
+            describe_plane_properties(&plane_props),
+        )]);
+        planes.extend(secondaries.iter().map(|output| {
+            (
+                output.name.clone(),
+                describe_plane_properties(&output.plane_props),
+            )
+        }));
+        plane_properties.publish(planes);
+        outputs::publish(
+            &render_state,
+            found.into_iter().map(|output| output.info).collect(),
+        );
+
         let mut current_bo = Some(bo);
         let mut last_cursor = cursor;
-        let cursor_plane_error = cursor_plane.as_ref().and_then(|plane| {
-            update_cursor_plane(&card, crtc_handle, plane, cursor, dimensions).err()
+        let cursor_plane_error = cursor_plane.as_mut().and_then(|plane| {
+            update_cursor_plane(
+                &card,
+                crtc_handle,
+                plane,
+                cursor,
+                &config.cursor_image,
+                dimensions,
+            )
+            .err()
         });
         if let Some(err) = cursor_plane_error
             && !is_ebusy(&err)
         {
-            eprintln!("DRM cursor update failed: {err}");
+            log_line!("DRM cursor update failed: {err}");
             cursor_plane = None;
             dirty.store(true, Ordering::Relaxed);
         }
 
-        let mut next_hotplug_check = Instant::now() + hotplug_interval;
+        let mut next_hotplug_check = Instant::now() + HOTPLUG_INTERVAL;
+        let mut cursor_dirty = false;
 
         loop {
             if stop.load(Ordering::Relaxed) {
@@ -982,57 +2175,92 @@ pub fn run(
             }
 
             if Instant::now() >= next_hotplug_check {
-                let resources = match card.resource_handles() {
-                    Ok(handles) => handles,
-                    Err(_) => break,
-                };
-                let next = first_connected_connector(&card, &resources, config.requested_size);
-                match next {
-                    Ok((next_connector, next_mode, next_crtc)) => {
-                        let next_dimensions = next_mode.size();
-                        let next_dimensions = (next_dimensions.0 as u32, next_dimensions.1 as u32);
-                        if next_connector != connector
-                            || next_crtc != crtc_handle
-                            || next_dimensions != dimensions
-                        {
-                            break;
-                        }
-                    }
-                    Err(_) => break,
+                if output_changed(
+                    &card,
+                    &session_vt,
+                    config.requested_size,
+                    connector,
+                    crtc_handle,
+                    dimensions,
+                ) || connectors_changed(&card, &connected)
+                {
+                    break;
                 }
-                next_hotplug_check = Instant::now() + hotplug_interval;
+                next_hotplug_check = Instant::now() + HOTPLUG_INTERVAL;
             }
 
-            input.poll();
-            cursor = cursor_snapshot(&config.cursor_state);
+            cursor = cursor_snapshot(&config);
+            if config.pointer.take_moved() {
+                dirty.store(true, Ordering::Relaxed);
+            }
             if cursor_plane.is_some() {
-                if cursor.visible != last_cursor.visible || cursor.pos != last_cursor.pos {
-                    let cursor_plane_error = cursor_plane.as_ref().and_then(|plane| {
-                        update_cursor_plane(&card, crtc_handle, plane, cursor, dimensions).err()
+                if cursor.visible != last_cursor.visible
+                    || cursor.pos != last_cursor.pos
+                    || cursor.shape != last_cursor.shape
+                    || cursor.image != last_cursor.image
+                {
+                    let cursor_plane_error = cursor_plane.as_mut().and_then(|plane| {
+                        update_cursor_plane(
+                            &card,
+                            crtc_handle,
+                            plane,
+                            cursor,
+                            &config.cursor_image,
+                            dimensions,
+                        )
+                        .err()
                     });
                     if let Some(err) = cursor_plane_error
                         && !is_ebusy(&err)
                     {
-                        eprintln!("DRM cursor update failed: {err}");
+                        log_line!("DRM cursor update failed: {err}");
                         cursor_plane = None;
                         dirty.store(true, Ordering::Relaxed);
                     }
                 }
             } else {
-                if cursor.visible && cursor.pos != last_cursor.pos {
-                    dirty.store(true, Ordering::Relaxed);
+                if cursor.visible
+                    && (cursor.pos != last_cursor.pos
+                        || cursor.shape != last_cursor.shape
+                        || cursor.image != last_cursor.image)
+                {
+                    cursor_dirty = true;
                 }
                 if cursor.visible != last_cursor.visible {
-                    dirty.store(true, Ordering::Relaxed);
+                    cursor_dirty = true;
                 }
             }
             last_cursor = cursor;
-            if dirty.swap(false, Ordering::Relaxed) {
-                if let Ok(state) = render_state.lock() {
-                    renderer.redraw(&state);
+            if poll_scheduled_scripts(&render_state).0 {
+                dirty.store(true, Ordering::Relaxed);
+            }
+            let scene_dirty = dirty.swap(false, Ordering::Relaxed);
+            if scene_dirty || cursor_dirty {
+                if cursor_dirty && let Ok(state) = render_state.lock() {
+                    state.redraws.add_cursor();
+                }
+                cursor_dirty = false;
+                if !secondaries.is_empty()
+                    && let Err(e) = egl_state.make_current()
+                {
+                    log_line!("DRM backend unavailable: {e}");
+                    break;
+                }
+                // Cursor-only updates composite the cursor over the last scene snapshot.
+                if scene_dirty || cursor_plane.is_some() || !renderer.restore_scene() {
+                    if let Ok(state) = render_state.lock() {
+                        renderer.redraw(&state);
+                    }
+                    if cursor_plane.is_none() {
+                        renderer.snapshot_scene();
+                    }
+                } else if let Ok(state) = render_state.lock() {
+                    // The scene is not redrawn, so the cursor frame is closed here.
+                    state.redraws.finish_frame(monotonic_now_ms());
                 }
                 if cursor_plane.is_none() && cursor.visible {
-                    draw_software_cursor(&mut renderer, cursor.pos, dimensions);
+                    draw_software_cursor(&mut renderer, cursor, &config.cursor_image, dimensions);
+                    renderer.flush();
                 }
 
                 if unsafe {
@@ -1041,33 +2269,61 @@ pub fn run(
                         .SwapBuffers(egl_state.display, egl_state.surface)
                 } == egl::FALSE
                 {
-                    eprintln!("DRM backend unavailable: eglSwapBuffers failed");
+                    log_line!("DRM backend unavailable: eglSwapBuffers failed");
                     break;
                 }
 
                 let next_bo = match unsafe { gbm_surface.lock_front_buffer() } {
                     Ok(bo) => bo,
                     Err(e) => {
-                        eprintln!("DRM backend unavailable: {e}");
+                        log_line!("DRM backend unavailable: {e}");
                         break;
                     }
                 };
 
-                let next_fb = match framebuffer_for_bo(&card, &mut framebuffer_cache, &next_bo) {
-                    Ok(fb) => fb,
-                    Err(e) => {
-                        eprintln!("DRM backend unavailable: {e}");
-                        break;
-                    }
-                };
+                let next_fb =
+                    match framebuffer_for_bo(&card, &mut framebuffer_cache, &next_bo, cross_device)
+                    {
+                        Ok(fb) => fb,
+                        Err(e) => {
+                            log_line!("DRM backend unavailable: {e}");
+                            break;
+                        }
+                    };
 
                 let mut flip_req = atomic::AtomicModeReq::new();
                 if let Err(e) =
                     add_plane_properties(&mut flip_req, plane, &plane_props, crtc_handle, next_fb)
                 {
-                    eprintln!("DRM backend unavailable: {e}");
+                    log_line!("DRM backend unavailable: {e}");
                     break;
                 }
+                // Secondary outputs only change with the scene and flip in the same commit.
+                let mut flips = 1;
+                if scene_dirty {
+                    if let Err(e) =
+                        render_secondary_outputs(&render_state, &mut secondaries, &mut flip_req)
+                    {
+                        log_line!("DRM backend unavailable: {e}");
+                        break;
+                    }
+                    flips += secondaries.len();
+                }
+                let mut flipping = vec![(primary_name.as_str(), plane, &plane_props)];
+                if scene_dirty {
+                    flipping.extend(
+                        secondaries.iter().map(|output| {
+                            (output.name.as_str(), output.plane, &output.plane_props)
+                        }),
+                    );
+                }
+                let changes = match add_plane_changes(&mut flip_req, &plane_properties, &flipping) {
+                    Ok(changes) => changes,
+                    Err(e) => {
+                        log_line!("DRM backend unavailable: {e}");
+                        break;
+                    }
+                };
 
                 if let Err(e) = card.atomic_commit(
                     AtomicCommitFlags::NONBLOCK | AtomicCommitFlags::PAGE_FLIP_EVENT,
@@ -1075,21 +2331,41 @@ pub fn run(
                 ) {
                     let err = e.to_string();
                     if is_ebusy(&err) {
+                        plane_properties.requeue(changes);
                         drop(next_bo);
+                        for output in &mut secondaries {
+                            output.next_bo = None;
+                        }
                         std::thread::sleep(Duration::from_millis(2));
                         continue;
                     }
-                    eprintln!("DRM backend unavailable: {err}");
+                    if !changes.is_empty() {
+                        // The frame is flipped again without the values the kernel refused.
+                        plane_properties.reject(&changes, &err);
+                        drop(next_bo);
+                        for output in &mut secondaries {
+                            output.next_bo = None;
+                        }
+                        dirty.store(true, Ordering::Relaxed);
+                        continue;
+                    }
+                    log_line!("DRM backend unavailable: {err}");
                     break;
                 }
 
-                if let Err(e) = wait_for_page_flip(&card) {
-                    eprintln!("DRM backend unavailable: {e}");
-                    break;
+                match wait_for_page_flips(&card, flips) {
+                    Ok(flipped_us) => notify_frame_presented(&input_events, flipped_us),
+                    Err(e) => {
+                        log_line!("DRM backend unavailable: {e}");
+                        break;
+                    }
                 }
 
                 drop(current_bo.take());
                 current_bo = Some(next_bo);
+                for output in &mut secondaries {
+                    output.flipped();
+                }
             }
             std::thread::sleep(Duration::from_millis(4));
         }
@@ -3,20 +3,25 @@ use std::os::fd::AsRawFd;
 use std::path::Path;
 use std::sync::{
     Arc, Mutex,
-    atomic::{AtomicU32, Ordering},
+    atomic::{AtomicBool, AtomicU32, Ordering},
 };
+use std::thread;
+use std::time::{Duration, Instant};
 
 use evdev::{
     AbsoluteAxisType, Device, InputEventKind, Key, PropType, RelativeAxisType, Synchronization,
 };
 use libc::input_absinfo;
 
-use crate::cursor::CursorState;
+use crate::cursor::{CursorState, PointerAccel};
+use crate::flight_recorder::log_line;
 use crate::input::{
-    ACTION_PRESS, ACTION_RELEASE, INPUT_MASK_CODEPOINT, INPUT_MASK_CURSOR_BUTTON,
-    INPUT_MASK_CURSOR_POS, INPUT_MASK_CURSOR_SCROLL, INPUT_MASK_KEY, InputEvent, InputQueue,
+    ACTION_PRESS, ACTION_RELEASE, DeviceClass, DeviceClassMasks, INPUT_MASK_CODEPOINT,
+    INPUT_MASK_CURSOR_BUTTON, INPUT_MASK_CURSOR_POS, INPUT_MASK_CURSOR_SCROLL, INPUT_MASK_KEY,
+    INPUT_MASK_TOUCH, InputEvent, InputQueue, ScrollSource, TouchPhase, flush_held_cursor,
     notify_input_ready,
 };
+use crate::input_transform::InputTransform;
 use crate::input_translate::{
     Key as ScenicKey, KeyLocation, Modifiers, MouseButton, NamedKey, button_to_scenic,
     key_to_scenic, modifiers_to_mask,
@@ -24,6 +29,7 @@ use crate::input_translate::{
 
 struct InputDevice {
     device: Device,
+    class: DeviceClass,
     abs_x: Option<AbsAxisState>,
     abs_y: Option<AbsAxisState>,
     abs_x_dirty: bool,
@@ -32,6 +38,129 @@ struct InputDevice {
     last_abs_scaled: Option<(f32, f32)>,
     touch_active: bool,
     touch_tracking: bool,
+    /// Contacts of a multi-touch screen, reported as touch events next to the emulated
+    /// pointer; `None` for devices without `ABS_MT_SLOT` and for touchpads.
+    touch_slots: Option<TouchSlots>,
+}
+
+/// Wheel motion of one evdev frame. High-resolution wheels report 120ths of a detent
+/// (`REL_WHEEL_HI_RES`) next to whole detents; the finer value is used where present.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct PendingScroll {
+    detents: (f32, f32),
+    hi_res: (Option<f32>, Option<f32>),
+}
+
+impl PendingScroll {
+    /// Lines scrolled in the frame, if any.
+    fn lines(self) -> Option<(f32, f32)> {
+        let dx = self.hi_res.0.map_or(self.detents.0, |value| value / 120.0);
+        let dy = self.hi_res.1.map_or(self.detents.1, |value| value / 120.0);
+        (dx != 0.0 || dy != 0.0).then_some((dx, dy))
+    }
+}
+
+/// Most contacts tracked per touchscreen.
+const MAX_TOUCH_SLOTS: usize = 16;
+
+/// Multi-touch protocol B state: the kernel selects a slot with `ABS_MT_SLOT` and then
+/// reports that contact's `ABS_MT_TRACKING_ID` (`-1` once lifted) and position, all
+/// applied together at `SYN_REPORT`.
+#[derive(Clone, Debug)]
+struct TouchSlots {
+    x: AbsAxisState,
+    y: AbsAxisState,
+    current: usize,
+    slots: Vec<TouchSlot>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct TouchSlot {
+    tracking_id: Option<u32>,
+    x: i32,
+    y: i32,
+    began: bool,
+    moved: bool,
+    ended: bool,
+}
+
+impl TouchSlots {
+    fn new(x: AbsAxisState, y: AbsAxisState, slots: usize) -> Self {
+        Self {
+            x,
+            y,
+            current: 0,
+            slots: vec![TouchSlot::default(); slots.clamp(1, MAX_TOUCH_SLOTS)],
+        }
+    }
+
+    fn update(&mut self, axis: AbsoluteAxisType, value: i32) {
+        if axis == AbsoluteAxisType::ABS_MT_SLOT {
+            if let Ok(slot) = usize::try_from(value)
+                && slot < self.slots.len()
+            {
+                self.current = slot;
+            }
+            return;
+        }
+        let slot = &mut self.slots[self.current];
+        match axis {
+            AbsoluteAxisType::ABS_MT_TRACKING_ID => match u32::try_from(value) {
+                Ok(id) => {
+                    slot.tracking_id = Some(id);
+                    slot.began = true;
+                    slot.ended = false;
+                }
+                Err(_) => slot.ended = slot.tracking_id.is_some(),
+            },
+            AbsoluteAxisType::ABS_MT_POSITION_X => {
+                slot.x = value;
+                slot.moved = true;
+            }
+            AbsoluteAxisType::ABS_MT_POSITION_Y => {
+                slot.y = value;
+                slot.moved = true;
+            }
+            _ => {}
+        }
+    }
+
+    /// Touch events for the contacts that changed since the last report, in screen
+    /// pixels. A contact that went down and up within one report gets both events.
+    fn take_events(&mut self, screen_size: (u32, u32)) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        for slot in &mut self.slots {
+            let Some(id) = slot.tracking_id else {
+                continue;
+            };
+            let x = scale_abs_value(
+                AbsAxisState {
+                    value: slot.x,
+                    ..self.x
+                },
+                screen_size.0,
+            );
+            let y = scale_abs_value(
+                AbsAxisState {
+                    value: slot.y,
+                    ..self.y
+                },
+                screen_size.1,
+            );
+            let mut push = |phase| events.push(InputEvent::Touch { id, phase, x, y });
+            if slot.began {
+                push(TouchPhase::Down);
+            } else if slot.moved {
+                push(TouchPhase::Move);
+            }
+            if slot.ended {
+                push(TouchPhase::Up);
+                slot.tracking_id = None;
+            }
+            (slot.began, slot.moved, slot.ended) = (false, false, false);
+        }
+        events
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -52,10 +181,17 @@ pub struct DrmInput {
     cursor_pos: (f32, f32),
     modifiers: Modifiers,
     caps_lock: bool,
+    /// Auto-repeats (evdev value `2`) seen since the last key press.
+    key_repeat: u32,
     screen_size: (u32, u32),
     input_mask: Arc<AtomicU32>,
     input_events: Arc<Mutex<InputQueue>>,
     cursor_state: Arc<Mutex<CursorState>>,
+    pointer_accel: Arc<Mutex<PointerAccel>>,
+    device_masks: Arc<DeviceClassMasks>,
+    pending_rel: (f32, f32),
+    pending_scroll: PendingScroll,
+    input_transform: Arc<Mutex<InputTransform>>,
 }
 
 impl DrmInput {
@@ -64,6 +200,9 @@ impl DrmInput {
         input_mask: Arc<AtomicU32>,
         input_events: Arc<Mutex<InputQueue>>,
         cursor_state: Arc<Mutex<CursorState>>,
+        pointer_accel: Arc<Mutex<PointerAccel>>,
+        device_masks: Arc<DeviceClassMasks>,
+        input_transform: Arc<Mutex<InputTransform>>,
         log_enabled: bool,
     ) -> Self {
         let devices = enumerate_devices(log_enabled);
@@ -72,25 +211,58 @@ impl DrmInput {
             cursor_pos: (0.0, 0.0),
             modifiers: Modifiers::default(),
             caps_lock: false,
+            key_repeat: 0,
             screen_size,
             input_mask,
             input_events,
             cursor_state,
+            pointer_accel,
+            device_masks,
+            pending_rel: (0.0, 0.0),
+            pending_scroll: PendingScroll::default(),
+            input_transform,
+        }
+    }
+
+    fn raw_fds(&self) -> Vec<i32> {
+        self.devices
+            .iter()
+            .map(|device| device.device.as_raw_fd())
+            .collect()
+    }
+
+    /// Drops the device with descriptor `fd` once it has been unplugged; closing it also
+    /// takes it out of any epoll set.
+    fn remove_device(&mut self, fd: i32) {
+        let before = self.devices.len();
+        self.devices
+            .retain(|device| device.device.as_raw_fd() != fd);
+        if self.devices.len() != before {
+            log_line!("drm_input device fd={fd} is gone, no longer polling it");
         }
     }
 
     pub fn poll(&mut self) {
-        let mask = self.input_mask.load(Ordering::Relaxed);
-        if mask == 0 {
+        let global_mask = self.input_mask.load(Ordering::Relaxed);
+        if global_mask == 0 {
             return;
         }
 
+        let mut unplugged = Vec::new();
         for idx in 0..self.devices.len() {
+            let mask = self
+                .device_masks
+                .filter(self.devices[idx].class, global_mask);
             let events = {
                 let device = &mut self.devices[idx];
                 match device.device.fetch_events() {
                     Ok(events) => events.collect::<Vec<_>>(),
-                    Err(_) => Vec::new(),
+                    Err(err) => {
+                        if err.raw_os_error() == Some(libc::ENODEV) {
+                            unplugged.push(device.device.as_raw_fd());
+                        }
+                        Vec::new()
+                    }
                 }
             };
 
@@ -100,14 +272,27 @@ impl DrmInput {
                         self.handle_key_event_with_device(idx, key, event.value(), mask);
                     }
                     InputEventKind::RelAxis(axis) => {
-                        self.handle_rel_event(axis, event.value(), mask);
+                        self.handle_rel_event(axis, event.value());
                     }
                     InputEventKind::AbsAxis(axis) => {
                         let device = &mut self.devices[idx];
                         update_abs_state(device, axis, event.value(), self.screen_size);
+                        if let Some(slots) = device.touch_slots.as_mut() {
+                            slots.update(axis, event.value());
+                        }
                     }
                     InputEventKind::Synchronization(sync) => {
                         if sync == Synchronization::SYN_REPORT {
+                            let (dx, dy) = std::mem::take(&mut self.pending_rel);
+                            if dx != 0.0 || dy != 0.0 {
+                                self.handle_relative_motion(dx, dy, mask);
+                            }
+                            let scroll = std::mem::take(&mut self.pending_scroll);
+                            if let Some(lines) = scroll.lines()
+                                && mask & INPUT_MASK_CURSOR_SCROLL != 0
+                            {
+                                self.handle_scroll(lines);
+                            }
                             let action = {
                                 let device = &mut self.devices[idx];
                                 consume_abs_action(device, self.screen_size)
@@ -115,20 +300,38 @@ impl DrmInput {
                             match action {
                                 AbsAction::Absolute(x, y) => self.handle_abs_position(x, y, mask),
                                 AbsAction::Relative(dx, dy) => {
-                                    self.handle_abs_relative(dx, dy, mask);
+                                    self.handle_relative_motion(dx, dy, mask);
                                 }
                                 AbsAction::None => {}
                             }
+                            let touches = self.devices[idx]
+                                .touch_slots
+                                .as_mut()
+                                .map(|slots| slots.take_events(self.screen_size))
+                                .unwrap_or_default();
+                            if mask & INPUT_MASK_TOUCH != 0 {
+                                for touch in touches {
+                                    self.push_input(touch);
+                                }
+                            }
                         }
                     }
                     _ => {}
                 }
             }
         }
+        for fd in unplugged {
+            self.remove_device(fd);
+        }
     }
 
     fn handle_key_event(&mut self, key: Key, value: i32, mask: u32) {
         let pressed = value != 0;
+        self.key_repeat = if value == 2 {
+            self.key_repeat.saturating_add(1)
+        } else {
+            0
+        };
         self.update_modifiers(key, pressed);
         if key == Key::KEY_CAPSLOCK && pressed {
             self.caps_lock = !self.caps_lock;
@@ -168,6 +371,8 @@ impl DrmInput {
                 key: key_to_scenic(key, location),
                 action,
                 mods,
+                scancode: u32::from(key.code()),
+                repeat: self.key_repeat,
             });
         }
 
@@ -179,50 +384,44 @@ impl DrmInput {
         }
     }
 
-    fn handle_rel_event(&mut self, axis: RelativeAxisType, value: i32, mask: u32) {
-        let (mut x, mut y) = self.cursor_pos;
+    fn handle_rel_event(&mut self, axis: RelativeAxisType, value: i32) {
+        // Motion is accumulated until SYN_REPORT so acceleration sees both axes together,
+        // and wheels report detents and high-resolution steps in the same frame.
         match axis {
             RelativeAxisType::REL_X => {
-                x += value as f32;
+                self.pending_rel.0 += value as f32;
             }
             RelativeAxisType::REL_Y => {
-                y += value as f32;
+                self.pending_rel.1 += value as f32;
             }
             RelativeAxisType::REL_WHEEL => {
-                if mask & INPUT_MASK_CURSOR_SCROLL != 0 {
-                    let (cx, cy) = self.cursor_pos;
-                    self.push_input(InputEvent::CursorScroll {
-                        dx: 0.0,
-                        dy: value as f32,
-                        x: cx,
-                        y: cy,
-                    });
-                }
-                return;
+                self.pending_scroll.detents.1 += value as f32;
             }
             RelativeAxisType::REL_HWHEEL => {
-                if mask & INPUT_MASK_CURSOR_SCROLL != 0 {
-                    let (cx, cy) = self.cursor_pos;
-                    self.push_input(InputEvent::CursorScroll {
-                        dx: value as f32,
-                        dy: 0.0,
-                        x: cx,
-                        y: cy,
-                    });
-                }
-                return;
+                self.pending_scroll.detents.0 += value as f32;
+            }
+            RelativeAxisType::REL_WHEEL_HI_RES => {
+                *self.pending_scroll.hi_res.1.get_or_insert(0.0) += value as f32;
+            }
+            RelativeAxisType::REL_HWHEEL_HI_RES => {
+                *self.pending_scroll.hi_res.0.get_or_insert(0.0) += value as f32;
             }
-            _ => return,
+            _ => {}
         }
+    }
 
-        let (width, height) = self.screen_size;
-        x = x.clamp(0.0, width.saturating_sub(1) as f32);
-        y = y.clamp(0.0, height.saturating_sub(1) as f32);
-        self.set_cursor_pos(x, y);
-
-        if mask & INPUT_MASK_CURSOR_POS != 0 {
-            self.push_input(InputEvent::CursorPos { x, y });
-        }
+    fn handle_scroll(&self, (dx, dy): (f32, f32)) {
+        let (x, y) = self.cursor_pos;
+        self.push_input(InputEvent::CursorScroll {
+            dx,
+            dy,
+            x,
+            y,
+            source: ScrollSource::Wheel,
+            lines: Some((dx, dy)),
+            pixels: None,
+            stop: false,
+        });
     }
 
     fn handle_abs_position(&mut self, x: f32, y: f32, mask: u32) {
@@ -232,7 +431,13 @@ impl DrmInput {
         }
     }
 
-    fn handle_abs_relative(&mut self, dx: f32, dy: f32, mask: u32) {
+    fn handle_relative_motion(&mut self, dx: f32, dy: f32, mask: u32) {
+        let accel = self
+            .pointer_accel
+            .lock()
+            .map(|accel| *accel)
+            .unwrap_or_else(|_| PointerAccel::new());
+        let (dx, dy) = accel.apply(dx, dy);
         let (mut x, mut y) = self.cursor_pos;
         x += dx;
         y += dy;
@@ -262,19 +467,166 @@ impl DrmInput {
         }
     }
 
-    fn push_input(&self, event: InputEvent) {
+    fn push_input(&self, mut event: InputEvent) {
+        if let Ok(mut transform) = self.input_transform.lock() {
+            let size = (self.screen_size.0 as f32, self.screen_size.1 as f32);
+            transform.apply(size, &mut event);
+        }
         let notify = if let Ok(mut queue) = self.input_events.lock() {
             queue.push_event(event)
         } else {
-            None
+            Vec::new()
         };
 
-        if let Some(pid) = notify {
+        for pid in notify {
             notify_input_ready(pid);
         }
     }
 }
 
+/// Polls evdev devices on a dedicated thread so input latency does not depend on render pacing.
+///
+/// The thread blocks in `epoll_wait` on the device fds plus an eventfd used for shutdown and
+/// feeds the shared input queue and cursor state. Dropping the handle stops and joins it.
+pub struct DrmInputThread {
+    stop: Arc<AtomicBool>,
+    wake_fd: i32,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl DrmInputThread {
+    const MASKED_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+    pub fn spawn(input: DrmInput) -> Result<Self, String> {
+        let fds = input.raw_fds();
+        Self::spawn_watching(input, fds)
+    }
+
+    /// Spawns the thread blocking on `fds`, normally the descriptors of `input`'s devices.
+    fn spawn_watching(input: DrmInput, fds: Vec<i32>) -> Result<Self, String> {
+        let epoll_fd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+        if epoll_fd < 0 {
+            return Err(format!(
+                "epoll_create1 failed: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        let wake_fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if wake_fd < 0 {
+            let err = std::io::Error::last_os_error();
+            unsafe { libc::close(epoll_fd) };
+            return Err(format!("eventfd failed: {err}"));
+        }
+        for fd in fds.into_iter().chain(std::iter::once(wake_fd)) {
+            let mut event = libc::epoll_event {
+                events: libc::EPOLLIN as u32,
+                u64: fd as u64,
+            };
+            if unsafe { libc::epoll_ctl(epoll_fd, libc::EPOLL_CTL_ADD, fd, &mut event) } < 0 {
+                let err = std::io::Error::last_os_error();
+                unsafe {
+                    libc::close(wake_fd);
+                    libc::close(epoll_fd);
+                }
+                return Err(format!("epoll_ctl failed: {err}"));
+            }
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let handle = thread::Builder::new()
+            .name("scenic-driver-drm-input".to_string())
+            .spawn(move || {
+                run_input_thread(input, epoll_fd, thread_stop);
+                unsafe { libc::close(epoll_fd) };
+            })
+            .map_err(|err| {
+                unsafe { libc::close(wake_fd) };
+                format!("failed to spawn DRM input thread: {err}")
+            })?;
+
+        Ok(Self {
+            stop,
+            wake_fd,
+            handle: Some(handle),
+        })
+    }
+}
+
+impl Drop for DrmInputThread {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let value: u64 = 1;
+        unsafe {
+            libc::write(
+                self.wake_fd,
+                (&value as *const u64).cast(),
+                std::mem::size_of::<u64>(),
+            );
+        }
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+        unsafe { libc::close(self.wake_fd) };
+    }
+}
+
+fn run_input_thread(mut input: DrmInput, epoll_fd: i32, stop: Arc<AtomicBool>) {
+    let mut events = [libc::epoll_event { events: 0, u64: 0 }; 16];
+    let mut held_cursor_due = None;
+    while !stop.load(Ordering::Relaxed) {
+        if input.input_mask.load(Ordering::Relaxed) == 0 {
+            // Leave queued events in the kernel until input is requested, matching the
+            // render-loop behaviour, without spinning on readable fds.
+            thread::sleep(DrmInputThread::MASKED_POLL_INTERVAL);
+            continue;
+        }
+
+        // Wake up for a held-back cursor position even when no device is readable.
+        let timeout_ms = held_cursor_due.map_or(-1, |due: Instant| {
+            due.saturating_duration_since(Instant::now())
+                .as_millis()
+                .clamp(1, i32::MAX as u128) as i32
+        });
+        let ready = unsafe {
+            libc::epoll_wait(
+                epoll_fd,
+                events.as_mut_ptr(),
+                events.len() as i32,
+                timeout_ms,
+            )
+        };
+        if ready < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            log_line!("DRM input thread stopped: epoll_wait failed: {err}");
+            return;
+        }
+        if stop.load(Ordering::Relaxed) {
+            return;
+        }
+
+        // Unplugged devices report a hangup or error until they are removed, which would
+        // otherwise wake this level-triggered wait forever.
+        let mut readable = false;
+        for event in &events[..ready as usize] {
+            let (flags, fd) = (event.events, event.u64 as i32);
+            if flags & (libc::EPOLLHUP | libc::EPOLLERR) as u32 != 0 {
+                unsafe { libc::epoll_ctl(epoll_fd, libc::EPOLL_CTL_DEL, fd, std::ptr::null_mut()) };
+                input.remove_device(fd);
+            } else {
+                readable = true;
+            }
+        }
+        if readable {
+            input.poll();
+        }
+        held_cursor_due = flush_held_cursor(&input.input_events).1;
+    }
+}
+
 fn enumerate_devices(log_enabled: bool) -> Vec<InputDevice> {
     let mut devices = Vec::new();
     let entries = match fs::read_dir("/dev/input") {
@@ -294,15 +646,26 @@ fn enumerate_devices(log_enabled: bool) -> Vec<InputDevice> {
         set_non_blocking(device.as_raw_fd());
         let (abs_mode, info) = detect_abs_mode(&device);
         let (abs_x, abs_y) = init_abs_axes(&device);
+        let touch_slots = if abs_mode == AbsMode::Absolute {
+            init_touch_slots(&device)
+        } else {
+            None
+        };
+        let class = classify_device(&device);
         if log_enabled {
             let name = device.name().unwrap_or("unknown");
-            eprintln!(
-                "drm_input device={:?} name=\"{}\" abs_mode={:?} {}",
-                path, name, abs_mode, info
+            log_line!(
+                "drm_input device={:?} name=\"{}\" class={:?} abs_mode={:?} {}",
+                path,
+                name,
+                class,
+                abs_mode,
+                info
             );
         }
         devices.push(InputDevice {
             device,
+            class,
             abs_x,
             abs_y,
             abs_x_dirty: false,
@@ -311,6 +674,7 @@ fn enumerate_devices(log_enabled: bool) -> Vec<InputDevice> {
             last_abs_scaled: None,
             touch_active: false,
             touch_tracking: false,
+            touch_slots,
         });
     }
 
@@ -423,6 +787,22 @@ fn init_abs_axes(device: &Device) -> (Option<AbsAxisState>, Option<AbsAxisState>
     (abs_x, abs_y)
 }
 
+/// Slot state for multi-touch screens that report contacts per slot.
+fn init_touch_slots(device: &Device) -> Option<TouchSlots> {
+    if !device
+        .supported_absolute_axes()
+        .is_some_and(|axes| axes.contains(AbsoluteAxisType::ABS_MT_SLOT))
+    {
+        return None;
+    }
+    let abs_state = device.get_abs_state().ok()?;
+    let info = |axis: AbsoluteAxisType| abs_state.get(axis.0 as usize);
+    let x = axis_state_from_abs(info(AbsoluteAxisType::ABS_MT_POSITION_X))?;
+    let y = axis_state_from_abs(info(AbsoluteAxisType::ABS_MT_POSITION_Y))?;
+    let slots = info(AbsoluteAxisType::ABS_MT_SLOT).map_or(1, |slot| slot.maximum + 1);
+    Some(TouchSlots::new(x, y, usize::try_from(slots).unwrap_or(1)))
+}
+
 fn axis_state_from_abs(info: Option<&input_absinfo>) -> Option<AbsAxisState> {
     info.map(|info| AbsAxisState {
         value: info.value,
@@ -431,6 +811,30 @@ fn axis_state_from_abs(info: Option<&input_absinfo>) -> Option<AbsAxisState> {
     })
 }
 
+/// Picks one class per evdev node. Touch wins over the phantom keys many touchscreens
+/// advertise, so their key events can be masked separately from real keyboards.
+fn classify_device(device: &Device) -> DeviceClass {
+    let keys = device.supported_keys();
+    let has_key = |key: Key| keys.is_some_and(|keys| keys.contains(key));
+    let has_mt = device.supported_absolute_axes().is_some_and(|axes| {
+        axes.contains(AbsoluteAxisType::ABS_MT_POSITION_X)
+            || axes.contains(AbsoluteAxisType::ABS_MT_SLOT)
+    });
+    let has_rel = device
+        .supported_relative_axes()
+        .is_some_and(|axes| axes.contains(RelativeAxisType::REL_X));
+
+    if device.properties().contains(PropType::DIRECT) || has_mt || has_key(Key::BTN_TOUCH) {
+        DeviceClass::Touch
+    } else if has_key(Key::BTN_SOUTH) || has_key(Key::BTN_TRIGGER) {
+        DeviceClass::Gamepad
+    } else if has_rel || has_key(Key::BTN_LEFT) {
+        DeviceClass::Pointer
+    } else {
+        DeviceClass::Keyboard
+    }
+}
+
 fn detect_abs_mode(device: &Device) -> (AbsMode, String) {
     let has_abs = device.supported_absolute_axes().is_some_and(|axes| {
         axes.contains(AbsoluteAxisType::ABS_X) && axes.contains(AbsoluteAxisType::ABS_Y)
@@ -796,6 +1200,9 @@ mod tests {
         RelativeAxisType, UinputAbsSetup, uinput::VirtualDevice, uinput::VirtualDeviceBuilder,
     };
 
+    use crate::input_transform::Rotation;
+    use crate::renderer::Overscan;
+
     #[test]
     fn key_to_codepoint_respects_shift_and_caps() {
         let mut mods = Modifiers::default();
@@ -865,6 +1272,66 @@ mod tests {
         assert_eq!(scale_abs_value(state, 100), 99.0);
     }
 
+    #[test]
+    fn wheel_frames_prefer_high_resolution_steps() {
+        assert_eq!(PendingScroll::default().lines(), None);
+        let detents = PendingScroll {
+            detents: (0.0, -1.0),
+            hi_res: (None, None),
+        };
+        assert_eq!(detents.lines(), Some((0.0, -1.0)));
+        // A free-spinning wheel reports a third of a detent before the first whole one.
+        let hi_res = PendingScroll {
+            detents: (1.0, 0.0),
+            hi_res: (None, Some(40.0)),
+        };
+        assert_eq!(hi_res.lines(), Some((1.0, 40.0 / 120.0)));
+    }
+
+    #[test]
+    fn touch_slots_report_each_contact_by_tracking_id() {
+        let axis = AbsAxisState {
+            value: 0,
+            min: 0,
+            max: 1000,
+        };
+        let mut slots = TouchSlots::new(axis, axis, 2);
+        let touch = |id, phase, x, y| InputEvent::Touch { id, phase, x, y };
+        slots.update(AbsoluteAxisType::ABS_MT_TRACKING_ID, 7);
+        slots.update(AbsoluteAxisType::ABS_MT_POSITION_X, 500);
+        slots.update(AbsoluteAxisType::ABS_MT_POSITION_Y, 250);
+        slots.update(AbsoluteAxisType::ABS_MT_SLOT, 1);
+        slots.update(AbsoluteAxisType::ABS_MT_TRACKING_ID, 8);
+        slots.update(AbsoluteAxisType::ABS_MT_POSITION_X, 1000);
+        assert_eq!(
+            slots.take_events((1001, 1001)),
+            vec![
+                touch(7, TouchPhase::Down, 500.0, 250.0),
+                touch(8, TouchPhase::Down, 1000.0, 0.0),
+            ]
+        );
+        assert!(slots.take_events((1001, 1001)).is_empty());
+
+        slots.update(AbsoluteAxisType::ABS_MT_SLOT, 0);
+        slots.update(AbsoluteAxisType::ABS_MT_POSITION_Y, 300);
+        slots.update(AbsoluteAxisType::ABS_MT_SLOT, 1);
+        slots.update(AbsoluteAxisType::ABS_MT_TRACKING_ID, -1);
+        assert_eq!(
+            slots.take_events((1001, 1001)),
+            vec![
+                touch(7, TouchPhase::Move, 500.0, 300.0),
+                touch(8, TouchPhase::Up, 1000.0, 0.0),
+            ]
+        );
+        // Slots beyond the device's count are ignored.
+        slots.update(AbsoluteAxisType::ABS_MT_SLOT, 5);
+        slots.update(AbsoluteAxisType::ABS_MT_TRACKING_ID, -1);
+        assert_eq!(
+            slots.take_events((1001, 1001)),
+            vec![touch(7, TouchPhase::Up, 500.0, 300.0)]
+        );
+    }
+
     #[test]
     #[cfg(target_os = "linux")]
     fn drm_input_reads_uinput_events() {
@@ -880,6 +1347,7 @@ mod tests {
         let (abs_x, abs_y) = init_abs_axes(&device);
         let input_device = InputDevice {
             device,
+            class: DeviceClass::Pointer,
             abs_x,
             abs_y,
             abs_x_dirty: false,
@@ -888,26 +1356,17 @@ mod tests {
             last_abs_scaled: None,
             touch_active: false,
             touch_tracking: false,
+            touch_slots: None,
         };
 
-        let input_mask = Arc::new(AtomicU32::new(
+        let mut drm_input = test_input(
             INPUT_MASK_KEY
                 | INPUT_MASK_CODEPOINT
                 | INPUT_MASK_CURSOR_POS
                 | INPUT_MASK_CURSOR_BUTTON,
-        ));
-        let queue = Arc::new(Mutex::new(InputQueue::new()));
-        let cursor_state = Arc::new(Mutex::new(CursorState::new()));
-        let mut drm_input = DrmInput {
-            devices: vec![input_device],
-            cursor_pos: (0.0, 0.0),
-            modifiers: Modifiers::default(),
-            caps_lock: false,
-            screen_size: (100, 50),
-            input_mask,
-            input_events: Arc::clone(&queue),
-            cursor_state,
-        };
+        );
+        drm_input.devices = vec![input_device];
+        let queue = Arc::clone(&drm_input.input_events);
 
         let _ = vdev.emit(&[
             EvdevInputEvent::new(EventType::ABSOLUTE, AbsoluteAxisType::ABS_X.0, 512),
@@ -964,6 +1423,127 @@ mod tests {
         assert!((y - expected_y).abs() < 1.0);
     }
 
+    #[test]
+    fn key_events_carry_scancode_and_repeat_count() {
+        let mut drm_input = test_input(INPUT_MASK_KEY);
+        let input_events = Arc::clone(&drm_input.input_events);
+
+        for value in [1, 2, 2, 0] {
+            drm_input.handle_key_event(Key::KEY_A, value, INPUT_MASK_KEY);
+        }
+
+        let events = input_events.lock().expect("input queue").drain();
+        let keys: Vec<(u8, u32, u32)> = events
+            .iter()
+            .filter_map(|event| match event {
+                InputEvent::Key {
+                    action,
+                    scancode,
+                    repeat,
+                    ..
+                } => Some((*action, *scancode, *repeat)),
+                _ => None,
+            })
+            .collect();
+        let code = u32::from(Key::KEY_A.code());
+        assert_eq!(
+            keys,
+            vec![
+                (ACTION_PRESS, code, 0),
+                (ACTION_PRESS, code, 1),
+                (ACTION_PRESS, code, 2),
+                (ACTION_RELEASE, code, 0),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn drm_input_thread_stops_on_drop() {
+        let drm_input = test_input(INPUT_MASK_KEY);
+        let input_transform = Arc::clone(&drm_input.input_transform);
+
+        let thread = DrmInputThread::spawn(drm_input).expect("spawn failed");
+        input_transform.lock().expect("input transform").overscan = Overscan {
+            left: 10.0,
+            ..Overscan::default()
+        };
+        drop(thread);
+    }
+
+    #[test]
+    fn positions_go_through_shared_input_transform() {
+        let mut drm_input = test_input(INPUT_MASK_CURSOR_POS);
+        let input_events = Arc::clone(&drm_input.input_events);
+        let input_transform = Arc::clone(&drm_input.input_transform);
+
+        input_transform.lock().expect("input transform").rotation = Rotation::Cw180;
+        drm_input.handle_abs_position(10.0, 5.0, INPUT_MASK_CURSOR_POS);
+
+        assert_eq!(drm_input.cursor_pos, (10.0, 5.0));
+        let events = input_events.lock().expect("input queue").drain();
+        assert!(matches!(
+            events.as_slice(),
+            [InputEvent::CursorPos { x, y }] if *x == 90.0 && *y == 45.0
+        ));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn drm_input_thread_blocks_after_a_watched_fd_hangs_up() {
+        use std::os::unix::thread::JoinHandleExt;
+
+        let mut pipe = [0; 2];
+        assert_eq!(
+            unsafe { libc::pipe2(pipe.as_mut_ptr(), libc::O_CLOEXEC) },
+            0
+        );
+        unsafe { libc::close(pipe[1]) };
+        let thread = DrmInputThread::spawn_watching(test_input(INPUT_MASK_KEY), vec![pipe[0]])
+            .expect("spawn failed");
+        let pthread = thread.handle.as_ref().expect("handle").as_pthread_t();
+        let mut clock = 0;
+        assert_eq!(
+            unsafe { libc::pthread_getcpuclockid(pthread, &mut clock) },
+            0
+        );
+        let cpu_time = || {
+            let mut ts = libc::timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            };
+            unsafe { libc::clock_gettime(clock, &mut ts) };
+            Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+        };
+
+        thread::sleep(Duration::from_millis(50));
+        let before = cpu_time();
+        thread::sleep(Duration::from_millis(200));
+        assert!(cpu_time() - before < Duration::from_millis(20));
+        drop(thread);
+        unsafe { libc::close(pipe[0]) };
+    }
+
+    /// A `DrmInput` without devices on a 100x50 screen, delivering the `input_mask` events.
+    fn test_input(input_mask: u32) -> DrmInput {
+        DrmInput {
+            devices: Vec::new(),
+            cursor_pos: (0.0, 0.0),
+            modifiers: Modifiers::default(),
+            caps_lock: false,
+            key_repeat: 0,
+            screen_size: (100, 50),
+            input_mask: Arc::new(AtomicU32::new(input_mask)),
+            input_events: Arc::new(Mutex::new(InputQueue::new())),
+            cursor_state: Arc::new(Mutex::new(CursorState::new())),
+            pointer_accel: Arc::new(Mutex::new(PointerAccel::new())),
+            device_masks: Arc::new(DeviceClassMasks::new()),
+            pending_rel: (0.0, 0.0),
+            pending_scroll: PendingScroll::default(),
+            input_transform: Arc::new(Mutex::new(InputTransform::new())),
+        }
+    }
+
     #[cfg(target_os = "linux")]
     fn build_virtual_device() -> Option<(VirtualDevice, PathBuf)> {
         let mut keys = AttributeSet::<Key>::new();
@@ -0,0 +1,221 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use skia_safe::{AlphaType, ColorType, Data, EncodedImageFormat, ImageInfo, images};
+
+use crate::input::InputEvent;
+
+const MAX_INPUT_EVENTS: usize = 512;
+const MAX_LOG_LINES: usize = 256;
+
+static RECENT_LOG: Mutex<VecDeque<(u64, String)>> = Mutex::new(VecDeque::new());
+
+/// Logs to stderr like `eprintln!` and keeps the line for flight recorder dumps.
+macro_rules! log_line {
+    ($($arg:tt)*) => {
+        $crate::flight_recorder::record_log(format!($($arg)*))
+    };
+}
+pub(crate) use log_line;
+
+pub fn record_log(line: String) {
+    eprintln!("{line}");
+    if let Ok(mut log) = RECENT_LOG.lock() {
+        log.push_back((now_ms(), line));
+        if log.len() > MAX_LOG_LINES {
+            log.pop_front();
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+/// A downsampled copy of a rendered frame, stored as opaque RGBA.
+#[derive(Debug)]
+pub struct RecordedFrame {
+    pub timestamp_ms: u64,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// Ring buffers of recent frames and input events for post-mortem debugging.
+///
+/// Recording is off until [`FlightRecorder::configure`] sets a frame count. Frames are
+/// scaled down to at most `max_width` pixels wide by the renderer before they are kept.
+/// Log lines written with [`log_line!`] are kept process-wide and included in every dump.
+#[derive(Debug, Default)]
+pub struct FlightRecorder {
+    capacity: AtomicU32,
+    max_width: AtomicU32,
+    frames: Mutex<VecDeque<RecordedFrame>>,
+    events: Mutex<VecDeque<(u64, String)>>,
+}
+
+impl FlightRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps the last `frames` frames (`0` disables recording) and drops recorded data.
+    pub fn configure(&self, frames: u32, max_width: u32) -> Result<(), String> {
+        if frames > 0 && max_width == 0 {
+            return Err("flight recorder max_width must be positive".to_string());
+        }
+        let mut recorded = self
+            .frames
+            .lock()
+            .map_err(|_| "flight recorder lock poisoned".to_string())?;
+        recorded.clear();
+        self.events
+            .lock()
+            .map_err(|_| "flight recorder lock poisoned".to_string())?
+            .clear();
+        self.capacity.store(frames, Ordering::Relaxed);
+        self.max_width.store(max_width, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity.load(Ordering::Relaxed) > 0
+    }
+
+    /// Returns the size a frame of `size` is recorded at.
+    pub fn frame_size(&self, size: (u32, u32)) -> (u32, u32) {
+        let (width, height) = size;
+        let max_width = self.max_width.load(Ordering::Relaxed);
+        if width <= max_width || width == 0 {
+            return size;
+        }
+        let scaled = (height as u64 * max_width as u64 / width as u64).max(1) as u32;
+        (max_width, scaled)
+    }
+
+    pub fn record_frame(&self, width: u32, height: u32, pixels: Vec<u8>) {
+        let capacity = self.capacity.load(Ordering::Relaxed) as usize;
+        if capacity == 0 {
+            return;
+        }
+        if let Ok(mut frames) = self.frames.lock() {
+            while frames.len() >= capacity {
+                frames.pop_front();
+            }
+            frames.push_back(RecordedFrame {
+                timestamp_ms: now_ms(),
+                width,
+                height,
+                pixels,
+            });
+        }
+    }
+
+    pub fn record_input(&self, event: &InputEvent) {
+        if !self.is_enabled() {
+            return;
+        }
+        if let Ok(mut events) = self.events.lock() {
+            events.push_back((now_ms(), format!("{event:?}")));
+            if events.len() > MAX_INPUT_EVENTS {
+                events.pop_front();
+            }
+        }
+    }
+
+    /// Writes the recorded frames as `frame_NNN.png` (oldest first, timestamps in
+    /// `frames.log`), `input.log` and `driver.log` into `dir`, creating it if needed.
+    /// Returns the number of frames written.
+    pub fn dump(&self, dir: &Path) -> Result<usize, String> {
+        fs::create_dir_all(dir)
+            .map_err(|err| format!("failed to create {}: {err}", dir.display()))?;
+
+        let frames = self
+            .frames
+            .lock()
+            .map_err(|_| "flight recorder lock poisoned".to_string())?;
+        let mut index = Vec::with_capacity(frames.len());
+        for (i, frame) in frames.iter().enumerate() {
+            let name = format!("frame_{i:03}.png");
+            fs::write(dir.join(&name), encode_png(frame)?)
+                .map_err(|err| format!("failed to write {name}: {err}"))?;
+            index.push(format!("{} {name}", frame.timestamp_ms));
+        }
+        let count = frames.len();
+        drop(frames);
+
+        let events = self
+            .events
+            .lock()
+            .map_err(|_| "flight recorder lock poisoned".to_string())?
+            .iter()
+            .map(|(timestamp_ms, event)| format!("{timestamp_ms} {event}"))
+            .collect::<Vec<_>>();
+        let log = RECENT_LOG
+            .lock()
+            .map_err(|_| "flight recorder log lock poisoned".to_string())?
+            .iter()
+            .map(|(timestamp_ms, line)| format!("{timestamp_ms} {line}"))
+            .collect::<Vec<_>>();
+
+        for (name, lines) in [
+            ("frames.log", index),
+            ("input.log", events),
+            ("driver.log", log),
+        ] {
+            let mut contents = lines.join("\n");
+            contents.push('\n');
+            fs::write(dir.join(name), contents)
+                .map_err(|err| format!("failed to write {name}: {err}"))?;
+        }
+        Ok(count)
+    }
+}
+
+fn encode_png(frame: &RecordedFrame) -> Result<Vec<u8>, String> {
+    let info = ImageInfo::new(
+        (frame.width as i32, frame.height as i32),
+        ColorType::RGBA8888,
+        AlphaType::Opaque,
+        None,
+    );
+    images::raster_from_data(&info, Data::new_copy(&frame.pixels), info.min_row_bytes())
+        .and_then(|image| image.encode(None, EncodedImageFormat::PNG, None))
+        .map(|data| data.as_bytes().to_vec())
+        .ok_or_else(|| "failed to encode flight recorder frame".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flight_recorder_keeps_the_last_frames() {
+        let recorder = FlightRecorder::new();
+        recorder.record_frame(1, 1, vec![0; 4]);
+        recorder.record_input(&InputEvent::CursorPos { x: 1.0, y: 2.0 });
+        assert!(recorder.frames.lock().unwrap().is_empty());
+        assert!(recorder.events.lock().unwrap().is_empty());
+
+        recorder.configure(2, 160).expect("configure");
+        assert_eq!(recorder.frame_size((640, 480)), (160, 120));
+        assert_eq!(recorder.frame_size((100, 50)), (100, 50));
+        for value in 1..=3 {
+            recorder.record_frame(1, 1, vec![value; 4]);
+        }
+        recorder.record_input(&InputEvent::CursorPos { x: 1.0, y: 2.0 });
+
+        let frames = recorder.frames.lock().unwrap();
+        let first: Vec<u8> = frames.iter().map(|frame| frame.pixels[0]).collect();
+        assert_eq!(first, vec![2, 3]);
+        drop(frames);
+        assert_eq!(recorder.events.lock().unwrap().len(), 1);
+        assert!(recorder.configure(1, 0).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+use skia_safe::{Canvas, Color, Paint, PaintStyle, RRect, Rect};
+
+/// Direction of a `focus_next` move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub fn parse(direction: &str) -> Result<Self, String> {
+        match direction {
+            "up" => Ok(Self::Up),
+            "down" => Ok(Self::Down),
+            "left" => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            other => Err(format!("unsupported focus direction: {other}")),
+        }
+    }
+
+    /// `rect` as `(along, across)` extents, with `along` growing in this direction.
+    fn project(self, (x, y, w, h): (f32, f32, f32, f32)) -> ((f32, f32), (f32, f32)) {
+        match self {
+            Self::Right => ((x, x + w), (y, y + h)),
+            Self::Left => ((-(x + w), -x), (y, y + h)),
+            Self::Down => ((y, y + h), (x, x + w)),
+            Self::Up => ((-(y + h), -y), (x, x + w)),
+        }
+    }
+}
+
+/// Outline drawn natively around the focused region.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FocusRing {
+    pub color: Color,
+    pub width: f32,
+    pub radius: f32,
+}
+
+impl FocusRing {
+    /// Area the ring covers around `rect`, for damage.
+    fn bounds(&self, (x, y, w, h): (f32, f32, f32, f32)) -> Rect {
+        Rect::from_xywh(x, y, w, h).with_outset((self.width + 1.0, self.width + 1.0))
+    }
+}
+
+/// Focusable scene-space rects `(x, y, w, h)` for keypad and remote-driven UIs.
+///
+/// `focus_next` moves the focus to the region nearest the focused one in a direction, so
+/// the Elixir side only handles the key press. A candidate has to lie further along the
+/// direction than the focused region; candidates are ranked by the gap along the
+/// direction plus twice the gap across it (zero while the two overlap across it), then
+/// by how far their centers are apart across it, then by registration order.
+#[derive(Debug, Default)]
+pub struct FocusRegions {
+    regions: Vec<(String, (f32, f32, f32, f32))>,
+    focused: Option<String>,
+    pub ring: Option<FocusRing>,
+}
+
+impl FocusRegions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the regions. The focus stays on its region if the id is still registered.
+    pub fn set(&mut self, regions: Vec<(String, (f32, f32, f32, f32))>) -> Result<(), String> {
+        for (index, (id, _)) in regions.iter().enumerate() {
+            if regions[..index].iter().any(|(other, _)| other == id) {
+                return Err(format!("duplicate focus region {id}"));
+            }
+        }
+        self.regions = regions;
+        if self.focused_rect().is_none() {
+            self.focused = None;
+        }
+        Ok(())
+    }
+
+    pub fn focused(&self) -> Option<&str> {
+        self.focused.as_deref()
+    }
+
+    /// Focuses region `id`, or clears the focus with `None`.
+    pub fn focus(&mut self, id: Option<&str>) -> Result<(), String> {
+        if let Some(id) = id
+            && !self.regions.iter().any(|(region, _)| region == id)
+        {
+            return Err(format!("no focus region {id}"));
+        }
+        self.focused = id.map(str::to_string);
+        Ok(())
+    }
+
+    /// Moves the focus towards `direction` and returns the focused region. Without a
+    /// focus the first region is focused; the focus stays put when nothing lies that way.
+    pub fn next(&mut self, direction: Direction) -> Option<&str> {
+        let Some(current) = self.focused_rect() else {
+            self.focused = self.regions.first().map(|(id, _)| id.clone());
+            return self.focused();
+        };
+        let (along, across) = direction.project(current);
+        let center = |(low, high): (f32, f32)| (low + high) / 2.0;
+        let mut best: Option<(f32, f32, &str)> = None;
+        for (id, rect) in &self.regions {
+            if Some(id.as_str()) == self.focused() {
+                continue;
+            }
+            let (cand_along, cand_across) = direction.project(*rect);
+            if center(cand_along) <= center(along) || cand_along.1 <= along.1 {
+                continue;
+            }
+            let gap = (cand_along.0 - along.1).max(0.0);
+            let side_gap = (cand_across.0 - across.1)
+                .max(across.0 - cand_across.1)
+                .max(0.0);
+            let score = gap + side_gap * 2.0;
+            let offset = (center(cand_across) - center(across)).abs();
+            if best.is_none_or(|(best_score, best_offset, _)| {
+                (score, offset) < (best_score, best_offset)
+            }) {
+                best = Some((score, offset, id));
+            }
+        }
+        if let Some((_, _, id)) = best {
+            self.focused = Some(id.to_string());
+        }
+        self.focused()
+    }
+
+    /// Area the focus ring currently covers, if one is drawn.
+    pub fn ring_bounds(&self) -> Option<Rect> {
+        Some(self.ring?.bounds(self.focused_rect()?))
+    }
+
+    fn focused_rect(&self) -> Option<(f32, f32, f32, f32)> {
+        let focused = self.focused.as_deref()?;
+        self.regions
+            .iter()
+            .find(|(id, _)| id == focused)
+            .map(|(_, rect)| *rect)
+    }
+}
+
+/// Draws the focus ring around the focused region, on a canvas whose matrix maps scene
+/// coordinates. The ring sits just outside the region so it never covers its content.
+pub fn draw(canvas: &Canvas, regions: &FocusRegions) {
+    let (Some(ring), Some((x, y, w, h))) = (regions.ring, regions.focused_rect()) else {
+        return;
+    };
+    let outset = ring.width / 2.0;
+    let rect = Rect::from_xywh(x, y, w, h).with_outset((outset, outset));
+    let radius = ring.radius + outset;
+    let mut paint = Paint::default();
+    paint.set_anti_alias(true);
+    paint.set_style(PaintStyle::Stroke);
+    paint.set_stroke_width(ring.width);
+    paint.set_color(ring.color);
+    canvas.draw_rrect(RRect::new_rect_xy(rect, radius, radius), &paint);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_pick_the_nearest_region_in_the_direction() {
+        let mut focus = FocusRegions::new();
+        focus
+            .set(vec![
+                ("a".to_string(), (0.0, 0.0, 40.0, 40.0)),
+                ("b".to_string(), (60.0, 0.0, 40.0, 40.0)),
+                ("c".to_string(), (200.0, 10.0, 40.0, 40.0)),
+                ("d".to_string(), (50.0, 60.0, 40.0, 40.0)),
+                ("e".to_string(), (0.0, 300.0, 40.0, 40.0)),
+            ])
+            .expect("regions");
+        assert_eq!(focus.next(Direction::Down), Some("a"));
+        assert_eq!(focus.next(Direction::Right), Some("b"));
+        assert_eq!(focus.next(Direction::Right), Some("c"));
+        assert_eq!(focus.next(Direction::Up), Some("b"));
+        assert_eq!(focus.next(Direction::Left), Some("a"));
+        assert_eq!(focus.next(Direction::Down), Some("d"));
+        assert_eq!(focus.next(Direction::Down), Some("e"));
+        assert_eq!(focus.next(Direction::Down), Some("e"));
+        assert_eq!(focus.next(Direction::Up), Some("d"));
+
+        assert!(focus.focus(Some("z")).is_err());
+        focus.focus(Some("e")).expect("focus");
+        assert_eq!(focus.ring_bounds(), None);
+        focus.ring = Some(FocusRing {
+            color: Color::WHITE,
+            width: 2.0,
+            radius: 4.0,
+        });
+        assert_eq!(
+            focus.ring_bounds(),
+            Some(Rect::from_xywh(-3.0, 297.0, 46.0, 46.0))
+        );
+        focus
+            .set(vec![("a".to_string(), (0.0, 0.0, 40.0, 40.0))])
+            .expect("regions");
+        assert_eq!(focus.focused(), None);
+        assert!(
+            focus
+                .set(vec![
+                    ("a".to_string(), (0.0, 0.0, 1.0, 1.0)),
+                    ("a".to_string(), (2.0, 0.0, 1.0, 1.0)),
+                ])
+                .is_err()
+        );
+    }
+}
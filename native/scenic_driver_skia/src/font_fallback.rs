@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Mutex;
+
+use skia_safe::{Font, FontMgr, FontStyle, TextBlob, TextBlobBuilder, Typeface};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Typefaces tried for characters the font of a text run has no glyph for, so emoji and
+/// other scripts draw instead of missing-glyph boxes.
+///
+/// The fonts of `chain` are tried in order; after them the system font manager is asked
+/// for an installed font covering the character (Noto Color Emoji for emoji on most
+/// Linux images) unless `system` is off. System matches are remembered per character.
+#[derive(Debug)]
+pub struct FontFallback {
+    chain: Vec<Typeface>,
+    system: bool,
+    matches: Mutex<HashMap<char, Option<Typeface>>>,
+}
+
+impl FontFallback {
+    pub fn new(chain: Vec<Typeface>, system: bool) -> Self {
+        Self {
+            chain,
+            system,
+            matches: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The first fallback typeface with a glyph for `ch`.
+    pub fn typeface_for(&self, ch: char) -> Option<Typeface> {
+        if let Some(typeface) = self
+            .chain
+            .iter()
+            .find(|typeface| typeface.unichar_to_glyph(ch as i32) != 0)
+        {
+            return Some(typeface.clone());
+        }
+        if !self.system {
+            return None;
+        }
+        let Ok(mut matches) = self.matches.lock() else {
+            return system_match(ch);
+        };
+        matches
+            .entry(ch)
+            .or_insert_with(|| system_match(ch))
+            .clone()
+    }
+
+    /// Splits `text` into runs of whole graphemes, so emoji sequences stay in one font.
+    /// A grapheme uses `font` unless one of its visible characters is missing there and a
+    /// fallback typeface covers it; `None` marks runs drawn with `font` itself.
+    pub fn runs(&self, text: &str, font: &Font) -> Vec<(Range<usize>, Option<Typeface>)> {
+        let mut runs: Vec<(Range<usize>, Option<Typeface>)> = Vec::new();
+        for (offset, grapheme) in text.grapheme_indices(true) {
+            let end = offset + grapheme.len();
+            let typeface = grapheme
+                .chars()
+                .find(|&ch| !is_invisible(ch) && font.unichar_to_glyph(ch as i32) == 0)
+                .and_then(|ch| self.typeface_for(ch));
+            match runs.last_mut() {
+                Some((range, last))
+                    if last.as_ref().map(Typeface::unique_id)
+                        == typeface.as_ref().map(Typeface::unique_id) =>
+                {
+                    range.end = end;
+                }
+                _ => runs.push((offset..end, typeface)),
+            }
+        }
+        runs
+    }
+
+    /// Advance width of `text` as `shape` lays it out.
+    pub fn measure(&self, text: &str, font: &Font) -> f32 {
+        self.runs(text, font)
+            .into_iter()
+            .map(|(range, typeface)| run_font(font, typeface).measure_str(&text[range], None).0)
+            .sum()
+    }
+
+    /// Builds one blob for `text` with each run in its own typeface at the size of `font`,
+    /// and returns it with its advance width. `None` when nothing could be shaped.
+    pub fn shape(&self, text: &str, font: &Font) -> Option<(TextBlob, f32)> {
+        let runs = self.runs(text, font);
+        if let [(_, None)] = runs.as_slice() {
+            let blob = TextBlob::from_str(text, font)?;
+            return Some((blob, font.measure_str(text, None).0));
+        }
+        let mut builder = TextBlobBuilder::new();
+        let mut x = 0.0;
+        for (range, typeface) in runs {
+            let font = run_font(font, typeface);
+            let run = &text[range];
+            let glyphs = font.str_to_glyphs_vec(run);
+            if !glyphs.is_empty() {
+                builder
+                    .alloc_run(&font, glyphs.len(), (x, 0.0), None)
+                    .copy_from_slice(&glyphs);
+            }
+            x += font.measure_str(run, None).0;
+        }
+        Some((builder.make()?, x))
+    }
+}
+
+impl Default for FontFallback {
+    fn default() -> Self {
+        Self::new(Vec::new(), true)
+    }
+}
+
+/// Control characters and invisible format characters such as zero-width joiners and
+/// variation selectors, which fonts are not expected to have glyphs for.
+pub fn is_invisible(ch: char) -> bool {
+    ch.is_control()
+        || matches!(
+            ch,
+            '\u{200B}'..='\u{200F}'
+                | '\u{2060}'..='\u{2064}'
+                | '\u{FE00}'..='\u{FE0F}'
+                | '\u{E0020}'..='\u{E007F}'
+        )
+}
+
+fn run_font(font: &Font, typeface: Option<Typeface>) -> Font {
+    let mut font = font.clone();
+    if let Some(typeface) = typeface {
+        font.set_typeface(typeface);
+    }
+    font
+}
+
+fn system_match(ch: char) -> Option<Typeface> {
+    FontMgr::new().match_family_style_character("", FontStyle::normal(), &[], ch as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_switch_to_the_first_font_covering_a_character() {
+        let fm = FontMgr::new();
+        let roboto = fm
+            .new_from_data(include_bytes!("../../../assets/fonts/roboto.ttf"), 0)
+            .expect("roboto");
+        let mono = fm
+            .new_from_data(include_bytes!("../../../assets/fonts/roboto_mono.ttf"), 0)
+            .expect("roboto mono");
+        let font = Font::new(roboto, 20.0);
+
+        // Roboto has no DIVISION SLASH; Roboto Mono does.
+        let text = "a\u{2215}\u{2215}b\u{200D}";
+        let none = FontFallback::new(Vec::new(), false);
+        let runs = none.runs(text, &font);
+        assert!(matches!(runs.as_slice(), [(range, None)] if *range == (0..11)));
+
+        let fallback = FontFallback::new(vec![mono.clone()], false);
+        let runs = fallback.runs(text, &font);
+        let ranges: Vec<_> = runs.iter().map(|(range, _)| range.clone()).collect();
+        assert_eq!(ranges, vec![0..1, 1..7, 7..11]);
+        assert!(runs[0].1.is_none() && runs[2].1.is_none());
+        assert_eq!(
+            runs[1].1.as_ref().map(Typeface::unique_id),
+            Some(mono.unique_id())
+        );
+
+        let (blob, width) = fallback.shape(text, &font).expect("blob");
+        assert!(!blob.bounds().is_empty());
+        assert!((width - fallback.measure(text, &font)).abs() < 1e-3);
+        assert!(width > font.measure_str("ab", None).0);
+        assert!(fallback.shape("", &font).is_none());
+    }
+}
@@ -0,0 +1,131 @@
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// RGBA pixels of one rendered frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapturedFrame {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+struct Captures {
+    requested: u64,
+    /// Highest ticket a frame started drawing for.
+    claimed: u64,
+    captured: u64,
+    frame: Option<CapturedFrame>,
+}
+
+/// Hands frames read back on the render thread to NIF callers.
+///
+/// GL, Vulkan and DRM surfaces belong to the backend thread, so a caller asks for the
+/// next frame, wakes the renderer and waits. A frame started while a capture is pending
+/// reads the surface back once drawn; captures asked for mid-frame wait for the frame
+/// after, so they never see a scene older than the request. Callers waiting at the same
+/// time share the frame.
+#[derive(Debug, Default)]
+pub struct FrameCapture {
+    captures: Mutex<Captures>,
+    ready: Condvar,
+}
+
+impl FrameCapture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks for the next rendered frame and returns the ticket to wait on.
+    pub fn request(&self) -> Result<u64, String> {
+        let mut captures = self
+            .captures
+            .lock()
+            .map_err(|_| "frame capture lock poisoned".to_string())?;
+        if captures.requested == captures.claimed {
+            captures.requested += 1;
+        }
+        Ok(captures.requested)
+    }
+
+    /// Ticket of the capture a frame starting now has to fulfil, if any.
+    pub fn pending(&self) -> Option<u64> {
+        let mut captures = self.captures.lock().ok()?;
+        if captures.requested == captures.claimed {
+            return None;
+        }
+        captures.claimed = captures.requested;
+        Some(captures.claimed)
+    }
+
+    /// Stores the frame for `ticket` and wakes the callers waiting for it.
+    pub fn fulfil(&self, ticket: u64, frame: CapturedFrame) {
+        let Ok(mut captures) = self.captures.lock() else {
+            return;
+        };
+        captures.captured = captures.captured.max(ticket);
+        captures.frame = Some(frame);
+        self.ready.notify_all();
+    }
+
+    /// Waits until the frame for `ticket` has been captured.
+    pub fn wait(&self, ticket: u64, timeout: Duration) -> Result<CapturedFrame, String> {
+        let deadline = Instant::now() + timeout;
+        let mut captures = self
+            .captures
+            .lock()
+            .map_err(|_| "frame capture lock poisoned".to_string())?;
+        while captures.captured < ticket {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err("timed out waiting for a rendered frame".to_string());
+            }
+            captures = self
+                .ready
+                .wait_timeout(captures, remaining)
+                .map_err(|_| "frame capture lock poisoned".to_string())?
+                .0;
+        }
+        captures
+            .frame
+            .clone()
+            .ok_or_else(|| "frame not captured".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn waiting_callers_get_the_next_frame() {
+        let capture = Arc::new(FrameCapture::new());
+        assert_eq!(capture.pending(), None);
+        let ticket = capture.request().expect("request");
+        assert_eq!(capture.request(), Ok(ticket));
+        assert!(capture.wait(ticket, Duration::from_millis(10)).is_err());
+
+        // A capture asked for while that frame is drawn waits for the next one.
+        let frame_start = capture.pending().expect("pending");
+        assert_eq!(capture.pending(), None);
+        let later = capture.request().expect("request");
+        assert_eq!(later, ticket + 1);
+        let renderer = Arc::clone(&capture);
+        let render = thread::spawn(move || {
+            renderer.fulfil(
+                frame_start,
+                CapturedFrame {
+                    width: 1,
+                    height: 1,
+                    pixels: vec![1, 2, 3, 255],
+                },
+            );
+        });
+        let frame = capture.wait(ticket, Duration::from_secs(5)).expect("frame");
+        render.join().expect("render thread");
+        assert_eq!((frame.width, frame.height, frame.pixels.len()), (1, 1, 4));
+        assert_eq!(capture.pending(), Some(later));
+    }
+}
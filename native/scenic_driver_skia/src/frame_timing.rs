@@ -0,0 +1,130 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Frames the frame rate is averaged over.
+const FPS_WINDOW: usize = 60;
+
+/// Where the time of the last rendered frame went, as
+/// `%{frame: 12, parse_us: .., draw_us: .., flush_us: .., frame_us: .., ops: .., fps: ..}`.
+///
+/// `parse_us` is the CPU time spent parsing the scripts submitted since the frame before,
+/// `draw_us` the time drawing them into Skia, `flush_us` the time Skia took to flush and
+/// submit the frame to the GPU (`0` on raster surfaces) and `frame_us` the whole frame.
+/// `ops` counts the ops executed and `fps` is the frame rate over the last 60 frames.
+#[derive(Clone, Debug, Default, PartialEq, rustler::NifMap)]
+pub struct RenderStats {
+    pub frame: u64,
+    pub parse_us: u64,
+    pub draw_us: u64,
+    pub flush_us: u64,
+    pub frame_us: u64,
+    pub ops: u64,
+    pub fps: f64,
+}
+
+/// Time one frame took in the renderer, split by where it went.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameTimes {
+    pub frame: Duration,
+    pub flush: Duration,
+    pub ops: u64,
+}
+
+#[derive(Debug, Default)]
+struct Timing {
+    parsing: Duration,
+    last: RenderStats,
+    presented_us: VecDeque<u64>,
+}
+
+/// Collects parse, draw and flush times so jank can be traced to script submission,
+/// script drawing or the GPU.
+#[derive(Debug, Default)]
+pub struct FrameTiming(Mutex<Timing>);
+
+impl FrameTiming {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the time a submitted script took to parse to the next frame.
+    pub fn record_parse(&self, elapsed: Duration) {
+        if let Ok(mut timing) = self.0.lock() {
+            timing.parsing += elapsed;
+        }
+    }
+
+    /// Closes the frame rendered at `now_us`.
+    pub fn finish_frame(&self, times: FrameTimes, now_us: u64) {
+        let Ok(mut timing) = self.0.lock() else {
+            return;
+        };
+        if timing.presented_us.len() >= FPS_WINDOW {
+            timing.presented_us.pop_front();
+        }
+        timing.presented_us.push_back(now_us);
+        let fps = match (timing.presented_us.front(), timing.presented_us.back()) {
+            (Some(&first), Some(&last)) if last > first => {
+                (timing.presented_us.len() - 1) as f64 * 1_000_000.0 / (last - first) as f64
+            }
+            _ => 0.0,
+        };
+        let parsing = std::mem::take(&mut timing.parsing);
+        timing.last = RenderStats {
+            frame: timing.last.frame + 1,
+            parse_us: micros(parsing),
+            draw_us: micros(times.frame.saturating_sub(times.flush)),
+            flush_us: micros(times.flush),
+            frame_us: micros(times.frame),
+            ops: times.ops,
+            fps,
+        };
+    }
+
+    pub fn last(&self) -> Result<RenderStats, String> {
+        self.0
+            .lock()
+            .map(|timing| timing.last.clone())
+            .map_err(|_| "frame timing lock poisoned".to_string())
+    }
+}
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_micros().min(u64::MAX as u128) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_split_their_time_and_average_the_rate() {
+        let timing = FrameTiming::new();
+        assert_eq!(timing.last(), Ok(RenderStats::default()));
+
+        timing.record_parse(Duration::from_micros(300));
+        timing.record_parse(Duration::from_micros(200));
+        let times = FrameTimes {
+            frame: Duration::from_micros(4_000),
+            flush: Duration::from_micros(1_500),
+            ops: 42,
+        };
+        timing.finish_frame(times, 1_000_000);
+        let stats = timing.last().expect("stats");
+        assert_eq!(
+            (stats.frame, stats.parse_us, stats.draw_us, stats.flush_us),
+            (1, 500, 2_500, 1_500)
+        );
+        assert_eq!((stats.frame_us, stats.ops, stats.fps), (4_000, 42, 0.0));
+
+        // Parse time belongs to the frame that picks the scripts up.
+        for frame in 1..=FPS_WINDOW as u64 + 10 {
+            timing.finish_frame(times, 1_000_000 + frame * 20_000);
+        }
+        let stats = timing.last().expect("stats");
+        assert_eq!(stats.parse_us, 0);
+        assert_eq!(stats.frame, FPS_WINDOW as u64 + 11);
+        assert!((stats.fps - 50.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,617 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use crate::flight_recorder::log_line;
+use crate::renderer::{GradientShape, ScriptOp, SpriteCommand};
+
+/// Largest coordinate, size or radius a script may use. Values beyond it are far outside
+/// any display and only overflow Skia's fixed point math.
+pub const MAX_GEOMETRY: f32 = 1.0e6;
+const MAX_WARNINGS: usize = 64;
+
+/// What happens to a script with unusable geometry.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GeometryMode {
+    /// Values are replaced with the nearest usable one and a warning is recorded.
+    #[default]
+    Clamp,
+    /// The script is rejected.
+    Strict,
+}
+
+impl GeometryMode {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "clamp" => Ok(Self::Clamp),
+            "strict" => Ok(Self::Strict),
+            _ => Err(format!("unsupported geometry mode: {value}")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Problem {
+    NotANumber,
+    Infinite,
+    Negative,
+    OutOfRange,
+}
+
+impl Problem {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::NotANumber => "nan",
+            Self::Infinite => "infinite",
+            Self::Negative => "negative",
+            Self::OutOfRange => "out_of_range",
+        }
+    }
+}
+
+/// A value [`clamp`] replaced in a parsed op.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClampedValue {
+    pub op: &'static str,
+    pub field: &'static str,
+    pub value: f32,
+    pub problem: Problem,
+}
+
+/// A value of a parsed op that was rejected or clamped.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeometryIssue {
+    pub script: String,
+    pub op: &'static str,
+    pub field: &'static str,
+    pub value: f32,
+    pub problem: Problem,
+}
+
+impl std::fmt::Display for GeometryIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} is {} ({})",
+            self.op,
+            self.field,
+            self.problem.name().replace('_', " "),
+            self.value
+        )
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    /// Positions and offsets: finite and within [`MAX_GEOMETRY`].
+    Coordinate,
+    /// Widths, heights and radii: like coordinates, and not negative.
+    Size,
+    /// Angles in radians: finite.
+    Angle,
+    /// Scales and other multipliers: finite, a NaN becomes 1.
+    Factor,
+}
+
+/// Reports the geometry [`clamp`] fixed in submitted scripts.
+///
+/// NaN, infinite, negative or absurdly large values otherwise reach Skia unchanged, where
+/// they draw nothing, draw garbage or trip debug asserts. Scripts are clamped once when
+/// parsed, and the values that changed are reported on every submission: in clamp mode
+/// each is kept as a warning for `take_geometry_warnings`, strict mode rejects the script.
+#[derive(Debug, Default)]
+pub struct GeometryGuard {
+    strict: AtomicBool,
+    clamped: AtomicU64,
+    warnings: Mutex<VecDeque<GeometryIssue>>,
+}
+
+impl GeometryGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_mode(&self, mode: GeometryMode) {
+        self.strict
+            .store(mode == GeometryMode::Strict, Ordering::Relaxed);
+    }
+
+    pub fn mode(&self) -> GeometryMode {
+        if self.strict.load(Ordering::Relaxed) {
+            GeometryMode::Strict
+        } else {
+            GeometryMode::Clamp
+        }
+    }
+
+    /// Rejects script `id` in strict mode if `clamped` is not empty, otherwise keeps its
+    /// values as warnings.
+    pub fn report(&self, id: &str, clamped: &[ClampedValue]) -> Result<(), String> {
+        let Some(first) = clamped.first() else {
+            return Ok(());
+        };
+        let issue = |value: &ClampedValue| GeometryIssue {
+            script: id.to_string(),
+            op: value.op,
+            field: value.field,
+            value: value.value,
+            problem: value.problem,
+        };
+        if self.mode() == GeometryMode::Strict {
+            return Err(format!("invalid geometry: {}", issue(first)));
+        }
+        if self.clamped.fetch_add(1, Ordering::Relaxed) == 0 {
+            log_line!(
+                "Clamped {} geometry values in script {id:?}, first {}",
+                clamped.len(),
+                issue(first)
+            );
+        }
+        let mut warnings = self
+            .warnings
+            .lock()
+            .map_err(|_| "geometry warnings lock poisoned".to_string())?;
+        for value in clamped {
+            if warnings.len() == MAX_WARNINGS {
+                warnings.pop_front();
+            }
+            warnings.push_back(issue(value));
+        }
+        Ok(())
+    }
+
+    /// Returns and forgets the most recent clamp warnings, oldest first.
+    pub fn take_warnings(&self) -> Result<Vec<GeometryIssue>, String> {
+        let mut warnings = self
+            .warnings
+            .lock()
+            .map_err(|_| "geometry warnings lock poisoned".to_string())?;
+        Ok(warnings.drain(..).collect())
+    }
+}
+
+/// Replaces unusable geometry in `ops`: a NaN becomes zero (one for scale factors and the
+/// diagonal of a matrix), other values are clamped into range. Returns each replaced value.
+pub fn clamp(ops: &mut [ScriptOp]) -> Vec<ClampedValue> {
+    let mut clamped = Vec::new();
+    for op in ops.iter_mut() {
+        let name = op.name();
+        for (field, value, kind) in fields(op) {
+            let Some((problem, replacement)) = check_value(*value, kind) else {
+                continue;
+            };
+            clamped.push(ClampedValue {
+                op: name,
+                field,
+                value: *value,
+                problem,
+            });
+            *value = replacement;
+        }
+    }
+    clamped
+}
+
+/// The problem with `value` and its replacement, if it is unusable as a `kind`.
+fn check_value(value: f32, kind: Kind) -> Option<(Problem, f32)> {
+    if value.is_nan() {
+        let fallback = match kind {
+            Kind::Factor => 1.0,
+            _ => 0.0,
+        };
+        return Some((Problem::NotANumber, fallback));
+    }
+    match kind {
+        Kind::Angle if value.is_infinite() => Some((Problem::Infinite, 0.0)),
+        Kind::Angle => None,
+        Kind::Size if value < 0.0 => Some((Problem::Negative, 0.0)),
+        _ if value.is_infinite() => Some((Problem::Infinite, value.signum() * MAX_GEOMETRY)),
+        _ if value.abs() > MAX_GEOMETRY => {
+            Some((Problem::OutOfRange, value.signum() * MAX_GEOMETRY))
+        }
+        _ => None,
+    }
+}
+
+/// The float fields of `op` that describe geometry.
+fn fields(op: &mut ScriptOp) -> Vec<(&'static str, &mut f32, Kind)> {
+    use Kind::{Angle, Coordinate, Factor, Size};
+    match op {
+        ScriptOp::Translate(x, y) => vec![("x", x, Coordinate), ("y", y, Coordinate)],
+        ScriptOp::Rotate(radians) => vec![("radians", radians, Angle)],
+        ScriptOp::Scale(x, y) => vec![("x", x, Factor), ("y", y, Factor)],
+        ScriptOp::Transform { a, b, c, d, e, f } => vec![
+            ("a", a, Factor),
+            ("b", b, Coordinate),
+            ("c", c, Coordinate),
+            ("d", d, Factor),
+            ("e", e, Coordinate),
+            ("f", f, Coordinate),
+        ],
+        ScriptOp::StrokeWidth(width) => vec![("width", width, Size)],
+        ScriptOp::StrokeMiterLimit(limit) => vec![("limit", limit, Size)],
+        ScriptOp::DropShadow { dx, dy, sigma, .. } => vec![
+            ("dx", dx, Coordinate),
+            ("dy", dy, Coordinate),
+            ("sigma", sigma, Size),
+        ],
+        ScriptOp::Blur { sigma } => vec![("sigma", sigma, Size)],
+        ScriptOp::FontSize(size) => vec![("size", size, Size)],
+        ScriptOp::TextWidth(width) => vec![("width", width, Size)],
+        ScriptOp::LineHeight(height) => vec![("height", height, Size)],
+        ScriptOp::FillLinear {
+            start_x,
+            start_y,
+            end_x,
+            end_y,
+            ..
+        }
+        | ScriptOp::StrokeLinear {
+            start_x,
+            start_y,
+            end_x,
+            end_y,
+            ..
+        } => vec![
+            ("start_x", start_x, Coordinate),
+            ("start_y", start_y, Coordinate),
+            ("end_x", end_x, Coordinate),
+            ("end_y", end_y, Coordinate),
+        ],
+        ScriptOp::FillRadial {
+            center_x,
+            center_y,
+            inner_radius,
+            outer_radius,
+            ..
+        }
+        | ScriptOp::StrokeRadial {
+            center_x,
+            center_y,
+            inner_radius,
+            outer_radius,
+            ..
+        } => vec![
+            ("center_x", center_x, Coordinate),
+            ("center_y", center_y, Coordinate),
+            ("inner_radius", inner_radius, Size),
+            ("outer_radius", outer_radius, Size),
+        ],
+        ScriptOp::FillGradient { shape, .. } | ScriptOp::StrokeGradient { shape, .. } => {
+            match shape {
+                GradientShape::Linear {
+                    start_x,
+                    start_y,
+                    end_x,
+                    end_y,
+                } => vec![
+                    ("start_x", start_x, Coordinate),
+                    ("start_y", start_y, Coordinate),
+                    ("end_x", end_x, Coordinate),
+                    ("end_y", end_y, Coordinate),
+                ],
+                GradientShape::Radial {
+                    center_x,
+                    center_y,
+                    inner_radius,
+                    outer_radius,
+                } => vec![
+                    ("center_x", center_x, Coordinate),
+                    ("center_y", center_y, Coordinate),
+                    ("inner_radius", inner_radius, Size),
+                    ("outer_radius", outer_radius, Size),
+                ],
+                GradientShape::Conic {
+                    center_x,
+                    center_y,
+                    start_angle,
+                } => vec![
+                    ("center_x", center_x, Coordinate),
+                    ("center_y", center_y, Coordinate),
+                    ("start_angle", start_angle, Angle),
+                ],
+            }
+        }
+        ScriptOp::FillPattern { width, height, .. }
+        | ScriptOp::Scissor { width, height }
+        | ScriptOp::PathRect { width, height }
+        | ScriptOp::DrawRect { width, height, .. } => {
+            vec![("width", width, Size), ("height", height, Size)]
+        }
+        ScriptOp::SaveLayer { x, y, w, h, .. } => vec![
+            ("x", x, Coordinate),
+            ("y", y, Coordinate),
+            ("w", w, Size),
+            ("h", h, Size),
+        ],
+        ScriptOp::MoveTo { x, y } | ScriptOp::LineTo { x, y } => {
+            vec![("x", x, Coordinate), ("y", y, Coordinate)]
+        }
+        ScriptOp::ArcTo {
+            x1,
+            y1,
+            x2,
+            y2,
+            radius,
+        } => vec![
+            ("x1", x1, Coordinate),
+            ("y1", y1, Coordinate),
+            ("x2", x2, Coordinate),
+            ("y2", y2, Coordinate),
+            ("radius", radius, Size),
+        ],
+        ScriptOp::BezierTo {
+            cp1x,
+            cp1y,
+            cp2x,
+            cp2y,
+            x,
+            y,
+        } => vec![
+            ("cp1x", cp1x, Coordinate),
+            ("cp1y", cp1y, Coordinate),
+            ("cp2x", cp2x, Coordinate),
+            ("cp2y", cp2y, Coordinate),
+            ("x", x, Coordinate),
+            ("y", y, Coordinate),
+        ],
+        ScriptOp::QuadraticTo { cpx, cpy, x, y } => vec![
+            ("cpx", cpx, Coordinate),
+            ("cpy", cpy, Coordinate),
+            ("x", x, Coordinate),
+            ("y", y, Coordinate),
+        ],
+        ScriptOp::PathTriangle {
+            x0,
+            y0,
+            x1,
+            y1,
+            x2,
+            y2,
+        }
+        | ScriptOp::DrawTriangle {
+            x0,
+            y0,
+            x1,
+            y1,
+            x2,
+            y2,
+            ..
+        } => vec![
+            ("x0", x0, Coordinate),
+            ("y0", y0, Coordinate),
+            ("x1", x1, Coordinate),
+            ("y1", y1, Coordinate),
+            ("x2", x2, Coordinate),
+            ("y2", y2, Coordinate),
+        ],
+        ScriptOp::PathQuad {
+            x0,
+            y0,
+            x1,
+            y1,
+            x2,
+            y2,
+            x3,
+            y3,
+        }
+        | ScriptOp::DrawQuad {
+            x0,
+            y0,
+            x1,
+            y1,
+            x2,
+            y2,
+            x3,
+            y3,
+            ..
+        } => vec![
+            ("x0", x0, Coordinate),
+            ("y0", y0, Coordinate),
+            ("x1", x1, Coordinate),
+            ("y1", y1, Coordinate),
+            ("x2", x2, Coordinate),
+            ("y2", y2, Coordinate),
+            ("x3", x3, Coordinate),
+            ("y3", y3, Coordinate),
+        ],
+        ScriptOp::DrawLine { x0, y0, x1, y1, .. } => vec![
+            ("x0", x0, Coordinate),
+            ("y0", y0, Coordinate),
+            ("x1", x1, Coordinate),
+            ("y1", y1, Coordinate),
+        ],
+        ScriptOp::PathRRect {
+            width,
+            height,
+            radius,
+        }
+        | ScriptOp::DrawRRect {
+            width,
+            height,
+            radius,
+            ..
+        } => vec![
+            ("width", width, Size),
+            ("height", height, Size),
+            ("radius", radius, Size),
+        ],
+        ScriptOp::DrawRRectV {
+            width,
+            height,
+            ul_radius,
+            ur_radius,
+            lr_radius,
+            ll_radius,
+            ..
+        } => vec![
+            ("width", width, Size),
+            ("height", height, Size),
+            ("ul_radius", ul_radius, Size),
+            ("ur_radius", ur_radius, Size),
+            ("lr_radius", lr_radius, Size),
+            ("ll_radius", ll_radius, Size),
+        ],
+        ScriptOp::PathSuperellipse {
+            width,
+            height,
+            exponent,
+        }
+        | ScriptOp::DrawSquircle {
+            width,
+            height,
+            exponent,
+            ..
+        } => vec![
+            ("width", width, Size),
+            ("height", height, Size),
+            ("exponent", exponent, Factor),
+        ],
+        ScriptOp::PathSector { radius, radians }
+        | ScriptOp::DrawArc {
+            radius, radians, ..
+        }
+        | ScriptOp::DrawSector {
+            radius, radians, ..
+        } => vec![("radius", radius, Size), ("radians", radians, Angle)],
+        ScriptOp::PathCircle { radius } | ScriptOp::DrawCircle { radius, .. } => {
+            vec![("radius", radius, Size)]
+        }
+        ScriptOp::PathEllipse { radius0, radius1 }
+        | ScriptOp::DrawEllipse {
+            radius0, radius1, ..
+        } => vec![("radius0", radius0, Size), ("radius1", radius1, Size)],
+        ScriptOp::PathArc {
+            cx,
+            cy,
+            radius,
+            start,
+            end,
+            ..
+        } => vec![
+            ("cx", cx, Coordinate),
+            ("cy", cy, Coordinate),
+            ("radius", radius, Size),
+            ("start", start, Angle),
+            ("end", end, Angle),
+        ],
+        ScriptOp::DrawPolygon {
+            radius, rounding, ..
+        } => vec![("radius", radius, Size), ("rounding", rounding, Size)],
+        ScriptOp::DrawStar {
+            outer,
+            inner,
+            rounding,
+            ..
+        } => vec![
+            ("outer", outer, Size),
+            ("inner", inner, Size),
+            ("rounding", rounding, Size),
+        ],
+        ScriptOp::DrawSprites { cmds, .. } => cmds
+            .iter_mut()
+            .flat_map(
+                |SpriteCommand {
+                     sx,
+                     sy,
+                     sw,
+                     sh,
+                     dx,
+                     dy,
+                     dw,
+                     dh,
+                     ..
+                 }| {
+                    [
+                        ("sx", sx, Coordinate),
+                        ("sy", sy, Coordinate),
+                        ("sw", sw, Size),
+                        ("sh", sh, Size),
+                        ("dx", dx, Coordinate),
+                        ("dy", dy, Coordinate),
+                        ("dw", dw, Size),
+                        ("dh", dh, Size),
+                    ]
+                },
+            )
+            .collect(),
+        ScriptOp::Marquee { width, speed } => {
+            vec![("width", width, Size), ("speed", speed, Coordinate)]
+        }
+        ScriptOp::DrawTextEllipsized { max_width, .. } => vec![("max_width", max_width, Size)],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamp_mode_fixes_values_and_strict_mode_rejects_them() {
+        let guard = GeometryGuard::new();
+        let mut ops = vec![
+            ScriptOp::Translate(f32::NAN, 4.0),
+            ScriptOp::Scale(f32::NAN, 2.0),
+            ScriptOp::DrawRect {
+                width: -4.0,
+                height: f32::INFINITY,
+                flag: 0x01,
+            },
+            ScriptOp::DrawCircle {
+                radius: 1.0e9,
+                flag: 0x01,
+            },
+            ScriptOp::Rotate(f32::NEG_INFINITY),
+            ScriptOp::LineTo { x: -2.0, y: 3.0 },
+        ];
+        let clamped = clamp(&mut ops);
+        guard.report("root", &clamped).expect("clamped");
+        assert_eq!(
+            ops,
+            vec![
+                ScriptOp::Translate(0.0, 4.0),
+                ScriptOp::Scale(1.0, 2.0),
+                ScriptOp::DrawRect {
+                    width: 0.0,
+                    height: MAX_GEOMETRY,
+                    flag: 0x01,
+                },
+                ScriptOp::DrawCircle {
+                    radius: MAX_GEOMETRY,
+                    flag: 0x01,
+                },
+                ScriptOp::Rotate(0.0),
+                ScriptOp::LineTo { x: -2.0, y: 3.0 },
+            ]
+        );
+
+        let warnings = guard.take_warnings().expect("warnings");
+        let problems: Vec<_> = warnings
+            .iter()
+            .map(|issue| (issue.op, issue.field, issue.problem))
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                ("translate", "x", Problem::NotANumber),
+                ("scale", "x", Problem::NotANumber),
+                ("draw_rect", "width", Problem::Negative),
+                ("draw_rect", "height", Problem::Infinite),
+                ("draw_circle", "radius", Problem::OutOfRange),
+                ("rotate", "radians", Problem::Infinite),
+            ]
+        );
+        assert_eq!(warnings[2].to_string(), "draw_rect width is negative (-4)");
+        assert!(guard.take_warnings().expect("drained").is_empty());
+
+        guard.set_mode(GeometryMode::Strict);
+        assert_eq!(
+            guard.report("root", &clamped),
+            Err("invalid geometry: translate x is nan (NaN)".to_string())
+        );
+        assert!(clamp(&mut ops).is_empty());
+        guard.report("root", &[]).expect("clean script");
+        assert!(GeometryMode::parse("loose").is_err());
+    }
+}
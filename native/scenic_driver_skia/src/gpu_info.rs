@@ -0,0 +1,175 @@
+use std::ffi::{CStr, c_char};
+use std::sync::Arc;
+
+use crate::backend_probe;
+use crate::flight_recorder::log_line;
+use crate::state_lock::RenderStateLock;
+
+/// EGL extensions that change how the driver allocates buffers, presents or syncs.
+const RELEVANT_EGL_EXTENSIONS: &[&str] = &[
+    "EGL_ANDROID_native_fence_sync",
+    "EGL_EXT_buffer_age",
+    "EGL_EXT_image_dma_buf_import",
+    "EGL_EXT_image_dma_buf_import_modifiers",
+    "EGL_EXT_platform_base",
+    "EGL_KHR_fence_sync",
+    "EGL_KHR_image_base",
+    "EGL_KHR_no_config_context",
+    "EGL_KHR_partial_update",
+    "EGL_KHR_platform_gbm",
+    "EGL_KHR_surfaceless_context",
+    "EGL_KHR_swap_buffers_with_damage",
+    "EGL_MESA_platform_gbm",
+];
+
+/// Lowercase `GL_RENDERER` fragments of the Mesa, Google and Microsoft software rasterizers.
+const SOFTWARE_RENDERERS: &[&str] = &[
+    "llvmpipe",
+    "softpipe",
+    "swrast",
+    "software rasterizer",
+    "swiftshader",
+    "microsoft basic render",
+];
+
+/// Whether a GL renderer string names a rasterizer that runs on the CPU.
+pub fn is_software_renderer(renderer: &str) -> bool {
+    let renderer = renderer.to_lowercase();
+    SOFTWARE_RENDERERS
+        .iter()
+        .any(|name| renderer.contains(name))
+}
+
+/// Graphics stack details reported by `get_gpu_info`, as
+/// `%{backend: "drm", gpu: true, vendor: .., renderer: .., version: .., ...}`.
+///
+/// `gpu` is `false` when frames are rendered on the CPU; the GL strings are then empty.
+/// `software` is `true` when the GL or Vulkan driver itself rasterizes on the CPU, as
+/// Mesa's llvmpipe does when no hardware driver loads.
+/// The Vulkan backend fills `renderer` with the device name and `version` with the API
+/// and driver version.
+/// `fallback` holds the GPU setup error when the backend fell back to CPU rendering.
+/// On DRM, `card` is the node frames are scanned out on and `render_node` the node of
+/// another GPU they are drawn on, if any, each with the kernel driver bound to it.
+#[derive(Clone, Debug, Default, PartialEq, rustler::NifMap)]
+pub struct GpuInfo {
+    pub backend: String,
+    pub gpu: bool,
+    pub software: bool,
+    pub vendor: String,
+    pub renderer: String,
+    pub version: String,
+    pub shading_language_version: String,
+    pub egl_extensions: Vec<String>,
+    pub fallback: Option<String>,
+    pub card: Option<String>,
+    pub card_driver: Option<String>,
+    pub render_node: Option<String>,
+    pub render_driver: Option<String>,
+}
+
+impl GpuInfo {
+    /// Reads the GL strings of the current context.
+    ///
+    /// # Safety
+    /// A GL context must be current on this thread and the `gl` bindings loaded.
+    pub unsafe fn query_gl(backend: &str) -> Self {
+        let string = |name| {
+            let value = unsafe { gl::GetString(name) };
+            if value.is_null() {
+                return String::new();
+            }
+            unsafe { CStr::from_ptr(value as *const c_char) }
+                .to_string_lossy()
+                .into_owned()
+        };
+        let renderer = string(gl::RENDERER);
+        Self {
+            backend: backend.to_string(),
+            gpu: true,
+            software: is_software_renderer(&renderer),
+            vendor: string(gl::VENDOR),
+            renderer,
+            version: string(gl::VERSION),
+            shading_language_version: string(gl::SHADING_LANGUAGE_VERSION),
+            egl_extensions: Vec::new(),
+            fallback: None,
+            card: None,
+            card_driver: None,
+            render_node: None,
+            render_driver: None,
+        }
+    }
+
+    pub fn raster(backend: &str) -> Self {
+        Self {
+            backend: backend.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Keeps the driver-relevant entries of an EGL extension list, sorted.
+    pub fn with_egl_extensions<'a>(
+        mut self,
+        extensions: impl IntoIterator<Item = &'a str>,
+    ) -> Self {
+        let mut relevant: Vec<String> = extensions
+            .into_iter()
+            .filter(|name| RELEVANT_EGL_EXTENSIONS.contains(name))
+            .map(str::to_string)
+            .collect();
+        relevant.sort();
+        relevant.dedup();
+        self.egl_extensions = relevant;
+        self
+    }
+
+    /// Records the DRM nodes the backend opened and their drivers.
+    pub fn with_nodes(mut self, card: Option<&str>, render_node: Option<&str>) -> Self {
+        self.card_driver = card.and_then(backend_probe::node_driver);
+        self.card = card.map(str::to_string);
+        self.render_driver = render_node.and_then(backend_probe::node_driver);
+        self.render_node = render_node.map(str::to_string);
+        self
+    }
+
+    /// Stores the info for `get_gpu_info` once the backend has created its renderer.
+    pub fn publish(self, render_state: &Arc<RenderStateLock>) {
+        if self.software {
+            log_line!(
+                "{} GL is the software rasterizer {}; frames render on the CPU",
+                self.backend,
+                self.renderer
+            );
+        }
+        if let Ok(mut state) = render_state.lock() {
+            state.gpu_info = Some(self);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gpu_info_keeps_relevant_egl_extensions() {
+        let info = GpuInfo::raster("drm").with_egl_extensions(
+            "EGL_MESA_platform_gbm EGL_KHR_image_base EGL_EXT_pixel_format_float EGL_KHR_image_base"
+                .split_whitespace(),
+        );
+        assert!(!info.gpu);
+        assert_eq!(
+            info.egl_extensions,
+            vec!["EGL_KHR_image_base", "EGL_MESA_platform_gbm"]
+        );
+
+        assert!(is_software_renderer("llvmpipe (LLVM 17.0.6, 256 bits)"));
+        assert!(is_software_renderer("Software Rasterizer"));
+        assert!(is_software_renderer("Google SwiftShader"));
+        assert!(!is_software_renderer("Mali-G52 r1p0"));
+        assert!(!is_software_renderer(
+            "AMD Radeon Graphics (radeonsi, renoir)"
+        ));
+    }
+}
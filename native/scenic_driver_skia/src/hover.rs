@@ -0,0 +1,121 @@
+use crate::input::InputEvent;
+
+/// Named scene-space rects `(x, y, w, h)` the pointer is tracked against, so input targets
+/// get `{:hover, {:enter | :leave, id}}` as it crosses them instead of working hover out
+/// from every cursor position.
+///
+/// Overlapping regions are hovered together, and rects sharing an id form one region.
+/// Changes are only reported when the set of regions under the pointer changes, leaves
+/// before enters; positions come from the cursor events the queue delivers, so a cursor
+/// interval coalesces them too.
+#[derive(Debug, Default)]
+pub struct HoverRegions {
+    regions: Vec<(String, (f32, f32, f32, f32))>,
+    hovered: Vec<String>,
+    /// Last delivered pointer position, `None` while the pointer is outside the output.
+    pointer: Option<(f32, f32)>,
+}
+
+impl HoverRegions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the regions. Returns the changes for a pointer already over them.
+    pub fn set(&mut self, regions: Vec<(String, (f32, f32, f32, f32))>) -> Vec<InputEvent> {
+        self.regions = regions;
+        self.update()
+    }
+
+    pub fn pointer_moved(&mut self, x: f32, y: f32) -> Vec<InputEvent> {
+        self.pointer = Some((x, y));
+        self.update()
+    }
+
+    /// The pointer left the output; every hovered region is left.
+    pub fn pointer_left(&mut self) -> Vec<InputEvent> {
+        self.pointer = None;
+        self.update()
+    }
+
+    fn update(&mut self) -> Vec<InputEvent> {
+        let mut under: Vec<&str> = Vec::new();
+        if let Some((x, y)) = self.pointer {
+            for (id, (rx, ry, rw, rh)) in &self.regions {
+                if x >= *rx
+                    && x < rx + rw
+                    && y >= *ry
+                    && y < ry + rh
+                    && !under.contains(&id.as_str())
+                {
+                    under.push(id);
+                }
+            }
+        }
+        let mut changes: Vec<InputEvent> = self
+            .hovered
+            .iter()
+            .filter(|id| !under.contains(&id.as_str()))
+            .map(|id| InputEvent::Hover {
+                region: id.clone(),
+                entered: false,
+            })
+            .collect();
+        changes.extend(
+            under
+                .iter()
+                .filter(|id| !self.hovered.iter().any(|hovered| hovered == *id))
+                .map(|id| InputEvent::Hover {
+                    region: id.to_string(),
+                    entered: true,
+                }),
+        );
+        self.hovered = under.into_iter().map(str::to_string).collect();
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hover(region: &str, entered: bool) -> InputEvent {
+        InputEvent::Hover {
+            region: region.to_string(),
+            entered,
+        }
+    }
+
+    #[test]
+    fn crossing_region_edges_enters_and_leaves() {
+        let mut regions = HoverRegions::new();
+        assert!(regions.pointer_moved(15.0, 15.0).is_empty());
+        assert_eq!(
+            regions.set(vec![
+                ("panel".to_string(), (0.0, 0.0, 100.0, 100.0)),
+                ("button".to_string(), (10.0, 10.0, 20.0, 20.0)),
+                ("button".to_string(), (60.0, 10.0, 20.0, 20.0)),
+            ]),
+            vec![hover("panel", true), hover("button", true)]
+        );
+        assert!(regions.pointer_moved(20.0, 20.0).is_empty());
+        assert_eq!(
+            regions.pointer_moved(40.0, 20.0),
+            vec![hover("button", false)]
+        );
+        assert_eq!(
+            regions.pointer_moved(65.0, 20.0),
+            vec![hover("button", true)]
+        );
+        assert_eq!(
+            regions.pointer_moved(150.0, 20.0),
+            vec![hover("panel", false), hover("button", false)]
+        );
+        assert_eq!(
+            regions.pointer_moved(50.0, 50.0),
+            vec![hover("panel", true)]
+        );
+        assert_eq!(regions.pointer_left(), vec![hover("panel", false)]);
+        assert!(regions.set(Vec::new()).is_empty());
+    }
+}
@@ -0,0 +1,582 @@
+//! Optional JPEG decoding through a V4L2 memory-to-memory decoder (for example the
+//! `bcm2835-codec` decoder on a Raspberry Pi).
+//!
+//! Off by default. When enabled, `decode_jpeg` runs the JPEG through the decoder and
+//! converts the YUV output to RGBA; any failure returns `None` so callers fall back to
+//! Skia's software decoder. Frames are copied out of the decoder's mmap buffers, so the
+//! resulting images are plain raster images.
+
+use std::ffi::{CString, c_ulong, c_void};
+use std::fs::{File, OpenOptions};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+use std::sync::Mutex;
+
+use crate::flight_recorder::log_line;
+
+const DEFAULT_DEVICES: &[&str] = &["/dev/video10", "/dev/video11", "/dev/video12"];
+const DECODE_TIMEOUT_MS: i32 = 1000;
+
+const BUF_TYPE_VIDEO_CAPTURE_MPLANE: u32 = 9;
+const BUF_TYPE_VIDEO_OUTPUT_MPLANE: u32 = 10;
+const MEMORY_MMAP: u32 = 1;
+const FIELD_NONE: u32 = 1;
+const CAP_VIDEO_M2M_MPLANE: u32 = 0x0000_4000;
+const CAP_DEVICE_CAPS: u32 = 0x8000_0000;
+
+const fn fourcc(code: &[u8; 4]) -> u32 {
+    u32::from_le_bytes(*code)
+}
+
+const PIX_FMT_JPEG: u32 = fourcc(b"JPEG");
+const PIX_FMT_MJPEG: u32 = fourcc(b"MJPG");
+const PIX_FMT_YUV420: u32 = fourcc(b"YU12");
+const PIX_FMT_NV12: u32 = fourcc(b"NV12");
+
+#[repr(C)]
+struct Capability {
+    driver: [u8; 16],
+    card: [u8; 32],
+    bus_info: [u8; 32],
+    version: u32,
+    capabilities: u32,
+    device_caps: u32,
+    reserved: [u32; 3],
+}
+
+#[repr(C)]
+struct FmtDesc {
+    index: u32,
+    type_: u32,
+    flags: u32,
+    description: [u8; 32],
+    pixelformat: u32,
+    mbus_code: u32,
+    reserved: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct PlanePixFormat {
+    sizeimage: u32,
+    bytesperline: u32,
+    reserved: [u16; 6],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct PixFormatMplane {
+    width: u32,
+    height: u32,
+    pixelformat: u32,
+    field: u32,
+    colorspace: u32,
+    plane_fmt: [PlanePixFormat; 8],
+    num_planes: u8,
+    flags: u8,
+    ycbcr_enc: u8,
+    quantization: u8,
+    xfer_func: u8,
+    reserved: [u8; 7],
+}
+
+#[repr(C)]
+union FormatUnion {
+    pix_mp: PixFormatMplane,
+    raw: [u8; 200],
+    // `struct v4l2_window` in the kernel union holds pointers.
+    _align: [*const c_void; 0],
+}
+
+#[repr(C)]
+struct Format {
+    type_: u32,
+    fmt: FormatUnion,
+}
+
+#[repr(C)]
+struct RequestBuffers {
+    count: u32,
+    type_: u32,
+    memory: u32,
+    capabilities: u32,
+    flags: u8,
+    reserved: [u8; 3],
+}
+
+#[repr(C)]
+union PlaneM {
+    mem_offset: u32,
+    userptr: c_ulong,
+    fd: i32,
+}
+
+#[repr(C)]
+struct Plane {
+    bytesused: u32,
+    length: u32,
+    m: PlaneM,
+    data_offset: u32,
+    reserved: [u32; 11],
+}
+
+#[repr(C)]
+struct Timecode {
+    type_: u32,
+    flags: u32,
+    frames: u8,
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    userbits: [u8; 4],
+}
+
+#[repr(C)]
+union BufferM {
+    offset: u32,
+    userptr: c_ulong,
+    planes: *mut Plane,
+    fd: i32,
+}
+
+#[repr(C)]
+struct Buffer {
+    index: u32,
+    type_: u32,
+    bytesused: u32,
+    flags: u32,
+    field: u32,
+    timestamp: libc::timeval,
+    timecode: Timecode,
+    sequence: u32,
+    memory: u32,
+    m: BufferM,
+    length: u32,
+    reserved2: u32,
+    request_fd: i32,
+}
+
+const fn ioc(dir: c_ulong, nr: c_ulong, size: usize) -> c_ulong {
+    (dir << 30) | ((size as c_ulong) << 16) | ((b'V' as c_ulong) << 8) | nr
+}
+
+const IOC_WRITE: c_ulong = 1;
+const IOC_READ: c_ulong = 2;
+const IOC_RW: c_ulong = IOC_READ | IOC_WRITE;
+
+const VIDIOC_QUERYCAP: c_ulong = ioc(IOC_READ, 0, size_of::<Capability>());
+const VIDIOC_ENUM_FMT: c_ulong = ioc(IOC_RW, 2, size_of::<FmtDesc>());
+const VIDIOC_G_FMT: c_ulong = ioc(IOC_RW, 4, size_of::<Format>());
+const VIDIOC_S_FMT: c_ulong = ioc(IOC_RW, 5, size_of::<Format>());
+const VIDIOC_REQBUFS: c_ulong = ioc(IOC_RW, 8, size_of::<RequestBuffers>());
+const VIDIOC_QUERYBUF: c_ulong = ioc(IOC_RW, 9, size_of::<Buffer>());
+const VIDIOC_QBUF: c_ulong = ioc(IOC_RW, 15, size_of::<Buffer>());
+const VIDIOC_DQBUF: c_ulong = ioc(IOC_RW, 17, size_of::<Buffer>());
+const VIDIOC_STREAMON: c_ulong = ioc(IOC_WRITE, 18, size_of::<i32>());
+const VIDIOC_STREAMOFF: c_ulong = ioc(IOC_WRITE, 19, size_of::<i32>());
+
+/// # Safety
+/// `arg` must be the struct `request` expects.
+unsafe fn ioctl<T>(file: &File, request: c_ulong, arg: &mut T) -> Result<(), String> {
+    if unsafe { libc::ioctl(file.as_raw_fd(), request as _, arg as *mut T) } < 0 {
+        return Err(format!(
+            "ioctl {request:#x} failed: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(())
+}
+
+/// Zero-initialized V4L2 struct; all of them are plain data.
+fn zeroed<T>() -> T {
+    unsafe { std::mem::zeroed() }
+}
+
+static DECODER: Mutex<Option<String>> = Mutex::new(None);
+
+/// Enables hardware JPEG decoding with `device`, or the first JPEG-capable M2M decoder
+/// among the usual Raspberry Pi nodes. Returns the device in use, or `None` when
+/// disabled or no decoder was found.
+pub fn configure(enabled: bool, device: Option<String>) -> Result<Option<String>, String> {
+    let mut decoder = DECODER
+        .lock()
+        .map_err(|_| "hardware decoder lock poisoned".to_string())?;
+    *decoder = None;
+    if !enabled {
+        return Ok(None);
+    }
+    let candidates = match device {
+        Some(device) => vec![device],
+        None => DEFAULT_DEVICES
+            .iter()
+            .map(|path| path.to_string())
+            .collect(),
+    };
+    *decoder = candidates
+        .into_iter()
+        .find(|path| probe(path).unwrap_or(false));
+    Ok(decoder.clone())
+}
+
+fn open_device(path: &str) -> Result<File, String> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_CLOEXEC)
+        .open(path)
+        .map_err(|err| format!("failed to open {path}: {err}"))
+}
+
+/// Checks that `path` is a multi-planar M2M device that accepts JPEG input.
+fn probe(path: &str) -> Result<bool, String> {
+    let file = open_device(path)?;
+    let mut caps: Capability = zeroed();
+    unsafe { ioctl(&file, VIDIOC_QUERYCAP, &mut caps)? };
+    let caps = if caps.capabilities & CAP_DEVICE_CAPS != 0 {
+        caps.device_caps
+    } else {
+        caps.capabilities
+    };
+    if caps & CAP_VIDEO_M2M_MPLANE == 0 {
+        return Ok(false);
+    }
+    Ok(jpeg_input_format(&file).is_some())
+}
+
+fn jpeg_input_format(file: &File) -> Option<u32> {
+    (0..)
+        .map_while(|index| {
+            let mut desc: FmtDesc = zeroed();
+            desc.index = index;
+            desc.type_ = BUF_TYPE_VIDEO_OUTPUT_MPLANE;
+            unsafe { ioctl(file, VIDIOC_ENUM_FMT, &mut desc) }
+                .ok()
+                .map(|_| desc.pixelformat)
+        })
+        .find(|format| matches!(*format, PIX_FMT_JPEG | PIX_FMT_MJPEG))
+}
+
+/// Decodes `data` with the configured hardware decoder into `(width, height, rgba)`.
+/// Returns `None` when hardware decoding is off or fails.
+pub fn decode_jpeg(data: &[u8]) -> Option<(u32, u32, Vec<u8>)> {
+    // Decoding holds the lock so requests do not interleave on the device.
+    let decoder = DECODER.lock().ok()?;
+    let device = decoder.as_deref()?;
+    match decode_with(device, data) {
+        Ok(decoded) => Some(decoded),
+        Err(err) => {
+            log_line!("Hardware JPEG decode failed, using software decode: {err}");
+            None
+        }
+    }
+}
+
+/// A buffer mapped from the device, unmapped when dropped.
+struct Mapping {
+    ptr: *mut c_void,
+    len: usize,
+}
+
+impl Mapping {
+    fn new(file: &File, offset: u32, len: u32) -> Result<Self, String> {
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len as usize,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                offset as libc::off_t,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(format!("mmap failed: {}", std::io::Error::last_os_error()));
+        }
+        Ok(Self {
+            ptr,
+            len: len as usize,
+        })
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr as *mut u8, self.len) }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr, self.len) };
+    }
+}
+
+/// Requests one mmap buffer on `type_` and maps its first plane.
+fn map_buffer(file: &File, type_: u32) -> Result<Mapping, String> {
+    let mut request = RequestBuffers {
+        count: 1,
+        type_,
+        memory: MEMORY_MMAP,
+        ..zeroed()
+    };
+    unsafe { ioctl(file, VIDIOC_REQBUFS, &mut request)? };
+    if request.count < 1 {
+        return Err("decoder allocated no buffers".to_string());
+    }
+    let mut plane: Plane = zeroed();
+    let mut buffer = plane_buffer(type_, &mut plane);
+    unsafe { ioctl(file, VIDIOC_QUERYBUF, &mut buffer)? };
+    Mapping::new(file, unsafe { plane.m.mem_offset }, plane.length)
+}
+
+fn plane_buffer(type_: u32, plane: &mut Plane) -> Buffer {
+    Buffer {
+        type_,
+        memory: MEMORY_MMAP,
+        m: BufferM { planes: plane },
+        length: 1,
+        ..zeroed()
+    }
+}
+
+fn stream(file: &File, request: c_ulong, type_: u32) -> Result<(), String> {
+    let mut type_ = type_ as i32;
+    unsafe { ioctl(file, request, &mut type_) }
+}
+
+fn decode_with(device: &str, data: &[u8]) -> Result<(u32, u32, Vec<u8>), String> {
+    let (width, height) = jpeg_dimensions(data).ok_or("no JPEG frame header")?;
+    let file = open_device(device)?;
+    let input_format = jpeg_input_format(&file).ok_or("decoder does not accept JPEG")?;
+
+    let mut format = Format {
+        type_: BUF_TYPE_VIDEO_OUTPUT_MPLANE,
+        fmt: FormatUnion { raw: [0; 200] },
+    };
+    let mut pix: PixFormatMplane = zeroed();
+    pix.width = width;
+    pix.height = height;
+    pix.pixelformat = input_format;
+    pix.field = FIELD_NONE;
+    pix.num_planes = 1;
+    pix.plane_fmt[0].sizeimage = data.len() as u32;
+    format.fmt.pix_mp = pix;
+    unsafe { ioctl(&file, VIDIOC_S_FMT, &mut format)? };
+
+    let mut format = Format {
+        type_: BUF_TYPE_VIDEO_CAPTURE_MPLANE,
+        fmt: FormatUnion { raw: [0; 200] },
+    };
+    pix = zeroed();
+    pix.width = width;
+    pix.height = height;
+    pix.pixelformat = PIX_FMT_YUV420;
+    pix.field = FIELD_NONE;
+    pix.num_planes = 1;
+    format.fmt.pix_mp = pix;
+    unsafe { ioctl(&file, VIDIOC_S_FMT, &mut format)? };
+    unsafe { ioctl(&file, VIDIOC_G_FMT, &mut format)? };
+    let output = unsafe { format.fmt.pix_mp };
+    if output.num_planes != 1 || !matches!(output.pixelformat, PIX_FMT_YUV420 | PIX_FMT_NV12) {
+        return Err("decoder offers no single-plane YUV 4:2:0 output".to_string());
+    }
+    // Decoders clamp the capture size to their limits; a smaller frame cannot hold the image.
+    if output.width < width || output.height < height {
+        return Err(format!(
+            "decoder output {}x{} is smaller than the {width}x{height} JPEG",
+            output.width, output.height
+        ));
+    }
+
+    let mut input = map_buffer(&file, BUF_TYPE_VIDEO_OUTPUT_MPLANE)?;
+    if input.len < data.len() {
+        return Err("JPEG does not fit the decoder input buffer".to_string());
+    }
+    input.as_mut_slice()[..data.len()].copy_from_slice(data);
+    let captured = map_buffer(&file, BUF_TYPE_VIDEO_CAPTURE_MPLANE)?;
+
+    let mut plane = Plane {
+        bytesused: data.len() as u32,
+        length: input.len as u32,
+        ..zeroed()
+    };
+    let mut buffer = plane_buffer(BUF_TYPE_VIDEO_OUTPUT_MPLANE, &mut plane);
+    unsafe { ioctl(&file, VIDIOC_QBUF, &mut buffer)? };
+    let mut plane = Plane {
+        length: captured.len as u32,
+        ..zeroed()
+    };
+    let mut buffer = plane_buffer(BUF_TYPE_VIDEO_CAPTURE_MPLANE, &mut plane);
+    unsafe { ioctl(&file, VIDIOC_QBUF, &mut buffer)? };
+
+    stream(&file, VIDIOC_STREAMON, BUF_TYPE_VIDEO_OUTPUT_MPLANE)?;
+    stream(&file, VIDIOC_STREAMON, BUF_TYPE_VIDEO_CAPTURE_MPLANE)?;
+    let result = wait_for_frame(&file).and_then(|_| {
+        let mut plane: Plane = zeroed();
+        let mut buffer = plane_buffer(BUF_TYPE_VIDEO_CAPTURE_MPLANE, &mut plane);
+        unsafe { ioctl(&file, VIDIOC_DQBUF, &mut buffer)? };
+        let stride = output.plane_fmt[0].bytesperline as usize;
+        yuv420_to_rgba(
+            captured.as_slice(),
+            (output.width, output.height),
+            stride,
+            output.pixelformat == PIX_FMT_NV12,
+        )
+        .and_then(|rgba| {
+            crop(rgba, output.width, width, height)
+                .map(|rgba| (width, height, rgba))
+                .ok_or_else(|| "decoded frame is smaller than the JPEG".to_string())
+        })
+    });
+    let _ = stream(&file, VIDIOC_STREAMOFF, BUF_TYPE_VIDEO_CAPTURE_MPLANE);
+    let _ = stream(&file, VIDIOC_STREAMOFF, BUF_TYPE_VIDEO_OUTPUT_MPLANE);
+    result
+}
+
+fn wait_for_frame(file: &File) -> Result<(), String> {
+    let mut fds = libc::pollfd {
+        fd: file.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    match unsafe { libc::poll(&mut fds, 1, DECODE_TIMEOUT_MS) } {
+        0 => Err("decoder timed out".to_string()),
+        n if n < 0 => Err(format!("poll failed: {}", std::io::Error::last_os_error())),
+        _ if fds.revents & libc::POLLERR != 0 => Err("decoder reported an error".to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// Drops the padding columns and rows decoders add to align the output. `None` when the
+/// decoded frame is narrower or shorter than `width` x `height`.
+fn crop(rgba: Vec<u8>, decoded_width: u32, width: u32, height: u32) -> Option<Vec<u8>> {
+    let (row, rows) = (width as usize * 4, height as usize);
+    let decoded_row = decoded_width as usize * 4;
+    if decoded_width < width || width == 0 || rgba.len() < decoded_row * rows {
+        return None;
+    }
+    if decoded_width == width && rgba.len() == row * rows {
+        return Some(rgba);
+    }
+    let cropped = rgba
+        .chunks_exact(decoded_row)
+        .take(rows)
+        .flat_map(|line| &line[..row])
+        .copied()
+        .collect();
+    Some(cropped)
+}
+
+/// Width and height from the first SOF marker of a JPEG.
+fn jpeg_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    let mut pos = 2;
+    while pos + 9 <= data.len() {
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            let height = u16::from_be_bytes([data[pos + 5], data[pos + 6]]) as u32;
+            let width = u16::from_be_bytes([data[pos + 7], data[pos + 8]]) as u32;
+            return (width > 0 && height > 0).then_some((width, height));
+        }
+        pos += 2 + length;
+    }
+    None
+}
+
+/// Converts full-range BT.601 YUV 4:2:0 (I420, or NV12 when `interleaved`) to RGBA.
+fn yuv420_to_rgba(
+    data: &[u8],
+    size: (u32, u32),
+    stride: usize,
+    interleaved: bool,
+) -> Result<Vec<u8>, String> {
+    let (width, height) = (size.0 as usize, size.1 as usize);
+    let chroma_height = height.div_ceil(2);
+    let chroma_stride = if interleaved { stride } else { stride / 2 };
+    let luma_len = stride * height;
+    let chroma_len = chroma_stride * chroma_height;
+    let needed = luma_len
+        + if interleaved {
+            chroma_len
+        } else {
+            chroma_len * 2
+        };
+    if stride < width || data.len() < needed {
+        return Err("decoded frame is smaller than its format".to_string());
+    }
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        let chroma_row = luma_len + (y / 2) * chroma_stride;
+        for x in 0..width {
+            let luma = data[y * stride + x] as f32;
+            let (u, v) = if interleaved {
+                let at = chroma_row + (x / 2) * 2;
+                (data[at], data[at + 1])
+            } else {
+                let at = chroma_row + x / 2;
+                (data[at], data[at + chroma_len])
+            };
+            let (u, v) = (u as f32 - 128.0, v as f32 - 128.0);
+            rgba.extend_from_slice(&[
+                (luma + 1.402 * v).clamp(0.0, 255.0) as u8,
+                (luma - 0.344_136 * u - 0.714_136 * v).clamp(0.0, 255.0) as u8,
+                (luma + 1.772 * u).clamp(0.0, 255.0) as u8,
+                0xFF,
+            ]);
+        }
+    }
+    Ok(rgba)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn v4l2_structs_match_the_kernel_layout() {
+        assert_eq!(size_of::<Capability>(), 104);
+        assert_eq!(size_of::<FmtDesc>(), 64);
+        assert_eq!(size_of::<PixFormatMplane>(), 192);
+        assert_eq!(size_of::<RequestBuffers>(), 20);
+        if cfg!(target_pointer_width = "64") {
+            assert_eq!(size_of::<Format>(), 208);
+            assert_eq!(size_of::<Plane>(), 64);
+            assert_eq!(size_of::<Buffer>(), 88);
+            assert_eq!(VIDIOC_QBUF, 0xC058_560F);
+        }
+    }
+
+    #[test]
+    fn jpeg_dimensions_and_yuv_conversion() {
+        let jpeg = [
+            0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00,
+            0x02, 0x00, 0x03,
+        ];
+        assert_eq!(jpeg_dimensions(&jpeg), Some((3, 2)));
+        assert_eq!(jpeg_dimensions(&[0xFF, 0xD8]), None);
+
+        // 2x2 I420 frame: white luma, neutral chroma.
+        let frame = [255, 255, 255, 255, 128, 128];
+        let rgba = yuv420_to_rgba(&frame, (2, 2), 2, false).expect("convert");
+        assert_eq!(rgba, [255; 16]);
+        let nv12 = [0, 0, 0, 0, 128, 128];
+        let rgba = yuv420_to_rgba(&nv12, (2, 2), 2, true).expect("convert");
+        assert_eq!(&rgba[..4], &[0, 0, 0, 255]);
+        assert!(yuv420_to_rgba(&frame[..4], (2, 2), 2, false).is_err());
+
+        assert_eq!(crop(vec![1; 32], 4, 2, 2), Some(vec![1; 16]));
+        assert_eq!(crop(vec![1; 16], 2, 4, 2), None);
+        assert_eq!(crop(vec![1; 16], 4, 4, 2), None);
+        assert_eq!(crop(Vec::new(), 0, 2, 2), None);
+    }
+}
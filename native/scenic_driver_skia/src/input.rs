@@ -1,13 +1,25 @@
 use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use rustler::{Atom, Encoder, Env, LocalPid, OwnedEnv, Term};
 
-#[derive(Clone, Debug)]
+use crate::flight_recorder::{FlightRecorder, log_line};
+use crate::hover::HoverRegions;
+use crate::input_record::InputRecorder;
+use crate::input_watchdog::InputWatchdog;
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum InputEvent {
     Key {
         key: String,
         action: u8,
         mods: u8,
+        /// Platform scan code of the physical key (evdev key code on DRM), `0` if unknown.
+        scancode: u32,
+        /// Number of auto-repeats since the key went down; `0` for the initial press.
+        repeat: u32,
     },
     Codepoint {
         codepoint: char,
@@ -24,11 +36,18 @@ pub enum InputEvent {
         x: f32,
         y: f32,
     },
+    /// `dx`/`dy` are the line deltas when the device reports them and pixel deltas
+    /// otherwise, as Scenic expects; `lines` and `pixels` carry whichever the device gave.
+    /// `stop` marks the end of a finger scroll, where kinetic scrolling takes over.
     CursorScroll {
         dx: f32,
         dy: f32,
         x: f32,
         y: f32,
+        source: ScrollSource,
+        lines: Option<(f32, f32)>,
+        pixels: Option<(f32, f32)>,
+        stop: bool,
     },
     Viewport {
         entered: bool,
@@ -39,6 +58,90 @@ pub enum InputEvent {
         width: u32,
         height: u32,
     },
+    /// One contact on a touchscreen. `id` stays the same from the contact's `Down` to its
+    /// `Up` or `Cancel`, so several fingers can be told apart.
+    Touch {
+        id: u32,
+        phase: TouchPhase,
+        x: f32,
+        y: f32,
+    },
+    /// The pointer entered or left a region registered with `set_hover_regions`.
+    Hover {
+        region: String,
+        entered: bool,
+    },
+}
+
+/// Where a touch contact is in its life, sent as `:down`, `:move`, `:up` or `:cancel`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TouchPhase {
+    Down,
+    Move,
+    Up,
+    /// The contact was lost without being lifted, e.g. the device went away.
+    Cancel,
+}
+
+impl TouchPhase {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Down => "down",
+            Self::Move => "move",
+            Self::Up => "up",
+            Self::Cancel => "cancel",
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Down),
+            1 => Some(Self::Move),
+            2 => Some(Self::Up),
+            3 => Some(Self::Cancel),
+            _ => None,
+        }
+    }
+}
+
+/// What produced a scroll, sent as `:wheel`, `:finger` or `:continuous`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScrollSource {
+    /// A wheel turning in steps; its deltas are in lines.
+    Wheel,
+    /// Fingers on a touchpad; their deltas are in pixels and end with a stop.
+    Finger,
+    /// A continuous axis such as a trackpoint with a scroll button. Backends that cannot
+    /// tell these from touchpads report them as `Finger`.
+    Continuous,
+}
+
+impl ScrollSource {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Wheel => "wheel",
+            Self::Finger => "finger",
+            Self::Continuous => "continuous",
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Wheel),
+            1 => Some(Self::Finger),
+            2 => Some(Self::Continuous),
+            _ => None,
+        }
+    }
+}
+
+/// Third element of `:cursor_scroll` events, which Scenic itself does not receive.
+#[derive(rustler::NifMap)]
+struct ScrollDetails {
+    source: Atom,
+    lines: Option<(f32, f32)>,
+    pixels: Option<(f32, f32)>,
+    stop: bool,
 }
 
 pub const INPUT_MASK_KEY: u32 = 0x01;
@@ -47,6 +150,17 @@ pub const INPUT_MASK_CURSOR_POS: u32 = 0x04;
 pub const INPUT_MASK_CURSOR_BUTTON: u32 = 0x08;
 pub const INPUT_MASK_CURSOR_SCROLL: u32 = 0x10;
 pub const INPUT_MASK_VIEWPORT: u32 = 0x20;
+pub const INPUT_MASK_TOUCH: u32 = 0x40;
+pub const INPUT_MASK_HOVER: u32 = 0x80;
+pub const INPUT_MASK_ALL: u32 = 0xFF;
+
+/// Logical size and scale of the output, as `%{width: .., height: .., scale: ..}`.
+#[derive(Clone, Copy, Debug, PartialEq, rustler::NifMap)]
+pub struct ViewportInfo {
+    pub width: u32,
+    pub height: u32,
+    pub scale: f64,
+}
 
 pub const MOD_SHIFT: u8 = 0x01;
 pub const MOD_CTRL: u8 = 0x02;
@@ -63,19 +177,108 @@ rustler::atoms! {
     cursor_button,
     cursor_scroll,
     viewport,
+    touch,
+    hover,
     enter,
     exit,
+    leave,
     reshape,
     shift,
     ctrl,
     alt,
     meta,
-    input_ready
+    input_ready,
+    drm_session,
+    paused,
+    resumed,
+    frame
+}
+
+/// Coarse classification of the device an input event came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceClass {
+    Keyboard,
+    Pointer,
+    Touch,
+    Gamepad,
+}
+
+impl DeviceClass {
+    const COUNT: usize = 4;
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "keyboard" => Ok(Self::Keyboard),
+            "pointer" => Ok(Self::Pointer),
+            "touch" => Ok(Self::Touch),
+            "gamepad" => Ok(Self::Gamepad),
+            _ => Err(format!("unsupported device class: {value}")),
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Self::Keyboard => 0,
+            Self::Pointer => 1,
+            Self::Touch => 2,
+            Self::Gamepad => 3,
+        }
+    }
+}
+
+/// Per-device-class input masks, ANDed with the global input mask by the backends.
+///
+/// Every class allows all event kinds until a narrower mask is set.
+pub struct DeviceClassMasks {
+    masks: [AtomicU32; DeviceClass::COUNT],
+}
+
+impl DeviceClassMasks {
+    pub fn new() -> Self {
+        Self {
+            masks: std::array::from_fn(|_| AtomicU32::new(INPUT_MASK_ALL)),
+        }
+    }
+
+    pub fn set(&self, class: DeviceClass, mask: u32) {
+        self.masks[class.index()].store(mask, Ordering::Relaxed);
+    }
+
+    /// Returns `mask` restricted to the event kinds enabled for `class`.
+    pub fn filter(&self, class: DeviceClass, mask: u32) -> u32 {
+        mask & self.masks[class.index()].load(Ordering::Relaxed)
+    }
 }
 
+/// Events kept per additional input target before the oldest are dropped, so a
+/// target that stops draining cannot grow its queue without bound.
+const MAX_TARGET_EVENTS: usize = 1024;
+
 pub struct InputQueue {
     events: VecDeque<InputEvent>,
     target: Option<LocalPid>,
+    mask: u32,
+    extra_targets: Vec<InputTarget>,
+    recorder: Option<InputRecorder>,
+    flight_recorder: Option<Arc<FlightRecorder>>,
+    viewport: Option<ViewportInfo>,
+    /// Minimum time between delivered cursor positions; `None` delivers every position.
+    cursor_interval: Option<Duration>,
+    last_cursor: Option<Instant>,
+    /// Newest position held back by `cursor_interval`, delivered once the interval ends
+    /// or right before the next button or scroll event.
+    held_cursor: Option<InputEvent>,
+    watchdog: Arc<InputWatchdog>,
+    /// Send `{:frame, timestamp_us}` to the target after every presented frame.
+    animation_frames: bool,
+    hover: HoverRegions,
+}
+
+/// An additional consumer registered next to the driver, with its own mask and queue.
+struct InputTarget {
+    pid: LocalPid,
+    mask: u32,
+    events: VecDeque<InputEvent>,
 }
 
 impl InputQueue {
@@ -83,18 +286,206 @@ impl InputQueue {
         Self {
             events: VecDeque::new(),
             target: None,
+            mask: INPUT_MASK_ALL,
+            extra_targets: Vec::new(),
+            recorder: None,
+            flight_recorder: None,
+            viewport: None,
+            cursor_interval: None,
+            last_cursor: None,
+            held_cursor: None,
+            watchdog: Arc::new(InputWatchdog::new()),
+            animation_frames: false,
+            hover: HoverRegions::new(),
         }
     }
 
-    pub fn push_event(&mut self, event: InputEvent) -> Option<LocalPid> {
-        // For cursor position events, replace any existing one to avoid stale positions
-        if matches!(event, InputEvent::CursorPos { .. }) {
-            self.events
-                .retain(|e| !matches!(e, InputEvent::CursorPos { .. }));
+    /// Queues `event` for the driver and every extra target whose mask accepts it, and
+    /// returns the pids to notify. With a cursor interval set, positions arriving faster
+    /// are held back and only the newest is delivered.
+    pub fn push_event(&mut self, event: InputEvent) -> Vec<LocalPid> {
+        self.watchdog.touch();
+        let Some(interval) = self.cursor_interval else {
+            return self.deliver(event);
+        };
+        let now = Instant::now();
+        match event {
+            InputEvent::CursorPos { .. } => {
+                if self.last_cursor.is_some_and(|last| now < last + interval) {
+                    self.held_cursor = Some(event);
+                    return Vec::new();
+                }
+                self.held_cursor = None;
+                self.last_cursor = Some(now);
+                self.deliver(event)
+            }
+            InputEvent::CursorButton { .. } | InputEvent::CursorScroll { .. } => {
+                let Some(held) = self.held_cursor.take() else {
+                    return self.deliver(event);
+                };
+                self.last_cursor = Some(now);
+                let mut notify = self.deliver(held);
+                for pid in self.deliver(event) {
+                    if !notify.contains(&pid) {
+                        notify.push(pid);
+                    }
+                }
+                notify
+            }
+            event => self.deliver(event),
+        }
+    }
+
+    /// Sets the minimum time between delivered cursor positions; zero delivers all.
+    pub fn set_cursor_interval(&mut self, interval: Duration) {
+        self.cursor_interval = (!interval.is_zero()).then_some(interval);
+        if self.cursor_interval.is_none()
+            && let Some(held) = self.held_cursor.take()
+        {
+            // Called from NIF context, which cannot notify; the next event picks it up.
+            self.deliver(held);
         }
-        self.events.push_back(event);
-        // Always notify when events are pushed - removes batching delay
-        self.target
+    }
+
+    /// When the held-back cursor position is due, for backends to wake up and flush it.
+    pub fn held_cursor_due(&self) -> Option<Instant> {
+        let interval = self.cursor_interval?;
+        self.held_cursor.as_ref()?;
+        Some(
+            self.last_cursor
+                .map_or_else(Instant::now, |last| last + interval),
+        )
+    }
+
+    /// Delivers the held-back cursor position once its interval has passed, so the last
+    /// position of a motion is not lost when the pointer stops.
+    pub fn flush_held_cursor(&mut self) -> Vec<LocalPid> {
+        let now = Instant::now();
+        if self.held_cursor_due().is_none_or(|due| due > now) {
+            return Vec::new();
+        }
+        let Some(held) = self.held_cursor.take() else {
+            return Vec::new();
+        };
+        self.last_cursor = Some(now);
+        self.deliver(held)
+    }
+
+    fn deliver(&mut self, event: InputEvent) -> Vec<LocalPid> {
+        if let Some(recorder) = self.recorder.as_mut()
+            && let Err(err) = recorder.record(&event)
+        {
+            log_line!("Input recording stopped: {err}");
+            self.recorder = None;
+        }
+        if let Some(flight_recorder) = &self.flight_recorder {
+            flight_recorder.record_input(&event);
+        }
+        let hover = match &event {
+            InputEvent::CursorPos { x, y } => self.hover.pointer_moved(*x, *y),
+            InputEvent::Viewport { entered: false, .. } => self.hover.pointer_left(),
+            _ => Vec::new(),
+        };
+        let mut notify = self.dispatch(event);
+        for change in hover {
+            for pid in self.dispatch(change) {
+                if !notify.contains(&pid) {
+                    notify.push(pid);
+                }
+            }
+        }
+        notify
+    }
+
+    /// Queues `event` for the driver and the targets whose mask accepts it.
+    fn dispatch(&mut self, event: InputEvent) -> Vec<LocalPid> {
+        let bit = event.mask_bit();
+        let mut notify = Vec::new();
+        for target in self
+            .extra_targets
+            .iter_mut()
+            .filter(|target| target.mask & bit != 0)
+        {
+            push_coalesced(&mut target.events, event.clone());
+            if target.events.len() > MAX_TARGET_EVENTS {
+                target.events.pop_front();
+            }
+            notify.push(target.pid);
+        }
+        if self.mask & bit != 0 {
+            push_coalesced(&mut self.events, event);
+            // Always notify when events are pushed - removes batching delay
+            notify.extend(self.target);
+        }
+        notify
+    }
+
+    /// Sets the driver's own mask. Returns the union with all extra target masks, which
+    /// is what backends should filter on.
+    pub fn set_mask(&mut self, mask: u32) -> u32 {
+        self.mask = mask;
+        self.combined_mask()
+    }
+
+    /// Registers (or updates the mask of) an extra target. Returns the combined mask.
+    pub fn add_target(&mut self, pid: LocalPid, mask: u32) -> u32 {
+        match self
+            .extra_targets
+            .iter_mut()
+            .find(|target| target.pid == pid)
+        {
+            Some(target) => target.mask = mask,
+            None => self.extra_targets.push(InputTarget {
+                pid,
+                mask,
+                events: VecDeque::new(),
+            }),
+        }
+        self.combined_mask()
+    }
+
+    /// Removes an extra target and its pending events. Returns the combined mask.
+    pub fn remove_target(&mut self, pid: LocalPid) -> u32 {
+        self.extra_targets.retain(|target| target.pid != pid);
+        self.combined_mask()
+    }
+
+    pub fn drain_target(&mut self, pid: LocalPid) -> Result<Vec<InputEvent>, String> {
+        self.extra_targets
+            .iter_mut()
+            .find(|target| target.pid == pid)
+            .map(|target| target.events.drain(..).collect())
+            .ok_or_else(|| "input target not registered".to_string())
+    }
+
+    fn combined_mask(&self) -> u32 {
+        let mask = self
+            .extra_targets
+            .iter()
+            .fold(self.mask, |mask, target| mask | target.mask);
+        // Hover changes are worked out from cursor positions, so backends must report them.
+        if mask & INPUT_MASK_HOVER != 0 {
+            mask | INPUT_MASK_CURSOR_POS | INPUT_MASK_VIEWPORT
+        } else {
+            mask
+        }
+    }
+
+    /// Replaces the hover regions, given in scene coordinates. Returns the pids to notify
+    /// of changes for a pointer already over them.
+    pub fn set_hover_regions(
+        &mut self,
+        regions: Vec<(String, (f32, f32, f32, f32))>,
+    ) -> Vec<LocalPid> {
+        let mut notify = Vec::new();
+        for change in self.hover.set(regions) {
+            for pid in self.dispatch(change) {
+                if !notify.contains(&pid) {
+                    notify.push(pid);
+                }
+            }
+        }
+        notify
     }
 
     pub fn set_target(&mut self, target: Option<LocalPid>) {
@@ -103,17 +494,147 @@ impl InputQueue {
         // which cannot use OwnedEnv::send_and_clear. Events will be picked up on next push.
     }
 
+    pub fn target(&self) -> Option<LocalPid> {
+        self.target
+    }
+
+    pub fn set_animation_frames(&mut self, enabled: bool) {
+        self.animation_frames = enabled;
+    }
+
+    /// The target to tell about presented frames, while `request_animation_frames` is on.
+    pub fn frame_target(&self) -> Option<LocalPid> {
+        self.target.filter(|_| self.animation_frames)
+    }
+
+    pub fn start_recording(&mut self, recorder: InputRecorder) -> Result<(), String> {
+        self.stop_recording()?;
+        self.recorder = Some(recorder);
+        Ok(())
+    }
+
+    /// Keeps recent events in `recorder` for flight recorder dumps.
+    pub fn set_flight_recorder(&mut self, recorder: Arc<FlightRecorder>) {
+        self.flight_recorder = Some(recorder);
+    }
+
+    pub fn stop_recording(&mut self) -> Result<(), String> {
+        match self.recorder.take() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// Dead-man timer touched by every pushed event.
+    pub fn input_watchdog(&self) -> Arc<InputWatchdog> {
+        Arc::clone(&self.watchdog)
+    }
+
+    /// Records the viewport the backend currently presents, for `reshape_viewport`.
+    pub fn set_viewport(&mut self, viewport: ViewportInfo) {
+        self.viewport = Some(viewport);
+    }
+
+    pub fn viewport(&self) -> Option<ViewportInfo> {
+        self.viewport
+    }
+
+    /// Queues a `ViewportReshape` for the current viewport again, so targets attached
+    /// after the backend's own reshape learn the size. Returns the viewport and the pids
+    /// to notify.
+    pub fn reshape_viewport(&mut self) -> Result<(ViewportInfo, Vec<LocalPid>), String> {
+        let viewport = self
+            .viewport
+            .ok_or_else(|| "viewport size not known yet".to_string())?;
+        let notify = self.push_event(InputEvent::ViewportReshape {
+            width: viewport.width,
+            height: viewport.height,
+        });
+        Ok((viewport, notify))
+    }
+
     pub fn drain(&mut self) -> Vec<InputEvent> {
         self.events.drain(..).collect()
     }
 }
 
+/// For cursor position events, replace any existing one to avoid stale positions.
+fn push_coalesced(events: &mut VecDeque<InputEvent>, event: InputEvent) {
+    if matches!(event, InputEvent::CursorPos { .. }) {
+        events.retain(|e| !matches!(e, InputEvent::CursorPos { .. }));
+    }
+    events.push_back(event);
+}
+
 pub fn notify_input_ready(pid: LocalPid) {
     let mut env = OwnedEnv::new();
     let _ = env.send_and_clear(&pid, |_| input_ready());
 }
 
+/// Delivers a cursor position held back by the cursor interval once it is due and tells
+/// its targets. Returns the targets notified and when the next held position is due.
+pub fn flush_held_cursor(queue: &Mutex<InputQueue>) -> (Vec<LocalPid>, Option<Instant>) {
+    let (notify, due) = match queue.lock() {
+        Ok(mut queue) => (queue.flush_held_cursor(), queue.held_cursor_due()),
+        Err(_) => return (Vec::new(), None),
+    };
+    for pid in &notify {
+        notify_input_ready(*pid);
+    }
+    (notify, due)
+}
+
+/// Sends `{:drm_session, :paused | :resumed}` when the DRM session is deactivated or regained.
+pub fn notify_drm_session(pid: LocalPid, active: bool) {
+    let mut env = OwnedEnv::new();
+    let state = if active { resumed() } else { paused() };
+    let _ = env.send_and_clear(&pid, |_| (drm_session(), state));
+}
+
+/// Sends `{:frame, timestamp_us}` once a frame is on screen, if animation frames were
+/// requested. `timestamp_us` is on the driver clock (`CLOCK_MONOTONIC`).
+pub fn notify_frame_presented(input_events: &Mutex<InputQueue>, timestamp_us: u64) {
+    let target = input_events
+        .lock()
+        .ok()
+        .and_then(|queue| queue.frame_target());
+    if let Some(pid) = target {
+        let mut env = OwnedEnv::new();
+        let _ = env.send_and_clear(&pid, |_| (frame(), timestamp_us));
+    }
+}
+
 impl InputEvent {
+    pub fn mask_bit(&self) -> u32 {
+        match self {
+            InputEvent::Key { .. } => INPUT_MASK_KEY,
+            InputEvent::Codepoint { .. } => INPUT_MASK_CODEPOINT,
+            InputEvent::CursorPos { .. } => INPUT_MASK_CURSOR_POS,
+            InputEvent::CursorButton { .. } => INPUT_MASK_CURSOR_BUTTON,
+            InputEvent::CursorScroll { .. } => INPUT_MASK_CURSOR_SCROLL,
+            InputEvent::Viewport { .. } | InputEvent::ViewportReshape { .. } => INPUT_MASK_VIEWPORT,
+            InputEvent::Touch { .. } => INPUT_MASK_TOUCH,
+            InputEvent::Hover { .. } => INPUT_MASK_HOVER,
+        }
+    }
+
+    /// Rewrites the pointer coordinates carried by the event, if any.
+    pub fn map_position(&mut self, map: impl Fn(f32, f32) -> (f32, f32)) {
+        match self {
+            InputEvent::CursorPos { x, y }
+            | InputEvent::CursorButton { x, y, .. }
+            | InputEvent::CursorScroll { x, y, .. }
+            | InputEvent::Viewport { x, y, .. }
+            | InputEvent::Touch { x, y, .. } => {
+                (*x, *y) = map(*x, *y);
+            }
+            InputEvent::Key { .. }
+            | InputEvent::Codepoint { .. }
+            | InputEvent::ViewportReshape { .. }
+            | InputEvent::Hover { .. } => {}
+        }
+    }
+
     fn mods_to_terms<'a>(env: Env<'a>, mods: u8) -> Vec<Term<'a>> {
         let mut terms = Vec::new();
         if mods & MOD_SHIFT != 0 {
@@ -139,11 +660,13 @@ impl Encoder for InputEvent {
                 key: key_name,
                 action,
                 mods,
+                scancode,
+                repeat,
             } => {
                 let key_atom = Atom::from_str(env, key_name)
                     .unwrap_or_else(|_| Atom::from_str(env, "key_unknown").expect("key_unknown"));
                 let mods = InputEvent::mods_to_terms(env, *mods);
-                (key(), (key_atom, *action, mods)).encode(env)
+                (key(), (key_atom, *action, mods, *scancode, *repeat)).encode(env)
             }
             InputEvent::Codepoint {
                 codepoint: codepoint_char,
@@ -165,8 +688,23 @@ impl Encoder for InputEvent {
                 let mods = InputEvent::mods_to_terms(env, *mods);
                 (cursor_button(), (button_atom, *action, mods, (*x, *y))).encode(env)
             }
-            InputEvent::CursorScroll { dx, dy, x, y } => {
-                (cursor_scroll(), ((*dx, *dy), (*x, *y))).encode(env)
+            InputEvent::CursorScroll {
+                dx,
+                dy,
+                x,
+                y,
+                source,
+                lines,
+                pixels,
+                stop,
+            } => {
+                let details = ScrollDetails {
+                    source: Atom::from_str(env, source.name()).expect("scroll source atom"),
+                    lines: *lines,
+                    pixels: *pixels,
+                    stop: *stop,
+                };
+                (cursor_scroll(), ((*dx, *dy), (*x, *y), details)).encode(env)
             }
             InputEvent::Viewport { entered, x, y } => {
                 let dir = if *entered { enter() } else { exit() };
@@ -175,6 +713,14 @@ impl Encoder for InputEvent {
             InputEvent::ViewportReshape { width, height } => {
                 (viewport(), (reshape(), (*width, *height))).encode(env)
             }
+            InputEvent::Touch { id, phase, x, y } => {
+                let phase = Atom::from_str(env, phase.name()).expect("touch phase atom");
+                (touch(), (*id, phase, (*x, *y))).encode(env)
+            }
+            InputEvent::Hover { region, entered } => {
+                let change = if *entered { enter() } else { leave() };
+                (hover(), (change, region.as_str())).encode(env)
+            }
         }
     }
 }
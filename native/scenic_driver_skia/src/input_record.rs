@@ -0,0 +1,503 @@
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+use std::time::Instant;
+
+use crate::input::{InputEvent, ScrollSource, TouchPhase};
+
+const LOG_MAGIC: &[u8; 4] = b"SDIR";
+const LOG_VERSION: u8 = 3;
+/// Version 1 logs lack key scan codes and repeat counts; they replay with zeroes.
+const LOG_VERSION_V1: u8 = 1;
+/// Version 2 and older logs lack scroll sources; their scrolls replay as wheel lines.
+const LOG_VERSION_V2: u8 = 2;
+
+const SCROLL_LINES: u8 = 0x01;
+const SCROLL_PIXELS: u8 = 0x02;
+const SCROLL_STOP: u8 = 0x04;
+
+const TAG_KEY: u8 = 0;
+const TAG_CODEPOINT: u8 = 1;
+const TAG_CURSOR_POS: u8 = 2;
+const TAG_CURSOR_BUTTON: u8 = 3;
+const TAG_CURSOR_SCROLL: u8 = 4;
+const TAG_VIEWPORT: u8 = 5;
+const TAG_VIEWPORT_RESHAPE: u8 = 6;
+const TAG_TOUCH: u8 = 7;
+/// Only in `drain_input_binary` batches; hover changes are never recorded.
+const TAG_HOVER: u8 = 8;
+
+/// Writes queued input events to a compact binary log.
+///
+/// The log starts with a `SDIR` magic and a version byte, followed by
+/// records of `u32` milliseconds since recording started, a tag byte and a
+/// big-endian payload.
+pub struct InputRecorder {
+    writer: BufWriter<File>,
+    started: Instant,
+}
+
+impl InputRecorder {
+    pub fn create(path: &Path) -> Result<Self, String> {
+        let file = File::create(path)
+            .map_err(|err| format!("failed to create input log {}: {err}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        writer
+            .write_all(LOG_MAGIC)
+            .and_then(|_| writer.write_all(&[LOG_VERSION]))
+            .map_err(|err| format!("failed to write input log header: {err}"))?;
+        Ok(Self {
+            writer,
+            started: Instant::now(),
+        })
+    }
+
+    pub fn record(&mut self, event: &InputEvent) -> Result<(), String> {
+        // Hover changes are worked out from the cursor positions again on replay.
+        if matches!(event, InputEvent::Hover { .. }) {
+            return Ok(());
+        }
+        let elapsed_ms = self.started.elapsed().as_millis().min(u32::MAX as u128) as u32;
+        let mut buf = Vec::with_capacity(24);
+        buf.extend_from_slice(&elapsed_ms.to_be_bytes());
+        encode_event(&mut buf, event);
+        self.writer
+            .write_all(&buf)
+            .map_err(|err| format!("failed to write input log: {err}"))
+    }
+
+    pub fn finish(mut self) -> Result<(), String> {
+        self.writer
+            .flush()
+            .map_err(|err| format!("failed to flush input log: {err}"))
+    }
+}
+
+/// Packs events for `drain_input_binary`: each is a tag byte and the payload of its log
+/// record, without the timestamp, so a burst of touch or pen events crosses the NIF as
+/// one binary instead of a term per event.
+pub fn encode_events(events: &[InputEvent]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(events.len() * 20);
+    for event in events {
+        encode_event(&mut buf, event);
+    }
+    buf
+}
+
+pub fn read_log(path: &Path) -> Result<Vec<(u32, InputEvent)>, String> {
+    let mut bytes = Vec::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut bytes))
+        .map_err(|err| format!("failed to read input log {}: {err}", path.display()))?;
+    decode_log(&bytes)
+}
+
+fn encode_event(buf: &mut Vec<u8>, event: &InputEvent) {
+    match event {
+        InputEvent::Key {
+            key,
+            action,
+            mods,
+            scancode,
+            repeat,
+        } => {
+            buf.extend_from_slice(&[TAG_KEY, *action, *mods]);
+            buf.extend_from_slice(&scancode.to_be_bytes());
+            buf.extend_from_slice(&repeat.to_be_bytes());
+            push_str(buf, key);
+        }
+        InputEvent::Codepoint { codepoint, mods } => {
+            buf.push(TAG_CODEPOINT);
+            buf.extend_from_slice(&(*codepoint as u32).to_be_bytes());
+            buf.push(*mods);
+        }
+        InputEvent::CursorPos { x, y } => {
+            buf.push(TAG_CURSOR_POS);
+            push_f32s(buf, &[*x, *y]);
+        }
+        InputEvent::CursorButton {
+            button,
+            action,
+            mods,
+            x,
+            y,
+        } => {
+            buf.extend_from_slice(&[TAG_CURSOR_BUTTON, *action, *mods]);
+            push_f32s(buf, &[*x, *y]);
+            push_str(buf, button);
+        }
+        InputEvent::CursorScroll {
+            dx,
+            dy,
+            x,
+            y,
+            source,
+            lines,
+            pixels,
+            stop,
+        } => {
+            let mut flags = 0;
+            if lines.is_some() {
+                flags |= SCROLL_LINES;
+            }
+            if pixels.is_some() {
+                flags |= SCROLL_PIXELS;
+            }
+            if *stop {
+                flags |= SCROLL_STOP;
+            }
+            buf.extend_from_slice(&[TAG_CURSOR_SCROLL, *source as u8, flags]);
+            push_f32s(buf, &[*dx, *dy, *x, *y]);
+            for delta in lines.iter().chain(pixels) {
+                push_f32s(buf, &[delta.0, delta.1]);
+            }
+        }
+        InputEvent::Viewport { entered, x, y } => {
+            buf.extend_from_slice(&[TAG_VIEWPORT, u8::from(*entered)]);
+            push_f32s(buf, &[*x, *y]);
+        }
+        InputEvent::ViewportReshape { width, height } => {
+            buf.push(TAG_VIEWPORT_RESHAPE);
+            buf.extend_from_slice(&width.to_be_bytes());
+            buf.extend_from_slice(&height.to_be_bytes());
+        }
+        InputEvent::Touch { id, phase, x, y } => {
+            buf.push(TAG_TOUCH);
+            buf.extend_from_slice(&id.to_be_bytes());
+            buf.push(*phase as u8);
+            push_f32s(buf, &[*x, *y]);
+        }
+        InputEvent::Hover { region, entered } => {
+            buf.extend_from_slice(&[TAG_HOVER, u8::from(*entered)]);
+            push_str(buf, region);
+        }
+    }
+}
+
+fn push_f32s(buf: &mut Vec<u8>, values: &[f32]) {
+    for value in values {
+        buf.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn push_str(buf: &mut Vec<u8>, value: &str) {
+    let len = value.len().min(u16::MAX as usize);
+    buf.extend_from_slice(&(len as u16).to_be_bytes());
+    buf.extend_from_slice(&value.as_bytes()[..len]);
+}
+
+fn decode_log(bytes: &[u8]) -> Result<Vec<(u32, InputEvent)>, String> {
+    if bytes.len() < 5 || !bytes.starts_with(LOG_MAGIC) {
+        return Err("input log header missing".to_string());
+    }
+    let version = bytes[4];
+    if ![LOG_VERSION, LOG_VERSION_V2, LOG_VERSION_V1].contains(&version) {
+        return Err(format!("unsupported input log version: {version}"));
+    }
+
+    let mut reader = LogReader {
+        bytes,
+        offset: LOG_MAGIC.len() + 1,
+    };
+    let mut events = Vec::new();
+    while reader.offset < bytes.len() {
+        let elapsed_ms = reader.u32()?;
+        let event = match reader.u8()? {
+            TAG_KEY => {
+                let action = reader.u8()?;
+                let mods = reader.u8()?;
+                let (scancode, repeat) = if version == LOG_VERSION_V1 {
+                    (0, 0)
+                } else {
+                    (reader.u32()?, reader.u32()?)
+                };
+                let key = reader.string()?;
+                InputEvent::Key {
+                    key,
+                    action,
+                    mods,
+                    scancode,
+                    repeat,
+                }
+            }
+            TAG_CODEPOINT => {
+                let codepoint = char::from_u32(reader.u32()?)
+                    .ok_or_else(|| "input log codepoint invalid".to_string())?;
+                let mods = reader.u8()?;
+                InputEvent::Codepoint { codepoint, mods }
+            }
+            TAG_CURSOR_POS => InputEvent::CursorPos {
+                x: reader.f32()?,
+                y: reader.f32()?,
+            },
+            TAG_CURSOR_BUTTON => {
+                let action = reader.u8()?;
+                let mods = reader.u8()?;
+                let x = reader.f32()?;
+                let y = reader.f32()?;
+                let button = reader.string()?;
+                InputEvent::CursorButton {
+                    button,
+                    action,
+                    mods,
+                    x,
+                    y,
+                }
+            }
+            TAG_CURSOR_SCROLL if version < LOG_VERSION => {
+                let (dx, dy) = (reader.f32()?, reader.f32()?);
+                InputEvent::CursorScroll {
+                    dx,
+                    dy,
+                    x: reader.f32()?,
+                    y: reader.f32()?,
+                    source: ScrollSource::Wheel,
+                    lines: Some((dx, dy)),
+                    pixels: None,
+                    stop: false,
+                }
+            }
+            TAG_CURSOR_SCROLL => {
+                let source = ScrollSource::from_u8(reader.u8()?)
+                    .ok_or_else(|| "input log scroll source invalid".to_string())?;
+                let flags = reader.u8()?;
+                let (dx, dy, x, y) = (reader.f32()?, reader.f32()?, reader.f32()?, reader.f32()?);
+                let mut pair = |flag: u8| -> Result<Option<(f32, f32)>, String> {
+                    if flags & flag == 0 {
+                        return Ok(None);
+                    }
+                    Ok(Some((reader.f32()?, reader.f32()?)))
+                };
+                let lines = pair(SCROLL_LINES)?;
+                let pixels = pair(SCROLL_PIXELS)?;
+                InputEvent::CursorScroll {
+                    dx,
+                    dy,
+                    x,
+                    y,
+                    source,
+                    lines,
+                    pixels,
+                    stop: flags & SCROLL_STOP != 0,
+                }
+            }
+            TAG_VIEWPORT => InputEvent::Viewport {
+                entered: reader.u8()? != 0,
+                x: reader.f32()?,
+                y: reader.f32()?,
+            },
+            TAG_VIEWPORT_RESHAPE => InputEvent::ViewportReshape {
+                width: reader.u32()?,
+                height: reader.u32()?,
+            },
+            TAG_TOUCH => InputEvent::Touch {
+                id: reader.u32()?,
+                phase: TouchPhase::from_u8(reader.u8()?)
+                    .ok_or_else(|| "input log touch phase invalid".to_string())?,
+                x: reader.f32()?,
+                y: reader.f32()?,
+            },
+            tag => return Err(format!("unsupported input log tag: {tag}")),
+        };
+        events.push((elapsed_ms, event));
+    }
+    Ok(events)
+}
+
+struct LogReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl LogReader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], String> {
+        let end = self.offset + len;
+        if end > self.bytes.len() {
+            return Err("input log record truncated".to_string());
+        }
+        let slice = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn f32(&mut self) -> Result<f32, String> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len_bytes = self.take(2)?;
+        let len = u16::from_be_bytes([len_bytes[0], len_bytes[1]]) as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| "input log string invalid".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_round_trips_events() {
+        let events = vec![
+            InputEvent::Key {
+                key: "key_a".to_string(),
+                action: 1,
+                mods: 0x03,
+                scancode: 30,
+                repeat: 2,
+            },
+            InputEvent::Codepoint {
+                codepoint: 'é',
+                mods: 0,
+            },
+            InputEvent::CursorPos { x: 10.5, y: 20.0 },
+            InputEvent::CursorButton {
+                button: "btn_left".to_string(),
+                action: 0,
+                mods: 0x08,
+                x: 1.0,
+                y: 2.0,
+            },
+            InputEvent::CursorScroll {
+                dx: 0.0,
+                dy: -1.0,
+                x: 3.0,
+                y: 4.0,
+                source: ScrollSource::Wheel,
+                lines: Some((0.0, -1.0)),
+                pixels: None,
+                stop: false,
+            },
+            InputEvent::CursorScroll {
+                dx: 2.5,
+                dy: -12.0,
+                x: 3.0,
+                y: 4.0,
+                source: ScrollSource::Finger,
+                lines: None,
+                pixels: Some((2.5, -12.0)),
+                stop: true,
+            },
+            InputEvent::Viewport {
+                entered: true,
+                x: 5.0,
+                y: 6.0,
+            },
+            InputEvent::ViewportReshape {
+                width: 800,
+                height: 600,
+            },
+            InputEvent::Touch {
+                id: 7,
+                phase: TouchPhase::Move,
+                x: 7.5,
+                y: 8.0,
+            },
+        ];
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(LOG_MAGIC);
+        bytes.push(LOG_VERSION);
+        for (idx, event) in events.iter().enumerate() {
+            bytes.extend_from_slice(&(idx as u32 * 16).to_be_bytes());
+            encode_event(&mut bytes, event);
+        }
+
+        let decoded = decode_log(&bytes).expect("decode_log failed");
+        assert_eq!(decoded.len(), events.len());
+        for (idx, ((elapsed_ms, decoded), original)) in decoded.iter().zip(&events).enumerate() {
+            assert_eq!(*elapsed_ms, idx as u32 * 16);
+            assert_eq!(format!("{decoded:?}"), format!("{original:?}"));
+        }
+    }
+
+    #[test]
+    fn batches_pack_events_without_timestamps() {
+        let bytes = encode_events(&[
+            InputEvent::CursorPos { x: 1.0, y: 2.0 },
+            InputEvent::Hover {
+                region: "menu".to_string(),
+                entered: true,
+            },
+        ]);
+        let mut expected = vec![TAG_CURSOR_POS];
+        push_f32s(&mut expected, &[1.0, 2.0]);
+        expected.extend_from_slice(&[TAG_HOVER, 1]);
+        push_str(&mut expected, "menu");
+        assert_eq!(bytes, expected);
+        assert!(encode_events(&[]).is_empty());
+    }
+
+    #[test]
+    fn log_rejects_bad_header_and_truncation() {
+        assert!(decode_log(b"NOPE\x01").is_err());
+        assert!(decode_log(b"SDIR\x04").is_err());
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(LOG_MAGIC);
+        bytes.push(LOG_VERSION);
+        bytes.extend_from_slice(&0u32.to_be_bytes());
+        bytes.push(TAG_CURSOR_POS);
+        bytes.extend_from_slice(&1.0f32.to_be_bytes());
+        assert_eq!(
+            decode_log(&bytes).unwrap_err(),
+            "input log record truncated"
+        );
+    }
+
+    #[test]
+    fn log_reads_version_two_scrolls_as_wheel_lines() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(LOG_MAGIC);
+        bytes.push(LOG_VERSION_V2);
+        bytes.extend_from_slice(&0u32.to_be_bytes());
+        bytes.push(TAG_CURSOR_SCROLL);
+        push_f32s(&mut bytes, &[0.0, 2.0, 3.0, 4.0]);
+        let decoded = decode_log(&bytes).expect("decode_log failed");
+        assert_eq!(
+            decoded,
+            vec![(
+                0,
+                InputEvent::CursorScroll {
+                    dx: 0.0,
+                    dy: 2.0,
+                    x: 3.0,
+                    y: 4.0,
+                    source: ScrollSource::Wheel,
+                    lines: Some((0.0, 2.0)),
+                    pixels: None,
+                    stop: false,
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn log_reads_version_one_key_records() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(LOG_MAGIC);
+        bytes.push(LOG_VERSION_V1);
+        bytes.extend_from_slice(&0u32.to_be_bytes());
+        bytes.extend_from_slice(&[TAG_KEY, 1, 0]);
+        push_str(&mut bytes, "key_a");
+
+        let decoded = decode_log(&bytes).expect("decode_log failed");
+        assert!(matches!(
+            &decoded[0].1,
+            InputEvent::Key {
+                scancode: 0,
+                repeat: 0,
+                ..
+            }
+        ));
+    }
+}
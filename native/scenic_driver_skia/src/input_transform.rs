@@ -0,0 +1,198 @@
+use crate::input::InputEvent;
+use crate::renderer::Overscan;
+
+/// Clockwise rotation of the output relative to the panel's native orientation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rotation {
+    #[default]
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+impl Rotation {
+    pub fn from_degrees(degrees: u32) -> Result<Self, String> {
+        match degrees {
+            0 => Ok(Self::None),
+            90 => Ok(Self::Cw90),
+            180 => Ok(Self::Cw180),
+            270 => Ok(Self::Cw270),
+            _ => Err(format!("unsupported input rotation: {degrees}")),
+        }
+    }
+
+    pub fn degrees(self) -> u32 {
+        match self {
+            Self::None => 0,
+            Self::Cw90 => 90,
+            Self::Cw180 => 180,
+            Self::Cw270 => 270,
+        }
+    }
+
+    /// Whether the rotated output is the panel turned on its side.
+    pub fn swaps_axes(self) -> bool {
+        matches!(self, Self::Cw90 | Self::Cw270)
+    }
+
+    /// Size of the rotated output for a panel of `size`.
+    pub fn rotate_size<T>(self, size: (T, T)) -> (T, T) {
+        if self.swaps_axes() {
+            (size.1, size.0)
+        } else {
+            size
+        }
+    }
+
+    /// Maps a panel point into the rotated output, returning the point and the rotated size.
+    fn unrotate(self, size: (f32, f32), x: f32, y: f32) -> ((f32, f32), (f32, f32)) {
+        let (width, height) = size;
+        match self {
+            Self::None => ((x, y), size),
+            Self::Cw90 => ((y, width - x), (height, width)),
+            Self::Cw180 => ((width - x, height - y), size),
+            Self::Cw270 => ((height - y, x), (height, width)),
+        }
+    }
+}
+
+pub const IDENTITY_CALIBRATION: [f32; 6] = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+
+/// Maps device positions into scene coordinates for every backend.
+///
+/// Stages run in order: the calibration matrix (`[a, b, c, d, e, f]` on coordinates
+/// normalized to the output size, as in libinput), output rotation, letterbox offset,
+/// scale and finally the overscan margins. Pointer, touch and pen positions all go
+/// through [`InputTransform::apply`], so new output features only extend this chain.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InputTransform {
+    pub rotation: Rotation,
+    pub scale: (f32, f32),
+    pub offset: (f32, f32),
+    pub calibration: [f32; 6],
+    pub overscan: Overscan,
+    /// Output size seen by the last mapped event, kept for `map_point`.
+    pub output_size: (f32, f32),
+}
+
+impl Default for InputTransform {
+    fn default() -> Self {
+        Self {
+            rotation: Rotation::None,
+            scale: (1.0, 1.0),
+            offset: (0.0, 0.0),
+            calibration: IDENTITY_CALIBRATION,
+            overscan: Overscan::default(),
+            output_size: (0.0, 0.0),
+        }
+    }
+}
+
+impl InputTransform {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.rotation == Rotation::None
+            && self.scale == (1.0, 1.0)
+            && self.offset == (0.0, 0.0)
+            && self.calibration == IDENTITY_CALIBRATION
+            && self.overscan.is_zero()
+    }
+
+    /// Maps the position of `event` for an output of `size` and remembers the size.
+    pub fn apply(&mut self, size: (f32, f32), event: &mut InputEvent) {
+        self.output_size = size;
+        if self.is_identity() {
+            return;
+        }
+        event.map_position(|x, y| self.map(size, x, y));
+    }
+
+    /// Maps a point using the output size of the last event.
+    pub fn map_point(&self, x: f32, y: f32) -> (f32, f32) {
+        self.map(self.output_size, x, y)
+    }
+
+    pub fn map(&self, size: (f32, f32), x: f32, y: f32) -> (f32, f32) {
+        let (width, height) = size;
+        let (x, y) = if width > 0.0 && height > 0.0 {
+            let [a, b, c, d, e, f] = self.calibration;
+            let (nx, ny) = (x / width, y / height);
+            (
+                (a * nx + b * ny + c) * width,
+                (d * nx + e * ny + f) * height,
+            )
+        } else {
+            (x, y)
+        };
+
+        let ((x, y), (width, height)) = self.rotation.unrotate(size, x, y);
+
+        // The letterboxed content is centered, so the offset applies on both sides.
+        let (offset_x, offset_y) = self.offset;
+        let (scale_x, scale_y) = self.scale;
+        let content = (
+            (width - 2.0 * offset_x).max(0.0) / scale_x,
+            (height - 2.0 * offset_y).max(0.0) / scale_y,
+        );
+        let (x, y) = ((x - offset_x) / scale_x, (y - offset_y) / scale_y);
+
+        self.overscan.to_content(content, x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_point(actual: (f32, f32), expected: (f32, f32)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-3 && (actual.1 - expected.1).abs() < 1e-3,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn input_transform_applies_stages_in_order() {
+        let mut transform = InputTransform::new();
+        assert_point(transform.map((200.0, 100.0), 20.0, 10.0), (20.0, 10.0));
+
+        transform.rotation = Rotation::Cw90;
+        assert_point(transform.map((200.0, 100.0), 20.0, 10.0), (10.0, 180.0));
+        transform.rotation = Rotation::Cw180;
+        assert_point(transform.map((200.0, 100.0), 20.0, 10.0), (180.0, 90.0));
+        transform.rotation = Rotation::Cw270;
+        assert_point(transform.map((200.0, 100.0), 20.0, 10.0), (90.0, 20.0));
+
+        transform.rotation = Rotation::None;
+        transform.offset = (20.0, 0.0);
+        transform.scale = (2.0, 2.0);
+        assert_point(transform.map((200.0, 100.0), 120.0, 50.0), (50.0, 25.0));
+
+        transform.overscan = Overscan {
+            left: 8.0,
+            right: 8.0,
+            ..Overscan::default()
+        };
+        assert_point(transform.map((200.0, 100.0), 120.0, 50.0), (52.5, 25.0));
+        assert_point(transform.map((200.0, 100.0), 36.0, 50.0), (0.0, 25.0));
+    }
+
+    #[test]
+    fn input_transform_calibrates_normalized_coordinates() {
+        let mut transform = InputTransform::new();
+        transform.calibration = [-1.0, 0.0, 1.0, 0.0, 1.0, 0.0];
+        let mut event = InputEvent::CursorPos { x: 30.0, y: 40.0 };
+        transform.apply((100.0, 50.0), &mut event);
+        let InputEvent::CursorPos { x, y } = event else {
+            panic!("unexpected event: {event:?}");
+        };
+        assert_point((x, y), (70.0, 40.0));
+        assert_eq!(transform.output_size, (100.0, 50.0));
+        assert_point(transform.map_point(30.0, 40.0), (70.0, 40.0));
+        assert!(Rotation::from_degrees(45).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rustler::{Encoder, LocalPid, OwnedEnv};
+
+use crate::flight_recorder::log_line;
+
+rustler::atoms! {
+    input_watchdog_expired
+}
+
+#[derive(Debug)]
+struct WatchState {
+    timeout: Option<Duration>,
+    target: Option<LocalPid>,
+    last_input: Instant,
+    expired: bool,
+    /// Bumped on every arm and disarm, so a timer thread left from an earlier arm exits.
+    generation: u64,
+}
+
+/// Dead-man timer on input.
+///
+/// While armed, the input queue touches the watchdog for every event it receives. When no
+/// event arrives within the timeout, a native timer thread sets the expired flag and sends
+/// `{:input_watchdog_expired, idle_ms}` to the armed process, without depending on the
+/// BEAM being scheduled in time. The next input event clears the flag and restarts the
+/// timeout, so every idle period is reported once.
+#[derive(Debug)]
+pub struct InputWatchdog {
+    state: Mutex<WatchState>,
+    wake: Condvar,
+}
+
+impl InputWatchdog {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(WatchState {
+                timeout: None,
+                target: None,
+                last_input: Instant::now(),
+                expired: false,
+                generation: 0,
+            }),
+            wake: Condvar::new(),
+        }
+    }
+
+    /// Starts (or restarts) the timeout from now; `target` receives the expiry message.
+    pub fn arm(
+        self: &Arc<Self>,
+        timeout: Duration,
+        target: Option<LocalPid>,
+    ) -> Result<(), String> {
+        let generation = {
+            let mut state = self
+                .state
+                .lock()
+                .map_err(|_| "input watchdog lock poisoned".to_string())?;
+            state.timeout = Some(timeout);
+            state.target = target;
+            state.last_input = Instant::now();
+            state.expired = false;
+            state.generation += 1;
+            state.generation
+        };
+        self.wake.notify_all();
+        let watchdog = Arc::clone(self);
+        thread::Builder::new()
+            .name("scenic-driver-input-watchdog".to_string())
+            .spawn(move || watchdog.run(generation))
+            .map_err(|err| format!("failed to spawn input watchdog thread: {err}"))?;
+        Ok(())
+    }
+
+    /// Stops the timer and clears the expired flag. Returns whether it was armed.
+    pub fn disarm(&self) -> Result<bool, String> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| "input watchdog lock poisoned".to_string())?;
+        let armed = state.timeout.take().is_some();
+        state.target = None;
+        state.expired = false;
+        state.generation += 1;
+        drop(state);
+        self.wake.notify_all();
+        Ok(armed)
+    }
+
+    /// Records an input event.
+    pub fn touch(&self) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if state.timeout.is_none() {
+            return;
+        }
+        state.last_input = Instant::now();
+        if state.expired {
+            state.expired = false;
+            drop(state);
+            self.wake.notify_all();
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.state
+            .lock()
+            .map(|state| state.expired)
+            .unwrap_or(false)
+    }
+
+    fn run(&self, generation: u64) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        loop {
+            if state.generation != generation {
+                return;
+            }
+            let Some(timeout) = state.timeout else {
+                return;
+            };
+            if state.expired {
+                state = match self.wake.wait(state) {
+                    Ok(state) => state,
+                    Err(_) => return,
+                };
+                continue;
+            }
+            let idle = state.last_input.elapsed();
+            if idle < timeout {
+                state = match self.wake.wait_timeout(state, timeout - idle) {
+                    Ok((state, _)) => state,
+                    Err(_) => return,
+                };
+                continue;
+            }
+
+            state.expired = true;
+            let idle_ms = idle.as_millis() as u64;
+            log_line!("Input watchdog expired after {idle_ms}ms without input");
+            if let Some(pid) = state.target {
+                let mut env = OwnedEnv::new();
+                let _ =
+                    env.send_and_clear(&pid, |env| (input_watchdog_expired(), idle_ms).encode(env));
+            }
+        }
+    }
+}
+
+impl Default for InputWatchdog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_watchdog_expires_and_rearms_on_input() {
+        let watchdog = Arc::new(InputWatchdog::new());
+        watchdog.touch();
+        assert!(!watchdog.is_expired());
+
+        watchdog.arm(Duration::from_millis(20), None).expect("arm");
+        thread::sleep(Duration::from_millis(150));
+        assert!(watchdog.is_expired());
+
+        watchdog.touch();
+        assert!(!watchdog.is_expired());
+
+        assert_eq!(watchdog.disarm(), Ok(true));
+        thread::sleep(Duration::from_millis(60));
+        assert!(!watchdog.is_expired());
+        assert_eq!(watchdog.disarm(), Ok(false));
+    }
+}
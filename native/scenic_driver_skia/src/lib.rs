@@ -1,11 +1,51 @@
 mod backend;
+mod backend_probe;
+#[cfg(feature = "c-api")]
+mod c_api;
+mod checkpoint;
+mod clock;
+mod color_utils;
 mod cursor;
+mod dev_overlay;
+mod digest;
 mod drm_backend;
 mod drm_input;
+mod flight_recorder;
+mod focus;
+mod font_fallback;
+mod frame_capture;
+mod frame_timing;
+mod geometry_guard;
+mod gpu_info;
+mod hover;
+mod hw_decode;
 mod input;
+mod input_record;
+mod input_transform;
 mod input_translate;
+mod input_watchdog;
+mod op_stats;
+mod outputs;
+mod path_measure;
+mod pattern_cache;
+mod plane_props;
+mod quality;
 mod raster_backend;
+mod redraw;
+mod references;
 mod renderer;
+mod script_cache;
+mod script_limits;
+mod state_lock;
+mod stats_export;
+mod stream_frames;
+mod text_cache;
+mod text_fallback;
+mod text_layout;
+mod virtual_pointer;
+mod vulkan_backend;
+mod watchdog;
+mod watermark;
 
 use std::collections::HashMap;
 use std::sync::{
@@ -16,28 +56,60 @@ use std::sync::{
 use std::thread;
 use std::time::Duration;
 
-use backend::UserEvent;
-use cursor::CursorState;
-use input::{InputEvent, InputQueue};
-use renderer::{RenderState, ScriptOp};
+use backend::{GraphicsApi, UserEvent};
+use color_utils::PaletteEntry;
+use cursor::{AccelProfile, CursorImage, CursorShape, CursorState, PointerAccel};
+use dev_overlay::DevOverlay;
+use font_fallback::FontFallback;
+use frame_timing::RenderStats;
+use geometry_guard::GeometryMode;
+use gpu_info::GpuInfo;
+use input::{DeviceClass, DeviceClassMasks, InputEvent, InputQueue, ViewportInfo};
+use input_transform::{InputTransform, Rotation};
+use input_watchdog::InputWatchdog;
+use outputs::OutputInfo;
+use path_measure::PathMeasure;
+use quality::QualityConfig;
+use redraw::RedrawReasons;
+use references::Reference;
+use renderer::{
+    AssetStore, GradientShape, RenderState, ScheduledScript, ScheduledTexture, ScriptOp,
+    TextureOptions, UploadKind,
+};
 use rustler::{Binary, Env, OwnedBinary, ResourceArc, Term};
-use skia_safe::ClipOp;
+use script_cache::{ScriptCache, ScriptCacheStats};
+use script_limits::ScriptLimits;
+use skia_safe::{BlendMode, ClipOp, Matrix, Rect};
+use state_lock::{PoisonPolicy, RenderStateLock};
+use stats_export::StatsFormat;
+use stream_frames::DropPolicy;
+use text_fallback::TextFallbackMode;
+use text_layout::TextWrap;
+use virtual_pointer::{Easing, PointerPath, VirtualCursor, VirtualPointer, Waypoint};
+use watermark::{Corner, Watermark, WatermarkTarget};
 
 enum StopSignal {
     Wayland(winit::event_loop::EventLoopProxy<UserEvent>),
+    /// Wayland window presenting through Vulkan instead of EGL.
+    Vulkan(winit::event_loop::EventLoopProxy<UserEvent>),
     Drm(Arc<AtomicBool>),
     Raster(Arc<AtomicBool>),
 }
 
 struct DriverHandle {
     stop: StopSignal,
-    render_state: Arc<Mutex<RenderState>>,
+    render_state: Arc<RenderStateLock>,
     input_events: Arc<Mutex<InputQueue>>,
     input_mask: Arc<AtomicU32>,
+    device_masks: Arc<DeviceClassMasks>,
     raster_frame: Option<Arc<Mutex<Option<RasterFrame>>>>,
     dirty: Option<Arc<AtomicBool>>,
     running: Arc<AtomicBool>,
     cursor_state: Option<Arc<Mutex<CursorState>>>,
+    pointer_accel: Option<Arc<Mutex<PointerAccel>>>,
+    input_transform: Arc<Mutex<InputTransform>>,
+    /// Why `backend: "auto"` picked this backend; `None` when it was configured.
+    backend_reason: Option<String>,
     thread: Option<thread::JoinHandle<()>>,
 }
 
@@ -47,6 +119,42 @@ struct RendererResource {
 
 impl rustler::Resource for RendererResource {}
 
+struct AssetStoreResource {
+    store: Arc<AssetStore>,
+}
+
+impl rustler::Resource for AssetStoreResource {}
+
+/// Read-only attachment to a running renderer, for inspecting a production display from
+/// another process. The inspector NIFs only read state, capture frames and subscribe to
+/// input; since resource types are checked when NIF arguments are decoded, an inspector
+/// cannot be passed to the NIFs that change the renderer.
+struct InspectorResource {
+    renderer: ResourceArc<RendererResource>,
+}
+
+impl rustler::Resource for InspectorResource {}
+
+/// Image metadata returned to Elixir as `%{width: .., height: .., format: .., has_alpha: ..}`.
+#[derive(rustler::NifMap)]
+struct ImageInfo {
+    width: u32,
+    height: u32,
+    format: String,
+    has_alpha: bool,
+}
+
+impl From<renderer::ImageMeta> for ImageInfo {
+    fn from(meta: renderer::ImageMeta) -> Self {
+        Self {
+            width: meta.width,
+            height: meta.height,
+            format: meta.format,
+            has_alpha: meta.has_alpha,
+        }
+    }
+}
+
 pub(crate) struct RasterFrame {
     width: u32,
     height: u32,
@@ -55,24 +163,97 @@ pub(crate) struct RasterFrame {
 
 const ROOT_ID: &str = "_root_";
 
+/// The `drm_card` argument of `start`: a card path, or `{card, render_node}` to draw on a
+/// render node of another GPU and scan out on the card (`"auto"` picks one for
+/// display-only cards). A `nil` card picks one as for `nil`.
+#[derive(rustler::NifUntaggedEnum)]
+pub enum DrmNodes {
+    Card(String),
+    WithRenderNode((Option<String>, String)),
+}
+
 #[rustler::nif(schedule = "DirtyIo")]
 pub fn start(
     backend: Option<String>,
     viewport_size: Option<(u32, u32)>,
     window_title: String,
     window_resizeable: bool,
-    drm_card: Option<String>,
+    drm_card: Option<DrmNodes>,
     drm_hw_cursor: bool,
     drm_input_log: bool,
 ) -> Result<ResourceArc<RendererResource>, String> {
+    let (drm_card, drm_render_node) = match drm_card {
+        Some(DrmNodes::Card(card)) => (Some(card), None),
+        Some(DrmNodes::WithRenderNode((card, render_node))) => (card, Some(render_node)),
+        None => (None, None),
+    };
+    let handle = start_driver(
+        backend,
+        viewport_size,
+        window_title,
+        window_resizeable,
+        drm_card,
+        drm_render_node,
+        drm_hw_cursor,
+        drm_input_log,
+    )?;
+    Ok(ResourceArc::new(RendererResource {
+        handle: Mutex::new(handle),
+    }))
+}
+
+/// Path of the DRM card bound to the kernel `driver`, for the `drm: [driver: ...]` option.
+/// Cards with a display attached win over the rest.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn find_drm_card(driver: String) -> Result<String, String> {
+    backend_probe::card_for_driver(&driver)
+}
+
+/// Cards and render nodes in `/dev/dri` with their drivers and connectors.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn list_dri_devices() -> Vec<backend_probe::DriDevice> {
+    backend_probe::dri_devices()
+}
+
+/// Spawns the backend thread and returns its handle. Shared by the NIF and the C API.
+fn start_driver(
+    backend: Option<String>,
+    viewport_size: Option<(u32, u32)>,
+    window_title: String,
+    window_resizeable: bool,
+    drm_card: Option<String>,
+    drm_render_node: Option<String>,
+    drm_hw_cursor: bool,
+    drm_input_log: bool,
+) -> Result<DriverHandle, String> {
     let backend = backend
         .map(|b| b.to_lowercase())
         .unwrap_or_else(|| String::from("wayland"));
+    let (backend, backend_reason, drm_card) = if backend == "auto" {
+        let choice = backend_probe::probe(drm_card.as_deref());
+        flight_recorder::log_line!(
+            "Backend auto-selected {}: {}",
+            choice.backend,
+            choice.reason
+        );
+        (
+            choice.backend,
+            Some(choice.reason),
+            drm_card.or(choice.card),
+        )
+    } else {
+        (backend, None, drm_card)
+    };
 
     let thread_name = format!("scenic-driver-{backend}");
-    let render_state = Arc::new(Mutex::new(RenderState::default()));
-    let input_events = Arc::new(Mutex::new(InputQueue::new()));
+    let render_state = RenderState::default();
+    let mut input_queue = InputQueue::new();
+    input_queue.set_flight_recorder(Arc::clone(&render_state.flight_recorder));
+    let render_state = Arc::new(RenderStateLock::new(render_state));
+    let input_events = Arc::new(Mutex::new(input_queue));
     let input_mask = Arc::new(AtomicU32::new(0));
+    let device_masks = Arc::new(DeviceClassMasks::new());
+    let input_transform = Arc::new(Mutex::new(InputTransform::new()));
     let running = Arc::new(AtomicBool::new(true));
     let handle = if backend == "drm" {
         let stop = Arc::new(AtomicBool::new(false));
@@ -85,7 +266,31 @@ pub fn start(
         let requested_size = viewport_size;
         let cursor_state = Arc::new(Mutex::new(CursorState::new()));
         let cursor_for_thread = Arc::clone(&cursor_state);
-        let drm_card = drm_card.clone();
+        let pointer_accel = Arc::new(Mutex::new(PointerAccel::new()));
+        let pointer_accel_for_thread = Arc::clone(&pointer_accel);
+        let device_masks_for_thread = Arc::clone(&device_masks);
+        let input_transform_for_thread = Arc::clone(&input_transform);
+        let (cursor_regions, pointer, cursor_image) = render_state
+            .lock()
+            .map(|state| {
+                (
+                    Arc::clone(&state.cursor_regions),
+                    Arc::clone(&state.pointer),
+                    Arc::clone(&state.cursor_image),
+                )
+            })
+            .unwrap_or_default();
+        let drm_card = drm_card
+            .clone()
+            .or_else(backend_probe::default_card)
+            .unwrap_or_else(|| backend_probe::DEFAULT_CARD.to_string());
+        let render_node = match drm_render_node.as_deref() {
+            Some("auto") => backend_probe::render_node_for(&drm_card),
+            other => other.map(str::to_string),
+        };
+        if let Some(render_node) = &render_node {
+            flight_recorder::log_line!("DRM scanout on {drm_card}, rendering on {render_node}");
+        }
         let thread = thread::Builder::new()
             .name(thread_name)
             .spawn(move || {
@@ -94,11 +299,18 @@ pub fn start(
                     dirty_for_thread,
                     state_for_thread,
                     input_for_thread,
-                    input_events_for_thread,
                     drm_backend::DrmRunConfig {
                         requested_size,
+                        input_events: input_events_for_thread,
                         cursor_state: cursor_for_thread,
+                        pointer_accel: pointer_accel_for_thread,
+                        device_masks: device_masks_for_thread,
+                        input_transform: input_transform_for_thread,
+                        cursor_regions,
+                        pointer,
+                        cursor_image,
                         card_path: drm_card,
+                        render_node,
                         hw_cursor: drm_hw_cursor,
                         input_log: drm_input_log,
                     },
@@ -110,13 +322,17 @@ pub fn start(
             render_state,
             input_events,
             input_mask,
+            device_masks,
             raster_frame: None,
             dirty: Some(dirty),
             running,
             cursor_state: Some(cursor_state),
+            pointer_accel: Some(pointer_accel),
+            input_transform,
+            backend_reason,
             thread: Some(thread),
         }
-    } else if backend == "raster" {
+    } else if backend == "raster" || backend == "raster_gpu" {
         let stop = Arc::new(AtomicBool::new(false));
         let dirty = Arc::new(AtomicBool::new(false));
         let state_for_thread = Arc::clone(&render_state);
@@ -125,7 +341,11 @@ pub fn start(
         let raster_frame = Arc::new(Mutex::new(None));
         let frame_for_thread = Arc::clone(&raster_frame);
         let input_for_thread = Arc::clone(&input_mask);
-        let requested_size = viewport_size;
+        let input_events_for_thread = Arc::clone(&input_events);
+        let config = raster_backend::RasterConfig {
+            size: viewport_size,
+            gpu: backend == "raster_gpu",
+        };
         let thread = thread::Builder::new()
             .name(thread_name)
             .spawn(move || {
@@ -135,7 +355,8 @@ pub fn start(
                     state_for_thread,
                     frame_for_thread,
                     input_for_thread,
-                    requested_size,
+                    input_events_for_thread,
+                    config,
                 )
             })
             .map_err(|err| format!("failed to spawn renderer thread: {err}"))?;
@@ -144,19 +365,32 @@ pub fn start(
             render_state,
             input_events,
             input_mask,
+            device_masks,
             raster_frame: Some(raster_frame),
             dirty: Some(dirty),
             running,
             cursor_state: None,
+            pointer_accel: None,
+            input_transform,
+            backend_reason,
             thread: Some(thread),
         }
     } else {
+        let graphics = if backend == "vulkan" {
+            GraphicsApi::Vulkan
+        } else {
+            GraphicsApi::Gl
+        };
         let (proxy_tx, proxy_rx) = mpsc::channel();
         let running_for_thread = Arc::clone(&running);
         let state_for_thread = Arc::clone(&render_state);
         let input_for_thread = Arc::clone(&input_mask);
         let input_events_for_thread = Arc::clone(&input_events);
+        let device_masks_for_thread = Arc::clone(&device_masks);
+        let input_transform_for_thread = Arc::clone(&input_transform);
         let requested_size = viewport_size;
+        let cursor_state = Arc::new(Mutex::new(CursorState::new()));
+        let cursor_for_thread = Arc::clone(&cursor_state);
         let thread = thread::Builder::new()
             .name(thread_name)
             .spawn(move || {
@@ -166,10 +400,14 @@ pub fn start(
                     state_for_thread,
                     input_for_thread,
                     input_events_for_thread,
+                    device_masks_for_thread,
+                    input_transform_for_thread,
                     backend::WaylandWindowConfig {
                         requested_size,
                         window_title,
                         window_resizeable,
+                        cursor_state: cursor_for_thread,
+                        graphics,
                     },
                 )
             })
@@ -177,22 +415,28 @@ pub fn start(
         let proxy = proxy_rx
             .recv_timeout(Duration::from_secs(5))
             .map_err(|_| "renderer did not initialize in time".to_string())?;
+        let stop = match graphics {
+            GraphicsApi::Gl => StopSignal::Wayland(proxy),
+            GraphicsApi::Vulkan => StopSignal::Vulkan(proxy),
+        };
         DriverHandle {
-            stop: StopSignal::Wayland(proxy),
+            stop,
             render_state,
             input_events,
             input_mask,
+            device_masks,
             raster_frame: None,
             dirty: None,
             running,
-            cursor_state: None,
+            cursor_state: Some(cursor_state),
+            pointer_accel: None,
+            input_transform,
+            backend_reason,
             thread: Some(thread),
         }
     };
 
-    Ok(ResourceArc::new(RendererResource {
-        handle: Mutex::new(handle),
-    }))
+    Ok(handle)
 }
 
 fn with_handle<T>(
@@ -207,8 +451,23 @@ fn with_handle<T>(
 }
 
 fn signal_redraw(handle: &mut DriverHandle) -> Result<(), String> {
+    signal_redraw_with_damage(handle, None)
+}
+
+/// Wakes the renderer. `damage` bounds the change in root coordinates; `None` damages the
+/// whole output.
+fn signal_redraw_with_damage(
+    handle: &mut DriverHandle,
+    damage: Option<Rect>,
+) -> Result<(), String> {
+    if let Ok(render_state) = handle.render_state.lock() {
+        match damage {
+            Some(rect) => render_state.damage.add_rect(rect),
+            None => render_state.damage.add_full(),
+        }
+    }
     match &handle.stop {
-        StopSignal::Wayland(proxy) => proxy
+        StopSignal::Wayland(proxy) | StopSignal::Vulkan(proxy) => proxy
             .send_event(UserEvent::Redraw)
             .map_err(|err| format!("failed to signal renderer: {err}")),
         StopSignal::Drm(_) | StopSignal::Raster(_) => {
@@ -220,280 +479,2895 @@ fn signal_redraw(handle: &mut DriverHandle) -> Result<(), String> {
     }
 }
 
+/// Wakes the renderer after texture `id` changed, recording it as the redraw reason.
+fn signal_texture_redraw(renderer: &RendererResource, id: &str) -> Result<(), String> {
+    with_handle(renderer, |handle| {
+        if let Ok(render_state) = handle.render_state.lock() {
+            render_state.redraws.add_texture(id);
+        }
+        signal_redraw(handle)
+    })
+}
+
 fn update_render_state<F>(renderer: &RendererResource, update: F) -> Result<(), String>
 where
     F: FnOnce(&mut RenderState) -> Result<(), String>,
+{
+    update_render_state_with_damage(renderer, |state| {
+        update(state)?;
+        Ok(None)
+    })
+}
+
+/// Like `update_render_state`, with `update` returning the area its change damaged in root
+/// coordinates; `None` damages the whole output.
+fn update_render_state_with_damage<F>(renderer: &RendererResource, update: F) -> Result<(), String>
+where
+    F: FnOnce(&mut RenderState) -> Result<Option<Rect>, String>,
 {
     with_handle(renderer, |handle| {
         let mut render_state = handle
             .render_state
             .lock()
             .map_err(|_| "render state lock poisoned".to_string())?;
-        update(&mut render_state)?;
+        let damage = update(&mut render_state)?;
         drop(render_state);
-        signal_redraw(handle)
+        signal_redraw_with_damage(handle, damage)
     })
 }
 
+/// Applies `change` to script `id` and returns the area the script covered before and
+/// after it, measured where the script was drawn in the last frame; `None` when that is
+/// not known.
+fn change_script(
+    state: &mut RenderState,
+    id: &str,
+    change: impl FnOnce(&mut RenderState),
+) -> Option<Rect> {
+    let before = renderer::script_extent(state, id);
+    change(state);
+    let after = renderer::script_extent(state, id);
+    Some(Rect::join2(before?, after?))
+}
+
 #[rustler::nif(schedule = "DirtyIo")]
 pub fn stop(renderer: ResourceArc<RendererResource>) -> Result<(), String> {
-    with_handle(&renderer, |handle| {
-        if !handle.running.load(Ordering::Relaxed) {
-            return Ok(());
-        }
+    with_handle(&renderer, stop_driver)
+}
 
-        let signal_result = match &handle.stop {
-            StopSignal::Wayland(proxy) => proxy
-                .send_event(UserEvent::Stop)
-                .map_err(|err| format!("failed to signal renderer: {err}")),
-            StopSignal::Drm(stop) => {
-                stop.store(true, Ordering::Relaxed);
-                Ok(())
-            }
-            StopSignal::Raster(stop) => {
-                stop.store(true, Ordering::Relaxed);
-                Ok(())
-            }
-        };
-        handle.running.store(false, Ordering::Relaxed);
+fn stop_driver(handle: &mut DriverHandle) -> Result<(), String> {
+    if !handle.running.load(Ordering::Relaxed) {
+        return Ok(());
+    }
 
-        let join_result = match handle.thread.take() {
-            Some(thread) => thread
-                .join()
-                .map_err(|_| "renderer thread panicked".to_string()),
-            None => Ok(()),
-        };
+    let signal_result = match &handle.stop {
+        StopSignal::Wayland(proxy) | StopSignal::Vulkan(proxy) => proxy
+            .send_event(UserEvent::Stop)
+            .map_err(|err| format!("failed to signal renderer: {err}")),
+        StopSignal::Drm(stop) => {
+            stop.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+        StopSignal::Raster(stop) => {
+            stop.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+    };
+    handle.running.store(false, Ordering::Relaxed);
 
-        signal_result.and(join_result)
-    })
+    let join_result = match handle.thread.take() {
+        Some(thread) => thread
+            .join()
+            .map_err(|_| "renderer thread panicked".to_string()),
+        None => Ok(()),
+    };
+
+    signal_result.and(join_result)
 }
 
 #[rustler::nif(schedule = "DirtyIo")]
 pub fn reset_scene(renderer: ResourceArc<RendererResource>) -> Result<(), String> {
     update_render_state(&renderer, |state| {
-        state.scripts = HashMap::new();
-        state.root_id = None;
+        renderer::clear_scene(state);
         Ok(())
     })
 }
 
+/// Shows the development overlay as `{spacing, color, rulers, pointer}` over the scene, or
+/// hides it with `None`.
 #[rustler::nif(schedule = "DirtyIo")]
-pub fn set_clear_color(
+pub fn set_dev_overlay(
     renderer: ResourceArc<RendererResource>,
-    color: (u8, u8, u8, u8),
+    overlay: Option<(f32, (u8, u8, u8, u8), bool, bool)>,
 ) -> Result<(), String> {
+    let overlay = match overlay {
+        Some((spacing, _, _, _)) if !(spacing.is_finite() && spacing > 0.0) => {
+            return Err("dev overlay spacing must be positive".to_string());
+        }
+        Some((spacing, (r, g, b, a), rulers, pointer)) => Some(DevOverlay {
+            spacing,
+            color: skia_safe::Color::from_argb(a, r, g, b),
+            rulers,
+            pointer,
+        }),
+        None => None,
+    };
     update_render_state(&renderer, |state| {
-        state.clear_color = skia_safe::Color::from_argb(color.3, color.0, color.1, color.2);
+        state
+            .pointer
+            .set_tracking(overlay.is_some_and(|overlay| overlay.pointer));
+        state.dev_overlay = overlay;
         Ok(())
     })
 }
 
+/// Watermark settings passed to `set_watermark` as a map.
+#[derive(rustler::NifMap)]
+pub struct WatermarkConfig {
+    text: String,
+    font: Option<String>,
+    size: f32,
+    position: String,
+    margin: f32,
+    color: (u8, u8, u8, u8),
+    background: Option<(u8, u8, u8, u8)>,
+    target: String,
+}
+
+/// Stamps text into a corner of every frame (`target: "frames"`) or only into frames read
+/// back with `capture_frame` (`target: "captures"`), or removes the stamp with `None`.
+/// `font` is an uploaded font id or an installed family name.
 #[rustler::nif(schedule = "DirtyIo")]
-pub fn submit_script(
+pub fn set_watermark(
     renderer: ResourceArc<RendererResource>,
-    script: rustler::Binary,
+    config: Option<WatermarkConfig>,
 ) -> Result<(), String> {
+    let Some(config) = config else {
+        return update_render_state(&renderer, |state| {
+            state.watermark = None;
+            Ok(())
+        });
+    };
+    if !config.size.is_finite() || config.size <= 0.0 {
+        return Err(format!("invalid watermark size: {}", config.size));
+    }
+    if !config.margin.is_finite() || config.margin < 0.0 {
+        return Err(format!("invalid watermark margin: {}", config.margin));
+    }
+    let corner = Corner::parse(&config.position)?;
+    let target = match config.target.as_str() {
+        "frames" => WatermarkTarget::Frames,
+        "captures" => WatermarkTarget::Captures,
+        other => return Err(format!("unsupported watermark target: {other}")),
+    };
+    let color = |(r, g, b, a): (u8, u8, u8, u8)| skia_safe::Color::from_argb(a, r, g, b);
     update_render_state(&renderer, |state| {
-        let ops = parse_script(script.as_slice())?;
-        set_script(state, ROOT_ID.to_string(), ops);
+        let typeface = config
+            .font
+            .as_deref()
+            .map(|font| renderer::resolve_typeface(&state.assets, font))
+            .transpose()?;
+        state.watermark = Some(Watermark {
+            text: config.text,
+            typeface,
+            size: config.size,
+            corner,
+            margin: config.margin,
+            color: color(config.color),
+            background: config.background.map(color),
+            target,
+        });
         Ok(())
     })
 }
 
+/// Presents a solid `color` frame next, skipping scripts entirely. With `hold`, every
+/// frame stays blank until `release_display`; the scene keeps accepting updates meanwhile.
 #[rustler::nif(schedule = "DirtyIo")]
-pub fn submit_script_with_id(
+pub fn clear_display(
     renderer: ResourceArc<RendererResource>,
-    id: String,
-    script: rustler::Binary,
+    color: (u8, u8, u8, u8),
+    hold: bool,
 ) -> Result<(), String> {
     update_render_state(&renderer, |state| {
-        let ops = parse_script(script.as_slice())?;
-        set_script(state, id.clone(), ops);
+        let color = skia_safe::Color::from_argb(color.3, color.0, color.1, color.2);
+        state.blank.set(color, hold);
         Ok(())
     })
 }
 
+/// Ends a held `clear_display` and redraws the scene; returns whether a blank was set.
 #[rustler::nif(schedule = "DirtyIo")]
-pub fn submit_scripts(
-    renderer: ResourceArc<RendererResource>,
-    scripts: Vec<(String, rustler::Binary)>,
-) -> Result<(), String> {
+pub fn release_display(renderer: ResourceArc<RendererResource>) -> Result<bool, String> {
+    let mut released = false;
     update_render_state(&renderer, |state| {
-        let mut staged: Vec<(String, Vec<ScriptOp>)> = Vec::with_capacity(scripts.len());
-        for (id, script) in scripts.iter() {
-            let ops = parse_script(script.as_slice())?;
-            staged.push((id.clone(), ops));
-        }
-        for (id, ops) in staged {
-            set_script(state, id, ops);
-        }
+        released = state.blank.release();
         Ok(())
-    })
+    })?;
+    Ok(released)
 }
 
 #[rustler::nif(schedule = "DirtyIo")]
-pub fn put_static_image(
+pub fn set_clear_color(
     renderer: ResourceArc<RendererResource>,
-    id: String,
-    data: rustler::Binary,
+    color: (u8, u8, u8, u8),
 ) -> Result<(), String> {
-    let image = renderer::decode_texture_image("file", 0, 0, data.as_slice())?;
-    renderer::insert_static_image(&id, image);
-    with_handle(&renderer, signal_redraw)
+    update_render_state(&renderer, |state| {
+        state.clear_color = skia_safe::Color::from_argb(color.3, color.0, color.1, color.2);
+        Ok(())
+    })
 }
 
+/// Sets the font used by text drawn without a `font` op. `font` is an uploaded font id or
+/// an installed family name; `None` restores the built-in sans font.
 #[rustler::nif(schedule = "DirtyIo")]
-pub fn put_font(
+pub fn set_default_font(
     renderer: ResourceArc<RendererResource>,
-    id: String,
-    data: rustler::Binary,
+    font: Option<String>,
+    size: f64,
 ) -> Result<(), String> {
-    renderer::insert_font(&id, data.as_slice())?;
-    with_handle(&renderer, signal_redraw)
+    if !size.is_finite() || size <= 0.0 {
+        return Err(format!("invalid default font size: {size}"));
+    }
+    update_render_state(&renderer, |state| {
+        state.default_typeface = font
+            .map(|font| renderer::resolve_typeface(&state.assets, &font))
+            .transpose()?;
+        state.default_font_size = size as f32;
+        Ok(())
+    })
 }
 
+/// Sets the fonts tried, in order, for characters the drawing font has no glyph for. Each
+/// entry is an uploaded font id or an installed family name; `system` asks the font
+/// manager for any installed font covering the character after them.
 #[rustler::nif(schedule = "DirtyIo")]
-pub fn put_stream_texture(
+pub fn set_fallback_fonts(
     renderer: ResourceArc<RendererResource>,
-    id: String,
-    format: String,
-    width: u32,
-    height: u32,
-    data: rustler::Binary,
+    fonts: Vec<String>,
+    system: bool,
 ) -> Result<(), String> {
-    let image = renderer::decode_texture_image(&format, width, height, data.as_slice())?;
-    renderer::insert_stream_image(&id, image);
-    with_handle(&renderer, signal_redraw)
+    update_render_state(&renderer, |state| {
+        let chain = fonts
+            .iter()
+            .map(|font| renderer::resolve_typeface(&state.assets, font))
+            .collect::<Result<Vec<_>, _>>()?;
+        renderer::set_font_fallback(state, FontFallback::new(chain, system));
+        Ok(())
+    })
 }
 
 #[rustler::nif(schedule = "DirtyIo")]
-pub fn del_stream_texture(
+pub fn set_overscan(
     renderer: ResourceArc<RendererResource>,
-    id: String,
+    margins: (f64, f64, f64, f64),
+    unit: String,
 ) -> Result<(), String> {
-    renderer::remove_stream_image(&id);
-    with_handle(&renderer, signal_redraw)
+    let percent = match unit.as_str() {
+        "px" => false,
+        "percent" => true,
+        other => return Err(format!("unsupported overscan unit: {other}")),
+    };
+    let (left, top, right, bottom) = margins;
+    if [left, top, right, bottom]
+        .iter()
+        .any(|value| !value.is_finite() || *value < 0.0)
+    {
+        return Err("overscan margins must be non-negative".to_string());
+    }
+    let overscan = renderer::Overscan {
+        left: left as f32,
+        top: top as f32,
+        right: right as f32,
+        bottom: bottom as f32,
+        percent,
+    };
+    update_input_transform(&renderer, |transform| {
+        transform.overscan = overscan;
+        Ok(())
+    })?;
+    update_render_state(&renderer, |state| {
+        state.overscan = overscan;
+        Ok(())
+    })
 }
 
+/// Rotates the scene clockwise by `degrees` on DRM and raster outputs. Frames are
+/// pre-rotated: surfaces keep the panel's native orientation and the root transform turns
+/// the scene into it, so no plane rotation is needed. Input positions are rotated back,
+/// and a quarter turn queues a `ViewportReshape` with width and height swapped.
 #[rustler::nif(schedule = "DirtyIo")]
-pub fn del_script(renderer: ResourceArc<RendererResource>, id: String) -> Result<(), String> {
-    update_render_state(&renderer, |state| {
-        state.scripts.remove(&id);
-        if state.root_id.as_deref() == Some(id.as_str()) {
-            state.root_id = None;
+pub fn set_output_rotation(
+    renderer: ResourceArc<RendererResource>,
+    degrees: u32,
+) -> Result<(), String> {
+    let rotation = Rotation::from_degrees(degrees)?;
+    with_handle(&renderer, |handle| {
+        if matches!(handle.stop, StopSignal::Wayland(_) | StopSignal::Vulkan(_)) {
+            return Err("output rotation is left to the compositor on this backend".to_string());
         }
-        Ok(())
+        let previous = {
+            let mut render_state = handle
+                .render_state
+                .lock()
+                .map_err(|_| "render state lock poisoned".to_string())?;
+            std::mem::replace(&mut render_state.output_rotation, rotation)
+        };
+        handle
+            .input_transform
+            .lock()
+            .map_err(|_| "input transform lock poisoned".to_string())?
+            .rotation = rotation;
+        if previous.swaps_axes() != rotation.swaps_axes() {
+            let mut queue = handle
+                .input_events
+                .lock()
+                .map_err(|_| "input queue lock poisoned".to_string())?;
+            if let Some(viewport) = queue.viewport() {
+                let (width, height) = (viewport.height, viewport.width);
+                queue.set_viewport(ViewportInfo {
+                    width,
+                    height,
+                    ..viewport
+                });
+                for pid in queue.reshape_viewport()?.1 {
+                    input::notify_input_ready(pid);
+                }
+            }
+        }
+        signal_redraw(handle)
     })
 }
 
-#[rustler::nif(schedule = "DirtyIo")]
-pub fn script_count(renderer: ResourceArc<RendererResource>) -> Result<u64, String> {
+/// Sets a property of the primary plane of a DRM output, such as `zpos`, `alpha` or
+/// `COLOR_RANGE`. `value` is an integer or, for enum properties, one of the names the
+/// kernel lists. It is checked against the plane's property list, goes out with the next
+/// page flip and is applied again whenever the output is set up. Properties the backend
+/// sets itself, like `FB_ID` or `CRTC_X`, are rejected.
+#[rustler::nif]
+pub fn set_plane_property(
+    renderer: ResourceArc<RendererResource>,
+    output: String,
+    name: String,
+    value: String,
+) -> Result<(), String> {
     with_handle(&renderer, |handle| {
-        let render_state = handle
+        if !matches!(handle.stop, StopSignal::Drm(_)) {
+            return Err("plane properties are only available on the DRM backend".to_string());
+        }
+        handle
             .render_state
             .lock()
-            .map_err(|_| "render state lock poisoned".to_string())?;
-        Ok(render_state.scripts.len() as u64)
+            .map(|state| Arc::clone(&state.plane_properties))
+            .map_err(|_| "render state lock poisoned".to_string())?
+            .set(&output, &name, &value)?;
+        signal_redraw(handle)
+    })
+}
+
+fn update_input_transform(
+    renderer: &RendererResource,
+    update: impl FnOnce(&mut InputTransform) -> Result<(), String>,
+) -> Result<(), String> {
+    with_handle(renderer, |handle| {
+        let mut transform = handle
+            .input_transform
+            .lock()
+            .map_err(|_| "input transform lock poisoned".to_string())?;
+        update(&mut transform)
     })
 }
 
+/// Sets the input coordinate pipeline applied to pointer, touch and pen positions:
+/// calibration matrix, output rotation (degrees clockwise), letterbox offset and scale.
+/// Overscan margins are part of the same pipeline and stay as set by `set_overscan`.
 #[rustler::nif(schedule = "DirtyIo")]
-pub fn get_raster_frame<'a>(
-    env: Env<'a>,
+pub fn set_input_transform(
     renderer: ResourceArc<RendererResource>,
-) -> Result<(u32, u32, Binary<'a>), String> {
-    with_handle(&renderer, |handle| {
-        let frame_slot = handle
-            .raster_frame
-            .as_ref()
-            .ok_or_else(|| "raster backend not active".to_string())?;
-        let frame_guard = frame_slot
+    rotation: u32,
+    scale: (f64, f64),
+    offset: (f64, f64),
+    calibration: Vec<f64>,
+) -> Result<(), String> {
+    let rotation = Rotation::from_degrees(rotation)?;
+    if !(scale.0.is_finite() && scale.1.is_finite() && scale.0 > 0.0 && scale.1 > 0.0) {
+        return Err(format!("invalid input scale: {scale:?}"));
+    }
+    if !(offset.0.is_finite() && offset.1.is_finite()) {
+        return Err(format!("invalid input offset: {offset:?}"));
+    }
+    let calibration: [f32; 6] = calibration
+        .iter()
+        .map(|value| *value as f32)
+        .collect::<Vec<_>>()
+        .try_into()
+        .map_err(|_| "input calibration must have 6 values".to_string())?;
+    if calibration.iter().any(|value| !value.is_finite()) {
+        return Err("input calibration values must be finite".to_string());
+    }
+    update_input_transform(&renderer, |transform| {
+        transform.rotation = rotation;
+        transform.scale = (scale.0 as f32, scale.1 as f32);
+        transform.offset = (offset.0 as f32, offset.1 as f32);
+        transform.calibration = calibration;
+        Ok(())
+    })
+}
+
+/// Input pipeline returned to Elixir for debugging.
+#[derive(rustler::NifMap)]
+struct InputTransformInfo {
+    rotation: u32,
+    scale: (f64, f64),
+    offset: (f64, f64),
+    calibration: Vec<f64>,
+    overscan: (f64, f64, f64, f64),
+    overscan_unit: String,
+    output_size: (f64, f64),
+}
+
+#[rustler::nif]
+pub fn get_input_transform(
+    renderer: ResourceArc<RendererResource>,
+) -> Result<InputTransformInfo, String> {
+    let transform = input_transform_snapshot(&renderer)?;
+    let overscan = transform.overscan;
+    Ok(InputTransformInfo {
+        rotation: transform.rotation.degrees(),
+        scale: (transform.scale.0 as f64, transform.scale.1 as f64),
+        offset: (transform.offset.0 as f64, transform.offset.1 as f64),
+        calibration: transform.calibration.iter().map(|v| *v as f64).collect(),
+        overscan: (
+            overscan.left as f64,
+            overscan.top as f64,
+            overscan.right as f64,
+            overscan.bottom as f64,
+        ),
+        overscan_unit: if overscan.percent { "percent" } else { "px" }.to_string(),
+        output_size: (
+            transform.output_size.0 as f64,
+            transform.output_size.1 as f64,
+        ),
+    })
+}
+
+/// Maps a device point through the input pipeline, using the output size of the last
+/// input event.
+#[rustler::nif]
+pub fn map_input_point(
+    renderer: ResourceArc<RendererResource>,
+    x: f64,
+    y: f64,
+) -> Result<(f64, f64), String> {
+    let transform = input_transform_snapshot(&renderer)?;
+    let (x, y) = transform.map_point(x as f32, y as f32);
+    Ok((x as f64, y as f64))
+}
+
+fn input_transform_snapshot(renderer: &RendererResource) -> Result<InputTransform, String> {
+    with_handle(renderer, |handle| {
+        handle
+            .input_transform
             .lock()
-            .map_err(|_| "raster frame lock poisoned".to_string())?;
-        let frame = frame_guard
-            .as_ref()
-            .ok_or_else(|| "raster frame not available".to_string())?;
-        let mut binary = OwnedBinary::new(frame.data.len())
-            .ok_or_else(|| "failed to allocate raster frame binary".to_string())?;
-        binary.as_mut_slice().copy_from_slice(&frame.data);
-        Ok((frame.width, frame.height, Binary::from_owned(binary, env)))
+            .map(|transform| *transform)
+            .map_err(|_| "input transform lock poisoned".to_string())
     })
 }
 
 #[rustler::nif(schedule = "DirtyIo")]
-pub fn set_input_mask(renderer: ResourceArc<RendererResource>, mask: u32) -> Result<(), String> {
-    with_handle(&renderer, |handle| {
-        handle.input_mask.store(mask, Ordering::Relaxed);
+pub fn set_gradient_dither(
+    renderer: ResourceArc<RendererResource>,
+    mode: String,
+) -> Result<(), String> {
+    let mode = renderer::GradientDither::parse(&mode)?;
+    update_render_state(&renderer, |state| {
+        state.gradient_dither = mode;
+        Ok(())
+    })
+}
+
+/// Runs every `draw_script` child with an isolated copy of the caller's draw state.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_script_isolation(
+    renderer: ResourceArc<RendererResource>,
+    enabled: bool,
+) -> Result<(), String> {
+    update_render_state(&renderer, |state| {
+        state.isolate_scripts = enabled;
         Ok(())
     })
 }
 
+/// Switches the clock animations and scheduled updates follow: `"monotonic"` is the
+/// driver clock, `"manual"` stands still at `start_ms` (or the current time) until moved
+/// with `advance_time`.
+#[rustler::nif]
+pub fn set_clock(
+    renderer: ResourceArc<RendererResource>,
+    mode: String,
+    start_ms: Option<u64>,
+) -> Result<(), String> {
+    update_render_state(&renderer, |state| {
+        match mode.as_str() {
+            "monotonic" => state.clock.set_monotonic(),
+            "manual" => state.clock.set_manual(start_ms),
+            other => return Err(format!("unsupported clock: {other}")),
+        }
+        state.redraws.add_animation();
+        Ok(())
+    })
+}
+
+/// Moves the manual clock forward by `ms` and redraws; returns the new time.
+#[rustler::nif]
+pub fn advance_time(renderer: ResourceArc<RendererResource>, ms: u64) -> Result<u64, String> {
+    let mut now_ms = 0;
+    update_render_state(&renderer, |state| {
+        now_ms = state.clock.advance(ms)?;
+        state.redraws.add_animation();
+        Ok(())
+    })?;
+    Ok(now_ms)
+}
+
+/// Honors a reduced-motion accessibility preference in every animation the renderer runs
+/// itself; pointer paths already playing keep their motion.
+#[rustler::nif]
+pub fn set_reduced_motion(
+    renderer: ResourceArc<RendererResource>,
+    enabled: bool,
+) -> Result<(), String> {
+    update_render_state(&renderer, |state| {
+        state.reduced_motion = enabled;
+        Ok(())
+    })
+}
+
+/// Selects what happens when the GL driver is a software rasterizer such as llvmpipe:
+/// `"warn"` only reports it through the log, `get_status` and `get_gpu_info`; `"raster"`
+/// also renders frames with Skia's CPU rasterizer and uses GL just to show them.
+#[rustler::nif]
+pub fn set_software_gl(
+    renderer: ResourceArc<RendererResource>,
+    mode: String,
+) -> Result<(), String> {
+    let raster = match mode.as_str() {
+        "warn" => false,
+        "raster" => true,
+        other => return Err(format!("unsupported software GL mode: {other}")),
+    };
+    update_render_state(&renderer, |state| {
+        state.raster_on_software_gl = raster;
+        Ok(())
+    })
+}
+
+/// Blends in linear light on an intermediate surface that is encoded to sRGB on present,
+/// trading memory and a full-screen copy per frame for gamma-correct edges.
+#[rustler::nif]
+pub fn set_linear_blending(
+    renderer: ResourceArc<RendererResource>,
+    enabled: bool,
+) -> Result<(), String> {
+    update_render_state(&renderer, |state| {
+        state.linear_blending = enabled;
+        Ok(())
+    })
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn submit_script(
+    renderer: ResourceArc<RendererResource>,
+    script: rustler::Binary,
+) -> Result<Vec<Reference>, String> {
+    let mut unresolved = Vec::new();
+    update_render_state_with_damage(&renderer, |state| {
+        let ops = parse_counted(state, ROOT_ID, script.as_slice())?;
+        let extent = change_script(state, ROOT_ID, |state| {
+            set_script(state, ROOT_ID.to_string(), ops)
+        });
+        unresolved = state.references.check(state, [&ROOT_ID.to_string()])?;
+        Ok(extent)
+    })?;
+    Ok(unresolved)
+}
+
+/// Stores a script under `id`. `bounds` (`{x, y, w, h}` in root coordinates) tells the
+/// renderer that only that area changed, so raster backends can repaint just that part.
+/// Without it the area is measured from where the script was drawn in the last frame.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn submit_script_with_id(
+    renderer: ResourceArc<RendererResource>,
+    id: String,
+    script: rustler::Binary,
+    bounds: Option<(f64, f64, f64, f64)>,
+) -> Result<Vec<Reference>, String> {
+    let damage = bounds
+        .map(|(x, y, w, h)| {
+            if w < 0.0 || h < 0.0 || ![x, y, w, h].iter().all(|value| value.is_finite()) {
+                return Err(format!("invalid bounds hint: {bounds:?}"));
+            }
+            Ok(Rect::from_xywh(x as f32, y as f32, w as f32, h as f32))
+        })
+        .transpose()?;
+    store_script(&renderer, id, script.as_slice(), damage)
+}
+
+/// Parses a script from the file at `path` and stores it under `id`, without copying
+/// the bytes through the BEAM.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn submit_script_file(
+    renderer: ResourceArc<RendererResource>,
+    id: String,
+    path: String,
+) -> Result<Vec<Reference>, String> {
+    let script = read_file(&path)?;
+    store_script(&renderer, id, &script, None)
+}
+
+fn store_script(
+    renderer: &RendererResource,
+    id: String,
+    script: &[u8],
+    damage: Option<Rect>,
+) -> Result<Vec<Reference>, String> {
+    let mut unresolved = Vec::new();
+    update_render_state_with_damage(renderer, |state| {
+        let ops = parse_counted(state, &id, script)?;
+        let extent = change_script(state, &id, |state| set_script(state, id.clone(), ops));
+        unresolved = state.references.check(state, [&id])?;
+        Ok(damage.or(extent))
+    })?;
+    Ok(unresolved)
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn submit_scripts(
+    renderer: ResourceArc<RendererResource>,
+    scripts: Vec<(String, rustler::Binary)>,
+) -> Result<Vec<Reference>, String> {
+    let mut unresolved = Vec::new();
+    update_render_state_with_damage(&renderer, |state| {
+        let mut staged: Vec<(String, Arc<Vec<ScriptOp>>)> = Vec::with_capacity(scripts.len());
+        for (id, script) in scripts.iter() {
+            let ops = parse_counted(state, id, script.as_slice())?;
+            staged.push((id.clone(), ops));
+        }
+        let mut damage = Some(Rect::new_empty());
+        for (id, ops) in staged {
+            let extent = change_script(state, &id, |state| set_script(state, id.clone(), ops));
+            damage = damage
+                .zip(extent)
+                .map(|(damage, extent)| Rect::join2(damage, extent));
+        }
+        unresolved = state
+            .references
+            .check(state, scripts.iter().map(|(id, _)| id))?;
+        Ok(damage)
+    })?;
+    Ok(unresolved)
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn submit_script_at(
+    renderer: ResourceArc<RendererResource>,
+    id: String,
+    script: rustler::Binary,
+    monotonic_ms: u64,
+) -> Result<(), String> {
+    update_render_state(&renderer, |state| {
+        let ops = parse_counted(state, &id, script.as_slice())?;
+        schedule_script(state, id, ops, monotonic_ms);
+        Ok(())
+    })
+}
+
+/// Applies stream textures and scripts together.
+///
+/// Textures are decoded up front; then every texture and script is swapped in under one
+/// render state lock, so the next presented frame shows all of them or none. With
+/// `monotonic_ms`, the whole update is held back until that time on the driver clock.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn submit_frame(
+    renderer: ResourceArc<RendererResource>,
+    textures: Vec<(String, String, u32, u32, rustler::Binary)>,
+    scripts: Vec<(String, rustler::Binary)>,
+    monotonic_ms: Option<u64>,
+) -> Result<Vec<Reference>, String> {
+    let mut unresolved = Vec::new();
+    let assets = asset_store(&renderer)?;
+    let mut decoded = Vec::with_capacity(textures.len());
+    for (id, format, width, height, data) in textures {
+        let options = assets.texture_options(&id);
+        let (image, meta) =
+            renderer::decode_texture_image_with(&format, width, height, data.as_slice(), options)?;
+        decoded.push((id, image, meta));
+    }
+    update_render_state(&renderer, |state| {
+        let mut staged: Vec<(String, Arc<Vec<ScriptOp>>)> = Vec::with_capacity(scripts.len());
+        for (id, script) in scripts.iter() {
+            let ops = parse_counted(state, id, script.as_slice())?;
+            staged.push((id.clone(), ops));
+        }
+        match monotonic_ms {
+            Some(due_ms) if due_ms > state.clock.now_ms() => {
+                for (id, image, meta) in decoded {
+                    schedule_texture(
+                        state,
+                        ScheduledTexture {
+                            due_ms,
+                            id,
+                            image,
+                            meta,
+                        },
+                    );
+                }
+                for (id, ops) in staged {
+                    schedule_script(state, id, ops, due_ms);
+                }
+            }
+            _ => {
+                for (id, image, meta) in decoded {
+                    state.redraws.add_texture(&id);
+                    renderer::insert_stream_image(&state.assets, &id, image, meta);
+                }
+                for (id, ops) in staged {
+                    set_script(state, id, ops);
+                }
+                unresolved = state
+                    .references
+                    .check(state, scripts.iter().map(|(id, _)| id))?;
+            }
+        }
+        Ok(())
+    })?;
+    Ok(unresolved)
+}
+
+/// Registers the fonts, static images and scripts of a bundle together.
+///
+/// Fonts and images are decoded and scripts parsed before anything is stored, so a bad
+/// entry leaves the renderer untouched; everything is then stored under one render state
+/// lock and shows up in the same frame.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn load_bundle(
+    renderer: ResourceArc<RendererResource>,
+    fonts: Vec<(String, rustler::Binary)>,
+    images: Vec<(String, rustler::Binary)>,
+    scripts: Vec<(String, rustler::Binary)>,
+) -> Result<Vec<Reference>, String> {
+    let mut typefaces = Vec::with_capacity(fonts.len());
+    for (id, data) in fonts {
+        let typeface = renderer::decode_font(data.as_slice())
+            .map_err(|err| format!("bundle font {id:?}: {err}"))?;
+        typefaces.push((id, typeface));
+    }
+    let assets = asset_store(&renderer)?;
+    let mut decoded = Vec::with_capacity(images.len());
+    for (id, data) in images {
+        let options = assets.texture_options(&id);
+        let (image, meta) =
+            renderer::decode_texture_image_with("file", 0, 0, data.as_slice(), options)
+                .map_err(|err| format!("bundle image {id:?}: {err}"))?;
+        decoded.push((id, image, meta));
+    }
+    let mut unresolved = Vec::new();
+    update_render_state(&renderer, |state| {
+        let mut staged: Vec<(String, Arc<Vec<ScriptOp>>)> = Vec::with_capacity(scripts.len());
+        for (id, script) in scripts.iter() {
+            let ops = parse_counted(state, id, script.as_slice())
+                .map_err(|err| format!("bundle script {id:?}: {err}"))?;
+            staged.push((id.clone(), ops));
+        }
+        for (id, typeface) in typefaces {
+            renderer::store_font(&state.assets, &id, typeface)?;
+        }
+        for (id, image, meta) in decoded {
+            state.redraws.add_texture(&id);
+            renderer::insert_static_image(&state.assets, &id, image, meta);
+        }
+        for (id, ops) in staged {
+            set_script(state, id, ops);
+        }
+        unresolved = state
+            .references
+            .check(state, scripts.iter().map(|(id, _)| id))?;
+        Ok(())
+    })?;
+    Ok(unresolved)
+}
+
+/// Scripts, static images, stream textures and deletions applied by `submit_transaction`.
+#[derive(rustler::NifMap)]
+pub struct Transaction<'a> {
+    scripts: Vec<(String, rustler::Binary<'a>)>,
+    images: Vec<(String, rustler::Binary<'a>)>,
+    streams: Vec<(String, String, u32, u32, rustler::Binary<'a>)>,
+    deletes: Vec<Reference>,
+}
+
+/// Applies a scene update that spans scripts and textures as one step.
+///
+/// Images and stream frames are decoded and scripts parsed before anything changes, so a
+/// bad entry leaves the renderer untouched. Deletions are applied first, then everything
+/// is stored under one render state lock; the next frame shows the whole transaction.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn submit_transaction(
+    renderer: ResourceArc<RendererResource>,
+    transaction: Transaction,
+) -> Result<Vec<Reference>, String> {
+    let assets = asset_store(&renderer)?;
+    let mut images = Vec::with_capacity(transaction.images.len());
+    for (id, data) in transaction.images {
+        let options = assets.texture_options(&id);
+        let (image, meta) =
+            renderer::decode_texture_image_with("file", 0, 0, data.as_slice(), options)
+                .map_err(|err| format!("transaction image {id:?}: {err}"))?;
+        images.push((id, image, meta));
+    }
+    let mut streams = Vec::with_capacity(transaction.streams.len());
+    for (id, format, width, height, data) in transaction.streams {
+        let options = assets.texture_options(&id);
+        let (image, meta) =
+            renderer::decode_texture_image_with(&format, width, height, data.as_slice(), options)
+                .map_err(|err| format!("transaction stream {id:?}: {err}"))?;
+        streams.push((id, image, meta));
+    }
+    let scripts = transaction.scripts;
+    let mut unresolved = Vec::new();
+    update_render_state(&renderer, |state| {
+        let mut staged: Vec<(String, Arc<Vec<ScriptOp>>)> = Vec::with_capacity(scripts.len());
+        for (id, script) in scripts.iter() {
+            let ops = parse_counted(state, id, script.as_slice())
+                .map_err(|err| format!("transaction script {id:?}: {err}"))?;
+            staged.push((id.clone(), ops));
+        }
+        for reference in transaction.deletes {
+            match reference {
+                Reference::Script(id) => remove_script(state, &id),
+                Reference::Image(id) => {
+                    state.redraws.add_texture(&id);
+                    renderer::remove_static_image(&state.assets, &id);
+                }
+                Reference::Stream(id) => {
+                    state.redraws.add_texture(&id);
+                    renderer::remove_stream_image(&state.assets, &id);
+                }
+            }
+        }
+        for (id, image, meta) in images {
+            state.redraws.add_texture(&id);
+            renderer::insert_static_image(&state.assets, &id, image, meta);
+        }
+        for (id, image, meta) in streams {
+            state.redraws.add_texture(&id);
+            renderer::insert_stream_image(&state.assets, &id, image, meta);
+        }
+        for (id, ops) in staged {
+            set_script(state, id, ops);
+        }
+        unresolved = state
+            .references
+            .check(state, scripts.iter().map(|(id, _)| id))?;
+        Ok(())
+    })?;
+    Ok(unresolved)
+}
+
+/// Draws a script over the scene on the next frame without storing it.
+///
+/// A batch that has not been drawn yet is replaced by the newer one. Sending an empty
+/// script redraws without the overlay.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn draw_immediate(
+    renderer: ResourceArc<RendererResource>,
+    script: rustler::Binary,
+) -> Result<(), String> {
+    update_render_state(&renderer, |state| {
+        let ops = parse_counted(state, renderer::IMMEDIATE_ID, script.as_slice())?;
+        let mut immediate = state
+            .immediate
+            .lock()
+            .map_err(|_| "immediate ops lock poisoned".to_string())?;
+        *immediate = ops;
+        Ok(())
+    })
+}
+
+#[rustler::nif]
+pub fn monotonic_ms() -> u64 {
+    monotonic_now_ms()
+}
+
+#[rustler::nif(name = "submit_scripts", schedule = "DirtyIo")]
+pub fn submit_scripts_in_namespace(
+    renderer: ResourceArc<RendererResource>,
+    namespace: String,
+    scripts: Vec<(String, rustler::Binary)>,
+) -> Result<(), String> {
+    validate_namespace(&namespace)?;
+    let mut unresolved = Vec::new();
+    update_render_state(&renderer, |state| {
+        let mut staged: Vec<(String, Arc<Vec<ScriptOp>>)> = Vec::with_capacity(scripts.len());
+        for (id, script) in scripts.iter() {
+            let mut ops = parse_counted(state, id, script.as_slice())?;
+            // Scripts that draw no others keep sharing the cached ops.
+            if ops
+                .iter()
+                .any(|op| matches!(op, ScriptOp::DrawScript(_) | ScriptOp::FillPattern { .. }))
+            {
+                resolve_namespace_refs(Arc::make_mut(&mut ops), &namespace);
+            }
+            staged.push((namespaced_id(&namespace, id), ops));
+        }
+        let ids: Vec<String> = staged.iter().map(|(id, _)| id.clone()).collect();
+        for (id, ops) in staged {
+            set_script(state, id, ops);
+        }
+        unresolved = state.references.check(state, &ids)?;
+        Ok(())
+    })?;
+    Ok(unresolved)
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn del_namespace(
+    renderer: ResourceArc<RendererResource>,
+    namespace: String,
+) -> Result<u64, String> {
+    validate_namespace(&namespace)?;
+    let mut removed = 0;
+    update_render_state(&renderer, |state| {
+        removed = remove_namespace(state, &namespace);
+        Ok(())
+    })?;
+    Ok(removed as u64)
+}
+
+#[rustler::nif]
+pub fn new_asset_store() -> ResourceArc<AssetStoreResource> {
+    ResourceArc::new(AssetStoreResource {
+        store: Arc::new(AssetStore::new(true)),
+    })
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_asset_store(
+    renderer: ResourceArc<RendererResource>,
+    store: ResourceArc<AssetStoreResource>,
+) -> Result<(), String> {
+    update_render_state(&renderer, |state| {
+        state.assets = Arc::clone(&store.store);
+        Ok(())
+    })
+}
+
+fn asset_store(renderer: &RendererResource) -> Result<Arc<AssetStore>, String> {
+    with_handle(renderer, |handle| {
+        let render_state = handle
+            .render_state
+            .lock()
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        Ok(Arc::clone(&render_state.assets))
+    })
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn put_static_image(
+    renderer: ResourceArc<RendererResource>,
+    id: String,
+    data: rustler::Binary,
+) -> Result<ImageInfo, String> {
+    store_static_image(&renderer, &id, data.as_slice())
+}
+
+/// Decodes the encoded image at `path` into the static image `id`, reading the file
+/// in Rust instead of passing it through the BEAM.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn put_static_image_file(
+    renderer: ResourceArc<RendererResource>,
+    id: String,
+    path: String,
+) -> Result<ImageInfo, String> {
+    let data = read_file(&path)?;
+    store_static_image(&renderer, &id, &data)
+}
+
+/// Reads the whole file at `path`. It is copied rather than memory-mapped: a mapping of a
+/// file rewritten or truncated during the call faults with SIGBUS and takes down the VM.
+fn read_file(path: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|err| format!("failed to read {path}: {err}"))
+}
+
+fn store_static_image(
+    renderer: &RendererResource,
+    id: &str,
+    data: &[u8],
+) -> Result<ImageInfo, String> {
+    let assets = asset_store(renderer)?;
+    let options = assets.texture_options(id);
+    let digest = assets.is_shared().then(|| digest::sha256(data));
+    let cached = match &digest {
+        Some(digest) if assets.is_current_upload(UploadKind::Image, id, digest) => {
+            renderer::static_image_meta(&assets, id)
+        }
+        _ => None,
+    };
+    let meta = match cached {
+        Some(meta) => meta,
+        // Deduplicated blobs are shared across ids, so converted images are kept apart.
+        None if assets.is_dedup() && options == TextureOptions::default() => {
+            renderer::insert_static_image_dedup(&assets, id, data)?
+        }
+        None => {
+            let (image, meta) = renderer::decode_texture_image_with("file", 0, 0, data, options)?;
+            renderer::insert_static_image(&assets, id, image, meta.clone());
+            meta
+        }
+    };
+    if let Some(digest) = digest {
+        assets.record_upload(UploadKind::Image, id, digest);
+    }
+    signal_texture_redraw(renderer, id)?;
+    Ok(meta.into())
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn put_font(
+    renderer: ResourceArc<RendererResource>,
+    id: String,
+    data: rustler::Binary,
+) -> Result<(), String> {
+    let assets = asset_store(&renderer)?;
+    let data = data.as_slice();
+    let digest = assets.is_shared().then(|| digest::sha256(data));
+    let current = digest
+        .as_ref()
+        .is_some_and(|digest| assets.is_current_upload(UploadKind::Font, &id, digest));
+    if !current {
+        renderer::insert_font(&assets, &id, data).map_err(|err| format!("font {id:?}: {err}"))?;
+        if let Some(digest) = digest {
+            assets.record_upload(UploadKind::Font, &id, digest);
+        }
+    }
+    with_handle(&renderer, signal_redraw)
+}
+
+/// Removes font `id`; returns whether it was loaded. Text that scripts draw with it is
+/// skipped until the font is uploaded again.
+#[rustler::nif]
+pub fn del_font(renderer: ResourceArc<RendererResource>, id: String) -> Result<bool, String> {
+    let removed = renderer::remove_font(&asset_store(&renderer)?, &id)?;
+    if removed {
+        with_handle(&renderer, signal_redraw)?;
+    }
+    Ok(removed)
+}
+
+/// Returns `(byte_offset, codepoint)` for each character of `text` that neither `font` (an
+/// uploaded font id, or the default font when `None`) nor a fallback font has a glyph for.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn missing_glyphs(
+    renderer: ResourceArc<RendererResource>,
+    text: String,
+    font: Option<String>,
+) -> Result<Vec<(usize, u32)>, String> {
+    with_handle(&renderer, |handle| {
+        let render_state = handle
+            .render_state
+            .lock()
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        let missing = renderer::missing_glyphs(&render_state, font.as_deref(), &text)?;
+        Ok(missing
+            .into_iter()
+            .map(|(offset, ch)| (offset, ch as u32))
+            .collect())
+    })
+}
+
+/// Extents of a text run for layout in Elixir, as returned by `measure_text`:
+/// `%{width: 52.3, ascent: 18.6, descent: 4.9, line_height: 23.4}`.
+///
+/// `ascent` and `descent` are distances above and below the baseline; `line_height` adds
+/// the font's leading to them.
+#[derive(rustler::NifMap)]
+pub struct TextMetrics {
+    width: f32,
+    ascent: f32,
+    descent: f32,
+    line_height: f32,
+}
+
+/// Measures `text` as `draw_text` would draw it in `font` (an uploaded font id, or the
+/// default font when `None`) at `size`.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn measure_text(
+    renderer: ResourceArc<RendererResource>,
+    font: Option<String>,
+    size: f64,
+    text: String,
+) -> Result<TextMetrics, String> {
+    with_handle(&renderer, |handle| {
+        let render_state = handle
+            .render_state
+            .lock()
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        let (width, metrics) =
+            renderer::measure_text(&render_state, font.as_deref(), size as f32, &text)?;
+        Ok(TextMetrics {
+            width,
+            ascent: -metrics.ascent,
+            descent: metrics.descent,
+            line_height: metrics.descent - metrics.ascent + metrics.leading,
+        })
+    })
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn put_stream_texture(
+    renderer: ResourceArc<RendererResource>,
+    id: String,
+    format: String,
+    width: u32,
+    height: u32,
+    data: rustler::Binary,
+) -> Result<ImageInfo, String> {
+    let assets = asset_store(&renderer)?;
+    let options = assets.texture_options(&id);
+    let (image, meta) =
+        renderer::decode_texture_image_with(&format, width, height, data.as_slice(), options)?;
+    renderer::insert_stream_image(&assets, &id, image, meta.clone());
+    signal_texture_redraw(&renderer, &id)?;
+    Ok(meta.into())
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn del_static_image(
+    renderer: ResourceArc<RendererResource>,
+    id: String,
+) -> Result<bool, String> {
+    let assets = asset_store(&renderer)?;
+    let removed = renderer::remove_static_image(&assets, &id);
+    signal_texture_redraw(&renderer, &id)?;
+    Ok(removed)
+}
+
+/// Sets the alpha type and color space applied to the next uploads of texture `id`.
+#[rustler::nif]
+pub fn set_texture_options(
+    renderer: ResourceArc<RendererResource>,
+    id: String,
+    alpha: String,
+    color_space: String,
+) -> Result<(), String> {
+    let options = TextureOptions::parse(&alpha, &color_space)?;
+    asset_store(&renderer)?.set_texture_options(&id, options);
+    Ok(())
+}
+
+/// Sets how frames of stream texture `id` that arrive before the previous one was drawn
+/// are handled: `"latest"` replaces the waiting frame, `"queue"` keeps up to `depth`
+/// frames and draws them one per rendered frame.
+#[rustler::nif]
+pub fn set_stream_policy(
+    renderer: ResourceArc<RendererResource>,
+    id: String,
+    policy: String,
+    depth: u32,
+) -> Result<(), String> {
+    let policy = DropPolicy::parse(&policy, depth)?;
+    asset_store(&renderer)?.set_stream_policy(&id, policy);
+    Ok(())
+}
+
+#[rustler::nif]
+pub fn set_image_dedup(
+    renderer: ResourceArc<RendererResource>,
+    enabled: bool,
+) -> Result<(), String> {
+    asset_store(&renderer)?.set_dedup(enabled);
+    Ok(())
+}
+
+#[rustler::nif]
+pub fn get_image_info(
+    renderer: ResourceArc<RendererResource>,
+    id: String,
+) -> Result<ImageInfo, String> {
+    let assets = asset_store(&renderer)?;
+    renderer::static_image_meta(&assets, &id)
+        .or_else(|| renderer::stream_image_meta(&assets, &id))
+        .map(ImageInfo::from)
+        .ok_or_else(|| format!("image not found: {id}"))
+}
+
+/// WCAG 2 relative luminance of an RGBA color.
+#[rustler::nif]
+pub fn relative_luminance(color: (u8, u8, u8, u8)) -> f64 {
+    color_utils::relative_luminance(color)
+}
+
+/// WCAG 2 contrast ratio between two RGBA colors, from 1.0 to 21.0.
+#[rustler::nif]
+pub fn contrast_ratio(a: (u8, u8, u8, u8), b: (u8, u8, u8, u8)) -> f64 {
+    color_utils::contrast_ratio(a, b)
+}
+
+/// Composites `fg` over `bg`, both unpremultiplied RGBA.
+#[rustler::nif]
+pub fn blend_colors(fg: (u8, u8, u8, u8), bg: (u8, u8, u8, u8)) -> (u8, u8, u8, u8) {
+    color_utils::blend(fg, bg)
+}
+
+/// Measures the path that the path ops of `script` build. Returns its total length and
+/// `{x, y, tangent_x, tangent_y}` at each of `fractions` of that length.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn measure_path(
+    script: rustler::Binary,
+    fractions: Vec<f32>,
+) -> Result<(f32, Vec<(f32, f32, f32, f32)>), String> {
+    if fractions.iter().any(|fraction| !fraction.is_finite()) {
+        return Err("path fractions must be finite".to_string());
+    }
+    let measure = PathMeasure::new(&parse_script(script.as_slice())?);
+    let samples = fractions
+        .into_iter()
+        .map(|fraction| {
+            let sample = measure
+                .sample(fraction)
+                .ok_or_else(|| "path is empty".to_string())?;
+            Ok((sample.x, sample.y, sample.tangent_x, sample.tangent_y))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok((measure.length(), samples))
+}
+
+/// Returns up to `count` dominant colors of the cached image `id`, most common first.
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn extract_palette(
+    renderer: ResourceArc<RendererResource>,
+    id: String,
+    count: usize,
+) -> Result<Vec<PaletteEntry>, String> {
+    let assets = asset_store(&renderer)?;
+    let image =
+        renderer::lookup_image(&assets, &id).ok_or_else(|| format!("image not found: {id}"))?;
+    color_utils::extract_palette(&image, count)
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn del_stream_texture(
+    renderer: ResourceArc<RendererResource>,
+    id: String,
+) -> Result<(), String> {
+    let assets = asset_store(&renderer)?;
+    renderer::remove_stream_image(&assets, &id);
+    signal_texture_redraw(&renderer, &id)
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn del_script(renderer: ResourceArc<RendererResource>, id: String) -> Result<(), String> {
+    update_render_state_with_damage(&renderer, |state| {
+        Ok(change_script(state, &id, |state| remove_script(state, &id)))
+    })
+}
+
+fn remove_script(state: &mut RenderState, id: &str) {
+    state.redraws.add_script(id);
+    state.scripts.remove(id);
+    state.script_transforms.remove(id);
+    state.script_params.remove(id);
+    state.scheduled.retain(|scheduled| scheduled.id != id);
+    if state.root_id.as_deref() == Some(id) {
+        state.root_id = None;
+    }
+}
+
+/// Sets the affine matrix `{a, b, c, d, e, f}` (as in the `transform` op) applied to
+/// script `id` wherever it is drawn, or clears it with `None`, so scrolling a long list
+/// only moves the cached script instead of re-submitting it.
+#[rustler::nif]
+pub fn set_script_transform(
+    renderer: ResourceArc<RendererResource>,
+    id: String,
+    matrix: Option<(f32, f32, f32, f32, f32, f32)>,
+) -> Result<(), String> {
+    let matrix = match matrix {
+        Some((a, b, c, d, e, f)) => {
+            if ![a, b, c, d, e, f].iter().all(|value| value.is_finite()) {
+                return Err("script transform must be finite".to_string());
+            }
+            Some(Matrix::new_all(a, c, e, b, d, f, 0.0, 0.0, 1.0))
+        }
+        None => None,
+    };
+    update_render_state_with_damage(&renderer, |state| {
+        Ok(change_script(state, &id, |state| {
+            state.redraws.add_script(&id);
+            renderer::set_script_transform(state, &id, matrix);
+        }))
+    })
+}
+
+/// Sets the cell index `draw_sprite_cell` ops of script `id` draw, so an animated icon
+/// steps through its sprite sheet without its script being encoded and parsed again.
+#[rustler::nif]
+pub fn set_script_param(
+    renderer: ResourceArc<RendererResource>,
+    id: String,
+    index: u32,
+) -> Result<(), String> {
+    update_render_state_with_damage(&renderer, |state| {
+        Ok(change_script(state, &id, |state| {
+            state.redraws.add_script(&id);
+            renderer::set_script_param(state, &id, index);
+        }))
+    })
+}
+
+/// Snapshots the script store and returns a token for `rollback`.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn checkpoint(renderer: ResourceArc<RendererResource>) -> Result<u64, String> {
+    with_handle(&renderer, |handle| {
+        let mut render_state = handle
+            .render_state
+            .lock()
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        Ok(checkpoint::checkpoint(&mut render_state))
+    })
+}
+
+/// Restores the scripts, root and scheduled scripts saved under `token`.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn rollback(renderer: ResourceArc<RendererResource>, token: u64) -> Result<(), String> {
+    update_render_state(&renderer, |state| checkpoint::rollback(state, token))
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn release_checkpoint(
+    renderer: ResourceArc<RendererResource>,
+    token: u64,
+) -> Result<bool, String> {
+    with_handle(&renderer, |handle| {
+        let mut render_state = handle
+            .render_state
+            .lock()
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        Ok(checkpoint::release(&mut render_state, token))
+    })
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn script_count(renderer: ResourceArc<RendererResource>) -> Result<u64, String> {
+    with_handle(&renderer, |handle| {
+        let render_state = handle
+            .render_state
+            .lock()
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        Ok(render_state.scripts.len() as u64)
+    })
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn get_raster_frame<'a>(
+    env: Env<'a>,
+    renderer: ResourceArc<RendererResource>,
+) -> Result<(u32, u32, Binary<'a>), String> {
+    with_handle(&renderer, |handle| {
+        let frame_slot = handle
+            .raster_frame
+            .as_ref()
+            .ok_or_else(|| "raster backend not active".to_string())?;
+        let frame_guard = frame_slot
+            .lock()
+            .map_err(|_| "raster frame lock poisoned".to_string())?;
+        let frame = frame_guard
+            .as_ref()
+            .ok_or_else(|| "raster frame not available".to_string())?;
+        let mut binary = OwnedBinary::new(frame.data.len())
+            .ok_or_else(|| "failed to allocate raster frame binary".to_string())?;
+        binary.as_mut_slice().copy_from_slice(&frame.data);
+        Ok((frame.width, frame.height, Binary::from_owned(binary, env)))
+    })
+}
+
+/// Reads back the next frame the backend renders, on any backend, as
+/// `{width, height, rgba}`. Waits up to `timeout_ms` for the frame.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn capture_frame<'a>(
+    env: Env<'a>,
+    renderer: ResourceArc<RendererResource>,
+    timeout_ms: u64,
+) -> Result<(u32, u32, Binary<'a>), String> {
+    capture_frame_inner(env, &renderer, timeout_ms)
+}
+
+fn capture_frame_inner<'a>(
+    env: Env<'a>,
+    renderer: &RendererResource,
+    timeout_ms: u64,
+) -> Result<(u32, u32, Binary<'a>), String> {
+    let (capture, ticket) = with_handle(renderer, |handle| {
+        let capture = handle
+            .render_state
+            .lock()
+            .map(|state| Arc::clone(&state.frame_capture))
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        let ticket = capture.request()?;
+        signal_redraw(handle)?;
+        Ok((capture, ticket))
+    })?;
+    let frame = capture.wait(ticket, Duration::from_millis(timeout_ms))?;
+    let mut binary = OwnedBinary::new(frame.pixels.len())
+        .ok_or_else(|| "failed to allocate frame binary".to_string())?;
+    binary.as_mut_slice().copy_from_slice(&frame.pixels);
+    Ok((frame.width, frame.height, Binary::from_owned(binary, env)))
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_input_mask(renderer: ResourceArc<RendererResource>, mask: u32) -> Result<(), String> {
+    update_input_queue(&renderer, |queue| queue.set_mask(mask))
+}
+
+/// Arms the input dead-man timer: `pid` receives `{:input_watchdog_expired, idle_ms}`
+/// when no input event reaches the queue for `timeout_ms`.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn arm_input_watchdog(
+    renderer: ResourceArc<RendererResource>,
+    timeout_ms: u64,
+    pid: Option<rustler::LocalPid>,
+) -> Result<(), String> {
+    if timeout_ms == 0 {
+        return Err("input watchdog timeout must be positive".to_string());
+    }
+    input_watchdog(&renderer)?.arm(Duration::from_millis(timeout_ms), pid)
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn disarm_input_watchdog(renderer: ResourceArc<RendererResource>) -> Result<bool, String> {
+    input_watchdog(&renderer)?.disarm()
+}
+
+fn input_watchdog(renderer: &RendererResource) -> Result<Arc<InputWatchdog>, String> {
+    with_handle(renderer, |handle| {
+        let queue = handle
+            .input_events
+            .lock()
+            .map_err(|_| "input queue lock poisoned".to_string())?;
+        Ok(queue.input_watchdog())
+    })
+}
+
+/// Limits cursor position delivery to one event per `interval_ms`; `0` delivers every
+/// position. Button and scroll events always flush the newest held-back position first.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_cursor_interval(
+    renderer: ResourceArc<RendererResource>,
+    interval_ms: u32,
+) -> Result<(), String> {
+    with_handle(&renderer, |handle| {
+        handle
+            .input_events
+            .lock()
+            .map_err(|_| "input queue lock poisoned".to_string())?
+            .set_cursor_interval(Duration::from_millis(interval_ms as u64));
+        Ok(())
+    })
+}
+
+/// Registers `pid` as an additional input target that receives events matching `mask`
+/// in its own queue, next to the driver's. Calling it again updates the mask.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn add_input_target(
+    renderer: ResourceArc<RendererResource>,
+    pid: rustler::LocalPid,
+    mask: u32,
+) -> Result<(), String> {
+    update_input_queue(&renderer, |queue| queue.add_target(pid, mask))
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn remove_input_target(
+    renderer: ResourceArc<RendererResource>,
+    pid: rustler::LocalPid,
+) -> Result<(), String> {
+    update_input_queue(&renderer, |queue| queue.remove_target(pid))
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn drain_input_target(
+    renderer: ResourceArc<RendererResource>,
+    pid: rustler::LocalPid,
+) -> Result<Vec<InputEvent>, String> {
+    with_handle(&renderer, |handle| {
+        let mut queue = handle
+            .input_events
+            .lock()
+            .map_err(|_| "input queue lock poisoned".to_string())?;
+        queue.drain_target(pid)
+    })
+}
+
+/// Queues a `ViewportReshape` with the current logical size for the driver and input
+/// targets accepting viewport events, and returns `%{width: .., height: .., scale: ..}`.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn request_viewport_info(
+    env: Env,
+    renderer: ResourceArc<RendererResource>,
+) -> Result<ViewportInfo, String> {
+    let (viewport, notify) = with_handle(&renderer, |handle| {
+        handle
+            .input_events
+            .lock()
+            .map_err(|_| "input queue lock poisoned".to_string())?
+            .reshape_viewport()
+    })?;
+    for pid in notify {
+        let _ = env.send(&pid, input::input_ready());
+    }
+    Ok(viewport)
+}
+
+/// Applies a target or mask change and stores the combined mask backends filter on.
+fn update_input_queue<F>(renderer: &RendererResource, update: F) -> Result<(), String>
+where
+    F: FnOnce(&mut InputQueue) -> u32,
+{
+    with_handle(renderer, |handle| {
+        let mut queue = handle
+            .input_events
+            .lock()
+            .map_err(|_| "input queue lock poisoned".to_string())?;
+        let mask = update(&mut queue);
+        handle.input_mask.store(mask, Ordering::Relaxed);
+        Ok(())
+    })
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_device_class_mask(
+    renderer: ResourceArc<RendererResource>,
+    class: String,
+    mask: u32,
+) -> Result<(), String> {
+    let class = DeviceClass::parse(&class)?;
+    with_handle(&renderer, |handle| {
+        handle.device_masks.set(class, mask);
+        Ok(())
+    })
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn show_cursor(renderer: ResourceArc<RendererResource>) -> Result<(), String> {
+    set_cursor_visible(&renderer, true)
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn hide_cursor(renderer: ResourceArc<RendererResource>) -> Result<(), String> {
+    set_cursor_visible(&renderer, false)
+}
+
+fn set_cursor_visible(renderer: &RendererResource, visible: bool) -> Result<(), String> {
+    with_handle(renderer, |handle| {
+        if let Some(cursor_state) = &handle.cursor_state
+            && let Ok(mut cursor) = cursor_state.lock()
+        {
+            cursor.visible = visible;
+        }
+        signal_cursor_changed(handle)
+    })
+}
+
+/// Shows `image`, `{width, height, rgba, {hotspot_x, hotspot_y}}` with unpremultiplied RGBA
+/// rows of at most 64x64 pixels, in place of the default cursor; `None` restores it.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_cursor_image(
+    renderer: ResourceArc<RendererResource>,
+    image: Option<(u32, u32, Binary, (u32, u32))>,
+) -> Result<(), String> {
+    let image = image
+        .map(|(width, height, rgba, hotspot)| {
+            CursorImage::new(width, height, rgba.as_slice().to_vec(), hotspot)
+        })
+        .transpose()?;
+    with_handle(&renderer, |handle| {
+        let cursor_image = handle
+            .render_state
+            .lock()
+            .map(|state| Arc::clone(&state.cursor_image))
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        cursor_image.set(image)?;
+        signal_cursor_changed(handle)
+    })
+}
+
+/// Wakes the backend to apply cursor changes: Wayland updates the window cursor, DRM
+/// redraws the cursor plane or software cursor.
+fn signal_cursor_changed(handle: &DriverHandle) -> Result<(), String> {
+    match &handle.stop {
+        StopSignal::Wayland(proxy) | StopSignal::Vulkan(proxy) => proxy
+            .send_event(UserEvent::Cursor)
+            .map_err(|err| format!("failed to signal renderer: {err}")),
+        StopSignal::Drm(_) | StopSignal::Raster(_) => {
+            if let Some(dirty) = &handle.dirty {
+                dirty.store(true, Ordering::Relaxed);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Replaces the cursor hit regions, given as `[{{x, y, w, h}, cursor}]` in scene
+/// coordinates. The backend shows the cursor of the last region under the pointer.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_cursor_regions(
+    renderer: ResourceArc<RendererResource>,
+    regions: Vec<((f64, f64, f64, f64), String)>,
+) -> Result<(), String> {
+    let regions = regions
+        .into_iter()
+        .map(|((x, y, w, h), cursor)| {
+            if ![x, y, w, h].iter().all(|value| value.is_finite()) || w < 0.0 || h < 0.0 {
+                return Err(format!("invalid cursor region: {:?}", (x, y, w, h)));
+            }
+            let rect = (x as f32, y as f32, w as f32, h as f32);
+            Ok((rect, CursorShape::parse(&cursor)?))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    with_handle(&renderer, |handle| {
+        let cursor_regions = handle
+            .render_state
+            .lock()
+            .map(|state| Arc::clone(&state.cursor_regions))
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        cursor_regions.set(regions)?;
+        if let Some(dirty) = &handle.dirty {
+            dirty.store(true, Ordering::Relaxed);
+        }
+        Ok(())
+    })
+}
+
+/// Replaces the hover regions, given as `[{id, {x, y, w, h}}]` in scene coordinates. Input
+/// targets accepting hover input get `{:hover, {:enter | :leave, id}}` as the pointer
+/// crosses them.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_hover_regions(
+    env: Env,
+    renderer: ResourceArc<RendererResource>,
+    regions: Vec<(String, (f64, f64, f64, f64))>,
+) -> Result<(), String> {
+    let regions = regions
+        .into_iter()
+        .map(|(id, (x, y, w, h))| {
+            if ![x, y, w, h].iter().all(|value| value.is_finite()) || w < 0.0 || h < 0.0 {
+                return Err(format!("invalid hover region {id}: {:?}", (x, y, w, h)));
+            }
+            Ok((id, (x as f32, y as f32, w as f32, h as f32)))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let notify = with_handle(&renderer, |handle| {
+        Ok(handle
+            .input_events
+            .lock()
+            .map_err(|_| "input queue lock poisoned".to_string())?
+            .set_hover_regions(regions))
+    })?;
+    for pid in notify {
+        let _ = env.send(&pid, input::input_ready());
+    }
+    Ok(())
+}
+
+/// Replaces the focus regions, given as `[{id, {x, y, w, h}}]` in scene coordinates with
+/// unique ids. The focus stays on its region while the id is still registered.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_focus_regions(
+    renderer: ResourceArc<RendererResource>,
+    regions: Vec<(String, (f64, f64, f64, f64))>,
+) -> Result<(), String> {
+    let regions = regions
+        .into_iter()
+        .map(|(id, (x, y, w, h))| {
+            if ![x, y, w, h].iter().all(|value| value.is_finite()) || w < 0.0 || h < 0.0 {
+                return Err(format!("invalid focus region {id}: {:?}", (x, y, w, h)));
+            }
+            Ok((id, (x as f32, y as f32, w as f32, h as f32)))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    update_focus(&renderer, |focus| focus.set(regions))
+}
+
+/// Moves the focus to the nearest region `direction` (`"up"`, `"down"`, `"left"` or
+/// `"right"`) of the focused one and returns the focused id.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn focus_next(
+    renderer: ResourceArc<RendererResource>,
+    direction: String,
+) -> Result<Option<String>, String> {
+    let direction = focus::Direction::parse(&direction)?;
+    update_focus(&renderer, |focus| {
+        Ok(focus.next(direction).map(str::to_string))
+    })
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_focus(
+    renderer: ResourceArc<RendererResource>,
+    id: Option<String>,
+) -> Result<(), String> {
+    update_focus(&renderer, |focus| focus.focus(id.as_deref()))
+}
+
+/// Draws a ring given as `{color, width, radius}` around the focused region, or stops
+/// drawing it with `None`.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_focus_ring(
+    renderer: ResourceArc<RendererResource>,
+    ring: Option<((u8, u8, u8, u8), f32, f32)>,
+) -> Result<(), String> {
+    let ring = match ring {
+        Some((_, width, radius))
+            if !(width > 0.0 && radius >= 0.0 && (width + radius).is_finite()) =>
+        {
+            return Err("focus ring needs a positive width and radius of at least 0".to_string());
+        }
+        Some(((r, g, b, a), width, radius)) => Some(focus::FocusRing {
+            color: skia_safe::Color::from_argb(a, r, g, b),
+            width,
+            radius,
+        }),
+        None => None,
+    };
+    update_focus(&renderer, |focus| {
+        focus.ring = ring;
+        Ok(())
+    })
+}
+
+/// Applies `update` to the focus regions and redraws where the focus ring moved; nothing
+/// is redrawn while no ring is shown.
+fn update_focus<T>(
+    renderer: &RendererResource,
+    update: impl FnOnce(&mut focus::FocusRegions) -> Result<T, String>,
+) -> Result<T, String> {
+    with_handle(renderer, |handle| {
+        let mut render_state = handle
+            .render_state
+            .lock()
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        let before = (render_state.focus.ring, render_state.focus.ring_bounds());
+        let result = update(&mut render_state.focus)?;
+        let after = (render_state.focus.ring, render_state.focus.ring_bounds());
+        drop(render_state);
+        let damage = match (before.1, after.1) {
+            _ if before == after => return Ok(result),
+            (Some(before), Some(after)) => Rect::join2(before, after),
+            (Some(bounds), None) | (None, Some(bounds)) => bounds,
+            (None, None) => return Ok(result),
+        };
+        signal_redraw_with_damage(handle, Some(damage))?;
+        Ok(result)
+    })
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_pointer_accel(
+    renderer: ResourceArc<RendererResource>,
+    profile: String,
+    sensitivity: f64,
+) -> Result<(), String> {
+    let profile = AccelProfile::parse(&profile)?;
+    if !sensitivity.is_finite() || sensitivity <= 0.0 {
+        return Err(format!("invalid pointer sensitivity: {sensitivity}"));
+    }
+    with_handle(&renderer, |handle| {
+        if let Some(pointer_accel) = &handle.pointer_accel {
+            let mut accel = pointer_accel
+                .lock()
+                .map_err(|_| "pointer accel lock poisoned".to_string())?;
+            accel.profile = profile;
+            accel.sensitivity = sensitivity as f32;
+        }
+        Ok(())
+    })
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_input_target(
+    renderer: ResourceArc<RendererResource>,
+    pid: Option<rustler::LocalPid>,
+) -> Result<(), String> {
+    with_handle(&renderer, |handle| {
+        let mut queue = handle
+            .input_events
+            .lock()
+            .map_err(|_| "input queue lock poisoned".to_string())?;
+        queue.set_target(pid);
+        // Don't notify from NIF context - managed threads can't use OwnedEnv::send_and_clear
+        Ok(())
+    })
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn request_animation_frames(
+    renderer: ResourceArc<RendererResource>,
+    enabled: bool,
+) -> Result<(), String> {
+    with_handle(&renderer, |handle| {
+        handle
+            .input_events
+            .lock()
+            .map_err(|_| "input queue lock poisoned".to_string())?
+            .set_animation_frames(enabled);
+        Ok(())
+    })
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_frame_deadline(
+    renderer: ResourceArc<RendererResource>,
+    deadline_ms: u64,
+    pid: Option<rustler::LocalPid>,
+) -> Result<(), String> {
+    frame_watchdog(&renderer)?.configure(deadline_ms, pid)
+}
+
+/// Enables strict reference checks on submit and/or logging of references still missing
+/// after `max_frames` rendered frames (`0` disables the frame check).
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_reference_check(
+    renderer: ResourceArc<RendererResource>,
+    strict: bool,
+    max_frames: u32,
+) -> Result<(), String> {
+    with_handle(&renderer, |handle| {
+        let render_state = handle
+            .render_state
+            .lock()
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        render_state.references.configure(strict, max_frames)
+    })
+}
+
+#[rustler::nif]
+pub fn set_flight_recorder(
+    renderer: ResourceArc<RendererResource>,
+    frames: u32,
+    max_width: u32,
+) -> Result<(), String> {
+    flight_recorder(&renderer)?.configure(frames, max_width)
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn dump_flight_recorder(
+    renderer: ResourceArc<RendererResource>,
+    dir: String,
+) -> Result<usize, String> {
+    flight_recorder(&renderer)?.dump(std::path::Path::new(&dir))
+}
+
+fn flight_recorder(
+    renderer: &RendererResource,
+) -> Result<Arc<flight_recorder::FlightRecorder>, String> {
+    with_handle(renderer, |handle| {
+        let render_state = handle
+            .render_state
+            .lock()
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        Ok(Arc::clone(&render_state.flight_recorder))
+    })
+}
+
+/// Streams per-frame statistics to clients of a Unix socket at `path` in `format`
+/// (`"json"` or `"binary"`); `None` stops exporting and removes the socket.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_stats_export(
+    renderer: ResourceArc<RendererResource>,
+    path: Option<String>,
+    format: String,
+) -> Result<(), String> {
+    let format = StatsFormat::parse(&format)?;
+    let export = with_handle(&renderer, |handle| {
+        let render_state = handle
+            .render_state
+            .lock()
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        Ok(Arc::clone(&render_state.stats_export))
+    })?;
+    export.configure(path.as_deref().map(std::path::Path::new), format)
+}
+
+/// Renderer health, returned as
+/// `%{backend: "drm", running: true, gpu: false, fallback: "failed to initialize EGL", ...}`.
+///
+/// `gpu`, `software` and `fallback` are `nil` until the backend has created its renderer;
+/// `software` is set when the GPU API is served by a CPU rasterizer such as llvmpipe and
+/// `fallback` when the backend fell back to CPU rendering. `degraded` reports the frame
+/// watchdog's degraded mode and `input_idle` an expired input watchdog. `backend_reason`
+/// explains the probe result when the driver was started with `backend: "auto"`.
+#[derive(rustler::NifMap)]
+struct DriverStatus {
+    backend: String,
+    running: bool,
+    gpu: Option<bool>,
+    software: Option<bool>,
+    fallback: Option<String>,
+    degraded: bool,
+    input_idle: bool,
+    backend_reason: Option<String>,
+}
+
+#[rustler::nif]
+pub fn get_status(renderer: ResourceArc<RendererResource>) -> Result<DriverStatus, String> {
+    with_handle(&renderer, |handle| {
+        let backend = match handle.stop {
+            StopSignal::Wayland(_) => "wayland",
+            StopSignal::Vulkan(_) => "vulkan",
+            StopSignal::Drm(_) => "drm",
+            StopSignal::Raster(_) => "raster",
+        }
+        .to_string();
+        let running = handle.running.load(Ordering::Relaxed)
+            && handle
+                .thread
+                .as_ref()
+                .is_some_and(|thread| !thread.is_finished());
+        let input_idle = handle
+            .input_events
+            .lock()
+            .map_err(|_| "input queue lock poisoned".to_string())?
+            .input_watchdog()
+            .is_expired();
+        let render_state = handle
+            .render_state
+            .lock()
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        let gpu_info = render_state.gpu_info.as_ref();
+        Ok(DriverStatus {
+            backend,
+            running,
+            gpu: gpu_info.map(|info| info.gpu),
+            software: gpu_info.map(|info| info.software),
+            fallback: gpu_info.and_then(|info| info.fallback.clone()),
+            degraded: render_state.watchdog.is_degraded(),
+            input_idle,
+            backend_reason: handle.backend_reason.clone(),
+        })
+    })
+}
+
+/// Returns the graphics stack of the running backend, or an error until it has started.
+#[rustler::nif]
+pub fn get_gpu_info(renderer: ResourceArc<RendererResource>) -> Result<GpuInfo, String> {
+    with_handle(&renderer, |handle| {
+        let render_state = handle
+            .render_state
+            .lock()
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        render_state
+            .gpu_info
+            .clone()
+            .ok_or_else(|| "renderer not initialized".to_string())
+    })
+}
+
+/// Returns the display connectors the DRM backend found when it last set up its outputs,
+/// or an empty list on other backends.
+#[rustler::nif]
+pub fn list_outputs(renderer: ResourceArc<RendererResource>) -> Result<Vec<OutputInfo>, String> {
+    with_handle(&renderer, |handle| {
+        let render_state = handle
+            .render_state
+            .lock()
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        Ok(render_state.outputs.clone())
+    })
+}
+
+/// Draws the script `script_id` as the root of the output named `output` instead of the
+/// main root, or goes back to the main root for `nil`. The script may be submitted later.
+#[rustler::nif]
+pub fn set_output_root(
+    renderer: ResourceArc<RendererResource>,
+    output: String,
+    script_id: Option<String>,
+) -> Result<(), String> {
+    update_render_state(&renderer, |state| {
+        match script_id {
+            Some(id) => state.output_roots.insert(output, id),
+            None => state.output_roots.remove(&output),
+        };
+        Ok(())
+    })
+}
+
+/// Returns why the last frames were rendered, oldest first. Up to 64 frames are kept.
+#[rustler::nif]
+pub fn get_redraw_reasons(
+    renderer: ResourceArc<RendererResource>,
+) -> Result<Vec<RedrawReasons>, String> {
+    let redraws = with_handle(&renderer, |handle| {
+        let render_state = handle
+            .render_state
+            .lock()
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        Ok(Arc::clone(&render_state.redraws))
+    })?;
+    Ok(redraws.recent())
+}
+
+/// Returns where the time of the last rendered frame went; see [`RenderStats`].
+#[rustler::nif]
+pub fn get_render_stats(renderer: ResourceArc<RendererResource>) -> Result<RenderStats, String> {
+    get_render_stats_inner(&renderer)
+}
+
+fn get_render_stats_inner(renderer: &RendererResource) -> Result<RenderStats, String> {
+    let frame_timing = with_handle(renderer, |handle| {
+        let render_state = handle
+            .render_state
+            .lock()
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        Ok(Arc::clone(&render_state.frame_timing))
+    })?;
+    frame_timing.last()
+}
+
+/// Enables or disables hardware JPEG decoding for every renderer in this VM. Returns the
+/// decoder device in use, or `nil` when disabled or no decoder was found.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_hardware_decode(
+    enabled: bool,
+    device: Option<String>,
+) -> Result<Option<String>, String> {
+    hw_decode::configure(enabled, device)
+}
+
+#[rustler::nif]
+pub fn degraded_mode(renderer: ResourceArc<RendererResource>) -> Result<bool, String> {
+    Ok(frame_watchdog(&renderer)?.is_degraded())
+}
+
+/// Op usage counts for this session, returned as `%{parsed: %{"line_to" => n}, drawn: %{...}}`.
+///
+/// `text_fallback` counts detected glyph upload failures and the text runs drawn through
+/// the CPU text fallback; `script_limits` counts frames cut short by each script limit;
+/// `streams` has the frame counts of each live stream texture; `quality` has the level
+/// the quality governor holds and how often it changed; `script_cache` counts the
+/// submissions the parsed script cache answered.
+#[derive(rustler::NifMap)]
+struct OpStatsInfo {
+    parsed: HashMap<String, u64>,
+    drawn: HashMap<String, u64>,
+    text_fallback: TextFallbackInfo,
+    script_limits: ScriptLimitsInfo,
+    streams: HashMap<String, StreamStatsInfo>,
+    quality: QualityInfo,
+    script_cache: ScriptCacheInfo,
+}
+
+#[derive(rustler::NifMap)]
+struct TextFallbackInfo {
+    failures: u64,
+    runs: u64,
+}
+
+#[derive(rustler::NifMap)]
+struct ScriptLimitsInfo {
+    ops: u64,
+    expansions: u64,
+    text: u64,
+}
+
+#[derive(rustler::NifMap)]
+struct StreamStatsInfo {
+    received: u64,
+    presented: u64,
+    dropped: u64,
+    pending: usize,
+}
+
+#[derive(rustler::NifMap)]
+struct QualityInfo {
+    level: String,
+    antialias: bool,
+    scale: f64,
+    downgrades: u64,
+    upgrades: u64,
+    temperature_c: Option<f64>,
+}
+
+#[derive(rustler::NifMap)]
+struct ScriptCacheInfo {
+    entries: usize,
+    hits: u64,
+    misses: u64,
+}
+
+impl From<ScriptCacheStats> for ScriptCacheInfo {
+    fn from(stats: ScriptCacheStats) -> Self {
+        Self {
+            entries: stats.entries,
+            hits: stats.hits,
+            misses: stats.misses,
+        }
+    }
+}
+
+#[rustler::nif]
+pub fn get_stats(renderer: ResourceArc<RendererResource>) -> Result<OpStatsInfo, String> {
+    get_stats_inner(&renderer)
+}
+
+fn get_stats_inner(renderer: &RendererResource) -> Result<OpStatsInfo, String> {
+    let (op_stats, text_fallback, script_guard, assets, quality, script_cache) =
+        with_handle(renderer, |handle| {
+            let render_state = handle
+                .render_state
+                .lock()
+                .map_err(|_| "render state lock poisoned".to_string())?;
+            Ok((
+                Arc::clone(&render_state.op_stats),
+                Arc::clone(&render_state.text_fallback),
+                Arc::clone(&render_state.script_guard),
+                Arc::clone(&render_state.assets),
+                Arc::clone(&render_state.quality),
+                Arc::clone(&render_state.script_cache),
+            ))
+        })?;
+    let (parsed, drawn) = op_stats.snapshot()?;
+    let (failures, runs) = text_fallback.counts();
+    let (ops, expansions, text) = script_guard.violations();
+    let streams = assets
+        .stream_counts()?
+        .into_iter()
+        .map(|(id, (counts, pending))| {
+            let info = StreamStatsInfo {
+                received: counts.received,
+                presented: counts.presented,
+                dropped: counts.dropped,
+                pending,
+            };
+            (id, info)
+        })
+        .collect();
+    let quality = quality.stats()?;
+    let script_cache = script_cache.stats()?;
+    Ok(OpStatsInfo {
+        parsed,
+        drawn,
+        text_fallback: TextFallbackInfo { failures, runs },
+        script_limits: ScriptLimitsInfo {
+            ops,
+            expansions,
+            text,
+        },
+        streams,
+        quality: QualityInfo {
+            level: quality.level.name().to_string(),
+            antialias: quality.level.antialias(),
+            scale: f64::from(quality.level.render_scale().factor()),
+            downgrades: quality.downgrades,
+            upgrades: quality.upgrades,
+            temperature_c: quality.temperature_mc.map(|mc| mc as f64 / 1000.0),
+        },
+        script_cache: script_cache.into(),
+    })
+}
+
+/// Selects when text is rasterized on the CPU: `"auto"` after a detected glyph upload
+/// failure, `"always"` or `"off"`.
+#[rustler::nif]
+pub fn set_text_fallback(
+    renderer: ResourceArc<RendererResource>,
+    mode: String,
+) -> Result<(), String> {
+    let mode = TextFallbackMode::parse(&mode)?;
+    update_render_state(&renderer, |state| {
+        state.text_fallback.set_mode(mode);
+        Ok(())
+    })
+}
+
+/// Per-frame limits on ops executed, `draw_script` expansions and text bytes drawn; `0`
+/// leaves a limit off.
+#[rustler::nif]
+pub fn set_script_limits(
+    renderer: ResourceArc<RendererResource>,
+    max_ops: u64,
+    max_expansions: u64,
+    max_text_bytes: u64,
+) -> Result<(), String> {
+    update_render_state(&renderer, |state| {
+        state.script_guard.set_limits(ScriptLimits {
+            max_ops,
+            max_expansions,
+            max_text_bytes,
+        });
+        Ok(())
+    })
+}
+
+/// Stores the bytes of parsed scripts in `dir`, parsing what is already stored there now
+/// so later submissions of the same scripts skip the parser. `nil` closes the directory
+/// and keeps only the in-memory cache. Returns the number of stored scripts loaded.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_script_cache(
+    renderer: ResourceArc<RendererResource>,
+    dir: Option<String>,
+    max_bytes: u64,
+) -> Result<usize, String> {
+    script_cache(&renderer)?.open(dir.map(Into::into), max_bytes, parse_script)
+}
+
+/// Parsed scripts held by content and the submissions that reused or parsed them.
+#[rustler::nif]
+pub fn cache_stats(renderer: ResourceArc<RendererResource>) -> Result<ScriptCacheInfo, String> {
+    Ok(script_cache(&renderer)?.stats()?.into())
+}
+
+fn script_cache(renderer: &RendererResource) -> Result<Arc<ScriptCache>, String> {
+    with_handle(renderer, |handle| {
+        let render_state = handle
+            .render_state
+            .lock()
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        Ok(Arc::clone(&render_state.script_cache))
+    })
+}
+
+/// Lowers render quality while frames take longer than `target_fps` allows, or the
+/// hottest of `thermal_zones` (sysfs `temp` files) is above `thermal_limit_c`, and
+/// restores it once frames have headroom again. A `target_fps` of `0` turns it off.
+#[rustler::nif]
+pub fn set_quality_governor(
+    renderer: ResourceArc<RendererResource>,
+    target_fps: u32,
+    thermal_zones: Vec<String>,
+    thermal_limit_c: f64,
+) -> Result<(), String> {
+    if !thermal_limit_c.is_finite() {
+        return Err("thermal limit must be finite".to_string());
+    }
+    let config = QualityConfig {
+        target_frame_us: if target_fps == 0 {
+            0
+        } else {
+            1_000_000 / u64::from(target_fps)
+        },
+        thermal_zones: thermal_zones.into_iter().map(Into::into).collect(),
+        thermal_limit_mc: (thermal_limit_c * 1000.0) as i64,
+    };
+    update_render_state(&renderer, |state| state.quality.configure(config))
+}
+
+/// Selects how scripts with NaN, infinite, negative or oversized geometry are handled:
+/// `"clamp"` fixes the values and records warnings, `"strict"` rejects the script.
+#[rustler::nif]
+pub fn set_geometry_mode(
+    renderer: ResourceArc<RendererResource>,
+    mode: String,
+) -> Result<(), String> {
+    let mode = GeometryMode::parse(&mode)?;
+    with_handle(&renderer, |handle| {
+        let render_state = handle
+            .render_state
+            .lock()
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        render_state.geometry.set_mode(mode);
+        Ok(())
+    })
+}
+
+/// Selects what happens after a panic while the render state was locked: `"recover"`
+/// clears the scene and sends `pid` `{:render_state_recovered, count}`, `"fail"` leaves
+/// every later call failing with "render state lock poisoned".
+#[rustler::nif]
+pub fn set_poisoned_state(
+    renderer: ResourceArc<RendererResource>,
+    policy: String,
+    pid: Option<rustler::LocalPid>,
+) -> Result<(), String> {
+    let policy = PoisonPolicy::parse(&policy)?;
+    with_handle(&renderer, |handle| {
+        handle.render_state.configure(policy, pid)
+    })
+}
+
+/// A value clamped in clamp mode, as returned by `take_geometry_warnings`:
+/// `%{script: "_root_", op: "draw_rect", field: "width", value: -4.0, problem: "negative"}`.
+///
+/// `value` is `nil` for NaN and infinite values, which have no Erlang float; `problem` is
+/// `"nan"`, `"infinite"`, `"negative"` or `"out_of_range"`.
+#[derive(rustler::NifMap)]
+pub struct GeometryWarning {
+    script: String,
+    op: String,
+    field: String,
+    value: Option<f64>,
+    problem: String,
+}
+
+/// Returns and clears the most recent geometry warnings, oldest first.
+#[rustler::nif]
+pub fn take_geometry_warnings(
+    renderer: ResourceArc<RendererResource>,
+) -> Result<Vec<GeometryWarning>, String> {
+    let geometry = with_handle(&renderer, |handle| {
+        let render_state = handle
+            .render_state
+            .lock()
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        Ok(Arc::clone(&render_state.geometry))
+    })?;
+    Ok(geometry
+        .take_warnings()?
+        .into_iter()
+        .map(|issue| GeometryWarning {
+            script: issue.script,
+            op: issue.op.to_string(),
+            field: issue.field.to_string(),
+            value: issue.value.is_finite().then_some(issue.value as f64),
+            problem: issue.problem.name().to_string(),
+        })
+        .collect())
+}
+
+fn frame_watchdog(renderer: &RendererResource) -> Result<Arc<watchdog::FrameWatchdog>, String> {
+    with_handle(renderer, |handle| {
+        let render_state = handle
+            .render_state
+            .lock()
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        Ok(Arc::clone(&render_state.watchdog))
+    })
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn drain_input_events(
+    renderer: ResourceArc<RendererResource>,
+) -> Result<Vec<InputEvent>, String> {
+    drain_input_events_inner(&renderer)
+}
+
+/// Drains the driver's queue like `drain_input_events`, packed into one binary by
+/// `input_record::encode_events` for the Elixir driver to decode.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn drain_input_binary<'a>(
+    env: Env<'a>,
+    renderer: ResourceArc<RendererResource>,
+) -> Result<Binary<'a>, String> {
+    let bytes = input_record::encode_events(&drain_input_events_inner(&renderer)?);
+    let mut binary = OwnedBinary::new(bytes.len())
+        .ok_or_else(|| "failed to allocate input binary".to_string())?;
+    binary.as_mut_slice().copy_from_slice(&bytes);
+    Ok(Binary::from_owned(binary, env))
+}
+
+fn drain_input_events_inner(renderer: &RendererResource) -> Result<Vec<InputEvent>, String> {
+    with_handle(renderer, |handle| {
+        let mut queue = handle
+            .input_events
+            .lock()
+            .map_err(|_| "input queue lock poisoned".to_string())?;
+        Ok(queue.drain())
+    })
+}
+
+/// Scripts the renderer holds, as returned by `inspector_dump_scene`.
+#[derive(rustler::NifMap)]
+struct SceneDump {
+    root_id: Option<String>,
+    output_roots: HashMap<String, String>,
+    scripts: HashMap<String, ScriptDump>,
+    scheduled: usize,
+}
+
+/// One stored script: its op count and the scripts it draws with `draw_script`.
+#[derive(rustler::NifMap)]
+struct ScriptDump {
+    ops: usize,
+    draw_scripts: Vec<String>,
+}
+
+/// Attaches a read-only inspector to `renderer`; see [`InspectorResource`].
+#[rustler::nif]
+pub fn attach_inspector(
+    renderer: ResourceArc<RendererResource>,
+) -> Result<ResourceArc<InspectorResource>, String> {
+    with_handle(&renderer, |_handle| Ok(()))?;
+    Ok(ResourceArc::new(InspectorResource { renderer }))
+}
+
+#[rustler::nif]
+pub fn inspector_get_stats(
+    inspector: ResourceArc<InspectorResource>,
+) -> Result<OpStatsInfo, String> {
+    get_stats_inner(&inspector.renderer)
+}
+
+#[rustler::nif]
+pub fn inspector_get_render_stats(
+    inspector: ResourceArc<InspectorResource>,
+) -> Result<RenderStats, String> {
+    get_render_stats_inner(&inspector.renderer)
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn inspector_dump_scene(
+    inspector: ResourceArc<InspectorResource>,
+) -> Result<SceneDump, String> {
+    with_handle(&inspector.renderer, |handle| {
+        let render_state = handle
+            .render_state
+            .lock()
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        let scripts = render_state
+            .scripts
+            .iter()
+            .map(|(id, ops)| {
+                let draw_scripts = ops
+                    .iter()
+                    .filter_map(|op| match op {
+                        ScriptOp::DrawScript(child) => Some(child.clone()),
+                        _ => None,
+                    })
+                    .collect();
+                let dump = ScriptDump {
+                    ops: ops.len(),
+                    draw_scripts,
+                };
+                (id.clone(), dump)
+            })
+            .collect();
+        Ok(SceneDump {
+            root_id: render_state.root_id.clone(),
+            output_roots: render_state.output_roots.clone(),
+            scripts,
+            scheduled: render_state.scheduled.len(),
+        })
+    })
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn inspector_capture_frame<'a>(
+    env: Env<'a>,
+    inspector: ResourceArc<InspectorResource>,
+    timeout_ms: u64,
+) -> Result<(u32, u32, Binary<'a>), String> {
+    capture_frame_inner(env, &inspector.renderer, timeout_ms)
+}
+
+/// Subscribes `pid` to input like [`add_input_target`]; the driver's own mask is kept.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn inspector_subscribe_input(
+    inspector: ResourceArc<InspectorResource>,
+    pid: rustler::LocalPid,
+    mask: u32,
+) -> Result<(), String> {
+    update_input_queue(&inspector.renderer, |queue| queue.add_target(pid, mask))
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn inspector_unsubscribe_input(
+    inspector: ResourceArc<InspectorResource>,
+    pid: rustler::LocalPid,
+) -> Result<(), String> {
+    update_input_queue(&inspector.renderer, |queue| queue.remove_target(pid))
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn inspector_drain_input(
+    inspector: ResourceArc<InspectorResource>,
+    pid: rustler::LocalPid,
+) -> Result<Vec<InputEvent>, String> {
+    with_handle(&inspector.renderer, |handle| {
+        let mut queue = handle
+            .input_events
+            .lock()
+            .map_err(|_| "input queue lock poisoned".to_string())?;
+        queue.drain_target(pid)
+    })
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn record_input(renderer: ResourceArc<RendererResource>, path: String) -> Result<(), String> {
+    let recorder = input_record::InputRecorder::create(std::path::Path::new(&path))?;
+    with_handle(&renderer, |handle| {
+        let mut queue = handle
+            .input_events
+            .lock()
+            .map_err(|_| "input queue lock poisoned".to_string())?;
+        queue.start_recording(recorder)
+    })
+}
+
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn stop_record(renderer: ResourceArc<RendererResource>) -> Result<(), String> {
+    with_handle(&renderer, |handle| {
+        let mut queue = handle
+            .input_events
+            .lock()
+            .map_err(|_| "input queue lock poisoned".to_string())?;
+        queue.stop_recording()
+    })
+}
+
 #[rustler::nif(schedule = "DirtyIo")]
-pub fn show_cursor(renderer: ResourceArc<RendererResource>) -> Result<(), String> {
-    set_cursor_visible(&renderer, true)
-}
-
-#[rustler::nif(schedule = "DirtyIo")]
-pub fn hide_cursor(renderer: ResourceArc<RendererResource>) -> Result<(), String> {
-    set_cursor_visible(&renderer, false)
+pub fn replay_input(renderer: ResourceArc<RendererResource>, path: String) -> Result<(), String> {
+    let events = input_record::read_log(std::path::Path::new(&path))?;
+    let (input_events, input_mask, running) = with_handle(&renderer, |handle| {
+        Ok((
+            Arc::clone(&handle.input_events),
+            Arc::clone(&handle.input_mask),
+            Arc::clone(&handle.running),
+        ))
+    })?;
+    thread::Builder::new()
+        .name("scenic-driver-replay".to_string())
+        .spawn(move || replay_events(events, input_events, input_mask, running))
+        .map_err(|err| format!("failed to spawn replay thread: {err}"))?;
+    Ok(())
 }
 
-fn set_cursor_visible(renderer: &RendererResource, visible: bool) -> Result<(), String> {
-    with_handle(renderer, |handle| {
-        if let Some(cursor_state) = &handle.cursor_state
-            && let Ok(mut cursor) = cursor_state.lock()
-        {
-            cursor.visible = visible;
+fn replay_events(
+    events: Vec<(u32, InputEvent)>,
+    input_events: Arc<Mutex<InputQueue>>,
+    input_mask: Arc<AtomicU32>,
+    running: Arc<AtomicBool>,
+) {
+    let started = std::time::Instant::now();
+    for (elapsed_ms, event) in events {
+        let due = started + Duration::from_millis(elapsed_ms as u64);
+        loop {
+            if !running.load(Ordering::Relaxed) {
+                return;
+            }
+            let now = std::time::Instant::now();
+            if now >= due {
+                break;
+            }
+            thread::sleep((due - now).min(Duration::from_millis(100)));
         }
 
-        if let Some(dirty) = &handle.dirty {
-            dirty.store(true, Ordering::Relaxed);
+        if input_mask.load(Ordering::Relaxed) & event.mask_bit() == 0 {
+            continue;
         }
-
-        Ok(())
-    })
+        let notify = match input_events.lock() {
+            Ok(mut queue) => queue.push_event(event),
+            Err(_) => return,
+        };
+        for pid in notify {
+            input::notify_input_ready(pid);
+        }
+    }
 }
 
+/// Moves the virtual pointer through `path`, `{x, y, duration_ms, click}` waypoints in scene
+/// coordinates, on a background thread, drawing it and pushing the matching cursor events.
+/// The path starts where the previous one left the pointer and replaces any still playing.
 #[rustler::nif(schedule = "DirtyIo")]
-pub fn set_input_target(
+pub fn play_pointer_path(
     renderer: ResourceArc<RendererResource>,
-    pid: Option<rustler::LocalPid>,
+    path: Vec<(f32, f32, u32, bool)>,
+    easing: String,
 ) -> Result<(), String> {
-    with_handle(&renderer, |handle| {
-        let mut queue = handle
-            .input_events
+    let easing = Easing::parse(&easing)?;
+    let waypoints: Vec<Waypoint> = path
+        .into_iter()
+        .map(|(x, y, duration_ms, click)| Waypoint {
+            x,
+            y,
+            duration_ms,
+            click,
+        })
+        .collect();
+    let (pointer, tracker, reduced_motion) = with_handle(&renderer, |handle| {
+        let render_state = handle
+            .render_state
             .lock()
-            .map_err(|_| "input queue lock poisoned".to_string())?;
-        queue.set_target(pid);
-        // Don't notify from NIF context - managed threads can't use OwnedEnv::send_and_clear
+            .map_err(|_| "render state lock poisoned".to_string())?;
+        Ok((
+            Arc::clone(&render_state.virtual_pointer),
+            Arc::clone(&render_state.pointer),
+            render_state.reduced_motion,
+        ))
+    })?;
+    let start = pointer
+        .cursor()
+        .map(|cursor| cursor.pos)
+        .or_else(|| waypoints.first().map(|point| (point.x, point.y)))
+        .unwrap_or_default();
+    let mut path = PointerPath::new(start, waypoints, easing)?;
+    if reduced_motion {
+        path = path.jumping();
+    }
+    let generation = pointer.begin();
+    thread::Builder::new()
+        .name("scenic-driver-pointer".to_string())
+        .spawn(move || play_pointer(renderer, pointer, tracker, path, generation))
+        .map_err(|err| format!("failed to spawn pointer thread: {err}"))?;
+    Ok(())
+}
+
+/// Stops a playing pointer path and removes the virtual pointer from the frame.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn hide_pointer(renderer: ResourceArc<RendererResource>) -> Result<(), String> {
+    update_render_state(&renderer, |state| {
+        state.virtual_pointer.hide();
         Ok(())
     })
 }
 
-#[rustler::nif(schedule = "DirtyIo")]
-pub fn drain_input_events(
+fn play_pointer(
     renderer: ResourceArc<RendererResource>,
-) -> Result<Vec<InputEvent>, String> {
-    drain_input_events_inner(&renderer)
+    pointer: Arc<VirtualPointer>,
+    tracker: Arc<dev_overlay::PointerTracker>,
+    path: PointerPath,
+    generation: u64,
+) {
+    let mut started = std::time::Instant::now();
+    let mut reached = 0;
+    let mut last = None;
+    let mut move_to = |pos: (f32, f32), pressed: bool| {
+        if last == Some((pos, pressed)) {
+            return true;
+        }
+        let moved = last.is_none_or(|(at, _)| at != pos);
+        last = Some((pos, pressed));
+        pointer.set(VirtualCursor { pos, pressed });
+        tracker.update(pos.0, pos.1);
+        let event = moved.then_some(InputEvent::CursorPos { x: pos.0, y: pos.1 });
+        push_pointer_input(&renderer, event)
+    };
+    loop {
+        if !pointer.is_current(generation) {
+            return;
+        }
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+        let (pos, now_reached) = path.sample(elapsed_ms);
+        for point in &path.waypoints()[reached..now_reached] {
+            if !point.click {
+                continue;
+            }
+            let (x, y) = (point.x, point.y);
+            if !move_to((x, y), true) {
+                return;
+            }
+            let press = |action| InputEvent::CursorButton {
+                button: "btn_left".to_string(),
+                action,
+                mods: 0,
+                x,
+                y,
+            };
+            if !push_pointer_input(&renderer, Some(press(input::ACTION_PRESS))) {
+                return;
+            }
+            // The button stays down on screen for a moment; the path clock waits with it.
+            let hold = Duration::from_millis(virtual_pointer::CLICK_HOLD_MS);
+            thread::sleep(hold);
+            started += hold;
+            if !pointer.is_current(generation)
+                || !push_pointer_input(&renderer, Some(press(input::ACTION_RELEASE)))
+            {
+                return;
+            }
+        }
+        reached = now_reached;
+        if !move_to(pos, false) || reached == path.waypoints().len() {
+            return;
+        }
+        thread::sleep(Duration::from_millis(virtual_pointer::STEP_MS));
+    }
 }
 
-fn drain_input_events_inner(renderer: &RendererResource) -> Result<Vec<InputEvent>, String> {
-    with_handle(renderer, |handle| {
+/// Redraws so the virtual pointer follows and pushes `event` through the input mask and
+/// queue. Returns `false` once the renderer has stopped.
+fn push_pointer_input(renderer: &RendererResource, event: Option<InputEvent>) -> bool {
+    let notify = with_handle(renderer, |handle| {
+        if !handle.running.load(Ordering::Relaxed) {
+            return Err("renderer stopped".to_string());
+        }
+        signal_redraw(handle)?;
+        let Some(event) = event else {
+            return Ok(Vec::new());
+        };
+        if handle.input_mask.load(Ordering::Relaxed) & event.mask_bit() == 0 {
+            return Ok(Vec::new());
+        }
         let mut queue = handle
             .input_events
             .lock()
             .map_err(|_| "input queue lock poisoned".to_string())?;
-        Ok(queue.drain())
-    })
+        Ok(queue.push_event(event))
+    });
+    let Ok(notify) = notify else {
+        return false;
+    };
+    for pid in notify {
+        input::notify_input_ready(pid);
+    }
+    true
+}
+
+/// Milliseconds on the driver clock (`CLOCK_MONOTONIC`) used by `submit_script_at`.
+pub(crate) fn monotonic_now_ms() -> u64 {
+    monotonic_now_us() / 1000
+}
+
+/// Microseconds on the driver clock, as sent in `{:frame, timestamp_us}` messages.
+pub(crate) fn monotonic_now_us() -> u64 {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts);
+    }
+    ts.tv_sec as u64 * 1_000_000 + ts.tv_nsec as u64 / 1000
+}
+
+fn schedule_script(state: &mut RenderState, id: String, ops: Arc<Vec<ScriptOp>>, due_ms: u64) {
+    let index = state
+        .scheduled
+        .partition_point(|scheduled| scheduled.due_ms <= due_ms);
+    state
+        .scheduled
+        .insert(index, ScheduledScript { due_ms, id, ops });
+}
+
+fn schedule_texture(state: &mut RenderState, texture: ScheduledTexture) {
+    let index = state
+        .scheduled_textures
+        .partition_point(|scheduled| scheduled.due_ms <= texture.due_ms);
+    state.scheduled_textures.insert(index, texture);
+}
+
+fn activate_scheduled(state: &mut RenderState, now_ms: u64) -> bool {
+    let due_textures = state
+        .scheduled_textures
+        .partition_point(|scheduled| scheduled.due_ms <= now_ms);
+    let due = state
+        .scheduled
+        .partition_point(|scheduled| scheduled.due_ms <= now_ms);
+    if due == 0 && due_textures == 0 {
+        return false;
+    }
+    for texture in state.scheduled_textures.drain(..due_textures) {
+        state.redraws.add_texture(&texture.id);
+        renderer::insert_stream_image(&state.assets, &texture.id, texture.image, texture.meta);
+    }
+    let activated: Vec<ScheduledScript> = state.scheduled.drain(..due).collect();
+    let ids: Vec<String> = activated
+        .iter()
+        .map(|scheduled| scheduled.id.clone())
+        .collect();
+    for scheduled in activated {
+        set_script(state, scheduled.id, scheduled.ops);
+    }
+    let _ = state.references.check(state, &ids);
+    state.damage.add_full();
+    true
+}
+
+/// Swaps in scheduled scripts that are due and notices animated sprites that need a
+/// new frame.
+///
+/// Returns whether a redraw is needed and how long the backend may sleep before the next
+/// scheduled script or animation frame. Uses `try_lock` so render loops never block on NIF updates.
+pub(crate) fn poll_scheduled_scripts(render_state: &RenderStateLock) -> (bool, Option<Duration>) {
+    let Some(mut state) = render_state.try_lock() else {
+        return (false, Some(Duration::from_millis(1)));
+    };
+    let now_ms = state.clock.now_ms();
+    let mut activated = activate_scheduled(&mut state, now_ms);
+    if let Some(due_ms) = state.animations.next_due()
+        && due_ms <= now_ms
+    {
+        state.damage.add_full();
+        state.redraws.add_animation();
+        activated = true;
+    }
+    let next_due = [
+        state.animations.next_due(),
+        state.scheduled.first().map(|scheduled| scheduled.due_ms),
+        state
+            .scheduled_textures
+            .first()
+            .map(|scheduled| scheduled.due_ms),
+    ]
+    .into_iter()
+    .flatten()
+    .min();
+    let next = next_due.map(|due_ms| Duration::from_millis(due_ms.saturating_sub(now_ms)));
+    (activated, next)
+}
+
+const NAMESPACE_SEPARATOR: char = '/';
+
+fn validate_namespace(namespace: &str) -> Result<(), String> {
+    if namespace.is_empty()
+        || namespace.starts_with(NAMESPACE_SEPARATOR)
+        || namespace.ends_with(NAMESPACE_SEPARATOR)
+    {
+        return Err(format!("invalid script namespace: {namespace:?}"));
+    }
+    Ok(())
+}
+
+fn namespaced_id(namespace: &str, id: &str) -> String {
+    format!("{namespace}{NAMESPACE_SEPARATOR}{id}")
+}
+
+/// Rewrites `draw_script` and `fill_pattern` references so they resolve inside `namespace`.
+///
+/// A leading `/` marks an absolute id that refers to a script outside the namespace.
+fn resolve_namespace_refs(ops: &mut [ScriptOp], namespace: &str) {
+    for op in ops.iter_mut() {
+        if let ScriptOp::DrawScript(id) | ScriptOp::FillPattern { id, .. } = op {
+            *id = match id.strip_prefix(NAMESPACE_SEPARATOR) {
+                Some(absolute) => absolute.to_string(),
+                None => namespaced_id(namespace, id),
+            };
+        }
+    }
 }
 
-fn set_script(state: &mut RenderState, id: String, ops: Vec<ScriptOp>) {
+fn remove_namespace(state: &mut RenderState, namespace: &str) -> usize {
+    let prefix = namespaced_id(namespace, "");
+    let before = state.scripts.len();
+    state.scripts.retain(|id, _| !id.starts_with(&prefix));
+    state
+        .scheduled
+        .retain(|scheduled| !scheduled.id.starts_with(&prefix));
+    if let Some(root_id) = state.root_id.as_deref()
+        && !state.scripts.contains_key(root_id)
+    {
+        state.root_id = None;
+    }
+    before - state.scripts.len()
+}
+
+/// Parses submitted script `id` through the script cache, reports the geometry clamped in
+/// it and counts its ops in the session's op stats.
+fn parse_counted(
+    state: &RenderState,
+    id: &str,
+    script: &[u8],
+) -> Result<Arc<Vec<ScriptOp>>, String> {
+    let started = std::time::Instant::now();
+    let checked = state.script_cache.parse(script, parse_script)?;
+    state.geometry.report(id, &checked.clamped)?;
+    state.frame_timing.record_parse(started.elapsed());
+    state.op_stats.record_parsed(&checked.ops)?;
+    Ok(checked.ops)
+}
+
+fn set_script(state: &mut RenderState, id: String, ops: Arc<Vec<ScriptOp>>) {
+    state.redraws.add_script(&id);
     state.scripts.insert(id.clone(), ops);
     if id == ROOT_ID {
         state.root_id = Some(id);
     }
 }
 
+/// Blend modes accepted in the high byte of the `save_layer` flag.
+fn layer_blend_mode(index: u8) -> Option<BlendMode> {
+    match index {
+        0 => Some(BlendMode::SrcOver),
+        1 => Some(BlendMode::Multiply),
+        2 => Some(BlendMode::Screen),
+        3 => Some(BlendMode::Overlay),
+        4 => Some(BlendMode::Darken),
+        5 => Some(BlendMode::Lighten),
+        6 => Some(BlendMode::Plus),
+        7 => Some(BlendMode::Difference),
+        _ => None,
+    }
+}
+
 fn is_known_opcode(opcode: u16) -> bool {
     matches!(
         opcode,
@@ -527,11 +3401,24 @@ fn is_known_opcode(opcode: u16) -> bool {
             | 0x30
             | 0x31
             | 0x32
+            | 0x33
+            | 0x34
+            | 0x35
+            | 0x36
+            | 0x37
+            | 0x38
+            | 0x39
+            | 0x3A
+            | 0x3B
+            | 0x3C
+            | 0x3D
             | 0x40
             | 0x41
             | 0x42
             | 0x44
             | 0x45
+            | 0x46
+            | 0x47
             | 0x50
             | 0x51
             | 0x52
@@ -541,12 +3428,18 @@ fn is_known_opcode(opcode: u16) -> bool {
             | 0x62
             | 0x63
             | 0x64
+            | 0x65
+            | 0x66
+            | 0x67
+            | 0x68
             | 0x70
             | 0x71
             | 0x72
             | 0x73
             | 0x74
             | 0x75
+            | 0x76
+            | 0x77
             | 0x80
             | 0x81
             | 0x82
@@ -554,9 +3447,101 @@ fn is_known_opcode(opcode: u16) -> bool {
             | 0x91
             | 0x92
             | 0x93
+            | 0x94
+            | 0x95
+            | 0x96
     )
 }
 
+/// Upper bound on the vertices of `draw_polygon` and `draw_star` shapes.
+const MAX_POLYGON_VERTICES: u32 = 1024;
+
+/// Upper bound on the color stops of `fill_gradient` and `stroke_gradient`.
+const MAX_GRADIENT_STOPS: usize = 256;
+
+/// Parses the payload of `fill_gradient` or `stroke_gradient`: the dither flag, a `u16`
+/// shape (0 linear, 1 radial, 2 conic), a `u16` stop count, two reserved bytes and four
+/// `f32` of shape geometry, then an `f32` position and RGBA per stop. Returns the op and
+/// the number of bytes it used.
+fn parse_gradient(rest: &[u8], stroke: bool) -> Result<(ScriptOp, usize), String> {
+    let name = if stroke {
+        "stroke_gradient"
+    } else {
+        "fill_gradient"
+    };
+    if rest.len() < 24 {
+        return Err(format!("{name} opcode truncated"));
+    }
+    let dither = renderer::GradientDither::from_flag(u16::from_be_bytes([rest[0], rest[1]]));
+    let kind = u16::from_be_bytes([rest[2], rest[3]]);
+    let count = u16::from_be_bytes([rest[4], rest[5]]) as usize;
+    let value = |i: usize| {
+        f32::from_bits(u32::from_be_bytes([
+            rest[i],
+            rest[i + 1],
+            rest[i + 2],
+            rest[i + 3],
+        ]))
+    };
+    let shape = match kind {
+        0 => GradientShape::Linear {
+            start_x: value(8),
+            start_y: value(12),
+            end_x: value(16),
+            end_y: value(20),
+        },
+        1 => GradientShape::Radial {
+            center_x: value(8),
+            center_y: value(12),
+            inner_radius: value(16),
+            outer_radius: value(20),
+        },
+        2 => GradientShape::Conic {
+            center_x: value(8),
+            center_y: value(12),
+            start_angle: value(16),
+        },
+        other => return Err(format!("{name} shape {other} is not supported")),
+    };
+    if !(2..=MAX_GRADIENT_STOPS).contains(&count) {
+        return Err(format!(
+            "{name} takes 2 to {MAX_GRADIENT_STOPS} stops, got {count}"
+        ));
+    }
+    let len = 24 + count * 8;
+    if rest.len() < len {
+        return Err(format!("{name} payload truncated"));
+    }
+    let mut stops = Vec::with_capacity(count);
+    let mut previous = 0.0;
+    for at in (24..len).step_by(8) {
+        let position = value(at);
+        if !(previous..=1.0).contains(&position) {
+            return Err(format!(
+                "{name} stop positions must rise from 0 to 1, got {position}"
+            ));
+        }
+        previous = position;
+        let rgba = &rest[at + 4..at + 8];
+        let color = skia_safe::Color::from_argb(rgba[3], rgba[0], rgba[1], rgba[2]);
+        stops.push((position, color));
+    }
+    let op = if stroke {
+        ScriptOp::StrokeGradient {
+            shape,
+            stops,
+            dither,
+        }
+    } else {
+        ScriptOp::FillGradient {
+            shape,
+            stops,
+            dither,
+        }
+    };
+    Ok((op, len))
+}
+
 fn next_opcode_valid(bytes: &[u8]) -> bool {
     if bytes.len() < 2 {
         return true;
@@ -722,6 +3707,47 @@ fn parse_script(script: &[u8]) -> Result<Vec<ScriptOp>, String> {
                 ops.push(ScriptOp::ClipPath(clip_op));
                 rest = tail;
             }
+            0x46 => {
+                if rest.len() < 18 {
+                    return Err("save_layer opcode truncated".to_string());
+                }
+                let (flag_bytes, tail) = rest.split_at(2);
+                let flag = u16::from_be_bytes([flag_bytes[0], flag_bytes[1]]);
+                let blend = layer_blend_mode((flag >> 8) as u8)
+                    .ok_or_else(|| "save_layer blend mode invalid".to_string())?;
+                let (x_bytes, tail) = tail.split_at(4);
+                let (y_bytes, tail) = tail.split_at(4);
+                let (w_bytes, tail) = tail.split_at(4);
+                let (h_bytes, tail) = tail.split_at(4);
+                let x = f32::from_bits(u32::from_be_bytes([
+                    x_bytes[0], x_bytes[1], x_bytes[2], x_bytes[3],
+                ]));
+                let y = f32::from_bits(u32::from_be_bytes([
+                    y_bytes[0], y_bytes[1], y_bytes[2], y_bytes[3],
+                ]));
+                let w = f32::from_bits(u32::from_be_bytes([
+                    w_bytes[0], w_bytes[1], w_bytes[2], w_bytes[3],
+                ]));
+                let h = f32::from_bits(u32::from_be_bytes([
+                    h_bytes[0], h_bytes[1], h_bytes[2], h_bytes[3],
+                ]));
+                ops.push(ScriptOp::SaveLayer {
+                    x,
+                    y,
+                    w,
+                    h,
+                    alpha: (flag & 0xFF) as u8,
+                    blend,
+                });
+                rest = tail;
+            }
+            0x47 => {
+                if rest.len() < 2 {
+                    return Err("restore opcode truncated".to_string());
+                }
+                ops.push(ScriptOp::Restore);
+                rest = &rest[2..];
+            }
             0x20 => {
                 if rest.len() < 2 {
                     return Err("begin_path opcode truncated".to_string());
@@ -1198,6 +4224,246 @@ fn parse_script(script: &[u8]) -> Result<Vec<ScriptOp>, String> {
                 ops.push(ScriptOp::DrawScript(id));
                 rest = &tail[pad..];
             }
+            0x33 => {
+                if rest.len() < 2 {
+                    return Err("text_path opcode truncated".to_string());
+                }
+                let (len_bytes, tail) = rest.split_at(2);
+                let len = u16::from_be_bytes([len_bytes[0], len_bytes[1]]) as usize;
+                let pad = (4 - (len % 4)) % 4;
+                if tail.len() < len + pad {
+                    return Err("text_path payload truncated".to_string());
+                }
+                let (text_bytes, tail) = tail.split_at(len);
+                let text = String::from_utf8_lossy(text_bytes).to_string();
+                ops.push(ScriptOp::TextPath(text));
+                rest = &tail[pad..];
+            }
+            0x34 => {
+                if rest.len() < 6 {
+                    return Err("draw_text_ellipsized opcode truncated".to_string());
+                }
+                let len = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+                let max_width =
+                    f32::from_bits(u32::from_be_bytes([rest[2], rest[3], rest[4], rest[5]]));
+                let tail = &rest[6..];
+                let pad = (4 - (len % 4)) % 4;
+                if tail.len() < len + pad {
+                    return Err("draw_text_ellipsized payload truncated".to_string());
+                }
+                let (text_bytes, tail) = tail.split_at(len);
+                let text = String::from_utf8_lossy(text_bytes).to_string();
+                ops.push(ScriptOp::DrawTextEllipsized { text, max_width });
+                rest = &tail[pad..];
+            }
+            0x35 => {
+                if rest.len() < 2 {
+                    return Err("draw_number opcode truncated".to_string());
+                }
+                let (len_bytes, tail) = rest.split_at(2);
+                let len = u16::from_be_bytes([len_bytes[0], len_bytes[1]]) as usize;
+                let pad = (4 - (len % 4)) % 4;
+                if tail.len() < len + pad {
+                    return Err("draw_number payload truncated".to_string());
+                }
+                let (text_bytes, tail) = tail.split_at(len);
+                let text = String::from_utf8_lossy(text_bytes).to_string();
+                ops.push(ScriptOp::DrawNumber(text));
+                rest = &tail[pad..];
+            }
+            0x36 => {
+                if rest.len() < 6 {
+                    return Err("draw_animated_sprite opcode truncated".to_string());
+                }
+                let len = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+                let count = u32::from_be_bytes([rest[2], rest[3], rest[4], rest[5]]) as usize;
+                let tail = &rest[6..];
+                let pad = (4 - (len % 4)) % 4;
+                let data_len = count
+                    .checked_mul(20)
+                    .and_then(|frames| frames.checked_add(len + pad + 16))
+                    .ok_or_else(|| "draw_animated_sprite frame count overflow".to_string())?;
+                if tail.len() < data_len {
+                    return Err("draw_animated_sprite payload truncated".to_string());
+                }
+                let (id_bytes, tail) = tail.split_at(len);
+                let image_id = String::from_utf8_lossy(id_bytes).to_string();
+                let (rect_bytes, tail) = tail[pad..].split_at(16);
+                let rect = |bytes: &[u8]| {
+                    let value = |i: usize| {
+                        f32::from_bits(u32::from_be_bytes([
+                            bytes[i],
+                            bytes[i + 1],
+                            bytes[i + 2],
+                            bytes[i + 3],
+                        ]))
+                    };
+                    Rect::from_xywh(value(0), value(4), value(8), value(12))
+                };
+                let (frame_bytes, tail) = tail.split_at(count * 20);
+                let frames = frame_bytes
+                    .chunks_exact(20)
+                    .map(|frame| renderer::SpriteFrame {
+                        src: rect(frame),
+                        duration_ms: u32::from_be_bytes([
+                            frame[16], frame[17], frame[18], frame[19],
+                        ]),
+                    })
+                    .collect();
+                ops.push(ScriptOp::DrawAnimatedSprite {
+                    image_id,
+                    dst: rect(rect_bytes),
+                    frames,
+                });
+                rest = tail;
+            }
+            0x3D => {
+                if rest.len() < 6 {
+                    return Err("draw_sprite_cell opcode truncated".to_string());
+                }
+                let len = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+                let columns = u16::from_be_bytes([rest[2], rest[3]]);
+                let rows = u16::from_be_bytes([rest[4], rest[5]]);
+                let tail = &rest[6..];
+                let pad = (4 - (len % 4)) % 4;
+                if tail.len() < len + pad + 16 {
+                    return Err("draw_sprite_cell payload truncated".to_string());
+                }
+                let (id_bytes, tail) = tail.split_at(len);
+                let image_id = String::from_utf8_lossy(id_bytes).to_string();
+                let (rect_bytes, tail) = tail[pad..].split_at(16);
+                let value = |i: usize| {
+                    f32::from_bits(u32::from_be_bytes([
+                        rect_bytes[i],
+                        rect_bytes[i + 1],
+                        rect_bytes[i + 2],
+                        rect_bytes[i + 3],
+                    ]))
+                };
+                ops.push(ScriptOp::DrawSpriteCell {
+                    image_id,
+                    dst: Rect::from_xywh(value(0), value(4), value(8), value(12)),
+                    columns,
+                    rows,
+                });
+                rest = tail;
+            }
+            0x37 => {
+                if rest.len() < 14 {
+                    return Err("draw_polygon opcode truncated".to_string());
+                }
+                let flag = u16::from_be_bytes([rest[0], rest[1]]);
+                let sides = u32::from_be_bytes([rest[2], rest[3], rest[4], rest[5]]);
+                let value = |i: usize| {
+                    f32::from_bits(u32::from_be_bytes([
+                        rest[i],
+                        rest[i + 1],
+                        rest[i + 2],
+                        rest[i + 3],
+                    ]))
+                };
+                if !(3..=MAX_POLYGON_VERTICES).contains(&sides) {
+                    return Err(format!("draw_polygon side count out of range: {sides}"));
+                }
+                ops.push(ScriptOp::DrawPolygon {
+                    sides,
+                    radius: value(6),
+                    rounding: value(10),
+                    flag,
+                });
+                rest = &rest[14..];
+            }
+            0x38 => {
+                if rest.len() < 18 {
+                    return Err("draw_star opcode truncated".to_string());
+                }
+                let flag = u16::from_be_bytes([rest[0], rest[1]]);
+                let points = u32::from_be_bytes([rest[2], rest[3], rest[4], rest[5]]);
+                let value = |i: usize| {
+                    f32::from_bits(u32::from_be_bytes([
+                        rest[i],
+                        rest[i + 1],
+                        rest[i + 2],
+                        rest[i + 3],
+                    ]))
+                };
+                if !(2..=MAX_POLYGON_VERTICES / 2).contains(&points) {
+                    return Err(format!("draw_star point count out of range: {points}"));
+                }
+                ops.push(ScriptOp::DrawStar {
+                    points,
+                    outer: value(6),
+                    inner: value(10),
+                    rounding: value(14),
+                    flag,
+                });
+                rest = &rest[18..];
+            }
+            0x39 | 0x3A => {
+                let name = if opcode == 0x39 {
+                    "draw_squircle"
+                } else {
+                    "superellipse"
+                };
+                if rest.len() < 14 {
+                    return Err(format!("{name} opcode truncated"));
+                }
+                let flag = u16::from_be_bytes([rest[0], rest[1]]);
+                let value = |i: usize| {
+                    f32::from_bits(u32::from_be_bytes([
+                        rest[i],
+                        rest[i + 1],
+                        rest[i + 2],
+                        rest[i + 3],
+                    ]))
+                };
+                let (width, height, exponent) = (value(2), value(6), value(10));
+                if !(exponent > 0.0 && exponent.is_finite()) {
+                    return Err(format!("{name} exponent must be positive: {exponent}"));
+                }
+                if opcode == 0x39 {
+                    ops.push(ScriptOp::DrawSquircle {
+                        width,
+                        height,
+                        exponent,
+                        flag,
+                    });
+                } else {
+                    ops.push(ScriptOp::PathSuperellipse {
+                        width,
+                        height,
+                        exponent,
+                    });
+                }
+                rest = &rest[14..];
+            }
+            0x3B => {
+                if rest.len() < 6 {
+                    return Err("blink opcode truncated".to_string());
+                }
+                let period_ms = u32::from_be_bytes([rest[2], rest[3], rest[4], rest[5]]);
+                ops.push(ScriptOp::Blink { period_ms });
+                rest = &rest[6..];
+            }
+            0x3C => {
+                if rest.len() < 10 {
+                    return Err("marquee opcode truncated".to_string());
+                }
+                let value = |i: usize| {
+                    f32::from_bits(u32::from_be_bytes([
+                        rest[i],
+                        rest[i + 1],
+                        rest[i + 2],
+                        rest[i + 3],
+                    ]))
+                };
+                let (width, speed) = (value(2), value(6));
+                if !(width.is_finite() && speed.is_finite()) {
+                    return Err(format!("marquee values must be finite: {width}, {speed}"));
+                }
+                ops.push(ScriptOp::Marquee { width, speed });
+                rest = &rest[10..];
+            }
             0x40 => {
                 if rest.len() < 2 {
                     return Err("push_state opcode truncated".to_string());
@@ -1234,7 +4500,11 @@ fn parse_script(script: &[u8]) -> Result<Vec<ScriptOp>, String> {
                 if rest.len() < 26 {
                     return Err("fill_linear opcode truncated".to_string());
                 }
-                let (_reserved, tail) = rest.split_at(2);
+                let (flag_bytes, tail) = rest.split_at(2);
+                let dither = renderer::GradientDither::from_flag(u16::from_be_bytes([
+                    flag_bytes[0],
+                    flag_bytes[1],
+                ]));
                 let (start_x_bytes, tail) = tail.split_at(4);
                 let (start_y_bytes, tail) = tail.split_at(4);
                 let (end_x_bytes, tail) = tail.split_at(4);
@@ -1280,6 +4550,7 @@ fn parse_script(script: &[u8]) -> Result<Vec<ScriptOp>, String> {
                     end_y,
                     start_color,
                     end_color,
+                    dither,
                 });
                 rest = tail;
             }
@@ -1287,7 +4558,11 @@ fn parse_script(script: &[u8]) -> Result<Vec<ScriptOp>, String> {
                 if rest.len() < 26 {
                     return Err("fill_radial opcode truncated".to_string());
                 }
-                let (_reserved, tail) = rest.split_at(2);
+                let (flag_bytes, tail) = rest.split_at(2);
+                let dither = renderer::GradientDither::from_flag(u16::from_be_bytes([
+                    flag_bytes[0],
+                    flag_bytes[1],
+                ]));
                 let (center_x_bytes, tail) = tail.split_at(4);
                 let (center_y_bytes, tail) = tail.split_at(4);
                 let (inner_bytes, tail) = tail.split_at(4);
@@ -1333,6 +4608,7 @@ fn parse_script(script: &[u8]) -> Result<Vec<ScriptOp>, String> {
                     outer_radius,
                     start_color,
                     end_color,
+                    dither,
                 });
                 rest = tail;
             }
@@ -1368,6 +4644,58 @@ fn parse_script(script: &[u8]) -> Result<Vec<ScriptOp>, String> {
                 ops.push(ScriptOp::FillStream(id));
                 rest = &tail[pad..];
             }
+            0x65 => {
+                if rest.len() < 10 {
+                    return Err("fill_pattern opcode truncated".to_string());
+                }
+                let len = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+                let width =
+                    f32::from_bits(u32::from_be_bytes([rest[2], rest[3], rest[4], rest[5]]));
+                let height =
+                    f32::from_bits(u32::from_be_bytes([rest[6], rest[7], rest[8], rest[9]]));
+                if !width.is_finite() || !height.is_finite() || width <= 0.0 || height <= 0.0 {
+                    return Err("fill_pattern tile size must be positive".to_string());
+                }
+                let tail = &rest[10..];
+                let pad = (4 - (len % 4)) % 4;
+                if tail.len() < len + pad {
+                    return Err("fill_pattern payload truncated".to_string());
+                }
+                let (id_bytes, tail) = tail.split_at(len);
+                let id = String::from_utf8_lossy(id_bytes).to_string();
+                ops.push(ScriptOp::FillPattern { id, width, height });
+                rest = &tail[pad..];
+            }
+            0x66 => {
+                if rest.len() < 18 {
+                    return Err("drop_shadow opcode truncated".to_string());
+                }
+                let value = |i: usize| {
+                    f32::from_bits(u32::from_be_bytes([
+                        rest[i],
+                        rest[i + 1],
+                        rest[i + 2],
+                        rest[i + 3],
+                    ]))
+                };
+                let rgba = &rest[14..18];
+                ops.push(ScriptOp::DropShadow {
+                    dx: value(2),
+                    dy: value(6),
+                    sigma: value(10),
+                    color: skia_safe::Color::from_argb(rgba[3], rgba[0], rgba[1], rgba[2]),
+                });
+                rest = &rest[18..];
+            }
+            0x67 => {
+                if rest.len() < 6 {
+                    return Err("blur opcode truncated".to_string());
+                }
+                let sigma =
+                    f32::from_bits(u32::from_be_bytes([rest[2], rest[3], rest[4], rest[5]]));
+                ops.push(ScriptOp::Blur { sigma });
+                rest = &rest[6..];
+            }
             0x50 => {
                 if rest.len() < 26 {
                     return Err("transform opcode truncated".to_string());
@@ -1896,7 +5224,11 @@ fn parse_script(script: &[u8]) -> Result<Vec<ScriptOp>, String> {
                 if rest.len() < 26 {
                     return Err("stroke_linear opcode truncated".to_string());
                 }
-                let (_reserved, tail) = rest.split_at(2);
+                let (flag_bytes, tail) = rest.split_at(2);
+                let dither = renderer::GradientDither::from_flag(u16::from_be_bytes([
+                    flag_bytes[0],
+                    flag_bytes[1],
+                ]));
                 let (start_x_bytes, tail) = tail.split_at(4);
                 let (start_y_bytes, tail) = tail.split_at(4);
                 let (end_x_bytes, tail) = tail.split_at(4);
@@ -1942,6 +5274,7 @@ fn parse_script(script: &[u8]) -> Result<Vec<ScriptOp>, String> {
                     end_y,
                     start_color,
                     end_color,
+                    dither,
                 });
                 rest = tail;
             }
@@ -1949,7 +5282,11 @@ fn parse_script(script: &[u8]) -> Result<Vec<ScriptOp>, String> {
                 if rest.len() < 26 {
                     return Err("stroke_radial opcode truncated".to_string());
                 }
-                let (_reserved, tail) = rest.split_at(2);
+                let (flag_bytes, tail) = rest.split_at(2);
+                let dither = renderer::GradientDither::from_flag(u16::from_be_bytes([
+                    flag_bytes[0],
+                    flag_bytes[1],
+                ]));
                 let (center_x_bytes, tail) = tail.split_at(4);
                 let (center_y_bytes, tail) = tail.split_at(4);
                 let (inner_bytes, tail) = tail.split_at(4);
@@ -1995,6 +5332,7 @@ fn parse_script(script: &[u8]) -> Result<Vec<ScriptOp>, String> {
                     outer_radius,
                     start_color,
                     end_color,
+                    dither,
                 });
                 rest = tail;
             }
@@ -2030,6 +5368,25 @@ fn parse_script(script: &[u8]) -> Result<Vec<ScriptOp>, String> {
                 ops.push(ScriptOp::StrokeStream(id));
                 rest = &tail[pad..];
             }
+            0x68 | 0x77 => {
+                let (op, len) = parse_gradient(rest, opcode == 0x77)?;
+                ops.push(op);
+                rest = &rest[len..];
+            }
+            0x76 => {
+                if rest.len() < 2 {
+                    return Err("non_scaling_stroke opcode truncated".to_string());
+                }
+                let (flag_bytes, tail) = rest.split_at(2);
+                let flag = u16::from_be_bytes([flag_bytes[0], flag_bytes[1]]);
+                let enabled = match flag {
+                    0x00 => false,
+                    0x01 => true,
+                    _ => return Err("non_scaling_stroke opcode invalid".to_string()),
+                };
+                ops.push(ScriptOp::NonScalingStroke(enabled));
+                rest = tail;
+            }
             0x80 => {
                 if rest.len() < 2 {
                     return Err("cap opcode truncated".to_string());
@@ -2125,8 +5482,48 @@ fn parse_script(script: &[u8]) -> Result<Vec<ScriptOp>, String> {
                 ops.push(ScriptOp::TextBase(base));
                 rest = tail;
             }
+            0x94 | 0x95 => {
+                let name = if opcode == 0x94 {
+                    "text_width"
+                } else {
+                    "line_height"
+                };
+                if rest.len() < 6 {
+                    return Err(format!("{name} opcode truncated"));
+                }
+                let value =
+                    f32::from_bits(u32::from_be_bytes([rest[2], rest[3], rest[4], rest[5]]));
+                if !(value.is_finite() && value >= 0.0) {
+                    return Err(format!("{name} must be finite and non-negative: {value}"));
+                }
+                ops.push(if opcode == 0x94 {
+                    ScriptOp::TextWidth(value)
+                } else {
+                    ScriptOp::LineHeight(value)
+                });
+                rest = &rest[6..];
+            }
+            0x96 => {
+                if rest.len() < 2 {
+                    return Err("text_wrap opcode truncated".to_string());
+                }
+                let wrap = TextWrap::from_code(u16::from_be_bytes([rest[0], rest[1]]))?;
+                ops.push(ScriptOp::TextWrap(wrap));
+                rest = &rest[2..];
+            }
             _ => {
-                return Err(format!("unsupported opcode: 0x{opcode:02x}"));
+                let offset = script.len() - rest.len() - 2;
+                if offset == 0 {
+                    // Nothing parsed at all: more likely a serializer this parser does not
+                    // know than a corrupt script.
+                    return Err(format!(
+                        "unsupported opcode: 0x{opcode:02x} at byte 0; the script format may \
+                         not match this driver (mismatched Scenic and native driver versions?)"
+                    ));
+                }
+                return Err(format!(
+                    "unsupported opcode: 0x{opcode:02x} at byte {offset}"
+                ));
             }
         }
     }
@@ -2135,6 +5532,8 @@ fn parse_script(script: &[u8]) -> Result<Vec<ScriptOp>, String> {
 
 fn load(env: Env, _info: Term) -> bool {
     env.register::<RendererResource>().is_ok()
+        && env.register::<AssetStoreResource>().is_ok()
+        && env.register::<InspectorResource>().is_ok()
 }
 
 rustler::init!("Elixir.Scenic.Driver.Skia.Native", load = load);
@@ -2184,7 +5583,12 @@ mod tests {
     fn parse_rejects_unknown_opcode() {
         let script: [u8; 2] = [0x12, 0x34];
         let err = parse_script(&script).unwrap_err();
-        assert!(err.contains("unsupported opcode"));
+        assert!(err.starts_with("unsupported opcode: 0x1234 at byte 0;"));
+        assert!(err.contains("mismatched Scenic and native driver versions"));
+
+        let script: [u8; 6] = [0x00, 0x40, 0x00, 0x00, 0x12, 0x34];
+        let err = parse_script(&script).unwrap_err();
+        assert_eq!(err, "unsupported opcode: 0x1234 at byte 4");
     }
 
     #[test]
@@ -2204,6 +5608,25 @@ mod tests {
         }));
     }
 
+    #[test]
+    fn resubmitted_scripts_share_ops_and_report_clamped_geometry() {
+        let state = RenderState::default();
+        let script: [u8; 12] = [
+            0x00, 0x53, 0x00, 0x00, 0x7F, 0xC0, 0x00, 0x00, 0x42, 0x70, 0x00, 0x00,
+        ];
+        let first = parse_counted(&state, "a", &script).expect("clamped");
+        let again = parse_counted(&state, "b", &script).expect("cached");
+
+        assert!(Arc::ptr_eq(&first, &again));
+        assert_eq!(*first, vec![ScriptOp::Translate(0.0, 60.0)]);
+        let warnings = state.geometry.take_warnings().expect("warnings");
+        let scripts: Vec<_> = warnings.iter().map(|issue| issue.script.as_str()).collect();
+        assert_eq!(scripts, ["a", "b"]);
+
+        state.geometry.set_mode(GeometryMode::Strict);
+        assert!(parse_counted(&state, "c", &script).is_err());
+    }
+
     #[test]
     fn parse_includes_draw_script() {
         let mut script: Vec<u8> = vec![0x00, 0x0f, 0x00, 0x04];
@@ -2223,6 +5646,290 @@ mod tests {
         assert_eq!(ops, vec![ScriptOp::DrawText("hi".to_string())]);
     }
 
+    #[test]
+    fn parse_text_path() {
+        let script: [u8; 12] = [
+            0x00, 0x33, 0x00, 0x05, b'h', b'e', b'l', b'l', b'o', 0x00, 0x00, 0x00,
+        ];
+        let ops = parse_script(&script).expect("parse_script failed");
+        assert_eq!(ops, vec![ScriptOp::TextPath("hello".to_string())]);
+        assert!(parse_script(&script[..8]).is_err());
+    }
+
+    #[test]
+    fn parse_draw_text_ellipsized() {
+        let mut script = vec![0x00, 0x34, 0x00, 0x03];
+        script.extend_from_slice(&80.0f32.to_be_bytes());
+        script.extend_from_slice(&[0xE2, 0x80, 0xA6, 0x00]);
+        let ops = parse_script(&script).expect("parse_script failed");
+        assert_eq!(
+            ops,
+            vec![ScriptOp::DrawTextEllipsized {
+                text: "\u{2026}".to_string(),
+                max_width: 80.0
+            }]
+        );
+        assert!(parse_script(&script[..10]).is_err());
+    }
+
+    #[test]
+    fn parse_draw_number() {
+        let script = [
+            0x00, 0x35, 0x00, 0x05, b'-', b'1', b'2', b'.', b'5', 0x00, 0x00, 0x00,
+        ];
+        let ops = parse_script(&script).expect("parse_script failed");
+        assert_eq!(ops, vec![ScriptOp::DrawNumber("-12.5".to_string())]);
+        assert!(parse_script(&script[..6]).is_err());
+        let after_sprites =
+            parse_script(&after_draw_sprites(&script)).expect("parse_script failed");
+        assert_eq!(after_sprites[1..], ops[..]);
+    }
+
+    #[test]
+    fn parse_draw_animated_sprite() {
+        let mut script = vec![0x00, 0x36, 0x00, 0x03, 0x00, 0x00, 0x00, 0x02];
+        script.extend_from_slice(&[b'a', b'b', b'c', 0x00]);
+        for value in [10.0f32, 20.0, 32.0, 32.0] {
+            script.extend_from_slice(&value.to_be_bytes());
+        }
+        for (x, duration_ms) in [(0.0f32, 100u32), (16.0, 50)] {
+            for value in [x, 0.0, 16.0, 16.0] {
+                script.extend_from_slice(&value.to_be_bytes());
+            }
+            script.extend_from_slice(&duration_ms.to_be_bytes());
+        }
+        let ops = parse_script(&script).expect("parse_script failed");
+        assert_eq!(
+            ops,
+            vec![ScriptOp::DrawAnimatedSprite {
+                image_id: "abc".to_string(),
+                dst: Rect::from_xywh(10.0, 20.0, 32.0, 32.0),
+                frames: vec![
+                    renderer::SpriteFrame {
+                        src: Rect::from_xywh(0.0, 0.0, 16.0, 16.0),
+                        duration_ms: 100,
+                    },
+                    renderer::SpriteFrame {
+                        src: Rect::from_xywh(16.0, 0.0, 16.0, 16.0),
+                        duration_ms: 50,
+                    },
+                ],
+            }]
+        );
+        assert!(parse_script(&script[..script.len() - 4]).is_err());
+    }
+
+    #[test]
+    fn parse_draw_sprite_cell() {
+        let mut script = vec![0x00, 0x3D, 0x00, 0x05, 0x00, 0x04, 0x00, 0x02];
+        script.extend_from_slice(&[b'i', b'c', b'o', b'n', b's', 0x00, 0x00, 0x00]);
+        for value in [10.0f32, 20.0, 24.0, 24.0] {
+            script.extend_from_slice(&value.to_be_bytes());
+        }
+        let ops = parse_script(&script).expect("parse_script failed");
+        assert_eq!(
+            ops,
+            vec![ScriptOp::DrawSpriteCell {
+                image_id: "icons".to_string(),
+                dst: Rect::from_xywh(10.0, 20.0, 24.0, 24.0),
+                columns: 4,
+                rows: 2,
+            }]
+        );
+        let after_sprites =
+            parse_script(&after_draw_sprites(&script)).expect("parse_script failed");
+        assert_eq!(after_sprites[1..], ops[..]);
+        assert!(parse_script(&script[..script.len() - 4]).is_err());
+    }
+
+    #[test]
+    fn parse_blink_and_marquee() {
+        let mut script = vec![0x00, 0x3B, 0x00, 0x00];
+        script.extend_from_slice(&1000u32.to_be_bytes());
+        script.extend_from_slice(&[0x00, 0x3C, 0x00, 0x00]);
+        for value in [200.0f32, -40.0] {
+            script.extend_from_slice(&value.to_be_bytes());
+        }
+        let ops = parse_script(&script).expect("parse_script failed");
+        assert_eq!(
+            ops,
+            vec![
+                ScriptOp::Blink { period_ms: 1000 },
+                ScriptOp::Marquee {
+                    width: 200.0,
+                    speed: -40.0,
+                },
+            ]
+        );
+        let after_sprites =
+            parse_script(&after_draw_sprites(&script)).expect("parse_script failed");
+        assert_eq!(after_sprites[1..], ops[..]);
+        assert!(parse_script(&script[..script.len() - 4]).is_err());
+        let len = script.len();
+        script[len - 4..].copy_from_slice(&f32::NAN.to_be_bytes());
+        assert!(parse_script(&script).is_err());
+    }
+
+    #[test]
+    fn parse_gradient_stops() {
+        let mut script = vec![0x00, 0x68, 0x00, 0x01, 0x00, 0x02, 0x00, 0x03, 0x00, 0x00];
+        for value in [10.0f32, 20.0, 1.5, 0.0] {
+            script.extend_from_slice(&value.to_be_bytes());
+        }
+        for (position, rgba) in [(0.0f32, [255, 0, 0, 255]), (0.25, [0, 255, 0, 255])] {
+            script.extend_from_slice(&position.to_be_bytes());
+            script.extend_from_slice(&rgba);
+        }
+        script.extend_from_slice(&1.0f32.to_be_bytes());
+        script.extend_from_slice(&[0, 0, 255, 128]);
+        let ops = parse_script(&script).expect("parse_script failed");
+        assert_eq!(
+            ops,
+            vec![ScriptOp::FillGradient {
+                shape: GradientShape::Conic {
+                    center_x: 10.0,
+                    center_y: 20.0,
+                    start_angle: 1.5,
+                },
+                stops: vec![
+                    (0.0, skia_safe::Color::RED),
+                    (0.25, skia_safe::Color::GREEN),
+                    (1.0, skia_safe::Color::from_argb(128, 0, 0, 255)),
+                ],
+                dither: renderer::GradientDither::Paint,
+            }]
+        );
+        let after_sprites =
+            parse_script(&after_draw_sprites(&script)).expect("parse_script failed");
+        assert_eq!(after_sprites[1..], ops[..]);
+        let mut stroke = script.clone();
+        stroke[1] = 0x77;
+        let after_sprites =
+            parse_script(&after_draw_sprites(&stroke)).expect("parse_script failed");
+        assert!(matches!(after_sprites[1], ScriptOp::StrokeGradient { .. }));
+        assert!(parse_script(&script[..script.len() - 8]).is_err());
+
+        // Stops must not go backwards, and two are the least a gradient takes.
+        let mut backwards = script.clone();
+        backwards[42..46].copy_from_slice(&0.1f32.to_be_bytes());
+        assert!(parse_script(&backwards).is_err());
+        script[1] = 0x77;
+        script[7] = 0x01;
+        assert!(parse_script(&script).is_err());
+    }
+
+    #[test]
+    fn parse_drop_shadow_and_blur() {
+        let mut script = vec![0x00, 0x66, 0x00, 0x00];
+        for value in [2.0f32, 4.0, 6.0] {
+            script.extend_from_slice(&value.to_be_bytes());
+        }
+        script.extend_from_slice(&[0x00, 0x00, 0x00, 0x80]);
+        script.extend_from_slice(&[0x00, 0x67, 0x00, 0x00]);
+        script.extend_from_slice(&3.0f32.to_be_bytes());
+        let ops = parse_script(&script).expect("parse_script failed");
+        assert_eq!(
+            ops,
+            vec![
+                ScriptOp::DropShadow {
+                    dx: 2.0,
+                    dy: 4.0,
+                    sigma: 6.0,
+                    color: skia_safe::Color::from_argb(0x80, 0, 0, 0),
+                },
+                ScriptOp::Blur { sigma: 3.0 },
+            ]
+        );
+        let after_sprites =
+            parse_script(&after_draw_sprites(&script)).expect("parse_script failed");
+        assert_eq!(after_sprites[1..], ops[..]);
+        assert!(parse_script(&script[..script.len() - 4]).is_err());
+    }
+
+    #[test]
+    fn parse_text_layout_ops() {
+        let mut script = vec![0x00, 0x94, 0x00, 0x00];
+        script.extend_from_slice(&120.0f32.to_be_bytes());
+        script.extend_from_slice(&[0x00, 0x95, 0x00, 0x00]);
+        script.extend_from_slice(&1.5f32.to_be_bytes());
+        script.extend_from_slice(&[0x00, 0x96, 0x00, 0x01]);
+        let ops = parse_script(&script).expect("parse_script failed");
+        assert_eq!(
+            ops,
+            vec![
+                ScriptOp::TextWidth(120.0),
+                ScriptOp::LineHeight(1.5),
+                ScriptOp::TextWrap(TextWrap::Word),
+            ]
+        );
+        let after_sprites =
+            parse_script(&after_draw_sprites(&script)).expect("parse_script failed");
+        assert_eq!(after_sprites[1..], ops[..]);
+        assert!(parse_script(&[0x00, 0x96, 0x00, 0x07]).is_err());
+        let mut negative = vec![0x00, 0x94, 0x00, 0x00];
+        negative.extend_from_slice(&(-1.0f32).to_be_bytes());
+        assert!(parse_script(&negative).is_err());
+    }
+
+    #[test]
+    fn parse_fill_pattern() {
+        let mut script = vec![0x00, 0x65, 0x00, 0x05];
+        script.extend_from_slice(&16.0f32.to_be_bytes());
+        script.extend_from_slice(&8.0f32.to_be_bytes());
+        script.extend_from_slice(b"hatch\0\0\0");
+        let ops = parse_script(&script).expect("parse_script failed");
+        assert_eq!(
+            ops,
+            vec![ScriptOp::FillPattern {
+                id: "hatch".to_string(),
+                width: 16.0,
+                height: 8.0,
+            }]
+        );
+        let after_sprites =
+            parse_script(&after_draw_sprites(&script)).expect("parse_script failed");
+        assert_eq!(after_sprites[1..], ops[..]);
+        assert!(parse_script(&script[..14]).is_err());
+        script[4..8].copy_from_slice(&0.0f32.to_be_bytes());
+        assert!(parse_script(&script).is_err());
+
+        let mut ops = vec![ScriptOp::FillPattern {
+            id: "tile".to_string(),
+            width: 4.0,
+            height: 4.0,
+        }];
+        resolve_namespace_refs(&mut ops, "button/1");
+        assert!(matches!(&ops[0], ScriptOp::FillPattern { id, .. } if id == "button/1/tile"));
+    }
+
+    #[test]
+    fn parse_save_layer_and_restore() {
+        let mut script = vec![0x00, 0x46, 0x01, 0x80];
+        for value in [4.0f32, 8.0, 32.0, 16.0] {
+            script.extend_from_slice(&value.to_be_bytes());
+        }
+        script.extend_from_slice(&[0x00, 0x47, 0x00, 0x00]);
+        let ops = parse_script(&script).expect("parse_script failed");
+        assert_eq!(
+            ops,
+            vec![
+                ScriptOp::SaveLayer {
+                    x: 4.0,
+                    y: 8.0,
+                    w: 32.0,
+                    h: 16.0,
+                    alpha: 0x80,
+                    blend: BlendMode::Multiply,
+                },
+                ScriptOp::Restore,
+            ]
+        );
+
+        script[2] = 0x20;
+        let err = parse_script(&script).unwrap_err();
+        assert!(err.contains("save_layer blend mode invalid"));
+    }
+
     #[test]
     fn parse_finished_marker() {
         let script: [u8; 4] = [0x00, 0x00, 0x00, 0x00];
@@ -2305,6 +6012,66 @@ mod tests {
         );
     }
 
+    #[test]
+    fn dedup_static_images_share_until_last_alias_removed() {
+        let png = include_bytes!("../../../assets/images/test_red.png");
+        let assets = AssetStore::new(false);
+        assets.set_dedup(true);
+
+        let first = renderer::insert_static_image_dedup(&assets, "icon_a", png).expect("insert");
+        let second = renderer::insert_static_image_dedup(&assets, "icon_b", png).expect("insert");
+        assert_eq!(first, second);
+        assert_eq!(assets.blob_count(), 1);
+
+        assert!(renderer::remove_static_image(&assets, "icon_a"));
+        assert!(!renderer::remove_static_image(&assets, "icon_a"));
+        assert!(renderer::static_image_meta(&assets, "icon_b").is_some());
+        assert_eq!(assets.blob_count(), 1);
+
+        assert!(renderer::remove_static_image(&assets, "icon_b"));
+        assert_eq!(assets.blob_count(), 0);
+    }
+
+    #[test]
+    fn parse_gradient_dither_flags() {
+        let mut script = vec![0x00, 0x61, 0x00, 0x01];
+        script.extend_from_slice(&[0; 24]);
+        script.extend_from_slice(&[0x00, 0x73, 0x00, 0x02]);
+        script.extend_from_slice(&[0; 24]);
+
+        let ops = parse_script(&script).expect("parse_script failed");
+        assert!(matches!(
+            ops[0],
+            ScriptOp::FillLinear {
+                dither: renderer::GradientDither::Paint,
+                ..
+            }
+        ));
+        assert!(matches!(
+            ops[1],
+            ScriptOp::StrokeRadial {
+                dither: renderer::GradientDither::Noise,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_non_scaling_stroke() {
+        let script: [u8; 8] = [0x00, 0x76, 0x00, 0x01, 0x00, 0x76, 0x00, 0x00];
+        let ops = parse_script(&script).expect("parse_script failed");
+        assert_eq!(
+            ops,
+            vec![
+                ScriptOp::NonScalingStroke(true),
+                ScriptOp::NonScalingStroke(false)
+            ]
+        );
+
+        let err = parse_script(&[0x00, 0x76, 0x00, 0x02]).unwrap_err();
+        assert!(err.contains("non_scaling_stroke opcode invalid"));
+    }
+
     #[test]
     fn parse_clip_path() {
         let script: [u8; 4] = [0x00, 0x45, 0x00, 0x00];
@@ -2451,6 +6218,8 @@ mod tests {
             key: "key_a".to_string(),
             action: 1,
             mods: 0,
+            scancode: 30,
+            repeat: 0,
         });
         queue.push_event(InputEvent::ViewportReshape {
             width: 1280,
@@ -2461,13 +6230,16 @@ mod tests {
         let handle = DriverHandle {
             stop: StopSignal::Raster(Arc::clone(&stop)),
             text: Arc::new(Mutex::new(String::new())),
-            render_state: Arc::new(Mutex::new(RenderState::default())),
+            render_state: Arc::new(RenderStateLock::new(RenderState::default())),
             input_events: Arc::clone(&input_events),
             input_mask: Arc::new(AtomicU32::new(0)),
+            device_masks: Arc::new(DeviceClassMasks::new()),
             raster_frame: None,
             dirty: Some(Arc::new(AtomicBool::new(false))),
             running: Arc::new(AtomicBool::new(false)),
             cursor_state: None,
+            pointer_accel: None,
+            input_transform: Arc::new(Mutex::new(InputTransform::new())),
             thread: Some(thread),
         };
         let renderer = RendererResource {
@@ -2481,6 +6253,109 @@ mod tests {
         assert!(matches!(drained[2], InputEvent::ViewportReshape { .. }));
     }
 
+    #[test]
+    fn input_queue_derives_hover_changes_from_cursor_positions() {
+        let mut queue = InputQueue::new();
+        assert_eq!(
+            queue.set_mask(input::INPUT_MASK_HOVER),
+            input::INPUT_MASK_HOVER | input::INPUT_MASK_CURSOR_POS | input::INPUT_MASK_VIEWPORT
+        );
+        queue.set_hover_regions(vec![("button".to_string(), (0.0, 0.0, 10.0, 10.0))]);
+        queue.push_event(InputEvent::CursorPos { x: 5.0, y: 5.0 });
+        queue.push_event(InputEvent::CursorPos { x: 6.0, y: 5.0 });
+        queue.push_event(InputEvent::Viewport {
+            entered: false,
+            x: 6.0,
+            y: 5.0,
+        });
+
+        let hover = |entered| InputEvent::Hover {
+            region: "button".to_string(),
+            entered,
+        };
+        assert_eq!(queue.drain(), vec![hover(true), hover(false)]);
+    }
+
+    #[test]
+    fn input_queue_filters_driver_events_by_mask() {
+        let mut queue = InputQueue::new();
+        assert_eq!(queue.set_mask(input::INPUT_MASK_KEY), input::INPUT_MASK_KEY);
+        let notify = queue.push_event(InputEvent::CursorPos { x: 1.0, y: 2.0 });
+        assert!(notify.is_empty());
+        queue.push_event(InputEvent::Key {
+            key: "key_a".to_string(),
+            action: 1,
+            mods: 0,
+            scancode: 30,
+            repeat: 0,
+        });
+
+        let drained = queue.drain();
+        assert_eq!(drained.len(), 1);
+        assert!(matches!(drained[0], InputEvent::Key { .. }));
+    }
+
+    #[test]
+    fn input_queue_holds_cursor_positions_within_the_interval() {
+        let mut queue = InputQueue::new();
+        queue.set_cursor_interval(Duration::from_secs(60));
+        queue.push_event(InputEvent::CursorPos { x: 1.0, y: 1.0 });
+        queue.push_event(InputEvent::CursorPos { x: 2.0, y: 2.0 });
+        queue.push_event(InputEvent::CursorPos { x: 3.0, y: 3.0 });
+        assert!(queue.held_cursor_due().is_some());
+        assert!(queue.flush_held_cursor().is_empty());
+        assert!(matches!(
+            queue.drain().as_slice(),
+            [InputEvent::CursorPos { x: 1.0, .. }]
+        ));
+
+        queue.push_event(InputEvent::CursorPos { x: 4.0, y: 4.0 });
+        queue.push_event(InputEvent::CursorButton {
+            button: "btn_left".to_string(),
+            action: input::ACTION_PRESS,
+            mods: 0,
+            x: 4.0,
+            y: 4.0,
+        });
+        assert!(matches!(
+            queue.drain().as_slice(),
+            [
+                InputEvent::CursorPos { x: 4.0, .. },
+                InputEvent::CursorButton { .. }
+            ]
+        ));
+
+        queue.push_event(InputEvent::CursorPos { x: 5.0, y: 5.0 });
+        queue.set_cursor_interval(Duration::ZERO);
+        assert!(queue.held_cursor_due().is_none());
+        assert!(matches!(
+            queue.drain().as_slice(),
+            [InputEvent::CursorPos { x: 5.0, .. }]
+        ));
+    }
+
+    #[test]
+    fn input_queue_reshapes_the_last_viewport_again() {
+        let mut queue = InputQueue::new();
+        assert!(queue.reshape_viewport().is_err());
+        let viewport = ViewportInfo {
+            width: 640,
+            height: 360,
+            scale: 2.0,
+        };
+        queue.set_viewport(viewport);
+
+        let (reshaped, _notify) = queue.reshape_viewport().expect("reshape_viewport");
+        assert_eq!(reshaped, viewport);
+        assert!(matches!(
+            queue.drain().as_slice(),
+            [InputEvent::ViewportReshape {
+                width: 640,
+                height: 360
+            }]
+        ));
+    }
+
     #[test]
     fn parse_draw_rrect() {
         let script: [u8; 16] = [
@@ -2520,6 +6395,72 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_draw_polygon_and_star() {
+        let script: [u8; 36] = [
+            0x00, 0x37, 0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x42, 0x20, 0x00, 0x00, 0x40, 0x80,
+            0x00, 0x00, 0x00, 0x38, 0x00, 0x03, 0x00, 0x00, 0x00, 0x05, 0x42, 0x20, 0x00, 0x00,
+            0x41, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let ops = parse_script(&script).expect("parse_script failed");
+        assert_eq!(
+            ops,
+            vec![
+                ScriptOp::DrawPolygon {
+                    sides: 6,
+                    radius: 40.0,
+                    rounding: 4.0,
+                    flag: 0x01
+                },
+                ScriptOp::DrawStar {
+                    points: 5,
+                    outer: 40.0,
+                    inner: 16.0,
+                    rounding: 0.0,
+                    flag: 0x03
+                }
+            ]
+        );
+
+        let degenerate = [
+            0x00, 0x37, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x42, 0x20, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ];
+        assert!(parse_script(&degenerate).is_err());
+    }
+
+    #[test]
+    fn parse_squircle_and_superellipse() {
+        let script: [u8; 32] = [
+            0x00, 0x39, 0x00, 0x01, 0x42, 0x20, 0x00, 0x00, 0x41, 0xA0, 0x00, 0x00, 0x40, 0xA0,
+            0x00, 0x00, 0x00, 0x3A, 0x00, 0x00, 0x42, 0x20, 0x00, 0x00, 0x42, 0x20, 0x00, 0x00,
+            0x40, 0x80, 0x00, 0x00,
+        ];
+        let ops = parse_script(&script).expect("parse_script failed");
+        assert_eq!(
+            ops,
+            vec![
+                ScriptOp::DrawSquircle {
+                    width: 40.0,
+                    height: 20.0,
+                    exponent: 5.0,
+                    flag: 0x01
+                },
+                ScriptOp::PathSuperellipse {
+                    width: 40.0,
+                    height: 40.0,
+                    exponent: 4.0
+                }
+            ]
+        );
+
+        let flat = [
+            0x00, 0x39, 0x00, 0x01, 0x42, 0x20, 0x00, 0x00, 0x41, 0xA0, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ];
+        assert!(parse_script(&flat).is_err());
+    }
+
     #[test]
     fn parse_stroke_cap_join_miter() {
         let script: [u8; 6] = [
@@ -2734,6 +6675,7 @@ mod tests {
                     end_y: 4.0,
                     start_color: skia_safe::Color::from_argb(40, 10, 20, 30),
                     end_color: skia_safe::Color::from_argb(80, 50, 60, 70),
+                    dither: renderer::GradientDither::Off,
                 },
                 ScriptOp::StrokeLinear {
                     start_x: 5.0,
@@ -2742,12 +6684,123 @@ mod tests {
                     end_y: 8.0,
                     start_color: skia_safe::Color::from_argb(4, 1, 2, 3),
                     end_color: skia_safe::Color::from_argb(8, 5, 6, 7),
+                    dither: renderer::GradientDither::Off,
                 }
             ]
         );
     }
 
+    #[test]
+    fn scheduled_scripts_activate_in_due_order() {
+        let mut state = RenderState::default();
+        schedule_script(&mut state, "b".to_string(), vec![ScriptOp::PopState], 200);
+        schedule_script(
+            &mut state,
+            ROOT_ID.to_string(),
+            vec![ScriptOp::PushState],
+            100,
+        );
+        schedule_script(&mut state, "b".to_string(), vec![ScriptOp::PushState], 300);
+
+        assert!(!activate_scheduled(&mut state, 99));
+        assert!(state.scripts.is_empty());
+
+        assert!(activate_scheduled(&mut state, 200));
+        assert_eq!(state.root_id.as_deref(), Some(ROOT_ID));
+        assert_eq!(
+            state.scripts.get("b"),
+            Some(&Arc::new(vec![ScriptOp::PopState]))
+        );
+        assert_eq!(state.scheduled.len(), 1);
+
+        assert!(activate_scheduled(&mut state, 1_000));
+        assert_eq!(
+            state.scripts.get("b"),
+            Some(&Arc::new(vec![ScriptOp::PushState]))
+        );
+        assert!(state.scheduled.is_empty());
+    }
+
+    #[test]
+    fn scheduled_textures_activate_with_scripts() {
+        let mut state = RenderState::default();
+        state.assets = Arc::new(AssetStore::new(false));
+        let (image, meta) =
+            renderer::decode_texture_image("rgb", 1, 1, &[255, 0, 0]).expect("decode");
+        schedule_texture(
+            &mut state,
+            ScheduledTexture {
+                due_ms: 100,
+                id: "video".to_string(),
+                image,
+                meta,
+            },
+        );
+        schedule_script(
+            &mut state,
+            "subtitle".to_string(),
+            vec![ScriptOp::PushState],
+            100,
+        );
+
+        assert!(!activate_scheduled(&mut state, 99));
+        assert!(renderer::stream_image_meta(&state.assets, "video").is_none());
+
+        assert!(activate_scheduled(&mut state, 100));
+        assert!(renderer::stream_image_meta(&state.assets, "video").is_some());
+        assert!(state.scripts.contains_key("subtitle"));
+        assert!(state.scheduled_textures.is_empty());
+    }
+
+    #[test]
+    fn namespace_refs_resolve_relative_and_absolute_ids() {
+        let mut ops = vec![
+            ScriptOp::DrawScript("child".to_string()),
+            ScriptOp::DrawScript("/shared".to_string()),
+            ScriptOp::PushState,
+        ];
+        resolve_namespace_refs(&mut ops, "button/1");
+        assert_eq!(ops[0], ScriptOp::DrawScript("button/1/child".to_string()));
+        assert_eq!(ops[1], ScriptOp::DrawScript("shared".to_string()));
+        assert_eq!(ops[2], ScriptOp::PushState);
+    }
+
+    #[test]
+    fn remove_namespace_drops_only_prefixed_scripts() {
+        let mut state = RenderState::default();
+        set_script(&mut state, ROOT_ID.to_string(), Arc::default());
+        set_script(&mut state, "ui/a".to_string(), Arc::default());
+        set_script(&mut state, "ui/b/c".to_string(), Arc::default());
+        set_script(&mut state, "uix".to_string(), Arc::default());
+
+        assert_eq!(remove_namespace(&mut state, "ui"), 2);
+        assert!(state.scripts.contains_key("uix"));
+        assert!(state.scripts.contains_key(ROOT_ID));
+        assert_eq!(state.root_id.as_deref(), Some(ROOT_ID));
+    }
+
+    #[test]
+    fn validate_namespace_rejects_empty_and_slashes() {
+        assert!(validate_namespace("ui").is_ok());
+        assert!(validate_namespace("ui/button").is_ok());
+        assert!(validate_namespace("").is_err());
+        assert!(validate_namespace("/ui").is_err());
+        assert!(validate_namespace("ui/").is_err());
+    }
+
     fn push_f32(buf: &mut Vec<u8>, value: f32) {
         buf.extend_from_slice(&value.to_bits().to_be_bytes());
     }
+
+    /// `draw_sprites` with one command followed by `next`, whose opcode the sprite parser
+    /// has to recognize to pick the command layout.
+    fn after_draw_sprites(next: &[u8]) -> Vec<u8> {
+        let mut script = vec![0x00, 0x0B, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01];
+        script.extend_from_slice(b"s\0\0\0");
+        for value in [0.0f32, 0.0, 4.0, 4.0, 0.0, 0.0, 4.0, 4.0, 0.5] {
+            push_f32(&mut script, value);
+        }
+        script.extend_from_slice(next);
+        script
+    }
 }
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::renderer::ScriptOp;
+use crate::script_limits::{FrameBudget, ScriptLimits};
+
+/// Per-session counts of parsed and drawn script ops, keyed by op name.
+///
+/// Parsing is counted as scripts are submitted; drawing is collected per frame in an
+/// [`OpCounts`] and merged once the frame is finished, so the render loop only takes
+/// the lock once per frame.
+#[derive(Debug, Default)]
+pub struct OpStats {
+    parsed: Mutex<HashMap<&'static str, u64>>,
+    drawn: Mutex<HashMap<&'static str, u64>>,
+}
+
+impl OpStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_parsed(&self, ops: &[ScriptOp]) -> Result<(), String> {
+        let mut parsed = self
+            .parsed
+            .lock()
+            .map_err(|_| "op stats lock poisoned".to_string())?;
+        for op in ops {
+            *parsed.entry(op.name()).or_default() += 1;
+        }
+        Ok(())
+    }
+
+    pub fn record_drawn(&self, counts: &OpCounts) {
+        if counts.drawn.is_empty() {
+            return;
+        }
+        if let Ok(mut drawn) = self.drawn.lock() {
+            for (name, count) in &counts.drawn {
+                *drawn.entry(name).or_default() += count;
+            }
+        }
+    }
+
+    /// Returns `(parsed, drawn)` counts.
+    pub fn snapshot(&self) -> Result<(HashMap<String, u64>, HashMap<String, u64>), String> {
+        let copy = |counts: &Mutex<HashMap<&'static str, u64>>| {
+            counts
+                .lock()
+                .map(|counts| {
+                    counts
+                        .iter()
+                        .map(|(name, count)| (name.to_string(), *count))
+                        .collect()
+                })
+                .map_err(|_| "op stats lock poisoned".to_string())
+        };
+        Ok((copy(&self.parsed)?, copy(&self.drawn)?))
+    }
+}
+
+/// Draw counts for a single frame, charged against the frame's script limits.
+#[derive(Debug, Default)]
+pub struct OpCounts {
+    drawn: HashMap<&'static str, u64>,
+    budget: FrameBudget,
+}
+
+impl OpCounts {
+    pub fn with_limits(limits: ScriptLimits) -> Self {
+        Self {
+            drawn: HashMap::new(),
+            budget: FrameBudget::new(limits),
+        }
+    }
+
+    /// Counts `op` and returns whether it may still be drawn this frame.
+    pub fn record(&mut self, op: &ScriptOp) -> bool {
+        if !self.budget.charge(op) {
+            return false;
+        }
+        *self.drawn.entry(op.name()).or_default() += 1;
+        true
+    }
+
+    pub fn budget(&self) -> &FrameBudget {
+        &self.budget
+    }
+
+    /// Ops drawn this frame.
+    pub fn total(&self) -> u64 {
+        self.drawn.values().sum()
+    }
+
+    /// Whether the frame drew any text runs.
+    pub fn drew_text(&self) -> bool {
+        ["draw_text", "draw_text_ellipsized", "draw_number"]
+            .iter()
+            .any(|name| self.drawn.contains_key(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn op_stats_accumulate_parsed_and_drawn_counts() {
+        let stats = OpStats::new();
+        let ops = vec![
+            ScriptOp::BeginPath,
+            ScriptOp::LineTo { x: 1.0, y: 2.0 },
+            ScriptOp::LineTo { x: 3.0, y: 4.0 },
+        ];
+        stats.record_parsed(&ops).expect("record_parsed");
+
+        let mut frame = OpCounts::default();
+        for op in &ops[1..] {
+            frame.record(op);
+        }
+        stats.record_drawn(&frame);
+        stats.record_drawn(&frame);
+        assert_eq!(frame.total(), 2);
+
+        let (parsed, drawn) = stats.snapshot().expect("snapshot");
+        assert_eq!(parsed.get("begin_path"), Some(&1));
+        assert_eq!(parsed.get("line_to"), Some(&2));
+        assert_eq!(drawn.get("line_to"), Some(&4));
+        assert_eq!(drawn.get("begin_path"), None);
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use crate::state_lock::RenderStateLock;
+
+/// A display connector found by the DRM backend, as returned by `list_outputs`:
+/// `%{name: "HDMI-A-1", connected: true, width: 1920, height: 1080, refresh: 60, ...}`.
+///
+/// `width`, `height` and `refresh` describe the mode the output is or would be driven
+/// at and are zero while nothing is connected. `active` is `true` while the backend
+/// drives the output.
+#[derive(Clone, Debug, Default, PartialEq, rustler::NifMap)]
+pub struct OutputInfo {
+    pub name: String,
+    pub connected: bool,
+    pub width: u32,
+    pub height: u32,
+    pub refresh: u32,
+    pub active: bool,
+}
+
+/// Stores the outputs for `list_outputs` each time the backend sets them up.
+pub fn publish(render_state: &Arc<RenderStateLock>, outputs: Vec<OutputInfo>) {
+    if let Ok(mut state) = render_state.lock() {
+        state.outputs = outputs;
+    }
+}
+
+/// Connector name as the kernel prints it, such as `HDMI-A-1` or `DP-2`.
+pub fn output_name(interface: &str, interface_id: u32) -> String {
+    format!("{interface}-{interface_id}")
+}
+
+/// Routes each output to one of its `candidates` (the CRTCs it can be routed to) that no
+/// other output and nothing in `taken` uses. This is a maximum bipartite matching, so an
+/// output only gets `None` when lighting it would darken an earlier one.
+pub fn assign_crtcs<T: Copy + PartialEq>(candidates: &[Vec<T>], taken: &[T]) -> Vec<Option<T>> {
+    let mut assigned = vec![None; candidates.len()];
+    for output in 0..candidates.len() {
+        augment(candidates, taken, output, &mut assigned, &mut Vec::new());
+    }
+    assigned
+}
+
+/// Finds `output` a free CRTC, moving outputs that hold its candidates to other candidates
+/// of theirs. Returns whether it succeeded; `visited` holds the CRTCs already tried.
+fn augment<T: Copy + PartialEq>(
+    candidates: &[Vec<T>],
+    taken: &[T],
+    output: usize,
+    assigned: &mut [Option<T>],
+    visited: &mut Vec<T>,
+) -> bool {
+    for &crtc in &candidates[output] {
+        if taken.contains(&crtc) || visited.contains(&crtc) {
+            continue;
+        }
+        visited.push(crtc);
+        let holder = assigned.iter().position(|held| *held == Some(crtc));
+        if holder.is_none_or(|holder| augment(candidates, taken, holder, assigned, visited)) {
+            assigned[output] = Some(crtc);
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crtcs_are_assigned_once_in_output_order() {
+        assert_eq!(output_name("HDMI-A", 1), "HDMI-A-1");
+
+        let candidates = vec![vec![0, 1], vec![1], vec![1, 2], vec![0]];
+        assert_eq!(
+            assign_crtcs(&candidates, &[0]),
+            vec![Some(1), None, Some(2), None]
+        );
+        assert_eq!(
+            assign_crtcs(&candidates, &[]),
+            vec![Some(0), Some(1), Some(2), None]
+        );
+        // The first panel moves to its other CRTC so the second one lights up too.
+        assert_eq!(
+            assign_crtcs(&[vec![0, 1], vec![0]], &[]),
+            vec![Some(1), Some(0)]
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use skia_safe::{ContourMeasure, ContourMeasureIter, PathBuilder};
+
+use crate::renderer::{self, ScriptOp};
+
+/// A point on a measured path and the unit tangent there.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PathSample {
+    pub x: f32,
+    pub y: f32,
+    pub tangent_x: f32,
+    pub tangent_y: f32,
+}
+
+/// Length of a path built from script ops, and points along it, for placing tick marks
+/// and labels on gauges without repeating the arc math in Elixir.
+pub struct PathMeasure {
+    contours: Vec<ContourMeasure>,
+    length: f32,
+}
+
+impl PathMeasure {
+    /// Builds the path the way the renderer would: `begin_path` starts over and ops
+    /// that do not add to the path are skipped. Transforms are not applied.
+    pub fn new(ops: &[ScriptOp]) -> Self {
+        let mut builder = PathBuilder::new();
+        for op in ops {
+            match op {
+                ScriptOp::BeginPath => builder = PathBuilder::new(),
+                op => renderer::add_path_op(&mut builder, op, renderer::SUPERELLIPSE_FLATNESS),
+            }
+        }
+        let path = builder.detach();
+        let contours: Vec<ContourMeasure> = ContourMeasureIter::new(&path, false, None).collect();
+        let length = contours.iter().map(ContourMeasure::length).sum();
+        Self { contours, length }
+    }
+
+    /// Total length of every contour.
+    pub fn length(&self) -> f32 {
+        self.length
+    }
+
+    /// Samples the path at `fraction` of its total length, clamped to `0.0..=1.0`.
+    /// Contours follow each other in the order they were added. `None` for an empty path.
+    pub fn sample(&self, fraction: f32) -> Option<PathSample> {
+        let mut distance = fraction.clamp(0.0, 1.0) * self.length;
+        let (last, leading) = self.contours.split_last()?;
+        let contour = leading
+            .iter()
+            .find(|contour| {
+                if distance <= contour.length() {
+                    return true;
+                }
+                distance -= contour.length();
+                false
+            })
+            .unwrap_or(last);
+        let (point, tangent) = contour.pos_tan(distance.min(contour.length()))?;
+        Some(PathSample {
+            x: point.x,
+            y: point.y,
+            tangent_x: tangent.x,
+            tangent_y: tangent.y,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(sample: PathSample, expected: (f32, f32, f32, f32)) -> bool {
+        let actual = (sample.x, sample.y, sample.tangent_x, sample.tangent_y);
+        [
+            (actual.0, expected.0),
+            (actual.1, expected.1),
+            (actual.2, expected.2),
+            (actual.3, expected.3),
+        ]
+        .iter()
+        .all(|(a, e)| (a - e).abs() < 1e-3)
+    }
+
+    #[test]
+    fn samples_follow_the_path_length() {
+        let ops = vec![
+            ScriptOp::BeginPath,
+            ScriptOp::MoveTo { x: 5.0, y: 5.0 },
+            ScriptOp::LineTo { x: 9.0, y: 9.0 },
+            ScriptOp::BeginPath,
+            ScriptOp::MoveTo { x: 0.0, y: 0.0 },
+            ScriptOp::LineTo { x: 100.0, y: 0.0 },
+            ScriptOp::FillColor(skia_safe::Color::RED),
+            ScriptOp::LineTo { x: 100.0, y: 100.0 },
+        ];
+        let measure = PathMeasure::new(&ops);
+        assert!((measure.length() - 200.0).abs() < 1e-3);
+        let start = measure.sample(0.0).expect("start");
+        assert!(close(start, (0.0, 0.0, 1.0, 0.0)));
+        let corner = measure.sample(0.75).expect("sample");
+        assert!(close(corner, (100.0, 50.0, 0.0, 1.0)));
+        let end = measure.sample(2.0).expect("end");
+        assert!(close(end, (100.0, 100.0, 0.0, 1.0)));
+
+        let arc = PathMeasure::new(&[ScriptOp::PathArc {
+            cx: 0.0,
+            cy: 0.0,
+            radius: 10.0,
+            start: 0.0,
+            end: std::f32::consts::PI,
+            dir: 1,
+        }]);
+        assert!((arc.length() - 10.0 * std::f32::consts::PI).abs() < 1e-2);
+        let top = arc.sample(0.5).expect("middle");
+        assert!(close(top, (0.0, 10.0, -1.0, 0.0)));
+
+        let empty = PathMeasure::new(&[ScriptOp::BeginPath]);
+        assert_eq!(empty.length(), 0.0);
+        assert_eq!(empty.sample(0.5), None);
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::renderer::ScriptOp;
+
+const MAX_PATTERNS: usize = 32;
+
+type Scripts = HashMap<String, Arc<Vec<ScriptOp>>>;
+
+/// Identifies a rasterized tile: the pattern script, the tile size in script units and
+/// the scale it was rasterized at.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PatternKey {
+    id: String,
+    width_bits: u32,
+    height_bits: u32,
+    scale_bits: u32,
+}
+
+impl PatternKey {
+    pub fn new(id: &str, width: f32, height: f32, scale: f32) -> Self {
+        Self {
+            id: id.to_string(),
+            width_bits: width.to_bits(),
+            height_bits: height.to_bits(),
+            scale_bits: scale.to_bits(),
+        }
+    }
+}
+
+/// The scripts a tile was drawn from, as they were when it was drawn. `None` for ids that
+/// were not submitted yet.
+type Dependencies = Vec<(String, Option<Arc<Vec<ScriptOp>>>)>;
+
+/// Tiles rasterized from scripts for `fill_pattern`.
+///
+/// A tile stays valid while the pattern script and every script it draws are the same
+/// submissions it was drawn from; replacing or deleting any of them redraws the tile on
+/// next use. Stale tiles are dropped when the cache fills up.
+#[derive(Debug)]
+pub struct PatternCache<T> {
+    tiles: Mutex<HashMap<PatternKey, (T, Dependencies)>>,
+}
+
+impl<T: Clone> PatternCache<T> {
+    pub fn new() -> Self {
+        Self {
+            tiles: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the tile for `key`, drawing it with `make` when it is missing or stale.
+    /// The lock is not held while `make` runs, so patterns can fill with other patterns.
+    pub fn get_or_insert_with(
+        &self,
+        key: PatternKey,
+        scripts: &Scripts,
+        make: impl FnOnce() -> Option<T>,
+    ) -> Option<T> {
+        if let Ok(tiles) = self.tiles.lock()
+            && let Some((tile, dependencies)) = tiles.get(&key)
+            && is_current(dependencies, scripts)
+        {
+            return Some(tile.clone());
+        }
+        let dependencies = dependencies(&key.id, scripts);
+        let tile = make()?;
+        if let Ok(mut tiles) = self.tiles.lock() {
+            if tiles.len() >= MAX_PATTERNS && !tiles.contains_key(&key) {
+                tiles.retain(|_, (_, dependencies)| is_current(dependencies, scripts));
+                if tiles.len() >= MAX_PATTERNS {
+                    tiles.clear();
+                }
+            }
+            tiles.insert(key, (tile.clone(), dependencies));
+        }
+        Some(tile)
+    }
+
+    /// Drops every tile, for changes the script identity check cannot see.
+    pub fn clear(&self) {
+        if let Ok(mut tiles) = self.tiles.lock() {
+            tiles.clear();
+        }
+    }
+}
+
+impl<T: Clone> Default for PatternCache<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `id` and every script it reaches through `draw_script` and `fill_pattern`.
+fn dependencies(id: &str, scripts: &Scripts) -> Dependencies {
+    let mut dependencies: Dependencies = Vec::new();
+    let mut pending = vec![id.to_string()];
+    while let Some(id) = pending.pop() {
+        if dependencies.iter().any(|(seen, _)| *seen == id) {
+            continue;
+        }
+        let ops = scripts.get(&id).cloned();
+        if let Some(ops) = &ops {
+            pending.extend(ops.iter().filter_map(|op| match op {
+                ScriptOp::DrawScript(child) | ScriptOp::FillPattern { id: child, .. } => {
+                    Some(child.clone())
+                }
+                _ => None,
+            }));
+        }
+        dependencies.push((id, ops));
+    }
+    dependencies
+}
+
+fn is_current(dependencies: &Dependencies, scripts: &Scripts) -> bool {
+    dependencies
+        .iter()
+        .all(|(id, drawn)| match (drawn, scripts.get(id)) {
+            (Some(drawn), Some(current)) => Arc::ptr_eq(drawn, current),
+            (None, None) => true,
+            _ => false,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn tiles_are_redrawn_when_a_script_they_draw_changes() {
+        let mut scripts: Scripts = HashMap::new();
+        scripts.insert(
+            "hatch".to_string(),
+            Arc::new(vec![ScriptOp::DrawScript("line".to_string())]),
+        );
+        let cache = PatternCache::new();
+        let draws = Cell::new(0);
+        let draw = |scripts: &Scripts| {
+            cache.get_or_insert_with(PatternKey::new("hatch", 8.0, 8.0, 1.0), scripts, || {
+                draws.set(draws.get() + 1);
+                Some(draws.get())
+            })
+        };
+
+        assert_eq!(draw(&scripts), Some(1));
+        assert_eq!(draw(&scripts), Some(1));
+
+        // The child script arriving, then being replaced, each invalidate the tile.
+        scripts.insert("line".to_string(), Arc::new(Vec::new()));
+        assert_eq!(draw(&scripts), Some(2));
+        assert_eq!(draw(&scripts), Some(2));
+        scripts.insert("line".to_string(), Arc::new(vec![ScriptOp::BeginPath]));
+        assert_eq!(draw(&scripts), Some(3));
+        scripts.remove("hatch");
+        assert_eq!(draw(&scripts), Some(4));
+        assert_eq!(cache.tiles.lock().expect("tiles").len(), 1);
+
+        let scaled =
+            cache.get_or_insert_with(PatternKey::new("hatch", 8.0, 8.0, 2.0), &scripts, || None);
+        assert_eq!(scaled, None);
+        assert_eq!(cache.tiles.lock().expect("tiles").len(), 1);
+        cache.clear();
+        assert_eq!(draw(&scripts), Some(5));
+    }
+}
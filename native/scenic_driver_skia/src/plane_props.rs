@@ -0,0 +1,259 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::flight_recorder::log_line;
+
+/// Plane properties the DRM backend sets itself on every commit.
+const RESERVED: &[&str] = &[
+    "FB_ID",
+    "CRTC_ID",
+    "CRTC_X",
+    "CRTC_Y",
+    "CRTC_W",
+    "CRTC_H",
+    "SRC_X",
+    "SRC_Y",
+    "SRC_W",
+    "SRC_H",
+    "IN_FENCE_FD",
+    "FB_DAMAGE_CLIPS",
+];
+
+/// Values a plane property accepts, as the kernel describes it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyKind {
+    Unsigned {
+        min: u64,
+        max: u64,
+    },
+    Signed {
+        min: i64,
+        max: i64,
+    },
+    Boolean,
+    /// Named values such as the `COLOR_RANGE` choices.
+    Enum(Vec<(String, u64)>),
+    /// Blobs, objects and bitmasks, which cannot be set from Elixir.
+    Other,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlaneProperty {
+    pub name: String,
+    pub kind: PropertyKind,
+    pub immutable: bool,
+}
+
+impl PlaneProperty {
+    /// Raw value for `value`, given as an integer or an enum name.
+    fn raw_value(&self, value: &str) -> Result<u64, String> {
+        let name = &self.name;
+        match &self.kind {
+            PropertyKind::Unsigned { min, max } => value
+                .parse::<u64>()
+                .ok()
+                .filter(|raw| (min..=max).contains(&raw))
+                .ok_or_else(|| format!("plane property {name} takes {min}..{max}, got {value}")),
+            PropertyKind::Signed { min, max } => value
+                .parse::<i64>()
+                .ok()
+                .filter(|raw| (min..=max).contains(&raw))
+                .map(|raw| raw as u64)
+                .ok_or_else(|| format!("plane property {name} takes {min}..{max}, got {value}")),
+            PropertyKind::Boolean => match value {
+                "0" | "false" => Ok(0),
+                "1" | "true" => Ok(1),
+                _ => Err(format!(
+                    "plane property {name} takes true or false, got {value}"
+                )),
+            },
+            PropertyKind::Enum(values) => values
+                .iter()
+                .find(|(label, raw)| label == value || raw.to_string() == value)
+                .map(|(_, raw)| *raw)
+                .ok_or_else(|| {
+                    let labels: Vec<&str> =
+                        values.iter().map(|(label, _)| label.as_str()).collect();
+                    format!("plane property {name} takes one of {labels:?}, got {value:?}")
+                }),
+            PropertyKind::Other => Err(format!("plane property {name} cannot be set")),
+        }
+    }
+}
+
+/// A value to add to the next atomic commit of an output's primary plane.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaneChange {
+    pub output: String,
+    pub name: String,
+    pub value: u64,
+}
+
+#[derive(Debug, Default)]
+struct Planes {
+    /// Properties of the primary plane of each output the backend drives.
+    available: HashMap<String, Vec<PlaneProperty>>,
+    /// Values set per output, reapplied after every modeset.
+    values: HashMap<String, BTreeMap<String, u64>>,
+    pending: Vec<PlaneChange>,
+}
+
+/// Plane properties such as `zpos`, `alpha`, `COLOR_ENCODING` and `COLOR_RANGE` set from
+/// Elixir on the primary plane of an output.
+///
+/// The DRM backend publishes each plane's property list when it sets its outputs up, so
+/// [`PlaneProperties::set`] can reject unknown, immutable, reserved and out-of-range values
+/// before they reach the kernel. Accepted values ride along with the next page flip and
+/// are applied again after every modeset; a value the kernel refuses is dropped.
+#[derive(Debug, Default)]
+pub struct PlaneProperties(Mutex<Planes>);
+
+impl PlaneProperties {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the property lists of all outputs after the backend set them up.
+    pub fn publish(&self, available: HashMap<String, Vec<PlaneProperty>>) {
+        if let Ok(mut planes) = self.0.lock() {
+            planes.available = available;
+        }
+    }
+
+    /// Validates `value` against the plane's property list and queues it for the next
+    /// commit. Returns the raw value.
+    pub fn set(&self, output: &str, name: &str, value: &str) -> Result<u64, String> {
+        if RESERVED.contains(&name) {
+            return Err(format!("plane property {name} is managed by the backend"));
+        }
+        let mut planes = self
+            .0
+            .lock()
+            .map_err(|_| "plane properties lock poisoned".to_string())?;
+        let property = planes
+            .available
+            .get(output)
+            .ok_or_else(|| format!("no DRM output named {output}"))?
+            .iter()
+            .find(|property| property.name == name)
+            .ok_or_else(|| format!("output {output} has no plane property {name}"))?;
+        if property.immutable {
+            return Err(format!("plane property {name} is immutable"));
+        }
+        let raw = property.raw_value(value)?;
+        planes
+            .values
+            .entry(output.to_string())
+            .or_default()
+            .insert(name.to_string(), raw);
+        planes
+            .pending
+            .retain(|change| change.output != output || change.name != name);
+        planes.pending.push(PlaneChange {
+            output: output.to_string(),
+            name: name.to_string(),
+            value: raw,
+        });
+        Ok(raw)
+    }
+
+    /// Values set for `output`, for the modeset that brings it up.
+    pub fn values(&self, output: &str) -> Vec<(String, u64)> {
+        self.0
+            .lock()
+            .ok()
+            .and_then(|planes| planes.values.get(output).cloned())
+            .map(|values| values.into_iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Changes not yet committed, removed from the queue.
+    pub fn take_pending(&self) -> Vec<PlaneChange> {
+        self.0
+            .lock()
+            .map(|mut planes| std::mem::take(&mut planes.pending))
+            .unwrap_or_default()
+    }
+
+    /// Puts changes back after a commit that has to be retried.
+    pub fn requeue(&self, changes: Vec<PlaneChange>) {
+        if let Ok(mut planes) = self.0.lock() {
+            let newer = std::mem::replace(&mut planes.pending, changes);
+            planes.pending.extend(newer);
+        }
+    }
+
+    /// Forgets changes the kernel refused, so later commits do not fail on them again.
+    pub fn reject(&self, changes: &[PlaneChange], err: &str) {
+        let Ok(mut planes) = self.0.lock() else {
+            return;
+        };
+        for change in changes {
+            log_line!(
+                "Dropping plane property {}={} on {}: {err}",
+                change.name,
+                change.value,
+                change.output
+            );
+            if let Some(values) = planes.values.get_mut(&change.output) {
+                values.remove(&change.name);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_validated_against_the_plane() {
+        let properties = PlaneProperties::new();
+        assert!(properties.set("HDMI-A-1", "zpos", "1").is_err());
+        properties.publish(HashMap::from([(
+            "HDMI-A-1".to_string(),
+            vec![
+                PlaneProperty {
+                    name: "zpos".to_string(),
+                    kind: PropertyKind::Unsigned { min: 0, max: 3 },
+                    immutable: false,
+                },
+                PlaneProperty {
+                    name: "COLOR_RANGE".to_string(),
+                    kind: PropertyKind::Enum(vec![
+                        ("YCbCr limited range".to_string(), 0),
+                        ("YCbCr full range".to_string(), 1),
+                    ]),
+                    immutable: false,
+                },
+                PlaneProperty {
+                    name: "type".to_string(),
+                    kind: PropertyKind::Enum(vec![("Primary".to_string(), 1)]),
+                    immutable: true,
+                },
+            ],
+        )]));
+
+        assert_eq!(properties.set("HDMI-A-1", "zpos", "2"), Ok(2));
+        assert!(properties.set("HDMI-A-1", "zpos", "4").is_err());
+        assert!(properties.set("HDMI-A-1", "zpos", "-1").is_err());
+        assert_eq!(
+            properties.set("HDMI-A-1", "COLOR_RANGE", "YCbCr full range"),
+            Ok(1)
+        );
+        assert!(properties.set("HDMI-A-1", "COLOR_RANGE", "full").is_err());
+        assert!(properties.set("HDMI-A-1", "type", "Primary").is_err());
+        assert!(properties.set("HDMI-A-1", "FB_ID", "1").is_err());
+        assert!(properties.set("DP-1", "zpos", "1").is_err());
+
+        assert_eq!(properties.set("HDMI-A-1", "zpos", "3"), Ok(3));
+        let pending = properties.take_pending();
+        assert_eq!(pending.len(), 2);
+        assert!(properties.take_pending().is_empty());
+        properties.requeue(pending.clone());
+        assert_eq!(properties.take_pending(), pending);
+
+        properties.reject(&pending[..1], "EINVAL");
+        assert_eq!(properties.values("HDMI-A-1"), vec![("zpos".to_string(), 3)]);
+    }
+}
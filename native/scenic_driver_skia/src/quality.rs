@@ -0,0 +1,305 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::flight_recorder::log_line;
+
+/// Frames averaged before the governor decides anything.
+const WINDOW_FRAMES: usize = 30;
+/// Consecutive windows with headroom before quality goes back up one level.
+const RESTORE_WINDOWS: u32 = 4;
+/// Frames restore quality only while they take less than this share of the budget, so
+/// the governor does not flip between two levels.
+const HEADROOM_PERCENT: u64 = 60;
+/// Thermal zones cool this far below the limit before quality comes back.
+const THERMAL_HYSTERESIS_MC: i64 = 5_000;
+const THERMAL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Render quality, from full down to the cheapest the governor goes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QualityLevel {
+    #[default]
+    Full,
+    /// Paths and shapes are drawn without anti-aliasing.
+    NoAntialias,
+    /// Also drawn at three quarters of the output size and scaled up on present.
+    ThreeQuarterScale,
+    /// Also drawn at half the output size.
+    HalfScale,
+}
+
+impl QualityLevel {
+    const LEVELS: [Self; 4] = [
+        Self::Full,
+        Self::NoAntialias,
+        Self::ThreeQuarterScale,
+        Self::HalfScale,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Full => "full",
+            Self::NoAntialias => "no_antialias",
+            Self::ThreeQuarterScale => "scale_75",
+            Self::HalfScale => "scale_50",
+        }
+    }
+
+    pub fn antialias(self) -> bool {
+        self == Self::Full
+    }
+
+    pub fn render_scale(self) -> RenderScale {
+        match self {
+            Self::Full | Self::NoAntialias => RenderScale::Full,
+            Self::ThreeQuarterScale => RenderScale::ThreeQuarters,
+            Self::HalfScale => RenderScale::Half,
+        }
+    }
+
+    fn lower(self) -> Self {
+        Self::LEVELS[(self as usize + 1).min(Self::LEVELS.len() - 1)]
+    }
+
+    fn higher(self) -> Self {
+        Self::LEVELS[(self as usize).saturating_sub(1)]
+    }
+}
+
+/// Size frames are drawn at relative to the output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderScale {
+    #[default]
+    Full,
+    ThreeQuarters,
+    Half,
+}
+
+impl RenderScale {
+    pub fn factor(self) -> f32 {
+        match self {
+            Self::Full => 1.0,
+            Self::ThreeQuarters => 0.75,
+            Self::Half => 0.5,
+        }
+    }
+}
+
+/// What the governor watches; a zero target frame time turns it off.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QualityConfig {
+    pub target_frame_us: u64,
+    /// `temp` files of `/sys/class/thermal` zones, in millidegrees Celsius.
+    pub thermal_zones: Vec<PathBuf>,
+    /// Temperature of the hottest zone above which quality drops whatever the frame times.
+    pub thermal_limit_mc: i64,
+}
+
+#[derive(Debug, Default)]
+struct GovernorState {
+    config: QualityConfig,
+    frames: Vec<u64>,
+    headroom_windows: u32,
+    downgrades: u64,
+    upgrades: u64,
+    thermal_read: Option<Instant>,
+    temperature_mc: Option<i64>,
+    hot: bool,
+}
+
+/// What `get_stats` reports about the governor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QualityStats {
+    pub level: QualityLevel,
+    pub downgrades: u64,
+    pub upgrades: u64,
+    pub temperature_mc: Option<i64>,
+}
+
+/// Lowers render quality when frames take longer than the target frame time, or a
+/// thermal zone runs hot, and raises it again once frames have headroom.
+///
+/// Frame times are averaged over windows of [`WINDOW_FRAMES`] rendered frames; a slow
+/// window lowers quality by one [`QualityLevel`], and [`RESTORE_WINDOWS`] windows in a
+/// row under [`HEADROOM_PERCENT`] of the budget raise it by one, so quality settles
+/// instead of oscillating. The renderer reads the level at the start of every frame.
+#[derive(Debug, Default)]
+pub struct QualityGovernor {
+    level: AtomicU8,
+    state: Mutex<GovernorState>,
+}
+
+impl QualityGovernor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the configuration and goes back to full quality.
+    pub fn configure(&self, config: QualityConfig) -> Result<(), String> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| "quality governor lock poisoned".to_string())?;
+        *state = GovernorState {
+            config,
+            ..GovernorState::default()
+        };
+        self.level
+            .store(QualityLevel::Full as u8, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn level(&self) -> QualityLevel {
+        QualityLevel::LEVELS[usize::from(self.level.load(Ordering::Relaxed))]
+    }
+
+    pub fn stats(&self) -> Result<QualityStats, String> {
+        let state = self
+            .state
+            .lock()
+            .map_err(|_| "quality governor lock poisoned".to_string())?;
+        Ok(QualityStats {
+            level: self.level(),
+            downgrades: state.downgrades,
+            upgrades: state.upgrades,
+            temperature_mc: state.temperature_mc,
+        })
+    }
+
+    /// Counts a rendered frame that took `frame_us`.
+    pub fn record_frame(&self, frame_us: u64) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let target = state.config.target_frame_us;
+        if target == 0 {
+            return;
+        }
+        state.frames.push(frame_us);
+        if state.frames.len() < WINDOW_FRAMES {
+            return;
+        }
+        let average = state.frames.iter().sum::<u64>() / state.frames.len() as u64;
+        state.frames.clear();
+        read_thermal(&mut state);
+
+        let level = self.level();
+        let next = if average > target || state.hot {
+            state.headroom_windows = 0;
+            level.lower()
+        } else if average * 100 < target * HEADROOM_PERCENT {
+            state.headroom_windows += 1;
+            if state.headroom_windows < RESTORE_WINDOWS {
+                return;
+            }
+            state.headroom_windows = 0;
+            level.higher()
+        } else {
+            state.headroom_windows = 0;
+            return;
+        };
+        if next == level {
+            return;
+        }
+        if next > level {
+            state.downgrades += 1;
+        } else {
+            state.upgrades += 1;
+        }
+        self.level.store(next as u8, Ordering::Relaxed);
+        let cause = if state.hot {
+            "thermal limit"
+        } else {
+            "frame times"
+        };
+        log_line!(
+            "Render quality {} -> {} ({cause}, {average} us average frame)",
+            level.name(),
+            next.name()
+        );
+    }
+}
+
+/// Reads the hottest configured zone at most once per [`THERMAL_INTERVAL`].
+fn read_thermal(state: &mut GovernorState) {
+    if state.config.thermal_zones.is_empty()
+        || state
+            .thermal_read
+            .is_some_and(|read| read.elapsed() < THERMAL_INTERVAL)
+    {
+        return;
+    }
+    state.thermal_read = Some(Instant::now());
+    state.temperature_mc = state
+        .config
+        .thermal_zones
+        .iter()
+        .filter_map(|zone| fs::read_to_string(zone).ok()?.trim().parse::<i64>().ok())
+        .max();
+    let limit = state.config.thermal_limit_mc;
+    state.hot = match state.temperature_mc {
+        Some(temperature) if state.hot => temperature > limit - THERMAL_HYSTERESIS_MC,
+        Some(temperature) => temperature > limit,
+        None => false,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quality_drops_under_load_and_heat_and_comes_back() {
+        let governor = QualityGovernor::new();
+        let frames = |frame_us: u64, windows: usize| {
+            for _ in 0..windows * WINDOW_FRAMES {
+                governor.record_frame(frame_us);
+            }
+        };
+        // Off until configured.
+        frames(100_000, 1);
+        assert_eq!(governor.level(), QualityLevel::Full);
+
+        let config = QualityConfig {
+            target_frame_us: 16_000,
+            ..QualityConfig::default()
+        };
+        governor.configure(config.clone()).expect("configure");
+        frames(20_000, 2);
+        assert_eq!(governor.level(), QualityLevel::ThreeQuarterScale);
+        assert_eq!(governor.level().render_scale().factor(), 0.75);
+        assert!(!governor.level().antialias());
+        frames(40_000, 3);
+        assert_eq!(governor.level(), QualityLevel::HalfScale);
+
+        // Within budget but without headroom, nothing changes.
+        frames(12_000, 8);
+        assert_eq!(governor.level(), QualityLevel::HalfScale);
+        frames(5_000, RESTORE_WINDOWS as usize - 1);
+        assert_eq!(governor.level(), QualityLevel::HalfScale);
+        frames(5_000, 1);
+        assert_eq!(governor.level(), QualityLevel::ThreeQuarterScale);
+        let stats = governor.stats().expect("stats");
+        assert_eq!((stats.downgrades, stats.upgrades), (3, 1));
+
+        let zone = std::env::temp_dir().join(format!("skia-thermal-{}", std::process::id()));
+        fs::write(&zone, "85000\n").expect("zone");
+        governor
+            .configure(QualityConfig {
+                thermal_zones: vec![zone.clone()],
+                thermal_limit_mc: 80_000,
+                ..config
+            })
+            .expect("configure");
+        assert_eq!(governor.level(), QualityLevel::Full);
+        frames(1_000, 1);
+        assert_eq!(governor.level(), QualityLevel::NoAntialias);
+        assert_eq!(
+            governor.stats().expect("stats").temperature_mc,
+            Some(85_000)
+        );
+        fs::remove_file(&zone).expect("remove zone");
+    }
+}
@@ -1,23 +1,262 @@
+use std::ffi::{CStr, CString};
+use std::os::raw::c_void;
+use std::ptr;
 use std::sync::{
     Arc, Mutex,
     atomic::{AtomicBool, AtomicU32, Ordering},
 };
 use std::time::Duration;
 
-use skia_safe::{AlphaType, ColorType, ImageInfo, image::CachingHint, surfaces};
+use glutin_egl_sys::egl;
+use glutin_egl_sys::egl::types::{EGLConfig, EGLContext, EGLDisplay, EGLSurface, EGLenum, EGLint};
+use libloading::Library;
+use skia_safe::{
+    AlphaType, ColorType, ImageInfo,
+    gpu::{self, Budgeted, SurfaceOrigin},
+    surfaces,
+};
 
 use crate::{
     RasterFrame,
-    renderer::{RenderState, Renderer},
+    drm_backend::load_egl,
+    flight_recorder::log_line,
+    gpu_info::GpuInfo,
+    input::{InputQueue, ViewportInfo, notify_frame_presented},
+    poll_scheduled_scripts,
+    renderer::Renderer,
+    state_lock::RenderStateLock,
 };
 
+const EGL_PLATFORM_SURFACELESS_MESA: EGLenum = 0x31DD;
+
+/// How the raster backend renders its frames.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RasterConfig {
+    pub size: Option<(u32, u32)>,
+    /// Render through a headless EGL context into a GPU render target and read frames
+    /// back, instead of rendering on the CPU.
+    pub gpu: bool,
+}
+
+/// Headless GLES context for GPU raster rendering.
+///
+/// Uses Mesa's surfaceless platform when the EGL library offers it and the default
+/// display otherwise. The context is made current without a surface where
+/// `EGL_KHR_surfaceless_context` is supported and with a 1x1 pbuffer elsewhere; Skia
+/// renders into its own framebuffer object either way.
+struct OffscreenEgl {
+    egl: egl::Egl,
+    _lib: Library,
+    display: EGLDisplay,
+    context: EGLContext,
+    pbuffer: EGLSurface,
+}
+
+impl OffscreenEgl {
+    fn new() -> Result<Self, String> {
+        let (lib, egl) = load_egl()?;
+        let display = initialize_display(&egl)?;
+        let mut offscreen = Self {
+            egl,
+            _lib: lib,
+            display,
+            context: egl::NO_CONTEXT,
+            pbuffer: egl::NO_SURFACE,
+        };
+        offscreen.create_context()?;
+        Ok(offscreen)
+    }
+
+    fn create_context(&mut self) -> Result<(), String> {
+        let egl = &self.egl;
+        if unsafe { egl.BindAPI(egl::OPENGL_ES_API) } == egl::FALSE {
+            return Err("failed to bind EGL OpenGL ES API".to_string());
+        }
+
+        let config_attribs: [EGLint; 13] = [
+            egl::SURFACE_TYPE as EGLint,
+            egl::PBUFFER_BIT as EGLint,
+            egl::RENDERABLE_TYPE as EGLint,
+            egl::OPENGL_ES2_BIT as EGLint,
+            egl::RED_SIZE as EGLint,
+            8,
+            egl::GREEN_SIZE as EGLint,
+            8,
+            egl::BLUE_SIZE as EGLint,
+            8,
+            egl::ALPHA_SIZE as EGLint,
+            8,
+            egl::NONE as EGLint,
+        ];
+        let mut config: EGLConfig = ptr::null();
+        let mut num_configs: EGLint = 0;
+        if unsafe {
+            egl.ChooseConfig(
+                self.display,
+                config_attribs.as_ptr(),
+                &mut config,
+                1,
+                &mut num_configs,
+            )
+        } == egl::FALSE
+            || num_configs == 0
+        {
+            return Err("failed to choose EGL config".to_string());
+        }
+
+        let context_attribs: [EGLint; 3] = [
+            egl::CONTEXT_CLIENT_VERSION as EGLint,
+            2,
+            egl::NONE as EGLint,
+        ];
+        self.context = unsafe {
+            egl.CreateContext(
+                self.display,
+                config,
+                egl::NO_CONTEXT,
+                context_attribs.as_ptr(),
+            )
+        };
+        if self.context == egl::NO_CONTEXT {
+            return Err("failed to create EGL context".to_string());
+        }
+
+        if !self.extensions().contains("EGL_KHR_surfaceless_context") {
+            let pbuffer_attribs: [EGLint; 5] = [
+                egl::WIDTH as EGLint,
+                1,
+                egl::HEIGHT as EGLint,
+                1,
+                egl::NONE as EGLint,
+            ];
+            self.pbuffer =
+                unsafe { egl.CreatePbufferSurface(self.display, config, pbuffer_attribs.as_ptr()) };
+            if self.pbuffer == egl::NO_SURFACE {
+                return Err("failed to create EGL pbuffer surface".to_string());
+            }
+        }
+
+        if unsafe { egl.MakeCurrent(self.display, self.pbuffer, self.pbuffer, self.context) }
+            == egl::FALSE
+        {
+            return Err("failed to make EGL context current".to_string());
+        }
+        Ok(())
+    }
+
+    fn extensions(&self) -> String {
+        let extensions = unsafe {
+            self.egl
+                .QueryString(self.display, egl::EXTENSIONS as EGLint)
+        };
+        if extensions.is_null() {
+            return String::new();
+        }
+        unsafe { CStr::from_ptr(extensions) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Creates a Skia GL context on the current EGL context and a render target for
+    /// frames of `size`.
+    fn create_renderer(&self, size: (u32, u32)) -> Result<Renderer, String> {
+        let egl = &self.egl;
+        gl::load_with(|s| unsafe {
+            let symbol = CString::new(s).expect("gl symbol");
+            egl.GetProcAddress(symbol.as_ptr()) as *const _
+        });
+
+        let interface = gpu::gl::Interface::new_load_with(|name| unsafe {
+            if name == "eglGetCurrentDisplay" {
+                return ptr::null();
+            }
+            let symbol = CString::new(name).expect("egl symbol");
+            egl.GetProcAddress(symbol.as_ptr()) as *const _
+        })
+        .ok_or_else(|| "could not create Skia GL interface".to_string())?;
+        let mut gr_context = gpu::direct_contexts::make_gl(interface, None)
+            .ok_or_else(|| "make_gl failed: could not create Skia direct context".to_string())?;
+
+        let image_info = ImageInfo::new(
+            (size.0 as i32, size.1 as i32),
+            ColorType::RGBA8888,
+            AlphaType::Premul,
+            None,
+        );
+        let surface = gpu::surfaces::render_target(
+            &mut gr_context,
+            Budgeted::Yes,
+            &image_info,
+            None,
+            SurfaceOrigin::TopLeft,
+            None,
+            false,
+            None,
+        )
+        .ok_or_else(|| format!("failed to create a {}x{} GPU render target", size.0, size.1))?;
+        Ok(Renderer::from_surface(surface, Some(gr_context)))
+    }
+}
+
+impl Drop for OffscreenEgl {
+    fn drop(&mut self) {
+        let egl = &self.egl;
+        unsafe {
+            egl.MakeCurrent(
+                self.display,
+                egl::NO_SURFACE,
+                egl::NO_SURFACE,
+                egl::NO_CONTEXT,
+            );
+            if self.pbuffer != egl::NO_SURFACE {
+                egl.DestroySurface(self.display, self.pbuffer);
+            }
+            if self.context != egl::NO_CONTEXT {
+                egl.DestroyContext(self.display, self.context);
+            }
+            egl.Terminate(self.display);
+        }
+    }
+}
+
+/// Initializes the surfaceless platform display, or the default display when the EGL
+/// library has no surfaceless platform.
+fn initialize_display(egl: &egl::Egl) -> Result<EGLDisplay, String> {
+    let initialize = |display: EGLDisplay| {
+        let mut major: EGLint = 0;
+        let mut minor: EGLint = 0;
+        display != egl::NO_DISPLAY
+            && unsafe { egl.Initialize(display, &mut major, &mut minor) } != egl::FALSE
+    };
+
+    let surfaceless = if egl.GetPlatformDisplayEXT.is_loaded() {
+        unsafe {
+            egl.GetPlatformDisplayEXT(EGL_PLATFORM_SURFACELESS_MESA, ptr::null_mut(), ptr::null())
+        }
+    } else if egl.GetPlatformDisplay.is_loaded() {
+        unsafe {
+            egl.GetPlatformDisplay(EGL_PLATFORM_SURFACELESS_MESA, ptr::null_mut(), ptr::null())
+        }
+    } else {
+        egl::NO_DISPLAY
+    };
+    if initialize(surfaceless) {
+        return Ok(surfaceless);
+    }
+
+    let display = unsafe { egl.GetDisplay(ptr::null_mut::<c_void>()) };
+    if initialize(display) {
+        return Ok(display);
+    }
+    Err("failed to initialize a headless EGL display".to_string())
+}
+
 fn store_frame(
     renderer: &mut Renderer,
     frame_slot: &Arc<Mutex<Option<RasterFrame>>>,
     size: (u32, u32),
 ) {
     let (width, height) = size;
-    let image = renderer.surface_mut().image_snapshot();
     let image_info = ImageInfo::new(
         (width as i32, height as i32),
         ColorType::RGB888x,
@@ -26,13 +265,10 @@ fn store_frame(
     );
     let row_bytes = image_info.min_row_bytes();
     let mut pixels = vec![0u8; row_bytes * height as usize];
-    let ok = image.read_pixels(
-        &image_info,
-        pixels.as_mut_slice(),
-        row_bytes,
-        (0, 0),
-        CachingHint::Disallow,
-    );
+    let ok =
+        renderer
+            .surface_mut()
+            .read_pixels(&image_info, pixels.as_mut_slice(), row_bytes, (0, 0));
     if !ok {
         return;
     }
@@ -56,43 +292,90 @@ fn store_frame(
 pub fn run(
     stop: Arc<AtomicBool>,
     dirty: Arc<AtomicBool>,
-    render_state: Arc<Mutex<RenderState>>,
+    render_state: Arc<RenderStateLock>,
     frame_slot: Arc<Mutex<Option<RasterFrame>>>,
     input_mask: Arc<AtomicU32>,
-    requested_size: Option<(u32, u32)>,
+    input_events: Arc<Mutex<InputQueue>>,
+    config: RasterConfig,
 ) {
     let _input_mask = input_mask;
-    let (width, height) = requested_size.unwrap_or((800, 600));
+    let (width, height) = config.size.unwrap_or((800, 600));
     let width = width.max(1);
     let height = height.max(1);
+    if let Ok(mut queue) = input_events.lock() {
+        queue.set_viewport(ViewportInfo {
+            width,
+            height,
+            scale: 1.0,
+        });
+    }
 
-    let image_info = ImageInfo::new(
-        (width as i32, height as i32),
-        ColorType::BGRA8888,
-        AlphaType::Premul,
-        None,
-    );
-
-    let surface =
-        surfaces::raster(&image_info, None, None).expect("Failed to create raster surface");
-
-    let mut renderer = Renderer::from_surface(surface, None);
+    let gpu = config.gpu.then(|| {
+        let offscreen = OffscreenEgl::new()?;
+        let renderer = offscreen.create_renderer((width, height))?;
+        Ok::<_, String>((offscreen, renderer))
+    });
+    let (offscreen, mut renderer) = match gpu {
+        Some(Ok((offscreen, renderer))) => {
+            let extensions = offscreen.extensions();
+            unsafe { GpuInfo::query_gl("raster") }
+                .with_egl_extensions(extensions.split_whitespace())
+                .publish(&render_state);
+            (Some(offscreen), renderer)
+        }
+        fallback => {
+            let fallback = fallback.and_then(Result::err);
+            if let Some(e) = &fallback {
+                log_line!("Offscreen GPU rendering unavailable ({e}); rendering on the CPU");
+            }
+            GpuInfo {
+                fallback,
+                ..GpuInfo::raster("raster")
+            }
+            .publish(&render_state);
+            (None, cpu_renderer((width, height)))
+        }
+    };
     if let Ok(state) = render_state.lock() {
         renderer.redraw(&state);
     }
 
     store_frame(&mut renderer, &frame_slot, (width, height));
 
+    let poll_interval = Duration::from_millis(100);
     loop {
         if stop.load(Ordering::Relaxed) {
             break;
         }
+        let (activated, next_scheduled) = poll_scheduled_scripts(&render_state);
+        if activated {
+            dirty.store(true, Ordering::Relaxed);
+        }
         if dirty.swap(false, Ordering::Relaxed) {
             if let Ok(state) = render_state.lock() {
                 renderer.redraw(&state);
             }
             store_frame(&mut renderer, &frame_slot, (width, height));
+            notify_frame_presented(&input_events, crate::monotonic_now_us());
         }
-        std::thread::sleep(Duration::from_millis(100));
+        let sleep = next_scheduled.map_or(poll_interval, |next| next.min(poll_interval));
+        std::thread::sleep(sleep);
     }
+
+    // The Skia context has to go while its EGL context still exists.
+    drop(renderer);
+    drop(offscreen);
+}
+
+fn cpu_renderer(size: (u32, u32)) -> Renderer {
+    let image_info = ImageInfo::new(
+        (size.0 as i32, size.1 as i32),
+        ColorType::BGRA8888,
+        AlphaType::Premul,
+        None,
+    );
+
+    let surface =
+        surfaces::raster(&image_info, None, None).expect("Failed to create raster surface");
+    Renderer::from_surface(surface, None)
 }
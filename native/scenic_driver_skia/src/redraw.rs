@@ -0,0 +1,149 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::Mutex;
+
+const MAX_FRAMES: usize = 64;
+
+/// Why a frame was rendered, as
+/// `%{frame: 12, timestamp_ms: .., scripts: ["_root_"], textures: [], cursor: false, ...}`.
+///
+/// `scripts` and `textures` are the ids submitted, deleted or swapped in since the previous
+/// frame. `cursor` marks a software cursor update, `resize` a resized or reconfigured
+/// output and `animation` a sprite animation tick. `other` is set when nothing was
+/// recorded, such as a compositor expose or a renderer setting change.
+/// `timestamp_ms` uses the driver's monotonic clock.
+#[derive(Clone, Debug, Default, PartialEq, rustler::NifMap)]
+pub struct RedrawReasons {
+    pub frame: u64,
+    pub timestamp_ms: u64,
+    pub scripts: Vec<String>,
+    pub textures: Vec<String>,
+    pub cursor: bool,
+    pub resize: bool,
+    pub animation: bool,
+    pub other: bool,
+}
+
+#[derive(Debug, Default)]
+struct Pending {
+    scripts: BTreeSet<String>,
+    textures: BTreeSet<String>,
+    cursor: bool,
+    resize: bool,
+    animation: bool,
+}
+
+#[derive(Debug, Default)]
+struct Frames {
+    pending: Pending,
+    count: u64,
+    recent: VecDeque<RedrawReasons>,
+}
+
+/// Collects why the renderer was woken alongside the dirty signal, and keeps the reasons
+/// of the last frames so redraw storms can be traced back to their source.
+#[derive(Debug, Default)]
+pub struct RedrawTracker(Mutex<Frames>);
+
+impl RedrawTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_script(&self, id: &str) {
+        self.update(|pending| {
+            pending.scripts.insert(id.to_string());
+        });
+    }
+
+    pub fn add_texture(&self, id: &str) {
+        self.update(|pending| {
+            pending.textures.insert(id.to_string());
+        });
+    }
+
+    pub fn add_cursor(&self) {
+        self.update(|pending| pending.cursor = true);
+    }
+
+    pub fn add_resize(&self) {
+        self.update(|pending| pending.resize = true);
+    }
+
+    pub fn add_animation(&self) {
+        self.update(|pending| pending.animation = true);
+    }
+
+    fn update(&self, apply: impl FnOnce(&mut Pending)) {
+        if let Ok(mut frames) = self.0.lock() {
+            apply(&mut frames.pending);
+        }
+    }
+
+    /// Closes the reasons collected for the frame just rendered and returns them.
+    pub fn finish_frame(&self, now_ms: u64) -> RedrawReasons {
+        let Ok(mut frames) = self.0.lock() else {
+            return RedrawReasons::default();
+        };
+        frames.count += 1;
+        let pending = std::mem::take(&mut frames.pending);
+        let other = pending.scripts.is_empty()
+            && pending.textures.is_empty()
+            && !pending.cursor
+            && !pending.resize
+            && !pending.animation;
+        let reasons = RedrawReasons {
+            frame: frames.count,
+            timestamp_ms: now_ms,
+            scripts: pending.scripts.into_iter().collect(),
+            textures: pending.textures.into_iter().collect(),
+            cursor: pending.cursor,
+            resize: pending.resize,
+            animation: pending.animation,
+            other,
+        };
+        if frames.recent.len() >= MAX_FRAMES {
+            frames.recent.pop_front();
+        }
+        frames.recent.push_back(reasons.clone());
+        reasons
+    }
+
+    /// Reasons of the last rendered frames, oldest first.
+    pub fn recent(&self) -> Vec<RedrawReasons> {
+        self.0
+            .lock()
+            .map(|frames| frames.recent.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redraw_tracker_closes_reasons_per_frame() {
+        let tracker = RedrawTracker::new();
+        tracker.add_script("b");
+        tracker.add_script("a");
+        tracker.add_script("b");
+        tracker.add_texture("camera");
+        tracker.add_animation();
+
+        let first = tracker.finish_frame(10);
+        assert_eq!(first.frame, 1);
+        assert_eq!(first.scripts, vec!["a", "b"]);
+        assert_eq!(first.textures, vec!["camera"]);
+        assert!(first.animation && !first.cursor && !first.other);
+
+        let second = tracker.finish_frame(20);
+        assert!(second.other && second.scripts.is_empty());
+        tracker.add_cursor();
+        tracker.finish_frame(30);
+
+        let recent = tracker.recent();
+        assert_eq!(recent.len(), 3);
+        assert_eq!(recent[0], first);
+        assert!(recent[2].cursor && !recent[2].other);
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use rustler::{Atom, Decoder, Encoder, Env, NifResult, Term};
+
+use crate::flight_recorder::log_line;
+use crate::renderer::{self, RenderState, ScriptOp};
+
+rustler::atoms! {
+    script,
+    image,
+    stream
+}
+
+/// An id a script depends on: another script, a static image or a stream texture.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Reference {
+    Script(String),
+    Image(String),
+    Stream(String),
+}
+
+impl Reference {
+    fn is_resolved(&self, state: &RenderState) -> bool {
+        match self {
+            Reference::Script(id) => state.scripts.contains_key(id),
+            Reference::Image(id) => renderer::static_image_meta(&state.assets, id).is_some(),
+            Reference::Stream(id) => renderer::stream_image_meta(&state.assets, id).is_some(),
+        }
+    }
+}
+
+impl std::fmt::Display for Reference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Reference::Script(id) => write!(f, "script {id:?}"),
+            Reference::Image(id) => write!(f, "image {id:?}"),
+            Reference::Stream(id) => write!(f, "stream {id:?}"),
+        }
+    }
+}
+
+/// Encodes as `{:script | :image | :stream, id}`.
+impl Encoder for Reference {
+    fn encode<'a>(&self, env: Env<'a>) -> Term<'a> {
+        match self {
+            Reference::Script(id) => (script(), id).encode(env),
+            Reference::Image(id) => (image(), id).encode(env),
+            Reference::Stream(id) => (stream(), id).encode(env),
+        }
+    }
+}
+
+/// Decodes `{:script | :image | :stream, id}`, as `submit_transaction` deletions are given.
+impl<'a> Decoder<'a> for Reference {
+    fn decode(term: Term<'a>) -> NifResult<Self> {
+        let (kind, id): (Atom, String) = term.decode()?;
+        if kind == script() {
+            Ok(Reference::Script(id))
+        } else if kind == image() {
+            Ok(Reference::Image(id))
+        } else if kind == stream() {
+            Ok(Reference::Stream(id))
+        } else {
+            Err(rustler::Error::BadArg)
+        }
+    }
+}
+
+fn references(ops: &[ScriptOp]) -> impl Iterator<Item = Reference> + '_ {
+    ops.iter().filter_map(|op| match op {
+        ScriptOp::DrawScript(id) | ScriptOp::FillPattern { id, .. } => {
+            Some(Reference::Script(id.clone()))
+        }
+        ScriptOp::FillImage(id) | ScriptOp::StrokeImage(id) => Some(Reference::Image(id.clone())),
+        ScriptOp::DrawSprites { image_id, .. }
+        | ScriptOp::DrawAnimatedSprite { image_id, .. }
+        | ScriptOp::DrawSpriteCell { image_id, .. } => Some(Reference::Image(image_id.clone())),
+        ScriptOp::FillStream(id) | ScriptOp::StrokeStream(id) => {
+            Some(Reference::Stream(id.clone()))
+        }
+        _ => None,
+    })
+}
+
+/// Checks submitted scripts for references to ids that have not been provided yet.
+///
+/// In strict mode, submits report their unresolved references. With `max_frames` set,
+/// unresolved references are also tracked while frames render and logged once if they
+/// are still missing after that many frames. Both are off by default.
+#[derive(Debug, Default)]
+pub struct ReferenceTracker {
+    strict: AtomicBool,
+    max_frames: AtomicU32,
+    pending: Mutex<HashMap<Reference, u32>>,
+}
+
+impl ReferenceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets strict mode and the frame budget (`0` disables tracking), dropping any
+    /// pending references.
+    pub fn configure(&self, strict: bool, max_frames: u32) -> Result<(), String> {
+        let mut pending = self
+            .pending
+            .lock()
+            .map_err(|_| "reference tracker lock poisoned".to_string())?;
+        pending.clear();
+        self.strict.store(strict, Ordering::Relaxed);
+        self.max_frames.store(max_frames, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Looks up the references of the stored scripts `ids`. Returns the unresolved ones in
+    /// strict mode and an empty list otherwise.
+    pub fn check<'a>(
+        &self,
+        state: &RenderState,
+        ids: impl IntoIterator<Item = &'a String>,
+    ) -> Result<Vec<Reference>, String> {
+        let strict = self.strict.load(Ordering::Relaxed);
+        let tracking = self.max_frames.load(Ordering::Relaxed) > 0;
+        if !strict && !tracking {
+            return Ok(Vec::new());
+        }
+
+        let mut unresolved: Vec<Reference> = ids
+            .into_iter()
+            .filter_map(|id| state.scripts.get(id))
+            .map(|ops| ops.as_slice())
+            .flat_map(references)
+            .filter(|reference| !reference.is_resolved(state))
+            .collect();
+        unresolved.sort();
+        unresolved.dedup();
+
+        if tracking && !unresolved.is_empty() {
+            let mut pending = self
+                .pending
+                .lock()
+                .map_err(|_| "reference tracker lock poisoned".to_string())?;
+            for reference in &unresolved {
+                pending.entry(reference.clone()).or_insert(0);
+            }
+        }
+        if !strict {
+            unresolved.clear();
+        }
+        Ok(unresolved)
+    }
+
+    /// Counts a rendered frame against the pending references, logging the ones that run
+    /// out of frames.
+    pub fn finish_frame(&self, state: &RenderState) {
+        let max_frames = self.max_frames.load(Ordering::Relaxed);
+        if max_frames == 0 {
+            return;
+        }
+        let Ok(mut pending) = self.pending.lock() else {
+            return;
+        };
+        pending.retain(|reference, frames| {
+            if reference.is_resolved(state) {
+                return false;
+            }
+            *frames += 1;
+            if *frames < max_frames {
+                return true;
+            }
+            log_line!("Referenced {reference} was not provided within {max_frames} frames");
+            false
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending_count(tracker: &ReferenceTracker) -> usize {
+        tracker.pending.lock().map(|pending| pending.len()).unwrap()
+    }
+
+    #[test]
+    fn tracker_reports_and_expires_unresolved_references() {
+        let mut state = RenderState::default();
+        state.scripts.insert(
+            "_root_".to_string(),
+            vec![
+                ScriptOp::DrawScript("menu".to_string()),
+                ScriptOp::DrawScript("menu".to_string()),
+                ScriptOp::FillStream("references_test_stream".to_string()),
+            ]
+            .into(),
+        );
+        let ids = ["_root_".to_string()];
+        let tracker = ReferenceTracker::new();
+        assert!(tracker.check(&state, &ids).expect("check").is_empty());
+
+        tracker.configure(true, 2).expect("configure");
+        let unresolved = tracker.check(&state, &ids).expect("check");
+        assert_eq!(
+            unresolved,
+            vec![
+                Reference::Script("menu".to_string()),
+                Reference::Stream("references_test_stream".to_string()),
+            ]
+        );
+        assert_eq!(pending_count(&tracker), 2);
+
+        state.scripts.insert("menu".to_string(), Default::default());
+        tracker.finish_frame(&state);
+        assert_eq!(pending_count(&tracker), 1);
+        tracker.finish_frame(&state);
+        assert_eq!(pending_count(&tracker), 0);
+
+        tracker.configure(false, 2).expect("configure");
+        assert!(tracker.check(&state, &ids).expect("check").is_empty());
+        assert_eq!(pending_count(&tracker), 1);
+    }
+}
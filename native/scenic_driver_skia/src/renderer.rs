@@ -1,15 +1,56 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::sync::{Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
 use skia_safe::{
-    AlphaType, ClipOp, Color, ColorType, Data, FilterMode, Font, FontMgr, FontStyle, Image,
-    ImageInfo, Matrix, MipmapMode, Paint, PaintCap, PaintJoin, PaintStyle, PathBuilder,
-    PathDirection, Point, RRect, Rect, SamplingOptions, Shader, Surface, TileMode, Typeface,
-    Vector,
-    canvas::SrcRectConstraint,
+    AlphaType, BlendMode, ClipOp, Color, ColorSpace, ColorType, Data, FilterMode, Font,
+    FontMetrics, FontMgr, FontStyle, IRect, ISize, Image, ImageFilter, ImageInfo, Matrix,
+    MipmapMode, Paint, PaintCap, PaintJoin, PaintStyle, PathBuilder, PathDirection,
+    PictureRecorder, Point, RRect, Rect, RuntimeEffect, SamplingOptions, Shader, Surface, TextBlob,
+    TileMode, Typeface, Vector,
+    canvas::{SaveLayerRec, SrcRectConstraint},
+    color_filters,
     gpu::{self, SurfaceOrigin, backend_render_targets, gl::FramebufferInfo},
-    images,
+    image_filters, images,
+    runtime_effect::ChildPtr,
+    surfaces,
+    utils::text_utils,
 };
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::checkpoint::Checkpoints;
+use crate::clock::RenderClock;
+use crate::cursor::{CursorImageSlot, CursorRegions};
+use crate::dev_overlay::{self, DevOverlay, PointerTracker};
+use crate::digest::{self, Digest};
+use crate::flight_recorder::{FlightRecorder, log_line};
+use crate::focus::{self, FocusRegions};
+use crate::font_fallback::{self, FontFallback};
+use crate::frame_capture::{CapturedFrame, FrameCapture};
+use crate::frame_timing::{FrameTimes, FrameTiming};
+use crate::geometry_guard::GeometryGuard;
+use crate::gpu_info::GpuInfo;
+use crate::hw_decode;
+use crate::input_transform::Rotation;
+use crate::op_stats::{OpCounts, OpStats};
+use crate::outputs::OutputInfo;
+use crate::pattern_cache::{PatternCache, PatternKey};
+use crate::plane_props::PlaneProperties;
+use crate::quality::{QualityGovernor, RenderScale};
+use crate::redraw::RedrawTracker;
+use crate::references::ReferenceTracker;
+use crate::script_cache::ScriptCache;
+use crate::script_limits::ScriptGuard;
+use crate::stats_export::{self, FrameStats, StatsExport};
+use crate::stream_frames::{DropPolicy, StreamCounts, StreamFrames};
+use crate::text_cache::{TextBlobCache, TextRunKey};
+use crate::text_fallback::TextFallback;
+use crate::text_layout::{TextLayout, TextWrap};
+use crate::virtual_pointer::{self, VirtualPointer};
+use crate::watchdog::{FrameWatchdog, ScriptTimings};
+use crate::watermark::{self, Watermark, WatermarkTarget};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum ScriptOp {
@@ -30,6 +71,7 @@ pub enum ScriptOp {
     FillColor(Color),
     StrokeColor(Color),
     StrokeWidth(f32),
+    NonScalingStroke(bool),
     FillLinear {
         start_x: f32,
         start_y: f32,
@@ -37,6 +79,7 @@ pub enum ScriptOp {
         end_y: f32,
         start_color: Color,
         end_color: Color,
+        dither: GradientDither,
     },
     FillRadial {
         center_x: f32,
@@ -45,6 +88,7 @@ pub enum ScriptOp {
         outer_radius: f32,
         start_color: Color,
         end_color: Color,
+        dither: GradientDither,
     },
     StrokeLinear {
         start_x: f32,
@@ -53,6 +97,7 @@ pub enum ScriptOp {
         end_y: f32,
         start_color: Color,
         end_color: Color,
+        dither: GradientDither,
     },
     StrokeRadial {
         center_x: f32,
@@ -61,15 +106,58 @@ pub enum ScriptOp {
         outer_radius: f32,
         start_color: Color,
         end_color: Color,
+        dither: GradientDither,
+    },
+    /// Fills with a gradient through `stops`, `(position, color)` pairs with positions
+    /// rising from 0 to 1 along `shape`.
+    FillGradient {
+        shape: GradientShape,
+        stops: Vec<(f32, Color)>,
+        dither: GradientDither,
     },
     FillImage(String),
     FillStream(String),
+    /// Fills with the script `id` drawn into a `width` x `height` tile and repeated.
+    FillPattern {
+        id: String,
+        width: f32,
+        height: f32,
+    },
+    StrokeGradient {
+        shape: GradientShape,
+        stops: Vec<(f32, Color)>,
+        dither: GradientDither,
+    },
     StrokeImage(String),
     StrokeStream(String),
     StrokeCap(PaintCap),
     StrokeJoin(PaintJoin),
     StrokeMiterLimit(f32),
+    /// Draws a shadow of the fills and strokes that follow, offset by `dx`, `dy` and
+    /// blurred by `sigma`, until the enclosing `pop_state`. A zero sigma and offset end it.
+    DropShadow {
+        dx: f32,
+        dy: f32,
+        sigma: f32,
+        color: Color,
+    },
+    /// Blurs the fills and strokes that follow by `sigma` until the enclosing `pop_state`.
+    /// A sigma of zero ends the blur.
+    Blur {
+        sigma: f32,
+    },
     ClipPath(ClipOp),
+    /// Starts an offscreen layer composited with `alpha` and `blend` on `Restore`.
+    /// A zero width or height leaves the layer unbounded.
+    SaveLayer {
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        alpha: u8,
+        blend: BlendMode,
+    },
+    Restore,
     Scissor {
         width: f32,
         height: f32,
@@ -134,6 +222,12 @@ pub enum ScriptOp {
         height: f32,
         radius: f32,
     },
+    /// Superellipse `|x/a|^n + |y/b|^n = 1` filling the `width` x `height` box at the origin.
+    PathSuperellipse {
+        width: f32,
+        height: f32,
+        exponent: f32,
+    },
     PathSector {
         radius: f32,
         radians: f32,
@@ -219,18 +313,166 @@ pub enum ScriptOp {
         ll_radius: f32,
         flag: u16,
     },
+    DrawSquircle {
+        width: f32,
+        height: f32,
+        exponent: f32,
+        flag: u16,
+    },
+    /// Regular polygon centered at the origin with its first vertex straight up.
+    DrawPolygon {
+        sides: u32,
+        radius: f32,
+        rounding: f32,
+        flag: u16,
+    },
+    /// Star centered at the origin, alternating `outer` and `inner` radius vertices.
+    DrawStar {
+        points: u32,
+        outer: f32,
+        inner: f32,
+        rounding: f32,
+        flag: u16,
+    },
     DrawSprites {
         image_id: String,
         cmds: Vec<SpriteCommand>,
     },
+    /// Draws the frame of `frames` that is current on the driver clock into `dst`.
+    DrawAnimatedSprite {
+        image_id: String,
+        dst: Rect,
+        frames: Vec<SpriteFrame>,
+    },
+    /// Draws one cell of an atlas split into `columns` by `rows` equal cells into `dst`. The
+    /// cell is the script's parameter set with `set_script_param`, counted row by row.
+    DrawSpriteCell {
+        image_id: String,
+        dst: Rect,
+        columns: u16,
+        rows: u16,
+    },
+    /// Hides the following ops during every other half of `period_ms` on the driver
+    /// clock, until the enclosing `pop_state`. A period of zero shows them steadily.
+    Blink {
+        period_ms: u32,
+    },
+    /// Scrolls `draw_text` runs that follow through a window `width` wide starting at
+    /// the origin, at `speed` pixels per second. A width of zero ends the marquee.
+    Marquee {
+        width: f32,
+        speed: f32,
+    },
     DrawText(String),
+    /// Appends the outlines of `text` in the current font to the active path.
+    TextPath(String),
+    /// Draws `text` cut at a grapheme boundary with a trailing ellipsis when it is wider
+    /// than `max_width`.
+    DrawTextEllipsized {
+        text: String,
+        max_width: f32,
+    },
+    DrawNumber(String),
     Font(String),
     FontSize(f32),
     TextAlign(TextAlign),
     TextBase(TextBase),
+    /// Widest line of laid out text; zero only breaks lines at `\n`.
+    TextWidth(f32),
+    /// Baseline distance of laid out text as a multiple of the font size.
+    LineHeight(f32),
+    /// Turns multi-line layout of `draw_text` on or off.
+    TextWrap(TextWrap),
     DrawScript(String),
 }
 
+impl ScriptOp {
+    /// Scenic name of the op, used as the key for op usage stats.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScriptOp::PushState => "push_state",
+            ScriptOp::PopState => "pop_state",
+            ScriptOp::PopPushState => "pop_push_state",
+            ScriptOp::Translate(..) => "translate",
+            ScriptOp::Rotate(..) => "rotate",
+            ScriptOp::Scale(..) => "scale",
+            ScriptOp::Transform { .. } => "transform",
+            ScriptOp::FillColor(..) => "fill_color",
+            ScriptOp::StrokeColor(..) => "stroke_color",
+            ScriptOp::StrokeWidth(..) => "stroke_width",
+            ScriptOp::NonScalingStroke(..) => "non_scaling_stroke",
+            ScriptOp::FillLinear { .. } => "fill_linear",
+            ScriptOp::FillRadial { .. } => "fill_radial",
+            ScriptOp::StrokeLinear { .. } => "stroke_linear",
+            ScriptOp::StrokeRadial { .. } => "stroke_radial",
+            ScriptOp::FillGradient { .. } => "fill_gradient",
+            ScriptOp::StrokeGradient { .. } => "stroke_gradient",
+            ScriptOp::FillImage(..) => "fill_image",
+            ScriptOp::FillStream(..) => "fill_stream",
+            ScriptOp::FillPattern { .. } => "fill_pattern",
+            ScriptOp::StrokeImage(..) => "stroke_image",
+            ScriptOp::StrokeStream(..) => "stroke_stream",
+            ScriptOp::StrokeCap(..) => "cap",
+            ScriptOp::StrokeJoin(..) => "join",
+            ScriptOp::StrokeMiterLimit(..) => "miter_limit",
+            ScriptOp::DropShadow { .. } => "drop_shadow",
+            ScriptOp::Blur { .. } => "blur",
+            ScriptOp::ClipPath(..) => "clip_path",
+            ScriptOp::SaveLayer { .. } => "save_layer",
+            ScriptOp::Restore => "restore",
+            ScriptOp::Scissor { .. } => "scissor",
+            ScriptOp::BeginPath => "begin_path",
+            ScriptOp::ClosePath => "close_path",
+            ScriptOp::FillPath => "fill_path",
+            ScriptOp::StrokePath => "stroke_path",
+            ScriptOp::MoveTo { .. } => "move_to",
+            ScriptOp::LineTo { .. } => "line_to",
+            ScriptOp::ArcTo { .. } => "arc_to",
+            ScriptOp::BezierTo { .. } => "bezier_to",
+            ScriptOp::QuadraticTo { .. } => "quadratic_to",
+            ScriptOp::PathTriangle { .. } => "triangle",
+            ScriptOp::PathQuad { .. } => "quad",
+            ScriptOp::PathRect { .. } => "rect",
+            ScriptOp::PathRRect { .. } => "rrect",
+            ScriptOp::PathSuperellipse { .. } => "superellipse",
+            ScriptOp::PathSector { .. } => "sector",
+            ScriptOp::PathCircle { .. } => "circle",
+            ScriptOp::PathEllipse { .. } => "ellipse",
+            ScriptOp::PathArc { .. } => "arc",
+            ScriptOp::DrawLine { .. } => "draw_line",
+            ScriptOp::DrawTriangle { .. } => "draw_triangle",
+            ScriptOp::DrawQuad { .. } => "draw_quad",
+            ScriptOp::DrawCircle { .. } => "draw_circle",
+            ScriptOp::DrawEllipse { .. } => "draw_ellipse",
+            ScriptOp::DrawArc { .. } => "draw_arc",
+            ScriptOp::DrawSector { .. } => "draw_sector",
+            ScriptOp::DrawRect { .. } => "draw_rect",
+            ScriptOp::DrawRRect { .. } => "draw_rrect",
+            ScriptOp::DrawRRectV { .. } => "draw_rrectv",
+            ScriptOp::DrawSquircle { .. } => "draw_squircle",
+            ScriptOp::DrawPolygon { .. } => "draw_polygon",
+            ScriptOp::DrawStar { .. } => "draw_star",
+            ScriptOp::DrawSprites { .. } => "draw_sprites",
+            ScriptOp::DrawAnimatedSprite { .. } => "draw_animated_sprite",
+            ScriptOp::DrawSpriteCell { .. } => "draw_sprite_cell",
+            ScriptOp::Blink { .. } => "blink",
+            ScriptOp::Marquee { .. } => "marquee",
+            ScriptOp::DrawText(..) => "draw_text",
+            ScriptOp::TextPath(..) => "text_path",
+            ScriptOp::DrawTextEllipsized { .. } => "draw_text_ellipsized",
+            ScriptOp::DrawNumber(..) => "draw_number",
+            ScriptOp::Font(..) => "font",
+            ScriptOp::FontSize(..) => "font_size",
+            ScriptOp::TextAlign(..) => "text_align",
+            ScriptOp::TextBase(..) => "text_base",
+            ScriptOp::TextWidth(..) => "text_width",
+            ScriptOp::LineHeight(..) => "line_height",
+            ScriptOp::TextWrap(..) => "text_wrap",
+            ScriptOp::DrawScript(..) => "draw_script",
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct SpriteCommand {
     pub sx: f32,
@@ -244,25 +486,1055 @@ pub struct SpriteCommand {
     pub alpha: f32,
 }
 
+/// One frame of an animated sprite: a source rect in the atlas and how long it shows.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpriteFrame {
+    pub src: Rect,
+    pub duration_ms: u32,
+}
+
+/// Source rect of cell `index` of an image split into `columns` by `rows` cells. Indexes
+/// past the last cell wrap around, so a frame counter can be passed as is.
+fn sprite_cell(size: ISize, columns: u16, rows: u16, index: u32) -> Option<Rect> {
+    let cells = u32::from(columns) * u32::from(rows);
+    if cells == 0 || size.is_empty() {
+        return None;
+    }
+    let index = index % cells;
+    let width = size.width as f32 / f32::from(columns);
+    let height = size.height as f32 / f32::from(rows);
+    let column = (index % u32::from(columns)) as f32;
+    let row = (index / u32::from(columns)) as f32;
+    Some(Rect::from_xywh(column * width, row * height, width, height))
+}
+
+/// Returns the index of the frame showing at `now_ms` and when the next one starts.
+///
+/// Animations loop from the start of the driver clock, so sprites with the same frames
+/// stay in step. Returns `None` for an empty animation; a single frame or zero total
+/// duration never advances.
+fn current_sprite_frame(frames: &[SpriteFrame], now_ms: u64) -> Option<(usize, Option<u64>)> {
+    let total: u64 = frames.iter().map(|frame| frame.duration_ms as u64).sum();
+    if frames.len() < 2 || total == 0 {
+        return (!frames.is_empty()).then_some((0, None));
+    }
+    let phase = now_ms % total;
+    let mut end = 0;
+    for (index, frame) in frames.iter().enumerate() {
+        end += frame.duration_ms as u64;
+        if phase < end {
+            return Some((index, Some(now_ms + end - phase)));
+        }
+    }
+    None
+}
+
+/// Returns whether ops under a `blink` op show at `now_ms` and when that next changes.
+///
+/// Like sprite animations, blinks run from the start of the driver clock, so every
+/// blink with the same period is in step. Periods shorter than 2 ms never hide.
+fn blink_phase(period_ms: u32, now_ms: u64) -> Option<(bool, u64)> {
+    let half = u64::from(period_ms / 2);
+    if half == 0 {
+        return None;
+    }
+    let phase = now_ms / half;
+    Some((phase % 2 == 0, (phase + 1) * half))
+}
+
+/// Frame interval a scrolling marquee asks backends for.
+const MARQUEE_FRAME_MS: u64 = 16;
+
+/// Text window set by a `marquee` op: runs enter at the right edge of a window `width`
+/// wide, leave at the left edge and start over; negative speeds scroll the other way.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Marquee {
+    width: f32,
+    speed: f32,
+}
+
+impl Marquee {
+    /// Left edge of a run `text_width` wide at `now_ms`, relative to the window.
+    fn offset(self, text_width: f32, now_ms: u64) -> f32 {
+        let cycle = f64::from(self.width + text_width);
+        if cycle <= 0.0 || self.speed == 0.0 {
+            return 0.0;
+        }
+        let travelled = (now_ms as f64 * f64::from(self.speed.abs()) / 1000.0) % cycle;
+        if self.speed > 0.0 {
+            self.width - travelled as f32
+        } else {
+            travelled as f32 - text_width
+        }
+    }
+}
+
+/// Earliest time an animated sprite drawn in the last frame changes, so backends can
+/// schedule a redraw. Reset at the start of every frame.
+#[derive(Debug)]
+pub struct AnimationClock(AtomicU64);
+
+impl Default for AnimationClock {
+    fn default() -> Self {
+        Self(AtomicU64::new(u64::MAX))
+    }
+}
+
+impl AnimationClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn begin_frame(&self) {
+        self.0.store(u64::MAX, Ordering::Relaxed);
+    }
+
+    fn schedule(&self, due_ms: u64) {
+        self.0.fetch_min(due_ms, Ordering::Relaxed);
+    }
+
+    /// When the next animation frame is due, if any sprite is animating.
+    pub fn next_due(&self) -> Option<u64> {
+        let due = self.0.load(Ordering::Relaxed);
+        (due != u64::MAX).then_some(due)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct RenderState {
     pub clear_color: Color,
-    pub scripts: HashMap<String, Vec<ScriptOp>>,
+    /// Parsed scripts by id. Ops are shared, so checkpoints copy the map cheaply.
+    pub scripts: HashMap<String, Arc<Vec<ScriptOp>>>,
+    /// Matrices concatenated before a script's ops wherever it is drawn, so a cached script
+    /// can be moved or scaled without being submitted again.
+    pub script_transforms: HashMap<String, Matrix>,
+    /// Cell indexes `draw_sprite_cell` ops of a script draw, so animated icons step
+    /// through their sprite sheet without the script being submitted again.
+    pub script_params: HashMap<String, u32>,
     pub root_id: Option<String>,
+    /// Script drawn as the root of a named output instead of `root_id`.
+    pub output_roots: HashMap<String, String>,
+    /// Outputs the DRM backend found when it last set them up.
+    pub outputs: Vec<OutputInfo>,
+    pub overscan: Overscan,
+    /// Clockwise rotation of the scene on the panel, applied by pre-rotating every frame
+    /// so surfaces keep the panel's native orientation.
+    pub output_rotation: Rotation,
+    pub assets: Arc<AssetStore>,
+    pub scheduled: Vec<ScheduledScript>,
+    pub scheduled_textures: Vec<ScheduledTexture>,
+    pub gradient_dither: GradientDither,
+    /// Draws every `draw_script` child with a copy of the caller's draw state inside a
+    /// canvas save/restore, so state the child leaves behind never leaks back.
+    pub isolate_scripts: bool,
+    /// Accessibility switch: animated sprites hold their first frame, blinks stay visible,
+    /// marquees stop scrolling and pointer paths jump between waypoints instead of gliding.
+    pub reduced_motion: bool,
+    /// Composites frames in linear light on an intermediate surface and encodes them to
+    /// sRGB on present, so anti-aliased edges and alpha blends keep their brightness.
+    pub linear_blending: bool,
+    /// Rasterizes frames with Skia's CPU backend when the GL driver turns out to be a
+    /// software rasterizer such as llvmpipe, which is faster than Skia's GL path on it.
+    /// GL only copies the finished frame to the output.
+    pub raster_on_software_gl: bool,
+    pub watchdog: Arc<FrameWatchdog>,
+    pub op_stats: Arc<OpStats>,
+    /// Per-frame op, `draw_script` expansion and text limits.
+    pub script_guard: Arc<ScriptGuard>,
+    /// Clamps or rejects NaN, infinite, negative and oversized geometry at parse time.
+    pub geometry: Arc<GeometryGuard>,
+    pub references: Arc<ReferenceTracker>,
+    /// Ops drawn over the scene on the next frame only, then dropped.
+    pub immediate: Arc<Mutex<Arc<Vec<ScriptOp>>>>,
+    /// Typeface for text drawn without a `font` op; `None` uses the system sans font.
+    pub default_typeface: Option<Typeface>,
+    pub default_font_size: f32,
+    pub flight_recorder: Arc<FlightRecorder>,
+    /// Frames read back for `capture_frame`.
+    pub frame_capture: Arc<FrameCapture>,
+    /// Plane property values set with `set_plane_property`, applied by the DRM backend.
+    pub plane_properties: Arc<PlaneProperties>,
+    /// Set by the backend once its renderer has been created.
+    pub gpu_info: Option<GpuInfo>,
+    pub damage: Arc<DamageTracker>,
+    pub placements: Arc<ScriptPlacements>,
+    pub animations: Arc<AnimationClock>,
+    /// Time animations and scheduled updates follow; see [`RenderClock`].
+    pub clock: Arc<RenderClock>,
+    /// Lowers anti-aliasing and render scale while frames miss their target time.
+    pub quality: Arc<QualityGovernor>,
+    /// Parse, draw and flush times of the last frame, for `get_render_stats`.
+    pub frame_timing: Arc<FrameTiming>,
+    /// Parsed scripts by content, persisted for the next start.
+    pub script_cache: Arc<ScriptCache>,
+    pub cursor_regions: Arc<CursorRegions>,
+    pub cursor_image: Arc<CursorImageSlot>,
+    pub redraws: Arc<RedrawTracker>,
+    pub checkpoints: Checkpoints,
+    pub blank: Arc<DisplayBlank>,
+    /// Development grid, rulers and pointer readout drawn over everything else.
+    pub dev_overlay: Option<DevOverlay>,
+    /// Text stamped into every frame or only into captures, after everything else.
+    pub watermark: Option<Watermark>,
+    /// Regions `focus_next` moves between, and the ring drawn around the focused one.
+    pub focus: FocusRegions,
+    pub pointer: Arc<PointerTracker>,
+    pub text_fallback: Arc<TextFallback>,
+    /// Typefaces tried for characters the drawing font has no glyph for.
+    pub font_fallback: Arc<FontFallback>,
+    /// Tiles drawn from scripts for `fill_pattern`.
+    pub patterns: Arc<PatternCache<Image>>,
+    /// Per-frame statistics streamed to a Unix socket for remote profiling.
+    pub stats_export: Arc<StatsExport>,
+    /// Cursor moved by `play_pointer_path`, drawn over the dev overlay.
+    pub virtual_pointer: Arc<VirtualPointer>,
+}
+
+/// Dithering applied to gradient paints to hide banding on 8-bit panels.
+///
+/// `Paint` sets Skia's paint dither flag; `Noise` additionally adds a small
+/// SkSL noise term to the gradient for panels where the flag is not enough.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GradientDither {
+    #[default]
+    Off,
+    Paint,
+    Noise,
+}
+
+impl GradientDither {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "off" => Ok(Self::Off),
+            "paint" => Ok(Self::Paint),
+            "noise" => Ok(Self::Noise),
+            _ => Err(format!("unsupported gradient dither mode: {value}")),
+        }
+    }
+
+    /// Decodes the per-gradient opcode flag: `0x02` selects noise, `0x01` the paint flag.
+    pub fn from_flag(flag: u16) -> Self {
+        if flag & 0x02 != 0 {
+            Self::Noise
+        } else if flag & 0x01 != 0 {
+            Self::Paint
+        } else {
+            Self::Off
+        }
+    }
+}
+
+/// Where the stops of a multi-stop gradient are laid out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientShape {
+    Linear {
+        start_x: f32,
+        start_y: f32,
+        end_x: f32,
+        end_y: f32,
+    },
+    Radial {
+        center_x: f32,
+        center_y: f32,
+        inner_radius: f32,
+        outer_radius: f32,
+    },
+    /// Sweeps the stops clockwise around the center, starting `start_angle` radians from
+    /// the positive x axis.
+    Conic {
+        center_x: f32,
+        center_y: f32,
+        start_angle: f32,
+    },
+}
+
+/// A parsed script waiting for its activation time on the driver's monotonic clock.
+#[derive(Clone, Debug)]
+pub struct ScheduledScript {
+    pub due_ms: u64,
+    pub id: String,
+    pub ops: Arc<Vec<ScriptOp>>,
+}
+
+/// A decoded stream texture waiting for its activation time. It is swapped in under the
+/// same render state lock as scripts due at the same time, so both land in one frame.
+#[derive(Clone, Debug)]
+pub struct ScheduledTexture {
+    pub due_ms: u64,
+    pub id: String,
+    pub image: Image,
+    pub meta: ImageMeta,
+}
+
+/// Area of the scene that changed since the last frame, in root coordinates.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Damage {
+    /// Nothing was reported; a redraw repaints everything.
+    #[default]
+    Clean,
+    Partial(Rect),
+    Full,
+}
+
+/// Collects damage between frames. Script submits with a bounds hint add their rect;
+/// every other change damages the whole output. The renderer takes the damage once
+/// per frame.
+#[derive(Debug)]
+pub struct DamageTracker(Mutex<Damage>);
+
+impl Default for DamageTracker {
+    fn default() -> Self {
+        Self(Mutex::new(Damage::Full))
+    }
+}
+
+impl DamageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_full(&self) {
+        if let Ok(mut damage) = self.0.lock() {
+            *damage = Damage::Full;
+        }
+    }
+
+    pub fn add_rect(&self, rect: Rect) {
+        if let Ok(mut damage) = self.0.lock() {
+            *damage = match *damage {
+                Damage::Clean => Damage::Partial(rect),
+                Damage::Partial(current) => Damage::Partial(Rect::join2(current, rect)),
+                Damage::Full => Damage::Full,
+            };
+        }
+    }
+
+    pub fn take(&self) -> Damage {
+        self.0
+            .lock()
+            .map(|mut damage| std::mem::take(&mut *damage))
+            .unwrap_or(Damage::Full)
+    }
+}
+
+/// Placements kept per script; scripts drawn more often are damaged as a whole.
+const MAX_PLACEMENTS: usize = 16;
+/// Recording area for measuring scripts. Ops reaching its edge, such as a full-canvas
+/// fill, are taken to cover everything.
+const MEASURE_BOUNDS: Rect = Rect::new(-1e7, -1e7, 1e7, 1e7);
+
+/// Where a script was drawn: the matrix from its coordinates to root coordinates, before
+/// its own transform, and the draw state it started with.
+#[derive(Clone)]
+struct Placement {
+    matrix: Matrix,
+    draw_state: DrawState,
+}
+
+/// Placements by script id. `None` marks scripts whose placements are not known, such as
+/// scripts drawn into pattern tiles, whose changes damage everything.
+type Placed = HashMap<String, Option<Vec<Placement>>>;
+
+#[derive(Default)]
+struct PlacementLog {
+    /// Placements of the frame being drawn; `None` outside of `Renderer::redraw`.
+    drawing: Option<Placed>,
+    /// Maps device coordinates of the frame being drawn to root coordinates.
+    device_to_root: Matrix,
+    /// Pattern tiles being drawn, which put their scripts on the output only indirectly.
+    tiles: usize,
+    /// Placements of the last complete frame; `None` before the first one.
+    last: Option<Placed>,
+    measuring: bool,
+}
+
+/// Where each script was drawn in the last frame, so changing a script without a bounds
+/// hint damages only the area it covered before and after the change.
+///
+/// `Renderer::redraw` records a placement whenever a script is drawn on the output;
+/// [`script_extent`] replays a script at its placements into a picture recorder and takes
+/// the bounds of what it drew.
+#[derive(Default)]
+pub struct ScriptPlacements(Mutex<PlacementLog>);
+
+impl std::fmt::Debug for ScriptPlacements {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScriptPlacements").finish_non_exhaustive()
+    }
+}
+
+impl ScriptPlacements {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn begin_frame(&self, root_matrix: &Matrix) {
+        if let Ok(mut log) = self.0.lock() {
+            log.drawing = Some(HashMap::new());
+            log.device_to_root = root_matrix.invert().unwrap_or_default();
+            log.tiles = 0;
+        }
+    }
+
+    /// Keeps the frame's placements, or forgets all placements when the frame was cut
+    /// short and some scripts were not drawn where they belong.
+    fn finish_frame(&self, complete: bool) {
+        if let Ok(mut log) = self.0.lock() {
+            let drawing = log.drawing.take();
+            log.last = drawing.filter(|_| complete);
+        }
+    }
+
+    fn record(&self, id: &str, canvas: &skia_safe::Canvas, draw_state: &DrawState) {
+        let Ok(mut log) = self.0.lock() else {
+            return;
+        };
+        let PlacementLog {
+            drawing: Some(drawing),
+            device_to_root,
+            tiles,
+            ..
+        } = &mut *log
+        else {
+            return;
+        };
+        let known = *tiles == 0;
+        let placed = drawing
+            .entry(id.to_string())
+            .or_insert_with(|| Some(Vec::new()));
+        match placed {
+            Some(placements) if known && placements.len() < MAX_PLACEMENTS => {
+                placements.push(Placement {
+                    matrix: Matrix::concat(device_to_root, &canvas.local_to_device_as_3x3()),
+                    draw_state: draw_state.isolated(),
+                })
+            }
+            _ => *placed = None,
+        }
+    }
+
+    fn enter_tile(&self) {
+        if let Ok(mut log) = self.0.lock() {
+            log.tiles += 1;
+        }
+    }
+
+    fn leave_tile(&self) {
+        if let Ok(mut log) = self.0.lock() {
+            log.tiles = log.tiles.saturating_sub(1);
+        }
+    }
+
+    /// Placements of `id` in the last frame; `None` when they are not known. Scripts
+    /// that were not drawn have none, except roots, which are drawn once they exist.
+    fn of(&self, id: &str, root: bool) -> Option<Vec<Placement>> {
+        let log = self.0.lock().ok()?;
+        match log.last.as_ref()?.get(id) {
+            Some(placed) => placed.clone(),
+            None => (!root).then(Vec::new),
+        }
+    }
+
+    /// Forgets the placements of `ids`, whose caller changed and may draw them elsewhere.
+    fn forget<'a>(&self, ids: impl IntoIterator<Item = &'a String>) {
+        if let Ok(mut log) = self.0.lock()
+            && let Some(last) = log.last.as_mut()
+        {
+            for id in ids {
+                last.insert(id.clone(), None);
+            }
+        }
+    }
+
+    /// Set while scripts are replayed for their bounds, so the replay leaves stream
+    /// frames and animation timers alone.
+    fn measuring(&self) -> bool {
+        self.0.lock().is_ok_and(|log| log.measuring)
+    }
+
+    fn set_measuring(&self, measuring: bool) {
+        if let Ok(mut log) = self.0.lock() {
+            log.measuring = measuring;
+        }
+    }
+}
+
+/// Root-coordinate bounds of what script `id` drew in the last frame, replayed as the
+/// script and the scripts it draws are now. `None` when that is not known and the whole
+/// output has to be treated as changed.
+///
+/// Calling this before and after changing a script gives the damage of the change; the
+/// scripts the changed one draws may move with it, so their placements are forgotten.
+pub fn script_extent(render_state: &RenderState, id: &str) -> Option<Rect> {
+    let root = render_state.root_id.as_deref() == Some(id)
+        || render_state.output_roots.values().any(|root| root == id);
+    let placements = render_state.placements.of(id, root);
+    let Some(ops) = render_state.scripts.get(id) else {
+        return placements.map(|_| Rect::new_empty());
+    };
+    render_state
+        .placements
+        .forget(drawn_scripts(id, &render_state.scripts).iter());
+    if !render_state.isolate_scripts && !keeps_caller_state(ops) {
+        return None;
+    }
+    placements?
+        .iter()
+        .try_fold(Rect::new_empty(), |extent, placement| {
+            let bounds = measure_script(render_state, id, ops, placement)?;
+            Some(Rect::join2(extent, bounds))
+        })
+}
+
+/// Replays `ops` of script `id` at `placement` and returns the bounds of what they drew.
+fn measure_script(
+    render_state: &RenderState,
+    id: &str,
+    ops: &[ScriptOp],
+    placement: &Placement,
+) -> Option<Rect> {
+    let mut recorder = PictureRecorder::new();
+    let canvas = recorder.begin_recording(MEASURE_BOUNDS, true);
+    // Matrix changes outside a save block are bounded by the whole recording, so the
+    // replay happens inside one.
+    let save_count = canvas.save();
+    canvas.concat(&placement.matrix);
+    if let Some(transform) = render_state.script_transforms.get(id) {
+        canvas.concat(transform);
+    }
+    let mut draw_state = placement.draw_state.clone();
+    let mut op_counts = OpCounts::with_limits(render_state.script_guard.limits());
+    render_state.placements.set_measuring(true);
+    draw_ops(
+        render_state,
+        ops,
+        canvas,
+        &mut draw_state,
+        &mut vec![id.to_string()],
+        &mut ScriptTimings::new(false),
+        &mut op_counts,
+    );
+    canvas.restore_to_count(save_count);
+    render_state.placements.set_measuring(false);
+    if op_counts.budget().exceeded().is_some() {
+        return None;
+    }
+    let bounds = recorder.finish_recording_as_picture(None)?.cull_rect();
+    let unbounded = bounds.left <= MEASURE_BOUNDS.left
+        || bounds.top <= MEASURE_BOUNDS.top
+        || bounds.right >= MEASURE_BOUNDS.right
+        || bounds.bottom >= MEASURE_BOUNDS.bottom;
+    (!unbounded).then_some(bounds)
+}
+
+/// Every script `id` reaches through `draw_script` and `fill_pattern`, without `id`.
+fn drawn_scripts(id: &str, scripts: &HashMap<String, Arc<Vec<ScriptOp>>>) -> Vec<String> {
+    let mut drawn: Vec<String> = Vec::new();
+    let mut pending = vec![id.to_string()];
+    while let Some(next) = pending.pop() {
+        let Some(ops) = scripts.get(&next) else {
+            continue;
+        };
+        for op in ops.iter() {
+            if let ScriptOp::DrawScript(child) | ScriptOp::FillPattern { id: child, .. } = op
+                && child != id
+                && !drawn.contains(child)
+            {
+                drawn.push(child.clone());
+                pending.push(child.clone());
+            }
+        }
+    }
+    drawn
+}
+
+/// Whether `ops` change the draw state and canvas only between a push and its pop.
+/// Without script isolation, anything else changes how the caller draws after them.
+fn keeps_caller_state(ops: &[ScriptOp]) -> bool {
+    let mut depth = 0usize;
+    let balanced = ops.iter().all(|op| match op {
+        ScriptOp::PushState | ScriptOp::SaveLayer { .. } => {
+            depth += 1;
+            true
+        }
+        ScriptOp::PopState | ScriptOp::Restore => {
+            depth = depth.saturating_sub(1);
+            true
+        }
+        _ if depth > 0 => true,
+        ScriptOp::FillPath | ScriptOp::StrokePath | ScriptOp::DrawScript(..) => true,
+        op => op.name().starts_with("draw_"),
+    });
+    balanced && depth == 0
+}
+
+/// Solid color presented in place of the scene by `clear_display`.
+///
+/// A held blank covers every frame until released; otherwise it covers the next frame
+/// only and the scene returns with the next redraw.
+#[derive(Debug, Default)]
+pub struct DisplayBlank(Mutex<Option<(Color, bool)>>);
+
+impl DisplayBlank {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, color: Color, hold: bool) {
+        if let Ok(mut blank) = self.0.lock() {
+            *blank = Some((color, hold));
+        }
+    }
+
+    /// Drops the blank; returns whether one was set.
+    pub fn release(&self) -> bool {
+        self.0
+            .lock()
+            .map(|mut blank| blank.take().is_some())
+            .unwrap_or(false)
+    }
+
+    /// Color of a held blank, without consuming one-shot blanks.
+    fn held(&self) -> Option<Color> {
+        let blank = self.0.lock().ok()?;
+        blank.and_then(|(color, hold)| hold.then_some(color))
+    }
+
+    /// Color covering the frame being rendered. One-shot blanks are consumed.
+    fn take_for_frame(&self) -> Option<Color> {
+        let mut blank = self.0.lock().ok()?;
+        let (color, hold) = (*blank)?;
+        if !hold {
+            *blank = None;
+        }
+        Some(color)
+    }
+}
+
+/// Safe-area margins that shrink the scene to fit inside an overscanned display.
+///
+/// Margins are in logical pixels, or in percent of the viewport when `percent` is set.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Overscan {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub percent: bool,
+}
+
+impl Overscan {
+    pub fn is_zero(&self) -> bool {
+        self.left == 0.0 && self.top == 0.0 && self.right == 0.0 && self.bottom == 0.0
+    }
+
+    /// Returns `(translate_x, translate_y, scale_x, scale_y)` for a viewport of `size`.
+    pub fn transform(&self, size: (f32, f32)) -> (f32, f32, f32, f32) {
+        let (width, height) = size;
+        if self.is_zero() || width <= 0.0 || height <= 0.0 {
+            return (0.0, 0.0, 1.0, 1.0);
+        }
+        let (left, top, right, bottom) = if self.percent {
+            (
+                self.left * width / 100.0,
+                self.top * height / 100.0,
+                self.right * width / 100.0,
+                self.bottom * height / 100.0,
+            )
+        } else {
+            (self.left, self.top, self.right, self.bottom)
+        };
+        let inner_w = (width - left - right).max(1.0);
+        let inner_h = (height - top - bottom).max(1.0);
+        (left, top, inner_w / width, inner_h / height)
+    }
+
+    /// Maps a point on the display back into scene coordinates.
+    pub fn to_content(&self, size: (f32, f32), x: f32, y: f32) -> (f32, f32) {
+        let (tx, ty, sx, sy) = self.transform(size);
+        ((x - tx) / sx, (y - ty) / sy)
+    }
+}
+
+/// Size and source format of an uploaded image or stream texture.
+///
+/// `format` is the detected container (`png`, `jpeg`, ...) for encoded files and the
+/// raw pixel layout (`g`, `ga`, `rgb`, `rgba`) for bitmaps.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageMeta {
+    pub width: u32,
+    pub height: u32,
+    pub format: String,
+    pub has_alpha: bool,
+}
+
+struct CachedImage {
+    image: Image,
+    meta: ImageMeta,
+    blob: Option<BlobKey>,
+}
+
+/// Content key for deduplicated static images: the SHA-256 digest of the encoded bytes.
+type BlobKey = Digest;
+
+/// A decoded image shared by every static image id uploaded with the same bytes.
+struct SharedBlob {
+    image: Image,
+    meta: ImageMeta,
+    refs: usize,
+}
+
+/// Decoded images, stream textures and typefaces used during replay.
+///
+/// Renderers use the process-wide store unless a dedicated store is attached. Stores hold
+/// CPU-side images; each GPU context instantiates its own textures from them on first draw,
+/// so a store can be shared between renderers running on different contexts.
+pub struct AssetStore {
+    images: Mutex<HashMap<String, CachedImage>>,
+    streams: Mutex<HashMap<String, StreamFrames<CachedImage>>>,
+    stream_policies: Mutex<HashMap<String, DropPolicy>>,
+    fonts: Mutex<HashMap<String, Typeface>>,
+    text_runs: TextBlobCache,
+    blobs: Mutex<HashMap<BlobKey, SharedBlob>>,
+    texture_options: Mutex<HashMap<String, TextureOptions>>,
+    uploads: Mutex<HashMap<(UploadKind, String), Digest>>,
+    dedup: AtomicBool,
+    shared: bool,
+}
+
+/// The asset maps a shared store tracks upload digests for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UploadKind {
+    Image,
+    Font,
+}
+
+impl std::fmt::Debug for AssetStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let count = |len: Option<usize>| len.unwrap_or(0);
+        f.debug_struct("AssetStore")
+            .field("images", &count(self.images.lock().ok().map(|m| m.len())))
+            .field("streams", &count(self.streams.lock().ok().map(|m| m.len())))
+            .field("fonts", &count(self.fonts.lock().ok().map(|m| m.len())))
+            .field("blobs", &count(self.blobs.lock().ok().map(|m| m.len())))
+            .field("dedup", &self.is_dedup())
+            .field("shared", &self.shared)
+            .finish()
+    }
+}
+
+impl AssetStore {
+    pub fn new(shared: bool) -> Self {
+        Self {
+            images: Mutex::new(HashMap::new()),
+            streams: Mutex::new(HashMap::new()),
+            stream_policies: Mutex::new(HashMap::new()),
+            fonts: Mutex::new(HashMap::new()),
+            text_runs: TextBlobCache::new(),
+            blobs: Mutex::new(HashMap::new()),
+            texture_options: Mutex::new(HashMap::new()),
+            uploads: Mutex::new(HashMap::new()),
+            dedup: AtomicBool::new(false),
+            shared,
+        }
+    }
+
+    pub fn global() -> Arc<AssetStore> {
+        static GLOBAL_STORE: OnceLock<Arc<AssetStore>> = OnceLock::new();
+        Arc::clone(GLOBAL_STORE.get_or_init(|| Arc::new(AssetStore::new(false))))
+    }
+
+    /// Shared stores skip re-decoding bytes that another renderer already uploaded to an
+    /// id; see [`AssetStore::is_current_upload`].
+    pub fn is_shared(&self) -> bool {
+        self.shared
+    }
+
+    /// With dedup enabled, static images uploaded with identical bytes share one decoded
+    /// image, which is freed once the last id referring to it is removed.
+    pub fn is_dedup(&self) -> bool {
+        self.dedup.load(Ordering::Relaxed)
+    }
+
+    pub fn set_dedup(&self, enabled: bool) {
+        self.dedup.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Options applied when texture `id` is next uploaded.
+    pub fn texture_options(&self, id: &str) -> TextureOptions {
+        self.texture_options
+            .lock()
+            .ok()
+            .and_then(|options| options.get(id).copied())
+            .unwrap_or_default()
+    }
+
+    pub fn set_texture_options(&self, id: &str, options: TextureOptions) {
+        if let Ok(mut all) = self.texture_options.lock() {
+            if options == TextureOptions::default() {
+                all.remove(id);
+            } else {
+                all.insert(id.to_string(), options);
+            }
+        }
+    }
+
+    /// Drop policy for frames of stream texture `id`, kept across deletes like texture
+    /// options.
+    pub fn set_stream_policy(&self, id: &str, policy: DropPolicy) {
+        if let Ok(mut policies) = self.stream_policies.lock() {
+            if policy == DropPolicy::default() {
+                policies.remove(id);
+            } else {
+                policies.insert(id.to_string(), policy);
+            }
+        }
+        if let Ok(mut streams) = self.streams.lock()
+            && let Some(frames) = streams.get_mut(id)
+        {
+            frames.set_policy(policy);
+        }
+    }
+
+    fn stream_policy(&self, id: &str) -> DropPolicy {
+        self.stream_policies
+            .lock()
+            .ok()
+            .and_then(|policies| policies.get(id).copied())
+            .unwrap_or_default()
+    }
+
+    /// Frame counts and waiting frames of every live stream texture, by id.
+    pub fn stream_counts(&self) -> Result<HashMap<String, (StreamCounts, usize)>, String> {
+        let streams = self
+            .streams
+            .lock()
+            .map_err(|_| "stream texture lock poisoned".to_string())?;
+        Ok(streams
+            .iter()
+            .map(|(id, frames)| (id.clone(), (frames.counts(), frames.pending())))
+            .collect())
+    }
+
+    /// Moves queued stream frames on screen whose predecessor has been drawn. Returns
+    /// `true` when any stream changed.
+    fn advance_streams(&self) -> bool {
+        let Ok(mut streams) = self.streams.lock() else {
+            return false;
+        };
+        streams
+            .values_mut()
+            .fold(false, |changed, frames| frames.advance() || changed)
+    }
+
+    fn streams_waiting(&self) -> bool {
+        self.streams
+            .lock()
+            .is_ok_and(|streams| streams.values().any(StreamFrames::can_advance))
+    }
+
+    #[cfg(test)]
+    pub fn blob_count(&self) -> usize {
+        self.blobs.lock().map(|blobs| blobs.len()).unwrap_or(0)
+    }
+
+    pub fn has_font(&self, id: &str) -> bool {
+        self.fonts
+            .lock()
+            .map(|fonts| fonts.contains_key(id))
+            .unwrap_or(false)
+    }
+
+    /// Whether image or font `id` is loaded from bytes with `digest`, so uploading them
+    /// again would change nothing.
+    pub fn is_current_upload(&self, kind: UploadKind, id: &str, digest: &Digest) -> bool {
+        let loaded = match kind {
+            UploadKind::Image => static_image_meta(self, id).is_some(),
+            UploadKind::Font => self.has_font(id),
+        };
+        loaded
+            && self
+                .uploads
+                .lock()
+                .map(|uploads| uploads.get(&(kind, id.to_string())) == Some(digest))
+                .unwrap_or(false)
+    }
+
+    /// Records the digest of the bytes just loaded into image or font `id`.
+    pub fn record_upload(&self, kind: UploadKind, id: &str, digest: Digest) {
+        if let Ok(mut uploads) = self.uploads.lock() {
+            uploads.insert((kind, id.to_string()), digest);
+        }
+    }
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        Self {
+            clear_color: Color::WHITE,
+            scripts: HashMap::new(),
+            script_transforms: HashMap::new(),
+            script_params: HashMap::new(),
+            root_id: None,
+            output_roots: HashMap::new(),
+            outputs: Vec::new(),
+            overscan: Overscan::default(),
+            output_rotation: Rotation::None,
+            assets: AssetStore::global(),
+            scheduled: Vec::new(),
+            scheduled_textures: Vec::new(),
+            gradient_dither: GradientDither::Off,
+            isolate_scripts: false,
+            reduced_motion: false,
+            linear_blending: false,
+            raster_on_software_gl: false,
+            watchdog: Arc::new(FrameWatchdog::new()),
+            op_stats: Arc::new(OpStats::new()),
+            script_guard: Arc::new(ScriptGuard::new()),
+            geometry: Arc::new(GeometryGuard::new()),
+            references: Arc::new(ReferenceTracker::new()),
+            immediate: Arc::new(Mutex::new(Arc::default())),
+            default_typeface: None,
+            default_font_size: DrawState::DEFAULT_FONT_SIZE,
+            flight_recorder: Arc::new(FlightRecorder::new()),
+            frame_capture: Arc::new(FrameCapture::new()),
+            plane_properties: Arc::new(PlaneProperties::new()),
+            gpu_info: None,
+            damage: Arc::new(DamageTracker::new()),
+            placements: Arc::new(ScriptPlacements::new()),
+            animations: Arc::new(AnimationClock::new()),
+            clock: Arc::new(RenderClock::new()),
+            quality: Arc::new(QualityGovernor::new()),
+            frame_timing: Arc::new(FrameTiming::new()),
+            script_cache: Arc::new(ScriptCache::new()),
+            cursor_regions: Arc::new(CursorRegions::new()),
+            cursor_image: Arc::new(CursorImageSlot::new()),
+            redraws: Arc::new(RedrawTracker::new()),
+            checkpoints: Checkpoints::default(),
+            blank: Arc::new(DisplayBlank::new()),
+            dev_overlay: None,
+            watermark: None,
+            focus: FocusRegions::new(),
+            pointer: Arc::new(PointerTracker::new()),
+            text_fallback: Arc::new(TextFallback::new()),
+            font_fallback: Arc::new(FontFallback::default()),
+            patterns: Arc::new(PatternCache::new()),
+            stats_export: Arc::new(StatsExport::new()),
+            virtual_pointer: Arc::new(VirtualPointer::new()),
+        }
+    }
+}
+
+/// Maps a scene laid out for the rotated output onto a surface of `panel_size` in the
+/// panel's native orientation. Drawing rotated into an unrotated surface is what mobile
+/// GPUs such as Mali and Adreno expect; their display engines often lack plane rotation,
+/// and scanning out a rotated swapchain costs them a copy every frame.
+fn pre_rotation(rotation: Rotation, panel_size: (f32, f32)) -> Matrix {
+    let (width, height) = panel_size;
+    let (translate, degrees) = match rotation {
+        Rotation::None => ((0.0, 0.0), 0.0),
+        Rotation::Cw90 => ((width, 0.0), 90.0),
+        Rotation::Cw180 => ((width, height), 180.0),
+        Rotation::Cw270 => ((0.0, height), 270.0),
+    };
+    let mut matrix = Matrix::translate(translate);
+    matrix.pre_rotate(degrees, None);
+    matrix
+}
+
+/// The watermark stamped into every frame, if one is set.
+fn frame_watermark(render_state: &RenderState) -> Option<&Watermark> {
+    render_state
+        .watermark
+        .as_ref()
+        .filter(|watermark| watermark.target == WatermarkTarget::Frames)
+}
+
+/// Stamps `watermark` over a `size` canvas holding an output frame, upright in scene units
+/// whatever the output rotation.
+fn stamp_watermark(
+    canvas: &skia_safe::Canvas,
+    size: (i32, i32),
+    scale_factor: f32,
+    render_state: &RenderState,
+    watermark: &Watermark,
+) {
+    let font = match watermark
+        .typeface
+        .clone()
+        .or_else(|| render_state.default_typeface.clone())
+    {
+        Some(typeface) => Some(Font::new(typeface, watermark.size)),
+        None => default_font(watermark.size),
+    };
+    let Some(font) = font else {
+        return;
+    };
+    let panel_size = (size.0 as f32 / scale_factor, size.1 as f32 / scale_factor);
+    let rotation = render_state.output_rotation;
+    let (width, height) = rotation.rotate_size(panel_size);
+    let save_count = canvas.save();
+    canvas.scale((scale_factor, scale_factor));
+    if rotation != Rotation::None {
+        canvas.concat(&pre_rotation(rotation, panel_size));
+    }
+    let text = watermark.current_text();
+    watermark::draw(
+        canvas,
+        watermark,
+        Rect::from_wh(width, height),
+        &font,
+        &text,
+    );
+    canvas.restore_to_count(save_count);
 }
 
-static IMAGE_CACHE: OnceLock<Mutex<HashMap<String, Image>>> = OnceLock::new();
-static STREAM_CACHE: OnceLock<Mutex<HashMap<String, Image>>> = OnceLock::new();
-static FONT_CACHE: OnceLock<Mutex<HashMap<String, Typeface>>> = OnceLock::new();
+/// How frames are drawn when they do not go straight into the output surface.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Intermediate {
+    /// Blend in linear light and encode to sRGB on the copy.
+    linear: bool,
+    /// Rasterize on the CPU, for GL contexts that are themselves software rasterizers.
+    cpu: bool,
+    /// Draw smaller than the output and scale up on the copy, as the quality governor asks.
+    scale: RenderScale,
+}
 
-impl Default for RenderState {
-    fn default() -> Self {
-        Self {
-            clear_color: Color::WHITE,
-            scripts: HashMap::new(),
-            root_id: None,
-        }
+/// Copies a frame drawn into an intermediate surface onto the output. Linear-light frames
+/// are encoded to sRGB on the way: outputs carry no color space, so Skia leaves the
+/// linear values alone and the color filter does the encoding. Frames drawn at a reduced
+/// scale are stretched over the output with linear filtering.
+fn copy_intermediate(
+    frame: &mut Surface,
+    target: &mut Surface,
+    clip: Option<IRect>,
+    kind: Intermediate,
+) {
+    let frame = frame.image_snapshot();
+    let mut paint = Paint::default();
+    paint.set_blend_mode(BlendMode::Src);
+    if kind.linear {
+        paint.set_color_filter(color_filters::linear_to_srgb_gamma());
+    }
+    let bounds = Rect::from_iwh(target.width(), target.height());
+    let canvas = target.canvas();
+    canvas.save();
+    canvas.reset_matrix();
+    if let Some(clip) = clip {
+        canvas.clip_irect(clip, ClipOp::Intersect);
+    }
+    if kind.scale == RenderScale::Full {
+        canvas.draw_image(&frame, (0, 0), Some(&paint));
+    } else {
+        canvas.draw_image_rect_with_sampling_options(
+            &frame,
+            None,
+            bounds,
+            SamplingOptions::new(FilterMode::Linear, MipmapMode::None),
+            &paint,
+        );
     }
+    canvas.restore();
 }
 
 fn create_skia_surface(
@@ -293,6 +1565,8 @@ pub enum SurfaceSource {
         num_samples: usize,
         stencil_size: usize,
     },
+    /// Swapchain images handed in by the Vulkan backend before each frame.
+    Vulkan,
     Raster,
 }
 
@@ -301,6 +1575,20 @@ pub struct Renderer {
     gr_context: Option<skia_safe::gpu::DirectContext>,
     source: SurfaceSource,
     scale_factor: f32,
+    scene_snapshot: Option<Image>,
+    /// Set when the next frame must repaint everything even if only part was damaged.
+    full_redraw: bool,
+    /// What [`Renderer::frame_damage`] reports for the last frame.
+    frame_damage: Option<IRect>,
+    /// What the current intermediate surface was created for.
+    intermediate_kind: Intermediate,
+    /// Surface frames are drawn into before being copied to `surface`; `None` while
+    /// frames go straight to the output or no intermediate surface could be created.
+    intermediate: Option<Surface>,
+    /// Output this renderer draws, for picking its root from `output_roots`.
+    output: Option<String>,
+    /// Set for outputs driven next to the primary one, which skip the input overlays.
+    secondary: bool,
 }
 
 impl Renderer {
@@ -329,6 +1617,13 @@ impl Renderer {
                 stencil_size,
             },
             scale_factor: 1.0,
+            scene_snapshot: None,
+            full_redraw: true,
+            frame_damage: None,
+            intermediate_kind: Intermediate::default(),
+            intermediate: None,
+            output: None,
+            secondary: false,
         }
     }
 
@@ -341,9 +1636,46 @@ impl Renderer {
             gr_context,
             source: SurfaceSource::Raster,
             scale_factor: 1.0,
+            scene_snapshot: None,
+            full_redraw: true,
+            frame_damage: None,
+            intermediate_kind: Intermediate::default(),
+            intermediate: None,
+            output: None,
+            secondary: false,
+        }
+    }
+
+    /// Renderer on a Skia Vulkan context, drawing into `surface` until the backend hands
+    /// in the next swapchain image with [`Renderer::set_target`].
+    pub fn new_vulkan(surface: Surface, gr_context: skia_safe::gpu::DirectContext) -> Self {
+        Self {
+            surface,
+            gr_context: Some(gr_context),
+            source: SurfaceSource::Vulkan,
+            scale_factor: 1.0,
+            scene_snapshot: None,
+            full_redraw: true,
+            frame_damage: None,
+            intermediate_kind: Intermediate::default(),
+            intermediate: None,
+            output: None,
+            secondary: false,
         }
     }
 
+    /// Points the renderer at the swapchain image the next frame is drawn into.
+    pub fn set_target(&mut self, surface: Surface) {
+        self.surface = surface;
+    }
+
+    /// Names the output this renderer draws. Secondary outputs draw the scene without
+    /// the dev overlay and virtual pointer, which belong to the output taking input.
+    pub fn set_output(&mut self, name: &str, secondary: bool) {
+        self.output = Some(name.to_string());
+        self.secondary = secondary;
+    }
+
     pub fn set_scale_factor(&mut self, scale_factor: f32) {
         self.scale_factor = scale_factor.max(0.1);
     }
@@ -352,17 +1684,259 @@ impl Renderer {
         &mut self.surface
     }
 
-    pub fn redraw(&mut self, render_state: &RenderState) {
-        let canvas = self.surface.canvas();
-        canvas.clear(render_state.clear_color);
+    /// Keeps a copy of the rendered scene so overlays such as a software cursor can be
+    /// redrawn over it without replaying the scripts.
+    pub fn snapshot_scene(&mut self) {
+        self.scene_snapshot = Some(self.surface.image_snapshot());
+    }
 
+    /// Draws the last scene snapshot back onto the surface. Returns `false` when no
+    /// snapshot is available and the scene has to be redrawn instead.
+    pub fn restore_scene(&mut self) -> bool {
+        let Some(snapshot) = self.scene_snapshot.as_ref() else {
+            return false;
+        };
+        let canvas = self.surface.canvas();
         canvas.save();
+        canvas.reset_matrix();
+        canvas.draw_image(snapshot, (0, 0), None);
+        canvas.restore();
+        true
+    }
+
+    pub fn flush(&mut self) {
+        if let Some(gr) = self.gr_context.as_mut() {
+            gr.flush_and_submit();
+        }
+    }
+
+    pub fn redraw(&mut self, render_state: &RenderState) {
+        if let Some(color) = render_state.blank.take_for_frame() {
+            self.present_blank(render_state, color);
+            return;
+        }
+        let started = Instant::now();
+        let capture = render_state.frame_capture.pending();
+        let exporting = render_state.stats_export.is_enabled();
+        let mut timings =
+            ScriptTimings::new(render_state.watchdog.deadline().is_some() || exporting);
+        let limits = render_state.script_guard.limits();
+        let mut op_counts = OpCounts::with_limits(limits);
+
+        let immediate = render_state
+            .immediate
+            .lock()
+            .map(|mut ops| std::mem::take(&mut *ops))
+            .unwrap_or_default();
+        if render_state.assets.advance_streams() {
+            render_state.damage.add_full();
+        }
+        self.prepare_intermediate(self.intermediate_for(render_state));
+        let root_matrix = self.root_matrix(render_state);
+        let damage = render_state.damage.take();
+        render_state.animations.begin_frame();
+        self.frame_damage = self.damaged_region(damage, &root_matrix, !immediate.is_empty());
+        let stamp = frame_watermark(render_state);
+        if stamp.is_some() {
+            // The stamp is drawn unclipped over the last frame, so frames are repainted whole.
+            self.frame_damage = None;
+        }
+        // Only raster surfaces keep the previous frame; GL surfaces repaint everything and
+        // pass the damage on to the compositor instead.
+        let clip = self
+            .frame_damage
+            .filter(|_| matches!(self.source, SurfaceSource::Raster));
+        render_state.placements.begin_frame(&root_matrix);
+        let mut flush = self.draw_frame(
+            render_state,
+            &root_matrix,
+            clip,
+            &immediate,
+            &mut timings,
+            &mut op_counts,
+        );
+        if render_state.assets.streams_waiting() {
+            // Queued stream frames are drawn one per frame; ask for the next one now.
+            render_state
+                .animations
+                .schedule(render_state.clock.now_ms());
+        }
+        if stamp.is_some_and(Watermark::ticks) {
+            render_state
+                .animations
+                .schedule(render_state.clock.now_ms() + watermark::until_next_second_ms());
+        }
+        if self.text_upload_failed(render_state, &op_counts) {
+            // Repaint before the frame is presented, now with text rasterized on the CPU.
+            log_line!("Glyph upload failed, drawing text on the CPU");
+            let mut retry_counts = OpCounts::with_limits(limits);
+            render_state.placements.begin_frame(&root_matrix);
+            flush += self.draw_frame(
+                render_state,
+                &root_matrix,
+                None,
+                &immediate,
+                &mut timings,
+                &mut retry_counts,
+            );
+            self.frame_damage = None;
+        }
+        render_state.text_fallback.finish_frame();
+        render_state
+            .placements
+            .finish_frame(op_counts.budget().exceeded().is_none());
+
+        let elapsed = started.elapsed();
+        render_state.watchdog.finish_frame(elapsed, &timings);
+        render_state
+            .quality
+            .record_frame(elapsed.as_micros().min(u64::MAX as u128) as u64);
+        let times = FrameTimes {
+            frame: elapsed,
+            flush,
+            ops: op_counts.total(),
+        };
+        render_state
+            .frame_timing
+            .finish_frame(times, crate::monotonic_now_us());
+        render_state.op_stats.record_drawn(&op_counts);
+        render_state.script_guard.finish_frame(op_counts.budget());
+        render_state.references.finish_frame(render_state);
+        let reasons = render_state.redraws.finish_frame(crate::monotonic_now_ms());
+        let (text_cache_hits, text_cache_misses) = render_state.assets.text_runs.take_counts();
+        if exporting {
+            render_state.stats_export.publish(&FrameStats {
+                frame: reasons.frame,
+                timestamp_us: crate::monotonic_now_us(),
+                frame_us: elapsed.as_micros().min(u64::MAX as u128) as u64,
+                ops: op_counts.total(),
+                scripts: timings.scripts() as u64,
+                text_cache_hits,
+                text_cache_misses,
+                slowest: timings
+                    .slowest(stats_export::REPORTED_SCRIPTS)
+                    .into_iter()
+                    .map(|(id, time)| (id, time.as_micros().min(u64::MAX as u128) as u64))
+                    .collect(),
+            });
+        }
+        if render_state.flight_recorder.is_enabled() {
+            self.record_flight_frame(&render_state.flight_recorder);
+        }
+        if let Some(ticket) = capture {
+            self.capture_frame(render_state, ticket);
+        }
+    }
+
+    /// Draws the frame on an output driven next to the primary one, after
+    /// [`Renderer::redraw`] drew the primary output. That call already took the frame's
+    /// damage, immediate ops and stream updates and recorded its stats, so this one only
+    /// repaints the scene.
+    pub fn redraw_output(&mut self, render_state: &RenderState) {
+        if let Some(color) = render_state.blank.held() {
+            self.surface.canvas().clear(color);
+            self.flush();
+            return;
+        }
+        let mut timings = ScriptTimings::new(false);
+        let mut op_counts = OpCounts::with_limits(render_state.script_guard.limits());
+        self.prepare_intermediate(self.intermediate_for(render_state));
+        let root_matrix = self.root_matrix(render_state);
+        self.draw_frame(
+            render_state,
+            &root_matrix,
+            None,
+            &[],
+            &mut timings,
+            &mut op_counts,
+        );
+    }
+
+    /// Device scale, output rotation, overscan and render scale applied to the root script.
+    fn root_matrix(&self, render_state: &RenderState) -> Matrix {
+        let panel_size = (
+            self.surface.width() as f32 / self.scale_factor,
+            self.surface.height() as f32 / self.scale_factor,
+        );
+        let rotation = render_state.output_rotation;
+        let logical_size = rotation.rotate_size(panel_size);
+        let mut root_matrix = Matrix::new_identity();
         if (self.scale_factor - 1.0).abs() > f32::EPSILON {
-            canvas.scale((self.scale_factor, self.scale_factor));
+            root_matrix.pre_scale((self.scale_factor, self.scale_factor), None);
+        }
+        if rotation != Rotation::None {
+            root_matrix.pre_concat(&pre_rotation(rotation, panel_size));
+        }
+        if !render_state.overscan.is_zero() {
+            let (tx, ty, sx, sy) = render_state.overscan.transform(logical_size);
+            root_matrix.pre_translate((tx, ty));
+            root_matrix.pre_scale((sx, sy), None);
+        }
+        let render_scale = self.render_scale().factor();
+        if render_scale < 1.0 {
+            root_matrix.post_scale((render_scale, render_scale), None);
+        }
+        root_matrix
+    }
+
+    /// Scale frames are drawn at; only reduced while a scaled intermediate surface exists.
+    fn render_scale(&self) -> RenderScale {
+        if self.intermediate.is_some() {
+            self.intermediate_kind.scale
+        } else {
+            RenderScale::Full
+        }
+    }
+
+    fn intermediate_for(&self, render_state: &RenderState) -> Intermediate {
+        let software_gl = render_state
+            .gpu_info
+            .as_ref()
+            .is_some_and(|info| info.software);
+        Intermediate {
+            linear: render_state.linear_blending,
+            cpu: render_state.raster_on_software_gl && software_gl && self.gr_context.is_some(),
+            scale: render_state.quality.level().render_scale(),
+        }
+    }
+
+    /// Script drawn as the root of this renderer's output.
+    fn output_root<'a>(&self, render_state: &'a RenderState) -> Option<&'a String> {
+        self.output
+            .as_ref()
+            .and_then(|name| render_state.output_roots.get(name))
+            .or(render_state.root_id.as_ref())
+    }
+
+    /// Draws scripts, immediate ops and the dev overlay, then flushes the GPU context.
+    /// Returns the time the flush took.
+    fn draw_frame(
+        &mut self,
+        render_state: &RenderState,
+        root_matrix: &Matrix,
+        clip: Option<IRect>,
+        immediate: &[ScriptOp],
+        timings: &mut ScriptTimings,
+        op_counts: &mut OpCounts,
+    ) -> Duration {
+        let root_id = self.output_root(render_state).cloned();
+        let secondary = self.secondary;
+        let mut intermediate = self.intermediate.take();
+        let canvas = match intermediate.as_mut() {
+            Some(intermediate) => intermediate.canvas(),
+            None => self.surface.canvas(),
+        };
+        let frame_save_count = canvas.save();
+        if let Some(clip) = clip {
+            canvas.clip_irect(clip, ClipOp::Intersect);
         }
+        canvas.clear(render_state.clear_color);
+
+        canvas.save();
+        canvas.concat(root_matrix);
 
-        if let Some(root_id) = render_state.root_id.clone() {
-            let mut draw_state = DrawState::default();
+        if let Some(root_id) = root_id {
+            let mut draw_state = DrawState::for_frame(render_state);
             let mut stack_ids = Vec::new();
             draw_script(
                 render_state,
@@ -370,17 +1944,295 @@ impl Renderer {
                 canvas,
                 &mut draw_state,
                 &mut stack_ids,
+                timings,
+                op_counts,
             );
         }
 
-        canvas.restore();
+        if !immediate.is_empty() {
+            let save_count = canvas.save();
+            let mut draw_state = DrawState::for_frame(render_state);
+            let mut stack_ids = Vec::new();
+            let started = timings.start();
+            draw_ops(
+                render_state,
+                immediate,
+                canvas,
+                &mut draw_state,
+                &mut stack_ids,
+                timings,
+                op_counts,
+            );
+            timings.record(IMMEDIATE_ID, started);
+            canvas.restore_to_count(save_count);
+        }
+
+        if !secondary {
+            focus::draw(canvas, &render_state.focus);
+        }
+
+        if let Some(overlay) = &render_state.dev_overlay
+            && !secondary
+        {
+            let font = match &render_state.default_typeface {
+                Some(typeface) => Some(Font::new(typeface.clone(), DEV_OVERLAY_FONT_SIZE)),
+                None => default_font(DEV_OVERLAY_FONT_SIZE),
+            };
+            let pointer = render_state.pointer.position();
+            dev_overlay::draw(canvas, overlay, pointer, font.as_ref());
+        }
+
+        if let Some(cursor) = render_state.virtual_pointer.cursor()
+            && !secondary
+        {
+            virtual_pointer::draw(canvas, cursor);
+        }
+
+        canvas.restore_to_count(frame_save_count);
+
+        if let Some(mut intermediate) = intermediate {
+            let kind = self.intermediate_kind;
+            copy_intermediate(&mut intermediate, &mut self.surface, clip, kind);
+            self.intermediate = Some(intermediate);
+        }
+
+        if let Some(watermark) = frame_watermark(render_state) {
+            let size = (self.surface.width(), self.surface.height());
+            stamp_watermark(
+                self.surface.canvas(),
+                size,
+                self.scale_factor,
+                render_state,
+                watermark,
+            );
+        }
+
+        let Some(gr) = self.gr_context.as_mut() else {
+            return Duration::ZERO;
+        };
+        let flush_started = Instant::now();
+        gr.flush_and_submit();
+        flush_started.elapsed()
+    }
+
+    /// Keeps an intermediate surface the size of the target, times the render scale, while
+    /// `kind` asks for one. Linear-light surfaces prefer half-float pixels; targets that
+    /// cannot render them get sRGB-encoded 8-bit pixels, which still blend in linear light.
+    fn prepare_intermediate(&mut self, kind: Intermediate) {
+        if kind == Intermediate::default() {
+            // The output still holds a stretched frame that partial repaints would keep.
+            self.full_redraw |= self.render_scale() != RenderScale::Full;
+            self.intermediate_kind = kind;
+            self.intermediate = None;
+            return;
+        }
+        let factor = kind.scale.factor();
+        let dimensions = ISize::new(
+            ((self.surface.width() as f32 * factor).ceil() as i32).max(1),
+            ((self.surface.height() as f32 * factor).ceil() as i32).max(1),
+        );
+        if kind == self.intermediate_kind
+            && self
+                .intermediate
+                .as_ref()
+                .is_none_or(|surface| surface.image_info().dimensions() == dimensions)
+        {
+            return;
+        }
+        // A new surface starts empty, so its first frame repaints everything.
+        self.full_redraw = true;
+        self.intermediate_kind = kind;
+        let (color_types, color_space) = if kind.linear {
+            (
+                &[ColorType::RGBAF16, ColorType::SRGBA8888][..],
+                Some(ColorSpace::new_srgb_linear()),
+            )
+        } else {
+            (&[ColorType::N32][..], None)
+        };
+        self.intermediate = color_types.iter().find_map(|&color_type| {
+            let info = ImageInfo::new(
+                dimensions,
+                color_type,
+                AlphaType::Premul,
+                color_space.clone(),
+            );
+            if kind.cpu {
+                surfaces::raster(&info, None, None)
+            } else {
+                self.surface.new_surface(&info)
+            }
+        });
+        if self.intermediate.is_none() {
+            log_line!("No intermediate surface for {kind:?}; drawing straight to the output");
+        }
+    }
+
+    /// Whether the GPU context reported running out of memory in a frame that drew text
+    /// and the CPU text fallback was just switched on for it. Skia reports failed glyph
+    /// atlas allocations and uploads only through this flag.
+    fn text_upload_failed(&mut self, render_state: &RenderState, op_counts: &OpCounts) -> bool {
+        let Some(gr) = self.gr_context.as_mut() else {
+            return false;
+        };
+        gr.oomed() && op_counts.drew_text() && render_state.text_fallback.report_failure()
+    }
 
+    /// Fills the whole surface with `color` without touching scripts, immediate ops or
+    /// damage, which stay pending for the first frame after the blank.
+    fn present_blank(&mut self, render_state: &RenderState, color: Color) {
+        self.surface.canvas().clear(color);
         if let Some(gr) = self.gr_context.as_mut() {
             gr.flush_and_submit();
         }
+        self.full_redraw = true;
+        self.frame_damage = None;
+        render_state.redraws.finish_frame(crate::monotonic_now_ms());
+    }
+
+    /// Device-space area of the surface that changes with `damage`, or `None` when the
+    /// whole surface does. Frames with immediate ops change everything, as does the frame
+    /// after them, and frames drawn at a reduced scale are always stretched whole.
+    fn damaged_region(
+        &mut self,
+        damage: Damage,
+        root_matrix: &Matrix,
+        immediate: bool,
+    ) -> Option<IRect> {
+        let full_redraw = std::mem::replace(&mut self.full_redraw, immediate);
+        let Damage::Partial(rect) = damage else {
+            return None;
+        };
+        if full_redraw || immediate || self.render_scale() != RenderScale::Full {
+            return None;
+        }
+        let device = root_matrix.map_rect(rect).0.round_out();
+        // One pixel of slack for anti-aliased edges that straddle the damage.
+        let region = IRect::from_ltrb(
+            device.left - 1,
+            device.top - 1,
+            device.right + 1,
+            device.bottom + 1,
+        );
+        let bounds = IRect::from_wh(self.surface.width(), self.surface.height());
+        Some(IRect::intersect(&region, &bounds).unwrap_or_default())
+    }
+
+    /// Surface area the last [`Renderer::redraw`] changed, for backends that can tell the
+    /// compositor; `None` when it changed everything.
+    pub fn frame_damage(&self) -> Option<IRect> {
+        self.frame_damage
+    }
+
+    /// Draws the frame into a small offscreen surface and keeps its pixels, so GPU
+    /// surfaces only read back the downsampled copy.
+    fn record_flight_frame(&mut self, recorder: &FlightRecorder) {
+        let (width, height) =
+            recorder.frame_size((self.surface.width() as u32, self.surface.height() as u32));
+        let info = ImageInfo::new(
+            (width as i32, height as i32),
+            ColorType::RGBA8888,
+            AlphaType::Premul,
+            None,
+        );
+        let snapshot = self.surface.image_snapshot();
+        let Some(mut small) = self.surface.new_surface(&info) else {
+            return;
+        };
+        small.canvas().draw_image_rect_with_sampling_options(
+            &snapshot,
+            None,
+            Rect::from_iwh(width as i32, height as i32),
+            SamplingOptions::new(FilterMode::Linear, MipmapMode::None),
+            &Paint::default(),
+        );
+        let row_bytes = info.min_row_bytes();
+        let mut pixels = vec![0u8; row_bytes * height as usize];
+        if small.read_pixels(&info, pixels.as_mut_slice(), row_bytes, (0, 0)) {
+            recorder.record_frame(width, height, pixels);
+        }
+    }
+
+    /// Reads the whole output surface back as unpremultiplied RGBA for `capture_frame`.
+    fn capture_frame(&mut self, render_state: &RenderState, ticket: u64) {
+        let (width, height) = (self.surface.width(), self.surface.height());
+        let info = ImageInfo::new(
+            (width, height),
+            ColorType::RGBA8888,
+            AlphaType::Unpremul,
+            None,
+        );
+        let row_bytes = info.min_row_bytes();
+        let mut pixels = vec![0u8; row_bytes * height as usize];
+        let stamp = render_state
+            .watermark
+            .as_ref()
+            .filter(|watermark| watermark.target == WatermarkTarget::Captures);
+        let read = match stamp {
+            Some(watermark) => self.read_stamped(&info, &mut pixels, render_state, watermark),
+            None => self
+                .surface
+                .read_pixels(&info, pixels.as_mut_slice(), row_bytes, (0, 0)),
+        };
+        if read {
+            render_state.frame_capture.fulfil(
+                ticket,
+                CapturedFrame {
+                    width: width as u32,
+                    height: height as u32,
+                    pixels,
+                },
+            );
+        } else {
+            log_line!("capture_frame: failed to read back the {width}x{height} surface");
+        }
+    }
+
+    /// Reads the surface back into `pixels` with `watermark` stamped into the copy, so
+    /// the presented frame stays clean.
+    fn read_stamped(
+        &mut self,
+        info: &ImageInfo,
+        pixels: &mut [u8],
+        render_state: &RenderState,
+        watermark: &Watermark,
+    ) -> bool {
+        let premul = ImageInfo::new(
+            info.dimensions(),
+            ColorType::RGBA8888,
+            AlphaType::Premul,
+            None,
+        );
+        let row_bytes = premul.min_row_bytes();
+        let mut frame = vec![0u8; pixels.len()];
+        if !self
+            .surface
+            .read_pixels(&premul, frame.as_mut_slice(), row_bytes, (0, 0))
+        {
+            return false;
+        }
+        let Some(image) = images::raster_from_data(&premul, Data::new_copy(&frame), row_bytes)
+        else {
+            return false;
+        };
+        let Some(mut stamped) = surfaces::raster(&premul, None, None) else {
+            return false;
+        };
+        stamped.canvas().draw_image(&image, (0, 0), None);
+        let size = (premul.width(), premul.height());
+        stamp_watermark(
+            stamped.canvas(),
+            size,
+            self.scale_factor,
+            render_state,
+            watermark,
+        );
+        stamped.read_pixels(info, pixels, row_bytes, (0, 0))
     }
 
     pub fn resize(&mut self, dimensions: (u32, u32)) {
+        self.scene_snapshot = None;
+        self.full_redraw = true;
         if let SurfaceSource::Gl {
             fb_info,
             num_samples,
@@ -405,10 +2257,15 @@ fn draw_script(
     canvas: &skia_safe::Canvas,
     draw_state: &mut DrawState,
     stack_ids: &mut Vec<String>,
+    timings: &mut ScriptTimings,
+    op_counts: &mut OpCounts,
 ) {
     if stack_ids.iter().any(|id| id == script_id) {
         return;
     }
+    render_state
+        .placements
+        .record(script_id, canvas, draw_state);
 
     let ops = match render_state.scripts.get(script_id) {
         Some(ops) => ops,
@@ -416,19 +2273,75 @@ fn draw_script(
     };
 
     stack_ids.push(script_id.to_string());
+    let started = timings.start();
+    let transformed = render_state.script_transforms.get(script_id).map(|matrix| {
+        let save_count = canvas.save();
+        canvas.concat(matrix);
+        save_count
+    });
+    draw_ops(
+        render_state,
+        ops,
+        canvas,
+        draw_state,
+        stack_ids,
+        timings,
+        op_counts,
+    );
+    if let Some(save_count) = transformed {
+        canvas.restore_to_count(save_count);
+    }
+    timings.record(script_id, started);
+    stack_ids.pop();
+}
+
+/// Timing label for `draw_immediate` ops in watchdog reports.
+pub const IMMEDIATE_ID: &str = "<immediate>";
+const DEV_OVERLAY_FONT_SIZE: f32 = 10.0;
 
+fn draw_ops(
+    render_state: &RenderState,
+    ops: &[ScriptOp],
+    canvas: &skia_safe::Canvas,
+    draw_state: &mut DrawState,
+    stack_ids: &mut Vec<String>,
+    timings: &mut ScriptTimings,
+    op_counts: &mut OpCounts,
+) {
     for op in ops {
+        if !op_counts.record(op) {
+            return;
+        }
         match op {
             ScriptOp::PushState => {
                 canvas.save();
                 draw_state.push();
             }
-            ScriptOp::PopState => {
+            ScriptOp::PopState | ScriptOp::Restore => {
                 if draw_state.can_pop() {
                     canvas.restore();
                     draw_state.pop();
                 }
             }
+            ScriptOp::SaveLayer {
+                x,
+                y,
+                w,
+                h,
+                alpha,
+                blend,
+            } => {
+                let bounds = (*w > 0.0 && *h > 0.0).then(|| Rect::from_xywh(*x, *y, *w, *h));
+                let mut paint = Paint::default();
+                paint.set_alpha(*alpha);
+                paint.set_blend_mode(*blend);
+                let mut rec = SaveLayerRec::default().paint(&paint);
+                if let Some(bounds) = bounds.as_ref() {
+                    rec = rec.bounds(bounds);
+                }
+                canvas.save_layer(&rec);
+                draw_state.push();
+            }
             ScriptOp::PopPushState => {
                 if draw_state.can_pop() {
                     canvas.restore();
@@ -452,12 +2365,15 @@ fn draw_script(
             ScriptOp::FillColor(color) => {
                 draw_state.fill_color = *color;
                 draw_state.fill_shader = None;
+                draw_state.fill_dither = false;
             }
             ScriptOp::StrokeColor(color) => {
                 draw_state.stroke_color = *color;
                 draw_state.stroke_shader = None;
+                draw_state.stroke_dither = false;
             }
             ScriptOp::StrokeWidth(width) => draw_state.stroke_width = *width,
+            ScriptOp::NonScalingStroke(enabled) => draw_state.non_scaling_stroke = *enabled,
             ScriptOp::FillLinear {
                 start_x,
                 start_y,
@@ -465,9 +2381,12 @@ fn draw_script(
                 end_y,
                 start_color,
                 end_color,
+                dither,
             } => {
                 draw_state.fill_color = *start_color;
                 let colors = [*start_color, *end_color];
+                let dither = effective_dither(render_state, *dither);
+                draw_state.fill_dither = dither != GradientDither::Off;
                 draw_state.fill_shader = Shader::linear_gradient(
                     (Point::new(*start_x, *start_y), Point::new(*end_x, *end_y)),
                     colors.as_slice(),
@@ -475,7 +2394,8 @@ fn draw_script(
                     TileMode::Clamp,
                     None,
                     None,
-                );
+                )
+                .map(|shader| dither_shader(shader, dither));
             }
             ScriptOp::FillRadial {
                 center_x,
@@ -484,16 +2404,21 @@ fn draw_script(
                 outer_radius,
                 start_color,
                 end_color,
+                dither,
             } => {
                 draw_state.fill_color = *start_color;
                 let colors = [*start_color, *end_color];
+                let dither = effective_dither(render_state, *dither);
+                draw_state.fill_dither = dither != GradientDither::Off;
                 draw_state.fill_shader = radial_shader(
                     *center_x,
                     *center_y,
                     *inner_radius,
                     *outer_radius,
                     colors.as_slice(),
-                );
+                    None,
+                )
+                .map(|shader| dither_shader(shader, dither));
             }
             ScriptOp::StrokeLinear {
                 start_x,
@@ -502,9 +2427,12 @@ fn draw_script(
                 end_y,
                 start_color,
                 end_color,
+                dither,
             } => {
                 draw_state.stroke_color = *start_color;
                 let colors = [*start_color, *end_color];
+                let dither = effective_dither(render_state, *dither);
+                draw_state.stroke_dither = dither != GradientDither::Off;
                 draw_state.stroke_shader = Shader::linear_gradient(
                     (Point::new(*start_x, *start_y), Point::new(*end_x, *end_y)),
                     colors.as_slice(),
@@ -512,7 +2440,8 @@ fn draw_script(
                     TileMode::Clamp,
                     None,
                     None,
-                );
+                )
+                .map(|shader| dither_shader(shader, dither));
             }
             ScriptOp::StrokeRadial {
                 center_x,
@@ -521,32 +2450,92 @@ fn draw_script(
                 outer_radius,
                 start_color,
                 end_color,
+                dither,
             } => {
                 draw_state.stroke_color = *start_color;
                 let colors = [*start_color, *end_color];
+                let dither = effective_dither(render_state, *dither);
+                draw_state.stroke_dither = dither != GradientDither::Off;
                 draw_state.stroke_shader = radial_shader(
                     *center_x,
                     *center_y,
                     *inner_radius,
                     *outer_radius,
                     colors.as_slice(),
-                );
+                    None,
+                )
+                .map(|shader| dither_shader(shader, dither));
+            }
+            ScriptOp::FillGradient {
+                shape,
+                stops,
+                dither,
+            } => {
+                if let Some((_, color)) = stops.first() {
+                    draw_state.fill_color = *color;
+                }
+                let dither = effective_dither(render_state, *dither);
+                draw_state.fill_dither = dither != GradientDither::Off;
+                draw_state.fill_shader =
+                    gradient_shader(shape, stops).map(|shader| dither_shader(shader, dither));
+            }
+            ScriptOp::StrokeGradient {
+                shape,
+                stops,
+                dither,
+            } => {
+                if let Some((_, color)) = stops.first() {
+                    draw_state.stroke_color = *color;
+                }
+                let dither = effective_dither(render_state, *dither);
+                draw_state.stroke_dither = dither != GradientDither::Off;
+                draw_state.stroke_shader =
+                    gradient_shader(shape, stops).map(|shader| dither_shader(shader, dither));
             }
             ScriptOp::FillImage(id) => {
-                set_fill_image_shader(draw_state, load_static_shader(id.as_str()));
+                let shader = load_static_shader(&render_state.assets, id.as_str());
+                set_fill_image_shader(draw_state, shader);
             }
             ScriptOp::FillStream(id) => {
-                set_fill_image_shader(draw_state, load_stream_shader(id.as_str()));
+                let present = !render_state.placements.measuring();
+                let shader = load_stream_shader(&render_state.assets, id.as_str(), present);
+                set_fill_image_shader(draw_state, shader);
+            }
+            ScriptOp::FillPattern { id, width, height } => {
+                let scale = matrix_scale(&canvas.local_to_device_as_3x3());
+                let shader = pattern_shader(
+                    render_state,
+                    id,
+                    (*width, *height),
+                    scale,
+                    stack_ids,
+                    timings,
+                    op_counts,
+                );
+                set_fill_image_shader(draw_state, shader);
             }
             ScriptOp::StrokeImage(id) => {
-                set_stroke_image_shader(draw_state, load_static_shader(id.as_str()));
+                let shader = load_static_shader(&render_state.assets, id.as_str());
+                set_stroke_image_shader(draw_state, shader);
             }
             ScriptOp::StrokeStream(id) => {
-                set_stroke_image_shader(draw_state, load_stream_shader(id.as_str()));
+                let present = !render_state.placements.measuring();
+                let shader = load_stream_shader(&render_state.assets, id.as_str(), present);
+                set_stroke_image_shader(draw_state, shader);
             }
             ScriptOp::StrokeCap(cap) => draw_state.stroke_cap = *cap,
             ScriptOp::StrokeJoin(join) => draw_state.stroke_join = *join,
             ScriptOp::StrokeMiterLimit(limit) => draw_state.stroke_miter_limit = *limit,
+            ScriptOp::DropShadow {
+                dx,
+                dy,
+                sigma,
+                color,
+            } => {
+                draw_state.drop_shadow = (*sigma > 0.0 || *dx != 0.0 || *dy != 0.0)
+                    .then_some((*dx, *dy, *sigma, *color));
+            }
+            ScriptOp::Blur { sigma } => draw_state.blur_sigma = *sigma,
             ScriptOp::ClipPath(clip_op) => {
                 if let Some(path) = draw_state.path.as_ref() {
                     let matrix = canvas.local_to_device();
@@ -572,138 +2561,37 @@ fn draw_script(
                     let mut paint = Paint::default();
                     apply_fill_paint(&mut paint, draw_state);
                     let mut cloned = path.clone();
-                    canvas.draw_path(&cloned.detach(), &paint);
-                }
-            }
-            ScriptOp::StrokePath => {
-                if let Some(mut path) = draw_state.path.take() {
-                    let mut paint = Paint::default();
-                    apply_stroke_paint(&mut paint, draw_state);
-                    canvas.draw_path(&path.detach(), &paint);
-                }
-            }
-            ScriptOp::MoveTo { x, y } => {
-                let path = draw_state.path.get_or_insert_with(PathBuilder::new);
-                path.move_to(Point::new(*x, *y));
-            }
-            ScriptOp::LineTo { x, y } => {
-                let path = draw_state.path.get_or_insert_with(PathBuilder::new);
-                path.line_to(Point::new(*x, *y));
-            }
-            ScriptOp::ArcTo {
-                x1,
-                y1,
-                x2,
-                y2,
-                radius,
-            } => {
-                let path = draw_state.path.get_or_insert_with(PathBuilder::new);
-                if !path.is_empty() {
-                    path.arc_to_tangent(Point::new(*x1, *y1), Point::new(*x2, *y2), *radius);
-                }
-            }
-            ScriptOp::BezierTo {
-                cp1x,
-                cp1y,
-                cp2x,
-                cp2y,
-                x,
-                y,
-            } => {
-                let path = draw_state.path.get_or_insert_with(PathBuilder::new);
-                path.cubic_to(
-                    Point::new(*cp1x, *cp1y),
-                    Point::new(*cp2x, *cp2y),
-                    Point::new(*x, *y),
-                );
-            }
-            ScriptOp::QuadraticTo { cpx, cpy, x, y } => {
-                let path = draw_state.path.get_or_insert_with(PathBuilder::new);
-                path.quad_to(Point::new(*cpx, *cpy), Point::new(*x, *y));
-            }
-            ScriptOp::PathTriangle {
-                x0,
-                y0,
-                x1,
-                y1,
-                x2,
-                y2,
-            } => {
-                let path = draw_state.path.get_or_insert_with(PathBuilder::new);
-                let points = [
-                    Point::new(*x0, *y0),
-                    Point::new(*x1, *y1),
-                    Point::new(*x2, *y2),
-                ];
-                path.add_polygon(&points, true);
-            }
-            ScriptOp::PathQuad {
-                x0,
-                y0,
-                x1,
-                y1,
-                x2,
-                y2,
-                x3,
-                y3,
-            } => {
-                let path = draw_state.path.get_or_insert_with(PathBuilder::new);
-                let points = [
-                    Point::new(*x0, *y0),
-                    Point::new(*x1, *y1),
-                    Point::new(*x2, *y2),
-                    Point::new(*x3, *y3),
-                ];
-                path.add_polygon(&points, true);
-            }
-            ScriptOp::PathRect { width, height } => {
-                let path = draw_state.path.get_or_insert_with(PathBuilder::new);
-                let rect = Rect::from_xywh(0.0, 0.0, *width, *height);
-                path.add_rect(rect, PathDirection::CW, None);
-            }
-            ScriptOp::PathRRect {
-                width,
-                height,
-                radius,
-            } => {
-                let path = draw_state.path.get_or_insert_with(PathBuilder::new);
-                let rect = Rect::from_xywh(0.0, 0.0, *width, *height);
-                let rrect = RRect::new_rect_xy(rect, *radius, *radius);
-                path.add_rrect(rrect, PathDirection::CW, None);
-            }
-            ScriptOp::PathSector { radius, radians } => {
-                let path = draw_state.path.get_or_insert_with(PathBuilder::new);
-                let rect = Rect::from_xywh(-radius, -radius, radius * 2.0, radius * 2.0);
-                let sweep = radians.to_degrees();
-                path.move_to(Point::new(0.0, 0.0));
-                path.line_to(Point::new(*radius, 0.0));
-                path.arc_to(rect, 0.0, sweep, false);
-                path.close();
-            }
-            ScriptOp::PathCircle { radius } => {
-                let path = draw_state.path.get_or_insert_with(PathBuilder::new);
-                path.add_circle(Point::new(0.0, 0.0), *radius, PathDirection::CW);
+                    canvas.draw_path(&cloned.detach(), &paint);
+                }
             }
-            ScriptOp::PathEllipse { radius0, radius1 } => {
-                let path = draw_state.path.get_or_insert_with(PathBuilder::new);
-                let rect = Rect::from_xywh(-radius0, -radius1, radius0 * 2.0, radius1 * 2.0);
-                path.add_oval(rect, PathDirection::CW, None);
+            ScriptOp::StrokePath => {
+                if let Some(mut path) = draw_state.path.take() {
+                    let mut paint = Paint::default();
+                    apply_stroke_paint(&mut paint, draw_state, canvas);
+                    canvas.draw_path(&path.detach(), &paint);
+                }
             }
-            ScriptOp::PathArc {
-                cx,
-                cy,
-                radius,
-                start,
-                end,
-                dir,
-            } => {
+            ScriptOp::MoveTo { .. }
+            | ScriptOp::LineTo { .. }
+            | ScriptOp::ArcTo { .. }
+            | ScriptOp::BezierTo { .. }
+            | ScriptOp::QuadraticTo { .. }
+            | ScriptOp::PathTriangle { .. }
+            | ScriptOp::PathQuad { .. }
+            | ScriptOp::PathRect { .. }
+            | ScriptOp::PathRRect { .. }
+            | ScriptOp::PathSuperellipse { .. }
+            | ScriptOp::PathSector { .. }
+            | ScriptOp::PathCircle { .. }
+            | ScriptOp::PathEllipse { .. }
+            | ScriptOp::PathArc { .. } => {
+                let tolerance = if matches!(op, ScriptOp::PathSuperellipse { .. }) {
+                    superellipse_tolerance(canvas)
+                } else {
+                    SUPERELLIPSE_FLATNESS
+                };
                 let path = draw_state.path.get_or_insert_with(PathBuilder::new);
-                let rect = Rect::from_xywh(cx - radius, cy - radius, radius * 2.0, radius * 2.0);
-                let mut sweep = (end - start).to_degrees();
-                if *dir == 2 {
-                    sweep = -sweep;
-                }
-                path.add_arc(rect, start.to_degrees(), sweep);
+                add_path_op(path, op, tolerance);
             }
             ScriptOp::DrawLine {
                 x0,
@@ -714,7 +2602,7 @@ fn draw_script(
             } => {
                 if flag & 0x02 == 0x02 {
                     let mut paint = Paint::default();
-                    apply_stroke_paint(&mut paint, draw_state);
+                    apply_stroke_paint(&mut paint, draw_state, canvas);
                     canvas.draw_line(Point::new(*x0, *y0), Point::new(*x1, *y1), &paint);
                 }
             }
@@ -741,7 +2629,7 @@ fn draw_script(
                 }
                 if flag & 0x02 == 0x02 {
                     let mut paint = Paint::default();
-                    apply_stroke_paint(&mut paint, draw_state);
+                    apply_stroke_paint(&mut paint, draw_state, canvas);
                     canvas.draw_path(&path, &paint);
                 }
             }
@@ -771,7 +2659,7 @@ fn draw_script(
                 }
                 if flag & 0x02 == 0x02 {
                     let mut paint = Paint::default();
-                    apply_stroke_paint(&mut paint, draw_state);
+                    apply_stroke_paint(&mut paint, draw_state, canvas);
                     canvas.draw_path(&path, &paint);
                 }
             }
@@ -783,7 +2671,7 @@ fn draw_script(
                 }
                 if flag & 0x02 == 0x02 {
                     let mut paint = Paint::default();
-                    apply_stroke_paint(&mut paint, draw_state);
+                    apply_stroke_paint(&mut paint, draw_state, canvas);
                     canvas.draw_circle(Point::new(0.0, 0.0), *radius, &paint);
                 }
             }
@@ -800,7 +2688,7 @@ fn draw_script(
                 }
                 if flag & 0x02 == 0x02 {
                     let mut paint = Paint::default();
-                    apply_stroke_paint(&mut paint, draw_state);
+                    apply_stroke_paint(&mut paint, draw_state, canvas);
                     canvas.draw_oval(rect, &paint);
                 }
             }
@@ -819,7 +2707,7 @@ fn draw_script(
                 }
                 if flag & 0x02 == 0x02 {
                     let mut paint = Paint::default();
-                    apply_stroke_paint(&mut paint, draw_state);
+                    apply_stroke_paint(&mut paint, draw_state, canvas);
                     canvas.draw_arc(rect, start, sweep, false, &paint);
                 }
             }
@@ -844,7 +2732,7 @@ fn draw_script(
                 }
                 if flag & 0x02 == 0x02 {
                     let mut paint = Paint::default();
-                    apply_stroke_paint(&mut paint, draw_state);
+                    apply_stroke_paint(&mut paint, draw_state, canvas);
                     canvas.draw_path(&path, &paint);
                 }
             }
@@ -857,12 +2745,15 @@ fn draw_script(
                     let rect = Rect::from_xywh(0.0, 0.0, *width, *height);
                     let mut paint = Paint::default();
                     apply_fill_paint(&mut paint, draw_state);
+                    if is_pixel_aligned(&canvas.local_to_device_as_3x3(), &rect) {
+                        paint.set_anti_alias(false);
+                    }
                     canvas.draw_rect(rect, &paint);
                 }
                 if flag & 0x02 == 0x02 {
                     let rect = Rect::from_xywh(0.0, 0.0, *width, *height);
                     let mut paint = Paint::default();
-                    apply_stroke_paint(&mut paint, draw_state);
+                    apply_stroke_paint(&mut paint, draw_state, canvas);
                     canvas.draw_rect(rect, &paint);
                 }
             }
@@ -877,11 +2768,16 @@ fn draw_script(
                 if flag & 0x01 == 0x01 {
                     let mut paint = Paint::default();
                     apply_fill_paint(&mut paint, draw_state);
+                    if rrect.is_rect()
+                        && is_pixel_aligned(&canvas.local_to_device_as_3x3(), rrect.rect())
+                    {
+                        paint.set_anti_alias(false);
+                    }
                     canvas.draw_rrect(rrect, &paint);
                 }
                 if flag & 0x02 == 0x02 {
                     let mut paint = Paint::default();
-                    apply_stroke_paint(&mut paint, draw_state);
+                    apply_stroke_paint(&mut paint, draw_state, canvas);
                     canvas.draw_rrect(rrect, &paint);
                 }
             }
@@ -905,16 +2801,80 @@ fn draw_script(
                 if flag & 0x01 == 0x01 {
                     let mut paint = Paint::default();
                     apply_fill_paint(&mut paint, draw_state);
+                    if rrect.is_rect()
+                        && is_pixel_aligned(&canvas.local_to_device_as_3x3(), rrect.rect())
+                    {
+                        paint.set_anti_alias(false);
+                    }
                     canvas.draw_rrect(rrect, &paint);
                 }
                 if flag & 0x02 == 0x02 {
                     let mut paint = Paint::default();
-                    apply_stroke_paint(&mut paint, draw_state);
+                    apply_stroke_paint(&mut paint, draw_state, canvas);
                     canvas.draw_rrect(rrect, &paint);
                 }
             }
+            ScriptOp::DrawSquircle {
+                width,
+                height,
+                exponent,
+                flag,
+            } => {
+                let mut builder = PathBuilder::new();
+                let tolerance = superellipse_tolerance(canvas);
+                add_superellipse(&mut builder, *width, *height, *exponent, tolerance);
+                let path = builder.detach();
+                if flag & 0x01 == 0x01 {
+                    let mut paint = Paint::default();
+                    apply_fill_paint(&mut paint, draw_state);
+                    canvas.draw_path(&path, &paint);
+                }
+                if flag & 0x02 == 0x02 {
+                    let mut paint = Paint::default();
+                    apply_stroke_paint(&mut paint, draw_state, canvas);
+                    canvas.draw_path(&path, &paint);
+                }
+            }
+            ScriptOp::DrawPolygon {
+                sides,
+                radius,
+                rounding,
+                flag,
+            } => {
+                let path = rounded_polygon_path(&polygon_vertices(*sides, *radius), *rounding);
+                if flag & 0x01 == 0x01 {
+                    let mut paint = Paint::default();
+                    apply_fill_paint(&mut paint, draw_state);
+                    canvas.draw_path(&path, &paint);
+                }
+                if flag & 0x02 == 0x02 {
+                    let mut paint = Paint::default();
+                    apply_stroke_paint(&mut paint, draw_state, canvas);
+                    canvas.draw_path(&path, &paint);
+                }
+            }
+            ScriptOp::DrawStar {
+                points,
+                outer,
+                inner,
+                rounding,
+                flag,
+            } => {
+                let path = rounded_polygon_path(&star_vertices(*points, *outer, *inner), *rounding);
+                if flag & 0x01 == 0x01 {
+                    let mut paint = Paint::default();
+                    apply_fill_paint(&mut paint, draw_state);
+                    canvas.draw_path(&path, &paint);
+                }
+                if flag & 0x02 == 0x02 {
+                    let mut paint = Paint::default();
+                    apply_stroke_paint(&mut paint, draw_state, canvas);
+                    canvas.draw_path(&path, &paint);
+                }
+            }
             ScriptOp::DrawSprites { image_id, cmds } => {
-                let Some(image) = cached_static_image(image_id.as_str()) else {
+                let Some(image) = cached_static_image(&render_state.assets, image_id.as_str())
+                else {
                     continue;
                 };
                 for cmd in cmds {
@@ -926,41 +2886,576 @@ fn draw_script(
                         &image,
                         Some((&src, SrcRectConstraint::Fast)),
                         dst,
-                        SamplingOptions::new(FilterMode::Linear, MipmapMode::None),
+                        sprite_sampling(canvas, &src, &dst),
                         &paint,
                     );
                 }
             }
-            ScriptOp::DrawText(text) => {
-                let font = match draw_state.font_id.as_deref() {
-                    Some(font_id) => font_from_asset(font_id, draw_state.font_size),
-                    None => default_font(draw_state.font_size),
+            ScriptOp::DrawAnimatedSprite {
+                image_id,
+                dst,
+                frames,
+            } => {
+                let Some(image) = cached_static_image(&render_state.assets, image_id.as_str())
+                else {
+                    continue;
+                };
+                let current = if render_state.reduced_motion {
+                    (!frames.is_empty()).then_some((0, None))
+                } else {
+                    current_sprite_frame(frames, render_state.clock.now_ms())
+                };
+                let Some((index, next_ms)) = current else {
+                    continue;
+                };
+                if let Some(next_ms) = next_ms
+                    && !render_state.placements.measuring()
+                {
+                    render_state.animations.schedule(next_ms);
+                }
+                let src = &frames[index].src;
+                canvas.draw_image_rect_with_sampling_options(
+                    &image,
+                    Some((src, SrcRectConstraint::Fast)),
+                    *dst,
+                    sprite_sampling(canvas, src, dst),
+                    &Paint::default(),
+                );
+            }
+            ScriptOp::DrawSpriteCell {
+                image_id,
+                dst,
+                columns,
+                rows,
+            } => {
+                let Some(image) = cached_static_image(&render_state.assets, image_id.as_str())
+                else {
+                    continue;
+                };
+                let index = stack_ids
+                    .last()
+                    .and_then(|id| render_state.script_params.get(id))
+                    .copied()
+                    .unwrap_or(0);
+                let Some(src) = sprite_cell(image.dimensions(), *columns, *rows, index) else {
+                    continue;
                 };
-                if let Some(font) = font.as_ref()
+                canvas.draw_image_rect_with_sampling_options(
+                    &image,
+                    Some((&src, SrcRectConstraint::Strict)),
+                    *dst,
+                    sprite_sampling(canvas, &src, dst),
+                    &Paint::default(),
+                );
+            }
+            ScriptOp::Blink { period_ms } => {
+                if !render_state.reduced_motion
+                    && let Some((visible, next_ms)) =
+                        blink_phase(*period_ms, render_state.clock.now_ms())
+                {
+                    // Bounds of a blinking script cover it whichever phase it is in.
+                    if render_state.placements.measuring() {
+                        continue;
+                    }
+                    render_state.animations.schedule(next_ms);
+                    if !visible {
+                        canvas.clip_rect(Rect::new_empty(), ClipOp::Intersect, false);
+                    }
+                }
+            }
+            ScriptOp::Marquee { width, speed } => {
+                draw_state.marquee = (*width > 0.0).then_some(Marquee {
+                    width: *width,
+                    speed: *speed,
+                });
+            }
+            ScriptOp::DrawText(text) if draw_state.marquee.is_some() => {
+                if let Some(font) = current_font(render_state, draw_state).as_ref()
+                    && let Some(marquee) = draw_state.marquee
+                    && let Some((blob, text_width)) = render_state.font_fallback.shape(text, font)
+                {
+                    let mut paint = Paint::default();
+                    apply_fill_paint(&mut paint, draw_state);
+                    let (_dx, dy) = draw_state.aligned_offsets(text_width, font);
+                    let x = if render_state.reduced_motion {
+                        0.0
+                    } else {
+                        let now_ms = render_state.clock.now_ms();
+                        if !render_state.placements.measuring() {
+                            render_state.animations.schedule(now_ms + MARQUEE_FRAME_MS);
+                        }
+                        marquee.offset(text_width, now_ms)
+                    };
+                    let metrics = font.metrics().1;
+                    canvas.save();
+                    canvas.clip_rect(
+                        Rect::from_ltrb(0.0, dy + metrics.top, marquee.width, dy + metrics.bottom),
+                        ClipOp::Intersect,
+                        true,
+                    );
+                    draw_text_run(render_state, canvas, &blob, Point::new(x, dy), &paint);
+                    canvas.restore();
+                }
+            }
+            ScriptOp::DrawText(text) if draw_state.text_layout.wrap != TextWrap::None => {
+                if let Some(font) = current_font(render_state, draw_state).as_ref()
+                    && !text.is_empty()
+                {
+                    let mut paint = Paint::default();
+                    apply_fill_paint(&mut paint, draw_state);
+                    let fallback = &render_state.font_fallback;
+                    let measure = |line: &str| fallback.measure(line, font);
+                    let lines = draw_state.text_layout.lines(text, measure);
+                    let (_dx, mut dy) = draw_state.block_offsets(lines.len(), font);
+                    for line in lines {
+                        if let Some((blob, width)) = fallback.shape(line, font) {
+                            let (dx, _dy) = draw_state.aligned_offsets(width, font);
+                            draw_text_run(render_state, canvas, &blob, Point::new(dx, dy), &paint);
+                        }
+                        dy += draw_state.line_advance();
+                    }
+                }
+            }
+            ScriptOp::DrawText(text) => {
+                if let Some(font) = current_font(render_state, draw_state).as_ref()
+                    && !text.is_empty()
+                {
+                    let mut paint = Paint::default();
+                    apply_fill_paint(&mut paint, draw_state);
+                    let key =
+                        TextRunKey::new(text, font, draw_state.text_align, draw_state.text_base);
+                    let run = render_state.assets.text_runs.get_or_insert_with(key, || {
+                        let (blob, width) = render_state.font_fallback.shape(text, font)?;
+                        Some((blob, draw_state.aligned_offsets(width, font)))
+                    });
+                    if let Some((blob, offset)) = run {
+                        draw_text_run(render_state, canvas, &blob, offset.into(), &paint);
+                    }
+                }
+            }
+            ScriptOp::TextPath(text) => {
+                if let Some(font) = current_font(render_state, draw_state).as_ref()
+                    && !text.is_empty()
+                {
+                    let (dx, dy) = draw_state.text_offsets(text, font, &Paint::default());
+                    let glyphs = text_utils::get_path(text, (dx, dy), font);
+                    let path = draw_state.path.get_or_insert_with(PathBuilder::new);
+                    path.add_path(&glyphs);
+                }
+            }
+            ScriptOp::DrawTextEllipsized { text, max_width } => {
+                if let Some(font) = current_font(render_state, draw_state).as_ref()
+                    && !text.is_empty()
+                {
+                    let mut paint = Paint::default();
+                    apply_fill_paint(&mut paint, draw_state);
+                    let fallback = &render_state.font_fallback;
+                    let text = ellipsize(text, *max_width, |s| fallback.measure(s, font));
+                    if !text.is_empty()
+                        && let Some((blob, width)) = fallback.shape(&text, font)
+                    {
+                        let (dx, dy) = draw_state.aligned_offsets(width, font);
+                        draw_text_run(render_state, canvas, &blob, Point::new(dx, dy), &paint);
+                    }
+                }
+            }
+            ScriptOp::DrawNumber(text) => {
+                if let Some(font) = current_font(render_state, draw_state).as_ref()
                     && !text.is_empty()
                 {
                     let mut paint = Paint::default();
                     apply_fill_paint(&mut paint, draw_state);
-                    let (dx, dy) = draw_state.text_offsets(text, font, &paint);
-                    canvas.draw_str(text, (dx, dy), font, &paint);
+                    let (positions, width) = tabular_layout(text, font);
+                    let (dx, dy) = draw_state.aligned_offsets(width, font);
+                    if let Some(blob) =
+                        TextBlob::from_pos_text_h(text.as_str(), &positions, 0.0, font)
+                    {
+                        draw_text_run(render_state, canvas, &blob, Point::new(dx, dy), &paint);
+                    }
                 }
             }
             ScriptOp::Font(font_id) => draw_state.font_id = Some(font_id.clone()),
             ScriptOp::FontSize(size) => draw_state.font_size = *size,
             ScriptOp::TextAlign(align) => draw_state.text_align = *align,
             ScriptOp::TextBase(base) => draw_state.text_base = *base,
+            ScriptOp::TextWidth(width) => draw_state.text_layout.max_width = *width,
+            ScriptOp::LineHeight(height) => draw_state.text_layout.line_height = *height,
+            ScriptOp::TextWrap(wrap) => draw_state.text_layout.wrap = *wrap,
+            ScriptOp::DrawScript(id) if render_state.isolate_scripts => {
+                let save_count = canvas.save();
+                let mut child_state = draw_state.isolated();
+                draw_script(
+                    render_state,
+                    id,
+                    canvas,
+                    &mut child_state,
+                    stack_ids,
+                    timings,
+                    op_counts,
+                );
+                canvas.restore_to_count(save_count);
+            }
             ScriptOp::DrawScript(id) => {
-                draw_script(render_state, id, canvas, draw_state, stack_ids);
+                draw_script(
+                    render_state,
+                    id,
+                    canvas,
+                    draw_state,
+                    stack_ids,
+                    timings,
+                    op_counts,
+                );
             }
         }
     }
+}
 
-    stack_ids.pop();
+/// Whether `rect` covers whole device pixels under `matrix`: the matrix is an integer
+/// translation and the rect edges are integral, so anti-aliasing has nothing to smooth
+/// and rects can take Skia's cheaper non-AA path.
+/// Draws a text run, through a CPU raster image while the text fallback is active.
+fn draw_text_run(
+    render_state: &RenderState,
+    canvas: &skia_safe::Canvas,
+    blob: &TextBlob,
+    origin: Point,
+    paint: &Paint,
+) {
+    if render_state.text_fallback.is_active()
+        && render_state
+            .text_fallback
+            .draw_blob(canvas, blob, origin, paint)
+    {
+        return;
+    }
+    canvas.draw_text_blob(blob, origin, paint);
+}
+
+fn is_pixel_aligned(matrix: &Matrix, rect: &Rect) -> bool {
+    let integral = |value: f32| value.fract() == 0.0;
+    matrix.is_translate()
+        && integral(matrix.translate_x())
+        && integral(matrix.translate_y())
+        && [rect.left, rect.top, rect.right, rect.bottom]
+            .into_iter()
+            .all(integral)
+}
+
+/// Copies sprites drawn at their source size onto whole pixels with nearest sampling,
+/// which matches linear filtering there and is cheaper on the CPU.
+/// Largest distance in screen pixels between a superellipse and its tessellation.
+pub const SUPERELLIPSE_FLATNESS: f32 = 0.2;
+const SUPERELLIPSE_MAX_DEPTH: u32 = 8;
+
+/// Appends a path-building op (`move_to` through `arc`, plus `close_path`) to `path`;
+/// other ops are ignored. `tolerance` is the superellipse flatness in local units.
+pub fn add_path_op(path: &mut PathBuilder, op: &ScriptOp, tolerance: f32) {
+    match op {
+        ScriptOp::ClosePath => {
+            path.close();
+        }
+        ScriptOp::MoveTo { x, y } => {
+            path.move_to(Point::new(*x, *y));
+        }
+        ScriptOp::LineTo { x, y } => {
+            path.line_to(Point::new(*x, *y));
+        }
+        ScriptOp::ArcTo {
+            x1,
+            y1,
+            x2,
+            y2,
+            radius,
+        } => {
+            if !path.is_empty() {
+                path.arc_to_tangent(Point::new(*x1, *y1), Point::new(*x2, *y2), *radius);
+            }
+        }
+        ScriptOp::BezierTo {
+            cp1x,
+            cp1y,
+            cp2x,
+            cp2y,
+            x,
+            y,
+        } => {
+            path.cubic_to(
+                Point::new(*cp1x, *cp1y),
+                Point::new(*cp2x, *cp2y),
+                Point::new(*x, *y),
+            );
+        }
+        ScriptOp::QuadraticTo { cpx, cpy, x, y } => {
+            path.quad_to(Point::new(*cpx, *cpy), Point::new(*x, *y));
+        }
+        ScriptOp::PathTriangle {
+            x0,
+            y0,
+            x1,
+            y1,
+            x2,
+            y2,
+        } => {
+            let points = [
+                Point::new(*x0, *y0),
+                Point::new(*x1, *y1),
+                Point::new(*x2, *y2),
+            ];
+            path.add_polygon(&points, true);
+        }
+        ScriptOp::PathQuad {
+            x0,
+            y0,
+            x1,
+            y1,
+            x2,
+            y2,
+            x3,
+            y3,
+        } => {
+            let points = [
+                Point::new(*x0, *y0),
+                Point::new(*x1, *y1),
+                Point::new(*x2, *y2),
+                Point::new(*x3, *y3),
+            ];
+            path.add_polygon(&points, true);
+        }
+        ScriptOp::PathRect { width, height } => {
+            let rect = Rect::from_xywh(0.0, 0.0, *width, *height);
+            path.add_rect(rect, PathDirection::CW, None);
+        }
+        ScriptOp::PathRRect {
+            width,
+            height,
+            radius,
+        } => {
+            let rect = Rect::from_xywh(0.0, 0.0, *width, *height);
+            let rrect = RRect::new_rect_xy(rect, *radius, *radius);
+            path.add_rrect(rrect, PathDirection::CW, None);
+        }
+        ScriptOp::PathSuperellipse {
+            width,
+            height,
+            exponent,
+        } => {
+            add_superellipse(path, *width, *height, *exponent, tolerance);
+        }
+        ScriptOp::PathSector { radius, radians } => {
+            let rect = Rect::from_xywh(-radius, -radius, radius * 2.0, radius * 2.0);
+            let sweep = radians.to_degrees();
+            path.move_to(Point::new(0.0, 0.0));
+            path.line_to(Point::new(*radius, 0.0));
+            path.arc_to(rect, 0.0, sweep, false);
+            path.close();
+        }
+        ScriptOp::PathCircle { radius } => {
+            path.add_circle(Point::new(0.0, 0.0), *radius, PathDirection::CW);
+        }
+        ScriptOp::PathEllipse { radius0, radius1 } => {
+            let rect = Rect::from_xywh(-radius0, -radius1, radius0 * 2.0, radius1 * 2.0);
+            path.add_oval(rect, PathDirection::CW, None);
+        }
+        ScriptOp::PathArc {
+            cx,
+            cy,
+            radius,
+            start,
+            end,
+            dir,
+        } => {
+            let rect = Rect::from_xywh(cx - radius, cy - radius, radius * 2.0, radius * 2.0);
+            let mut sweep = (end - start).to_degrees();
+            if *dir == 2 {
+                sweep = -sweep;
+            }
+            path.add_arc(rect, start.to_degrees(), sweep);
+        }
+        _ => {}
+    }
+}
+
+/// Flatness in local units at the current canvas scale, so zoomed shapes stay smooth.
+fn superellipse_tolerance(canvas: &skia_safe::Canvas) -> f32 {
+    screen_stroke_width(SUPERELLIPSE_FLATNESS, &canvas.local_to_device_as_3x3())
+}
+
+/// Appends a closed superellipse contour filling `width` x `height` at the origin.
+///
+/// One quadrant is tessellated adaptively, splitting a parameter interval until its
+/// midpoint lies within `tolerance` of the chord, then mirrored into the other three.
+fn add_superellipse(
+    builder: &mut PathBuilder,
+    width: f32,
+    height: f32,
+    exponent: f32,
+    tolerance: f32,
+) {
+    if width <= 0.0 || height <= 0.0 || exponent <= 0.0 || !exponent.is_finite() {
+        return;
+    }
+    let (a, b) = (width * 0.5, height * 0.5);
+    let quadrant = superellipse_quadrant(a, b, exponent, tolerance.max(f32::EPSILON));
+    let last = quadrant.len() - 1;
+    let at = |x: f32, y: f32| Point::new(a + x, b + y);
+
+    builder.move_to(at(quadrant[0].x, quadrant[0].y));
+    for point in &quadrant[1..] {
+        builder.line_to(at(point.x, point.y));
+    }
+    for point in quadrant[..last].iter().rev() {
+        builder.line_to(at(-point.x, point.y));
+    }
+    for point in &quadrant[1..] {
+        builder.line_to(at(-point.x, -point.y));
+    }
+    for point in quadrant[1..last].iter().rev() {
+        builder.line_to(at(point.x, -point.y));
+    }
+    builder.close();
+}
+
+/// Points from `(a, 0)` to `(0, b)` on the positive quadrant of the superellipse.
+fn superellipse_quadrant(a: f32, b: f32, exponent: f32, tolerance: f32) -> Vec<Point> {
+    let power = 2.0 / exponent;
+    let point = |t: f32| {
+        Point::new(
+            a * t.cos().max(0.0).powf(power),
+            b * t.sin().max(0.0).powf(power),
+        )
+    };
+    let mut points = vec![point(0.0)];
+    // Start from a few spans so a short flat first chord cannot hide a tight corner.
+    let step = std::f32::consts::FRAC_PI_2 / 4.0;
+    for index in 0..4 {
+        let (t0, t1) = (index as f32 * step, (index + 1) as f32 * step);
+        subdivide_superellipse(
+            &point,
+            (t0, point(t0)),
+            (t1, point(t1)),
+            tolerance,
+            0,
+            &mut points,
+        );
+    }
+    points
+}
+
+fn subdivide_superellipse(
+    point: &impl Fn(f32) -> Point,
+    (t0, p0): (f32, Point),
+    (t1, p1): (f32, Point),
+    tolerance: f32,
+    depth: u32,
+    points: &mut Vec<Point>,
+) {
+    let tm = (t0 + t1) * 0.5;
+    let pm = point(tm);
+    let chord = p1 - p0;
+    let offset = pm - p0;
+    let length = chord.length();
+    let distance = if length > f32::EPSILON {
+        chord.cross(offset).abs() / length
+    } else {
+        offset.length()
+    };
+    if depth < SUPERELLIPSE_MAX_DEPTH && distance > tolerance {
+        subdivide_superellipse(point, (t0, p0), (tm, pm), tolerance, depth + 1, points);
+        subdivide_superellipse(point, (tm, pm), (t1, p1), tolerance, depth + 1, points);
+    } else {
+        points.push(p1);
+    }
+}
+
+/// Vertices of a regular polygon of `radius`, clockwise from the top.
+fn polygon_vertices(sides: u32, radius: f32) -> Vec<Point> {
+    let step = std::f32::consts::TAU / sides.max(1) as f32;
+    (0..sides)
+        .map(|index| {
+            let angle = index as f32 * step - std::f32::consts::FRAC_PI_2;
+            Point::new(radius * angle.cos(), radius * angle.sin())
+        })
+        .collect()
+}
+
+/// Vertices of a star with `points` tips, clockwise from the top tip.
+fn star_vertices(points: u32, outer: f32, inner: f32) -> Vec<Point> {
+    let step = std::f32::consts::PI / points.max(1) as f32;
+    (0..points * 2)
+        .map(|index| {
+            let angle = index as f32 * step - std::f32::consts::FRAC_PI_2;
+            let radius = if index % 2 == 0 { outer } else { inner };
+            Point::new(radius * angle.cos(), radius * angle.sin())
+        })
+        .collect()
+}
+
+/// Closed path through `vertices` with every corner rounded by a circular arc of
+/// `rounding`, clamped per corner so the arcs never use more than half of an edge.
+fn rounded_polygon_path(vertices: &[Point], rounding: f32) -> skia_safe::Path {
+    let mut builder = PathBuilder::new();
+    let count = vertices.len();
+    if count < 3 {
+        return builder.detach();
+    }
+    let last = vertices[count - 1];
+    builder.move_to(Point::new(
+        (last.x + vertices[0].x) * 0.5,
+        (last.y + vertices[0].y) * 0.5,
+    ));
+    for (index, &vertex) in vertices.iter().enumerate() {
+        let prev = vertices[(index + count - 1) % count];
+        let next = vertices[(index + 1) % count];
+        let radius = corner_radius(prev, vertex, next, rounding);
+        if radius > 0.0 {
+            builder.arc_to_tangent(vertex, next, radius);
+        } else {
+            builder.line_to(vertex);
+        }
+    }
+    builder.close();
+    builder.detach()
+}
+
+/// Largest arc radius up to `rounding` whose tangent points stay within half of both
+/// edges meeting at `vertex`.
+fn corner_radius(prev: Point, vertex: Point, next: Point, rounding: f32) -> f32 {
+    if rounding <= 0.0 || !rounding.is_finite() {
+        return 0.0;
+    }
+    let incoming = prev - vertex;
+    let outgoing = next - vertex;
+    let (in_len, out_len) = (incoming.length(), outgoing.length());
+    if in_len <= f32::EPSILON || out_len <= f32::EPSILON {
+        return 0.0;
+    }
+    let cos = (incoming.dot(outgoing) / (in_len * out_len)).clamp(-1.0, 1.0);
+    let half_tan = (cos.acos() * 0.5).tan();
+    if half_tan <= f32::EPSILON || !half_tan.is_finite() {
+        return 0.0;
+    }
+    rounding.min(in_len.min(out_len) * 0.5 * half_tan)
+}
+
+fn sprite_sampling(canvas: &skia_safe::Canvas, src: &Rect, dst: &Rect) -> SamplingOptions {
+    let unscaled = src.width() == dst.width() && src.height() == dst.height();
+    let filter = if unscaled
+        && src.left.fract() == 0.0
+        && src.top.fract() == 0.0
+        && is_pixel_aligned(&canvas.local_to_device_as_3x3(), dst)
+    {
+        FilterMode::Nearest
+    } else {
+        FilterMode::Linear
+    };
+    SamplingOptions::new(filter, MipmapMode::None)
 }
 
 fn apply_fill_paint(paint: &mut Paint, draw_state: &DrawState) {
-    paint.set_anti_alias(true);
+    paint.set_anti_alias(draw_state.antialias);
     paint.set_style(PaintStyle::Fill);
+    paint.set_dither(draw_state.fill_dither);
+    paint.set_image_filter(draw_state.image_filter());
     if let Some(shader) = &draw_state.fill_shader {
         paint.set_shader(shader.clone());
         paint.set_color(Color::WHITE);
@@ -969,10 +3464,17 @@ fn apply_fill_paint(paint: &mut Paint, draw_state: &DrawState) {
     }
 }
 
-fn apply_stroke_paint(paint: &mut Paint, draw_state: &DrawState) {
-    paint.set_anti_alias(true);
+fn apply_stroke_paint(paint: &mut Paint, draw_state: &DrawState, canvas: &skia_safe::Canvas) {
+    paint.set_anti_alias(draw_state.antialias);
     paint.set_style(PaintStyle::Stroke);
-    paint.set_stroke_width(draw_state.stroke_width);
+    paint.set_dither(draw_state.stroke_dither);
+    paint.set_image_filter(draw_state.image_filter());
+    let stroke_width = if draw_state.non_scaling_stroke {
+        screen_stroke_width(draw_state.stroke_width, &canvas.local_to_device_as_3x3())
+    } else {
+        draw_state.stroke_width
+    };
+    paint.set_stroke_width(stroke_width);
     paint.set_stroke_cap(draw_state.stroke_cap);
     paint.set_stroke_join(draw_state.stroke_join);
     paint.set_stroke_miter(draw_state.stroke_miter_limit);
@@ -984,7 +3486,82 @@ fn apply_stroke_paint(paint: &mut Paint, draw_state: &DrawState) {
     }
 }
 
+/// Converts a screen-space stroke width into local units by dividing out the
+/// matrix scale, so the stroke keeps its width under scale transforms.
+fn screen_stroke_width(width: f32, matrix: &Matrix) -> f32 {
+    let scale = matrix_scale(matrix);
+    if scale.is_finite() && scale > f32::EPSILON {
+        width / scale
+    } else {
+        width
+    }
+}
+
+/// Average scale of `matrix`: the square root of its area scale factor.
+fn matrix_scale(matrix: &Matrix) -> f32 {
+    let det = matrix.scale_x() * matrix.scale_y() - matrix.skew_x() * matrix.skew_y();
+    det.abs().sqrt()
+}
+
+/// Largest side in pixels of a `fill_pattern` tile; larger tiles are drawn at a lower scale.
+const MAX_PATTERN_TILE: f32 = 1024.0;
+
+/// Repeating shader from the script `id` drawn into a `width` x `height` tile with a fresh
+/// draw state. The tile is rasterized at `scale` rounded up to a power of two, so it
+/// stays sharp under zoom without redrawing on every scale change, and is cached until
+/// the script or a script it draws is replaced. A script cannot fill with itself.
+fn pattern_shader(
+    render_state: &RenderState,
+    id: &str,
+    (width, height): (f32, f32),
+    scale: f32,
+    stack_ids: &mut Vec<String>,
+    timings: &mut ScriptTimings,
+    op_counts: &mut OpCounts,
+) -> Option<Shader> {
+    if width <= 0.0 || height <= 0.0 || stack_ids.iter().any(|drawing| drawing == id) {
+        return None;
+    }
+    let scale = if scale.is_finite() && scale > f32::EPSILON {
+        scale.log2().ceil().exp2()
+    } else {
+        1.0
+    };
+    let scale = scale.min(MAX_PATTERN_TILE / width.max(height));
+    let pixels = (
+        (width * scale).round().max(1.0),
+        (height * scale).round().max(1.0),
+    );
+    let key = PatternKey::new(id, width, height, scale);
+    let tile = render_state
+        .patterns
+        .get_or_insert_with(key, &render_state.scripts, || {
+            let mut surface = surfaces::raster_n32_premul((pixels.0 as i32, pixels.1 as i32))?;
+            let canvas = surface.canvas();
+            canvas.scale((pixels.0 / width, pixels.1 / height));
+            let mut draw_state = DrawState::for_frame(render_state);
+            render_state.placements.enter_tile();
+            draw_script(
+                render_state,
+                id,
+                canvas,
+                &mut draw_state,
+                stack_ids,
+                timings,
+                op_counts,
+            );
+            render_state.placements.leave_tile();
+            Some(surface.image_snapshot())
+        })?;
+    tile.to_shader(
+        Some((TileMode::Repeat, TileMode::Repeat)),
+        SamplingOptions::new(FilterMode::Linear, MipmapMode::None),
+        &Matrix::scale((width / pixels.0, height / pixels.1)),
+    )
+}
+
 fn set_fill_image_shader(draw_state: &mut DrawState, shader: Option<Shader>) {
+    draw_state.fill_dither = false;
     if let Some(shader) = shader {
         draw_state.fill_shader = Some(shader);
         draw_state.fill_color = Color::WHITE;
@@ -995,6 +3572,7 @@ fn set_fill_image_shader(draw_state: &mut DrawState, shader: Option<Shader>) {
 }
 
 fn set_stroke_image_shader(draw_state: &mut DrawState, shader: Option<Shader>) {
+    draw_state.stroke_dither = false;
     if let Some(shader) = shader {
         draw_state.stroke_shader = Some(shader);
         draw_state.stroke_color = Color::WHITE;
@@ -1004,6 +3582,147 @@ fn set_stroke_image_shader(draw_state: &mut DrawState, shader: Option<Shader>) {
     }
 }
 
+const ELLIPSIS: &str = "\u{2026}";
+
+/// Returns `text` unchanged if it fits in `max_width`, otherwise the longest grapheme
+/// prefix that fits with an ellipsis appended (or just the ellipsis, or nothing).
+/// Lays out a numeric readout with every digit in a cell as wide as the widest digit, so
+/// values change without the text shifting. Spaces take a digit cell too (figure spaces),
+/// other characters keep their own advance. Returns each glyph's x and the total width.
+fn tabular_layout(text: &str, font: &Font) -> (Vec<f32>, f32) {
+    let advances = |glyphs: &[u16]| {
+        let mut widths = vec![0.0; glyphs.len()];
+        font.get_widths(glyphs, &mut widths);
+        widths
+    };
+    let cell = advances(&font.str_to_glyphs_vec("0123456789"))
+        .into_iter()
+        .fold(0.0, f32::max);
+    let widths = advances(&font.str_to_glyphs_vec(text));
+    tabular_positions(text.chars().zip(widths), cell)
+}
+
+fn tabular_positions(chars: impl Iterator<Item = (char, f32)>, cell: f32) -> (Vec<f32>, f32) {
+    let mut x = 0.0;
+    let positions = chars
+        .map(|(ch, advance)| {
+            let start = x;
+            if ch.is_ascii_digit() || ch == ' ' {
+                x += cell;
+                start + (cell - advance) / 2.0
+            } else {
+                x += advance;
+                start
+            }
+        })
+        .collect();
+    (positions, x)
+}
+
+fn ellipsize(text: &str, max_width: f32, measure: impl Fn(&str) -> f32) -> Cow<'_, str> {
+    if measure(text) <= max_width {
+        return Cow::Borrowed(text);
+    }
+    let ends: Vec<usize> = text
+        .grapheme_indices(true)
+        .map(|(start, grapheme)| start + grapheme.len())
+        .collect();
+    let fits = |count: usize| {
+        let prefix = if count == 0 {
+            ""
+        } else {
+            &text[..ends[count - 1]]
+        };
+        measure(&format!("{prefix}{ELLIPSIS}")) <= max_width
+    };
+
+    // Widths grow with the prefix, so search for the longest prefix that fits.
+    let (mut low, mut high) = (0, ends.len());
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        if fits(mid) { low = mid } else { high = mid - 1 }
+    }
+    if low == 0 && !fits(0) {
+        return Cow::Borrowed("");
+    }
+    let prefix = if low == 0 { "" } else { &text[..ends[low - 1]] };
+    Cow::Owned(format!("{}{ELLIPSIS}", prefix.trim_end()))
+}
+
+fn current_font(render_state: &RenderState, draw_state: &DrawState) -> Option<Font> {
+    match (
+        draw_state.font_id.as_deref(),
+        &render_state.default_typeface,
+    ) {
+        (Some(font_id), _) => font_from_asset(&render_state.assets, font_id, draw_state.font_size),
+        (None, Some(typeface)) => Some(Font::new(typeface.clone(), draw_state.font_size)),
+        (None, None) => default_font(draw_state.font_size),
+    }
+}
+
+/// Characters of `text` that would draw as missing glyphs in `font_id` (or the default
+/// font) and every fallback font, with their byte offsets. Control characters and
+/// invisible format characters such as zero-width joiners and variation selectors are
+/// never reported.
+pub fn missing_glyphs(
+    render_state: &RenderState,
+    font_id: Option<&str>,
+    text: &str,
+) -> Result<Vec<(usize, char)>, String> {
+    let font = lookup_font(render_state, font_id, DrawState::DEFAULT_FONT_SIZE)?;
+    Ok(text
+        .char_indices()
+        .filter(|&(_, ch)| {
+            !font_fallback::is_invisible(ch)
+                && font.unichar_to_glyph(ch as i32) == 0
+                && render_state.font_fallback.typeface_for(ch).is_none()
+        })
+        .collect())
+}
+
+/// Advance width of `text` drawn by `draw_text` in `font_id` (or the default font) at
+/// `size`, fallback fonts included, with the font's vertical metrics.
+pub fn measure_text(
+    render_state: &RenderState,
+    font_id: Option<&str>,
+    size: f32,
+    text: &str,
+) -> Result<(f32, FontMetrics), String> {
+    if !size.is_finite() || size <= 0.0 {
+        return Err(format!("invalid font size: {size}"));
+    }
+    let font = lookup_font(render_state, font_id, size)?;
+    let width = render_state.font_fallback.measure(text, &font);
+    Ok((width, font.metrics().1))
+}
+
+fn lookup_font(
+    render_state: &RenderState,
+    font_id: Option<&str>,
+    size: f32,
+) -> Result<Font, String> {
+    match font_id {
+        Some(id) => font_from_asset(&render_state.assets, id, size)
+            .ok_or_else(|| format!("font not found: {id}")),
+        None => render_state
+            .default_typeface
+            .clone()
+            .map(|typeface| Font::new(typeface, size))
+            .or_else(|| default_font(size))
+            .ok_or_else(|| "no default font available".to_string()),
+    }
+}
+
+/// Resolves a default font: an uploaded font id first, then an installed family name.
+pub fn resolve_typeface(assets: &AssetStore, font: &str) -> Result<Typeface, String> {
+    if let Some(typeface) = typeface_from_asset(assets, font) {
+        return Ok(typeface);
+    }
+    FontMgr::new()
+        .match_family_style(font, FontStyle::normal())
+        .ok_or_else(|| format!("unknown font: {font}"))
+}
+
 fn default_font(size: f32) -> Option<Font> {
     static DEFAULT_TYPEFACE: OnceLock<Option<Typeface>> = OnceLock::new();
     let typeface = DEFAULT_TYPEFACE
@@ -1016,40 +3735,116 @@ fn default_font(size: f32) -> Option<Font> {
     Some(Font::new(typeface, size))
 }
 
-fn font_from_asset(font_id: &str, size: f32) -> Option<Font> {
-    let typeface = typeface_from_asset(font_id)?;
+fn font_from_asset(assets: &AssetStore, font_id: &str, size: f32) -> Option<Font> {
+    let typeface = typeface_from_asset(assets, font_id)?;
     Some(Font::new(typeface, size))
 }
 
-fn typeface_from_asset(font_id: &str) -> Option<Typeface> {
-    let cache = FONT_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
-    if let Ok(cache) = cache.lock()
-        && let Some(typeface) = cache.get(font_id)
-    {
-        return Some(typeface.clone());
+fn typeface_from_asset(assets: &AssetStore, font_id: &str) -> Option<Typeface> {
+    if let Ok(cache) = assets.fonts.lock()
+        && let Some(typeface) = cache.get(font_id)
+    {
+        return Some(typeface.clone());
+    }
+    None
+}
+
+pub fn insert_font(assets: &AssetStore, id: &str, data: &[u8]) -> Result<(), String> {
+    store_font(assets, id, decode_font(data)?)
+}
+
+pub fn decode_font(data: &[u8]) -> Result<Typeface, String> {
+    if data.is_empty() {
+        return Err("empty font data".to_string());
     }
-    None
+    FontMgr::new().new_from_data(data, 0).ok_or_else(|| {
+        format!(
+            "invalid font data: {} bytes starting {:02x?} are not a TrueType or OpenType font",
+            data.len(),
+            &data[..data.len().min(4)]
+        )
+    })
 }
 
-pub fn insert_font(id: &str, data: &[u8]) -> Result<(), String> {
-    let fm = FontMgr::new();
-    let typeface = fm
-        .new_from_data(data, 0)
-        .ok_or_else(|| "invalid font data".to_string())?;
-    let cache = FONT_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
-    let mut cache = cache
+pub fn store_font(assets: &AssetStore, id: &str, typeface: Typeface) -> Result<(), String> {
+    let mut cache = assets
+        .fonts
         .lock()
         .map_err(|_| "font cache lock poisoned".to_string())?;
     cache.insert(id.to_string(), typeface);
+    assets.text_runs.clear();
     Ok(())
 }
 
-fn load_static_shader(id: &str) -> Option<Shader> {
-    cached_static_image(id).and_then(|image| image_to_shader(&image))
+/// Drops font `id`. Text selecting it is skipped, as before the font was uploaded.
+pub fn remove_font(assets: &AssetStore, id: &str) -> Result<bool, String> {
+    let mut cache = assets
+        .fonts
+        .lock()
+        .map_err(|_| "font cache lock poisoned".to_string())?;
+    let removed = cache.remove(id).is_some();
+    if removed {
+        assets.text_runs.clear();
+    }
+    Ok(removed)
+}
+
+/// Sets or clears the transform of script `id`. Transforms are kept while the script is
+/// replaced; pattern tiles may draw the script, so they are redrawn.
+pub fn set_script_transform(render_state: &mut RenderState, id: &str, matrix: Option<Matrix>) {
+    match matrix {
+        Some(matrix) => render_state
+            .script_transforms
+            .insert(id.to_string(), matrix),
+        None => render_state.script_transforms.remove(id),
+    };
+    render_state.patterns.clear();
+}
+
+/// Drops every script, the root and anything scheduled, leaving an empty scene in the
+/// clear color. Assets, fonts and configuration are kept.
+pub fn clear_scene(render_state: &mut RenderState) {
+    render_state.scripts = HashMap::new();
+    render_state.script_transforms.clear();
+    render_state.script_params.clear();
+    render_state.root_id = None;
+    render_state.scheduled.clear();
+    render_state.scheduled_textures.clear();
+    if let Ok(mut immediate) = render_state.immediate.lock() {
+        *immediate = Arc::default();
+    }
+}
+
+/// Sets the cell `draw_sprite_cell` ops of script `id` draw. Parameters are kept while
+/// the script is replaced; pattern tiles may draw the script, so they are redrawn.
+pub fn set_script_param(render_state: &mut RenderState, id: &str, index: u32) {
+    render_state.script_params.insert(id.to_string(), index);
+    render_state.patterns.clear();
 }
 
-fn load_stream_shader(id: &str) -> Option<Shader> {
-    cached_stream_image(id).and_then(|image| image_to_shader(&image))
+/// Replaces the fallback fonts. Cached text runs were shaped with the old ones.
+pub fn set_font_fallback(render_state: &mut RenderState, fallback: FontFallback) {
+    render_state.font_fallback = Arc::new(fallback);
+    render_state.assets.text_runs.clear();
+}
+
+fn load_static_shader(assets: &AssetStore, id: &str) -> Option<Shader> {
+    cached_static_image(assets, id).and_then(|image| image_to_shader(&image))
+}
+
+/// Shader of the frame stream `id` shows. `present` counts the frame as presented; it is
+/// off when scripts are only replayed for their bounds.
+fn load_stream_shader(assets: &AssetStore, id: &str, present: bool) -> Option<Shader> {
+    let image = assets.streams.lock().ok().and_then(|mut cache| {
+        let frames = cache.get_mut(id)?;
+        let frame = if present {
+            frames.present()
+        } else {
+            frames.current()
+        };
+        frame.map(|cached| cached.image.clone())
+    })?;
+    image_to_shader(&image)
 }
 
 fn image_to_shader(image: &Image) -> Option<Shader> {
@@ -1066,13 +3861,14 @@ fn radial_shader(
     inner_radius: f32,
     outer_radius: f32,
     colors: &[Color],
+    positions: Option<&[f32]>,
 ) -> Option<Shader> {
     if inner_radius <= 0.0 {
         Shader::radial_gradient(
             Point::new(center_x, center_y),
             outer_radius,
             colors,
-            None,
+            positions,
             TileMode::Clamp,
             None,
             None,
@@ -1084,7 +3880,7 @@ fn radial_shader(
             Point::new(center_x, center_y),
             outer_radius,
             colors,
-            None,
+            positions,
             TileMode::Clamp,
             None,
             None,
@@ -1092,62 +3888,355 @@ fn radial_shader(
     }
 }
 
-fn cached_static_image(id: &str) -> Option<Image> {
-    let cache = IMAGE_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+fn gradient_shader(shape: &GradientShape, stops: &[(f32, Color)]) -> Option<Shader> {
+    let positions: Vec<f32> = stops.iter().map(|(position, _)| *position).collect();
+    let colors: Vec<Color> = stops.iter().map(|(_, color)| *color).collect();
+    match *shape {
+        GradientShape::Linear {
+            start_x,
+            start_y,
+            end_x,
+            end_y,
+        } => Shader::linear_gradient(
+            (Point::new(start_x, start_y), Point::new(end_x, end_y)),
+            colors.as_slice(),
+            positions.as_slice(),
+            TileMode::Clamp,
+            None,
+            None,
+        ),
+        GradientShape::Radial {
+            center_x,
+            center_y,
+            inner_radius,
+            outer_radius,
+        } => radial_shader(
+            center_x,
+            center_y,
+            inner_radius,
+            outer_radius,
+            &colors,
+            Some(&positions),
+        ),
+        GradientShape::Conic {
+            center_x,
+            center_y,
+            start_angle,
+        } => {
+            let center = Point::new(center_x, center_y);
+            // Skia sweeps from the positive x axis; rotating the shader moves the seam.
+            let rotation = Matrix::rotate_deg_pivot(start_angle.to_degrees(), center);
+            Shader::sweep_gradient(
+                center,
+                colors.as_slice(),
+                positions.as_slice(),
+                TileMode::Clamp,
+                None,
+                None,
+                &rotation,
+            )
+        }
+    }
+}
+
+const DITHER_NOISE_SKSL: &str = r#"
+uniform shader gradient;
+
+half4 main(float2 coord) {
+    half4 color = gradient.eval(coord);
+    float noise = fract(sin(dot(floor(coord), float2(12.9898, 78.233))) * 43758.5453) - 0.5;
+    color.rgb = clamp(color.rgb + half(noise * 1.5 / 255.0), 0.0, color.a);
+    return color;
+}
+"#;
+
+thread_local! {
+    static DITHER_NOISE_EFFECT: Option<RuntimeEffect> =
+        match RuntimeEffect::make_for_shader(DITHER_NOISE_SKSL, None) {
+            Ok(effect) => Some(effect),
+            Err(err) => {
+                log_line!("failed to compile gradient dither shader: {err}");
+                None
+            }
+        };
+}
+
+/// Noise dithering is an optional effect and drops to the paint flag in degraded mode.
+fn effective_dither(render_state: &RenderState, op_dither: GradientDither) -> GradientDither {
+    let dither = render_state.gradient_dither.max(op_dither);
+    if dither == GradientDither::Noise && render_state.watchdog.is_degraded() {
+        GradientDither::Paint
+    } else {
+        dither
+    }
+}
+
+/// Wraps a gradient shader in the SkSL noise pass when `dither` is `Noise`.
+fn dither_shader(shader: Shader, dither: GradientDither) -> Shader {
+    if dither != GradientDither::Noise {
+        return shader;
+    }
+    DITHER_NOISE_EFFECT.with(|effect| {
+        effect
+            .as_ref()
+            .and_then(|effect| {
+                effect.make_shader(Data::new_empty(), &[ChildPtr::from(shader.clone())], None)
+            })
+            .unwrap_or(shader)
+    })
+}
 
-    if let Ok(cache) = cache.lock()
-        && let Some(image) = cache.get(id)
+fn cached_static_image(assets: &AssetStore, id: &str) -> Option<Image> {
+    if let Ok(cache) = assets.images.lock()
+        && let Some(cached) = cache.get(id)
     {
-        return Some(image.clone());
+        return Some(cached.image.clone());
     }
     None
 }
 
-fn cached_stream_image(id: &str) -> Option<Image> {
-    let cache = STREAM_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+/// Decoded static image or stream texture `id`, for native pixel access outside drawing.
+pub fn lookup_image(assets: &AssetStore, id: &str) -> Option<Image> {
+    cached_static_image(assets, id).or_else(|| cached_stream_image(assets, id))
+}
 
-    if let Ok(cache) = cache.lock()
-        && let Some(image) = cache.get(id)
+fn cached_stream_image(assets: &AssetStore, id: &str) -> Option<Image> {
+    if let Ok(cache) = assets.streams.lock()
+        && let Some(cached) = cache.get(id).and_then(StreamFrames::current)
     {
-        return Some(image.clone());
+        return Some(cached.image.clone());
     }
 
     None
 }
 
-pub fn insert_static_image(id: &str, image: Image) {
-    let cache = IMAGE_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+pub fn static_image_meta(assets: &AssetStore, id: &str) -> Option<ImageMeta> {
+    assets
+        .images
+        .lock()
+        .ok()
+        .and_then(|cache| cache.get(id).map(|cached| cached.meta.clone()))
+}
+
+pub fn stream_image_meta(assets: &AssetStore, id: &str) -> Option<ImageMeta> {
+    assets.streams.lock().ok().and_then(|cache| {
+        cache
+            .get(id)
+            .and_then(StreamFrames::current)
+            .map(|cached| cached.meta.clone())
+    })
+}
+
+pub fn insert_static_image(assets: &AssetStore, id: &str, image: Image, meta: ImageMeta) {
+    let previous = assets.images.lock().ok().and_then(|mut cache| {
+        cache.insert(
+            id.to_string(),
+            CachedImage {
+                image,
+                meta,
+                blob: None,
+            },
+        )
+    });
+    if let Some(previous) = previous {
+        release_blob(assets, previous.blob);
+    }
+}
+
+/// Decodes an encoded static image, reusing the decoded image of any other id that was
+/// uploaded with the same bytes.
+pub fn insert_static_image_dedup(
+    assets: &AssetStore,
+    id: &str,
+    data: &[u8],
+) -> Result<ImageMeta, String> {
+    let key = digest::sha256(data);
+
+    let (image, meta) = {
+        let mut blobs = assets
+            .blobs
+            .lock()
+            .map_err(|_| "asset blob lock poisoned".to_string())?;
+        match blobs.get_mut(&key) {
+            Some(blob) => {
+                blob.refs += 1;
+                (blob.image.clone(), blob.meta.clone())
+            }
+            None => {
+                let (image, meta) = decode_texture_image("file", 0, 0, data)?;
+                blobs.insert(
+                    key,
+                    SharedBlob {
+                        image: image.clone(),
+                        meta: meta.clone(),
+                        refs: 1,
+                    },
+                );
+                (image, meta)
+            }
+        }
+    };
 
-    if let Ok(mut cache) = cache.lock() {
-        cache.insert(id.to_string(), image);
+    let previous = assets.images.lock().ok().and_then(|mut cache| {
+        cache.insert(
+            id.to_string(),
+            CachedImage {
+                image,
+                meta: meta.clone(),
+                blob: Some(key),
+            },
+        )
+    });
+    if let Some(previous) = previous {
+        release_blob(assets, previous.blob);
     }
+    Ok(meta)
 }
 
-pub fn insert_stream_image(id: &str, image: Image) {
-    let cache = STREAM_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+/// Removes a static image id. Returns `false` when the id was not cached.
+pub fn remove_static_image(assets: &AssetStore, id: &str) -> bool {
+    let removed = assets
+        .images
+        .lock()
+        .ok()
+        .and_then(|mut cache| cache.remove(id));
+    match removed {
+        Some(cached) => {
+            release_blob(assets, cached.blob);
+            true
+        }
+        None => false,
+    }
+}
 
-    if let Ok(mut cache) = cache.lock() {
-        cache.insert(id.to_string(), image);
+fn release_blob(assets: &AssetStore, key: Option<BlobKey>) {
+    let Some(key) = key else {
+        return;
+    };
+    if let Ok(mut blobs) = assets.blobs.lock()
+        && let Some(blob) = blobs.get_mut(&key)
+    {
+        blob.refs = blob.refs.saturating_sub(1);
+        if blob.refs == 0 {
+            blobs.remove(&key);
+        }
     }
 }
 
-pub fn remove_stream_image(id: &str) {
-    let cache = STREAM_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+/// Hands a new frame of stream texture `id` to its drop policy.
+pub fn insert_stream_image(assets: &AssetStore, id: &str, image: Image, meta: ImageMeta) {
+    let policy = assets.stream_policy(id);
+    if let Ok(mut cache) = assets.streams.lock() {
+        cache
+            .entry(id.to_string())
+            .or_insert_with(|| StreamFrames::new(policy))
+            .push(CachedImage {
+                image,
+                meta,
+                blob: None,
+            });
+    }
+}
 
-    if let Ok(mut cache) = cache.lock() {
+pub fn remove_stream_image(assets: &AssetStore, id: &str) {
+    if let Ok(mut cache) = assets.streams.lock() {
         cache.remove(id);
     }
 }
 
+/// Alpha interpretation of raw `ga` and `rgba` bitmaps.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextureAlpha {
+    #[default]
+    Premul,
+    Unpremul,
+}
+
+/// Color space handling on upload. Converted textures hold sRGB pixels, which is what
+/// the driver's surfaces display.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextureColorSpace {
+    /// Pixel values are drawn as they are; embedded ICC profiles are ignored.
+    #[default]
+    None,
+    /// Encoded files are converted from their embedded ICC profile, if they have one.
+    Embedded,
+    /// Pixels are linear sRGB, as produced by some camera pipelines.
+    LinearSrgb,
+}
+
+/// Per-texture upload options, set with `set_texture_options`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TextureOptions {
+    pub alpha: TextureAlpha,
+    pub color_space: TextureColorSpace,
+}
+
+impl TextureOptions {
+    pub fn parse(alpha: &str, color_space: &str) -> Result<Self, String> {
+        let alpha = match alpha {
+            "premul" => TextureAlpha::Premul,
+            "unpremul" => TextureAlpha::Unpremul,
+            other => return Err(format!("unsupported texture alpha: {other}")),
+        };
+        let color_space = match color_space {
+            "none" => TextureColorSpace::None,
+            "embedded" => TextureColorSpace::Embedded,
+            "linear_srgb" => TextureColorSpace::LinearSrgb,
+            other => return Err(format!("unsupported texture color space: {other}")),
+        };
+        Ok(Self { alpha, color_space })
+    }
+}
+
 pub fn decode_texture_image(
     format: &str,
     width: u32,
     height: u32,
     data: &[u8],
-) -> Result<Image, String> {
+) -> Result<(Image, ImageMeta), String> {
+    decode_texture_image_with(format, width, height, data, TextureOptions::default())
+}
+
+pub fn decode_texture_image_with(
+    format: &str,
+    width: u32,
+    height: u32,
+    data: &[u8],
+    options: TextureOptions,
+) -> Result<(Image, ImageMeta), String> {
     if format == "file" {
-        return Image::from_encoded(Data::new_copy(data))
-            .ok_or_else(|| "failed to decode image data".to_string());
+        // The hardware decoder drops ICC profiles, so embedded ones need the software path.
+        if encoded_format(data) == "jpeg"
+            && options.color_space != TextureColorSpace::Embedded
+            && let Some((width, height, rgba)) = hw_decode::decode_jpeg(data)
+        {
+            let info = ImageInfo::new(
+                (width as i32, height as i32),
+                ColorType::RGBA8888,
+                AlphaType::Opaque,
+                None,
+            );
+            let image =
+                images::raster_from_data(&info, Data::new_copy(&rgba), info.min_row_bytes())
+                    .ok_or_else(|| "failed to build raster image".to_string())?;
+            let meta = ImageMeta {
+                width,
+                height,
+                format: "jpeg".to_string(),
+                has_alpha: false,
+            };
+            return Ok((convert_to_srgb(image, options.color_space)?, meta));
+        }
+        let image = Image::from_encoded(Data::new_copy(data))
+            .ok_or_else(|| "failed to decode image data".to_string())?;
+        let meta = ImageMeta {
+            width: image.width() as u32,
+            height: image.height() as u32,
+            format: encoded_format(data).to_string(),
+            has_alpha: !image.is_opaque(),
+        };
+        return Ok((convert_to_srgb(image, options.color_space)?, meta));
     }
 
     let pixel_count = width
@@ -1194,36 +4283,139 @@ pub fn decode_texture_image(
             }
             data.to_vec()
         }
+        "nv12" | "yuv420p" => yuv420_to_rgba(format, width as usize, height as usize, data)?,
         _ => return Err(format!("unsupported texture format: {format}")),
     };
 
+    let alpha_type = match options.alpha {
+        TextureAlpha::Premul => AlphaType::Premul,
+        TextureAlpha::Unpremul => AlphaType::Unpremul,
+    };
     let info = ImageInfo::new(
         (width as i32, height as i32),
         ColorType::RGBA8888,
-        AlphaType::Premul,
+        alpha_type,
         None,
     );
     let row_bytes = (width as usize) * 4;
     let data = Data::new_copy(&rgba);
-    images::raster_from_data(&info, data, row_bytes)
-        .ok_or_else(|| "failed to build raster image".to_string())
+    let image = images::raster_from_data(&info, data, row_bytes)
+        .ok_or_else(|| "failed to build raster image".to_string())?;
+    let meta = ImageMeta {
+        width,
+        height,
+        format: format.to_string(),
+        has_alpha: matches!(format, "ga" | "rgba"),
+    };
+    Ok((convert_to_srgb(image, options.color_space)?, meta))
+}
+
+/// Converts a 4:2:0 camera or video frame to RGBA: `nv12` is a Y plane followed by
+/// interleaved U/V samples, `yuv420p` (I420) a Y plane followed by the U and V planes.
+/// Chroma planes cover odd sizes rounded up. Samples are read as BT.601 limited range,
+/// which is what V4L2 cameras and most decoders produce.
+fn yuv420_to_rgba(
+    format: &str,
+    width: usize,
+    height: usize,
+    data: &[u8],
+) -> Result<Vec<u8>, String> {
+    let luma_len = width * height;
+    let chroma_width = width.div_ceil(2);
+    let chroma_len = chroma_width * height.div_ceil(2);
+    if data.len() != luma_len + chroma_len * 2 {
+        return Err(format!("{format} frame size mismatch"));
+    }
+    let (luma, chroma) = data.split_at(luma_len);
+    let mut rgba = Vec::with_capacity(luma_len * 4);
+    for row in 0..height {
+        for col in 0..width {
+            let index = (row / 2) * chroma_width + col / 2;
+            let (u, v) = if format == "nv12" {
+                (chroma[index * 2], chroma[index * 2 + 1])
+            } else {
+                (chroma[index], chroma[chroma_len + index])
+            };
+            let c = 298 * (i32::from(luma[row * width + col]) - 16);
+            let d = i32::from(u) - 128;
+            let e = i32::from(v) - 128;
+            let channel = |value: i32| ((value + 128) >> 8).clamp(0, 255) as u8;
+            rgba.extend_from_slice(&[
+                channel(c + 409 * e),
+                channel(c - 100 * d - 208 * e),
+                channel(c + 516 * d),
+                0xFF,
+            ]);
+        }
+    }
+    Ok(rgba)
+}
+
+/// Converts `image` into sRGB pixels from the source color space `color_space` selects.
+/// The conversion decodes lazily decoded files once, on upload.
+fn convert_to_srgb(image: Image, color_space: TextureColorSpace) -> Result<Image, String> {
+    let source = match color_space {
+        TextureColorSpace::None => return Ok(image),
+        TextureColorSpace::Embedded => match image.color_space() {
+            Some(embedded) if !embedded.is_srgb() => embedded,
+            _ => return Ok(image),
+        },
+        TextureColorSpace::LinearSrgb => ColorSpace::new_srgb_linear(),
+    };
+    image
+        .reinterpret_color_space(source)
+        .and_then(|tagged| {
+            tagged.make_color_space(
+                None::<&mut skia_safe::gpu::DirectContext>,
+                ColorSpace::new_srgb(),
+            )
+        })
+        .ok_or_else(|| "failed to convert texture color space".to_string())
+}
+
+fn encoded_format(data: &[u8]) -> &'static str {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "png"
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "jpeg"
+    } else if data.starts_with(b"GIF8") {
+        "gif"
+    } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        "webp"
+    } else if data.starts_with(b"BM") {
+        "bmp"
+    } else {
+        "unknown"
+    }
 }
 
 #[derive(Clone)]
 struct DrawState {
     fill_color: Color,
     fill_shader: Option<Shader>,
+    fill_dither: bool,
     stroke_color: Color,
     stroke_shader: Option<Shader>,
+    stroke_dither: bool,
     stroke_width: f32,
+    non_scaling_stroke: bool,
     stroke_cap: PaintCap,
     stroke_join: PaintJoin,
     stroke_miter_limit: f32,
+    /// Offset, sigma and color of the shadow under fills and strokes.
+    drop_shadow: Option<(f32, f32, f32, Color)>,
+    blur_sigma: f32,
     path: Option<PathBuilder>,
     font_id: Option<String>,
     font_size: f32,
     text_align: TextAlign,
     text_base: TextBase,
+    marquee: Option<Marquee>,
+    text_layout: TextLayout,
+    /// Off while the quality governor has traded anti-aliasing for frame time.
+    antialias: bool,
+    /// Off in the frame watchdog's degraded mode, which skips blurs and drop shadows.
+    effects: bool,
     stack: Vec<DrawStateSnapshot>,
 }
 
@@ -1232,17 +4424,26 @@ impl Default for DrawState {
         Self {
             fill_color: Color::BLACK,
             fill_shader: None,
+            fill_dither: false,
             stroke_color: Color::BLACK,
             stroke_shader: None,
+            stroke_dither: false,
             stroke_width: 1.0,
+            non_scaling_stroke: false,
             stroke_cap: PaintCap::Butt,
             stroke_join: PaintJoin::Miter,
             stroke_miter_limit: 4.0,
+            drop_shadow: None,
+            blur_sigma: 0.0,
             path: None,
             font_id: None,
             font_size: Self::DEFAULT_FONT_SIZE,
             text_align: TextAlign::Left,
             text_base: TextBase::Alphabetic,
+            marquee: None,
+            text_layout: TextLayout::default(),
+            antialias: true,
+            effects: true,
             stack: Vec::new(),
         }
     }
@@ -1251,24 +4452,50 @@ impl Default for DrawState {
 impl DrawState {
     const DEFAULT_FONT_SIZE: f32 = 20.0;
 
+    /// Initial draw state of a frame, using the renderer's default font size, the
+    /// anti-aliasing of the current render quality and no effects in degraded mode.
+    fn for_frame(render_state: &RenderState) -> Self {
+        Self {
+            font_size: render_state.default_font_size,
+            antialias: render_state.quality.level().antialias(),
+            effects: !render_state.watchdog.is_degraded(),
+            ..Self::default()
+        }
+    }
+
     fn push(&mut self) {
         self.stack.push(DrawStateSnapshot {
             fill_color: self.fill_color,
             fill_shader: self.fill_shader.clone(),
+            fill_dither: self.fill_dither,
             stroke_color: self.stroke_color,
             stroke_shader: self.stroke_shader.clone(),
+            stroke_dither: self.stroke_dither,
             stroke_width: self.stroke_width,
+            non_scaling_stroke: self.non_scaling_stroke,
             stroke_cap: self.stroke_cap,
             stroke_join: self.stroke_join,
             stroke_miter_limit: self.stroke_miter_limit,
+            drop_shadow: self.drop_shadow,
+            blur_sigma: self.blur_sigma,
             path: self.path.clone(),
             font_id: self.font_id.clone(),
             font_size: self.font_size,
             text_align: self.text_align,
             text_base: self.text_base,
+            marquee: self.marquee,
+            text_layout: self.text_layout,
         });
     }
 
+    /// A copy of the current state with an empty stack, so a child script starts where
+    /// its caller is but cannot pop the caller's pushes.
+    fn isolated(&self) -> Self {
+        let mut state = self.clone();
+        state.stack.clear();
+        state
+    }
+
     fn pop(&mut self) {
         let snapshot = self.stack.pop().unwrap_or_default();
         self.apply_snapshot(snapshot);
@@ -1287,21 +4514,52 @@ impl DrawState {
     fn apply_snapshot(&mut self, snapshot: DrawStateSnapshot) {
         self.fill_color = snapshot.fill_color;
         self.fill_shader = snapshot.fill_shader;
+        self.fill_dither = snapshot.fill_dither;
         self.stroke_color = snapshot.stroke_color;
         self.stroke_shader = snapshot.stroke_shader;
+        self.stroke_dither = snapshot.stroke_dither;
         self.stroke_width = snapshot.stroke_width;
+        self.non_scaling_stroke = snapshot.non_scaling_stroke;
         self.stroke_cap = snapshot.stroke_cap;
         self.stroke_join = snapshot.stroke_join;
         self.stroke_miter_limit = snapshot.stroke_miter_limit;
+        self.drop_shadow = snapshot.drop_shadow;
+        self.blur_sigma = snapshot.blur_sigma;
         self.path = snapshot.path;
         self.font_id = snapshot.font_id;
         self.font_size = snapshot.font_size;
         self.text_align = snapshot.text_align;
         self.text_base = snapshot.text_base;
+        self.marquee = snapshot.marquee;
+        self.text_layout = snapshot.text_layout;
+    }
+
+    /// Blur and drop shadow for fill and stroke paints; the shadow is cast by the blurred
+    /// shape. Both are optional effects and dropped in degraded mode.
+    fn image_filter(&self) -> Option<ImageFilter> {
+        if !self.effects {
+            return None;
+        }
+        let blur = if self.blur_sigma > 0.0 {
+            image_filters::blur((self.blur_sigma, self.blur_sigma), None, None, None)
+        } else {
+            None
+        };
+        match self.drop_shadow {
+            Some((dx, dy, sigma, color)) => {
+                image_filters::drop_shadow((dx, dy), (sigma, sigma), color, None, blur, None)
+            }
+            None => blur,
+        }
     }
 
     fn text_offsets(&self, text: &str, font: &Font, paint: &Paint) -> (f32, f32) {
         let (width, _bounds) = font.measure_str(text, Some(paint));
+        self.aligned_offsets(width, font)
+    }
+
+    /// Offsets that apply `text_align` to a run of `width` and `text_base` for `font`.
+    fn aligned_offsets(&self, width: f32, font: &Font) -> (f32, f32) {
         let metrics = font.metrics().1;
         let dx = match self.text_align {
             TextAlign::Left => 0.0,
@@ -1316,23 +4574,47 @@ impl DrawState {
         };
         (dx, dy)
     }
+
+    fn line_advance(&self) -> f32 {
+        self.font_size * self.text_layout.line_height
+    }
+
+    /// Offsets of the first of `lines` laid out lines, with `text_base` applied to the
+    /// whole block: `middle` centers it and `bottom` puts the last baseline's descent there.
+    fn block_offsets(&self, lines: usize, font: &Font) -> (f32, f32) {
+        let (dx, dy) = self.aligned_offsets(0.0, font);
+        let extra = self.line_advance() * lines.saturating_sub(1) as f32;
+        let shift = match self.text_base {
+            TextBase::Middle => extra / 2.0,
+            TextBase::Bottom => extra,
+            TextBase::Top | TextBase::Alphabetic => 0.0,
+        };
+        (dx, dy - shift)
+    }
 }
 
 #[derive(Clone)]
 struct DrawStateSnapshot {
     fill_color: Color,
     fill_shader: Option<Shader>,
+    fill_dither: bool,
     stroke_color: Color,
     stroke_shader: Option<Shader>,
+    stroke_dither: bool,
     stroke_width: f32,
+    non_scaling_stroke: bool,
     stroke_cap: PaintCap,
     stroke_join: PaintJoin,
     stroke_miter_limit: f32,
+    drop_shadow: Option<(f32, f32, f32, Color)>,
+    blur_sigma: f32,
     path: Option<PathBuilder>,
     font_id: Option<String>,
     font_size: f32,
     text_align: TextAlign,
     text_base: TextBase,
+    marquee: Option<Marquee>,
+    text_layout: TextLayout,
 }
 
 impl Default for DrawStateSnapshot {
@@ -1340,32 +4622,693 @@ impl Default for DrawStateSnapshot {
         Self {
             fill_color: Color::BLACK,
             fill_shader: None,
+            fill_dither: false,
             stroke_color: Color::BLACK,
             stroke_shader: None,
+            stroke_dither: false,
             stroke_width: 1.0,
+            non_scaling_stroke: false,
             stroke_cap: PaintCap::Butt,
             stroke_join: PaintJoin::Miter,
             stroke_miter_limit: 4.0,
+            drop_shadow: None,
+            blur_sigma: 0.0,
             path: None,
             font_id: None,
             font_size: DrawState::DEFAULT_FONT_SIZE,
             text_align: TextAlign::Left,
             text_base: TextBase::Alphabetic,
+            marquee: None,
+            text_layout: TextLayout::default(),
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum TextAlign {
     Left,
     Center,
     Right,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum TextBase {
     Top,
     Middle,
     Alphabetic,
     Bottom,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn damage_maps_to_device_regions_between_full_repaints() {
+        let damage = DamageTracker::new();
+        assert_eq!(damage.take(), Damage::Full);
+        assert_eq!(damage.take(), Damage::Clean);
+        damage.add_rect(Rect::from_xywh(10.0, 10.0, 5.0, 5.0));
+        damage.add_rect(Rect::from_xywh(30.0, 0.0, 5.0, 5.0));
+        let joined = Rect::from_ltrb(10.0, 0.0, 35.0, 15.0);
+        assert_eq!(damage.take(), Damage::Partial(joined));
+
+        let surface = skia_safe::surfaces::raster_n32_premul((64, 64)).expect("surface");
+        let mut renderer = Renderer::from_surface(surface, None);
+        let scaled = Matrix::scale((2.0, 2.0));
+        // The first frame after creation always repaints everything.
+        assert_eq!(
+            renderer.damaged_region(Damage::Partial(joined), &scaled, false),
+            None
+        );
+        // Regions are cut to the surface.
+        assert_eq!(
+            renderer.damaged_region(Damage::Partial(joined), &scaled, false),
+            Some(IRect::from_ltrb(19, 0, 64, 31))
+        );
+        assert_eq!(renderer.damaged_region(Damage::Clean, &scaled, false), None);
+        assert_eq!(
+            renderer.damaged_region(Damage::Partial(joined), &scaled, true),
+            None
+        );
+        assert_eq!(
+            renderer.damaged_region(Damage::Partial(joined), &scaled, false),
+            None
+        );
+    }
+
+    #[test]
+    fn script_extents_follow_last_frame_placements() {
+        let dot = vec![
+            ScriptOp::PushState,
+            ScriptOp::FillColor(Color::RED),
+            ScriptOp::DrawRect {
+                width: 4.0,
+                height: 4.0,
+                flag: 0x01,
+            },
+            ScriptOp::PopState,
+        ];
+        let root_at = |x: f32| {
+            vec![
+                ScriptOp::PushState,
+                ScriptOp::Translate(x, 6.0),
+                ScriptOp::DrawScript("dot".to_string()),
+                ScriptOp::PopState,
+            ]
+        };
+        let mut state = RenderState::default();
+        state.scripts.insert("dot".to_string(), dot.into());
+        state
+            .scripts
+            .insert("root".to_string(), root_at(10.0).into());
+        state.root_id = Some("root".to_string());
+        // Nothing is known before the first frame.
+        assert_eq!(script_extent(&state, "dot"), None);
+
+        let surface = skia_safe::surfaces::raster_n32_premul((64, 64)).expect("surface");
+        let mut renderer = Renderer::from_surface(surface, None);
+        renderer.set_scale_factor(2.0);
+        renderer.redraw(&state);
+        assert_eq!(renderer.frame_damage(), None);
+        let placed = Rect::from_xywh(10.0, 6.0, 4.0, 4.0);
+        assert_eq!(script_extent(&state, "dot"), Some(placed));
+        assert_eq!(script_extent(&state, "hidden"), Some(Rect::new_empty()));
+
+        // Moving the root forgets where the dot was, since it moves along.
+        assert_eq!(script_extent(&state, "root"), Some(placed));
+        state
+            .scripts
+            .insert("root".to_string(), root_at(20.0).into());
+        assert_eq!(
+            script_extent(&state, "root"),
+            Some(Rect::from_xywh(20.0, 6.0, 4.0, 4.0))
+        );
+        assert_eq!(script_extent(&state, "dot"), None);
+
+        state.damage.add_rect(placed);
+        renderer.redraw(&state);
+        assert_eq!(
+            renderer.frame_damage(),
+            Some(IRect::from_ltrb(19, 11, 29, 21))
+        );
+        assert_eq!(
+            script_extent(&state, "dot"),
+            Some(Rect::from_xywh(20.0, 6.0, 4.0, 4.0))
+        );
+
+        // A script that leaves state behind changes how its caller goes on drawing.
+        let leaky = vec![ScriptOp::FillColor(Color::BLUE)];
+        state.scripts.insert("dot".to_string(), leaky.into());
+        assert_eq!(script_extent(&state, "dot"), None);
+        state.isolate_scripts = true;
+        assert_eq!(script_extent(&state, "dot"), Some(Rect::new_empty()));
+    }
+
+    #[test]
+    fn isolated_scripts_do_not_leak_state_to_the_caller() {
+        let mut state = RenderState::default();
+        state.scripts.insert(
+            "child".to_string(),
+            vec![
+                ScriptOp::FillColor(Color::RED),
+                ScriptOp::Translate(8.0, 0.0),
+            ]
+            .into(),
+        );
+        state.scripts.insert(
+            "root".to_string(),
+            vec![
+                ScriptOp::FillColor(Color::BLUE),
+                ScriptOp::DrawScript("child".to_string()),
+                ScriptOp::DrawRect {
+                    width: 4.0,
+                    height: 4.0,
+                    flag: 0x01,
+                },
+            ]
+            .into(),
+        );
+        state.root_id = Some("root".to_string());
+
+        let pixel = |state: &RenderState, x, y| {
+            let surface = skia_safe::surfaces::raster_n32_premul((16, 4)).expect("surface");
+            let mut renderer = Renderer::from_surface(surface, None);
+            renderer.redraw(state);
+            let image = renderer.surface_mut().image_snapshot();
+            let pixmap = image.peek_pixels().expect("pixels");
+            pixmap.get_color((x, y))
+        };
+        assert_eq!(pixel(&state, 10, 2), Color::RED);
+        assert_eq!(pixel(&state, 2, 2), Color::WHITE);
+
+        state.isolate_scripts = true;
+        assert_eq!(pixel(&state, 2, 2), Color::BLUE);
+        assert_eq!(pixel(&state, 10, 2), Color::WHITE);
+    }
+
+    #[test]
+    fn drop_shadows_last_until_pop_state() {
+        let rect = ScriptOp::DrawRect {
+            width: 4.0,
+            height: 4.0,
+            flag: 0x01,
+        };
+        let mut state = RenderState::default();
+        state.scripts.insert(
+            "root".to_string(),
+            vec![
+                ScriptOp::FillColor(Color::BLUE),
+                ScriptOp::PushState,
+                ScriptOp::DropShadow {
+                    dx: 8.0,
+                    dy: 0.0,
+                    sigma: 0.0,
+                    color: Color::BLACK,
+                },
+                rect.clone(),
+                ScriptOp::PopState,
+                ScriptOp::Translate(16.0, 0.0),
+                rect,
+            ]
+            .into(),
+        );
+        state.root_id = Some("root".to_string());
+
+        let surface = skia_safe::surfaces::raster_n32_premul((32, 4)).expect("surface");
+        let mut renderer = Renderer::from_surface(surface, None);
+        renderer.redraw(&state);
+        let image = renderer.surface_mut().image_snapshot();
+        let pixmap = image.peek_pixels().expect("pixels");
+        assert_eq!(pixmap.get_color((2, 2)), Color::BLUE);
+        assert_eq!(pixmap.get_color((10, 2)), Color::BLACK);
+        assert_eq!(pixmap.get_color((18, 2)), Color::BLUE);
+        assert_eq!(pixmap.get_color((26, 2)), Color::WHITE);
+
+        state.watchdog.configure(1, None).expect("configure");
+        assert!(
+            state
+                .watchdog
+                .finish_frame(Duration::from_millis(100), &ScriptTimings::new(true))
+        );
+        renderer.redraw(&state);
+        let image = renderer.surface_mut().image_snapshot();
+        let pixmap = image.peek_pixels().expect("pixels");
+        assert_eq!(pixmap.get_color((2, 2)), Color::BLUE);
+        assert_eq!(pixmap.get_color((10, 2)), Color::WHITE);
+    }
+
+    #[test]
+    fn gradient_stops_and_conic_start_angles_are_honored() {
+        let draw = |shape: GradientShape, stops: Vec<(f32, Color)>, x: i32, y: i32| {
+            let mut state = RenderState::default();
+            state.scripts.insert(
+                "root".to_string(),
+                vec![
+                    ScriptOp::FillGradient {
+                        shape,
+                        stops,
+                        dither: GradientDither::Off,
+                    },
+                    ScriptOp::DrawRect {
+                        width: 32.0,
+                        height: 32.0,
+                        flag: 0x01,
+                    },
+                ]
+                .into(),
+            );
+            state.root_id = Some("root".to_string());
+            let surface = skia_safe::surfaces::raster_n32_premul((32, 32)).expect("surface");
+            let mut renderer = Renderer::from_surface(surface, None);
+            renderer.redraw(&state);
+            let image = renderer.surface_mut().image_snapshot();
+            let pixmap = image.peek_pixels().expect("pixels");
+            pixmap.get_color((x, y))
+        };
+
+        let linear = GradientShape::Linear {
+            start_x: 0.0,
+            start_y: 0.0,
+            end_x: 32.0,
+            end_y: 0.0,
+        };
+        let three = vec![(0.0, Color::RED), (0.5, Color::GREEN), (1.0, Color::BLUE)];
+        let middle = draw(linear, three, 16, 16);
+        assert!(middle.g() > 200 && middle.r() < 16 && middle.b() < 32);
+
+        let halves = vec![
+            (0.0, Color::RED),
+            (0.5, Color::RED),
+            (0.5, Color::BLUE),
+            (1.0, Color::BLUE),
+        ];
+        let conic = |start_angle| GradientShape::Conic {
+            center_x: 16.0,
+            center_y: 16.0,
+            start_angle,
+        };
+        assert_eq!(draw(conic(0.0), halves.clone(), 16, 28), Color::RED);
+        assert_eq!(draw(conic(0.0), halves.clone(), 16, 4), Color::BLUE);
+        let flipped = conic(std::f32::consts::PI);
+        assert_eq!(draw(flipped, halves.clone(), 16, 28), Color::BLUE);
+        assert_eq!(draw(flipped, halves, 16, 4), Color::RED);
+    }
+
+    #[test]
+    fn script_transforms_move_cached_scripts() {
+        let rect = ScriptOp::DrawRect {
+            width: 4.0,
+            height: 4.0,
+            flag: 0x01,
+        };
+        let mut state = RenderState::default();
+        state.scripts.insert(
+            "row".to_string(),
+            vec![
+                ScriptOp::FillColor(Color::RED),
+                ScriptOp::Translate(8.0, 0.0),
+                rect.clone(),
+            ]
+            .into(),
+        );
+        state.scripts.insert(
+            "root".to_string(),
+            vec![
+                ScriptOp::DrawScript("row".to_string()),
+                ScriptOp::FillColor(Color::BLUE),
+                rect,
+            ]
+            .into(),
+        );
+        state.root_id = Some("root".to_string());
+
+        let pixel = |state: &RenderState, x, y| {
+            let surface = skia_safe::surfaces::raster_n32_premul((16, 16)).expect("surface");
+            let mut renderer = Renderer::from_surface(surface, None);
+            renderer.redraw(state);
+            let image = renderer.surface_mut().image_snapshot();
+            let pixmap = image.peek_pixels().expect("pixels");
+            pixmap.get_color((x, y))
+        };
+        assert_eq!(pixel(&state, 10, 2), Color::BLUE);
+        assert_eq!(pixel(&state, 2, 2), Color::WHITE);
+
+        // The row moves down and doubles in size; its own translate no longer leaks out.
+        let mut matrix = Matrix::translate((-16.0, 8.0));
+        matrix.pre_scale((2.0, 2.0), None);
+        set_script_transform(&mut state, "row", Some(matrix));
+        assert_eq!(pixel(&state, 2, 10), Color::RED);
+        assert_eq!(pixel(&state, 7, 15), Color::RED);
+        assert_eq!(pixel(&state, 2, 2), Color::BLUE);
+        assert_eq!(pixel(&state, 10, 2), Color::WHITE);
+
+        set_script_transform(&mut state, "row", None);
+        assert_eq!(pixel(&state, 10, 2), Color::BLUE);
+        assert!(state.script_transforms.is_empty());
+    }
+
+    #[test]
+    fn outputs_draw_their_assigned_root() {
+        let fill = |color| {
+            vec![
+                ScriptOp::FillColor(color),
+                ScriptOp::DrawRect {
+                    width: 4.0,
+                    height: 4.0,
+                    flag: 0x01,
+                },
+            ]
+            .into()
+        };
+        let mut state = RenderState::default();
+        state.scripts.insert("root".to_string(), fill(Color::RED));
+        state.scripts.insert("panel".to_string(), fill(Color::BLUE));
+        state.root_id = Some("root".to_string());
+
+        let surface = skia_safe::surfaces::raster_n32_premul((4, 4)).expect("surface");
+        let mut renderer = Renderer::from_surface(surface, None);
+        renderer.set_output("HDMI-A-2", true);
+        let mut pixel = |state: &RenderState| {
+            renderer.redraw_output(state);
+            let image = renderer.surface_mut().image_snapshot();
+            let pixmap = image.peek_pixels().expect("pixels");
+            pixmap.get_color((2, 2))
+        };
+        assert_eq!(pixel(&state), Color::RED);
+        state
+            .output_roots
+            .insert("HDMI-A-2".to_string(), "panel".to_string());
+        assert_eq!(pixel(&state), Color::BLUE);
+        state
+            .output_roots
+            .insert("DP-1".to_string(), "root".to_string());
+        assert_eq!(pixel(&state), Color::BLUE);
+    }
+
+    #[test]
+    fn animated_sprites_pick_the_frame_for_the_clock() {
+        let frame = |duration_ms| SpriteFrame {
+            src: Rect::from_xywh(0.0, 0.0, 8.0, 8.0),
+            duration_ms,
+        };
+        let frames = vec![frame(100), frame(50), frame(50)];
+        assert_eq!(current_sprite_frame(&frames, 0), Some((0, Some(100))));
+        assert_eq!(current_sprite_frame(&frames, 120), Some((1, Some(150))));
+        assert_eq!(current_sprite_frame(&frames, 1175), Some((2, Some(1200))));
+        assert_eq!(current_sprite_frame(&frames[..1], 1175), Some((0, None)));
+        assert_eq!(current_sprite_frame(&[], 10), None);
+
+        let clock = AnimationClock::new();
+        clock.schedule(300);
+        clock.schedule(200);
+        assert_eq!(clock.next_due(), Some(200));
+        clock.begin_frame();
+        assert_eq!(clock.next_due(), None);
+    }
+
+    #[test]
+    fn sprite_cells_are_counted_row_by_row() {
+        let size = ISize::new(64, 32);
+        assert_eq!(
+            sprite_cell(size, 4, 2, 0),
+            Some(Rect::from_xywh(0.0, 0.0, 16.0, 16.0))
+        );
+        assert_eq!(
+            sprite_cell(size, 4, 2, 5),
+            Some(Rect::from_xywh(16.0, 16.0, 16.0, 16.0))
+        );
+        assert_eq!(sprite_cell(size, 4, 2, 9), sprite_cell(size, 4, 2, 1));
+        assert_eq!(sprite_cell(size, 0, 2, 1), None);
+        assert_eq!(sprite_cell(ISize::new(0, 0), 1, 1, 0), None);
+
+        // A 16x8 sheet with a red cell on the left and a blue one on the right.
+        let row = [[255u8, 0, 0, 255].repeat(8), [0, 0, 255, 255].repeat(8)].concat();
+        let info = ImageInfo::new((16, 8), ColorType::RGBA8888, AlphaType::Unpremul, None);
+        let sheet =
+            images::raster_from_data(&info, Data::new_copy(&row.repeat(8)), 64).expect("sheet");
+        let meta = ImageMeta {
+            width: 16,
+            height: 8,
+            format: "rgba".to_string(),
+            has_alpha: false,
+        };
+        let mut state = RenderState::default();
+        insert_static_image(&state.assets, "sheet", sheet, meta);
+        state.scripts.insert(
+            "icon".to_string(),
+            vec![ScriptOp::DrawSpriteCell {
+                image_id: "sheet".to_string(),
+                dst: Rect::from_xywh(0.0, 0.0, 8.0, 8.0),
+                columns: 2,
+                rows: 1,
+            }]
+            .into(),
+        );
+        state.root_id = Some("icon".to_string());
+        let pixel = |state: &RenderState| {
+            let surface = skia_safe::surfaces::raster_n32_premul((8, 8)).expect("surface");
+            let mut renderer = Renderer::from_surface(surface, None);
+            renderer.redraw(state);
+            let image = renderer.surface_mut().image_snapshot();
+            let pixmap = image.peek_pixels().expect("pixels");
+            pixmap.get_color((4, 4))
+        };
+        assert_eq!(pixel(&state), Color::RED);
+        set_script_param(&mut state, "icon", 1);
+        assert_eq!(pixel(&state), Color::BLUE);
+        set_script_param(&mut state, "icon", 2);
+        assert_eq!(pixel(&state), Color::RED);
+    }
+
+    #[test]
+    fn blinks_and_marquees_follow_the_clock() {
+        assert_eq!(blink_phase(1000, 0), Some((true, 500)));
+        assert_eq!(blink_phase(1000, 499), Some((true, 500)));
+        assert_eq!(blink_phase(1000, 500), Some((false, 1000)));
+        assert_eq!(blink_phase(1000, 1250), Some((true, 1500)));
+        assert_eq!(blink_phase(1, 10), None);
+
+        let marquee = Marquee {
+            width: 100.0,
+            speed: 50.0,
+        };
+        assert_eq!(marquee.offset(40.0, 0), 100.0);
+        assert_eq!(marquee.offset(40.0, 1000), 50.0);
+        assert_eq!(marquee.offset(40.0, 2800), 100.0);
+        let reverse = Marquee {
+            speed: -50.0,
+            ..marquee
+        };
+        assert_eq!(reverse.offset(40.0, 1000), 10.0);
+        let still = Marquee {
+            speed: 0.0,
+            ..marquee
+        };
+        assert_eq!(still.offset(40.0, 1000), 0.0);
+    }
+
+    #[test]
+    fn pixel_alignment_requires_integer_translation_and_edges() {
+        let rect = Rect::from_xywh(0.0, 0.0, 10.0, 4.0);
+        let mut matrix = Matrix::translate((3.0, 5.0));
+        assert!(is_pixel_aligned(&matrix, &rect));
+        assert!(!is_pixel_aligned(
+            &matrix,
+            &Rect::from_xywh(0.0, 0.0, 10.5, 4.0)
+        ));
+        matrix.pre_translate((0.25, 0.0));
+        assert!(!is_pixel_aligned(&matrix, &rect));
+        assert!(!is_pixel_aligned(&Matrix::scale((2.0, 2.0)), &rect));
+        assert!(!is_pixel_aligned(&Matrix::rotate_deg(90.0), &rect));
+    }
+
+    #[test]
+    fn pre_rotation_matches_the_input_rotation() {
+        let panel = (800.0, 480.0);
+        for degrees in [0, 90, 180, 270] {
+            let rotation = Rotation::from_degrees(degrees).expect("rotation");
+            let matrix = pre_rotation(rotation, panel);
+            let (scene_width, scene_height) = rotation.rotate_size(panel);
+            let bounds = matrix.map_rect(Rect::from_wh(scene_width, scene_height)).0;
+            assert_eq!(bounds.round(), IRect::from_wh(800, 480), "{degrees}");
+
+            let transform = crate::input_transform::InputTransform {
+                rotation,
+                ..Default::default()
+            };
+            let on_panel = matrix.map_point((30.0, 10.0));
+            let (x, y) = transform.map(panel, on_panel.x, on_panel.y);
+            assert!(
+                (x - 30.0).abs() < 1e-3 && (y - 10.0).abs() < 1e-3,
+                "{degrees}"
+            );
+        }
+    }
+
+    #[test]
+    fn tabular_positions_center_digits_in_fixed_cells() {
+        let chars = [('1', 4.0), ('.', 3.0), (' ', 2.0), ('8', 6.0)];
+        let (positions, width) = tabular_positions(chars.into_iter(), 6.0);
+        assert_eq!(positions, vec![1.0, 6.0, 11.0, 15.0]);
+        assert_eq!(width, 21.0);
+    }
+
+    #[test]
+    fn ellipsize_cuts_on_grapheme_boundaries() {
+        let measure = |s: &str| s.graphemes(true).count() as f32;
+        assert_eq!(ellipsize("short", 10.0, measure), "short");
+        assert_eq!(ellipsize("hello world", 6.0, measure), "hello\u{2026}");
+        assert_eq!(ellipsize("ab cd", 4.0, measure), "ab\u{2026}");
+
+        let flags = "\u{1F1E9}\u{1F1EA}\u{1F1EB}\u{1F1F7}e\u{301}x";
+        assert_eq!(
+            ellipsize(flags, 3.0, measure),
+            "\u{1F1E9}\u{1F1EA}\u{1F1EB}\u{1F1F7}\u{2026}"
+        );
+        assert_eq!(ellipsize(flags, 1.0, measure), "\u{2026}");
+        assert_eq!(ellipsize(flags, 0.5, measure), "");
+    }
+
+    #[test]
+    fn rounded_polygon_corners_are_clamped_to_half_edges() {
+        let square = polygon_vertices(4, 10.0);
+        assert_eq!(square.len(), 4);
+        assert!(square[0].x.abs() < 1e-4 && (square[0].y + 10.0).abs() < 1e-4);
+        assert!((square[1].x - 10.0).abs() < 1e-4 && square[1].y.abs() < 1e-4);
+
+        let (prev, vertex, next) = (square[3], square[0], square[1]);
+        assert_eq!(corner_radius(prev, vertex, next, 0.0), 0.0);
+        assert_eq!(corner_radius(prev, vertex, next, 2.0), 2.0);
+        let half_edge = (vertex - next).length() * 0.5;
+        assert!((corner_radius(prev, vertex, next, 100.0) - half_edge).abs() < 1e-3);
+
+        let star = star_vertices(5, 20.0, 8.0);
+        assert_eq!(star.len(), 10);
+        assert!((star[1].length() - 8.0).abs() < 1e-4);
+        assert!(!rounded_polygon_path(&star, 3.0).bounds().is_empty());
+        assert!(rounded_polygon_path(&star[..2], 3.0).is_empty());
+    }
+
+    #[test]
+    fn superellipse_tessellation_adapts_to_the_exponent() {
+        let circle = superellipse_quadrant(10.0, 10.0, 2.0, 0.05);
+        assert_eq!(circle.first(), Some(&Point::new(10.0, 0.0)));
+        let end = circle.last().expect("end point");
+        assert!(end.x.abs() < 1e-3 && (end.y - 10.0).abs() < 1e-4);
+        assert!(circle.iter().all(|p| (p.length() - 10.0).abs() < 0.05));
+
+        let squircle = superellipse_quadrant(10.0, 10.0, 5.0, 0.05);
+        assert!(squircle.len() > circle.len());
+        let coarse = superellipse_quadrant(10.0, 10.0, 5.0, 1.0);
+        assert!(coarse.len() < squircle.len());
+
+        let mut builder = PathBuilder::new();
+        add_superellipse(&mut builder, 40.0, 20.0, 4.0, 0.1);
+        let bounds = *builder.detach().bounds();
+        assert!((bounds.width() - 40.0).abs() < 1e-3);
+        assert!((bounds.height() - 20.0).abs() < 1e-3);
+
+        let mut empty = PathBuilder::new();
+        add_superellipse(&mut empty, 40.0, 20.0, 0.0, 0.1);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn texture_options_set_alpha_type_and_convert_color_space() {
+        assert!(TextureOptions::parse("straight", "none").is_err());
+        assert!(TextureOptions::parse("premul", "p3").is_err());
+        let options = TextureOptions::parse("unpremul", "linear_srgb").expect("options");
+        assert_eq!(options.alpha, TextureAlpha::Unpremul);
+
+        let pixel = [255, 0, 0, 128];
+        let (image, _) = decode_texture_image_with("rgba", 1, 1, &pixel, options).expect("decode");
+        assert_eq!(image.alpha_type(), AlphaType::Unpremul);
+        assert!(image.color_space().is_some_and(|space| space.is_srgb()));
+
+        let (image, _) = decode_texture_image("rgba", 1, 1, &pixel).expect("decode");
+        assert_eq!(image.alpha_type(), AlphaType::Premul);
+    }
+
+    #[test]
+    fn yuv_frames_convert_to_rgba() {
+        // 3x2 frame: the left chroma sample is red, the right one neutral under white luma.
+        let luma = [81, 81, 235, 81, 81, 235];
+        let nv12: Vec<u8> = luma.iter().copied().chain([90, 240, 128, 128]).collect();
+        let i420: Vec<u8> = luma.iter().copied().chain([90, 128, 240, 128]).collect();
+        let row = [255, 0, 0, 255, 255, 0, 0, 255, 255, 255, 255, 255];
+        for (format, data) in [("nv12", &nv12), ("yuv420p", &i420)] {
+            let rgba = yuv420_to_rgba(format, 3, 2, data).expect("convert");
+            assert_eq!((&rgba[..12], &rgba[12..]), (&row[..], &row[..]));
+            let (image, meta) = decode_texture_image(format, 3, 2, data).expect("decode");
+            assert_eq!(image.width(), 3);
+            assert_eq!((meta.format.as_str(), meta.has_alpha), (format, false));
+        }
+        assert!(decode_texture_image("nv12", 3, 2, &nv12[1..]).is_err());
+    }
+
+    #[test]
+    fn fonts_are_replaced_and_removed_by_id() {
+        let state = RenderState::default();
+        assert_eq!(decode_font(&[]).err().as_deref(), Some("empty font data"));
+        let err = insert_font(&state.assets, "bad", b"<html>").expect_err("not a font");
+        assert!(err.contains("6 bytes starting [3c, 68, 74, 6d]"), "{err}");
+        assert!(!state.assets.has_font("bad"));
+
+        let roboto = include_bytes!("../../../assets/fonts/roboto.ttf");
+        insert_font(&state.assets, "roboto", roboto).expect("font");
+        assert!(state.assets.has_font("roboto"));
+        assert_eq!(remove_font(&state.assets, "roboto"), Ok(true));
+        assert_eq!(remove_font(&state.assets, "roboto"), Ok(false));
+        assert!(measure_text(&state, Some("roboto"), 20.0, "x").is_err());
+    }
+
+    #[test]
+    fn measure_text_reports_width_and_metrics() {
+        let state = RenderState::default();
+        let roboto = include_bytes!("../../../assets/fonts/roboto.ttf");
+        insert_font(&state.assets, "roboto", roboto).expect("font");
+
+        let (width, metrics) = measure_text(&state, Some("roboto"), 20.0, "Hello").expect("hello");
+        let (double, _) = measure_text(&state, Some("roboto"), 40.0, "Hello").expect("double");
+        assert!(width > 0.0);
+        assert!((double - 2.0 * width).abs() < 0.5);
+        assert!(metrics.ascent < 0.0 && metrics.descent > 0.0);
+        assert_eq!(
+            measure_text(&state, Some("roboto"), 20.0, "")
+                .expect("empty")
+                .0,
+            0.0
+        );
+        assert!(measure_text(&state, Some("roboto"), 0.0, "x").is_err());
+        assert!(measure_text(&state, Some("unknown"), 20.0, "x").is_err());
+    }
+
+    #[test]
+    fn missing_glyphs_reports_uncovered_characters() {
+        let mut state = RenderState::default();
+        set_font_fallback(&mut state, FontFallback::new(Vec::new(), false));
+        let roboto = include_bytes!("../../../assets/fonts/roboto.ttf");
+        insert_font(&state.assets, "roboto", roboto).expect("font");
+
+        let text = "Hi \u{1F600}\u{FE0F}\n\u{E000}!";
+        assert_eq!(
+            missing_glyphs(&state, Some("roboto"), text),
+            Ok(vec![(3, '\u{1F600}'), (11, '\u{E000}')])
+        );
+        assert_eq!(missing_glyphs(&state, Some("roboto"), "plain"), Ok(vec![]));
+        assert!(missing_glyphs(&state, Some("unknown"), "x").is_err());
+
+        let mono = include_bytes!("../../../assets/fonts/roboto_mono.ttf");
+        let chain = vec![decode_font(mono).expect("mono")];
+        assert_eq!(
+            missing_glyphs(&state, Some("roboto"), "1\u{2215}2"),
+            Ok(vec![(1, '\u{2215}')])
+        );
+        set_font_fallback(&mut state, FontFallback::new(chain, false));
+        assert_eq!(
+            missing_glyphs(&state, Some("roboto"), "1\u{2215}2"),
+            Ok(vec![])
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::digest::{self, Digest};
+use crate::flight_recorder::log_line;
+use crate::geometry_guard::{self, ClampedValue};
+use crate::renderer::ScriptOp;
+
+const EXTENSION: &str = "script";
+/// Bytes of submitted scripts whose ops are kept in memory, or the stored budget if larger.
+const MEMORY_BYTES: u64 = 16 * 1024 * 1024;
+
+/// SHA-256 digest and length of a script's submitted bytes.
+type Key = (Digest, usize);
+type Parse = fn(&[u8]) -> Result<Vec<ScriptOp>, String>;
+
+/// Ops of a script with their geometry clamped, shared by every submission of its bytes.
+#[derive(Clone, Debug)]
+pub struct CheckedScript {
+    pub ops: Arc<Vec<ScriptOp>>,
+    /// Values the clamp replaced, reported again on every submission.
+    pub clamped: Vec<ClampedValue>,
+}
+
+impl CheckedScript {
+    fn new(mut ops: Vec<ScriptOp>) -> Self {
+        let clamped = geometry_guard::clamp(&mut ops);
+        Self {
+            ops: Arc::new(ops),
+            clamped,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    dir: Option<PathBuf>,
+    parsed: HashMap<Key, CheckedScript>,
+    /// Keys in `parsed`, oldest first, for eviction.
+    order: VecDeque<Key>,
+    memory_bytes: u64,
+    writer: Option<Sender<(PathBuf, Vec<u8>)>>,
+    stored: HashSet<Key>,
+    stored_bytes: u64,
+    max_bytes: u64,
+}
+
+impl Entries {
+    fn insert(&mut self, key: Key, script: CheckedScript) {
+        if self.parsed.insert(key, script).is_some() {
+            return;
+        }
+        self.order.push_back(key);
+        self.memory_bytes += key.1 as u64;
+        let budget = MEMORY_BYTES.max(self.max_bytes);
+        while self.memory_bytes > budget
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.parsed.remove(&oldest);
+            self.memory_bytes -= oldest.1 as u64;
+        }
+    }
+}
+
+/// Parsed scripts kept by content, optionally backed by a directory that survives reboots.
+///
+/// Scenic pushes whole graphs again when only part of them changed, so a script submitted
+/// with the same bytes as before shares the ops parsed and clamped the first time instead
+/// of going through the parser. The ops of the oldest scripts are dropped once their
+/// submitted bytes pass [`MEMORY_BYTES`].
+///
+/// With a directory open, scripts are also stored on disk as the bytes Scenic submitted,
+/// named by their SHA-256 digest, and parsed again when the cache is opened, so a new parser never
+/// reads stale output of an old one. Opening the cache at start moves that parsing ahead
+/// of the first submissions. Geometry reports and op stats still run on every submission.
+#[derive(Debug, Default)]
+pub struct ScriptCache {
+    entries: Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// What `get_stats` reports about the script cache.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScriptCacheStats {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl ScriptCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens `dir`, creating it if needed, and parses every script stored there in place
+    /// of the scripts held in memory. Stored scripts beyond `max_bytes` are removed oldest
+    /// first. `None` closes the directory. Returns the number of scripts loaded.
+    pub fn open(
+        &self,
+        dir: Option<PathBuf>,
+        max_bytes: u64,
+        parse: Parse,
+    ) -> Result<usize, String> {
+        let mut entries = self
+            .entries
+            .lock()
+            .map_err(|_| "script cache lock poisoned".to_string())?;
+        *entries = Entries::default();
+        let Some(dir) = dir else {
+            return Ok(0);
+        };
+        fs::create_dir_all(&dir)
+            .map_err(|err| format!("failed to create script cache {}: {err}", dir.display()))?;
+        entries.max_bytes = max_bytes;
+        let stored = prune(&dir, max_bytes)?;
+        for path in stored {
+            let Ok(bytes) = fs::read(&path) else {
+                continue;
+            };
+            match parse(&bytes) {
+                Ok(ops) => {
+                    let key = key(&bytes);
+                    entries.stored.insert(key);
+                    entries.stored_bytes += bytes.len() as u64;
+                    entries.insert(key, CheckedScript::new(ops));
+                }
+                Err(err) => {
+                    log_line!("Dropping cached script {}: {err}", path.display());
+                    let _ = fs::remove_file(&path);
+                }
+            }
+        }
+        let (writer, writes) = mpsc::channel::<(PathBuf, Vec<u8>)>();
+        thread::Builder::new()
+            .name("scenic-driver-script-cache".to_string())
+            .spawn(move || {
+                for (path, bytes) in writes {
+                    write_atomically(&path, &bytes);
+                }
+            })
+            .map_err(|err| format!("failed to spawn script cache thread: {err}"))?;
+        let loaded = entries.parsed.len();
+        entries.dir = Some(dir);
+        entries.writer = Some(writer);
+        Ok(loaded)
+    }
+
+    /// Checked ops of `script`, from the cache or parsed with `parse` and then cached.
+    pub fn parse(&self, script: &[u8], parse: Parse) -> Result<CheckedScript, String> {
+        let Ok(mut entries) = self.entries.lock() else {
+            return parse(script).map(CheckedScript::new);
+        };
+        let key = key(script);
+        if let Some(checked) = entries.parsed.get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(checked.clone());
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let checked = CheckedScript::new(parse(script)?);
+        entries.insert(key, checked.clone());
+        let size = script.len() as u64;
+        if entries.stored_bytes + size <= entries.max_bytes
+            && !entries.stored.contains(&key)
+            && let Some(dir) = &entries.dir
+        {
+            let path = dir.join(format!("{}.{EXTENSION}", digest::hex(&key.0)));
+            if let Some(writer) = &entries.writer
+                && writer.send((path, script.to_vec())).is_ok()
+            {
+                entries.stored.insert(key);
+                entries.stored_bytes += size;
+            }
+        }
+        Ok(checked)
+    }
+
+    pub fn stats(&self) -> Result<ScriptCacheStats, String> {
+        let entries = self
+            .entries
+            .lock()
+            .map_err(|_| "script cache lock poisoned".to_string())?;
+        Ok(ScriptCacheStats {
+            entries: entries.parsed.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        })
+    }
+}
+
+fn key(script: &[u8]) -> Key {
+    (digest::sha256(script), script.len())
+}
+
+/// Stored scripts in `dir`, newest first, after removing the oldest beyond `max_bytes`.
+fn prune(dir: &Path, max_bytes: u64) -> Result<Vec<PathBuf>, String> {
+    let mut stored = fs::read_dir(dir)
+        .map_err(|err| format!("failed to read script cache {}: {err}", dir.display()))?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != OsStr::new(EXTENSION) {
+                return None;
+            }
+            let metadata = fs::metadata(&path).ok()?;
+            Some((metadata.modified().ok()?, metadata.len(), path))
+        })
+        .collect::<Vec<_>>();
+    stored.sort_by_key(|(modified, _, _)| std::cmp::Reverse(*modified));
+    let mut total = 0;
+    let mut kept = Vec::with_capacity(stored.len());
+    for (_, len, path) in stored {
+        total += len;
+        if total > max_bytes {
+            let _ = fs::remove_file(&path);
+        } else {
+            kept.push(path);
+        }
+    }
+    Ok(kept)
+}
+
+/// Writes through a temporary file, synced before the rename and the directory after it, so
+/// a power cut never leaves a truncated script behind.
+fn write_atomically(path: &Path, bytes: &[u8]) {
+    let partial = path.with_extension("partial");
+    let written = File::create(&partial)
+        .and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&partial, path))
+        .and_then(|()| match path.parent() {
+            Some(dir) => File::open(dir)?.sync_all(),
+            None => Ok(()),
+        });
+    if let Err(err) = written {
+        log_line!("Failed to cache script {}: {err}", path.display());
+        let _ = fs::remove_file(&partial);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn parse_len(script: &[u8]) -> Result<Vec<ScriptOp>, String> {
+        if script.is_empty() {
+            return Err("empty script".to_string());
+        }
+        Ok(vec![ScriptOp::PushState; script.len()])
+    }
+
+    #[test]
+    fn scripts_survive_reopening_the_cache_directory() {
+        let dir = std::env::temp_dir().join(format!("skia-script-cache-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let cache = ScriptCache::new();
+        let first = cache.parse(b"abc", parse_len).expect("parsed");
+        let again = cache.parse(b"abc", parse_len).expect("cached");
+        assert_eq!(first.ops.len(), 3);
+        assert!(Arc::ptr_eq(&first.ops, &again.ops));
+        let stats = cache.stats().expect("stats");
+        assert_eq!((stats.entries, stats.hits, stats.misses), (1, 1, 1));
+        let big = vec![0; MEMORY_BYTES as usize];
+        assert!(cache.parse(&big, |_| Ok(Vec::new())).is_ok());
+        assert_eq!(cache.stats().expect("stats").entries, 1);
+        assert!(cache.parse(b"abc", parse_len).is_ok());
+        assert_eq!(cache.stats().expect("stats").misses, 3);
+
+        let cache = ScriptCache::new();
+        assert_eq!(cache.open(Some(dir.clone()), 1 << 20, parse_len), Ok(0));
+        assert!(cache.parse(b"", parse_len).is_err());
+        assert_eq!(
+            cache
+                .parse(b"abcd", parse_len)
+                .map(|checked| checked.ops.len()),
+            Ok(4)
+        );
+        assert_eq!(
+            cache
+                .parse(b"abcd", parse_len)
+                .map(|checked| checked.ops.len()),
+            Ok(4)
+        );
+        let stats = cache.stats().expect("stats");
+        assert_eq!((stats.entries, stats.hits, stats.misses), (1, 1, 2));
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while fs::read_dir(&dir).expect("dir").count() == 0 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        let stored = format!("{}.{EXTENSION}", digest::hex(&digest::sha256(b"abcd")));
+        assert!(dir.join(stored).exists());
+        fs::write(dir.join("junk.partial"), b"x").expect("partial");
+
+        let reopened = ScriptCache::new();
+        assert_eq!(reopened.open(Some(dir.clone()), 1 << 20, parse_len), Ok(1));
+        assert!(reopened.parse(b"abcd", parse_len).is_ok());
+        assert_eq!(reopened.stats().expect("stats").hits, 1);
+
+        // Over budget, stored scripts are dropped.
+        assert_eq!(reopened.open(Some(dir.clone()), 2, parse_len), Ok(0));
+        assert_eq!(reopened.open(None, 0, parse_len), Ok(0));
+        fs::remove_dir_all(&dir).expect("remove cache");
+    }
+}
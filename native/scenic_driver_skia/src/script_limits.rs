@@ -0,0 +1,198 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::flight_recorder::log_line;
+use crate::renderer::ScriptOp;
+
+/// Per-frame bounds on script execution. Zero leaves a bound off.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScriptLimits {
+    /// Ops executed across every script drawn in the frame.
+    pub max_ops: u64,
+    /// `draw_script` ops expanded in the frame.
+    pub max_expansions: u64,
+    /// Bytes of text drawn or added to paths in the frame.
+    pub max_text_bytes: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LimitKind {
+    Ops,
+    Expansions,
+    Text,
+}
+
+impl LimitKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Ops => "op",
+            Self::Expansions => "draw_script expansion",
+            Self::Text => "text",
+        }
+    }
+}
+
+/// What a single frame has used of its [`ScriptLimits`].
+#[derive(Debug, Default)]
+pub struct FrameBudget {
+    limits: ScriptLimits,
+    ops: u64,
+    expansions: u64,
+    text_bytes: u64,
+    exceeded: Option<LimitKind>,
+}
+
+impl FrameBudget {
+    pub fn new(limits: ScriptLimits) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    /// Charges `op` to the frame. Returns `false` once any limit has been exceeded; the
+    /// op and everything after it in the frame are then skipped.
+    pub fn charge(&mut self, op: &ScriptOp) -> bool {
+        if self.exceeded.is_some() {
+            return false;
+        }
+        let over = |used: u64, max: u64| max > 0 && used > max;
+        self.ops += 1;
+        if over(self.ops, self.limits.max_ops) {
+            self.exceeded = Some(LimitKind::Ops);
+            return false;
+        }
+        match op {
+            ScriptOp::DrawScript(..) | ScriptOp::FillPattern { .. } => {
+                self.expansions += 1;
+                if over(self.expansions, self.limits.max_expansions) {
+                    self.exceeded = Some(LimitKind::Expansions);
+                }
+            }
+            ScriptOp::DrawText(text)
+            | ScriptOp::TextPath(text)
+            | ScriptOp::DrawNumber(text)
+            | ScriptOp::DrawTextEllipsized { text, .. } => {
+                self.text_bytes += text.len() as u64;
+                if over(self.text_bytes, self.limits.max_text_bytes) {
+                    self.exceeded = Some(LimitKind::Text);
+                }
+            }
+            _ => {}
+        }
+        self.exceeded.is_none()
+    }
+
+    pub fn exceeded(&self) -> Option<LimitKind> {
+        self.exceeded
+    }
+}
+
+/// Guards the render thread against runaway scripts.
+///
+/// A generator bug once submitted a two million op script that held the display thread
+/// for seconds. Frames that exceed a limit stop drawing scripts where they are, restore
+/// the canvas and still draw overlays; the violation is counted for `get_stats` and
+/// logged the first time each limit trips.
+#[derive(Debug, Default)]
+pub struct ScriptGuard {
+    max_ops: AtomicU64,
+    max_expansions: AtomicU64,
+    max_text_bytes: AtomicU64,
+    ops: AtomicU64,
+    expansions: AtomicU64,
+    text: AtomicU64,
+}
+
+impl ScriptGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_limits(&self, limits: ScriptLimits) {
+        self.max_ops.store(limits.max_ops, Ordering::Relaxed);
+        self.max_expansions
+            .store(limits.max_expansions, Ordering::Relaxed);
+        self.max_text_bytes
+            .store(limits.max_text_bytes, Ordering::Relaxed);
+    }
+
+    pub fn limits(&self) -> ScriptLimits {
+        ScriptLimits {
+            max_ops: self.max_ops.load(Ordering::Relaxed),
+            max_expansions: self.max_expansions.load(Ordering::Relaxed),
+            max_text_bytes: self.max_text_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn finish_frame(&self, budget: &FrameBudget) {
+        let Some(kind) = budget.exceeded() else {
+            return;
+        };
+        let counter = match kind {
+            LimitKind::Ops => &self.ops,
+            LimitKind::Expansions => &self.expansions,
+            LimitKind::Text => &self.text,
+        };
+        if counter.fetch_add(1, Ordering::Relaxed) == 0 {
+            log_line!(
+                "Frame exceeded the script {} limit; skipped the rest of its scripts",
+                kind.name()
+            );
+        }
+    }
+
+    /// Returns `(ops, expansions, text)`: frames cut short by each limit.
+    pub fn violations(&self) -> (u64, u64, u64) {
+        (
+            self.ops.load(Ordering::Relaxed),
+            self.expansions.load(Ordering::Relaxed),
+            self.text.load(Ordering::Relaxed),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_budget_stops_at_each_limit() {
+        let mut unlimited = FrameBudget::default();
+        for _ in 0..10_000 {
+            assert!(unlimited.charge(&ScriptOp::BeginPath));
+        }
+
+        let mut budget = FrameBudget::new(ScriptLimits {
+            max_ops: 3,
+            ..ScriptLimits::default()
+        });
+        assert!(budget.charge(&ScriptOp::BeginPath));
+        assert!(budget.charge(&ScriptOp::BeginPath));
+        assert!(budget.charge(&ScriptOp::BeginPath));
+        assert!(!budget.charge(&ScriptOp::BeginPath));
+        assert!(!budget.charge(&ScriptOp::BeginPath));
+        assert_eq!(budget.exceeded(), Some(LimitKind::Ops));
+
+        let mut budget = FrameBudget::new(ScriptLimits {
+            max_expansions: 1,
+            max_text_bytes: 4,
+            ..ScriptLimits::default()
+        });
+        assert!(budget.charge(&ScriptOp::DrawScript("child".to_string())));
+        assert!(budget.charge(&ScriptOp::DrawText("abcd".to_string())));
+        assert!(!budget.charge(&ScriptOp::DrawNumber("1".to_string())));
+        assert_eq!(budget.exceeded(), Some(LimitKind::Text));
+
+        let guard = ScriptGuard::new();
+        guard.set_limits(ScriptLimits {
+            max_expansions: 1,
+            ..ScriptLimits::default()
+        });
+        let mut budget = FrameBudget::new(guard.limits());
+        assert!(budget.charge(&ScriptOp::DrawScript("a".to_string())));
+        assert!(!budget.charge(&ScriptOp::DrawScript("b".to_string())));
+        guard.finish_frame(&budget);
+        guard.finish_frame(&FrameBudget::default());
+        assert_eq!(guard.violations(), (0, 1, 0));
+    }
+}
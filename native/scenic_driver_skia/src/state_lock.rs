@@ -0,0 +1,153 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, TryLockError};
+use std::thread;
+
+use rustler::{LocalPid, OwnedEnv};
+
+use crate::flight_recorder::log_line;
+use crate::renderer::{self, RenderState};
+
+rustler::atoms! {
+    render_state_recovered
+}
+
+/// What locking the render state does after a thread panicked while holding it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PoisonPolicy {
+    /// The scene is cleared and the lock is usable again.
+    #[default]
+    Recover,
+    /// Every later lock fails until the renderer is restarted.
+    Fail,
+}
+
+impl PoisonPolicy {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "recover" => Ok(Self::Recover),
+            "fail" => Ok(Self::Fail),
+            _ => Err(format!("unsupported poisoned state policy: {value}")),
+        }
+    }
+}
+
+/// The render state is poisoned and the policy is [`PoisonPolicy::Fail`].
+#[derive(Debug)]
+pub struct Poisoned;
+
+/// The render state shared by the NIFs and the render thread.
+///
+/// A panic while the state is locked leaves it half updated, and a plain mutex then
+/// refuses every later lock, so one bug would keep the display frozen until the BEAM
+/// restarts. Under [`PoisonPolicy::Recover`] the next lock instead clears the scene, keeps
+/// assets and configuration, and sends `{:render_state_recovered, count}` to the target so
+/// the driver can submit its scripts again.
+#[derive(Debug)]
+pub struct RenderStateLock {
+    state: Mutex<RenderState>,
+    fail: AtomicBool,
+    recoveries: AtomicU64,
+    target: Mutex<Option<LocalPid>>,
+}
+
+impl RenderStateLock {
+    pub fn new(state: RenderState) -> Self {
+        Self {
+            state: Mutex::new(state),
+            fail: AtomicBool::new(false),
+            recoveries: AtomicU64::new(0),
+            target: Mutex::new(None),
+        }
+    }
+
+    pub fn configure(&self, policy: PoisonPolicy, target: Option<LocalPid>) -> Result<(), String> {
+        let mut current = self
+            .target
+            .lock()
+            .map_err(|_| "render state target lock poisoned".to_string())?;
+        *current = target;
+        self.fail
+            .store(policy == PoisonPolicy::Fail, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn lock(&self) -> Result<MutexGuard<'_, RenderState>, Poisoned> {
+        match self.state.lock() {
+            Ok(state) => Ok(state),
+            Err(poisoned) => self.recover(poisoned.into_inner()),
+        }
+    }
+
+    /// Like [`RenderStateLock::lock`], but `None` while another thread holds the state.
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, RenderState>> {
+        match self.state.try_lock() {
+            Ok(state) => Some(state),
+            Err(TryLockError::Poisoned(poisoned)) => self.recover(poisoned.into_inner()).ok(),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+
+    /// Number of times the state was recovered after a panic.
+    pub fn recoveries(&self) -> u64 {
+        self.recoveries.load(Ordering::Relaxed)
+    }
+
+    fn recover<'a>(
+        &self,
+        mut state: MutexGuard<'a, RenderState>,
+    ) -> Result<MutexGuard<'a, RenderState>, Poisoned> {
+        if self.fail.load(Ordering::Relaxed) {
+            return Err(Poisoned);
+        }
+        renderer::clear_scene(&mut state);
+        state.damage.add_full();
+        self.state.clear_poison();
+        let count = self.recoveries.fetch_add(1, Ordering::Relaxed) + 1;
+        log_line!("Render state poisoned by a panic; scene cleared (recovery {count})");
+        let target = self.target.lock().ok().and_then(|target| *target);
+        if let Some(pid) = target {
+            // NIF threads cannot send from an owned env, so the message leaves from its own.
+            let _ = thread::Builder::new()
+                .name("scenic-driver-recovered".to_string())
+                .spawn(move || {
+                    let mut env = OwnedEnv::new();
+                    let _ = env.send_and_clear(&pid, |_| (render_state_recovered(), count));
+                });
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+
+    fn poison(lock: &RenderStateLock) {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _state = lock.lock();
+            panic!("poison the render state");
+        }));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn poisoned_state_is_cleared_or_refused_by_policy() {
+        let lock = RenderStateLock::new(RenderState::default());
+        lock.lock().expect("lock").root_id = Some("_root_".to_string());
+        poison(&lock);
+        let state = lock.try_lock().expect("recovered");
+        assert_eq!(state.root_id, None);
+        drop(state);
+        assert_eq!(lock.recoveries(), 1);
+        assert!(lock.lock().is_ok());
+
+        assert_eq!(PoisonPolicy::parse("fail"), Ok(PoisonPolicy::Fail));
+        assert!(PoisonPolicy::parse("panic").is_err());
+        lock.configure(PoisonPolicy::Fail, None).expect("configure");
+        poison(&lock);
+        assert!(lock.lock().is_err());
+        assert!(lock.try_lock().is_none());
+        assert_eq!(lock.recoveries(), 1);
+    }
+}
@@ -0,0 +1,291 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const MAX_CLIENTS: usize = 8;
+/// Scripts listed per frame, slowest first.
+pub const REPORTED_SCRIPTS: usize = 8;
+
+/// Wire format of exported frame records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatsFormat {
+    /// One JSON object per line.
+    Json,
+    /// Length-prefixed big-endian records, see [`FrameStats::encode`].
+    Binary,
+}
+
+impl StatsFormat {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "json" => Ok(Self::Json),
+            "binary" => Ok(Self::Binary),
+            other => Err(format!("unsupported stats format: {other}")),
+        }
+    }
+}
+
+/// What one rendered frame did and how long it took.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    pub frame: u64,
+    pub timestamp_us: u64,
+    pub frame_us: u64,
+    /// Script ops executed, including those of nested scripts.
+    pub ops: u64,
+    /// Distinct script ids drawn.
+    pub scripts: u64,
+    pub text_cache_hits: u64,
+    pub text_cache_misses: u64,
+    /// Inclusive render time of the slowest scripts in microseconds, slowest first.
+    pub slowest: Vec<(String, u64)>,
+}
+
+impl FrameStats {
+    /// JSON lines look like
+    /// `{"frame":12,"timestamp_us":..,"frame_us":850,"ops":120,..,"slowest":[["_root_",800]]}`.
+    ///
+    /// Binary records are a `u32` length of the rest, then `u64` frame and timestamp,
+    /// `u32` frame time, ops, scripts, text cache hits and misses, a `u16` script count
+    /// and per script a `u16` id length, the id and a `u32` time, all big-endian. Values
+    /// beyond a field's range saturate.
+    pub fn encode(&self, format: StatsFormat) -> Vec<u8> {
+        match format {
+            StatsFormat::Json => self.encode_json().into_bytes(),
+            StatsFormat::Binary => self.encode_binary(),
+        }
+    }
+
+    fn encode_json(&self) -> String {
+        let mut line = format!(
+            "{{\"frame\":{},\"timestamp_us\":{},\"frame_us\":{},\"ops\":{},\"scripts\":{},\
+             \"text_cache_hits\":{},\"text_cache_misses\":{},\"slowest\":[",
+            self.frame,
+            self.timestamp_us,
+            self.frame_us,
+            self.ops,
+            self.scripts,
+            self.text_cache_hits,
+            self.text_cache_misses
+        );
+        for (index, (id, time_us)) in self.slowest.iter().enumerate() {
+            if index > 0 {
+                line.push(',');
+            }
+            line.push('[');
+            push_json_string(&mut line, id);
+            let _ = write!(line, ",{time_us}]");
+        }
+        line.push_str("]}\n");
+        line
+    }
+
+    fn encode_binary(&self) -> Vec<u8> {
+        let narrow = |value: u64| u32::try_from(value).unwrap_or(u32::MAX);
+        let mut record = vec![0; 4];
+        record.extend_from_slice(&self.frame.to_be_bytes());
+        record.extend_from_slice(&self.timestamp_us.to_be_bytes());
+        for value in [
+            self.frame_us,
+            self.ops,
+            self.scripts,
+            self.text_cache_hits,
+            self.text_cache_misses,
+        ] {
+            record.extend_from_slice(&narrow(value).to_be_bytes());
+        }
+        let count = self.slowest.len().min(u16::MAX as usize);
+        record.extend_from_slice(&(count as u16).to_be_bytes());
+        for (id, time_us) in &self.slowest[..count] {
+            let id = &id.as_bytes()[..id.len().min(u16::MAX as usize)];
+            record.extend_from_slice(&(id.len() as u16).to_be_bytes());
+            record.extend_from_slice(id);
+            record.extend_from_slice(&narrow(*time_us).to_be_bytes());
+        }
+        let length = (record.len() - 4) as u32;
+        record[..4].copy_from_slice(&length.to_be_bytes());
+        record
+    }
+}
+
+fn push_json_string(out: &mut String, value: &str) {
+    out.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            ch if ch.is_control() => {
+                let _ = write!(out, "\\u{:04x}", ch as u32);
+            }
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+}
+
+#[derive(Debug)]
+struct Export {
+    path: PathBuf,
+    format: StatsFormat,
+    listener: UnixListener,
+    clients: Vec<UnixStream>,
+}
+
+impl Drop for Export {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Streams [`FrameStats`] of every rendered frame to clients of a Unix domain socket, so
+/// a profiler can follow the renderer on the device without going through the BEAM.
+///
+/// The render thread accepts pending connections and writes each record once per frame
+/// without blocking; a client that cannot take a whole record is disconnected, so slow
+/// readers never stall rendering or receive a partial record.
+#[derive(Debug, Default)]
+pub struct StatsExport {
+    enabled: AtomicBool,
+    export: Mutex<Option<Export>>,
+}
+
+impl StatsExport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Listens on `path` (replacing a stale socket there), or stops exporting and removes
+    /// the socket when `path` is `None`.
+    pub fn configure(&self, path: Option<&Path>, format: StatsFormat) -> Result<(), String> {
+        let mut export = self
+            .export
+            .lock()
+            .map_err(|_| "stats export lock poisoned".to_string())?;
+        *export = None;
+        self.enabled.store(false, Ordering::Relaxed);
+        let Some(path) = path else {
+            return Ok(());
+        };
+        if let Ok(metadata) = fs::symlink_metadata(path) {
+            if !metadata.file_type().is_socket() {
+                return Err(format!(
+                    "stats export path is not a socket: {}",
+                    path.display()
+                ));
+            }
+            fs::remove_file(path).map_err(|err| format!("remove {}: {err}", path.display()))?;
+        }
+        let listener =
+            UnixListener::bind(path).map_err(|err| format!("bind {}: {err}", path.display()))?;
+        listener
+            .set_nonblocking(true)
+            .map_err(|err| format!("stats export socket: {err}"))?;
+        *export = Some(Export {
+            path: path.to_path_buf(),
+            format,
+            listener,
+            clients: Vec::new(),
+        });
+        self.enabled.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Sends `stats` to every connected client, accepting new clients first.
+    pub fn publish(&self, stats: &FrameStats) {
+        let Ok(mut export) = self.export.lock() else {
+            return;
+        };
+        let Some(export) = export.as_mut() else {
+            return;
+        };
+        while let Ok((client, _)) = export.listener.accept() {
+            if export.clients.len() < MAX_CLIENTS && client.set_nonblocking(true).is_ok() {
+                export.clients.push(client);
+            }
+        }
+        if export.clients.is_empty() {
+            return;
+        }
+        let record = stats.encode(export.format);
+        export.clients.retain(|client| send(client, &record));
+    }
+}
+
+/// Writes all of `record` to `client` without blocking. `MSG_NOSIGNAL` keeps a client that
+/// disconnected from raising SIGPIPE, which the BEAM ignores but which kills other hosts
+/// of the C API.
+fn send(client: &UnixStream, record: &[u8]) -> bool {
+    let sent = unsafe {
+        libc::send(
+            client.as_raw_fd(),
+            record.as_ptr().cast(),
+            record.len(),
+            libc::MSG_NOSIGNAL,
+        )
+    };
+    usize::try_from(sent) == Ok(record.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+
+    #[test]
+    fn frames_stream_to_socket_clients() {
+        let stats = FrameStats {
+            frame: 3,
+            timestamp_us: 1_000,
+            frame_us: 850,
+            ops: 120,
+            scripts: 2,
+            text_cache_hits: 4,
+            text_cache_misses: 1,
+            slowest: vec![("_root_".to_string(), 800), ("a\"b".to_string(), 90)],
+        };
+        let binary = stats.encode(StatsFormat::Binary);
+        assert_eq!(binary.len(), 4 + 16 + 20 + 2 + (2 + 6 + 4) + (2 + 3 + 4));
+        assert_eq!(binary[..4], ((binary.len() - 4) as u32).to_be_bytes());
+        assert_eq!(binary[4..12], 3u64.to_be_bytes());
+        assert!(StatsFormat::parse("xml").is_err());
+
+        let path = std::env::temp_dir().join(format!("skia-stats-{}.sock", std::process::id()));
+        let export = StatsExport::new();
+        export
+            .configure(Some(&path), StatsFormat::Json)
+            .expect("configure");
+        assert!(export.is_enabled());
+        let client = UnixStream::connect(&path).expect("connect");
+        export.publish(&stats);
+
+        let mut line = String::new();
+        BufReader::new(client).read_line(&mut line).expect("read");
+        assert_eq!(
+            line,
+            "{\"frame\":3,\"timestamp_us\":1000,\"frame_us\":850,\"ops\":120,\"scripts\":2,\
+             \"text_cache_hits\":4,\"text_cache_misses\":1,\
+             \"slowest\":[[\"_root_\",800],[\"a\\\"b\",90]]}\n"
+        );
+
+        drop(UnixStream::connect(&path).expect("connect"));
+        export.publish(&stats);
+        let clients = |export: &StatsExport| {
+            let export = export.export.lock().expect("export");
+            export.as_ref().map_or(0, |export| export.clients.len())
+        };
+        assert_eq!(clients(&export), 0);
+
+        export.configure(None, StatsFormat::Json).expect("disable");
+        assert!(!export.is_enabled());
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::VecDeque;
+
+/// What happens to stream texture frames uploaded faster than frames are rendered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Each upload replaces a frame that has not been drawn yet, which is dropped.
+    #[default]
+    Latest,
+    /// Up to this many uploads wait behind the frame on screen and are drawn one per
+    /// rendered frame; the oldest waiting frame is dropped when the queue is full.
+    Queue(usize),
+}
+
+impl DropPolicy {
+    pub fn parse(name: &str, depth: u32) -> Result<Self, String> {
+        match name {
+            "latest" => Ok(Self::Latest),
+            "queue" if depth > 0 => Ok(Self::Queue(depth as usize)),
+            "queue" => Err("stream queue depth must be at least 1".to_string()),
+            other => Err(format!("unsupported stream drop policy: {other}")),
+        }
+    }
+
+    fn depth(self) -> usize {
+        match self {
+            Self::Latest => 0,
+            Self::Queue(depth) => depth,
+        }
+    }
+}
+
+/// Frames of one stream texture id: uploaded, drawn at least once, and discarded without
+/// ever being drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StreamCounts {
+    pub received: u64,
+    pub presented: u64,
+    pub dropped: u64,
+}
+
+/// The frame a stream texture currently shows and the uploads waiting behind it.
+#[derive(Debug)]
+pub struct StreamFrames<T> {
+    policy: DropPolicy,
+    current: Option<T>,
+    shown: bool,
+    pending: VecDeque<T>,
+    counts: StreamCounts,
+}
+
+impl<T> StreamFrames<T> {
+    pub fn new(policy: DropPolicy) -> Self {
+        Self {
+            policy,
+            current: None,
+            shown: false,
+            pending: VecDeque::new(),
+            counts: StreamCounts::default(),
+        }
+    }
+
+    /// Switches the policy, dropping waiting frames beyond the new queue depth.
+    pub fn set_policy(&mut self, policy: DropPolicy) {
+        self.policy = policy;
+        while self.pending.len() > policy.depth() {
+            self.pending.pop_front();
+            self.counts.dropped += 1;
+        }
+    }
+
+    pub fn push(&mut self, frame: T) {
+        self.counts.received += 1;
+        let idle = self.current.is_none() || (self.shown && self.pending.is_empty());
+        match self.policy {
+            _ if idle => {
+                self.current = Some(frame);
+                self.shown = false;
+            }
+            DropPolicy::Latest => {
+                self.current = Some(frame);
+                self.counts.dropped += 1;
+            }
+            DropPolicy::Queue(depth) => {
+                self.pending.push_back(frame);
+                if self.pending.len() > depth {
+                    self.pending.pop_front();
+                    self.counts.dropped += 1;
+                }
+            }
+        }
+    }
+
+    /// Moves the next waiting frame on screen once the current one has been drawn.
+    /// Returns `true` when the current frame changed.
+    pub fn advance(&mut self) -> bool {
+        if !self.shown {
+            return false;
+        }
+        let Some(next) = self.pending.pop_front() else {
+            return false;
+        };
+        self.current = Some(next);
+        self.shown = false;
+        true
+    }
+
+    pub fn current(&self) -> Option<&T> {
+        self.current.as_ref()
+    }
+
+    /// Returns the current frame for drawing, counting it as presented the first time.
+    pub fn present(&mut self) -> Option<&T> {
+        if self.current.is_some() && !self.shown {
+            self.shown = true;
+            self.counts.presented += 1;
+        }
+        self.current.as_ref()
+    }
+
+    /// Whether the next rendered frame would move a waiting frame on screen.
+    pub fn can_advance(&self) -> bool {
+        self.shown && !self.pending.is_empty()
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn counts(&self) -> StreamCounts {
+        self.counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stream_frames_drop_by_policy() {
+        let mut latest = StreamFrames::new(DropPolicy::Latest);
+        latest.push(1);
+        latest.push(2);
+        latest.push(3);
+        assert_eq!(latest.present(), Some(&3));
+        assert_eq!(latest.present(), Some(&3));
+        latest.push(4);
+        assert!(!latest.advance());
+        assert_eq!(latest.current(), Some(&4));
+        assert_eq!(
+            latest.counts(),
+            StreamCounts {
+                received: 4,
+                presented: 1,
+                dropped: 2,
+            }
+        );
+
+        let mut queued = StreamFrames::new(DropPolicy::Queue(2));
+        for frame in 1..=4 {
+            queued.push(frame);
+        }
+        assert_eq!(queued.pending(), 2);
+        assert!(!queued.advance());
+        assert_eq!(queued.present(), Some(&1));
+        assert!(queued.can_advance());
+        assert!(queued.advance());
+        assert_eq!(queued.present(), Some(&3));
+        assert!(queued.advance());
+        assert_eq!(queued.present(), Some(&4));
+        assert!(!queued.can_advance());
+        assert_eq!(queued.counts().dropped, 1);
+
+        queued.push(5);
+        queued.push(6);
+        queued.push(7);
+        queued.set_policy(DropPolicy::Latest);
+        assert_eq!(queued.pending(), 0);
+        assert_eq!(queued.current(), Some(&5));
+        assert_eq!(
+            queued.counts(),
+            StreamCounts {
+                received: 7,
+                presented: 3,
+                dropped: 3,
+            }
+        );
+
+        assert_eq!(DropPolicy::parse("queue", 3), Ok(DropPolicy::Queue(3)));
+        assert!(DropPolicy::parse("queue", 0).is_err());
+        assert!(DropPolicy::parse("oldest", 1).is_err());
+    }
+}
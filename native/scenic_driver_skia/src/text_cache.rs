@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use skia_safe::{Font, TextBlob};
+
+use crate::renderer::{TextAlign, TextBase};
+
+const MAX_TEXT_RUNS: usize = 512;
+
+/// Identifies a shaped run: the text, the typeface, the font size and the alignment the
+/// cached offset was computed for.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TextRunKey {
+    text: String,
+    typeface: u32,
+    size_bits: u32,
+    align: TextAlign,
+    base: TextBase,
+}
+
+impl TextRunKey {
+    pub fn new(text: &str, font: &Font, align: TextAlign, base: TextBase) -> Self {
+        Self {
+            text: text.to_string(),
+            typeface: font.typeface().unique_id(),
+            size_bits: font.size().to_bits(),
+            align,
+            base,
+        }
+    }
+}
+
+/// A shaped text blob and the offset that applies its alignment and baseline.
+pub type TextBlobCache = TextRunCache<(TextBlob, (f32, f32))>;
+
+/// Least recently used cache of shaped text runs, so labels and digits drawn every frame
+/// are measured and shaped once.
+///
+/// Keys include the typeface's unique id, so replaced fonts never hit stale entries;
+/// `clear` drops everything when the font cache changes.
+#[derive(Debug)]
+pub struct TextRunCache<V> {
+    capacity: usize,
+    runs: Mutex<TextRuns<V>>,
+}
+
+#[derive(Debug)]
+struct TextRuns<V> {
+    entries: HashMap<TextRunKey, (V, u64)>,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl<V: Clone> TextRunCache<V> {
+    pub fn new() -> Self {
+        Self::with_capacity(MAX_TEXT_RUNS)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            runs: Mutex::new(TextRuns {
+                entries: HashMap::new(),
+                clock: 0,
+                hits: 0,
+                misses: 0,
+            }),
+        }
+    }
+
+    /// Returns the cached run for `key`, shaping it with `make` on a miss. Runs `make`
+    /// fails to shape are not cached.
+    pub fn get_or_insert_with(
+        &self,
+        key: TextRunKey,
+        make: impl FnOnce() -> Option<V>,
+    ) -> Option<V> {
+        let Ok(mut runs) = self.runs.lock() else {
+            return make();
+        };
+        runs.clock += 1;
+        let clock = runs.clock;
+        if let Some((value, last_used)) = runs.entries.get_mut(&key) {
+            *last_used = clock;
+            let value = value.clone();
+            runs.hits += 1;
+            return Some(value);
+        }
+
+        runs.misses += 1;
+        let value = make()?;
+        if runs.entries.len() >= self.capacity
+            && let Some(oldest) = runs
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone())
+        {
+            runs.entries.remove(&oldest);
+        }
+        runs.entries.insert(key, (value.clone(), clock));
+        Some(value)
+    }
+
+    /// Returns `(hits, misses)` since the previous call and starts counting again.
+    pub fn take_counts(&self) -> (u64, u64) {
+        self.runs
+            .lock()
+            .map(|mut runs| {
+                (
+                    std::mem::take(&mut runs.hits),
+                    std::mem::take(&mut runs.misses),
+                )
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut runs) = self.runs.lock() {
+            runs.entries.clear();
+        }
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.runs.lock().map(|runs| runs.entries.len()).unwrap_or(0)
+    }
+}
+
+impl<V: Clone> Default for TextRunCache<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(text: &str) -> TextRunKey {
+        TextRunKey {
+            text: text.to_string(),
+            typeface: 1,
+            size_bits: 20.0f32.to_bits(),
+            align: TextAlign::Left,
+            base: TextBase::Alphabetic,
+        }
+    }
+
+    #[test]
+    fn text_run_cache_evicts_least_recently_used() {
+        let cache = TextRunCache::with_capacity(2);
+        assert_eq!(cache.get_or_insert_with(key("a"), || Some(1)), Some(1));
+        assert_eq!(cache.get_or_insert_with(key("b"), || Some(2)), Some(2));
+        assert_eq!(cache.get_or_insert_with(key("a"), || Some(10)), Some(1));
+
+        assert_eq!(cache.get_or_insert_with(key("c"), || Some(3)), Some(3));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get_or_insert_with(key("a"), || Some(10)), Some(1));
+        assert_eq!(cache.get_or_insert_with(key("b"), || Some(20)), Some(20));
+
+        assert_eq!(cache.get_or_insert_with(key("d"), || None), None);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.take_counts(), (2, 5));
+        assert_eq!(cache.take_counts(), (0, 0));
+        cache.clear();
+        assert_eq!(cache.len(), 0);
+    }
+}
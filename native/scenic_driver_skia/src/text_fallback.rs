@@ -0,0 +1,194 @@
+use std::sync::atomic::{AtomicU8, AtomicU32, AtomicU64, Ordering};
+
+use skia_safe::{Canvas, IRect, Paint, Point, TextBlob, surfaces};
+
+/// Frames drawn with CPU text after a failure before the GPU glyph atlas is tried again.
+const FALLBACK_FRAMES: u32 = 300;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextFallbackMode {
+    /// Rasterize text on the CPU for a while after a failed glyph upload.
+    Auto,
+    Always,
+    Off,
+}
+
+impl TextFallbackMode {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            "off" => Ok(Self::Off),
+            _ => Err(format!("unsupported text fallback mode: {value}")),
+        }
+    }
+}
+
+/// Software text rendering for GPU drivers whose glyph atlas allocation fails.
+///
+/// Some Mali drivers intermittently fail to allocate or upload the glyph atlas and text
+/// silently disappears. The renderer reports frames that drew text while the GPU context
+/// flagged an out-of-memory error; text runs are then rasterized into CPU images and
+/// drawn as images for the following frames.
+#[derive(Debug)]
+pub struct TextFallback {
+    mode: AtomicU8,
+    remaining: AtomicU32,
+    failures: AtomicU64,
+    runs: AtomicU64,
+}
+
+impl TextFallback {
+    pub fn new() -> Self {
+        Self {
+            mode: AtomicU8::new(TextFallbackMode::Auto as u8),
+            remaining: AtomicU32::new(0),
+            failures: AtomicU64::new(0),
+            runs: AtomicU64::new(0),
+        }
+    }
+
+    pub fn set_mode(&self, mode: TextFallbackMode) {
+        self.mode.store(mode as u8, Ordering::Relaxed);
+        self.remaining.store(0, Ordering::Relaxed);
+    }
+
+    fn mode(&self) -> TextFallbackMode {
+        match self.mode.load(Ordering::Relaxed) {
+            value if value == TextFallbackMode::Always as u8 => TextFallbackMode::Always,
+            value if value == TextFallbackMode::Off as u8 => TextFallbackMode::Off,
+            _ => TextFallbackMode::Auto,
+        }
+    }
+
+    /// Whether text in the current frame is rasterized on the CPU.
+    pub fn is_active(&self) -> bool {
+        match self.mode() {
+            TextFallbackMode::Always => true,
+            TextFallbackMode::Auto => self.remaining.load(Ordering::Relaxed) > 0,
+            TextFallbackMode::Off => false,
+        }
+    }
+
+    /// Records a frame whose text may not have reached the glyph atlas. Returns `true`
+    /// when the fallback was just switched on, so the frame is worth repainting.
+    pub fn report_failure(&self) -> bool {
+        if self.mode() != TextFallbackMode::Auto {
+            return false;
+        }
+        self.failures.fetch_add(1, Ordering::Relaxed);
+        self.remaining.swap(FALLBACK_FRAMES, Ordering::Relaxed) == 0
+    }
+
+    pub fn finish_frame(&self) {
+        let _ = self
+            .remaining
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |frames| {
+                frames.checked_sub(1)
+            });
+    }
+
+    /// Returns `(failures, runs)`: detected glyph upload failures and text runs drawn
+    /// through the CPU.
+    pub fn counts(&self) -> (u64, u64) {
+        (
+            self.failures.load(Ordering::Relaxed),
+            self.runs.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Draws `blob` at `origin` through a CPU raster image covering its device bounds.
+    /// Returns `false` when no image could be allocated and the caller should draw the
+    /// blob directly.
+    pub fn draw_blob(
+        &self,
+        canvas: &Canvas,
+        blob: &TextBlob,
+        origin: Point,
+        paint: &Paint,
+    ) -> bool {
+        let matrix = canvas.local_to_device_as_3x3();
+        let bounds = blob.bounds().with_offset(origin);
+        let device = matrix.map_rect(bounds).0.round_out();
+        let Some(clip) = canvas.device_clip_bounds() else {
+            return true;
+        };
+        // Two pixels of slack for anti-aliasing and strokes just outside the glyph bounds.
+        let area = IRect::from_ltrb(
+            (device.left - 2).max(clip.left),
+            (device.top - 2).max(clip.top),
+            (device.right + 2).min(clip.right),
+            (device.bottom + 2).min(clip.bottom),
+        );
+        if area.is_empty() {
+            return true;
+        }
+        let Some(mut surface) = surfaces::raster_n32_premul((area.width(), area.height())) else {
+            return false;
+        };
+        let raster = surface.canvas();
+        raster.translate((-area.left as f32, -area.top as f32));
+        raster.concat(&matrix);
+        raster.draw_text_blob(blob, origin, paint);
+        let image = surface.image_snapshot();
+
+        canvas.save();
+        canvas.reset_matrix();
+        canvas.draw_image(&image, (area.left as f32, area.top as f32), None);
+        canvas.restore();
+        self.runs.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+}
+
+impl Default for TextFallback {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use skia_safe::{Color, Font, FontMgr, FontStyle};
+
+    #[test]
+    fn text_fallback_activates_after_failures_and_draws_on_the_cpu() {
+        let fallback = TextFallback::new();
+        assert!(!fallback.is_active());
+        assert!(fallback.report_failure());
+        assert!(!fallback.report_failure());
+        assert!(fallback.is_active());
+        for _ in 0..FALLBACK_FRAMES {
+            fallback.finish_frame();
+        }
+        assert!(!fallback.is_active());
+        assert_eq!(fallback.counts(), (2, 0));
+
+        fallback.set_mode(TextFallbackMode::Off);
+        assert!(!fallback.report_failure());
+        assert!(!fallback.is_active());
+        fallback.set_mode(TextFallbackMode::Always);
+        assert!(fallback.is_active());
+        assert!(TextFallbackMode::parse("sometimes").is_err());
+
+        let Some(typeface) = FontMgr::new().legacy_make_typeface(None, FontStyle::normal()) else {
+            return;
+        };
+        let font = Font::new(typeface, 20.0);
+        let blob = TextBlob::from_str("Hi", &font).expect("blob");
+        let mut surface = surfaces::raster_n32_premul((40, 30)).expect("surface");
+        surface.canvas().clear(Color::BLACK);
+        let mut paint = Paint::default();
+        paint.set_color(Color::WHITE);
+        assert!(fallback.draw_blob(surface.canvas(), &blob, Point::new(4.0, 22.0), &paint));
+        assert_eq!(fallback.counts().1, 1);
+
+        let image = surface.image_snapshot();
+        let pixmap = image.peek_pixels().expect("pixels");
+        let lit = (0..30)
+            .flat_map(|y| (0..40).map(move |x| (x, y)))
+            .any(|point| pixmap.get_color(point) != Color::BLACK);
+        assert!(lit);
+    }
+}
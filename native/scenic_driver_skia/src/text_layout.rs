@@ -0,0 +1,160 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/// How `draw_text` lays out runs once a `text_wrap` op turns layout on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TextWrap {
+    /// One run per `draw_text`, as drawn without layout.
+    #[default]
+    None,
+    /// Lines break at `\n` and between words; words wider than a line break anywhere.
+    Word,
+    /// Lines break at `\n` and between any two graphemes.
+    Char,
+}
+
+impl TextWrap {
+    pub fn from_code(code: u16) -> Result<Self, String> {
+        match code {
+            0x00 => Ok(Self::None),
+            0x01 => Ok(Self::Word),
+            0x02 => Ok(Self::Char),
+            _ => Err("unsupported text_wrap value".to_string()),
+        }
+    }
+}
+
+/// Multi-line text settings of the draw state.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextLayout {
+    pub wrap: TextWrap,
+    /// Widest line in pixels; zero only breaks at `\n`.
+    pub max_width: f32,
+    /// Distance between baselines as a multiple of the font size.
+    pub line_height: f32,
+}
+
+impl Default for TextLayout {
+    fn default() -> Self {
+        Self {
+            wrap: TextWrap::None,
+            max_width: 0.0,
+            line_height: 1.2,
+        }
+    }
+}
+
+impl TextLayout {
+    /// Breaks `text` into lines with `measure` giving the drawn width of a line. Spaces
+    /// at a break are dropped; a line always holds at least one grapheme.
+    pub fn lines<'a>(&self, text: &'a str, measure: impl Fn(&str) -> f32) -> Vec<&'a str> {
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let paragraph = paragraph.strip_suffix('\r').unwrap_or(paragraph);
+            match self.wrap {
+                _ if self.max_width <= 0.0 => lines.push(paragraph),
+                TextWrap::None => lines.push(paragraph),
+                TextWrap::Word => {
+                    let pieces = paragraph.split_word_bound_indices();
+                    break_paragraph(paragraph, pieces, self.max_width, &measure, &mut lines);
+                }
+                TextWrap::Char => {
+                    let pieces = paragraph.grapheme_indices(true);
+                    break_paragraph(paragraph, pieces, self.max_width, &measure, &mut lines);
+                }
+            }
+        }
+        lines
+    }
+}
+
+fn break_paragraph<'a>(
+    paragraph: &'a str,
+    pieces: impl Iterator<Item = (usize, &'a str)>,
+    max_width: f32,
+    measure: &impl Fn(&str) -> f32,
+    lines: &mut Vec<&'a str>,
+) {
+    let (mut start, mut end) = (0, 0);
+    for (offset, piece) in pieces {
+        let piece_end = offset + piece.len();
+        let blank = piece.trim().is_empty();
+        if blank && start == end && start > 0 {
+            // Whitespace at a break does not carry over to the next line.
+            (start, end) = (piece_end, piece_end);
+            continue;
+        }
+        if measure(paragraph[start..piece_end].trim_end()) <= max_width {
+            end = piece_end;
+            continue;
+        }
+        if end > start {
+            lines.push(paragraph[start..end].trim_end());
+            (start, end) = if blank {
+                (piece_end, piece_end)
+            } else {
+                (offset, offset)
+            };
+            if blank || measure(piece) <= max_width {
+                end = piece_end;
+                continue;
+            }
+        }
+        // A piece wider than a whole line breaks between its graphemes.
+        for (index, grapheme) in piece.grapheme_indices(true) {
+            let grapheme_end = offset + index + grapheme.len();
+            if end > start && measure(&paragraph[start..grapheme_end]) > max_width {
+                lines.push(&paragraph[start..end]);
+                start = end;
+            }
+            end = grapheme_end;
+        }
+    }
+    lines.push(paragraph[start..end].trim_end());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_break_at_words_graphemes_and_newlines() {
+        // Ten pixels per character keeps the widths easy to follow.
+        let measure = |line: &str| line.chars().count() as f32 * 10.0;
+        let layout = TextLayout {
+            wrap: TextWrap::Word,
+            max_width: 100.0,
+            line_height: 1.2,
+        };
+        assert_eq!(
+            layout.lines("the quick brown fox jumps", measure),
+            vec!["the quick", "brown fox", "jumps"]
+        );
+        assert_eq!(
+            layout.lines("a\r\n\nextraordinarily long", measure),
+            vec!["a", "", "extraordin", "arily long"]
+        );
+
+        let chars = TextLayout {
+            wrap: TextWrap::Char,
+            ..layout
+        };
+        assert_eq!(
+            chars.lines("the quick brown", measure),
+            vec!["the quick", "brown"]
+        );
+        assert_eq!(
+            chars.lines("abcdefghijkl", measure),
+            vec!["abcdefghij", "kl"]
+        );
+
+        let unbounded = TextLayout {
+            max_width: 0.0,
+            ..layout
+        };
+        assert_eq!(
+            unbounded.lines("one line\ntwo", measure),
+            vec!["one line", "two"]
+        );
+        assert!(TextWrap::from_code(3).is_err());
+    }
+}
@@ -0,0 +1,272 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use skia_safe::{Canvas, Color, Paint, PaintStyle, PathBuilder};
+
+/// Milliseconds between pointer updates while a path plays, about one per frame.
+pub const STEP_MS: u64 = 16;
+/// How long a click at a waypoint keeps the button down.
+pub const CLICK_HOLD_MS: u64 = 90;
+
+const ARROW: [(f32, f32); 7] = [
+    (0.0, 0.0),
+    (0.0, 17.0),
+    (4.0, 13.0),
+    (7.0, 20.0),
+    (10.0, 19.0),
+    (7.0, 12.0),
+    (12.0, 12.0),
+];
+const PRESS_RADIUS: f32 = 11.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "linear" => Ok(Self::Linear),
+            "ease_in" => Ok(Self::EaseIn),
+            "ease_out" => Ok(Self::EaseOut),
+            "ease_in_out" => Ok(Self::EaseInOut),
+            other => Err(format!("unsupported pointer easing: {other}")),
+        }
+    }
+
+    /// Cubic easing of `t` in `0.0..=1.0`.
+    fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t * t,
+            Self::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Self::EaseInOut if t < 0.5 => 4.0 * t * t * t,
+            Self::EaseInOut => 1.0 - (2.0 - 2.0 * t).powi(3) / 2.0,
+        }
+    }
+}
+
+/// Point the pointer moves to in `duration_ms`, optionally clicking once it arrives.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Waypoint {
+    pub x: f32,
+    pub y: f32,
+    pub duration_ms: u32,
+    pub click: bool,
+}
+
+/// Eased pointer movement from `start` through each waypoint in turn.
+#[derive(Clone, Debug)]
+pub struct PointerPath {
+    start: (f32, f32),
+    waypoints: Vec<Waypoint>,
+    easing: Easing,
+    /// Jump to each waypoint as its segment starts instead of easing towards it.
+    jump: bool,
+}
+
+impl PointerPath {
+    pub fn new(
+        start: (f32, f32),
+        waypoints: Vec<Waypoint>,
+        easing: Easing,
+    ) -> Result<Self, String> {
+        if waypoints.is_empty() {
+            return Err("pointer path needs at least one waypoint".to_string());
+        }
+        if waypoints
+            .iter()
+            .any(|point| !(point.x.is_finite() && point.y.is_finite()))
+        {
+            return Err("pointer path coordinates must be finite".to_string());
+        }
+        Ok(Self {
+            start,
+            waypoints,
+            easing,
+            jump: false,
+        })
+    }
+
+    /// For reduced motion: the pointer appears at each waypoint right away and waits out
+    /// the segment there, so clicks keep their timing.
+    pub fn jumping(mut self) -> Self {
+        self.jump = true;
+        self
+    }
+
+    pub fn waypoints(&self) -> &[Waypoint] {
+        &self.waypoints
+    }
+
+    pub fn duration_ms(&self) -> u64 {
+        self.waypoints
+            .iter()
+            .map(|point| u64::from(point.duration_ms))
+            .sum()
+    }
+
+    /// Position at `elapsed_ms` and the number of waypoints reached by then.
+    pub fn sample(&self, elapsed_ms: u64) -> ((f32, f32), usize) {
+        let mut from = self.start;
+        let mut segment_start = 0u64;
+        for (index, point) in self.waypoints.iter().enumerate() {
+            let segment_end = segment_start + u64::from(point.duration_ms);
+            if elapsed_ms < segment_end {
+                let t = (elapsed_ms - segment_start) as f32 / point.duration_ms as f32;
+                let eased = if self.jump { 1.0 } else { self.easing.apply(t) };
+                let pos = (
+                    from.0 + (point.x - from.0) * eased,
+                    from.1 + (point.y - from.1) * eased,
+                );
+                return (pos, index);
+            }
+            from = (point.x, point.y);
+            segment_start = segment_end;
+        }
+        (from, self.waypoints.len())
+    }
+}
+
+/// Cursor drawn by a playing pointer path, in scene coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VirtualCursor {
+    pub pos: (f32, f32),
+    pub pressed: bool,
+}
+
+/// Rendered pointer driven by `play_pointer_path` for demos and walkthrough captures.
+///
+/// Each playback takes a new generation; a playback thread stops as soon as a newer one
+/// starts or the pointer is hidden.
+#[derive(Debug, Default)]
+pub struct VirtualPointer {
+    cursor: Mutex<Option<VirtualCursor>>,
+    generation: AtomicU64,
+}
+
+impl VirtualPointer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a playback, cancelling the one in progress, and returns its generation.
+    pub fn begin(&self) -> u64 {
+        self.generation.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub fn is_current(&self, generation: u64) -> bool {
+        self.generation.load(Ordering::Relaxed) == generation
+    }
+
+    pub fn set(&self, cursor: VirtualCursor) {
+        if let Ok(mut current) = self.cursor.lock() {
+            *current = Some(cursor);
+        }
+    }
+
+    /// Cancels any playback and stops drawing the cursor.
+    pub fn hide(&self) {
+        self.begin();
+        if let Ok(mut current) = self.cursor.lock() {
+            *current = None;
+        }
+    }
+
+    pub fn cursor(&self) -> Option<VirtualCursor> {
+        self.cursor.lock().ok().and_then(|cursor| *cursor)
+    }
+}
+
+/// Draws an arrow with its tip at the cursor position, ringed while the button is down.
+pub fn draw(canvas: &Canvas, cursor: VirtualCursor) {
+    let mut fill = Paint::default();
+    fill.set_anti_alias(true);
+    fill.set_color(Color::WHITE);
+    let mut outline = fill.clone();
+    outline.set_style(PaintStyle::Stroke);
+    outline.set_stroke_width(1.5);
+    outline.set_color(Color::BLACK);
+
+    canvas.save();
+    canvas.translate(cursor.pos);
+    if cursor.pressed {
+        let mut ring = fill.clone();
+        ring.set_color(Color::from_argb(96, 0, 0, 0));
+        canvas.draw_circle((0.0, 0.0), PRESS_RADIUS, &ring);
+        ring.set_style(PaintStyle::Stroke);
+        ring.set_stroke_width(2.0);
+        ring.set_color(Color::from_argb(220, 255, 255, 255));
+        canvas.draw_circle((0.0, 0.0), PRESS_RADIUS, &ring);
+    }
+    let mut arrow = PathBuilder::new();
+    arrow.move_to(ARROW[0]);
+    for point in &ARROW[1..] {
+        arrow.line_to(*point);
+    }
+    arrow.close();
+    let arrow = arrow.detach();
+    canvas.draw_path(&arrow, &fill);
+    canvas.draw_path(&arrow, &outline);
+    canvas.restore();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pointer_path_eases_through_waypoints() {
+        let waypoints = vec![
+            Waypoint {
+                x: 100.0,
+                y: 0.0,
+                duration_ms: 100,
+                click: false,
+            },
+            Waypoint {
+                x: 100.0,
+                y: 50.0,
+                duration_ms: 0,
+                click: true,
+            },
+        ];
+        let path = PointerPath::new((0.0, 0.0), waypoints.clone(), Easing::Linear).expect("path");
+        assert_eq!(path.duration_ms(), 100);
+        assert_eq!(path.sample(0), ((0.0, 0.0), 0));
+        assert_eq!(path.sample(25), ((25.0, 0.0), 0));
+        assert_eq!(path.sample(100), ((100.0, 50.0), 2));
+
+        let eased =
+            PointerPath::new((0.0, 0.0), waypoints.clone(), Easing::EaseInOut).expect("path");
+        assert!(eased.sample(25).0.0 < 25.0);
+        assert_eq!(eased.sample(50).0, (50.0, 0.0));
+        assert!(eased.sample(75).0.0 > 75.0);
+
+        let jumping = PointerPath::new((0.0, 0.0), waypoints, Easing::Linear)
+            .expect("path")
+            .jumping();
+        assert_eq!(jumping.sample(1), ((100.0, 0.0), 0));
+        assert_eq!(jumping.sample(100), ((100.0, 50.0), 2));
+        assert!((Easing::EaseOut.apply(1.0) - 1.0).abs() < 1e-6);
+        assert!(Easing::parse("bounce").is_err());
+        assert!(PointerPath::new((0.0, 0.0), Vec::new(), Easing::Linear).is_err());
+
+        let pointer = VirtualPointer::new();
+        let first = pointer.begin();
+        assert!(pointer.is_current(first));
+        pointer.set(VirtualCursor {
+            pos: (1.0, 2.0),
+            pressed: false,
+        });
+        assert!(pointer.cursor().is_some());
+        pointer.hide();
+        assert!(!pointer.is_current(first));
+        assert_eq!(pointer.cursor(), None);
+    }
+}
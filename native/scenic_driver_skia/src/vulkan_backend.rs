@@ -0,0 +1,549 @@
+use std::ffi::c_void;
+
+use ash::vk::{self, Handle};
+use raw_window_handle::{HasDisplayHandle, HasWindowHandle};
+use skia_safe::{
+    ColorType, Surface,
+    gpu::{self, DirectContext, SurfaceOrigin, backend_render_targets, vk as skia_vk},
+};
+use winit::window::Window;
+
+use crate::gpu_info::GpuInfo;
+use crate::renderer::{RenderState, Renderer};
+
+/// Presents frames to a window through Vulkan, for boards whose GL stack is broken while
+/// their Vulkan driver works.
+///
+/// The renderer draws straight into the acquired swapchain image. Acquisition waits on a
+/// fence and every frame is submitted synchronously, so Skia never races the presentation
+/// engine and no semaphores have to be threaded through it. The swapchain is rebuilt
+/// lazily after a resize or when the compositor reports it out of date.
+pub struct VulkanPresenter {
+    device: VulkanDevice,
+    context: DirectContext,
+    swapchain_loader: ash::khr::swapchain::Device,
+    swapchain: vk::SwapchainKHR,
+    /// Skia surfaces wrapping the swapchain images, in swapchain order.
+    targets: Vec<Surface>,
+    acquire_fence: vk::Fence,
+    size: (u32, u32),
+    stale: bool,
+    gpu_info: GpuInfo,
+}
+
+/// Instance, window surface and logical device, destroyed in reverse order on drop.
+struct VulkanDevice {
+    entry: ash::Entry,
+    instance: ash::Instance,
+    surface_loader: ash::khr::surface::Instance,
+    surface: vk::SurfaceKHR,
+    physical_device: vk::PhysicalDevice,
+    device: ash::Device,
+    queue: vk::Queue,
+    queue_family: u32,
+}
+
+impl VulkanPresenter {
+    /// Sets up Vulkan for `window` and a renderer drawing through it.
+    pub fn new(window: &Window) -> Result<(Self, Renderer), String> {
+        let device = VulkanDevice::new(window)?;
+        let context = device.direct_context()?;
+        let swapchain_loader = ash::khr::swapchain::Device::new(&device.instance, &device.device);
+        let acquire_fence = unsafe {
+            device
+                .device
+                .create_fence(&vk::FenceCreateInfo::default(), None)
+        }
+        .map_err(|err| format!("failed to create Vulkan fence: {err}"))?;
+        let gpu_info = device.gpu_info();
+        let size = window.inner_size();
+        let mut presenter = Self {
+            device,
+            context: context.clone(),
+            swapchain_loader,
+            swapchain: vk::SwapchainKHR::null(),
+            targets: Vec::new(),
+            acquire_fence,
+            size: (size.width.max(1), size.height.max(1)),
+            stale: true,
+            gpu_info,
+        };
+        presenter.rebuild_swapchain()?;
+        let surface = presenter
+            .targets
+            .first()
+            .cloned()
+            .ok_or_else(|| "Vulkan swapchain has no images".to_string())?;
+        Ok((presenter, Renderer::new_vulkan(surface, context)))
+    }
+
+    pub fn gpu_info(&self) -> GpuInfo {
+        self.gpu_info.clone()
+    }
+
+    /// Records the new window size; the swapchain follows on the next frame.
+    pub fn resize(&mut self, size: (u32, u32)) {
+        if size != self.size {
+            self.size = size;
+            self.stale = true;
+        }
+    }
+
+    /// Draws a frame into the next swapchain image and presents it. Returns `false` when
+    /// the frame was dropped because the swapchain went out of date and needs a redraw.
+    pub fn render(
+        &mut self,
+        renderer: &mut Renderer,
+        render_state: &RenderState,
+    ) -> Result<bool, String> {
+        let index = match self.acquire()? {
+            Some(index) => index,
+            None => {
+                self.rebuild_swapchain()?;
+                match self.acquire()? {
+                    Some(index) => index,
+                    None => return Ok(false),
+                }
+            }
+        };
+        let mut target = self.targets[index as usize].clone();
+        renderer.set_target(target.clone());
+        renderer.redraw(render_state);
+        self.context.flush_surface_with_access(
+            &mut target,
+            gpu::BackendSurfaceAccess::Present,
+            &gpu::FlushInfo::default(),
+        );
+        self.context.submit(gpu::SyncCpu::Yes);
+
+        let swapchains = [self.swapchain];
+        let indices = [index];
+        let present_info = vk::PresentInfoKHR::default()
+            .swapchains(&swapchains)
+            .image_indices(&indices);
+        match unsafe {
+            self.swapchain_loader
+                .queue_present(self.device.queue, &present_info)
+        } {
+            Ok(suboptimal) => {
+                self.stale |= suboptimal;
+                Ok(true)
+            }
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                self.stale = true;
+                Ok(false)
+            }
+            Err(err) => Err(format!("failed to present swapchain image: {err}")),
+        }
+    }
+
+    /// Index of the next swapchain image once it is free, or `None` when the swapchain
+    /// has to be rebuilt first.
+    fn acquire(&mut self) -> Result<Option<u32>, String> {
+        if self.stale {
+            return Ok(None);
+        }
+        let acquired = unsafe {
+            self.swapchain_loader.acquire_next_image(
+                self.swapchain,
+                u64::MAX,
+                vk::Semaphore::null(),
+                self.acquire_fence,
+            )
+        };
+        let index = match acquired {
+            Ok((index, suboptimal)) => {
+                self.stale |= suboptimal;
+                index
+            }
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                self.stale = true;
+                return Ok(None);
+            }
+            Err(err) => return Err(format!("failed to acquire swapchain image: {err}")),
+        };
+        let fences = [self.acquire_fence];
+        unsafe {
+            self.device
+                .device
+                .wait_for_fences(&fences, true, u64::MAX)
+                .and_then(|()| self.device.device.reset_fences(&fences))
+        }
+        .map_err(|err| format!("failed to wait for swapchain image: {err}"))?;
+        Ok(Some(index))
+    }
+
+    fn rebuild_swapchain(&mut self) -> Result<(), String> {
+        let device = &self.device;
+        unsafe { device.device.device_wait_idle() }
+            .map_err(|err| format!("failed to idle Vulkan device: {err}"))?;
+        self.targets.clear();
+
+        let capabilities = unsafe {
+            device
+                .surface_loader
+                .get_physical_device_surface_capabilities(device.physical_device, device.surface)
+        }
+        .map_err(|err| format!("failed to query surface capabilities: {err}"))?;
+        let formats = unsafe {
+            device
+                .surface_loader
+                .get_physical_device_surface_formats(device.physical_device, device.surface)
+        }
+        .map_err(|err| format!("failed to query surface formats: {err}"))?;
+        let (surface_format, skia_format, color_type) = pick_format(&formats)?;
+
+        let extent = if capabilities.current_extent.width != u32::MAX {
+            capabilities.current_extent
+        } else {
+            vk::Extent2D {
+                width: self.size.0.clamp(
+                    capabilities.min_image_extent.width,
+                    capabilities.max_image_extent.width,
+                ),
+                height: self.size.1.clamp(
+                    capabilities.min_image_extent.height,
+                    capabilities.max_image_extent.height,
+                ),
+            }
+        };
+        let mut image_count = capabilities.min_image_count + 1;
+        if capabilities.max_image_count > 0 {
+            image_count = image_count.min(capabilities.max_image_count);
+        }
+        let composite_alpha = [
+            vk::CompositeAlphaFlagsKHR::PRE_MULTIPLIED,
+            vk::CompositeAlphaFlagsKHR::OPAQUE,
+            vk::CompositeAlphaFlagsKHR::INHERIT,
+        ]
+        .into_iter()
+        .find(|flag| capabilities.supported_composite_alpha.contains(*flag))
+        .unwrap_or(vk::CompositeAlphaFlagsKHR::OPAQUE);
+
+        let old_swapchain = self.swapchain;
+        let create_info = vk::SwapchainCreateInfoKHR::default()
+            .surface(device.surface)
+            .min_image_count(image_count)
+            .image_format(surface_format.format)
+            .image_color_space(surface_format.color_space)
+            .image_extent(extent)
+            .image_array_layers(1)
+            .image_usage(
+                vk::ImageUsageFlags::COLOR_ATTACHMENT
+                    | vk::ImageUsageFlags::TRANSFER_SRC
+                    | vk::ImageUsageFlags::TRANSFER_DST,
+            )
+            .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .pre_transform(capabilities.current_transform)
+            .composite_alpha(composite_alpha)
+            .present_mode(vk::PresentModeKHR::FIFO)
+            .clipped(true)
+            .old_swapchain(old_swapchain);
+        let swapchain = unsafe { self.swapchain_loader.create_swapchain(&create_info, None) }
+            .map_err(|err| format!("failed to create Vulkan swapchain: {err}"))?;
+        if old_swapchain != vk::SwapchainKHR::null() {
+            unsafe { self.swapchain_loader.destroy_swapchain(old_swapchain, None) };
+        }
+        self.swapchain = swapchain;
+
+        let images = unsafe { self.swapchain_loader.get_swapchain_images(swapchain) }
+            .map_err(|err| format!("failed to get swapchain images: {err}"))?;
+        let dimensions = (extent.width as i32, extent.height as i32);
+        for image in images {
+            let image_info = unsafe {
+                skia_vk::ImageInfo::new(
+                    image.as_raw() as _,
+                    skia_vk::Alloc::default(),
+                    skia_vk::ImageTiling::OPTIMAL,
+                    skia_vk::ImageLayout::UNDEFINED,
+                    skia_format,
+                    1,
+                    self.device.queue_family,
+                    None,
+                    None,
+                    None,
+                )
+            };
+            let render_target = backend_render_targets::make_vk(dimensions, &image_info);
+            let surface = gpu::surfaces::wrap_backend_render_target(
+                &mut self.context,
+                &render_target,
+                SurfaceOrigin::TopLeft,
+                color_type,
+                None,
+                None,
+            )
+            .ok_or_else(|| "could not wrap swapchain image in a Skia surface".to_string())?;
+            self.targets.push(surface);
+        }
+        self.stale = false;
+        Ok(())
+    }
+}
+
+impl Drop for VulkanPresenter {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.device.device.device_wait_idle();
+        }
+        self.targets.clear();
+        // The renderer may still hold the context and a swapchain surface; abandoning the
+        // context lets them drop safely after the device is gone.
+        self.context.release_resources_and_abandon();
+        unsafe {
+            self.device.device.destroy_fence(self.acquire_fence, None);
+            if self.swapchain != vk::SwapchainKHR::null() {
+                self.swapchain_loader
+                    .destroy_swapchain(self.swapchain, None);
+            }
+        }
+    }
+}
+
+impl VulkanDevice {
+    fn new(window: &Window) -> Result<Self, String> {
+        let display_handle = window
+            .display_handle()
+            .map_err(|err| format!("failed to get display handle: {err}"))?
+            .as_raw();
+        let window_handle = window
+            .window_handle()
+            .map_err(|err| format!("failed to get window handle: {err}"))?
+            .as_raw();
+
+        let entry = unsafe { ash::Entry::load() }
+            .map_err(|err| format!("failed to load the Vulkan loader: {err}"))?;
+        let extensions = ash_window::enumerate_required_extensions(display_handle)
+            .map_err(|err| format!("Vulkan cannot present to this display: {err}"))?;
+        let app_info = vk::ApplicationInfo::default()
+            .application_name(c"scenic_driver_skia")
+            .api_version(vk::make_api_version(0, 1, 1, 0));
+        let instance_info = vk::InstanceCreateInfo::default()
+            .application_info(&app_info)
+            .enabled_extension_names(extensions);
+        let instance = unsafe { entry.create_instance(&instance_info, None) }
+            .map_err(|err| format!("failed to create Vulkan instance: {err}"))?;
+        let surface_loader = ash::khr::surface::Instance::new(&entry, &instance);
+
+        let surface = match unsafe {
+            ash_window::create_surface(&entry, &instance, display_handle, window_handle, None)
+        } {
+            Ok(surface) => surface,
+            Err(err) => {
+                unsafe { instance.destroy_instance(None) };
+                return Err(format!("failed to create Vulkan surface: {err}"));
+            }
+        };
+        let device = pick_physical_device(&instance, &surface_loader, surface).and_then(
+            |(physical_device, queue_family)| {
+                create_device(&instance, physical_device, queue_family)
+                    .map(|device| (physical_device, queue_family, device))
+            },
+        );
+        let (physical_device, queue_family, device) = match device {
+            Ok(values) => values,
+            Err(err) => {
+                unsafe {
+                    surface_loader.destroy_surface(surface, None);
+                    instance.destroy_instance(None);
+                }
+                return Err(err);
+            }
+        };
+        let queue = unsafe { device.get_device_queue(queue_family, 0) };
+
+        Ok(Self {
+            entry,
+            instance,
+            surface_loader,
+            surface,
+            physical_device,
+            device,
+            queue,
+            queue_family,
+        })
+    }
+
+    /// Skia context on this device, resolving Vulkan entry points through the loader.
+    fn direct_context(&self) -> Result<DirectContext, String> {
+        let get_device_proc_addr = self.instance.fp_v1_0().get_device_proc_addr;
+        let get_proc = |of: skia_vk::GetProcOf| unsafe {
+            let function = match of {
+                skia_vk::GetProcOf::Instance(instance, name) => self
+                    .entry
+                    .get_instance_proc_addr(vk::Instance::from_raw(instance as _), name),
+                skia_vk::GetProcOf::Device(device, name) => {
+                    get_device_proc_addr(vk::Device::from_raw(device as _), name)
+                }
+            };
+            function.map_or(std::ptr::null(), |function| function as *const c_void)
+        };
+        let backend_context = unsafe {
+            skia_vk::BackendContext::new(
+                self.instance.handle().as_raw() as _,
+                self.physical_device.as_raw() as _,
+                self.device.handle().as_raw() as _,
+                (self.queue.as_raw() as _, self.queue_family as usize),
+                &get_proc,
+            )
+        };
+        gpu::direct_contexts::make_vulkan(&backend_context, None)
+            .ok_or_else(|| "make_vulkan failed: could not create Skia direct context".to_string())
+    }
+
+    fn gpu_info(&self) -> GpuInfo {
+        let properties = unsafe {
+            self.instance
+                .get_physical_device_properties(self.physical_device)
+        };
+        let device_name = properties
+            .device_name_as_c_str()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let api = properties.api_version;
+        GpuInfo {
+            backend: "vulkan".to_string(),
+            gpu: true,
+            software: properties.device_type == vk::PhysicalDeviceType::CPU,
+            vendor: vendor_name(properties.vendor_id),
+            renderer: device_name,
+            version: format!(
+                "Vulkan {}.{}.{} (driver {:#x})",
+                vk::api_version_major(api),
+                vk::api_version_minor(api),
+                vk::api_version_patch(api),
+                properties.driver_version
+            ),
+            ..GpuInfo::default()
+        }
+    }
+}
+
+impl Drop for VulkanDevice {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_device(None);
+            self.surface_loader.destroy_surface(self.surface, None);
+            self.instance.destroy_instance(None);
+        }
+    }
+}
+
+/// First device with a queue family that can both draw and present to `surface`,
+/// preferring hardware GPUs over software rasterizers such as lavapipe.
+fn pick_physical_device(
+    instance: &ash::Instance,
+    surface_loader: &ash::khr::surface::Instance,
+    surface: vk::SurfaceKHR,
+) -> Result<(vk::PhysicalDevice, u32), String> {
+    let devices = unsafe { instance.enumerate_physical_devices() }
+        .map_err(|err| format!("failed to enumerate Vulkan devices: {err}"))?;
+    let rank = |device_type| match device_type {
+        vk::PhysicalDeviceType::DISCRETE_GPU => 0,
+        vk::PhysicalDeviceType::INTEGRATED_GPU => 1,
+        vk::PhysicalDeviceType::VIRTUAL_GPU => 2,
+        vk::PhysicalDeviceType::CPU => 4,
+        _ => 3,
+    };
+    let mut candidates: Vec<(u32, vk::PhysicalDevice, u32)> = devices
+        .into_iter()
+        .filter_map(|device| {
+            let families = unsafe { instance.get_physical_device_queue_family_properties(device) };
+            let family = families.iter().enumerate().find_map(|(index, family)| {
+                let index = index as u32;
+                let presents = unsafe {
+                    surface_loader.get_physical_device_surface_support(device, index, surface)
+                }
+                .unwrap_or(false);
+                (family.queue_flags.contains(vk::QueueFlags::GRAPHICS) && presents).then_some(index)
+            })?;
+            let properties = unsafe { instance.get_physical_device_properties(device) };
+            Some((rank(properties.device_type), device, family))
+        })
+        .collect();
+    candidates.sort_by_key(|(rank, _, _)| *rank);
+    candidates
+        .first()
+        .map(|(_, device, family)| (*device, *family))
+        .ok_or_else(|| "no Vulkan device can present to the window".to_string())
+}
+
+fn create_device(
+    instance: &ash::Instance,
+    physical_device: vk::PhysicalDevice,
+    queue_family: u32,
+) -> Result<ash::Device, String> {
+    let priorities = [1.0];
+    let queue_info = vk::DeviceQueueCreateInfo::default()
+        .queue_family_index(queue_family)
+        .queue_priorities(&priorities);
+    let extensions = [ash::khr::swapchain::NAME.as_ptr()];
+    let device_info = vk::DeviceCreateInfo::default()
+        .queue_create_infos(std::slice::from_ref(&queue_info))
+        .enabled_extension_names(&extensions);
+    unsafe { instance.create_device(physical_device, &device_info, None) }
+        .map_err(|err| format!("failed to create Vulkan device: {err}"))
+}
+
+/// Swapchain format Skia can draw into directly, with its Skia format and color type.
+fn pick_format(
+    formats: &[vk::SurfaceFormatKHR],
+) -> Result<(vk::SurfaceFormatKHR, skia_vk::Format, ColorType), String> {
+    formats
+        .iter()
+        .find_map(|format| match format.format {
+            vk::Format::B8G8R8A8_UNORM => Some((
+                *format,
+                skia_vk::Format::B8G8R8A8_UNORM,
+                ColorType::BGRA8888,
+            )),
+            vk::Format::R8G8B8A8_UNORM => Some((
+                *format,
+                skia_vk::Format::R8G8B8A8_UNORM,
+                ColorType::RGBA8888,
+            )),
+            _ => None,
+        })
+        .ok_or_else(|| "window surface offers no 8-bit RGBA swapchain format".to_string())
+}
+
+/// Vendor for a PCI or Khronos vendor id, falling back to the id in hex.
+fn vendor_name(vendor_id: u32) -> String {
+    let name = match vendor_id {
+        0x1002 => "AMD",
+        0x1010 => "Imagination Technologies",
+        0x10de => "NVIDIA",
+        0x13b5 => "ARM",
+        0x14e4 => "Broadcom",
+        0x5143 => "Qualcomm",
+        0x8086 => "Intel",
+        0x10005 => "Mesa",
+        _ => return format!("{vendor_id:#06x}"),
+    };
+    name.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swapchain_format_and_vendor_are_picked_for_skia() {
+        let format = |format| vk::SurfaceFormatKHR {
+            format,
+            color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        };
+        let formats = [
+            format(vk::Format::A2B10G10R10_UNORM_PACK32),
+            format(vk::Format::R8G8B8A8_UNORM),
+            format(vk::Format::B8G8R8A8_UNORM),
+        ];
+        let (picked, _, color_type) = pick_format(&formats).expect("format");
+        assert_eq!(picked.format, vk::Format::R8G8B8A8_UNORM);
+        assert_eq!(color_type, ColorType::RGBA8888);
+        assert!(pick_format(&formats[..1]).is_err());
+
+        assert_eq!(vendor_name(0x13b5), "ARM");
+        assert_eq!(vendor_name(0x1234), "0x1234");
+    }
+}
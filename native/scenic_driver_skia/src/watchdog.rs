@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use rustler::{LocalPid, OwnedEnv};
+
+use crate::flight_recorder::log_line;
+
+rustler::atoms! {
+    degraded_mode
+}
+
+const REPORTED_SCRIPTS: usize = 5;
+
+/// Frame deadline monitor shared between the NIF layer and the render thread.
+///
+/// When a frame takes longer than the deadline, the slowest scripts are logged, the
+/// renderer switches to degraded mode (optional effects are skipped) and the configured
+/// process receives `{:degraded_mode, frame_ms, deadline_ms}`. Degraded mode stays on
+/// until the watchdog is configured again.
+pub struct FrameWatchdog {
+    deadline_ms: AtomicU64,
+    degraded: AtomicBool,
+    target: Mutex<Option<LocalPid>>,
+}
+
+impl std::fmt::Debug for FrameWatchdog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FrameWatchdog")
+            .field("deadline_ms", &self.deadline_ms.load(Ordering::Relaxed))
+            .field("degraded", &self.is_degraded())
+            .finish()
+    }
+}
+
+impl FrameWatchdog {
+    pub fn new() -> Self {
+        Self {
+            deadline_ms: AtomicU64::new(0),
+            degraded: AtomicBool::new(false),
+            target: Mutex::new(None),
+        }
+    }
+
+    /// Sets the frame deadline (`0` disables the watchdog) and clears degraded mode.
+    pub fn configure(&self, deadline_ms: u64, target: Option<LocalPid>) -> Result<(), String> {
+        let mut current = self
+            .target
+            .lock()
+            .map_err(|_| "watchdog target lock poisoned".to_string())?;
+        *current = target;
+        self.deadline_ms.store(deadline_ms, Ordering::Relaxed);
+        self.degraded.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn deadline(&self) -> Option<Duration> {
+        match self.deadline_ms.load(Ordering::Relaxed) {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        }
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Relaxed)
+    }
+
+    /// Checks a finished frame against the deadline. Returns `true` when this frame
+    /// switched the renderer into degraded mode.
+    pub fn finish_frame(&self, elapsed: Duration, timings: &ScriptTimings) -> bool {
+        let Some(deadline) = self.deadline() else {
+            return false;
+        };
+        if elapsed <= deadline {
+            return false;
+        }
+
+        let slowest = timings
+            .slowest(REPORTED_SCRIPTS)
+            .iter()
+            .map(|(id, time)| format!("{id}={:.1}ms", time.as_secs_f64() * 1000.0))
+            .collect::<Vec<_>>()
+            .join(", ");
+        log_line!(
+            "Frame took {:.1}ms (deadline {}ms); slowest scripts: {slowest}",
+            elapsed.as_secs_f64() * 1000.0,
+            deadline.as_millis()
+        );
+
+        if self.degraded.swap(true, Ordering::Relaxed) {
+            return false;
+        }
+        log_line!("Entering degraded render mode; optional effects disabled");
+        if let Ok(target) = self.target.lock()
+            && let Some(pid) = *target
+        {
+            let frame_ms = elapsed.as_millis().min(u64::MAX as u128) as u64;
+            let deadline_ms = deadline.as_millis() as u64;
+            let mut env = OwnedEnv::new();
+            let _ = env.send_and_clear(&pid, |_| (degraded_mode(), frame_ms, deadline_ms));
+        }
+        true
+    }
+}
+
+/// Inclusive render time per script id, collected while the watchdog is enabled.
+pub struct ScriptTimings {
+    enabled: bool,
+    totals: HashMap<String, Duration>,
+}
+
+impl ScriptTimings {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            totals: HashMap::new(),
+        }
+    }
+
+    pub fn start(&self) -> Option<Instant> {
+        self.enabled.then(Instant::now)
+    }
+
+    pub fn record(&mut self, script_id: &str, started: Option<Instant>) {
+        if let Some(started) = started {
+            *self.totals.entry(script_id.to_string()).or_default() += started.elapsed();
+        }
+    }
+
+    /// Distinct script ids drawn.
+    pub fn scripts(&self) -> usize {
+        self.totals.len()
+    }
+
+    pub fn slowest(&self, count: usize) -> Vec<(String, Duration)> {
+        let mut totals: Vec<(String, Duration)> = self
+            .totals
+            .iter()
+            .map(|(id, time)| (id.clone(), *time))
+            .collect();
+        totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        totals.truncate(count);
+        totals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watchdog_enters_degraded_mode_once_over_deadline() {
+        let watchdog = FrameWatchdog::new();
+        let timings = ScriptTimings::new(true);
+        assert!(!watchdog.finish_frame(Duration::from_millis(500), &timings));
+
+        watchdog.configure(100, None).expect("configure");
+        assert!(!watchdog.finish_frame(Duration::from_millis(50), &timings));
+        assert!(!watchdog.is_degraded());
+        assert!(watchdog.finish_frame(Duration::from_millis(150), &timings));
+        assert!(watchdog.is_degraded());
+        assert!(!watchdog.finish_frame(Duration::from_millis(150), &timings));
+
+        watchdog.configure(100, None).expect("configure");
+        assert!(!watchdog.is_degraded());
+    }
+
+    #[test]
+    fn script_timings_report_slowest_first() {
+        let mut timings = ScriptTimings::new(false);
+        assert!(timings.start().is_none());
+
+        timings.enabled = true;
+        timings
+            .totals
+            .insert("fast".to_string(), Duration::from_millis(1));
+        timings
+            .totals
+            .insert("slow".to_string(), Duration::from_millis(30));
+        timings
+            .totals
+            .insert("mid".to_string(), Duration::from_millis(10));
+
+        assert_eq!(timings.scripts(), 3);
+        let slowest = timings.slowest(2);
+        assert_eq!(slowest[0].0, "slow");
+        assert_eq!(slowest[1].0, "mid");
+    }
+}
@@ -0,0 +1,180 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use skia_safe::{Canvas, Color, Font, Paint, Rect, Typeface};
+
+/// Placeholder replaced by the current UTC time, e.g. `2026-10-16T09:30:00Z`.
+const TIMESTAMP: &str = "{timestamp}";
+
+/// Which frames the watermark is stamped into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatermarkTarget {
+    /// Every presented frame, so recordings of the display carry it too.
+    Frames,
+    /// Only frames read back with `capture_frame`; the display stays clean.
+    Captures,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    pub fn parse(corner: &str) -> Result<Self, String> {
+        match corner {
+            "top_left" => Ok(Self::TopLeft),
+            "top_right" => Ok(Self::TopRight),
+            "bottom_left" => Ok(Self::BottomLeft),
+            "bottom_right" => Ok(Self::BottomRight),
+            other => Err(format!("unsupported watermark position: {other}")),
+        }
+    }
+}
+
+/// Text stamped into a corner of frames after the scene is drawn, such as a device id,
+/// the firmware version and the time, for compliance recordings and support screenshots.
+///
+/// Lines are split at `\n`; `{timestamp}` is replaced by the wall-clock time when the
+/// frame is drawn. Sizes and the margin are in scene units, and the stamp follows the
+/// output rotation so it reads upright.
+#[derive(Clone, Debug)]
+pub struct Watermark {
+    pub text: String,
+    /// `None` uses the driver's default font.
+    pub typeface: Option<Typeface>,
+    pub size: f32,
+    pub corner: Corner,
+    pub margin: f32,
+    pub color: Color,
+    /// Box drawn behind the text so it stays legible over any scene.
+    pub background: Option<Color>,
+    pub target: WatermarkTarget,
+}
+
+impl Watermark {
+    /// Whether the text changes every second and frames need redrawing for it.
+    pub fn ticks(&self) -> bool {
+        self.text.contains(TIMESTAMP)
+    }
+
+    /// The text to stamp now.
+    pub fn current_text(&self) -> String {
+        self.text_at(unix_now().as_secs())
+    }
+
+    fn text_at(&self, unix_secs: u64) -> String {
+        if self.ticks() {
+            self.text.replace(TIMESTAMP, &utc_timestamp(unix_secs))
+        } else {
+            self.text.clone()
+        }
+    }
+}
+
+/// Milliseconds until the wall clock reaches the next second.
+pub fn until_next_second_ms() -> u64 {
+    1_000 - u64::from(unix_now().subsec_millis())
+}
+
+fn unix_now() -> std::time::Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Formats seconds since the Unix epoch as an ISO 8601 UTC time.
+fn utc_timestamp(unix_secs: u64) -> String {
+    let days = (unix_secs / 86_400) as i64;
+    let secs = unix_secs % 86_400;
+    // Civil date from days since 1970-01-01, in 400-year eras starting in March.
+    let shifted = days + 719_468;
+    let era = shifted.div_euclid(146_097);
+    let day_of_era = shifted.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs / 3_600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Draws `text` into the corner of `bounds` the watermark asks for.
+pub fn draw(canvas: &Canvas, watermark: &Watermark, bounds: Rect, font: &Font, text: &str) {
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.is_empty() {
+        return;
+    }
+    let (line_height, metrics) = font.metrics();
+    let width = lines
+        .iter()
+        .map(|line| font.measure_str(line, None).0)
+        .fold(0.0, f32::max);
+    let padding = (watermark.size * 0.25).round();
+    let box_width = width + padding * 2.0;
+    let box_height = line_height * lines.len() as f32 + padding * 2.0;
+    let left = match watermark.corner {
+        Corner::TopLeft | Corner::BottomLeft => bounds.left + watermark.margin,
+        Corner::TopRight | Corner::BottomRight => bounds.right - watermark.margin - box_width,
+    };
+    let top = match watermark.corner {
+        Corner::TopLeft | Corner::TopRight => bounds.top + watermark.margin,
+        Corner::BottomLeft | Corner::BottomRight => bounds.bottom - watermark.margin - box_height,
+    };
+
+    if let Some(background) = watermark.background {
+        let mut paint = Paint::default();
+        paint.set_color(background);
+        canvas.draw_rect(Rect::from_xywh(left, top, box_width, box_height), &paint);
+    }
+    let mut paint = Paint::default();
+    paint.set_anti_alias(true);
+    paint.set_color(watermark.color);
+    for (index, line) in lines.iter().enumerate() {
+        let baseline = top + padding + line_height * index as f32 - metrics.ascent;
+        canvas.draw_str(line, (left + padding, baseline), font, &paint);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_are_expanded_as_utc() {
+        assert_eq!(utc_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(utc_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(utc_timestamp(1_700_000_000), "2023-11-14T22:13:20Z");
+
+        let mut watermark = Watermark {
+            text: "unit 42\nfw 1.2.0".to_string(),
+            typeface: None,
+            size: 14.0,
+            corner: Corner::parse("bottom_right").expect("corner"),
+            margin: 8.0,
+            color: Color::WHITE,
+            background: None,
+            target: WatermarkTarget::Captures,
+        };
+        assert!(!watermark.ticks());
+        assert_eq!(watermark.text_at(0), "unit 42\nfw 1.2.0");
+        watermark.text = "unit 42 {timestamp}".to_string();
+        assert!(watermark.ticks());
+        assert_eq!(watermark.text_at(86_399), "unit 42 1970-01-01T23:59:59Z");
+        assert!(Corner::parse("center").is_err());
+        assert!((1..=1_000).contains(&until_next_second_ms()));
+    }
+}
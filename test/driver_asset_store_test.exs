@@ -0,0 +1,458 @@
+defmodule Scenic.Driver.Skia.AssetStoreTest do
+  use ExUnit.Case, async: true
+
+  alias Scenic.Driver.Skia
+  alias Scenic.Driver.Skia.Native
+
+  @image_path Path.expand("../assets/images/test_red.png", __DIR__)
+  @photo_path Path.expand("../assets/images/stock.jpg", __DIR__)
+  @font_path Path.expand("../assets/fonts/roboto.ttf", __DIR__)
+  @mono_font_path Path.expand("../assets/fonts/roboto_mono.ttf", __DIR__)
+
+  test "shared asset store exposes images uploaded through another renderer" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    uploader = start_raster!()
+    viewer = start_raster!()
+
+    on_exit(fn ->
+      _ = Native.stop(uploader)
+      _ = Native.stop(viewer)
+    end)
+
+    store = Skia.new_asset_store()
+    assert :ok = Skia.set_asset_store(uploader, store)
+    assert :ok = Skia.set_asset_store(viewer, store)
+
+    image = File.read!(@image_path)
+    assert :ok = normalize(Native.put_static_image(uploader, "shared_red", image))
+    assert :ok = normalize(Native.set_clear_color(viewer, {0, 0, 0, 255}))
+    assert :ok = normalize(Native.submit_script(viewer, image_rect_script("shared_red")))
+
+    {width, _height, frame} =
+      wait_for_frame!(viewer, 40, fn {w, _h, data} ->
+        red_pixel?(pixel_at(data, w, 5, 5))
+      end)
+
+    assert red_pixel?(pixel_at(frame, width, 5, 5))
+  end
+
+  test "shared asset store replaces an id re-uploaded with different bytes" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    renderer = start_raster!()
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    assert :ok = Skia.set_asset_store(renderer, Skia.new_asset_store())
+
+    image = File.read!(@image_path)
+    assert {:ok, %{format: "png"}} = Native.put_static_image(renderer, "swapped", image)
+    assert {:ok, %{format: "png"}} = Native.put_static_image(renderer, "swapped", image)
+
+    photo = File.read!(@photo_path)
+    assert {:ok, %{format: "jpeg"}} = Native.put_static_image(renderer, "swapped", photo)
+    assert {:ok, %{format: "jpeg"}} = Skia.get_image_info(renderer, "swapped")
+  end
+
+  test "image uploads report dimensions and format" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    renderer = start_raster!()
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    image = File.read!(@image_path)
+
+    assert {:ok, %{width: 1, height: 1, format: "png"}} =
+             Native.put_static_image(renderer, "info_red", image)
+
+    assert {:ok, %{width: 1, height: 1, format: "png"}} =
+             Skia.get_image_info(renderer, "info_red")
+
+    bitmap = :binary.copy(<<255, 0, 0>>, 4)
+
+    assert {:ok, %{width: 2, height: 2, format: "rgb", has_alpha: false}} =
+             Native.put_stream_texture(renderer, "info_stream", "rgb", 2, 2, bitmap)
+
+    assert {:ok, %{format: "rgb"}} = Skia.get_image_info(renderer, "info_stream")
+    assert {:error, _} = Skia.get_image_info(renderer, "missing")
+  end
+
+  test "images and scripts can be loaded from files" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    renderer = start_raster!()
+    script_path = Path.join(System.tmp_dir!(), "skia_file_#{System.unique_integer()}.bin")
+
+    on_exit(fn ->
+      _ = Native.stop(renderer)
+      File.rm(script_path)
+    end)
+
+    assert {:ok, %{width: 1, height: 1, format: "png"}} =
+             Skia.put_static_image_file(renderer, "file_red", @image_path)
+
+    assert {:error, _} = Skia.put_static_image_file(renderer, "missing", "/nonexistent.png")
+
+    File.write!(script_path, image_rect_script("file_red"))
+    assert :ok = normalize(Native.set_clear_color(renderer, {0, 0, 0, 255}))
+    assert :ok = Skia.submit_script_file(renderer, "_root_", script_path)
+
+    {width, _height, frame} =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        red_pixel?(pixel_at(data, w, 5, 5))
+      end)
+
+    assert red_pixel?(pixel_at(frame, width, 5, 5))
+  end
+
+  test "measure_path returns the length and points along path ops" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    path =
+      Scenic.Script.start()
+      |> Scenic.Script.begin_path()
+      |> Scenic.Script.move_to(0, 0)
+      |> Scenic.Script.line_to(100, 0)
+      |> Scenic.Script.line_to(100, 100)
+      |> Scenic.Script.stroke_path()
+      |> Scenic.Script.finish()
+
+    assert {:ok, {length, [start, corner]}} = Skia.measure_path(path, [0, 0.75])
+    assert_in_delta length, 200.0, 0.01
+
+    for {actual, expected} <- [{start, {0.0, 0.0, 1.0, 0.0}}, {corner, {100.0, 50.0, 0.0, 1.0}}],
+        {a, e} <- Enum.zip(Tuple.to_list(actual), Tuple.to_list(expected)) do
+      assert_in_delta a, e, 0.01
+    end
+
+    assert {:error, _} = Skia.measure_path([], [0.5])
+    assert {:ok, {length, []}} = Skia.measure_path([], [])
+    assert length == 0
+  end
+
+  test "color utilities compute contrast, blends and image palettes" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    renderer = start_raster!()
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    assert_in_delta Skia.contrast_ratio(:black, :white), 21.0, 0.001
+    assert_in_delta Skia.relative_luminance(:white), 1.0, 0.001
+    assert Skia.blend_colors({255, 0, 0, 128}, :white) == {255, 127, 127, 255}
+
+    assert {:ok, _} = Native.put_static_image(renderer, "palette_red", File.read!(@image_path))
+
+    assert {:ok, [%{color: {r, g, b}, share: share}]} =
+             Skia.extract_palette(renderer, "palette_red", 3)
+
+    assert red_pixel?({r, g, b})
+    assert_in_delta share, 1.0, 0.001
+    assert {:error, _} = Skia.extract_palette(renderer, "missing")
+  end
+
+  test "dedup keeps shared images alive until the last alias is deleted" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    renderer = start_raster!()
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    assert :ok = Skia.set_asset_store(renderer, Skia.new_asset_store())
+    assert :ok = Skia.set_image_dedup(renderer, true)
+
+    image = File.read!(@image_path)
+    assert {:ok, _} = Native.put_static_image(renderer, "alias_a", image)
+    assert {:ok, _} = Native.put_static_image(renderer, "alias_b", image)
+
+    assert {:ok, true} = Skia.del_static_image(renderer, "alias_a")
+    assert {:ok, false} = Skia.del_static_image(renderer, "alias_a")
+    assert {:ok, %{width: 1}} = Skia.get_image_info(renderer, "alias_b")
+    assert {:error, _} = Skia.get_image_info(renderer, "alias_a")
+  end
+
+  test "texture options mark raw bitmaps as unpremultiplied" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    renderer = start_raster!()
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    assert :ok = Skia.set_asset_store(renderer, Skia.new_asset_store())
+    assert :ok = normalize(Native.set_clear_color(renderer, {0, 0, 0, 255}))
+    assert {:error, _} = Skia.set_texture_options(renderer, "half_red", alpha: :straight)
+    assert :ok = Skia.set_texture_options(renderer, "half_red", alpha: :unpremul)
+
+    bitmap = :binary.copy(<<255, 0, 0, 128>>, 4)
+    assert {:ok, _} = Native.put_stream_texture(renderer, "half_red", "rgba", 2, 2, bitmap)
+    assert :ok = normalize(Native.submit_script(renderer, stream_rect_script("half_red")))
+
+    {width, _height, frame} =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        {r, _g, _b} = pixel_at(data, w, 5, 5)
+        r > 100 and r < 160
+      end)
+
+    assert {r, _g, _b} = pixel_at(frame, width, 5, 5)
+    assert r in 100..160
+  end
+
+  test "stream textures take nv12 and yuv420p camera frames" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    renderer = start_raster!()
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    assert :ok = Skia.set_asset_store(renderer, Skia.new_asset_store())
+    assert :ok = normalize(Native.set_clear_color(renderer, {0, 0, 0, 255}))
+
+    # A 3x3 frame of BT.601 red; chroma planes round up to 2x2.
+    luma = :binary.copy(<<81>>, 9)
+    nv12 = luma <> :binary.copy(<<90, 240>>, 4)
+    i420 = luma <> :binary.copy(<<90>>, 4) <> :binary.copy(<<240>>, 4)
+
+    assert {:error, _} = Native.put_stream_texture(renderer, "camera", "nv12", 3, 3, luma)
+
+    assert {:ok, %{format: "yuv420p", has_alpha: false}} =
+             Native.put_stream_texture(renderer, "camera", "yuv420p", 3, 3, i420)
+
+    assert {:ok, %{width: 3, height: 3, format: "nv12"}} =
+             Native.put_stream_texture(renderer, "camera", "nv12", 3, 3, nv12)
+
+    assert :ok = normalize(Native.submit_script(renderer, stream_rect_script("camera")))
+
+    {width, _height, frame} =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        pixel_at(data, w, 5, 5) == {255, 0, 0}
+      end)
+
+    assert pixel_at(frame, width, 5, 5) == {255, 0, 0}
+  end
+
+  test "submit_frame applies a stream texture together with its script" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    renderer = start_raster!()
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    assert :ok = Skia.set_asset_store(renderer, Skia.new_asset_store())
+    assert :ok = normalize(Native.set_clear_color(renderer, {0, 0, 0, 255}))
+
+    bitmap = :binary.copy(<<255, 0, 0>>, 4)
+    textures = [{"frame_video", "rgb", 2, 2, bitmap}]
+    scripts = [{"_root_", stream_rect_script("frame_video")}]
+
+    at = Skia.monotonic_ms() + 50_000
+    assert :ok = Skia.submit_frame(renderer, textures, scripts, at: at)
+    assert {:error, _} = Skia.get_image_info(renderer, "frame_video")
+
+    assert :ok = Skia.submit_frame(renderer, textures, scripts)
+
+    assert {:ok, %{format: "rgb"}} = Skia.get_image_info(renderer, "frame_video")
+
+    {width, _height, frame} =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        red_pixel?(pixel_at(data, w, 5, 5))
+      end)
+
+    assert red_pixel?(pixel_at(frame, width, 5, 5))
+  end
+
+  test "submit_transaction swaps scripts, images and streams in together" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    renderer = start_raster!()
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    assert :ok = Skia.set_asset_store(renderer, Skia.new_asset_store())
+    assert :ok = normalize(Native.set_clear_color(renderer, {0, 0, 0, 255}))
+
+    bitmap = :binary.copy(<<255, 0, 0>>, 4)
+
+    assert :ok =
+             Skia.submit_transaction(renderer,
+               streams: [{"tx_video", "rgb", 2, 2, bitmap}],
+               scripts: [{"_root_", stream_rect_script("tx_video")}]
+             )
+
+    assert {:ok, %{format: "rgb"}} = Skia.get_image_info(renderer, "tx_video")
+
+    assert {:error, _} =
+             Skia.submit_transaction(renderer,
+               delete: [{:stream, "tx_video"}],
+               images: [{"tx_logo", "not an image"}],
+               scripts: [{"_root_", image_rect_script("tx_logo")}]
+             )
+
+    assert {:ok, %{format: "rgb"}} = Skia.get_image_info(renderer, "tx_video")
+
+    assert :ok =
+             Skia.submit_transaction(renderer,
+               delete: [{:stream, "tx_video"}],
+               images: [{"tx_logo", File.read!(@image_path)}],
+               scripts: [{"_root_", image_rect_script("tx_logo")}]
+             )
+
+    assert {:error, _} = Skia.get_image_info(renderer, "tx_video")
+    assert {:ok, %{format: "png"}} = Skia.get_image_info(renderer, "tx_logo")
+
+    {width, _height, frame} =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        red_pixel?(pixel_at(data, w, 5, 5))
+      end)
+
+    assert red_pixel?(pixel_at(frame, width, 5, 5))
+  end
+
+  test "put_font rejects invalid font data and del_font removes fonts" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    renderer = start_raster!()
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    assert {:error, reason} = Skia.put_font(renderer, "bad", "not a font")
+    assert reason =~ "invalid font data"
+    assert {:error, "font \"empty\": empty font data"} = Skia.put_font(renderer, "empty", "")
+
+    assert :ok = Skia.put_font(renderer, "roboto", File.read!(@font_path))
+    assert {:ok, %{width: width}} = Skia.measure_text(renderer, "Hello", font: "roboto")
+    assert width > 0
+
+    assert {:ok, true} = Skia.del_font(renderer, "roboto")
+    assert {:ok, false} = Skia.del_font(renderer, "roboto")
+    assert {:error, _} = Skia.measure_text(renderer, "Hello", font: "roboto")
+  end
+
+  test "missing_glyphs reports characters the font cannot draw" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    renderer = start_raster!()
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    assert :ok = normalize(Native.put_font(renderer, "roboto", File.read!(@font_path)))
+    assert :ok = Skia.set_fallback_fonts(renderer, [], system: false)
+
+    assert {:ok, [{3, 0x1F600}]} =
+             Skia.missing_glyphs(renderer, "Hi \u{1F600}\u{FE0F}!", font: "roboto")
+
+    assert {:ok, []} = Skia.missing_glyphs(renderer, "Hello", font: "roboto")
+    assert {:error, _} = Skia.missing_glyphs(renderer, "Hello", font: "unknown")
+  end
+
+  test "measure_text reports the extents of a run" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    renderer = start_raster!()
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    assert :ok = normalize(Native.put_font(renderer, "roboto", File.read!(@font_path)))
+
+    assert {:ok, %{width: width, ascent: ascent, descent: descent, line_height: line_height}} =
+             Skia.measure_text(renderer, "Hello", font: "roboto")
+
+    assert width > 0 and ascent > 0 and descent > 0
+    assert line_height >= ascent + descent
+
+    assert {:ok, %{width: double}} =
+             Skia.measure_text(renderer, "Hello", font: "roboto", font_size: 40)
+
+    assert_in_delta double, 2 * width, 0.5
+    assert {:error, _} = Skia.measure_text(renderer, "Hello", font: "unknown")
+  end
+
+  test "fallback fonts cover characters missing from the drawing font" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    renderer = start_raster!()
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    assert :ok = normalize(Native.put_font(renderer, "roboto", File.read!(@font_path)))
+    assert :ok = normalize(Native.put_font(renderer, "mono", File.read!(@mono_font_path)))
+    assert :ok = Skia.set_fallback_fonts(renderer, [], system: false)
+
+    # Roboto has no U+2215 DIVISION SLASH; Roboto Mono does.
+    assert {:ok, [{1, 0x2215}]} = Skia.missing_glyphs(renderer, "1\u{2215}2", font: "roboto")
+
+    assert :ok = Skia.set_fallback_fonts(renderer, ["mono"], system: false)
+    assert {:ok, []} = Skia.missing_glyphs(renderer, "1\u{2215}2", font: "roboto")
+  end
+
+  test "stats export streams a JSON line per rendered frame" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    renderer = start_raster!()
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    path = Path.join(System.tmp_dir!(), "skia-stats-#{System.unique_integer([:positive])}.sock")
+    on_exit(fn -> File.rm(path) end)
+    assert :ok = Skia.set_stats_export(renderer, path: path)
+
+    {:ok, socket} = :gen_tcp.connect({:local, path}, 0, [:binary, active: false, packet: :line])
+    assert :ok = Skia.set_linear_blending(renderer, true)
+    assert {:ok, line} = :gen_tcp.recv(socket, 0, 2_000)
+    assert line =~ ~r/^\{"frame":\d+,"timestamp_us":\d+,"frame_us":\d+,"ops":\d+,"scripts":\d+/
+    assert String.ends_with?(line, "]}\n")
+    :gen_tcp.close(socket)
+
+    assert :ok = Skia.set_stats_export(renderer, path: nil)
+    refute File.exists?(path)
+    assert {:error, _} = Skia.set_stats_export(renderer, path: path, format: :xml)
+  end
+
+  defp start_raster! do
+    case Native.start("raster", {32, 32}, "Scenic Window", false, nil, true, false) do
+      {:ok, renderer} -> renderer
+      other -> flunk("start returned #{inspect(other)}")
+    end
+  end
+
+  defp image_rect_script(id) do
+    pad = rem(4 - rem(byte_size(id), 4), 4)
+
+    <<0x0063::16-big, byte_size(id)::16-big, id::binary, 0::size(pad * 8), 0x0004::16-big,
+      0x0001::16-big, 20.0::float-32-big, 20.0::float-32-big>>
+  end
+
+  defp stream_rect_script(id) do
+    pad = rem(4 - rem(byte_size(id), 4), 4)
+
+    <<0x0064::16-big, byte_size(id)::16-big, id::binary, 0::size(pad * 8), 0x0004::16-big,
+      0x0001::16-big, 20.0::float-32-big, 20.0::float-32-big>>
+  end
+
+  defp wait_for_frame!(renderer, attempts_remaining, predicate) do
+    case Native.get_raster_frame(renderer) do
+      {:ok, {width, height, frame}} = ok ->
+        if predicate.({width, height, frame}) do
+          {width, height, frame}
+        else
+          retry_frame(renderer, ok, attempts_remaining, predicate)
+        end
+
+      other ->
+        retry_frame(renderer, other, attempts_remaining, predicate)
+    end
+  end
+
+  defp retry_frame(renderer, _last_result, attempts_remaining, predicate)
+       when attempts_remaining > 0 do
+    Process.sleep(50)
+    wait_for_frame!(renderer, attempts_remaining - 1, predicate)
+  end
+
+  defp retry_frame(_renderer, last_result, _attempts_remaining, _predicate) do
+    flunk("timed out waiting for raster frame: #{inspect(last_result)}")
+  end
+
+  defp pixel_at(frame, width, x, y) do
+    offset = (y * width + x) * 3
+
+    case frame do
+      <<_::binary-size(offset), r, g, b, _::binary>> -> {r, g, b}
+      _ -> {0, 0, 0}
+    end
+  end
+
+  defp red_pixel?({r, g, b}), do: r > 200 and g < 80 and b < 80
+
+  defp normalize(:ok), do: :ok
+  defp normalize({:ok, _}), do: :ok
+  defp normalize(other), do: other
+end
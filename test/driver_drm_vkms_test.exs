@@ -0,0 +1,48 @@
+defmodule Scenic.Driver.Skia.DrmVkmsTest do
+  use ExUnit.Case, async: false
+
+  alias Scenic.Driver.Skia.Native
+  alias Scenic.Driver.Skia.TestSupport.Vkms
+
+  @moduletag :vkms
+
+  test "drm backend drives the vkms card" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    card =
+      case Vkms.card() do
+        {:ok, card} -> card
+        {:error, reason} -> flunk("vkms unavailable: #{reason}")
+      end
+
+    renderer =
+      case Native.start("drm", nil, "Scenic Window", false, card, true, false) do
+        {:ok, renderer} -> renderer
+        other -> flunk("start returned #{inspect(other)}")
+      end
+
+    on_exit(fn ->
+      _ = Native.stop(renderer)
+    end)
+
+    status = wait_for_output!(renderer, 60)
+    assert status.backend == "drm"
+    assert status.running
+    assert is_boolean(status.gpu)
+  end
+
+  # `gpu` is published once the backend has set a mode and drawn its first frame.
+  defp wait_for_output!(renderer, attempts_remaining) do
+    case Scenic.Driver.Skia.get_status(renderer) do
+      {:ok, %{gpu: gpu} = status} when is_boolean(gpu) ->
+        status
+
+      {:ok, %{running: true}} when attempts_remaining > 0 ->
+        Process.sleep(50)
+        wait_for_output!(renderer, attempts_remaining - 1)
+
+      other ->
+        flunk("drm backend never came up on vkms: #{inspect(other)}")
+    end
+  end
+end
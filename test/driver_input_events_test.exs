@@ -142,6 +142,394 @@ defmodule Scenic.Driver.Skia.InputEventsTest do
     assert :ok = Scenic.Driver.Skia.show_cursor(renderer)
   end
 
+  test "cursor regions are validated and accepted while renderer is running" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+    ensure_renderer_stopped()
+
+    renderer =
+      case Native.start("raster", nil, "Scenic Window", false, nil, true, false) do
+        {:ok, renderer} -> renderer
+        other -> flunk("start returned #{inspect(other)}")
+      end
+
+    on_exit(fn ->
+      _ = Native.stop(renderer)
+    end)
+
+    regions = [{{0, 0, 100, 40}, :pointer}, {{10, 10, 50, 20}, :text}]
+    assert :ok = Scenic.Driver.Skia.set_cursor_regions(renderer, regions)
+
+    assert {:error, _} =
+             Scenic.Driver.Skia.set_cursor_regions(renderer, [{{0, 0, 10, 10}, :hand}])
+
+    assert :ok = Scenic.Driver.Skia.set_cursor_regions(renderer, [])
+
+    arrow = :binary.copy(<<255, 255, 255, 255>>, 16 * 16)
+    assert :ok = Scenic.Driver.Skia.set_cursor_image(renderer, {16, 16, arrow}, {1, 1})
+    assert {:error, _} = Scenic.Driver.Skia.set_cursor_image(renderer, {16, 8, arrow})
+
+    assert {:error, _} =
+             Scenic.Driver.Skia.set_cursor_image(renderer, {16, 16, arrow}, {16, 0})
+
+    assert :ok = Scenic.Driver.Skia.set_cursor_image(renderer, nil)
+    assert :ok = Scenic.Driver.Skia.hide_cursor(renderer)
+    assert :ok = Scenic.Driver.Skia.show_cursor(renderer)
+  end
+
+  test "late input targets can request the viewport reshape again" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+    ensure_renderer_stopped()
+
+    renderer =
+      case Native.start("raster", {64, 48}, "Scenic Window", false, nil, true, false) do
+        {:ok, renderer} -> renderer
+        other -> flunk("start returned #{inspect(other)}")
+      end
+
+    on_exit(fn ->
+      _ = Native.stop(renderer)
+    end)
+
+    assert :ok = Scenic.Driver.Skia.add_input_target(renderer, [:viewport])
+
+    assert {:ok, %{width: 64, height: 48, scale: 1.0}} =
+             request_viewport_info!(renderer, 40)
+
+    assert_receive :input_ready, 1_000
+
+    assert {:ok, [{:viewport, {:reshape, {64, 48}}}]} =
+             Scenic.Driver.Skia.drain_input(renderer)
+  end
+
+  test "input targets and device classes accept touch input" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+    ensure_renderer_stopped()
+
+    renderer =
+      case Native.start("raster", {64, 48}, "Scenic Window", false, nil, true, false) do
+        {:ok, renderer} -> renderer
+        other -> flunk("start returned #{inspect(other)}")
+      end
+
+    on_exit(fn ->
+      _ = Native.stop(renderer)
+    end)
+
+    assert :ok = Scenic.Driver.Skia.add_input_target(renderer, [:touch, :cursor_button])
+    assert :ok = Scenic.Driver.Skia.set_device_class_mask(renderer, :touch, [:touch])
+    assert {:ok, []} = Scenic.Driver.Skia.drain_input(renderer)
+    assert :ok = Scenic.Driver.Skia.remove_input_target(renderer)
+  end
+
+  test "output rotation pre-rotates frames and swaps the viewport" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+    ensure_renderer_stopped()
+
+    renderer =
+      case Native.start("raster", {32, 24}, "Scenic Window", false, nil, true, false) do
+        {:ok, renderer} -> renderer
+        other -> flunk("start returned #{inspect(other)}")
+      end
+
+    on_exit(fn ->
+      _ = Native.stop(renderer)
+    end)
+
+    assert {:ok, %{width: 32, height: 24}} = request_viewport_info!(renderer, 40)
+    assert :ok = Scenic.Driver.Skia.add_input_target(renderer, [:viewport])
+    assert :ok = Scenic.Driver.Skia.set_output_rotation(renderer, 90)
+    assert_receive :input_ready, 1_000
+
+    assert {:ok, events} = Scenic.Driver.Skia.drain_input(renderer)
+    assert {:viewport, {:reshape, {24, 32}}} in events
+
+    # A band along the top of the scene lands on the right edge of the panel.
+    script =
+      Scenic.Script.start()
+      |> Scenic.Script.fill_color(:red)
+      |> Scenic.Script.draw_rect(24, 8, :fill)
+      |> Scenic.Script.finish()
+      |> Scenic.Script.serialize()
+      |> IO.iodata_to_binary()
+
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "_root_", script)
+
+    {width, 24, frame} =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        pixel_at(data, w, 30, 12) == {255, 0, 0}
+      end)
+
+    refute pixel_at(frame, width, 4, 12) == {255, 0, 0}
+  end
+
+  test "input watchdog reports idle input until disarmed" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+    ensure_renderer_stopped()
+
+    renderer =
+      case Native.start("raster", nil, "Scenic Window", false, nil, true, false) do
+        {:ok, renderer} -> renderer
+        other -> flunk("start returned #{inspect(other)}")
+      end
+
+    on_exit(fn ->
+      _ = Native.stop(renderer)
+    end)
+
+    assert {:error, _} = Native.arm_input_watchdog(renderer, 0, self())
+    assert :ok = Scenic.Driver.Skia.arm_input_watchdog(renderer, 50)
+    assert_receive {:input_watchdog_expired, idle_ms}, 1_000
+    assert idle_ms >= 50
+    assert {:ok, %{input_idle: true}} = Scenic.Driver.Skia.get_status(renderer)
+
+    assert {:ok, true} = Scenic.Driver.Skia.disarm_input_watchdog(renderer)
+    assert {:ok, %{input_idle: false}} = Scenic.Driver.Skia.get_status(renderer)
+    assert {:ok, false} = Scenic.Driver.Skia.disarm_input_watchdog(renderer)
+  end
+
+  test "animation frames are sent to the input target for each rendered frame" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+    ensure_renderer_stopped()
+
+    renderer =
+      case Native.start("raster", {64, 48}, "Scenic Window", false, nil, true, false) do
+        {:ok, renderer} -> renderer
+        other -> flunk("start returned #{inspect(other)}")
+      end
+
+    on_exit(fn ->
+      _ = Native.stop(renderer)
+    end)
+
+    case Native.set_input_target(renderer, self()) do
+      :ok -> :ok
+      {:ok, _} -> :ok
+      other -> flunk("set_input_target returned #{inspect(other)}")
+    end
+
+    assert :ok = Scenic.Driver.Skia.request_animation_frames(renderer, true)
+    assert :ok = Scenic.Driver.Skia.set_linear_blending(renderer, true)
+    assert_receive {:frame, first_us}, 1_000
+
+    assert :ok = Scenic.Driver.Skia.set_linear_blending(renderer, false)
+    assert_receive {:frame, second_us}, 1_000
+    assert second_us > first_us
+
+    assert :ok = Scenic.Driver.Skia.request_animation_frames(renderer, false)
+    assert :ok = Scenic.Driver.Skia.set_linear_blending(renderer, true)
+    refute_receive {:frame, _}, 300
+  end
+
+  test "pointer paths push cursor events and click at waypoints" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+    ensure_renderer_stopped()
+
+    renderer =
+      case Native.start("raster", {64, 48}, "Scenic Window", false, nil, true, false) do
+        {:ok, renderer} -> renderer
+        other -> flunk("start returned #{inspect(other)}")
+      end
+
+    on_exit(fn ->
+      _ = Native.stop(renderer)
+    end)
+
+    case Native.set_input_mask(renderer, 0x0C) do
+      :ok -> :ok
+      {:ok, _} -> :ok
+      other -> flunk("set_input_mask returned #{inspect(other)}")
+    end
+
+    assert {:error, _} = Scenic.Driver.Skia.play_pointer_path(renderer, [])
+
+    assert {:error, _} =
+             Scenic.Driver.Skia.play_pointer_path(renderer, [{1, 1, 10}], easing: :bounce)
+
+    path = [{10, 10, 0}, {30, 20, 40, :click}]
+    assert :ok = Scenic.Driver.Skia.play_pointer_path(renderer, path, easing: :linear)
+
+    clicked? = &Enum.any?(&1, fn event -> match?({:cursor_button, _}, event) end)
+    events = drain_until!(renderer, 40, [], clicked?)
+    assert {:cursor_pos, {30.0, 20.0}} in events
+    assert :ok = Scenic.Driver.Skia.hide_pointer(renderer)
+  end
+
+  test "hover regions report enter and leave as the pointer crosses them" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+    ensure_renderer_stopped()
+
+    renderer =
+      case Native.start("raster", {64, 48}, "Scenic Window", false, nil, true, false) do
+        {:ok, renderer} -> renderer
+        other -> flunk("start returned #{inspect(other)}")
+      end
+
+    on_exit(fn ->
+      _ = Native.stop(renderer)
+    end)
+
+    case Native.set_input_mask(renderer, 0x80) do
+      :ok -> :ok
+      {:ok, _} -> :ok
+      other -> flunk("set_input_mask returned #{inspect(other)}")
+    end
+
+    assert {:error, _} =
+             Scenic.Driver.Skia.set_hover_regions(renderer, [{"bad", {0, 0, -1, 10}}])
+
+    regions = [{"button", {30, 20, 20, 20}}, {"panel", {0, 0, 64, 48}}]
+    assert :ok = Scenic.Driver.Skia.set_hover_regions(renderer, regions)
+    assert :ok = Scenic.Driver.Skia.play_pointer_path(renderer, [{5, 5, 0}, {40, 30, 40}])
+
+    entered? = &({:hover, {:enter, "button"}} in &1)
+    events = drain_until!(renderer, 40, [], entered?)
+    assert [{:hover, {:enter, "panel"}}, {:hover, {:enter, "button"}}] = events
+
+    assert :ok = Scenic.Driver.Skia.set_hover_regions(renderer, [{"panel", {0, 0, 64, 48}}])
+    assert [{:hover, {:leave, "button"}}] = drain_until!(renderer, 40, [], &(&1 != []))
+    assert :ok = Scenic.Driver.Skia.hide_pointer(renderer)
+  end
+
+  test "replayed scroll events carry their source and precise deltas" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+    ensure_renderer_stopped()
+
+    renderer =
+      case Native.start("raster", {64, 48}, "Scenic Window", false, nil, true, false) do
+        {:ok, renderer} -> renderer
+        other -> flunk("start returned #{inspect(other)}")
+      end
+
+    path = Path.join(System.tmp_dir!(), "skia_scroll_#{System.unique_integer([:positive])}.log")
+
+    on_exit(fn ->
+      _ = Native.stop(renderer)
+      File.rm(path)
+    end)
+
+    case Native.set_input_mask(renderer, 0x10) do
+      :ok -> :ok
+      {:ok, _} -> :ok
+      other -> flunk("set_input_mask returned #{inspect(other)}")
+    end
+
+    # A finger scroll and its stop, as record_input writes them (log version 3).
+    scroll = fn source, flags, {dx, dy} ->
+      <<0::32, 4, source, flags, dx::float-32-big, dy::float-32-big, 3.0::float-32-big,
+        4.0::float-32-big, dx::float-32-big, dy::float-32-big>>
+    end
+
+    File.write!(path, ["SDIR", 3, scroll.(1, 0x02, {2.5, -12.0}), scroll.(1, 0x06, {0.0, 0.0})])
+    assert :ok = Scenic.Driver.Skia.replay_input(renderer, path)
+
+    stopped? = &Enum.any?(&1, fn event -> match?({_, {_, _, %{stop: true}}}, event) end)
+    events = drain_until!(renderer, 40, [], stopped?)
+
+    assert [
+             {:cursor_scroll,
+              {{2.5, -12.0}, {3.0, 4.0},
+               %{source: :finger, lines: nil, pixels: {2.5, -12.0}, stop: false}}},
+             {:cursor_scroll,
+              {{+0.0, +0.0}, {3.0, 4.0},
+               %{source: :finger, lines: nil, pixels: {+0.0, +0.0}, stop: true}}}
+           ] = events
+  end
+
+  test "binary input drains decode to the event terms" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+    ensure_renderer_stopped()
+
+    renderer =
+      case Native.start("raster", {64, 48}, "Scenic Window", false, nil, true, false) do
+        {:ok, renderer} -> renderer
+        other -> flunk("start returned #{inspect(other)}")
+      end
+
+    path = Path.join(System.tmp_dir!(), "skia_binary_#{System.unique_integer([:positive])}.log")
+
+    on_exit(fn ->
+      _ = Native.stop(renderer)
+      File.rm(path)
+    end)
+
+    case Native.set_input_mask(renderer, 0x43) do
+      :ok -> :ok
+      {:ok, _} -> :ok
+      other -> flunk("set_input_mask returned #{inspect(other)}")
+    end
+
+    assert {:ok, ""} = Native.drain_input_binary(renderer)
+
+    File.write!(path, [
+      "SDIR",
+      3,
+      <<0::32, 0, 1, 0x03, 30::32, 0::32, 5::16, "key_a">>,
+      <<0::32, 1, ?é::32, 0x01>>,
+      <<0::32, 7, 9::32, 0, 3.0::float-32-big, 4.0::float-32-big>>
+    ])
+
+    assert :ok = Scenic.Driver.Skia.replay_input(renderer, path)
+
+    drain = fn renderer ->
+      {:ok, batch} = Native.drain_input_binary(renderer)
+      {:ok, events} = Scenic.Driver.Skia.InputBinary.decode(batch)
+      events
+    end
+
+    events = drain_until!(renderer, 40, [], &(length(&1) >= 3), drain)
+
+    assert [
+             {:key, {:key_a, 1, [:shift, :ctrl], 30, 0}},
+             {:codepoint, {"é", [:shift]}},
+             {:touch, {9, :down, {3.0, 4.0}}}
+           ] = events
+
+    assert {:ok, [{:hover, {:leave, "menu"}}]} =
+             Scenic.Driver.Skia.InputBinary.decode(<<8, 0, 4::16, "menu">>)
+
+    assert {:error, _} = Scenic.Driver.Skia.InputBinary.decode(<<2, 0, 0>>)
+  end
+
+  defp drain_until!(
+         renderer,
+         attempts_remaining,
+         seen,
+         done?,
+         drain \\ &Native.drain_input_events/1
+       ) do
+    seen =
+      case drain.(renderer) do
+        {:ok, events} -> seen ++ events
+        events when is_list(events) -> seen ++ events
+      end
+
+    cond do
+      done?.(seen) ->
+        seen
+
+      attempts_remaining > 0 ->
+        Process.sleep(25)
+        drain_until!(renderer, attempts_remaining - 1, seen, done?, drain)
+
+      true ->
+        flunk("pointer events never arrived: #{inspect(seen)}")
+    end
+  end
+
+  defp request_viewport_info!(renderer, attempts_remaining) do
+    case Scenic.Driver.Skia.request_viewport_info(renderer) do
+      {:ok, _} = ok ->
+        ok
+
+      {:error, _} when attempts_remaining > 0 ->
+        Process.sleep(50)
+        request_viewport_info!(renderer, attempts_remaining - 1)
+
+      other ->
+        flunk("request_viewport_info returned #{inspect(other)}")
+    end
+  end
+
   defp wait_for_frame!(renderer, attempts_remaining),
     do: wait_for_frame!(renderer, attempts_remaining, fn _ -> true end)
 
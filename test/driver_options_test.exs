@@ -24,4 +24,320 @@ defmodule Scenic.Driver.Skia.DriverOptionsTest do
     assert {:error, %NimbleOptions.ValidationError{}} =
              Skia.validate_opts(window: [resizeable: "nope"])
   end
+
+  test "validate_opts accepts drm pointer acceleration options" do
+    assert {:ok, opts} =
+             Skia.validate_opts(drm: [pointer_profile: :adaptive, pointer_sensitivity: 1.5])
+
+    assert Keyword.get(opts[:drm], :pointer_profile) == :adaptive
+    assert Keyword.get(opts[:drm], :pointer_sensitivity) == 1.5
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(drm: [pointer_profile: :turbo])
+  end
+
+  test "validate_opts accepts a drm output rotation" do
+    assert {:ok, opts} = Skia.validate_opts(drm: [rotation: 270])
+    assert Keyword.get(opts[:drm], :rotation) == 270
+
+    assert {:ok, opts} = Skia.validate_opts(drm: [])
+    assert Keyword.get(opts[:drm], :rotation) == 0
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(drm: [rotation: 45])
+  end
+
+  test "validate_opts accepts a drm driver name" do
+    assert {:ok, opts} = Skia.validate_opts(backend: :drm, drm: [driver: "vkms"])
+    assert Keyword.get(opts[:drm], :driver) == "vkms"
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(drm: [driver: :vkms])
+  end
+
+  test "validate_opts accepts a drm render node" do
+    assert {:ok, opts} = Skia.validate_opts(backend: :drm, drm: [render_node: :auto])
+    assert Keyword.get(opts[:drm], :render_node) == :auto
+
+    assert {:ok, opts} = Skia.validate_opts(drm: [render_node: "/dev/dri/renderD129"])
+    assert Keyword.get(opts[:drm], :render_node) == "/dev/dri/renderD129"
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(drm: [render_node: :card])
+  end
+
+  test "list_dri_devices describes every node" do
+    for device <- Skia.list_dri_devices() do
+      assert device.kind in ["card", "render"]
+      assert String.starts_with?(device.path, "/dev/dri/")
+      assert device.connected <= device.connectors
+    end
+  end
+
+  test "validate_opts accepts gradient dither modes" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    assert opts[:gradient_dither] == :off
+
+    assert {:ok, opts} = Skia.validate_opts(gradient_dither: :noise)
+    assert opts[:gradient_dither] == :noise
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(gradient_dither: :ordered)
+  end
+
+  test "validate_opts accepts text fallback modes" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    assert opts[:text_fallback] == :auto
+
+    assert {:ok, opts} = Skia.validate_opts(text_fallback: :always)
+    assert opts[:text_fallback] == :always
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(text_fallback: :sometimes)
+  end
+
+  test "validate_opts accepts geometry modes" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    assert opts[:geometry] == :clamp
+
+    assert {:ok, opts} = Skia.validate_opts(geometry: :strict)
+    assert opts[:geometry] == :strict
+
+    assert {:error, %NimbleOptions.ValidationError{}} = Skia.validate_opts(geometry: :loose)
+  end
+
+  test "validate_opts accepts poisoned state policies" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    assert opts[:poisoned_state] == :recover
+
+    assert {:ok, opts} = Skia.validate_opts(poisoned_state: :fail)
+    assert opts[:poisoned_state] == :fail
+
+    assert {:error, %NimbleOptions.ValidationError{}} = Skia.validate_opts(poisoned_state: :panic)
+  end
+
+  test "validate_opts accepts script limits" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    assert opts[:script_limits] == []
+
+    assert {:ok, opts} = Skia.validate_opts(script_limits: [max_ops: 200_000])
+    assert opts[:script_limits][:max_ops] == 200_000
+    assert opts[:script_limits][:max_text_bytes] == 0
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(script_limits: [max_ops: -1])
+  end
+
+  test "validate_opts accepts a script cache directory" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    assert opts[:script_cache] == []
+
+    assert {:ok, opts} = Skia.validate_opts(script_cache: [dir: "/data/scenic/scripts"])
+    assert opts[:script_cache][:dir] == "/data/scenic/scripts"
+    assert opts[:script_cache][:max_bytes] == 64 * 1024 * 1024
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(script_cache: [max_bytes: 0])
+  end
+
+  test "validate_opts accepts quality governor settings" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    assert opts[:quality] == []
+
+    assert {:ok, opts} =
+             Skia.validate_opts(
+               quality: [target_fps: 60, thermal_zones: ["/sys/class/thermal/thermal_zone0/temp"]]
+             )
+
+    assert opts[:quality][:target_fps] == 60
+    assert opts[:quality][:thermal_limit_c] == 80
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(quality: [target_fps: -1])
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(quality: [thermal_zones: "/sys/class/thermal"])
+  end
+
+  test "validate_opts accepts script isolation" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    refute opts[:isolate_scripts]
+
+    assert {:ok, opts} = Skia.validate_opts(isolate_scripts: true)
+    assert opts[:isolate_scripts]
+
+    assert {:error, %NimbleOptions.ValidationError{}} = Skia.validate_opts(isolate_scripts: :on)
+  end
+
+  test "validate_opts accepts reduced motion" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    refute opts[:reduced_motion]
+
+    assert {:ok, opts} = Skia.validate_opts(reduced_motion: true)
+    assert opts[:reduced_motion]
+
+    assert {:error, %NimbleOptions.ValidationError{}} = Skia.validate_opts(reduced_motion: :yes)
+  end
+
+  test "validate_opts accepts a software GL mode" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    assert opts[:software_gl] == :warn
+
+    assert {:ok, opts} = Skia.validate_opts(software_gl: :raster)
+    assert opts[:software_gl] == :raster
+
+    assert {:error, %NimbleOptions.ValidationError{}} = Skia.validate_opts(software_gl: :off)
+  end
+
+  test "validate_opts accepts linear blending" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    refute opts[:linear_blending]
+
+    assert {:ok, opts} = Skia.validate_opts(linear_blending: true)
+    assert opts[:linear_blending]
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(linear_blending: "yes")
+  end
+
+  test "validate_opts accepts a frame deadline" do
+    assert {:ok, opts} = Skia.validate_opts(frame_deadline_ms: 100)
+    assert opts[:frame_deadline_ms] == 100
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(frame_deadline_ms: -1)
+  end
+
+  test "validate_opts accepts a cursor interval" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    assert opts[:cursor_interval_ms] == 0
+
+    assert {:ok, opts} = Skia.validate_opts(cursor_interval_ms: 16)
+    assert opts[:cursor_interval_ms] == 16
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(cursor_interval_ms: -1)
+  end
+
+  test "validate_opts accepts an input encoding" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    assert opts[:input_encoding] == :terms
+
+    assert {:ok, opts} = Skia.validate_opts(input_encoding: :binary)
+    assert opts[:input_encoding] == :binary
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(input_encoding: :etf)
+  end
+
+  test "validate_opts accepts dev overlay settings" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    refute opts[:dev_overlay][:enabled]
+    assert opts[:dev_overlay][:spacing] == 10
+
+    assert {:ok, opts} = Skia.validate_opts(dev_overlay: [enabled: true, spacing: 8.0])
+    assert opts[:dev_overlay][:spacing] == 8.0
+    assert opts[:dev_overlay][:rulers]
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(dev_overlay: [spacing: 0])
+  end
+
+  test "validate_opts accepts flight recorder settings" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    assert opts[:flight_recorder][:frames] == 0
+    assert opts[:flight_recorder][:max_width] == 320
+
+    assert {:ok, opts} =
+             Skia.validate_opts(flight_recorder: [frames: 30, max_width: 160, dump_dir: "/tmp"])
+
+    assert opts[:flight_recorder][:frames] == 30
+    assert opts[:flight_recorder][:dump_dir] == "/tmp"
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(flight_recorder: [max_width: 0])
+  end
+
+  test "validate_opts accepts stats export settings" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    assert opts[:stats_export][:path] == nil
+    assert opts[:stats_export][:format] == :json
+
+    assert {:ok, opts} =
+             Skia.validate_opts(stats_export: [path: "/tmp/skia-stats.sock", format: :binary])
+
+    assert opts[:stats_export][:format] == :binary
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(stats_export: [format: :csv])
+  end
+
+  test "validate_opts accepts a watermark" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    assert opts[:watermark][:text] == nil
+    assert opts[:watermark][:position] == :bottom_right
+    assert opts[:watermark][:target] == :frames
+
+    assert {:ok, opts} =
+             Skia.validate_opts(watermark: [text: "unit 7 {timestamp}", target: :captures])
+
+    assert opts[:watermark][:target] == :captures
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(watermark: [position: :center])
+  end
+
+  test "validate_opts accepts hardware decode settings" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    refute opts[:hardware_decode]
+
+    assert {:ok, opts} = Skia.validate_opts(hardware_decode: "/dev/video10")
+    assert opts[:hardware_decode] == "/dev/video10"
+
+    assert {:error, %NimbleOptions.ValidationError{}} = Skia.validate_opts(hardware_decode: :auto)
+  end
+
+  test "validate_opts accepts a theme" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    assert opts[:theme] == [font_size: 20]
+
+    theme = [font: "Inter", font_size: 18, clear_color: :dark_slate_blue]
+    assert {:ok, opts} = Skia.validate_opts(theme: theme)
+
+    assert opts[:theme][:font] == "Inter"
+    assert opts[:theme][:clear_color] == :dark_slate_blue
+
+    assert {:error, %NimbleOptions.ValidationError{}} = Skia.validate_opts(theme: [font_size: 0])
+
+    fallback_fonts = ["Noto Color Emoji", :roboto]
+    assert {:ok, opts} = Skia.validate_opts(theme: [fallback_fonts: fallback_fonts])
+    assert opts[:theme][:fallback_fonts] == fallback_fonts
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(theme: [fallback_fonts: "Noto Color Emoji"])
+  end
+
+  test "validate_opts accepts reference checks" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    refute opts[:reference_check][:strict]
+    assert opts[:reference_check][:max_frames] == 0
+
+    assert {:ok, opts} = Skia.validate_opts(reference_check: [strict: true, max_frames: 30])
+    assert opts[:reference_check][:strict]
+    assert opts[:reference_check][:max_frames] == 30
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(reference_check: [max_frames: -1])
+  end
+
+  test "validate_opts accepts a key details target" do
+    assert {:ok, opts} = Skia.validate_opts([])
+    assert opts[:key_details] == nil
+
+    assert {:ok, opts} = Skia.validate_opts(key_details: self())
+    assert opts[:key_details] == self()
+
+    assert {:error, %NimbleOptions.ValidationError{}} =
+             Skia.validate_opts(key_details: "scene")
+  end
 end
@@ -1,6 +1,7 @@
 defmodule Scenic.Driver.Skia.RasterPrimitivesTest do
   use ExUnit.Case, async: true
 
+  alias Scenic.Driver.Skia.Inspector
   alias Scenic.Driver.Skia.Native
   alias Scenic.Driver.Skia.TestSupport.ViewPort, as: ViewPortHelper
   alias Scenic.ViewPort
@@ -145,6 +146,200 @@ defmodule Scenic.Driver.Skia.RasterPrimitivesTest do
     end
   end
 
+  defmodule PolygonScene do
+    use Scenic.Scene
+    import Scenic.Primitives
+    alias Scenic.Script
+
+    def init(scene, _args, _opts) do
+      graph =
+        Scenic.Graph.build()
+        |> script("polygon_demo")
+
+      script =
+        Script.start()
+        |> Script.fill_color(:white)
+        |> Script.push_state()
+        |> Script.translate(16, 32)
+        |> draw_polygon({6, 14, 3, :fill})
+        |> Script.pop_state()
+        |> Script.translate(48, 32)
+        |> draw_star({5, 14, 5, 1, :fill})
+        |> Script.finish()
+
+      scene = Scenic.Scene.push_script(scene, script, "polygon_demo")
+      {:ok, Scenic.Scene.push_graph(scene, graph)}
+    end
+
+    defp draw_polygon(ops, shape), do: [{:draw_polygon, shape} | ops]
+    defp draw_star(ops, shape), do: [{:draw_star, shape} | ops]
+  end
+
+  defmodule BlinkScene do
+    use Scenic.Scene
+    import Scenic.Primitives
+    alias Scenic.Script
+
+    def init(scene, _args, _opts) do
+      graph =
+        Scenic.Graph.build()
+        |> script("blink_demo")
+
+      script =
+        Script.start()
+        |> Script.fill_color(:white)
+        |> Script.push_state()
+        |> blink(200)
+        |> Script.draw_rectangle(32, 64, :fill)
+        |> Script.pop_state()
+        |> Script.draw_rectangle(64, 8, :fill)
+        |> Script.finish()
+
+      scene = Scenic.Scene.push_script(scene, script, "blink_demo")
+      {:ok, Scenic.Scene.push_graph(scene, graph)}
+    end
+
+    defp blink(ops, period_ms), do: [{:blink, period_ms} | ops]
+  end
+
+  defmodule SpriteCellScene do
+    use Scenic.Scene
+    import Scenic.Primitives
+    alias Scenic.Script
+
+    def init(scene, _args, _opts) do
+      graph =
+        Scenic.Graph.build()
+        |> script("sprite_cell_demo")
+
+      script =
+        Script.start()
+        |> draw_sprite_cell(:test_red, {8, 8, 32, 32}, {2, 1})
+        |> Script.finish()
+
+      scene = Scenic.Scene.push_script(scene, script, "sprite_cell_demo")
+      {:ok, Scenic.Scene.push_graph(scene, graph)}
+    end
+
+    defp draw_sprite_cell(ops, image, dst, grid),
+      do: [{:draw_sprite_cell, image, dst, grid} | ops]
+  end
+
+  defmodule ConicGradientScene do
+    use Scenic.Scene
+    import Scenic.Primitives
+    alias Scenic.Script
+
+    def init(scene, _args, _opts) do
+      graph =
+        Scenic.Graph.build()
+        |> script("conic_gradient_demo")
+
+      stops = [{0, :red}, {0.5, :red}, {0.5, :blue}, {1, :blue}]
+
+      script =
+        Script.start()
+        |> fill_gradient({:conic, {32, 32, 0}}, stops)
+        |> Script.draw_rectangle(64, 64, :fill)
+        |> Script.finish()
+
+      scene = Scenic.Scene.push_script(scene, script, "conic_gradient_demo")
+      {:ok, Scenic.Scene.push_graph(scene, graph)}
+    end
+
+    defp fill_gradient(ops, shape, stops), do: [{:fill_gradient, shape, stops} | ops]
+  end
+
+  defmodule DropShadowScene do
+    use Scenic.Scene
+    import Scenic.Primitives
+    alias Scenic.Script
+
+    def init(scene, _args, _opts) do
+      graph =
+        Scenic.Graph.build()
+        |> script("drop_shadow_demo")
+
+      script =
+        Script.start()
+        |> Script.push_state()
+        |> drop_shadow({16, 16, 0, :blue})
+        |> Script.fill_color(:red)
+        |> Script.draw_rectangle(16, 16, :fill)
+        |> Script.pop_state()
+        |> Script.finish()
+
+      scene = Scenic.Scene.push_script(scene, script, "drop_shadow_demo")
+      {:ok, Scenic.Scene.push_graph(scene, graph)}
+    end
+
+    defp drop_shadow(ops, shadow), do: [{:drop_shadow, shadow} | ops]
+  end
+
+  defmodule PatternScene do
+    use Scenic.Scene
+    import Scenic.Primitives
+    alias Scenic.Script
+
+    def init(scene, _args, _opts) do
+      graph =
+        Scenic.Graph.build()
+        |> script("pattern_demo")
+
+      tile =
+        Script.start()
+        |> Script.fill_color(:red)
+        |> Script.draw_rectangle(8, 8, :fill)
+        |> Script.finish()
+
+      script =
+        Script.start()
+        |> fill_pattern("pattern_tile", {16, 16})
+        |> Script.draw_rectangle(64, 32, :fill)
+        |> Script.finish()
+
+      scene =
+        scene
+        |> Scenic.Scene.push_script(tile, "pattern_tile")
+        |> Scenic.Scene.push_script(script, "pattern_demo")
+
+      {:ok, Scenic.Scene.push_graph(scene, graph)}
+    end
+
+    defp fill_pattern(ops, id, size), do: [{:fill_pattern, id, size} | ops]
+  end
+
+  defmodule SaveLayerScene do
+    use Scenic.Scene
+    import Scenic.Primitives
+    alias Scenic.Script
+
+    def init(scene, _args, _opts) do
+      graph =
+        Scenic.Graph.build()
+        |> script("save_layer_demo")
+
+      script =
+        Script.start()
+        |> Script.fill_color(:white)
+        |> save_layer({0, 0, 64, 64}, alpha: 0.5)
+        |> Script.push_state()
+        |> Script.translate(8, 8)
+        |> Script.draw_rectangle(24, 24, :fill)
+        |> Script.translate(12, 0)
+        |> Script.draw_rectangle(24, 24, :fill)
+        |> Script.pop_state()
+        |> restore()
+        |> Script.finish()
+
+      scene = Scenic.Scene.push_script(scene, script, "save_layer_demo")
+      {:ok, Scenic.Scene.push_graph(scene, graph)}
+    end
+
+    defp save_layer(ops, bounds, opts), do: [{:save_layer, bounds, opts} | ops]
+    defp restore(ops), do: [:restore | ops]
+  end
+
   defmodule RecursiveScriptScene do
     use Scenic.Scene
     import Scenic.Primitives
@@ -545,150 +740,1090 @@ defmodule Scenic.Driver.Skia.RasterPrimitivesTest do
     use Scenic.Scene
     import Scenic.Primitives
 
-    def init(scene, _args, _opts) do
-      graph =
-        Scenic.Graph.build()
-        |> line({{10, 20}, {30, 20}},
-          stroke: {6, {:radial, {20, 20, 0, 10, :red, :blue}}}
-        )
+    def init(scene, _args, _opts) do
+      graph =
+        Scenic.Graph.build()
+        |> line({{10, 20}, {30, 20}},
+          stroke: {6, {:radial, {20, 20, 0, 10, :red, :blue}}}
+        )
+
+      {:ok, Scenic.Scene.push_graph(scene, graph)}
+    end
+  end
+
+  defmodule ImageFillScene do
+    use Scenic.Scene
+    import Scenic.Primitives
+
+    def init(scene, _args, _opts) do
+      graph =
+        Scenic.Graph.build()
+        |> rect({20, 20},
+          fill: {:image, :test_red},
+          translate: {10, 10}
+        )
+
+      {:ok, Scenic.Scene.push_graph(scene, graph)}
+    end
+  end
+
+  defmodule StrokeImageScene do
+    use Scenic.Scene
+    import Scenic.Primitives
+
+    def init(scene, _args, _opts) do
+      graph =
+        Scenic.Graph.build()
+        |> line({{0, 0}, {20, 0}},
+          stroke: {6, {:image, :test_red}},
+          translate: {10, 20}
+        )
+
+      {:ok, Scenic.Scene.push_graph(scene, graph)}
+    end
+  end
+
+  defmodule StreamFillScene do
+    use Scenic.Scene
+    import Scenic.Primitives
+
+    def init(scene, stream_id, _opts) do
+      graph =
+        Scenic.Graph.build()
+        |> rect({20, 20},
+          fill: {:stream, stream_id},
+          translate: {10, 10}
+        )
+
+      {:ok, Scenic.Scene.push_graph(scene, graph)}
+    end
+  end
+
+  defmodule StreamStrokeScene do
+    use Scenic.Scene
+    import Scenic.Primitives
+
+    def init(scene, stream_id, _opts) do
+      graph =
+        Scenic.Graph.build()
+        |> line({{0, 0}, {20, 0}},
+          stroke: {6, {:stream, stream_id}},
+          translate: {10, 20}
+        )
+
+      {:ok, Scenic.Scene.push_graph(scene, graph)}
+    end
+  end
+
+  defmodule SpritesScene do
+    use Scenic.Scene
+    import Scenic.Primitives
+
+    def init(scene, _args, _opts) do
+      graph =
+        Scenic.Graph.build()
+        |> sprites(
+          {:test_red,
+           [
+             {{0, 0}, {2, 2}, {10, 10}, {20, 20}}
+           ]}
+        )
+
+      {:ok, Scenic.Scene.push_graph(scene, graph)}
+    end
+  end
+
+  defmodule CapCompareScene do
+    use Scenic.Scene
+    import Scenic.Primitives
+    alias Scenic.Script
+
+    def init(scene, _args, _opts) do
+      graph =
+        Scenic.Graph.build()
+        |> script("cap_compare_script")
+
+      script =
+        Script.start()
+        |> Script.stroke_color(:white)
+        |> Script.stroke_width(10)
+        |> Script.push_state()
+        |> Script.cap(:butt)
+        |> Script.translate(10, 20)
+        |> Script.draw_line(0, 0, 20, 0, :stroke)
+        |> Script.pop_state()
+        |> Script.push_state()
+        |> Script.cap(:square)
+        |> Script.translate(10, 40)
+        |> Script.draw_line(0, 0, 20, 0, :stroke)
+        |> Script.pop_state()
+        |> Script.finish()
+
+      scene = Scenic.Scene.push_script(scene, script, "cap_compare_script")
+      {:ok, Scenic.Scene.push_graph(scene, graph)}
+    end
+  end
+
+  defmodule StrokeQuadScene do
+    use Scenic.Scene
+    import Scenic.Primitives
+
+    def init(scene, _args, _opts) do
+      graph =
+        Scenic.Graph.build()
+        |> quad({{0, 0}, {20, 0}, {24, 20}, {0, 20}},
+          stroke: {2, :white},
+          translate: {10, 10}
+        )
+
+      {:ok, Scenic.Scene.push_graph(scene, graph)}
+    end
+  end
+
+  defmodule FullRectScene do
+    use Scenic.Scene
+    import Scenic.Primitives
+
+    def init(scene, _args, _opts) do
+      graph =
+        Scenic.Graph.build()
+        |> rect({64, 64}, fill: :red)
+
+      {:ok, Scenic.Scene.push_graph(scene, graph)}
+    end
+  end
+
+  test "draw_rect fills expected pixels" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    vp = ViewPortHelper.start(size: {64, 64}, scene: RectScene)
+    renderer = ViewPortHelper.renderer(vp)
+
+    on_exit(fn ->
+      if Process.alive?(vp.pid) do
+        _ = ViewPort.stop(vp)
+      end
+
+      _ = Native.stop(renderer)
+    end)
+
+    {width, _height, frame} =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        pixel_at(data, w, 20, 20) == {255, 0, 0} and
+          pixel_at(data, w, 15, 10) == {255, 255, 255}
+      end)
+
+    # Background just outside the translated rect bounds.
+    assert pixel_at(frame, width, 7, 10) == {0, 0, 0}
+    assert pixel_at(frame, width, 10, 7) == {0, 0, 0}
+    assert pixel_at(frame, width, 7, 7) == {0, 0, 0}
+    assert pixel_at(frame, width, 33, 10) == {0, 0, 0}
+    assert pixel_at(frame, width, 10, 33) == {0, 0, 0}
+    assert pixel_at(frame, width, 33, 33) == {0, 0, 0}
+    # Stroke samples on each edge.
+    assert pixel_at(frame, width, 15, 10) == {255, 255, 255}
+    assert pixel_at(frame, width, 10, 15) == {255, 255, 255}
+    assert pixel_at(frame, width, 30, 15) == {255, 255, 255}
+    assert pixel_at(frame, width, 15, 30) == {255, 255, 255}
+    # Fill sample inside the rect.
+    assert pixel_at(frame, width, 20, 20) == {255, 0, 0}
+  end
+
+  test "draw_immediate overlays the next frame without storing the script" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    vp = ViewPortHelper.start(size: {64, 64}, scene: RectScene)
+    renderer = ViewPortHelper.renderer(vp)
+
+    on_exit(fn ->
+      if Process.alive?(vp.pid) do
+        _ = ViewPort.stop(vp)
+      end
+
+      _ = Native.stop(renderer)
+    end)
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 20, 20) == {255, 0, 0}
+    end)
+
+    overlay =
+      Scenic.Script.start()
+      |> Scenic.Script.fill_color(:blue)
+      |> Scenic.Script.draw_rect(8, 8, :fill)
+      |> Scenic.Script.finish()
+
+    assert :ok = Scenic.Driver.Skia.draw_immediate(renderer, overlay)
+
+    {width, _height, frame} =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        pixel_at(data, w, 4, 4) == {0, 0, 255}
+      end)
+
+    assert pixel_at(frame, width, 20, 20) == {255, 0, 0}
+
+    assert :ok = Scenic.Driver.Skia.draw_immediate(renderer, [])
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 4, 4) == {0, 0, 0}
+    end)
+  end
+
+  test "get_gpu_info and get_status report the raster backend as CPU rendering" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    vp = ViewPortHelper.start(size: {64, 64}, scene: RectScene)
+    renderer = ViewPortHelper.renderer(vp)
+
+    on_exit(fn ->
+      if Process.alive?(vp.pid) do
+        _ = ViewPort.stop(vp)
+      end
+
+      _ = Native.stop(renderer)
+    end)
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 20, 20) == {255, 0, 0}
+    end)
+
+    assert {:ok, info} = Scenic.Driver.Skia.get_gpu_info(renderer)
+    assert info.backend == "raster"
+    refute info.gpu
+    refute info.software
+    assert info.egl_extensions == []
+    assert info.fallback == nil
+
+    assert {:ok, status} = Scenic.Driver.Skia.get_status(renderer)
+    assert status.backend == "raster"
+    assert status.running
+    assert status.gpu == false
+    assert status.software == false
+    assert status.fallback == nil
+    refute status.degraded
+    assert status.backend_reason == nil
+
+    assert {:ok, []} = Scenic.Driver.Skia.list_outputs(renderer)
+    assert :ok = Scenic.Driver.Skia.set_output_root(renderer, "HDMI-A-2", "_other_")
+    assert :ok = Scenic.Driver.Skia.set_output_root(renderer, "HDMI-A-2", nil)
+
+    # Plane properties belong to DRM outputs.
+    assert {:error, reason} =
+             Scenic.Driver.Skia.set_plane_property(renderer, "HDMI-A-1", "zpos", 1)
+
+    assert reason =~ "DRM"
+
+    assert_raise FunctionClauseError, fn ->
+      Scenic.Driver.Skia.set_plane_property(renderer, "HDMI-A-1", "zpos", 1.5)
+    end
+  end
+
+  test "raster_gpu frames come back through get_raster_frame with or without a GPU" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    {:ok, renderer} =
+      Native.start("raster_gpu", {32, 32}, "Scenic Window", false, nil, true, false)
+
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    assert :ok = normalize_nif_result(Native.set_clear_color(renderer, {0, 0, 255, 255}))
+
+    {width, height, frame} =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        pixel_at(data, w, 4, 4) == {0, 0, 255}
+      end)
+
+    assert {width, height} == {32, 32}
+    assert pixel_at(frame, width, 31, 31) == {0, 0, 255}
+
+    assert {:ok, info} = Scenic.Driver.Skia.get_gpu_info(renderer)
+    assert info.backend == "raster"
+
+    if info.gpu do
+      assert info.renderer != ""
+      assert info.fallback == nil
+    else
+      assert is_binary(info.fallback)
+    end
+  end
+
+  test "dev overlay draws a grid over the scene until disabled" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    {:ok, renderer} = Native.start("raster", {32, 32}, "Scenic Window", false, nil, true, false)
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    assert :ok = normalize_nif_result(Native.set_clear_color(renderer, {0, 0, 0, 255}))
+
+    assert {:error, _} = Native.set_dev_overlay(renderer, {0.0, {0, 255, 0, 255}, false, false})
+
+    assert :ok =
+             Scenic.Driver.Skia.set_dev_overlay(renderer,
+               enabled: true,
+               spacing: 16,
+               color: :lime,
+               rulers: false,
+               pointer: false
+             )
+
+    grid? = fn {w, _h, data} ->
+      Enum.any?(15..16, fn x -> pixel_at(data, w, x, 5) == {0, 255, 0} end)
+    end
+
+    {width, _height, frame} = wait_for_frame!(renderer, 40, grid?)
+    assert pixel_at(frame, width, 8, 5) == {0, 0, 0}
+
+    assert :ok = Scenic.Driver.Skia.set_dev_overlay(renderer, enabled: false)
+    wait_for_frame!(renderer, 40, fn frame -> not grid?.(frame) end)
+  end
+
+  test "focus moves between regions and the ring follows it" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    {:ok, renderer} = Native.start("raster", {64, 32}, "Scenic Window", false, nil, true, false)
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    assert :ok = normalize_nif_result(Native.set_clear_color(renderer, {0, 0, 0, 255}))
+    assert {:ok, nil} = Scenic.Driver.Skia.focus_next(renderer, :right)
+
+    regions = [{"left", {8, 8, 16, 16}}, {"right", {40, 8, 16, 16}}]
+    assert :ok = Scenic.Driver.Skia.set_focus_regions(renderer, regions)
+    assert {:error, _} = Scenic.Driver.Skia.set_focus(renderer, "missing")
+    assert {:ok, "left"} = Scenic.Driver.Skia.focus_next(renderer, :right)
+    assert {:ok, "right"} = Scenic.Driver.Skia.focus_next(renderer, :right)
+    assert {:ok, "right"} = Scenic.Driver.Skia.focus_next(renderer, :down)
+
+    assert {:error, _} = Native.set_focus_ring(renderer, {{255, 255, 255, 255}, 0.0, 0.0})
+
+    assert :ok =
+             Scenic.Driver.Skia.set_focus_ring(renderer, enabled: true, color: :lime, width: 2)
+
+    ring_on? = fn x -> fn {w, _h, data} -> pixel_at(data, w, x, 16) == {0, 255, 0} end end
+    {width, _height, frame} = wait_for_frame!(renderer, 40, ring_on?.(39))
+    assert pixel_at(frame, width, 48, 16) == {0, 0, 0}
+
+    assert :ok = Scenic.Driver.Skia.set_focus(renderer, "left")
+    {width, _height, frame} = wait_for_frame!(renderer, 40, ring_on?.(7))
+    assert pixel_at(frame, width, 39, 16) == {0, 0, 0}
+  end
+
+  test "linear blending brightens translucent fills over dark backgrounds" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    {:ok, renderer} = Native.start("raster", {32, 32}, "Scenic Window", false, nil, true, false)
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    assert :ok = normalize_nif_result(Native.set_clear_color(renderer, {0, 0, 0, 255}))
+
+    root =
+      Scenic.Script.start()
+      |> Scenic.Script.fill_color({255, 255, 255, 128})
+      |> Scenic.Script.draw_rect(32, 32, :fill)
+      |> Scenic.Script.finish()
+      |> Scenic.Script.serialize()
+      |> IO.iodata_to_binary()
+
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "_root_", root)
+
+    gray = fn {w, _h, data} -> elem(pixel_at(data, w, 16, 16), 0) end
+    srgb = renderer |> wait_for_frame!(40, fn frame -> gray.(frame) in 120..136 end) |> gray.()
+
+    assert :ok = Scenic.Driver.Skia.set_linear_blending(renderer, true)
+    linear = renderer |> wait_for_frame!(40, fn frame -> gray.(frame) > 170 end) |> gray.()
+    assert linear > srgb
+
+    assert :ok = Scenic.Driver.Skia.set_linear_blending(renderer, false)
+    wait_for_frame!(renderer, 40, fn frame -> gray.(frame) in 120..136 end)
+  end
+
+  test "hinted script submits repaint the hinted area" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    {:ok, renderer} = Native.start("raster", {32, 32}, "Scenic Window", false, nil, true, false)
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    box = fn color ->
+      Scenic.Script.start()
+      |> Scenic.Script.fill_color(color)
+      |> Scenic.Script.draw_rect(8, 8, :fill)
+      |> Scenic.Script.finish()
+      |> Scenic.Script.serialize()
+      |> IO.iodata_to_binary()
+    end
+
+    root =
+      Scenic.Script.start()
+      |> Scenic.Script.fill_color(:green)
+      |> Scenic.Script.draw_rect(32, 8, :fill)
+      |> Scenic.Script.translate({4, 16})
+      |> Scenic.Script.render_script("box")
+      |> Scenic.Script.finish()
+      |> Scenic.Script.serialize()
+      |> IO.iodata_to_binary()
+
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "box", box.(:red))
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "_root_", root)
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 8, 20) == {255, 0, 0}
+    end)
+
+    assert :ok =
+             Scenic.Driver.Skia.submit_script_with_id(renderer, "box", box.(:blue),
+               bounds: {4, 16, 8, 8}
+             )
+
+    {width, _height, frame} =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        pixel_at(data, w, 8, 20) == {0, 0, 255}
+      end)
+
+    assert pixel_at(frame, width, 20, 4) == {0, 128, 0}
+    assert {:error, _} = Native.submit_script_with_id(renderer, "box", "", {0.0, 0.0, -1.0, 1.0})
+  end
+
+  test "scripts submitted without a hint repaint where they were drawn and now are" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    {:ok, renderer} = Native.start("raster", {32, 32}, "Scenic Window", false, nil, true, false)
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    serialize = fn script ->
+      script |> Scenic.Script.finish() |> Scenic.Script.serialize() |> IO.iodata_to_binary()
+    end
+
+    box = fn x ->
+      Scenic.Script.start()
+      |> Scenic.Script.push_state()
+      |> Scenic.Script.translate({x, 16})
+      |> Scenic.Script.fill_color(:red)
+      |> Scenic.Script.draw_rect(8, 8, :fill)
+      |> Scenic.Script.pop_state()
+      |> serialize.()
+    end
+
+    root =
+      Scenic.Script.start()
+      |> Scenic.Script.push_state()
+      |> Scenic.Script.fill_color(:green)
+      |> Scenic.Script.draw_rect(32, 8, :fill)
+      |> Scenic.Script.pop_state()
+      |> Scenic.Script.render_script("box")
+      |> serialize.()
+
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "box", box.(4))
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "_root_", root)
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 8, 20) == {255, 0, 0}
+    end)
+
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "box", box.(20))
+
+    {width, _height, frame} =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        pixel_at(data, w, 24, 20) == {255, 0, 0} and pixel_at(data, w, 8, 20) != {255, 0, 0}
+      end)
+
+    assert pixel_at(frame, width, 20, 4) == {0, 128, 0}
+
+    refute match?({:error, _}, Native.del_script(renderer, "box"))
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 24, 20) != {255, 0, 0}
+    end)
+  end
+
+  test "get_redraw_reasons reports the scripts behind each frame" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    {:ok, renderer} = Native.start("raster", {32, 32}, "Scenic Window", false, nil, true, false)
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    root =
+      Scenic.Script.start()
+      |> Scenic.Script.fill_color(:red)
+      |> Scenic.Script.draw_rect(32, 32, :fill)
+      |> Scenic.Script.finish()
+      |> Scenic.Script.serialize()
+      |> IO.iodata_to_binary()
+
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "_root_", root)
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 4, 4) == {255, 0, 0}
+    end)
+
+    assert {:ok, frames} = Scenic.Driver.Skia.get_redraw_reasons(renderer)
+    assert Enum.any?(frames, &(&1.scripts == ["_root_"] and not &1.other))
+    assert frames == Enum.sort_by(frames, & &1.frame)
+  end
+
+  test "the script cache serves scripts stored by an earlier renderer" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    dir = Path.join(System.tmp_dir!(), "skia-script-cache-#{System.unique_integer([:positive])}")
+    on_exit(fn -> File.rm_rf(dir) end)
+
+    root =
+      Scenic.Script.start()
+      |> Scenic.Script.fill_color(:red)
+      |> Scenic.Script.draw_rect(32, 32, :fill)
+      |> Scenic.Script.finish()
+      |> Scenic.Script.serialize()
+      |> IO.iodata_to_binary()
+
+    {:ok, first} = Native.start("raster", {32, 32}, "Scenic Window", false, nil, true, false)
+    assert {:ok, 0} = Scenic.Driver.Skia.set_script_cache(first, dir: dir)
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(first, "_root_", root)
+    assert {:ok, %{script_cache: %{misses: 1, hits: 0}}} = Scenic.Driver.Skia.get_stats(first)
+    _ = Native.stop(first)
+
+    # The cache writes stored scripts from a background thread.
+    assert Enum.any?(1..40, fn _ ->
+             Process.sleep(50)
+             dir |> File.ls!() |> Enum.any?(&String.ends_with?(&1, ".script"))
+           end)
+
+    {:ok, renderer} = Native.start("raster", {32, 32}, "Scenic Window", false, nil, true, false)
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    assert {:ok, 1} = Scenic.Driver.Skia.set_script_cache(renderer, dir: dir)
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "_root_", root)
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 4, 4) == {255, 0, 0}
+    end)
+
+    assert {:ok, %{script_cache: %{hits: 1, misses: 0}}} = Scenic.Driver.Skia.get_stats(renderer)
+  end
+
+  test "resubmitted scripts reuse their parsed ops" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    {:ok, renderer} = Native.start("raster", {32, 32}, "Scenic Window", false, nil, true, false)
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    script = fn color ->
+      Scenic.Script.start()
+      |> Scenic.Script.fill_color(color)
+      |> Scenic.Script.draw_rect(32, 32, :fill)
+      |> Scenic.Script.finish()
+      |> Scenic.Script.serialize()
+      |> IO.iodata_to_binary()
+    end
+
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "_root_", script.(:red))
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "_root_", script.(:blue))
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "_root_", script.(:red))
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 4, 4) == {255, 0, 0}
+    end)
+
+    assert {:ok, %{entries: 2, hits: 1, misses: 2}} = Scenic.Driver.Skia.cache_stats(renderer)
+    assert {:ok, %{script_cache: %{hits: 1}}} = Scenic.Driver.Skia.get_stats(renderer)
+  end
+
+  test "get_render_stats splits the last frame's time" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    {:ok, renderer} = Native.start("raster", {32, 32}, "Scenic Window", false, nil, true, false)
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    root =
+      Scenic.Script.start()
+      |> Scenic.Script.fill_color(:red)
+      |> Scenic.Script.draw_rect(32, 32, :fill)
+      |> Scenic.Script.finish()
+      |> Scenic.Script.serialize()
+      |> IO.iodata_to_binary()
+
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "_root_", root)
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 4, 4) == {255, 0, 0}
+    end)
+
+    assert {:ok, stats} = Scenic.Driver.Skia.get_render_stats(renderer)
+    assert stats.frame >= 1
+    assert stats.ops > 0
+    assert stats.flush_us == 0
+    assert stats.draw_us <= stats.frame_us
+    assert is_float(stats.fps)
+  end
+
+  test "capture_frame reads back the next rendered frame as RGBA" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    {:ok, renderer} = Native.start("raster", {32, 24}, "Scenic Window", false, nil, true, false)
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    root =
+      Scenic.Script.start()
+      |> Scenic.Script.fill_color(:blue)
+      |> Scenic.Script.draw_rect(32, 24, :fill)
+      |> Scenic.Script.finish()
+      |> Scenic.Script.serialize()
+      |> IO.iodata_to_binary()
+
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "_root_", root)
+
+    assert {:ok, {32, 24, rgba}} = Scenic.Driver.Skia.capture_frame(renderer)
+    assert byte_size(rgba) == 32 * 24 * 4
+    assert <<0, 0, 255, 255, _::binary>> = rgba
+  end
+
+  test "a watermark stamps captures or every frame" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    {:ok, renderer} = Native.start("raster", {64, 32}, "Scenic Window", false, nil, true, false)
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    root =
+      Scenic.Script.start()
+      |> Scenic.Script.fill_color(:blue)
+      |> Scenic.Script.draw_rect(64, 32, :fill)
+      |> Scenic.Script.finish()
+      |> Scenic.Script.serialize()
+      |> IO.iodata_to_binary()
+
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "_root_", root)
+
+    stamp = [text: "unit 7", position: :top_left, margin: 0, background: :red]
+
+    assert {:error, _} =
+             Scenic.Driver.Skia.set_watermark(renderer, [position: :middle] ++ stamp)
+
+    assert :ok = Scenic.Driver.Skia.set_watermark(renderer, [target: :captures] ++ stamp)
+    assert {:ok, {64, 32, rgba}} = Scenic.Driver.Skia.capture_frame(renderer)
+    assert <<255, 0, 0, 255, _::binary>> = rgba
+
+    {width, _height, frame} =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} -> pixel_at(data, w, 0, 0) != {0, 0, 0} end)
+
+    assert pixel_at(frame, width, 0, 0) == {0, 0, 255}
+
+    assert :ok = Scenic.Driver.Skia.set_watermark(renderer, stamp)
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} -> pixel_at(data, w, 0, 0) == {255, 0, 0} end)
+
+    assert :ok = Scenic.Driver.Skia.set_watermark(renderer, text: nil)
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} -> pixel_at(data, w, 0, 0) == {0, 0, 255} end)
+  end
+
+  test "an inspector reads the renderer but cannot change it" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    {:ok, renderer} = Native.start("raster", {32, 24}, "Scenic Window", false, nil, true, false)
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    root =
+      Scenic.Script.start()
+      |> Scenic.Script.fill_color(:green)
+      |> Scenic.Script.draw_rect(32, 24, :fill)
+      |> Scenic.Script.finish()
+      |> Scenic.Script.serialize()
+      |> IO.iodata_to_binary()
+
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "_root_", root)
+
+    assert {:ok, inspector} = Inspector.attach(renderer)
+
+    assert {:ok, %{root_id: "_root_", scripts: %{"_root_" => %{ops: ops}}}} =
+             Inspector.dump_scene(inspector)
+
+    assert ops > 0
+    assert {:ok, {32, 24, rgba}} = Inspector.capture_frame(inspector)
+    assert byte_size(rgba) == 32 * 24 * 4
+    assert {:ok, %{frame: frame}} = Inspector.get_render_stats(inspector)
+    assert frame >= 1
+    assert {:ok, %{parsed: %{}}} = Inspector.get_stats(inspector)
+
+    assert :ok = Inspector.subscribe_input(inspector, [:cursor_button])
+    assert {:ok, []} = Inspector.drain_input(inspector)
+    assert :ok = Inspector.unsubscribe_input(inspector)
+
+    assert_raise ArgumentError, fn ->
+      Scenic.Driver.Skia.submit_script_with_id(inspector, "_root_", root)
+    end
+  end
+
+  test "rollback restores the scripts saved by checkpoint" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    {:ok, renderer} = Native.start("raster", {32, 32}, "Scenic Window", false, nil, true, false)
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    root = fn color ->
+      Scenic.Script.start()
+      |> Scenic.Script.fill_color(color)
+      |> Scenic.Script.draw_rect(32, 32, :fill)
+      |> Scenic.Script.finish()
+      |> Scenic.Script.serialize()
+      |> IO.iodata_to_binary()
+    end
+
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "_root_", root.(:red))
+    assert {:ok, token} = Scenic.Driver.Skia.checkpoint(renderer)
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "_root_", root.(:blue))
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 4, 4) == {0, 0, 255}
+    end)
+
+    assert :ok = Scenic.Driver.Skia.rollback(renderer, token)
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 4, 4) == {255, 0, 0}
+    end)
+
+    assert {:ok, true} = Scenic.Driver.Skia.release_checkpoint(renderer, token)
+    assert {:error, _} = Scenic.Driver.Skia.rollback(renderer, token)
+  end
+
+  test "set_script_transform moves a script without resubmitting it" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    {:ok, renderer} = Native.start("raster", {32, 32}, "Scenic Window", false, nil, true, false)
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    serialize = fn script ->
+      script |> Scenic.Script.finish() |> Scenic.Script.serialize() |> IO.iodata_to_binary()
+    end
+
+    list =
+      Scenic.Script.start()
+      |> Scenic.Script.fill_color(:red)
+      |> Scenic.Script.draw_rect(8, 8, :fill)
+      |> serialize.()
+
+    root = Scenic.Script.start() |> Scenic.Script.render_script("list") |> serialize.()
+
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "list", list)
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "_root_", root)
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 4, 4) == {255, 0, 0}
+    end)
+
+    assert :ok = Scenic.Driver.Skia.set_script_transform(renderer, "list", translate: {16, 16})
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 20, 20) == {255, 0, 0} and pixel_at(data, w, 4, 4) != {255, 0, 0}
+    end)
+
+    assert :ok = Scenic.Driver.Skia.set_script_transform(renderer, "list", scale: 2)
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 12, 12) == {255, 0, 0} and pixel_at(data, w, 20, 20) != {255, 0, 0}
+    end)
+
+    assert :ok = Scenic.Driver.Skia.set_script_transform(renderer, "list", nil)
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 4, 4) == {255, 0, 0} and pixel_at(data, w, 12, 12) != {255, 0, 0}
+    end)
+  end
+
+  test "negative sizes are clamped with warnings or rejected in strict mode" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    {:ok, renderer} = Native.start("raster", {32, 32}, "Scenic Window", false, nil, true, false)
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    script =
+      Scenic.Script.start()
+      |> Scenic.Script.fill_color(:red)
+      |> Scenic.Script.draw_rect(-8, 8, :fill)
+      |> Scenic.Script.finish()
+      |> Scenic.Script.serialize()
+      |> IO.iodata_to_binary()
+
+    assert :ok = Scenic.Driver.Skia.submit_script_with_id(renderer, "bad", script)
+
+    assert {:ok, [warning]} = Scenic.Driver.Skia.take_geometry_warnings(renderer)
+
+    assert %{script: "bad", op: "draw_rect", field: "width", value: -8.0, problem: "negative"} =
+             warning
+
+    assert {:ok, []} = Scenic.Driver.Skia.take_geometry_warnings(renderer)
+
+    assert :ok = Scenic.Driver.Skia.set_geometry_mode(renderer, :strict)
+    assert {:error, reason} = Scenic.Driver.Skia.submit_script_with_id(renderer, "bad", script)
+    assert reason =~ "draw_rect width is negative"
+    assert {:ok, []} = Scenic.Driver.Skia.take_geometry_warnings(renderer)
+  end
+
+  test "load_bundle registers every script of the archive" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    {:ok, renderer} = Native.start("raster", {32, 32}, "Scenic Window", false, nil, true, false)
+    on_exit(fn -> _ = Native.stop(renderer) end)
+
+    serialize = fn script ->
+      script |> Scenic.Script.finish() |> Scenic.Script.serialize() |> IO.iodata_to_binary()
+    end
+
+    box =
+      Scenic.Script.start()
+      |> Scenic.Script.fill_color(:red)
+      |> Scenic.Script.draw_rect(8, 8, :fill)
+      |> serialize.()
+
+    root =
+      Scenic.Script.start()
+      |> Scenic.Script.translate({4, 4})
+      |> Scenic.Script.render_script("box")
+      |> serialize.()
+
+    manifest = "# theme pack\nscript box scripts/box.bin\n\nscript _root_ scripts/root.bin\n"
+    path = Path.join(System.tmp_dir!(), "skia_bundle_#{System.unique_integer([:positive])}.tgz")
+    on_exit(fn -> File.rm(path) end)
+
+    files = [
+      {~c"manifest", manifest},
+      {~c"scripts/box.bin", box},
+      {~c"scripts/root.bin", root}
+    ]
+
+    assert :ok = :erl_tar.create(String.to_charlist(path), files, [:compressed])
+    assert :ok = Scenic.Driver.Skia.load_bundle(renderer, path)
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 8, 8) == {255, 0, 0}
+    end)
+
+    assert {:error, {:invalid_manifest_line, "sprite x y"}} =
+             files
+             |> List.keyreplace(~c"manifest", 0, {~c"manifest", "sprite x y\n"})
+             |> tar_binary()
+             |> then(&Scenic.Driver.Skia.load_bundle(renderer, &1))
+
+    truncated = binary_part(File.read!(path), 0, 24)
+
+    assert {:error, {:invalid_bundle, :gzip}} =
+             Scenic.Driver.Skia.load_bundle(renderer, truncated)
+  end
+
+  test "clip_path limits drawing to the path" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    vp = ViewPortHelper.start(size: {64, 64}, scene: ClipPathScene)
+    renderer = ViewPortHelper.renderer(vp)
+
+    on_exit(fn ->
+      if Process.alive?(vp.pid) do
+        _ = ViewPort.stop(vp)
+      end
+
+      _ = Native.stop(renderer)
+    end)
+
+    {width, _height, frame} =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        pixel_at(data, w, 20, 20) == {255, 255, 255} and
+          pixel_at(data, w, 35, 35) == {0, 0, 0}
+      end)
+
+    # Center of the clipped circle should be white.
+    assert pixel_at(frame, width, 20, 20) == {255, 255, 255}
+    # Inside the rectangle but outside the circle should remain background.
+    assert pixel_at(frame, width, 35, 35) == {0, 0, 0}
+  end
+
+  test "fill_pattern repeats a script drawn into a tile" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    vp = ViewPortHelper.start(size: {64, 64}, scene: PatternScene)
+    renderer = ViewPortHelper.renderer(vp)
+
+    on_exit(fn ->
+      if Process.alive?(vp.pid) do
+        _ = ViewPort.stop(vp)
+      end
+
+      _ = Native.stop(renderer)
+    end)
+
+    {width, _height, frame} =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        pixel_at(data, w, 4, 4) != {0, 0, 0}
+      end)
+
+    for {x, y} <- [{4, 4}, {20, 4}, {36, 20}, {52, 20}] do
+      assert {r, 0, 0} = pixel_at(frame, width, x, y)
+      assert r > 200
+    end
+
+    for {x, y} <- [{12, 12}, {28, 4}, {4, 28}, {4, 44}] do
+      assert pixel_at(frame, width, x, y) == {0, 0, 0}
+    end
+  end
+
+  test "save_layer applies opacity to the composited group" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    vp = ViewPortHelper.start(size: {64, 64}, scene: SaveLayerScene)
+    renderer = ViewPortHelper.renderer(vp)
+
+    on_exit(fn ->
+      if Process.alive?(vp.pid) do
+        _ = ViewPort.stop(vp)
+      end
+
+      _ = Native.stop(renderer)
+    end)
+
+    {width, _height, frame} =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        pixel_at(data, w, 12, 12) != {0, 0, 0}
+      end)
+
+    {r, _g, _b} = pixel_at(frame, width, 12, 12)
+    assert r in 120..135
+    # The overlap is drawn into the layer at full opacity, so it matches the single rect.
+    assert pixel_at(frame, width, 24, 12) == pixel_at(frame, width, 12, 12)
+  end
+
+  test "draw_polygon and draw_star fill shapes around the origin" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    vp = ViewPortHelper.start(size: {64, 64}, scene: PolygonScene)
+    renderer = ViewPortHelper.renderer(vp)
+
+    on_exit(fn ->
+      if Process.alive?(vp.pid) do
+        _ = ViewPort.stop(vp)
+      end
+
+      _ = Native.stop(renderer)
+    end)
+
+    {width, _height, frame} =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        pixel_at(data, w, 48, 32) == {255, 255, 255}
+      end)
 
-      {:ok, Scenic.Scene.push_graph(scene, graph)}
-    end
+    assert pixel_at(frame, width, 16, 32) == {255, 255, 255}
+    assert pixel_at(frame, width, 48, 24) == {255, 255, 255}
+    # Between two tips of the star, outside the inner radius.
+    assert pixel_at(frame, width, 55, 22) == {0, 0, 0}
+    assert pixel_at(frame, width, 2, 2) == {0, 0, 0}
   end
 
-  defmodule ImageFillScene do
-    use Scenic.Scene
-    import Scenic.Primitives
+  test "blink toggles the ops up to pop_state on the driver clock" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
 
-    def init(scene, _args, _opts) do
-      graph =
-        Scenic.Graph.build()
-        |> rect({20, 20},
-          fill: {:image, :test_red},
-          translate: {10, 10}
-        )
+    vp = ViewPortHelper.start(size: {64, 64}, scene: BlinkScene)
+    renderer = ViewPortHelper.renderer(vp)
 
-      {:ok, Scenic.Scene.push_graph(scene, graph)}
-    end
-  end
+    on_exit(fn ->
+      if Process.alive?(vp.pid) do
+        _ = ViewPort.stop(vp)
+      end
 
-  defmodule StrokeImageScene do
-    use Scenic.Scene
-    import Scenic.Primitives
+      _ = Native.stop(renderer)
+    end)
 
-    def init(scene, _args, _opts) do
-      graph =
-        Scenic.Graph.build()
-        |> line({{0, 0}, {20, 0}},
-          stroke: {6, {:image, :test_red}},
-          translate: {10, 20}
-        )
+    {width, _height, frame} =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        pixel_at(data, w, 48, 4) == {255, 255, 255} and pixel_at(data, w, 16, 32) == {0, 0, 0}
+      end)
 
-      {:ok, Scenic.Scene.push_graph(scene, graph)}
-    end
-  end
+    # The rect after pop_state stays visible while the blinking one is hidden.
+    assert pixel_at(frame, width, 8, 4) == {255, 255, 255}
 
-  defmodule StreamFillScene do
-    use Scenic.Scene
-    import Scenic.Primitives
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 16, 32) == {255, 255, 255}
+    end)
 
-    def init(scene, stream_id, _opts) do
-      graph =
-        Scenic.Graph.build()
-        |> rect({20, 20},
-          fill: {:stream, stream_id},
-          translate: {10, 10}
-        )
+    assert :ok = Scenic.Driver.Skia.set_reduced_motion(renderer, true)
 
-      {:ok, Scenic.Scene.push_graph(scene, graph)}
-    end
+    {width, _height, _frame} =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        pixel_at(data, w, 16, 32) == {255, 255, 255}
+      end)
+
+    Process.sleep(250)
+    assert {:ok, {^width, _height, frame}} = Native.get_raster_frame(renderer)
+    assert pixel_at(frame, width, 16, 32) == {255, 255, 255}
   end
 
-  defmodule StreamStrokeScene do
-    use Scenic.Scene
-    import Scenic.Primitives
+  test "set_script_param selects the sprite cell without resubmitting" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
 
-    def init(scene, stream_id, _opts) do
-      graph =
-        Scenic.Graph.build()
-        |> line({{0, 0}, {20, 0}},
-          stroke: {6, {:stream, stream_id}},
-          translate: {10, 20}
-        )
+    vp = ViewPortHelper.start(size: {64, 64}, scene: SpriteCellScene)
+    renderer = ViewPortHelper.renderer(vp)
 
-      {:ok, Scenic.Scene.push_graph(scene, graph)}
-    end
-  end
+    on_exit(fn ->
+      if Process.alive?(vp.pid) do
+        _ = ViewPort.stop(vp)
+      end
 
-  defmodule SpritesScene do
-    use Scenic.Scene
-    import Scenic.Primitives
+      _ = Native.stop(renderer)
+    end)
 
-    def init(scene, _args, _opts) do
-      graph =
-        Scenic.Graph.build()
-        |> sprites(
-          {:test_red,
-           [
-             {{0, 0}, {2, 2}, {10, 10}, {20, 20}}
-           ]}
-        )
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 24, 24) == {255, 0, 0} and pixel_at(data, w, 48, 48) == {0, 0, 0}
+    end)
 
-      {:ok, Scenic.Scene.push_graph(scene, graph)}
+    assert :ok = Scenic.Driver.Skia.set_script_param(renderer, "sprite_cell_demo", 1)
+    assert :ok = Scenic.Driver.Skia.set_script_param(renderer, "sprite_cell_demo", 7)
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 24, 24) == {255, 0, 0}
+    end)
+
+    assert_raise FunctionClauseError, fn ->
+      Scenic.Driver.Skia.set_script_param(renderer, "sprite_cell_demo", -1)
     end
   end
 
-  defmodule CapCompareScene do
-    use Scenic.Scene
-    import Scenic.Primitives
-    alias Scenic.Script
+  test "fill_gradient sweeps conic stops around the center" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
 
-    def init(scene, _args, _opts) do
-      graph =
-        Scenic.Graph.build()
-        |> script("cap_compare_script")
+    vp = ViewPortHelper.start(size: {64, 64}, scene: ConicGradientScene)
+    renderer = ViewPortHelper.renderer(vp)
 
-      script =
-        Script.start()
-        |> Script.stroke_color(:white)
-        |> Script.stroke_width(10)
-        |> Script.push_state()
-        |> Script.cap(:butt)
-        |> Script.translate(10, 20)
-        |> Script.draw_line(0, 0, 20, 0, :stroke)
-        |> Script.pop_state()
-        |> Script.push_state()
-        |> Script.cap(:square)
-        |> Script.translate(10, 40)
-        |> Script.draw_line(0, 0, 20, 0, :stroke)
-        |> Script.pop_state()
-        |> Script.finish()
+    on_exit(fn ->
+      if Process.alive?(vp.pid) do
+        _ = ViewPort.stop(vp)
+      end
 
-      scene = Scenic.Scene.push_script(scene, script, "cap_compare_script")
-      {:ok, Scenic.Scene.push_graph(scene, graph)}
-    end
+      _ = Native.stop(renderer)
+    end)
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 32, 56) == {255, 0, 0} and pixel_at(data, w, 32, 8) == {0, 0, 255}
+    end)
   end
 
-  defmodule StrokeQuadScene do
-    use Scenic.Scene
-    import Scenic.Primitives
+  test "drop_shadow draws an offset shadow under the fill" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
 
-    def init(scene, _args, _opts) do
-      graph =
-        Scenic.Graph.build()
-        |> quad({{0, 0}, {20, 0}, {24, 20}, {0, 20}},
-          stroke: {2, :white},
-          translate: {10, 10}
-        )
+    vp = ViewPortHelper.start(size: {64, 64}, scene: DropShadowScene)
+    renderer = ViewPortHelper.renderer(vp)
 
-      {:ok, Scenic.Scene.push_graph(scene, graph)}
-    end
+    on_exit(fn ->
+      if Process.alive?(vp.pid) do
+        _ = ViewPort.stop(vp)
+      end
+
+      _ = Native.stop(renderer)
+    end)
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 8, 8) == {255, 0, 0} and pixel_at(data, w, 24, 24) == {0, 0, 255} and
+        pixel_at(data, w, 48, 48) == {0, 0, 0}
+    end)
   end
 
-  test "draw_rect fills expected pixels" do
+  test "a manual clock steps blinks only when advanced" do
     assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
 
-    vp = ViewPortHelper.start(size: {64, 64}, scene: RectScene)
+    vp = ViewPortHelper.start(size: {64, 64}, scene: BlinkScene)
     renderer = ViewPortHelper.renderer(vp)
 
     on_exit(fn ->
@@ -699,32 +1834,38 @@ defmodule Scenic.Driver.Skia.RasterPrimitivesTest do
       _ = Native.stop(renderer)
     end)
 
-    {width, _height, frame} =
+    assert {:error, _} = Scenic.Driver.Skia.advance_time(renderer, 100)
+    assert :ok = Scenic.Driver.Skia.set_clock(renderer, {:manual, 0})
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 16, 32) == {255, 255, 255}
+    end)
+
+    assert {:ok, 100} = Scenic.Driver.Skia.advance_time(renderer, 100)
+
+    {width, _height, _frame} =
       wait_for_frame!(renderer, 40, fn {w, _h, data} ->
-        pixel_at(data, w, 20, 20) == {255, 0, 0} and
-          pixel_at(data, w, 15, 10) == {255, 255, 255}
+        pixel_at(data, w, 16, 32) == {0, 0, 0}
       end)
 
-    # Background just outside the translated rect bounds.
-    assert pixel_at(frame, width, 7, 10) == {0, 0, 0}
-    assert pixel_at(frame, width, 10, 7) == {0, 0, 0}
-    assert pixel_at(frame, width, 7, 7) == {0, 0, 0}
-    assert pixel_at(frame, width, 33, 10) == {0, 0, 0}
-    assert pixel_at(frame, width, 10, 33) == {0, 0, 0}
-    assert pixel_at(frame, width, 33, 33) == {0, 0, 0}
-    # Stroke samples on each edge.
-    assert pixel_at(frame, width, 15, 10) == {255, 255, 255}
-    assert pixel_at(frame, width, 10, 15) == {255, 255, 255}
-    assert pixel_at(frame, width, 30, 15) == {255, 255, 255}
-    assert pixel_at(frame, width, 15, 30) == {255, 255, 255}
-    # Fill sample inside the rect.
-    assert pixel_at(frame, width, 20, 20) == {255, 0, 0}
+    # The blink stays hidden however long the test waits.
+    Process.sleep(250)
+    assert {:ok, {^width, _height, frame}} = Native.get_raster_frame(renderer)
+    assert pixel_at(frame, width, 16, 32) == {0, 0, 0}
+
+    assert {:ok, 200} = Scenic.Driver.Skia.advance_time(renderer, 100)
+
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 16, 32) == {255, 255, 255}
+    end)
+
+    assert :ok = Scenic.Driver.Skia.set_clock(renderer, :monotonic)
   end
 
-  test "clip_path limits drawing to the path" do
+  test "the quality governor starts at full quality and reports through get_stats" do
     assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
 
-    vp = ViewPortHelper.start(size: {64, 64}, scene: ClipPathScene)
+    vp = ViewPortHelper.start(size: {64, 64}, scene: BlinkScene)
     renderer = ViewPortHelper.renderer(vp)
 
     on_exit(fn ->
@@ -735,16 +1876,27 @@ defmodule Scenic.Driver.Skia.RasterPrimitivesTest do
       _ = Native.stop(renderer)
     end)
 
-    {width, _height, frame} =
-      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
-        pixel_at(data, w, 20, 20) == {255, 255, 255} and
-          pixel_at(data, w, 35, 35) == {0, 0, 0}
-      end)
+    assert :ok =
+             Scenic.Driver.Skia.set_quality_governor(renderer,
+               target_fps: 30,
+               thermal_zones: ["/nonexistent/thermal_zone0/temp"],
+               thermal_limit_c: 75.5
+             )
 
-    # Center of the clipped circle should be white.
-    assert pixel_at(frame, width, 20, 20) == {255, 255, 255}
-    # Inside the rectangle but outside the circle should remain background.
-    assert pixel_at(frame, width, 35, 35) == {0, 0, 0}
+    wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+      pixel_at(data, w, 16, 32) == {255, 255, 255}
+    end)
+
+    assert {:ok, %{quality: quality}} = Scenic.Driver.Skia.get_stats(renderer)
+    assert quality.level in ["full", "no_antialias", "scale_75", "scale_50"]
+    assert quality.scale in [1.0, 0.75, 0.5]
+    assert quality.antialias == (quality.level == "full")
+    assert quality.temperature_c == nil
+
+    assert :ok = Scenic.Driver.Skia.set_quality_governor(renderer, target_fps: 0)
+
+    assert {:ok, %{quality: %{level: "full", scale: 1.0, downgrades: 0, upgrades: 0}}} =
+             Scenic.Driver.Skia.get_stats(renderer)
   end
 
   test "draw_script recursion guard prevents infinite loop" do
@@ -1734,6 +2886,16 @@ defmodule Scenic.Driver.Skia.RasterPrimitivesTest do
 
     # Stream fill should render within the rect bounds.
     assert any_non_background?(frame, width, 12..28, 12..28)
+
+    assert {:ok, %{streams: %{^stream_id => counts}}} = Scenic.Driver.Skia.get_stats(renderer)
+    assert counts.received >= 1
+    assert counts.presented >= 1
+    assert counts.presented + counts.dropped <= counts.received
+    assert counts.pending == 0
+
+    assert :ok = Scenic.Driver.Skia.set_stream_policy(renderer, stream_id, {:queue, 4})
+    assert :ok = Scenic.Driver.Skia.set_stream_policy(renderer, stream_id, :latest)
+    assert {:error, _} = Native.set_stream_policy(renderer, stream_id, "queue", 0)
   end
 
   test "stream stroke renders provided bitmap" do
@@ -1823,6 +2985,81 @@ defmodule Scenic.Driver.Skia.RasterPrimitivesTest do
     assert square_max_x >= butt_max_x
   end
 
+  test "overscan margins shrink the scene inside the frame" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    vp = ViewPortHelper.start(size: {64, 64}, scene: FullRectScene)
+    renderer = ViewPortHelper.renderer(vp)
+
+    on_exit(fn ->
+      if Process.alive?(vp.pid) do
+        _ = ViewPort.stop(vp)
+      end
+
+      _ = Native.stop(renderer)
+    end)
+
+    _ =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        pixel_at(data, w, 2, 2) == {255, 0, 0}
+      end)
+
+    assert :ok = Scenic.Driver.Skia.set_overscan(renderer, {8, 8, 8, 8})
+
+    {width, _height, frame} =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        pixel_at(data, w, 2, 2) == {0, 0, 0}
+      end)
+
+    # Margins stay clear on every edge while the scene still fills the safe area.
+    assert pixel_at(frame, width, 2, 32) == {0, 0, 0}
+    assert pixel_at(frame, width, 61, 32) == {0, 0, 0}
+    assert pixel_at(frame, width, 32, 61) == {0, 0, 0}
+    assert pixel_at(frame, width, 10, 10) == {255, 0, 0}
+    assert pixel_at(frame, width, 53, 53) == {255, 0, 0}
+  end
+
+  test "clear_display holds a solid frame until released" do
+    assert {:ok, _} = Application.ensure_all_started(:scenic_driver_skia)
+
+    vp = ViewPortHelper.start(size: {64, 64}, scene: FullRectScene)
+    renderer = ViewPortHelper.renderer(vp)
+
+    on_exit(fn ->
+      if Process.alive?(vp.pid) do
+        _ = ViewPort.stop(vp)
+      end
+
+      _ = Native.stop(renderer)
+    end)
+
+    _ =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        pixel_at(data, w, 32, 32) == {255, 0, 0}
+      end)
+
+    assert :ok = Scenic.Driver.Skia.clear_display(renderer, :blue, hold: true)
+
+    {width, _height, frame} =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        pixel_at(data, w, 32, 32) == {0, 0, 255}
+      end)
+
+    assert pixel_at(frame, width, 2, 2) == {0, 0, 255}
+    assert :ok = normalize_nif_result(Native.set_clear_color(renderer, {0, 0, 0, 255}))
+    Process.sleep(100)
+    assert {:ok, {_w, _h, held}} = Native.get_raster_frame(renderer)
+    assert pixel_at(held, width, 32, 32) == {0, 0, 255}
+
+    assert {:ok, true} = Scenic.Driver.Skia.release_display(renderer)
+    assert {:ok, false} = Scenic.Driver.Skia.release_display(renderer)
+
+    _ =
+      wait_for_frame!(renderer, 40, fn {w, _h, data} ->
+        pixel_at(data, w, 32, 32) == {255, 0, 0}
+      end)
+  end
+
   defp wait_for_frame!(renderer, attempts_remaining, predicate) do
     case Native.get_raster_frame(renderer) do
       {:ok, {width, height, frame}} = ok ->
@@ -1847,6 +3084,14 @@ defmodule Scenic.Driver.Skia.RasterPrimitivesTest do
     flunk("timed out waiting for raster frame: #{inspect(last_result)}")
   end
 
+  defp tar_binary(files) do
+    path = Path.join(System.tmp_dir!(), "skia_bundle_#{System.unique_integer([:positive])}.tar")
+    :ok = :erl_tar.create(String.to_charlist(path), files)
+    archive = File.read!(path)
+    File.rm!(path)
+    archive
+  end
+
   defp pixel_at(frame, width, x, y) do
     offset = (y * width + x) * 3
 
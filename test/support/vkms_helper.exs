@@ -0,0 +1,46 @@
+defmodule Scenic.Driver.Skia.TestSupport.Vkms do
+  @moduledoc false
+  # Finds the kernel's virtual KMS device (vkms) so the DRM modeset and page flip paths
+  # run in CI without display hardware. Tests that need it are tagged `:vkms`, excluded
+  # by default, and run with `mix test --only vkms` (as root, or with access to the card).
+
+  alias Scenic.Driver.Skia.Native
+
+  @attempts 40
+
+  @doc """
+  Returns `{:ok, card_path}` for the vkms card, loading the module with a cursor plane
+  first when it is missing and `modprobe` is available.
+  """
+  def card do
+    case Native.find_drm_card("vkms") do
+      {:ok, card} -> {:ok, card}
+      {:error, _} -> load_module()
+    end
+  end
+
+  defp load_module do
+    with modprobe when is_binary(modprobe) <- System.find_executable("modprobe"),
+         {_, 0} <- System.cmd(modprobe, ["vkms", "enable_cursor=1"], stderr_to_stdout: true) do
+      wait_for_card(@attempts)
+    else
+      nil -> {:error, "vkms is not loaded and modprobe is not available"}
+      {output, status} -> {:error, "modprobe vkms exited with #{status}: #{output}"}
+    end
+  end
+
+  # udev creates the device node shortly after the module loads.
+  defp wait_for_card(attempts_remaining) do
+    case Native.find_drm_card("vkms") do
+      {:ok, card} ->
+        {:ok, card}
+
+      {:error, _} when attempts_remaining > 0 ->
+        Process.sleep(50)
+        wait_for_card(attempts_remaining - 1)
+
+      error ->
+        error
+    end
+  end
+end
@@ -1,7 +1,10 @@
-ExUnit.start()
+# DRM tests against the virtual KMS driver need the vkms module; run them with
+# `mix test --only vkms`.
+ExUnit.start(exclude: [:vkms])
 
 Logger.configure(level: :debug)
 
 {:ok, _apps} = Application.ensure_all_started(:scenic)
 
 Code.require_file("support/view_port_helper.exs", __DIR__)
+Code.require_file("support/vkms_helper.exs", __DIR__)